@@ -0,0 +1,183 @@
+//! Per-account data isolation
+//!
+//! `AccountContext` identifies the Google account that owns a piece of local
+//! data. Cache keys (the frontend's included), storage collections (the search
+//! index among them) and account data in settings stores are namespaced with
+//! it so data from different accounts never mixes, and `purge_account_data`
+//! can remove everything belonging to one account on disconnect.
+
+use crate::auth::{delete_refresh_token, TokenStore};
+use crate::cache::CacheState;
+use crate::google::GoogleClient;
+use crate::storage::{self, LocalStorage};
+use crate::{planner, policy, routines};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+/// Settings stores holding per-account entries (see `AccountContext::store_key`)
+const ACCOUNT_SCOPED_STORES: &[&str] =
+    &[planner::PLANNER_STORE_FILE, routines::ROUTINES_STORE_FILE];
+
+/// The account owning cached and stored data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountContext {
+    email: String,
+}
+
+impl AccountContext {
+    pub fn new(email: &str) -> Self {
+        Self {
+            email: email.trim().to_lowercase(),
+        }
+    }
+
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    /// Prefix shared by all of this account's cache keys
+    pub fn cache_prefix(&self) -> String {
+        format!("account:{}:", self.email)
+    }
+
+    /// Namespace a cache key (e.g. `INBOX_SUMMARY_KEY`) with this account
+    pub fn cache_key(&self, key: &str) -> String {
+        format!("{}{}", self.cache_prefix(), key)
+    }
+
+    /// Settings store key of this account's copy of `key`
+    pub fn store_key(&self, key: &str) -> String {
+        format!("{}:{}", key, self.storage_id())
+    }
+
+    /// Filesystem-safe, reversible directory name for this account's storage
+    pub fn storage_id(&self) -> String {
+        self.email.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Inverse of `storage_id`
+    pub fn from_storage_id(id: &str) -> Option<Self> {
+        if id.is_empty() || !id.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let bytes = (0..id.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(id.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        String::from_utf8(bytes).ok().map(|email| Self { email })
+    }
+}
+
+/// Outcome of purging an account's local data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountPurgeReport {
+    pub email: String,
+    pub cache_entries_removed: usize,
+    pub storage_records_removed: usize,
+    pub store_entries_removed: usize,
+    /// Whether the purged account was the signed-in one (and was signed out)
+    pub signed_out: bool,
+}
+
+/// Remove an account's entries from the settings stores
+fn purge_store_entries(app: &AppHandle, account: &AccountContext) -> Result<usize, String> {
+    let suffix = account.store_key("");
+    let mut removed = 0;
+    for file in ACCOUNT_SCOPED_STORES {
+        let store = storage::fs::settings_store(app, file)
+            .map_err(|e| format!("Failed to access {}: {}", file, e))?;
+        let keys: Vec<String> = store
+            .keys()
+            .into_iter()
+            .filter(|key| key.ends_with(&suffix))
+            .collect();
+        if keys.is_empty() {
+            continue;
+        }
+        for key in &keys {
+            store.delete(key);
+        }
+        storage::fs::save_store(app, file, &store)?;
+        removed += keys.len();
+    }
+    Ok(removed)
+}
+
+/// Remove all local data belonging to an account
+///
+/// Clears its cache entries, storage collections, settings store entries,
+/// quota counters and keychain refresh token. Other accounts' data is left
+/// alone. Purging the signed-in account also signs out.
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
+#[tauri::command]
+pub async fn purge_account_data(
+    app: AppHandle,
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+    storage: State<'_, LocalStorage>,
+    client: State<'_, GoogleClient>,
+    email: String,
+    confirmation: Option<String>,
+) -> Result<AccountPurgeReport, String> {
+    crate::perf::trace_command!();
+    policy::authorize(&app, "purge_account_data", &email, confirmation.as_deref()).await?;
+    let account = AccountContext::new(&email);
+    let is_active = token_store
+        .account_context()
+        .await
+        .is_ok_and(|active| active == account);
+
+    let cache_entries_removed = cache
+        .0
+        .invalidate_pattern(&format!("{}*", account.cache_prefix()));
+    let storage_records_removed = storage.remove_account(&account)?;
+    let store_entries_removed = purge_store_entries(&app, &account)?;
+    client.quota().clear_user(account.email());
+
+    if is_active {
+        token_store.clear_tokens().await?;
+    } else {
+        delete_refresh_token(account.email())?;
+    }
+
+    Ok(AccountPurgeReport {
+        email: account.email().to_string(),
+        cache_entries_removed,
+        storage_records_removed,
+        store_entries_removed,
+        signed_out: is_active,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_id_roundtrip() {
+        let account = AccountContext::new(" Jane.Doe+work@Example.com ");
+        assert_eq!(account.email(), "jane.doe+work@example.com");
+
+        let id = account.storage_id();
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(AccountContext::from_storage_id(&id), Some(account));
+        assert_eq!(AccountContext::from_storage_id("zz"), None);
+    }
+
+    #[test]
+    fn test_cache_keys_are_scoped() {
+        let a = AccountContext::new("a@example.com");
+        let b = AccountContext::new("b@example.com");
+        assert_ne!(
+            a.cache_key("gmail:inbox_summary"),
+            b.cache_key("gmail:inbox_summary")
+        );
+        assert!(a.cache_key("x").starts_with(&a.cache_prefix()));
+
+        // A purge matches store keys by suffix
+        assert!(a.store_key("links").ends_with(&a.store_key("")));
+        assert!(!a.store_key("links").ends_with(&b.store_key("")));
+    }
+}
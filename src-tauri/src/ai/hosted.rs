@@ -0,0 +1,84 @@
+//! Rainy Day cloud backend
+
+use super::{check_status, GenerateRequest, Provider};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Backend used when the settings don't name one
+const DEFAULT_API_URL: &str = "http://localhost:3000";
+/// Environment variable overriding the default backend
+const API_URL_ENV: &str = "RAINY_API_URL";
+/// Model reported when the backend picks one
+const BACKEND_DEFAULT_MODEL: &str = "default";
+
+/// The backend at `base_url`, else the environment's or the default one
+pub(crate) fn api_url(base_url: Option<String>) -> String {
+    base_url
+        .or_else(|| std::env::var(API_URL_ENV).ok())
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+    text: String,
+}
+
+pub struct HostedProvider {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+    model: Option<String>,
+}
+
+impl HostedProvider {
+    pub fn new(
+        http: reqwest::Client,
+        base_url: Option<String>,
+        token: String,
+        model: Option<String>,
+    ) -> Self {
+        let base_url = api_url(base_url);
+        Self {
+            http,
+            base_url,
+            token,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for HostedProvider {
+    fn name(&self) -> &'static str {
+        "Rainy Day cloud"
+    }
+
+    fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(BACKEND_DEFAULT_MODEL)
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> Result<String, String> {
+        let body = serde_json::json!({
+            "system": request.system,
+            "prompt": request.prompt,
+            "maxTokens": request.max_tokens,
+            "temperature": request.temperature,
+            "modelId": self.model,
+        });
+        let response = self
+            .http
+            .post(format!("{}/ai/generate", self.base_url))
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("{} request failed: {}", self.name(), e))?;
+
+        let response: GenerateResponse = check_status(self.name(), response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse {} response: {}", self.name(), e))?;
+        Ok(response.text)
+    }
+}
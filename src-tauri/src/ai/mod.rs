@@ -0,0 +1,565 @@
+//! Pluggable AI providers
+//!
+//! Note generation talks to a `Provider` instead of one fixed backend:
+//! - `hosted`: the Rainy Day cloud backend (the default), authenticated with
+//!   the backend session
+//! - `openai`: any OpenAI-compatible chat completions endpoint
+//! - `ollama`: a local Ollama server
+//!
+//! The provider, its base URL and model are picked in settings
+//! (`set_ai_settings`). API keys are kept in the OS keychain
+//! (`set_ai_api_key`), never in the settings file.
+//!
+//! Calls are counted against a monthly budget (see `usage`).
+//!
+//! `generate_note_streamed` forwards the reply as `note:chunk` events while it
+//! is written and finishes with `note:done`. A cancelled or broken stream
+//! still yields the sections completed so far.
+
+mod hosted;
+mod ollama;
+mod openai;
+pub mod usage;
+
+use crate::app_lock::AppLockState;
+use crate::auth;
+use crate::data_pipeline::{self, NoteGenerationContext, ValidatedNote};
+use crate::storage;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::Notify;
+
+pub(crate) const AI_STORE_FILE: &str = "ai.json";
+const AI_SETTINGS_KEY: &str = "settings";
+
+/// Largest note context sent to a provider
+const MAX_NOTE_PROMPT_TOKENS: usize = 12_000;
+/// Timeout of a whole generation request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A prompt to complete
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerateRequest {
+    #[serde(default)]
+    pub system: Option<String>,
+    pub prompt: String,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// A text generation backend
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Name used in error messages
+    fn name(&self) -> &'static str;
+
+    /// Model the requests go to (used for pricing)
+    fn model(&self) -> &str;
+
+    /// Generate the whole reply
+    async fn generate(&self, request: &GenerateRequest) -> Result<String, String>;
+
+    /// Generate the reply piece by piece, handing each piece to `on_chunk`;
+    /// returns the whole reply
+    ///
+    /// Providers without streaming deliver the reply as a single chunk.
+    async fn stream(
+        &self,
+        request: &GenerateRequest,
+        on_chunk: &mut (dyn for<'c> FnMut(&'c str) + Send),
+    ) -> Result<String, String> {
+        let text = self.generate(request).await?;
+        on_chunk(&text);
+        Ok(text)
+    }
+
+    /// Tokens `text` takes up in the model's context
+    fn count_tokens(&self, text: &str) -> usize {
+        estimate_tokens(text)
+    }
+}
+
+/// Rough token count (about four characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Hosted,
+    OpenaiCompatible,
+    Ollama,
+}
+
+impl ProviderKind {
+    /// Keychain entry of the provider's API key
+    fn key_name(self) -> &'static str {
+        match self {
+            ProviderKind::Hosted => "hosted",
+            ProviderKind::OpenaiCompatible => "openai_compatible",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+}
+
+/// The selected provider; unset fields use the provider's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiSettings {
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<AiSettings, String> {
+    let store = storage::fs::settings_store(app, AI_STORE_FILE)
+        .map_err(|e| format!("Failed to access AI store: {}", e))?;
+
+    Ok(store
+        .get(AI_SETTINGS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Build the provider `settings` select, with its key from the keychain
+pub fn provider_for(settings: &AiSettings) -> Result<Box<dyn Provider>, String> {
+    let base_url = settings
+        .base_url
+        .as_deref()
+        .map(|url| url.trim_end_matches('/').to_string());
+    let http = http_client()?;
+
+    Ok(match settings.provider {
+        ProviderKind::Hosted => {
+            let token = auth::backend_access_token()?
+                .ok_or("Sign in to Rainy Day cloud to use the hosted AI")?;
+            Box::new(hosted::HostedProvider::new(
+                http,
+                base_url,
+                token,
+                settings.model.clone(),
+            ))
+        }
+        ProviderKind::OpenaiCompatible => {
+            let api_key = auth::get_ai_api_key(settings.provider.key_name())?;
+            Box::new(openai::OpenAiProvider::new(
+                http,
+                base_url,
+                api_key,
+                settings.model.clone(),
+            ))
+        }
+        ProviderKind::Ollama => Box::new(ollama::OllamaProvider::new(
+            http,
+            base_url,
+            settings.model.clone(),
+        )),
+    })
+}
+
+/// Base URL of the Rainy Day cloud backend
+///
+/// The settings' base URL only applies while the hosted provider is selected.
+pub fn backend_url(app: &AppHandle) -> Result<String, String> {
+    let settings = load_settings(app)?;
+    let base_url = match settings.provider {
+        ProviderKind::Hosted => settings.base_url,
+        _ => None,
+    };
+    Ok(hosted::api_url(base_url).trim_end_matches('/').to_string())
+}
+
+/// The provider selected in settings
+pub fn current_provider(app: &AppHandle) -> Result<Box<dyn Provider>, String> {
+    provider_for(&load_settings(app)?)
+}
+
+/// Read a streamed response line by line
+async fn for_each_line(
+    mut response: reqwest::Response,
+    mut on_line: impl FnMut(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read stream: {}", e))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            on_line(String::from_utf8_lossy(&line).trim())?;
+        }
+    }
+    if !buffer.is_empty() {
+        on_line(String::from_utf8_lossy(&buffer).trim())?;
+    }
+    Ok(())
+}
+
+/// Turn a non-success response into an error
+async fn check_status(
+    provider: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!(
+        "{} request failed ({}): {}",
+        provider, status, body
+    ))
+}
+
+// ============================================================================
+// Note Generation
+// ============================================================================
+
+const NOTE_SYSTEM_PROMPT: &str =
+    "You write a short daily briefing from the user's emails, tasks and meetings. \
+Reply with JSON only: {\"id\": string, \"date\": \"YYYY-MM-DD\", \"sections\": [{\"id\": string, \
+\"type\": \"email_summary\" | \"task_recap\" | \"meeting_notes\" | \"custom\", \"title\": string, \
+\"content\": string}]}.";
+
+/// Prompt asking for the note of `date` from the prepared context
+pub(crate) fn note_request(
+    date: &str,
+    context: &NoteGenerationContext,
+) -> Result<GenerateRequest, String> {
+    let context = serde_json::to_string(context)
+        .map_err(|e| format!("Failed to serialize note context: {}", e))?;
+    Ok(GenerateRequest {
+        system: Some(NOTE_SYSTEM_PROMPT.to_string()),
+        prompt: format!("Date: {}\nContext:\n{}", date, context),
+        max_tokens: Some(1500),
+        temperature: Some(0.3),
+    })
+}
+
+/// Validate a model's note reply, ignoring text around the JSON object
+pub(crate) fn parse_note(reply: &str) -> Result<ValidatedNote, String> {
+    let start = reply.find('{').ok_or("The AI reply has no note")?;
+    let end = reply.rfind('}').ok_or("The AI reply has no note")?;
+    let note: serde_json::Value = serde_json::from_str(&reply[start..=end])
+        .map_err(|e| format!("The AI reply is not valid JSON: {}", e))?;
+    data_pipeline::validate_note_schema(note)
+}
+
+/// Validate what a stream produced before it stopped: the whole note, or the
+/// note cut after its last complete section
+pub(crate) fn parse_partial_note(reply: &str) -> Option<ValidatedNote> {
+    if let Ok(note) = parse_note(reply) {
+        return Some(note);
+    }
+    let start = reply.find('{')?;
+    let sections = start + reply[start..].find("\"sections\"")?;
+    reply[sections..]
+        .rmatch_indices('}')
+        .filter_map(|(i, _)| {
+            let candidate = format!("{}]}}", &reply[start..=sections + i]);
+            let note = serde_json::from_str(&candidate).ok()?;
+            data_pipeline::validate_note_schema(note).ok()
+        })
+        .find(|note| !note.sections.is_empty())
+}
+
+// ============================================================================
+// Note Streaming
+// ============================================================================
+
+/// Event carrying a piece of a note being generated
+pub const NOTE_CHUNK_EVENT: &str = "note:chunk";
+/// Event sent once a streamed note generation ends
+pub const NOTE_DONE_EVENT: &str = "note:done";
+
+/// Cancellation handles of the note generations in flight
+#[derive(Default)]
+pub struct NoteStreams(Mutex<HashMap<String, Arc<Notify>>>);
+
+impl NoteStreams {
+    fn start(&self, generation_id: &str) -> Result<Arc<Notify>, String> {
+        let mut streams = self
+            .0
+            .lock()
+            .map_err(|_| "Note stream lock poisoned".to_string())?;
+        if streams.contains_key(generation_id) {
+            return Err(format!("Generation {} is already running", generation_id));
+        }
+        let cancel = Arc::new(Notify::new());
+        streams.insert(generation_id.to_string(), cancel.clone());
+        Ok(cancel)
+    }
+
+    fn finish(&self, generation_id: &str) {
+        if let Ok(mut streams) = self.0.lock() {
+            streams.remove(generation_id);
+        }
+    }
+
+    /// Stop a generation; false if it isn't running
+    fn cancel(&self, generation_id: &str) -> bool {
+        let cancel = self
+            .0
+            .lock()
+            .ok()
+            .and_then(|s| s.get(generation_id).cloned());
+        match cancel {
+            Some(cancel) => {
+                cancel.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteChunk {
+    pub generation_id: String,
+    pub text: String,
+}
+
+/// How a streamed generation ended
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteStreamResult {
+    pub generation_id: String,
+    /// The note, or the sections finished before the stream stopped
+    pub note: Option<ValidatedNote>,
+    /// `note` lacks what the stream didn't get to
+    pub partial: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the AI provider settings
+#[tauri::command]
+pub async fn get_ai_settings(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+) -> Result<AiSettings, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    load_settings(&app)
+}
+
+/// Select the AI provider
+#[tauri::command]
+pub async fn set_ai_settings(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    settings: AiSettings,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if let Some(url) = &settings.base_url {
+        let parsed =
+            reqwest::Url::parse(url).map_err(|e| format!("Invalid base URL {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Base URL must use http or https: {}", url));
+        }
+    }
+
+    let store = storage::fs::settings_store(&app, AI_STORE_FILE)
+        .map_err(|e| format!("Failed to access AI store: {}", e))?;
+    store.set(AI_SETTINGS_KEY, serde_json::json!(settings));
+    storage::fs::save_store(&app, AI_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save AI store: {}", e))
+}
+
+/// Save a provider's API key in the keychain; an empty key removes it
+#[tauri::command]
+pub async fn set_ai_api_key(
+    app_lock: State<'_, AppLockState>,
+    provider: ProviderKind,
+    api_key: String,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        auth::delete_ai_api_key(provider.key_name())
+    } else {
+        auth::store_ai_api_key(provider.key_name(), api_key)
+    }
+}
+
+/// Whether a provider has an API key saved
+#[tauri::command]
+pub async fn has_ai_api_key(
+    app_lock: State<'_, AppLockState>,
+    provider: ProviderKind,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    Ok(auth::get_ai_api_key(provider.key_name())?.is_some())
+}
+
+/// Generate the note of `date` (YYYY-MM-DD) with the selected provider
+#[tauri::command]
+pub async fn generate_ai_note(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    date: String,
+    context: NoteGenerationContext,
+) -> Result<ValidatedNote, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let provider = current_provider(&app)?;
+    let request = note_request(&date, &context)?;
+    let tokens = provider.count_tokens(&request.prompt);
+    if tokens > MAX_NOTE_PROMPT_TOKENS {
+        return Err(format!(
+            "Note context too large for {} ({} tokens)",
+            provider.name(),
+            tokens
+        ));
+    }
+    usage::check_budget(&app).await?;
+    let reply = provider.generate(&request).await?;
+    usage::record_call(&app, provider.as_ref(), &request, &reply).await;
+    parse_note(&reply)
+}
+
+/// Generate the note of `date` with the selected provider, forwarding the
+/// reply as `note:chunk` events; ends with `note:done` (also returned)
+///
+/// `generation_id` is picked by the caller and stops the generation when
+/// passed to `cancel_note_generation`.
+#[tauri::command]
+pub async fn generate_note_streamed(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    streams: State<'_, NoteStreams>,
+    generation_id: String,
+    date: String,
+    context: NoteGenerationContext,
+) -> Result<NoteStreamResult, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let provider = current_provider(&app)?;
+    let request = note_request(&date, &context)?;
+    usage::check_budget(&app).await?;
+    let cancel = streams.start(&generation_id)?;
+
+    let mut text = String::new();
+    let mut on_chunk = |piece: &str| {
+        text.push_str(piece);
+        let chunk = NoteChunk {
+            generation_id: generation_id.clone(),
+            text: piece.to_string(),
+        };
+        if let Err(e) = app.emit(NOTE_CHUNK_EVENT, chunk) {
+            eprintln!("Failed to emit {}: {}", NOTE_CHUNK_EVENT, e);
+        }
+    };
+    let outcome = tokio::select! {
+        result = provider.stream(&request, &mut on_chunk) => Some(result),
+        _ = cancel.notified() => None,
+    };
+    streams.finish(&generation_id);
+    usage::record_call(&app, provider.as_ref(), &request, &text).await;
+
+    let result = match outcome {
+        Some(Ok(reply)) => match parse_note(&reply) {
+            Ok(note) => NoteStreamResult {
+                generation_id,
+                note: Some(note),
+                partial: false,
+                cancelled: false,
+                error: None,
+            },
+            Err(e) => NoteStreamResult {
+                generation_id,
+                note: parse_partial_note(&reply),
+                partial: true,
+                cancelled: false,
+                error: Some(e),
+            },
+        },
+        Some(Err(e)) => NoteStreamResult {
+            generation_id,
+            note: parse_partial_note(&text),
+            partial: true,
+            cancelled: false,
+            error: Some(e),
+        },
+        None => NoteStreamResult {
+            generation_id,
+            note: parse_partial_note(&text),
+            partial: true,
+            cancelled: true,
+            error: None,
+        },
+    };
+    if let Err(e) = app.emit(NOTE_DONE_EVENT, &result) {
+        eprintln!("Failed to emit {}: {}", NOTE_DONE_EVENT, e);
+    }
+    Ok(result)
+}
+
+/// Stop a streamed note generation; false if it already ended
+#[tauri::command]
+pub async fn cancel_note_generation(
+    streams: State<'_, NoteStreams>,
+    generation_id: String,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    Ok(streams.cancel(&generation_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_note() {
+        let reply = "Here is your note:\n```json\n{\"id\": \"n1\", \"date\": \"2025-03-10\", \"sections\": [\
+            {\"id\": \"s1\", \"type\": \"task_recap\", \"title\": \"Tasks\", \"content\": \"Ship it\"}]}\n```";
+        let note = parse_note(reply).unwrap();
+        assert_eq!(note.date, "2025-03-10");
+        assert_eq!(note.sections[0].content, "Ship it");
+
+        assert!(parse_note("Sorry, I can't help with that.").is_err());
+        assert!(parse_note("{\"id\": \"n1\"}").is_err());
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_parse_partial_note() {
+        let cut = "{\"id\": \"n1\", \"date\": \"2025-03-10\", \"sections\": [\
+            {\"id\": \"s1\", \"type\": \"task_recap\", \"title\": \"Tasks\", \"content\": \"Ship it\"},\
+            {\"id\": \"s2\", \"type\": \"custom\", \"title\": \"Ne";
+        let note = parse_partial_note(cut).unwrap();
+        assert_eq!(note.sections.len(), 1);
+        assert_eq!(note.sections[0].id, "s1");
+
+        assert!(parse_partial_note("{\"id\": \"n1\", \"date\": \"2025-03-10\", \"sec").is_none());
+        assert!(parse_partial_note("").is_none());
+    }
+}
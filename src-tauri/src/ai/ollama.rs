@@ -0,0 +1,108 @@
+//! Local Ollama server
+
+use super::{check_status, for_each_line, GenerateRequest, Provider};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_MODEL: &str = "llama3.1";
+
+pub struct OllamaProvider {
+    http: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: reqwest::Client, base_url: Option<String>, model: Option<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &GenerateRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": request.prompt }));
+        let body = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": stream,
+            "options": {
+                "num_predict": request.max_tokens,
+                "temperature": request.temperature,
+            },
+        });
+
+        let response = self
+            .http
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("{} request failed (is Ollama running?): {}", self.name(), e))?;
+        check_status(self.name(), response).await
+    }
+}
+
+/// Message text of a chat response or of one streamed line
+fn message_text(reply: &Value) -> Option<&str> {
+    reply["message"]["content"].as_str()
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> Result<String, String> {
+        let reply: Value = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse {} response: {}", self.name(), e))?;
+        message_text(&reply)
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} response has no message", self.name()))
+    }
+
+    async fn stream(
+        &self,
+        request: &GenerateRequest,
+        on_chunk: &mut (dyn for<'c> FnMut(&'c str) + Send),
+    ) -> Result<String, String> {
+        let response = self.send(request, true).await?;
+        let mut text = String::new();
+        for_each_line(response, |line| {
+            if line.is_empty() {
+                return Ok(());
+            }
+            let reply: Value = serde_json::from_str(line)
+                .map_err(|e| format!("Failed to parse Ollama stream: {}", e))?;
+            if let Some(error) = reply["error"].as_str() {
+                return Err(format!("Ollama error: {}", error));
+            }
+            if let Some(piece) = message_text(&reply).filter(|p| !p.is_empty()) {
+                on_chunk(piece);
+                text.push_str(piece);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(text)
+    }
+}
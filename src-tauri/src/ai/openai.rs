@@ -0,0 +1,135 @@
+//! OpenAI-compatible chat completions endpoints (OpenAI, OpenRouter, LM
+//! Studio, vLLM, ...)
+
+use super::{check_status, for_each_line, GenerateRequest, Provider};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+pub struct OpenAiProvider {
+    http: reqwest::Client,
+    base_url: String,
+    /// Local servers often need none
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        http: reqwest::Client,
+        base_url: Option<String>,
+        api_key: Option<String>,
+        model: Option<String>,
+    ) -> Self {
+        Self {
+            http,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            api_key,
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &GenerateRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": request.prompt }));
+        let body = json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature,
+            "stream": stream,
+        });
+
+        let mut builder = self
+            .http
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| format!("{} request failed: {}", self.name(), e))?;
+        check_status(self.name(), response).await
+    }
+}
+
+/// Text of one server-sent event line; None for keep-alives and `[DONE]`
+fn delta_text(line: &str) -> Option<String> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return None;
+    }
+    let event: Value = serde_json::from_str(data).ok()?;
+    event["choices"][0]["delta"]["content"]
+        .as_str()
+        .map(str::to_string)
+}
+
+#[async_trait]
+impl Provider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> Result<String, String> {
+        let response: Value = self
+            .send(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse {} response: {}", self.name(), e))?;
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} response has no message", self.name()))
+    }
+
+    async fn stream(
+        &self,
+        request: &GenerateRequest,
+        on_chunk: &mut (dyn for<'c> FnMut(&'c str) + Send),
+    ) -> Result<String, String> {
+        let response = self.send(request, true).await?;
+        let mut text = String::new();
+        for_each_line(response, |line| {
+            if let Some(delta) = delta_text(line) {
+                on_chunk(&delta);
+                text.push_str(&delta);
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_text() {
+        assert_eq!(
+            delta_text(r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#),
+            Some("Hel".to_string())
+        );
+        assert_eq!(delta_text(r#"data: {"choices":[{"delta":{}}]}"#), None);
+        assert_eq!(delta_text("data: [DONE]"), None);
+        assert_eq!(delta_text(": keep-alive"), None);
+    }
+}
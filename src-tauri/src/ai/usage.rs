@@ -0,0 +1,362 @@
+//! Token and cost accounting of AI calls
+//!
+//! Every generation is counted per month and model in local storage
+//! (`AI_USAGE`, keyed by `YYYY-MM`). Costs come from a built-in price table;
+//! unknown models (local Ollama ones included) are counted at no cost. An
+//! optional monthly budget either warns once when crossed or blocks further
+//! calls until the next month.
+
+use super::{GenerateRequest, Provider, AI_STORE_FILE};
+use crate::analytics::ReportRange;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::notifications;
+use crate::storage::{self, LocalStorage, AI_USAGE};
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+const AI_BUDGET_KEY: &str = "budget";
+
+/// USD per million input and output tokens, by model name prefix
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("o4-mini", 1.10, 4.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+];
+
+/// Counters of a month or a model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub calls: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.calls += other.calls;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// Usage of one month (YYYY-MM)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonthlyUsage {
+    pub month: String,
+    pub total: UsageTotals,
+    pub by_model: BTreeMap<String, UsageTotals>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Notify once when the month's spend passes the limit
+    #[default]
+    Warn,
+    /// Refuse AI calls for the rest of the month
+    Block,
+}
+
+/// Monthly AI spending limit
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiBudget {
+    /// None means no limit
+    pub monthly_limit_usd: Option<f64>,
+    #[serde(default)]
+    pub action: BudgetAction,
+}
+
+/// Usage of the months a range touches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiUsageReport {
+    pub range: ReportRange,
+    pub months: Vec<MonthlyUsage>,
+    pub total: UsageTotals,
+    pub budget: AiBudget,
+    /// Spend of the current month
+    pub month_cost_usd: f64,
+    pub over_budget: bool,
+}
+
+/// Cost of a call; zero for models missing from the price table
+fn call_cost(model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+    let model = model.to_lowercase();
+    MODEL_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|(_, input, output)| {
+            (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+fn month_of(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+/// Months (YYYY-MM) from the one containing `start_ms` to the one containing `end_ms`
+fn months_between(start_ms: i64, end_ms: i64) -> Vec<String> {
+    let date = |ms: i64| {
+        Local
+            .timestamp_millis_opt(ms)
+            .single()
+            .map(|d| d.date_naive())
+    };
+    let (Some(start), Some(end)) = (date(start_ms), date(end_ms)) else {
+        return Vec::new();
+    };
+    let mut months = Vec::new();
+    let mut month = start.with_day(1);
+    while let Some(first) = month.filter(|m| *m <= end) {
+        months.push(month_of(first));
+        month = first.checked_add_months(chrono::Months::new(1));
+    }
+    months
+}
+
+fn load_budget(app: &AppHandle) -> Result<AiBudget, String> {
+    let store = storage::fs::settings_store(app, AI_STORE_FILE)
+        .map_err(|e| format!("Failed to access AI store: {}", e))?;
+
+    Ok(store
+        .get(AI_BUDGET_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn load_month(
+    storage: &LocalStorage,
+    account: &crate::account::AccountContext,
+    month: &str,
+) -> Result<MonthlyUsage, String> {
+    Ok(storage
+        .get(account, AI_USAGE, month)?
+        .and_then(|record| serde_json::from_value(record.value).ok())
+        .unwrap_or_else(|| MonthlyUsage {
+            month: month.to_string(),
+            ..Default::default()
+        }))
+}
+
+/// Refuse a call when a blocking budget is used up
+pub(crate) async fn check_budget(app: &AppHandle) -> Result<(), String> {
+    let budget = load_budget(app)?;
+    let (Some(limit), BudgetAction::Block) = (budget.monthly_limit_usd, budget.action) else {
+        return Ok(());
+    };
+    let account = app.state::<TokenStore>().account_context().await?;
+    let month = load_month(
+        &app.state::<LocalStorage>(),
+        &account,
+        &month_of(Local::now().date_naive()),
+    )?;
+    if month.total.cost_usd >= limit {
+        return Err(format!(
+            "Monthly AI budget of ${:.2} reached (${:.2} spent)",
+            limit, month.total.cost_usd
+        ));
+    }
+    Ok(())
+}
+
+/// Count a finished (or partial) call; failures are only logged
+pub(crate) async fn record_call(
+    app: &AppHandle,
+    provider: &dyn Provider,
+    request: &GenerateRequest,
+    reply: &str,
+) {
+    if let Err(e) = try_record_call(app, provider, request, reply).await {
+        eprintln!("Failed to record AI usage: {}", e);
+    }
+}
+
+async fn try_record_call(
+    app: &AppHandle,
+    provider: &dyn Provider,
+    request: &GenerateRequest,
+    reply: &str,
+) -> Result<(), String> {
+    let token_store = app.state::<TokenStore>();
+    let storage = app.state::<LocalStorage>();
+    let account = token_store.account_context().await?;
+
+    let input_tokens = (provider.count_tokens(&request.prompt)
+        + request
+            .system
+            .as_deref()
+            .map_or(0, |s| provider.count_tokens(s))) as u64;
+    let output_tokens = provider.count_tokens(reply) as u64;
+    let call = UsageTotals {
+        calls: 1,
+        input_tokens,
+        output_tokens,
+        cost_usd: call_cost(provider.model(), input_tokens, output_tokens),
+    };
+
+    let mut month = load_month(&storage, &account, &month_of(Local::now().date_naive()))?;
+    let spent_before = month.total.cost_usd;
+    month.total.add(&call);
+    month
+        .by_model
+        .entry(provider.model().to_string())
+        .or_default()
+        .add(&call);
+    let value =
+        serde_json::to_value(&month).map_err(|e| format!("Failed to serialize AI usage: {}", e))?;
+    storage.put(&account, AI_USAGE, &month.month, value)?;
+
+    let budget = load_budget(app)?;
+    if let Some(limit) = budget.monthly_limit_usd {
+        if spent_before < limit && month.total.cost_usd >= limit {
+            notify_budget_reached(app, &budget, limit).await;
+        }
+    }
+    Ok(())
+}
+
+async fn notify_budget_reached(app: &AppHandle, budget: &AiBudget, limit: f64) {
+    let title = "AI budget reached";
+    let body = match budget.action {
+        BudgetAction::Warn => format!("This month's AI usage passed ${:.2}", limit),
+        BudgetAction::Block => format!(
+            "This month's AI usage reached ${:.2}; AI features are paused until next month",
+            limit
+        ),
+    };
+    if let Err(e) = app.notification().builder().title(title).body(&body).show() {
+        eprintln!("Failed to show AI budget notification: {}", e);
+        return;
+    }
+    notifications::record_history(
+        &app.state::<TokenStore>(),
+        &app.state::<LocalStorage>(),
+        Some("ai_budget"),
+        title,
+        Some(&body),
+    )
+    .await;
+}
+
+/// Get AI usage and cost of the months `range` touches
+#[tauri::command]
+pub async fn get_ai_usage(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    range: ReportRange,
+) -> Result<AiUsageReport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if range.end_ms <= range.start_ms {
+        return Err("Range end must be after its start".to_string());
+    }
+    let account = token_store.account_context().await?;
+
+    let months = months_between(range.start_ms, range.end_ms - 1)
+        .iter()
+        .map(|month| load_month(&storage, &account, month))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut total = UsageTotals::default();
+    for month in &months {
+        total.add(&month.total);
+    }
+    let month_cost_usd = load_month(&storage, &account, &month_of(Local::now().date_naive()))?
+        .total
+        .cost_usd;
+    let budget = load_budget(&app)?;
+    let over_budget = budget
+        .monthly_limit_usd
+        .is_some_and(|limit| month_cost_usd >= limit);
+
+    Ok(AiUsageReport {
+        range,
+        months,
+        total,
+        budget,
+        month_cost_usd,
+        over_budget,
+    })
+}
+
+/// Get the monthly AI budget
+#[tauri::command]
+pub async fn get_ai_budget(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+) -> Result<AiBudget, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    load_budget(&app)
+}
+
+/// Set the monthly AI budget; no limit removes it
+#[tauri::command]
+pub async fn set_ai_budget(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    budget: AiBudget,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if budget.monthly_limit_usd.is_some_and(|limit| limit <= 0.0) {
+        return Err("Monthly budget must be positive".to_string());
+    }
+
+    let store = storage::fs::settings_store(&app, AI_STORE_FILE)
+        .map_err(|e| format!("Failed to access AI store: {}", e))?;
+    store.set(AI_BUDGET_KEY, serde_json::json!(budget));
+    storage::fs::save_store(&app, AI_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save AI store: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_cost() {
+        let cost = call_cost("gpt-4o-mini-2024-07-18", 1_000_000, 100_000);
+        assert!((cost - 0.21).abs() < 1e-9);
+        assert!((call_cost("gpt-4o", 1_000, 1_000) - 0.0125).abs() < 1e-9);
+        assert_eq!(call_cost("llama3.1", 5_000, 5_000), 0.0);
+    }
+
+    #[test]
+    fn test_months_between() {
+        let ms = |y: i32, m: u32, d: u32| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(y, m, d)
+                        .unwrap()
+                        .and_hms_opt(12, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+                .timestamp_millis()
+        };
+        assert_eq!(
+            months_between(ms(2025, 11, 20), ms(2026, 1, 5)),
+            vec!["2025-11", "2025-12", "2026-01"]
+        );
+        assert_eq!(
+            months_between(ms(2025, 3, 1), ms(2025, 3, 31)),
+            vec!["2025-03"]
+        );
+    }
+}
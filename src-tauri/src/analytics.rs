@@ -0,0 +1,1126 @@
+//! Inbox analytics
+//!
+//! Every thread fetched through `get_thread_detail`/`get_thread_details` has
+//! its message metadata (sender, date, read state, List-Unsubscribe) recorded
+//! in the `EMAIL_METADATA` storage collection. Reports are computed from that
+//! local history, so they cover months of mail without refetching from Gmail.
+//!
+//! The meeting load heatmap (`get_meeting_load_heatmap`) comes from the
+//! calendar instead; the planner uses it to fill historically quiet hours first.
+//!
+//! The contact timeline (`get_contact_timeline`) joins that history with the
+//! search index, the task links and the calendar to show everything involving
+//! one person.
+
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::cache::{CacheState, TASKS_KEY_PREFIX};
+use crate::google::calendar;
+use crate::google::tasks::TaskMetadata;
+use crate::google::types::{CalendarEvent, GmailMessage, GmailThreadDetail, Task, TaskRef};
+use crate::google::{GoogleClient, CALENDAR_API_BASE};
+use crate::processing::{self, FirstDayOfWeek};
+use crate::search::IndexedThread;
+use crate::storage::{LocalStorage, EMAIL_METADATA, SEARCH_INDEX, TASK_METADATA, TASK_REFS};
+use chrono::{Datelike, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::{AppHandle, State};
+
+/// Minimum messages before a sender or domain can be suggested
+const MIN_MESSAGES_FOR_SUGGESTION: u32 = 5;
+/// Share of unread messages that marks a sender or domain as noise
+const NOISE_IGNORE_RATIO: f64 = 0.8;
+/// Entries returned in each top list
+const TOP_LIMIT: usize = 25;
+
+const MEETING_LOAD_CACHE_KEY: &str = "analytics:meeting_load";
+const MEETING_LOAD_CACHE_TTL_SECS: u64 = 12 * 60 * 60;
+/// Weeks of calendar history behind the planner's quiet hours
+const QUIET_HOURS_LOOKBACK_WEEKS: i64 = 8;
+
+// ============================================================================
+// Metadata
+// ============================================================================
+
+/// Per-message metadata kept for analytics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageMetadata {
+    pub thread_id: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub domain: String,
+    pub date_ms: i64,
+    /// False while the message still carries the UNREAD label
+    pub opened: bool,
+    pub has_list_unsubscribe: bool,
+}
+
+/// Split a From header (`"Jane Doe" <jane@example.com>` or `jane@example.com`)
+pub(crate) fn parse_from_header(from: &str) -> (String, String) {
+    let from = from.trim();
+    match (from.rfind('<'), from.rfind('>')) {
+        (Some(start), Some(end)) if start < end => {
+            let name = from[..start].trim().trim_matches('"').trim().to_string();
+            (name, from[start + 1..end].trim().to_lowercase())
+        }
+        _ => (String::new(), from.to_lowercase()),
+    }
+}
+
+pub(crate) fn header<'a>(message: &'a GmailMessage, name: &str) -> Option<&'a str> {
+    message
+        .payload
+        .as_ref()?
+        .headers
+        .as_ref()?
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+fn message_metadata(thread_id: &str, message: &GmailMessage) -> Option<MessageMetadata> {
+    let (from_name, from_email) = parse_from_header(header(message, "From")?);
+    let domain = from_email.rsplit_once('@')?.1.to_string();
+    let date_ms = message.internal_date.as_deref()?.parse().ok()?;
+    let unread = message
+        .label_ids
+        .as_ref()
+        .is_some_and(|labels| labels.iter().any(|l| l == "UNREAD"));
+
+    Some(MessageMetadata {
+        thread_id: thread_id.to_string(),
+        from_name,
+        from_email,
+        domain,
+        date_ms,
+        opened: !unread,
+        has_list_unsubscribe: header(message, "List-Unsubscribe").is_some(),
+    })
+}
+
+/// Record the metadata of fetched threads (keyed by message ID)
+///
+/// Re-recording a message updates its read state.
+pub fn record_threads(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    threads: &[GmailThreadDetail],
+) -> Result<usize, String> {
+    let mut records = Vec::new();
+    for thread in threads {
+        for message in thread.messages.iter().flatten() {
+            let Some(metadata) = message_metadata(&thread.id, message) else {
+                continue;
+            };
+            let value = serde_json::to_value(&metadata)
+                .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+            records.push((message.id.clone(), value));
+        }
+    }
+
+    let recorded = records.len();
+    storage.put_many(account, EMAIL_METADATA, records)?;
+    Ok(recorded)
+}
+
+// ============================================================================
+// Noise Report
+// ============================================================================
+
+/// Time range of a report (epoch milliseconds, end exclusive)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReportRange {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Volume and engagement of one sender
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenderStats {
+    pub email: String,
+    pub name: String,
+    pub domain: String,
+    pub messages: u32,
+    pub opened: u32,
+    pub open_ratio: f64,
+    pub ignore_ratio: f64,
+    pub last_seen_ms: i64,
+    pub has_list_unsubscribe: bool,
+}
+
+/// Volume and engagement of one domain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainStats {
+    pub domain: String,
+    pub messages: u32,
+    pub senders: u32,
+    pub opened: u32,
+    pub open_ratio: f64,
+    pub ignore_ratio: f64,
+}
+
+/// Suggested action for a noisy sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseAction {
+    /// Mailing list offering List-Unsubscribe
+    Unsubscribe,
+    /// No unsubscribe header; filter it instead
+    Mute,
+}
+
+/// A sender suggested for muting or unsubscribing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoiseCandidate {
+    pub email: String,
+    pub domain: String,
+    pub action: NoiseAction,
+    pub messages: u32,
+    pub ignore_ratio: f64,
+}
+
+/// Volume of one week (weeks start on the configured first day)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekVolume {
+    /// YYYY-MM-DD of the week's first day
+    pub start_date: String,
+    pub iso_year: i32,
+    pub iso_week: u32,
+    pub messages: u32,
+    pub opened: u32,
+}
+
+/// Inbox noise report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoiseReport {
+    pub range: ReportRange,
+    pub total_messages: u32,
+    /// Busiest senders first
+    pub senders: Vec<SenderStats>,
+    /// Busiest domains first
+    pub domains: Vec<DomainStats>,
+    /// Noisiest candidates first
+    pub candidates: Vec<NoiseCandidate>,
+    /// Weeks with mail, oldest first
+    pub weeks: Vec<WeekVolume>,
+}
+
+fn ratio(part: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// Build the noise report from recorded metadata
+fn build_report(
+    metadata: impl IntoIterator<Item = MessageMetadata>,
+    range: ReportRange,
+    first_day: FirstDayOfWeek,
+) -> NoiseReport {
+    let mut senders: HashMap<String, SenderStats> = HashMap::new();
+    let mut domain_senders: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut weeks: BTreeMap<NaiveDate, (u32, u32)> = BTreeMap::new();
+    let mut total_messages = 0;
+
+    for m in metadata
+        .into_iter()
+        .filter(|m| m.date_ms >= range.start_ms && m.date_ms < range.end_ms)
+    {
+        total_messages += 1;
+        if let Some(date) = Local.timestamp_millis_opt(m.date_ms).single() {
+            let week = weeks
+                .entry(processing::week_start(date.date_naive(), first_day))
+                .or_default();
+            week.0 += 1;
+            week.1 += m.opened as u32;
+        }
+        domain_senders
+            .entry(m.domain.clone())
+            .or_default()
+            .insert(m.from_email.clone());
+
+        let stats = senders
+            .entry(m.from_email.clone())
+            .or_insert_with(|| SenderStats {
+                email: m.from_email.clone(),
+                name: m.from_name.clone(),
+                domain: m.domain.clone(),
+                messages: 0,
+                opened: 0,
+                open_ratio: 0.0,
+                ignore_ratio: 0.0,
+                last_seen_ms: m.date_ms,
+                has_list_unsubscribe: false,
+            });
+        stats.messages += 1;
+        stats.opened += m.opened as u32;
+        stats.has_list_unsubscribe |= m.has_list_unsubscribe;
+        if m.date_ms >= stats.last_seen_ms {
+            stats.last_seen_ms = m.date_ms;
+            if !m.from_name.is_empty() {
+                stats.name = m.from_name;
+            }
+        }
+    }
+
+    let mut senders: Vec<SenderStats> = senders
+        .into_values()
+        .map(|mut s| {
+            s.open_ratio = ratio(s.opened, s.messages);
+            s.ignore_ratio = 1.0 - s.open_ratio;
+            s
+        })
+        .collect();
+    senders.sort_by(|a, b| {
+        b.messages
+            .cmp(&a.messages)
+            .then_with(|| a.email.cmp(&b.email))
+    });
+
+    let mut domains: HashMap<String, DomainStats> = HashMap::new();
+    for s in &senders {
+        let d = domains
+            .entry(s.domain.clone())
+            .or_insert_with(|| DomainStats {
+                domain: s.domain.clone(),
+                messages: 0,
+                senders: domain_senders
+                    .get(&s.domain)
+                    .map_or(0, |set| set.len() as u32),
+                opened: 0,
+                open_ratio: 0.0,
+                ignore_ratio: 0.0,
+            });
+        d.messages += s.messages;
+        d.opened += s.opened;
+    }
+    let mut domains: Vec<DomainStats> = domains
+        .into_values()
+        .map(|mut d| {
+            d.open_ratio = ratio(d.opened, d.messages);
+            d.ignore_ratio = 1.0 - d.open_ratio;
+            d
+        })
+        .collect();
+    domains.sort_by(|a, b| {
+        b.messages
+            .cmp(&a.messages)
+            .then_with(|| a.domain.cmp(&b.domain))
+    });
+
+    let mut candidates: Vec<NoiseCandidate> = senders
+        .iter()
+        .filter(|s| {
+            s.messages >= MIN_MESSAGES_FOR_SUGGESTION && s.ignore_ratio >= NOISE_IGNORE_RATIO
+        })
+        .map(|s| NoiseCandidate {
+            email: s.email.clone(),
+            domain: s.domain.clone(),
+            action: if s.has_list_unsubscribe {
+                NoiseAction::Unsubscribe
+            } else {
+                NoiseAction::Mute
+            },
+            messages: s.messages,
+            ignore_ratio: s.ignore_ratio,
+        })
+        .collect();
+    // Ignored volume: messages never opened
+    candidates.sort_by(|a, b| {
+        let noise = |c: &NoiseCandidate| c.messages as f64 * c.ignore_ratio;
+        noise(b).total_cmp(&noise(a))
+    });
+
+    senders.truncate(TOP_LIMIT);
+    domains.truncate(TOP_LIMIT);
+    candidates.truncate(TOP_LIMIT);
+
+    let weeks = weeks
+        .into_iter()
+        .map(|(start, (messages, opened))| {
+            let (iso_year, iso_week) = processing::iso_week_number(start);
+            WeekVolume {
+                start_date: start.format("%Y-%m-%d").to_string(),
+                iso_year,
+                iso_week,
+                messages,
+                opened,
+            }
+        })
+        .collect();
+
+    NoiseReport {
+        range,
+        total_messages,
+        senders,
+        domains,
+        candidates,
+        weeks,
+    }
+}
+
+/// Report volume per sender/domain, open/ignore ratios and mute/unsubscribe candidates
+#[tauri::command]
+pub async fn get_inbox_noise_report(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    range: ReportRange,
+) -> Result<NoiseReport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if range.end_ms <= range.start_ms {
+        return Err("Range end must be after its start".to_string());
+    }
+    let account = token_store.account_context().await?;
+
+    let metadata = storage
+        .list(&account, EMAIL_METADATA)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok());
+
+    Ok(build_report(
+        metadata,
+        range,
+        processing::first_day_of_week(&app),
+    ))
+}
+
+// ============================================================================
+// Meeting Load
+// ============================================================================
+
+const WEEK_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Meeting density per weekday and hour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeetingHeatmap {
+    pub range: ReportRange,
+    /// Row labels, starting on the configured first day of the week
+    pub weekdays: Vec<String>,
+    /// `minutes[weekday][hour]`: average meeting minutes per week in that
+    /// local hour
+    pub minutes: Vec<Vec<f64>>,
+    /// Largest cell, for scaling the colors
+    pub max_minutes: f64,
+    pub weeks: f64,
+    pub meeting_count: usize,
+    pub total_hours: f64,
+}
+
+impl MeetingHeatmap {
+    fn row(first_day: FirstDayOfWeek, weekday: chrono::Weekday) -> usize {
+        (weekday.num_days_from_monday() + 7 - first_day.weekday().num_days_from_monday()) as usize
+            % 7
+    }
+
+    /// Average meeting minutes per week in the local hour containing `ms`
+    fn load_at(&self, first_day: FirstDayOfWeek, ms: i64) -> f64 {
+        Local
+            .timestamp_millis_opt(ms)
+            .single()
+            .map(|d| self.minutes[Self::row(first_day, d.weekday())][d.hour() as usize])
+            .unwrap_or(0.0)
+    }
+
+    /// Average load of the hours a slot touches
+    pub fn load_between(&self, first_day: FirstDayOfWeek, start_ms: i64, end_ms: i64) -> f64 {
+        let hour_ms = 60 * 60 * 1000;
+        let loads: Vec<f64> = (start_ms..end_ms)
+            .step_by(hour_ms as usize)
+            .map(|ms| self.load_at(first_day, ms))
+            .collect();
+        if loads.is_empty() {
+            0.0
+        } else {
+            loads.iter().sum::<f64>() / loads.len() as f64
+        }
+    }
+}
+
+/// Timed, busy meetings as (start, end) milliseconds
+fn meeting_spans(events: &[CalendarEvent]) -> Vec<(i64, i64)> {
+    let timed = |time: Option<&crate::google::types::EventDateTime>| {
+        time?
+            .date_time
+            .as_deref()
+            .map(calendar::start_sort_key)
+            .filter(|ms| *ms != i64::MAX)
+    };
+    events
+        .iter()
+        .filter(|e| e.status.as_deref() != Some("cancelled"))
+        .filter(|e| e.transparency.as_deref() != Some("transparent"))
+        .filter(|e| e.event_type.as_deref().is_none_or(|t| t == "default"))
+        .filter_map(|e| Some((timed(e.start.as_ref())?, timed(e.end.as_ref())?)))
+        .filter(|(start, end)| end > start)
+        .collect()
+}
+
+/// Spread meetings over weekday/hour cells, averaged per week of `range`
+fn build_heatmap(
+    spans: &[(i64, i64)],
+    range: ReportRange,
+    first_day: FirstDayOfWeek,
+) -> MeetingHeatmap {
+    let mut minutes = vec![vec![0.0; 24]; 7];
+    let mut meeting_count = 0;
+    let mut total_ms = 0;
+
+    for &(start, end) in spans {
+        let (start, end) = (start.max(range.start_ms), end.min(range.end_ms));
+        if start >= end {
+            continue;
+        }
+        meeting_count += 1;
+        total_ms += end - start;
+
+        let mut cursor = start;
+        while cursor < end {
+            let Some(local) = Local.timestamp_millis_opt(cursor).single() else {
+                break;
+            };
+            let into_hour = (local.minute() * 60 + local.second()) as i64 * 1000
+                + local.timestamp_subsec_millis() as i64;
+            let next = (cursor - into_hour + 60 * 60 * 1000).min(end);
+            minutes[MeetingHeatmap::row(first_day, local.weekday())][local.hour() as usize] +=
+                (next - cursor) as f64 / 60_000.0;
+            cursor = next;
+        }
+    }
+
+    let weeks = ((range.end_ms - range.start_ms) as f64 / WEEK_MS as f64).max(1.0);
+    for cell in minutes.iter_mut().flatten() {
+        *cell = (*cell / weeks * 10.0).round() / 10.0;
+    }
+    let max_minutes = minutes.iter().flatten().copied().fold(0.0, f64::max);
+    let weekdays = std::iter::successors(Some(first_day.weekday()), |d| Some(d.succ()))
+        .take(7)
+        .map(|d| d.to_string())
+        .collect();
+
+    MeetingHeatmap {
+        range,
+        weekdays,
+        minutes,
+        max_minutes,
+        weeks,
+        meeting_count,
+        total_hours: (total_ms as f64 / 3_600_000.0 * 10.0).round() / 10.0,
+    }
+}
+
+/// Heatmap of the last weeks, cached, for the planner
+pub(crate) async fn recent_meeting_load(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    cache: &CacheState,
+    first_day: FirstDayOfWeek,
+) -> Result<MeetingHeatmap, String> {
+    let account = token_store.account_context().await?;
+    let cache_key = account.cache_key(MEETING_LOAD_CACHE_KEY);
+    if let Some(cached) = cache.0.get_json::<MeetingHeatmap>(&cache_key) {
+        return Ok(cached);
+    }
+
+    let end_ms = chrono::Utc::now().timestamp_millis();
+    let range = ReportRange {
+        start_ms: end_ms - QUIET_HOURS_LOOKBACK_WEEKS * WEEK_MS,
+        end_ms,
+    };
+    let events =
+        calendar::events_between(token_store, client, range.start_ms, range.end_ms, None).await?;
+    let heatmap = build_heatmap(&meeting_spans(&events), range, first_day);
+    cache
+        .0
+        .set_json(&cache_key, &heatmap, MEETING_LOAD_CACHE_TTL_SECS);
+    Ok(heatmap)
+}
+
+/// Meeting density per weekday/hour over `range`
+#[tauri::command]
+pub async fn get_meeting_load_heatmap(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    range: ReportRange,
+) -> Result<MeetingHeatmap, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if range.end_ms <= range.start_ms {
+        return Err("Range end must be after its start".to_string());
+    }
+
+    let events =
+        calendar::events_between(&token_store, &client, range.start_ms, range.end_ms, None).await?;
+    Ok(build_heatmap(
+        &meeting_spans(&events),
+        range,
+        processing::first_day_of_week(&app),
+    ))
+}
+
+// ============================================================================
+// Contact Timeline
+// ============================================================================
+
+/// Days of calendar history searched for meetings with a contact
+const CONTACT_MEETING_PAST_DAYS: i64 = 180;
+/// Days ahead searched for upcoming meetings
+const CONTACT_MEETING_AHEAD_DAYS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactItemKind {
+    Thread,
+    Meeting,
+    /// A task from their email: you owe them
+    TaskOwed,
+    /// A task waiting on them: they owe you
+    TaskWaiting,
+}
+
+/// One entry of a contact timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContactItem {
+    pub kind: ContactItemKind,
+    /// Thread, event, or `<list_id>:<task_id>`
+    pub id: String,
+    pub title: String,
+    /// Latest message, meeting start, or task due date (None for undated tasks)
+    pub at_ms: Option<i64>,
+    /// A thread whose latest message is theirs, i.e. awaiting your reply
+    pub open: bool,
+}
+
+/// Everything involving one person, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactTimeline {
+    pub email: String,
+    pub name: String,
+    pub items: Vec<ContactItem>,
+    pub last_email_ms: Option<i64>,
+    pub last_met_ms: Option<i64>,
+    pub next_meeting_ms: Option<i64>,
+    pub open_threads: u32,
+    pub tasks_owed: u32,
+    pub tasks_waiting: u32,
+    /// e.g. "last met Jan 9, 2 open threads, 1 task you owe them"
+    pub summary: String,
+}
+
+/// A task involving the contact
+#[derive(Debug, Clone)]
+struct ContactTask {
+    id: String,
+    title: String,
+    due_ms: Option<i64>,
+    owed: bool,
+}
+
+fn plural(count: u32, word: &str) -> String {
+    format!("{} {}{}", count, word, if count == 1 { "" } else { "s" })
+}
+
+fn short_date(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|d| d.format("%b %-d").to_string())
+        .unwrap_or_default()
+}
+
+/// Threads with a message from `email`
+fn contact_threads(
+    email: &str,
+    metadata: &[MessageMetadata],
+    index: &HashMap<String, IndexedThread>,
+) -> HashSet<String> {
+    let mut threads: HashSet<String> = metadata
+        .iter()
+        .filter(|m| m.from_email == email)
+        .map(|m| m.thread_id.clone())
+        .collect();
+    threads.extend(
+        index
+            .values()
+            .filter(|t| t.senders.iter().any(|s| parse_from_header(s).1 == email))
+            .map(|t| t.thread_id.clone()),
+    );
+    threads
+}
+
+fn build_timeline(
+    email: &str,
+    metadata: Vec<MessageMetadata>,
+    index: &HashMap<String, IndexedThread>,
+    involved: &HashSet<String>,
+    events: &[CalendarEvent],
+    tasks: Vec<ContactTask>,
+    now_ms: i64,
+) -> ContactTimeline {
+    // A thread is open when its latest message is theirs
+    let mut threads: HashMap<String, (i64, bool)> = HashMap::new();
+    let mut name = String::new();
+    let mut latest_name_ms = i64::MIN;
+    for m in metadata.iter().filter(|m| involved.contains(&m.thread_id)) {
+        let from_them = m.from_email == email;
+        if from_them && !m.from_name.is_empty() && m.date_ms > latest_name_ms {
+            latest_name_ms = m.date_ms;
+            name = m.from_name.clone();
+        }
+        let entry = threads
+            .entry(m.thread_id.clone())
+            .or_insert((m.date_ms, from_them));
+        if m.date_ms >= entry.0 {
+            *entry = (m.date_ms, from_them);
+        }
+    }
+
+    let mut items: Vec<ContactItem> = threads
+        .iter()
+        .map(|(id, (at_ms, from_them))| ContactItem {
+            kind: ContactItemKind::Thread,
+            id: id.clone(),
+            title: index
+                .get(id)
+                .map(|t| t.subject.clone())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "(No subject)".to_string()),
+            at_ms: Some(*at_ms),
+            open: *from_them,
+        })
+        .collect();
+
+    let mut last_met_ms = None;
+    let mut next_meeting_ms = None;
+    for event in events {
+        let attendees = event.attendees.as_deref().unwrap_or_default();
+        let Some(attendee) = attendees
+            .iter()
+            .find(|a| a.email.eq_ignore_ascii_case(email))
+        else {
+            continue;
+        };
+        if event.status.as_deref() == Some("cancelled") {
+            continue;
+        }
+        if name.is_empty() {
+            name = attendee.display_name.clone().unwrap_or_default();
+        }
+        let start = event
+            .start
+            .as_ref()
+            .and_then(|s| s.date_time.clone().or(s.date.clone()))
+            .map(|s| calendar::start_sort_key(&s))
+            .filter(|ms| *ms != i64::MAX);
+        if let Some(start) = start {
+            if start <= now_ms {
+                last_met_ms = last_met_ms.max(Some(start));
+            } else if next_meeting_ms.is_none_or(|next| start < next) {
+                next_meeting_ms = Some(start);
+            }
+        }
+        items.push(ContactItem {
+            kind: ContactItemKind::Meeting,
+            id: event.id.clone(),
+            title: event
+                .summary
+                .clone()
+                .unwrap_or_else(|| "(No title)".to_string()),
+            at_ms: start,
+            open: false,
+        });
+    }
+
+    let (mut tasks_owed, mut tasks_waiting) = (0, 0);
+    for task in tasks {
+        if task.owed {
+            tasks_owed += 1;
+        } else {
+            tasks_waiting += 1;
+        }
+        items.push(ContactItem {
+            kind: if task.owed {
+                ContactItemKind::TaskOwed
+            } else {
+                ContactItemKind::TaskWaiting
+            },
+            id: task.id,
+            title: task.title,
+            at_ms: task.due_ms,
+            open: true,
+        });
+    }
+
+    // Newest first; undated tasks lead as they are still open
+    items.sort_by(|a, b| {
+        b.at_ms
+            .unwrap_or(i64::MAX)
+            .cmp(&a.at_ms.unwrap_or(i64::MAX))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let open_threads = items
+        .iter()
+        .filter(|i| i.open && i.kind == ContactItemKind::Thread)
+        .count() as u32;
+    let mut parts = Vec::new();
+    if let Some(met) = last_met_ms {
+        parts.push(format!("last met {}", short_date(met)));
+    }
+    if open_threads > 0 {
+        parts.push(plural(open_threads, "open thread"));
+    }
+    if tasks_owed > 0 {
+        parts.push(format!("{} you owe them", plural(tasks_owed, "task")));
+    }
+    if tasks_waiting > 0 {
+        parts.push(format!("{} waiting on them", plural(tasks_waiting, "task")));
+    }
+
+    ContactTimeline {
+        email: email.to_string(),
+        name,
+        last_email_ms: metadata
+            .iter()
+            .filter(|m| m.from_email == email)
+            .map(|m| m.date_ms)
+            .max(),
+        items,
+        last_met_ms,
+        next_meeting_ms,
+        open_threads,
+        tasks_owed,
+        tasks_waiting,
+        summary: parts.join(", "),
+    }
+}
+
+/// Meetings with `email` in the searched calendar window
+async fn contact_meetings(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    email: &str,
+    now_ms: i64,
+) -> Result<Vec<CalendarEvent>, String> {
+    let bound = |ms: i64| {
+        Local
+            .timestamp_millis_opt(ms)
+            .single()
+            .map(|d| d.to_rfc3339())
+            .ok_or_else(|| "Failed to create date".to_string())
+    };
+    let day_ms = 24 * 60 * 60 * 1000;
+    let url = format!(
+        "{}/calendars/primary/events?q={}&timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime&maxResults=250",
+        CALENDAR_API_BASE,
+        urlencoding::encode(email),
+        urlencoding::encode(&bound(now_ms - CONTACT_MEETING_PAST_DAYS * day_ms)?),
+        urlencoding::encode(&bound(now_ms + CONTACT_MEETING_AHEAD_DAYS * day_ms)?)
+    );
+    let response = client
+        .get_fields(&url, token_store, calendar::EVENT_LIST_FIELDS)
+        .await?;
+    Ok(response.items.unwrap_or_default())
+}
+
+/// Open tasks linked to the contact: from their threads (owed), or waiting on them
+fn contact_tasks(
+    storage: &LocalStorage,
+    cache: &CacheState,
+    account: &AccountContext,
+    email: &str,
+    threads: &HashSet<String>,
+) -> Result<Vec<ContactTask>, String> {
+    let due_ms = |due: Option<&str>| {
+        due.map(calendar::start_sort_key)
+            .filter(|ms| *ms != i64::MAX)
+    };
+    let refs: HashMap<String, TaskRef> = storage
+        .list(account, TASK_REFS)?
+        .into_iter()
+        .filter_map(|(id, record)| Some((id, serde_json::from_value(record.value).ok()?)))
+        .collect();
+
+    let mut tasks: Vec<ContactTask> = refs
+        .iter()
+        .filter(|(_, r)| r.status != "completed")
+        .filter(|(_, r)| {
+            r.source_thread_id
+                .as_ref()
+                .is_some_and(|t| threads.contains(t))
+        })
+        .map(|(id, r)| ContactTask {
+            id: id.clone(),
+            title: r.title.clone(),
+            due_ms: due_ms(r.due.as_deref()),
+            owed: true,
+        })
+        .collect();
+
+    let cached_task = |id: &str| -> Option<Task> {
+        let (list_id, task_id) = id.split_once(':')?;
+        cache
+            .0
+            .get_json::<Vec<Task>>(&account.cache_key(&format!("{}{}", TASKS_KEY_PREFIX, list_id)))?
+            .into_iter()
+            .find(|t| t.id.as_deref() == Some(task_id))
+    };
+    for (id, record) in storage.list(account, TASK_METADATA)? {
+        let Ok(metadata) = serde_json::from_value::<TaskMetadata>(record.value) else {
+            continue;
+        };
+        if !metadata
+            .waiting_on
+            .is_some_and(|w| w.trim().eq_ignore_ascii_case(email))
+        {
+            continue;
+        }
+        let (title, due, completed) = match (refs.get(&id), cached_task(&id)) {
+            (_, Some(task)) => (
+                task.title.clone(),
+                task.due.clone(),
+                task.status.as_deref() == Some("completed"),
+            ),
+            (Some(r), None) => (r.title.clone(), r.due.clone(), r.status == "completed"),
+            (None, None) => ("(Task)".to_string(), None, false),
+        };
+        if !completed {
+            tasks.push(ContactTask {
+                id,
+                title,
+                due_ms: due_ms(due.as_deref()),
+                owed: false,
+            });
+        }
+    }
+    Ok(tasks)
+}
+
+/// Get the threads, meetings and tasks involving `email`, newest first, with
+/// a one-line summary ("last met Jan 9, 2 open threads, 1 task you owe them")
+///
+/// Threads and tasks come from the local store; meetings are searched in the
+/// calendar, and a calendar failure only leaves them out.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_contact_timeline(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
+    storage: State<'_, LocalStorage>,
+    email: String,
+) -> Result<ContactTimeline, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let email = email.trim().to_lowercase();
+    if !email.contains('@') {
+        return Err("Invalid email address".to_string());
+    }
+    let account = token_store.account_context().await?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+
+    let metadata: Vec<MessageMetadata> = storage
+        .list(&account, EMAIL_METADATA)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok())
+        .collect();
+    let index: HashMap<String, IndexedThread> = storage
+        .list(&account, SEARCH_INDEX)?
+        .into_iter()
+        .filter_map(|(id, record)| Some((id, serde_json::from_value(record.value).ok()?)))
+        .collect();
+    let events = contact_meetings(&token_store, &client, &email, now_ms)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to search meetings with {}: {}", email, e);
+            Vec::new()
+        });
+
+    let threads = contact_threads(&email, &metadata, &index);
+    let tasks = contact_tasks(&storage, &cache, &account, &email, &threads)?;
+
+    Ok(build_timeline(
+        &email, metadata, &index, &threads, &events, tasks, now_ms,
+    ))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(from: &str, date_ms: i64, opened: bool, list: bool) -> MessageMetadata {
+        let (from_name, from_email) = parse_from_header(from);
+        MessageMetadata {
+            thread_id: "t".to_string(),
+            domain: from_email.rsplit_once('@').unwrap().1.to_string(),
+            from_name,
+            from_email,
+            date_ms,
+            opened,
+            has_list_unsubscribe: list,
+        }
+    }
+
+    #[test]
+    fn test_parse_from_header() {
+        assert_eq!(
+            parse_from_header("\"Jane Doe\" <Jane@Example.com>"),
+            ("Jane Doe".to_string(), "jane@example.com".to_string())
+        );
+        assert_eq!(
+            parse_from_header("bob@example.com"),
+            (String::new(), "bob@example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_noise_report() {
+        let mut metadata: Vec<_> = (0..6)
+            .map(|i| message("Deals <news@shop.com>", 1_000 + i, i == 0, true))
+            .collect();
+        metadata.extend((0..6).map(|i| message("alerts@shop.com", 1_000 + i, false, false)));
+        metadata.push(message("Jane <jane@work.com>", 1_500, true, false));
+        // Outside the range
+        metadata.push(message("Jane <jane@work.com>", 5_000, false, false));
+
+        let report = build_report(
+            metadata,
+            ReportRange {
+                start_ms: 0,
+                end_ms: 2_000,
+            },
+            FirstDayOfWeek::Monday,
+        );
+
+        assert_eq!(report.total_messages, 13);
+        assert_eq!(report.senders[0].email, "alerts@shop.com");
+        assert_eq!(report.senders[1].opened, 1);
+        assert_eq!(report.domains[0].domain, "shop.com");
+        assert_eq!(report.domains[0].messages, 12);
+        assert_eq!(report.domains[0].senders, 2);
+
+        assert_eq!(report.candidates.len(), 2);
+        assert_eq!(report.candidates[0].email, "alerts@shop.com");
+        assert_eq!(report.candidates[0].action, NoiseAction::Mute);
+        assert_eq!(report.candidates[1].action, NoiseAction::Unsubscribe);
+
+        // All within the first days of 1970, a Thursday
+        assert_eq!(report.weeks.len(), 1);
+        assert_eq!(report.weeks[0].messages, 13);
+        assert_eq!(report.weeks[0].iso_week, 1);
+    }
+
+    #[test]
+    fn test_contact_timeline() {
+        let mut metadata = vec![
+            // Jane wrote last: open
+            message("Jane Doe <jane@example.com>", 1_000, true, false),
+            // I replied last: not open
+            message("Jane <jane@example.com>", 2_000, true, false),
+            message("me@example.com", 3_000, true, false),
+            message("bob@example.com", 4_000, true, false),
+        ];
+        metadata[1].thread_id = "t2".to_string();
+        metadata[2].thread_id = "t2".to_string();
+        metadata[3].thread_id = "t3".to_string();
+        let event: CalendarEvent = serde_json::from_value(serde_json::json!({
+            "id": "e1",
+            "summary": "1:1",
+            "start": { "dateTime": "1970-01-01T00:00:05Z" },
+            "attendees": [{ "email": "Jane@Example.com" }, { "email": "me@example.com" }],
+        }))
+        .unwrap();
+        let tasks = vec![ContactTask {
+            id: "l1:k1".to_string(),
+            title: "Send Jane the deck".to_string(),
+            due_ms: None,
+            owed: true,
+        }];
+
+        let index = HashMap::new();
+        let threads = contact_threads("jane@example.com", &metadata, &index);
+        assert_eq!(threads.len(), 2);
+        let timeline = build_timeline(
+            "jane@example.com",
+            metadata,
+            &index,
+            &threads,
+            &[event],
+            tasks,
+            10_000,
+        );
+
+        assert_eq!(timeline.name, "Jane");
+        assert_eq!(timeline.last_email_ms, Some(2_000));
+        assert_eq!(timeline.last_met_ms, Some(5_000));
+        assert_eq!(timeline.open_threads, 1);
+        assert_eq!(timeline.tasks_owed, 1);
+        let kinds: Vec<_> = timeline.items.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ContactItemKind::TaskOwed,
+                ContactItemKind::Meeting,
+                ContactItemKind::Thread,
+                ContactItemKind::Thread
+            ]
+        );
+        assert!(timeline.summary.starts_with("last met "));
+        assert!(timeline
+            .summary
+            .ends_with("1 open thread, 1 task you owe them"));
+    }
+
+    #[test]
+    fn test_meeting_heatmap() {
+        let at = |day: u32, h: u32, m: u32| {
+            Local
+                .with_ymd_and_hms(2026, 1, day, h, m, 0)
+                .unwrap()
+                .timestamp_millis()
+        };
+        // Two weeks starting Monday Jan 5
+        let range = ReportRange {
+            start_ms: at(5, 0, 0),
+            end_ms: at(19, 0, 0),
+        };
+        let spans = vec![
+            // Monday 9:30-11:00, both weeks
+            (at(5, 9, 30), at(5, 11, 0)),
+            (at(12, 9, 30), at(12, 11, 0)),
+            // Wednesday 14:00-14:30, one week
+            (at(7, 14, 0), at(7, 14, 30)),
+            // Outside the range
+            (at(20, 9, 0), at(20, 10, 0)),
+        ];
+
+        let heatmap = build_heatmap(&spans, range, FirstDayOfWeek::Monday);
+        assert_eq!(heatmap.weekdays[0], "Mon");
+        assert_eq!(heatmap.meeting_count, 3);
+        assert_eq!(heatmap.total_hours, 3.5);
+        assert_eq!(heatmap.minutes[0][9], 30.0);
+        assert_eq!(heatmap.minutes[0][10], 60.0);
+        assert_eq!(heatmap.minutes[2][14], 15.0);
+        assert_eq!(heatmap.max_minutes, 60.0);
+
+        let sunday_first = build_heatmap(&spans, range, FirstDayOfWeek::Sunday);
+        assert_eq!(sunday_first.minutes[1][10], 60.0);
+        assert!(
+            heatmap.load_between(FirstDayOfWeek::Monday, at(19, 13, 0), at(19, 15, 0))
+                < heatmap.load_between(FirstDayOfWeek::Monday, at(19, 9, 0), at(19, 11, 0))
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Optional app lock
+//!
+//! When enabled, the app locks itself at startup and after a configurable idle
+//! period. While locked, every data-returning command fails with
+//! `APP_LOCKED_ERROR` and no data change events are broadcast, until the user
+//! unlocks with the OS prompt (Touch ID, Windows Hello or polkit; see
+//! `auth::os_gate`) or a passcode. Passcodes are only ever stored as an Argon2id
+//! hash.
+//!
+//! Commands call `AppLockState::ensure_unlocked` before touching user data;
+//! each successful call also counts as activity for the idle timer.
+
+use crate::auth::os_gate;
+use crate::storage;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const APP_LOCK_STORE_FILE: &str = "app_lock.json";
+const SETTINGS_KEY: &str = "settings";
+
+/// Error returned by gated commands while the app is locked
+pub const APP_LOCKED_ERROR: &str = "App is locked";
+/// Emitted when the app locks itself after the idle period
+pub const APP_LOCKED_EVENT: &str = "app-lock:locked";
+
+/// How often the idle watcher checks the timer
+const IDLE_CHECK_INTERVAL_SECS: u64 = 15;
+/// Wrong passcodes allowed before unlocking is throttled
+const MAX_PASSCODE_ATTEMPTS: u32 = 5;
+/// Wait imposed after too many wrong passcodes
+const PASSCODE_LOCKOUT: Duration = Duration::from_secs(30);
+const MIN_PASSCODE_LEN: usize = 4;
+
+/// How the user unlocks the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnlockMethod {
+    Biometric,
+    Passcode,
+}
+
+/// Persisted app lock configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AppLockConfig {
+    enabled: bool,
+    idle_timeout_minutes: u32,
+    method: UnlockMethod,
+    /// Argon2id PHC string (`<rounds>$<salt hex>$<hash hex>` from older
+    /// versions)
+    passcode_hash: Option<String>,
+}
+
+impl Default for AppLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_timeout_minutes: 5,
+            method: UnlockMethod::Passcode,
+            passcode_hash: None,
+        }
+    }
+}
+
+/// User-editable app lock settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockSettings {
+    pub enabled: bool,
+    pub idle_timeout_minutes: u32,
+    pub method: UnlockMethod,
+}
+
+/// App lock state reported to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub method: UnlockMethod,
+    pub idle_timeout_minutes: u32,
+    pub has_passcode: bool,
+    pub biometric_available: bool,
+}
+
+struct LockInner {
+    config: AppLockConfig,
+    locked: bool,
+    last_activity: Instant,
+    failed_attempts: u32,
+    retry_after: Option<Instant>,
+}
+
+impl LockInner {
+    fn idle_expired(&self) -> bool {
+        let timeout = Duration::from_secs(self.config.idle_timeout_minutes as u64 * 60);
+        self.last_activity.elapsed() >= timeout
+    }
+}
+
+/// Managed app lock state
+pub struct AppLockState(Mutex<LockInner>);
+
+impl Default for AppLockState {
+    fn default() -> Self {
+        Self(Mutex::new(LockInner {
+            config: AppLockConfig::default(),
+            locked: false,
+            last_activity: Instant::now(),
+            failed_attempts: 0,
+            retry_after: None,
+        }))
+    }
+}
+
+impl AppLockState {
+    fn guard(&self) -> Result<std::sync::MutexGuard<'_, LockInner>, String> {
+        self.0
+            .lock()
+            .map_err(|_| "App lock state poisoned".to_string())
+    }
+
+    /// Load the persisted configuration; an enabled lock starts locked
+    pub fn initialize(&self, app: &AppHandle) -> Result<(), String> {
+        let config = load_config(app)?;
+        let mut inner = self.guard()?;
+        inner.locked = config.enabled;
+        inner.config = config;
+        Ok(())
+    }
+
+    /// Fail with `APP_LOCKED_ERROR` unless the app is unlocked, and record activity
+    pub fn ensure_unlocked(&self) -> Result<(), String> {
+        let mut inner = self.guard()?;
+        if !inner.config.enabled {
+            return Ok(());
+        }
+
+        if inner.locked || inner.idle_expired() {
+            inner.locked = true;
+            return Err(APP_LOCKED_ERROR.to_string());
+        }
+
+        inner.last_activity = Instant::now();
+        Ok(())
+    }
+
+    /// Whether the app is locked, without counting as activity
+    pub fn is_locked(&self) -> bool {
+        self.guard()
+            .is_ok_and(|inner| inner.config.enabled && (inner.locked || inner.idle_expired()))
+    }
+
+    /// Lock if the idle period has elapsed; returns true if this call locked the app
+    fn lock_if_idle(&self) -> bool {
+        let Ok(mut inner) = self.guard() else {
+            return false;
+        };
+        if !inner.config.enabled || inner.locked || !inner.idle_expired() {
+            return false;
+        }
+        inner.locked = true;
+        true
+    }
+
+    fn unlock(inner: &mut LockInner) {
+        inner.locked = false;
+        inner.failed_attempts = 0;
+        inner.retry_after = None;
+        inner.last_activity = Instant::now();
+    }
+
+    fn status(&self) -> Result<AppLockStatus, String> {
+        let inner = self.guard()?;
+        Ok(AppLockStatus {
+            enabled: inner.config.enabled,
+            locked: inner.config.enabled && (inner.locked || inner.idle_expired()),
+            method: inner.config.method,
+            idle_timeout_minutes: inner.config.idle_timeout_minutes,
+            has_passcode: inner.config.passcode_hash.is_some(),
+            biometric_available: os_gate::is_available(),
+        })
+    }
+}
+
+fn load_config(app: &AppHandle) -> Result<AppLockConfig, String> {
+    let store = storage::fs::settings_store(app, APP_LOCK_STORE_FILE)
+        .map_err(|e| format!("Failed to access app lock store: {}", e))?;
+
+    Ok(store
+        .get(SETTINGS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_config(app: &AppHandle, config: &AppLockConfig) -> Result<(), String> {
+    let store = storage::fs::settings_store(app, APP_LOCK_STORE_FILE)
+        .map_err(|e| format!("Failed to access app lock store: {}", e))?;
+
+    store.set(SETTINGS_KEY, serde_json::json!(config));
+    storage::fs::save_store(app, APP_LOCK_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save app lock settings: {}", e))
+}
+
+// ============================================================================
+// Passcode Hashing
+// ============================================================================
+
+fn hash_passcode(passcode: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).map_err(|e| format!("Invalid salt: {}", e))?;
+    Argon2::default()
+        .hash_password(passcode.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash passcode: {}", e))
+}
+
+/// Check `passcode` against a stored Argon2id hash
+fn verify_passcode(passcode: &str, stored: &str) -> bool {
+    PasswordHash::new(stored).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(passcode.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+// ============================================================================
+// Idle Watcher
+// ============================================================================
+
+/// Lock the app once the idle period elapses and tell the windows to show the lock screen
+pub fn spawn_idle_watch(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(IDLE_CHECK_INTERVAL_SECS)).await;
+
+            if app.state::<AppLockState>().lock_if_idle() {
+                if let Err(e) = app.emit(APP_LOCKED_EVENT, ()) {
+                    eprintln!("Failed to emit {}: {}", APP_LOCKED_EVENT, e);
+                }
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Get the current app lock state
+#[tauri::command]
+pub fn get_app_lock_status(lock: State<'_, AppLockState>) -> Result<AppLockStatus, String> {
+    crate::perf::trace_command!();
+    lock.status()
+}
+
+/// Update app lock settings (only while unlocked)
+#[tauri::command]
+pub fn set_app_lock_settings(
+    app: AppHandle,
+    lock: State<'_, AppLockState>,
+    settings: AppLockSettings,
+) -> Result<AppLockStatus, String> {
+    crate::perf::trace_command!();
+    lock.ensure_unlocked()?;
+    if settings.idle_timeout_minutes == 0 {
+        return Err("idle_timeout_minutes must be at least 1".to_string());
+    }
+
+    {
+        let mut inner = lock.guard()?;
+        if settings.enabled {
+            match settings.method {
+                UnlockMethod::Passcode if inner.config.passcode_hash.is_none() => {
+                    return Err("Set a passcode before enabling the app lock".to_string());
+                }
+                UnlockMethod::Biometric if !os_gate::is_available() => {
+                    return Err("Biometric unlock is not available on this device".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let mut config = inner.config.clone();
+        config.enabled = settings.enabled;
+        config.idle_timeout_minutes = settings.idle_timeout_minutes;
+        config.method = settings.method;
+        save_config(&app, &config)?;
+
+        inner.config = config;
+        inner.last_activity = Instant::now();
+    }
+
+    lock.status()
+}
+
+/// Set or change the unlock passcode (only while unlocked)
+#[tauri::command]
+pub fn set_app_lock_passcode(
+    app: AppHandle,
+    lock: State<'_, AppLockState>,
+    passcode: String,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    lock.ensure_unlocked()?;
+    if passcode.chars().count() < MIN_PASSCODE_LEN {
+        return Err(format!(
+            "Passcode must be at least {} characters",
+            MIN_PASSCODE_LEN
+        ));
+    }
+
+    let mut inner = lock.guard()?;
+    let mut config = inner.config.clone();
+    config.passcode_hash = Some(hash_passcode(&passcode)?);
+    save_config(&app, &config)?;
+    inner.config = config;
+    Ok(())
+}
+
+/// Lock the app immediately
+#[tauri::command]
+pub fn lock_app(lock: State<'_, AppLockState>) -> Result<AppLockStatus, String> {
+    crate::perf::trace_command!();
+    {
+        let mut inner = lock.guard()?;
+        if !inner.config.enabled {
+            return Err("App lock is not enabled".to_string());
+        }
+        inner.locked = true;
+    }
+    lock.status()
+}
+
+/// Record user activity (keeps the idle timer from expiring)
+#[tauri::command]
+pub fn record_app_activity(lock: State<'_, AppLockState>) -> Result<(), String> {
+    crate::perf::trace_command!();
+    lock.ensure_unlocked()
+}
+
+/// Unlock with the passcode
+#[tauri::command]
+pub fn unlock_with_passcode(
+    lock: State<'_, AppLockState>,
+    passcode: String,
+) -> Result<AppLockStatus, String> {
+    crate::perf::trace_command!();
+    {
+        let mut inner = lock.guard()?;
+        if let Some(retry_after) = inner.retry_after {
+            let now = Instant::now();
+            if now < retry_after {
+                return Err(format!(
+                    "Too many attempts, try again in {} seconds",
+                    (retry_after - now).as_secs() + 1
+                ));
+            }
+        }
+
+        let stored = inner
+            .config
+            .passcode_hash
+            .clone()
+            .ok_or("No passcode has been set")?;
+
+        if !verify_passcode(&passcode, &stored) {
+            inner.failed_attempts += 1;
+            if inner.failed_attempts >= MAX_PASSCODE_ATTEMPTS {
+                inner.failed_attempts = 0;
+                inner.retry_after = Some(Instant::now() + PASSCODE_LOCKOUT);
+            }
+            return Err("Incorrect passcode".to_string());
+        }
+
+        AppLockState::unlock(&mut inner);
+    }
+    lock.status()
+}
+
+/// Unlock with the OS biometric prompt
+#[tauri::command]
+pub async fn unlock_with_biometric(lock: State<'_, AppLockState>) -> Result<AppLockStatus, String> {
+    crate::perf::trace_command!();
+    tokio::task::spawn_blocking(|| os_gate::verify("Unlock Rainy Day"))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))??;
+
+    AppLockState::unlock(&mut *lock.guard()?);
+    lock.status()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passcode_hash_roundtrip() {
+        let stored = hash_passcode("2468").unwrap();
+        assert!(stored.starts_with("$argon2id$"));
+        assert!(!stored.contains("2468"));
+        assert!(verify_passcode("2468", &stored));
+        assert!(!verify_passcode("1357", &stored));
+        assert!(!verify_passcode("2468", "garbage"));
+
+        // Fresh salt per hash
+        assert_ne!(stored, hash_passcode("2468").unwrap());
+    }
+
+    #[test]
+    fn test_gate_locks_after_idle_period() {
+        let lock = AppLockState::default();
+        assert!(lock.ensure_unlocked().is_ok());
+
+        {
+            let mut inner = lock.guard().unwrap();
+            inner.config.enabled = true;
+            inner.config.idle_timeout_minutes = 1;
+            inner.last_activity = Instant::now() - Duration::from_secs(61);
+        }
+
+        assert_eq!(lock.ensure_unlocked().unwrap_err(), APP_LOCKED_ERROR);
+        assert!(lock.status().unwrap().locked);
+
+        AppLockState::unlock(&mut lock.guard().unwrap());
+        assert!(lock.ensure_unlocked().is_ok());
+        assert!(!lock.lock_if_idle());
+    }
+}
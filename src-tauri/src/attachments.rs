@@ -0,0 +1,637 @@
+//! Attachment downloads and download policy
+//!
+//! `download_attachment` saves an attachment to the Downloads folder after
+//! enforcing the configured policy:
+//! - a maximum size, checked against the size Gmail reports before anything
+//!   is fetched and again against the downloaded bytes
+//! - blocked file extensions (executables and scripts by default)
+//! - an optional virus scan: one of the known `Scanner`s, run with fixed
+//!   arguments and the file path; a non-zero exit refuses the file
+//!
+//! The scanner can't be set through `set_download_policy`: it runs a program,
+//! so it's chosen with `set_attachment_scanner`, which always needs the OS
+//! confirmation, or with the `RAINY_DAY_ATTACHMENT_SCANNER` environment
+//! variable.
+//!
+//! The file is written under a temporary name and only renamed into place
+//! once it passed the scan, so a refused file never appears in Downloads.
+//! Refusals are returned as a typed `DownloadError` the UI can explain.
+//!
+//! With the `attachment-text` feature, `extract_attachment_text` pulls the
+//! text out of a PDF attachment and runs it through the email processing
+//! (snippet, urgent keywords, deadlines), so an invoice "due Friday" can raise
+//! its thread's priority (pass the text as `EmailInput::attachment_text`) and
+//! suggest a task.
+
+use crate::app_lock::AppLockState;
+use crate::auth::os_gate::{self, SensitiveAction};
+use crate::auth::TokenStore;
+use crate::google::mailbox::{self, Mailbox};
+use crate::google::{GoogleClient, GMAIL_API_BASE};
+use crate::natural_date;
+use crate::processing;
+use crate::storage::{self, LocalStorage};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const ATTACHMENTS_STORE_FILE: &str = "attachments.json";
+const POLICY_KEY: &str = "policy";
+const SCANNER_KEY: &str = "scanner";
+/// Scanner used when none is stored (`clamscan`, `clamdscan` or `defender`)
+const SCANNER_ENV: &str = "RAINY_DAY_ATTACHMENT_SCANNER";
+
+/// Gmail accepts attachments up to 25 MB
+const DEFAULT_MAX_SIZE_MB: u64 = 25;
+/// Response limit of an attachment download (base64 adds a third)
+const MAX_DOWNLOAD_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+/// How long a scan may run
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+/// Characters of scan output kept in a refusal
+const SCAN_OUTPUT_LIMIT: usize = 500;
+/// Suffix of files not yet scanned
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Executables, installers and scripts
+const DEFAULT_BLOCKED_EXTENSIONS: &[&str] = &[
+    "app", "bat", "cmd", "com", "cpl", "dll", "dmg", "exe", "hta", "jar", "js", "jse", "lnk",
+    "msi", "msix", "pif", "pkg", "ps1", "reg", "scr", "sh", "vb", "vbe", "vbs", "wsf",
+];
+
+// ============================================================================
+// Policy
+// ============================================================================
+
+/// Virus scanners a download can be checked with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scanner {
+    /// ClamAV's `clamscan`
+    Clamscan,
+    /// ClamAV's daemon client `clamdscan`
+    Clamdscan,
+    /// Microsoft Defender's `MpCmdRun.exe`
+    Defender,
+}
+
+impl Scanner {
+    fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::json!(name.trim().to_lowercase())).ok()
+    }
+
+    /// Program and the arguments passed before the file path
+    fn command(self) -> (PathBuf, &'static [&'static str]) {
+        match self {
+            Scanner::Clamscan => (PathBuf::from("clamscan"), &["--no-summary"]),
+            Scanner::Clamdscan => (PathBuf::from("clamdscan"), &["--no-summary", "--fdpass"]),
+            Scanner::Defender => {
+                let program_files =
+                    std::env::var("ProgramFiles").unwrap_or_else(|_| "C:\\Program Files".into());
+                (
+                    Path::new(&program_files)
+                        .join("Windows Defender")
+                        .join("MpCmdRun.exe"),
+                    &["-Scan", "-ScanType", "3", "-DisableRemediation", "-File"],
+                )
+            }
+        }
+    }
+}
+
+/// Rules applied to every attachment download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadPolicy {
+    /// None allows any size
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+    /// Extensions (without the dot) that are never saved
+    #[serde(default)]
+    pub blocked_extensions: Vec<String>,
+    /// Scanner run on each file before it is kept (see `set_attachment_scanner`)
+    #[serde(default)]
+    pub scanner: Option<Scanner>,
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        Self {
+            max_size_mb: Some(DEFAULT_MAX_SIZE_MB),
+            blocked_extensions: DEFAULT_BLOCKED_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+            scanner: None,
+        }
+    }
+}
+
+/// Why a download was refused or failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadError {
+    /// Larger than the policy allows
+    TooLarge { size_bytes: u64, max_bytes: u64 },
+    /// The file type is blocked
+    BlockedExtension { extension: String },
+    /// The scanner flagged the file
+    ScanRejected {
+        exit_code: Option<i32>,
+        output: String,
+    },
+    /// The scanner could not run or timed out; the file was not kept
+    ScanFailed { message: String },
+    /// Anything else (network, disk, ...)
+    Failed { message: String },
+}
+
+impl From<String> for DownloadError {
+    fn from(message: String) -> Self {
+        DownloadError::Failed { message }
+    }
+}
+
+impl DownloadPolicy {
+    fn max_bytes(&self) -> Option<u64> {
+        self.max_size_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Refuse a file by name and size
+    fn check(&self, filename: &str, size_bytes: u64) -> Result<(), DownloadError> {
+        if let Some(extension) = extension(filename) {
+            if self
+                .blocked_extensions
+                .iter()
+                .any(|b| b.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+            {
+                return Err(DownloadError::BlockedExtension { extension });
+            }
+        }
+        match self.max_bytes() {
+            Some(max_bytes) if size_bytes > max_bytes => Err(DownloadError::TooLarge {
+                size_bytes,
+                max_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn extension(filename: &str) -> Option<String> {
+    let (_, extension) = filename.trim().trim_end_matches('.').rsplit_once('.')?;
+    Some(extension.to_lowercase())
+}
+
+fn load_policy(app: &AppHandle) -> Result<DownloadPolicy, String> {
+    let store = storage::fs::settings_store(app, ATTACHMENTS_STORE_FILE)
+        .map_err(|e| format!("Failed to access attachments store: {}", e))?;
+
+    let mut policy: DownloadPolicy = store
+        .get(POLICY_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    policy.scanner = match store.get(SCANNER_KEY) {
+        Some(value) => serde_json::from_value(value).ok().flatten(),
+        None => std::env::var(SCANNER_ENV)
+            .ok()
+            .and_then(|name| Scanner::parse(&name)),
+    };
+    Ok(policy)
+}
+
+// ============================================================================
+// Download
+// ============================================================================
+
+/// A saved attachment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedAttachment {
+    pub path: String,
+    pub size_bytes: u64,
+    /// The scanner ran and accepted the file
+    pub scanned: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachmentBody {
+    data: String,
+}
+
+/// File name without directories or control characters
+fn safe_filename(filename: &str) -> String {
+    let name: String = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| {
+            if matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `dir/name`, or `dir/name (n).ext` when taken
+fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let candidate = dir.join(filename);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (filename, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(candidate)
+}
+
+/// Run the policy's scanner on `path`
+async fn scan(policy: &DownloadPolicy, path: &Path) -> Result<bool, DownloadError> {
+    let Some(scanner) = policy.scanner else {
+        return Ok(false);
+    };
+    let (program, args) = scanner.command();
+    let command = program.display();
+    let run = tokio::process::Command::new(&program)
+        .args(args)
+        .arg(path)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(SCAN_TIMEOUT, run)
+        .await
+        .map_err(|_| DownloadError::ScanFailed {
+            message: format!("{} timed out", command),
+        })?
+        .map_err(|e| DownloadError::ScanFailed {
+            message: format!("Failed to run {}: {}", command, e),
+        })?;
+
+    if output.status.success() {
+        return Ok(true);
+    }
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Err(DownloadError::ScanRejected {
+        exit_code: output.status.code(),
+        output: text.trim().chars().take(SCAN_OUTPUT_LIMIT).collect(),
+    })
+}
+
+/// Fetch an attachment's bytes
+async fn fetch_attachment(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    mailbox: &Mailbox,
+    message_id: &str,
+    attachment_id: &str,
+) -> Result<Vec<u8>, String> {
+    let url = format!(
+        "{}/{}/messages/{}/attachments/{}",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        message_id,
+        attachment_id
+    );
+    let body: AttachmentBody = client
+        .get_with(&url, token_store, MAX_DOWNLOAD_RESPONSE_BYTES, |body| {
+            serde_json::from_slice(body)
+        })
+        .await?;
+    // Gmail pads its base64url data
+    URL_SAFE_NO_PAD
+        .decode(body.data.trim_end_matches('='))
+        .map_err(|e| format!("Failed to decode attachment: {}", e))
+}
+
+/// Download an attachment to the Downloads folder, enforcing the download policy
+///
+/// `size_bytes` is the size listed for the attachment, checked before fetching.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_attachment(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    message_id: String,
+    attachment_id: String,
+    filename: String,
+    size_bytes: Option<u64>,
+    mailbox: Option<String>,
+) -> Result<SavedAttachment, DownloadError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let policy = load_policy(&app)?;
+    let filename = safe_filename(&filename);
+    policy.check(&filename, size_bytes.unwrap_or(0))?;
+
+    let mailbox: Mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let bytes =
+        fetch_attachment(&token_store, &client, &mailbox, &message_id, &attachment_id).await?;
+    let size_bytes = bytes.len() as u64;
+    policy.check(&filename, size_bytes)?;
+
+    let dir = app
+        .path()
+        .download_dir()
+        .map_err(|e| format!("Failed to find the Downloads folder: {}", e))?;
+    let target = unique_path(&dir, &filename);
+    let partial = PathBuf::from(format!("{}{}", target.display(), PARTIAL_SUFFIX));
+    tokio::fs::write(&partial, &bytes)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+
+    let scanned = match scan(&policy, &partial).await {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&partial, &target)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+
+    Ok(SavedAttachment {
+        path: target.display().to_string(),
+        size_bytes,
+        scanned,
+    })
+}
+
+// ============================================================================
+// Text Extraction
+// ============================================================================
+
+/// Largest attachment whose text is extracted
+const EXTRACT_MAX_BYTES: usize = 10 * 1024 * 1024;
+/// Characters of extracted text kept as the snippet
+const EXTRACT_SNIPPET_LEN: usize = 300;
+
+/// A deadline found in an attachment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentDeadline {
+    /// YYYY-MM-DD
+    pub date: String,
+    /// The words it was read from ("due friday")
+    pub text: String,
+}
+
+/// Task suggested by an attachment's deadline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuggestedTask {
+    pub title: String,
+    /// YYYY-MM-DD
+    pub due_date: String,
+}
+
+/// Text of an attachment and what the email processing made of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentText {
+    pub filename: String,
+    /// Full extracted text (pass as `EmailInput::attachment_text`)
+    pub text: String,
+    pub snippet: String,
+    pub has_urgent_keywords: bool,
+    pub deadline: Option<AttachmentDeadline>,
+    /// Whether the thread should rank as urgent (see `processing::attachment_is_urgent`)
+    pub raises_priority: bool,
+    pub suggested_task: Option<SuggestedTask>,
+}
+
+#[cfg(feature = "attachment-text")]
+fn pdf_text(bytes: &[u8]) -> Result<String, String> {
+    pdf_extract::extract_text_from_mem(bytes).map_err(|e| format!("Failed to read PDF text: {}", e))
+}
+
+#[cfg(not(feature = "attachment-text"))]
+fn pdf_text(_bytes: &[u8]) -> Result<String, String> {
+    Err("PDF text extraction is not enabled in this build".to_string())
+}
+
+/// Run extracted text through the email processing
+fn analyze_text(filename: &str, text: String, now: NaiveDateTime) -> AttachmentText {
+    let deadline = natural_date::due_date(&text, now).map(|due| AttachmentDeadline {
+        date: due.start.date().format("%Y-%m-%d").to_string(),
+        text: due.text,
+    });
+    let suggested_task = deadline.as_ref().map(|deadline| {
+        let action = if text.to_lowercase().contains("invoice") {
+            "Pay"
+        } else {
+            "Review"
+        };
+        SuggestedTask {
+            title: format!("{} {}", action, filename),
+            due_date: deadline.date.clone(),
+        }
+    });
+
+    AttachmentText {
+        filename: filename.to_string(),
+        snippet: processing::clean_snippet(text.clone(), Some(EXTRACT_SNIPPET_LEN)),
+        has_urgent_keywords: processing::has_urgent_keywords(text.clone()),
+        raises_priority: processing::attachment_is_urgent(&text),
+        deadline,
+        suggested_task,
+        text,
+    }
+}
+
+/// Extract the text of a PDF attachment and look for urgency and deadlines
+///
+/// Needs the `attachment-text` feature; other builds return an error.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn extract_attachment_text(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    message_id: String,
+    attachment_id: String,
+    filename: String,
+    mailbox: Option<String>,
+) -> Result<AttachmentText, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if extension(&filename).as_deref() != Some("pdf") {
+        return Err(format!(
+            "Text extraction only supports PDF files: {}",
+            filename
+        ));
+    }
+
+    let mailbox: Mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let bytes =
+        fetch_attachment(&token_store, &client, &mailbox, &message_id, &attachment_id).await?;
+    if bytes.len() > EXTRACT_MAX_BYTES {
+        return Err(format!(
+            "{} is too large to extract ({} MB)",
+            filename,
+            bytes.len() / (1024 * 1024)
+        ));
+    }
+    // PDF parsing is CPU-bound
+    let text = tokio::task::spawn_blocking(move || pdf_text(&bytes))
+        .await
+        .map_err(|e| format!("Extraction task failed: {}", e))??;
+
+    Ok(analyze_text(&filename, text, Local::now().naive_local()))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the attachment download policy
+#[tauri::command]
+pub async fn get_download_policy(app: AppHandle) -> Result<DownloadPolicy, String> {
+    crate::perf::trace_command!();
+    load_policy(&app)
+}
+
+/// Save the attachment download policy
+///
+/// `policy.scanner` is ignored; see `set_attachment_scanner`.
+#[tauri::command]
+pub async fn set_download_policy(app: AppHandle, policy: DownloadPolicy) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let policy = DownloadPolicy {
+        scanner: None,
+        ..policy
+    };
+    let store = storage::fs::settings_store(&app, ATTACHMENTS_STORE_FILE)
+        .map_err(|e| format!("Failed to access attachments store: {}", e))?;
+    store.set(POLICY_KEY, serde_json::json!(policy));
+    storage::fs::save_store(&app, ATTACHMENTS_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save attachments store: {}", e))
+}
+
+/// Choose the scanner run on downloads (None turns scanning off)
+///
+/// Always confirmed with the OS prompt, as it decides what program runs.
+#[tauri::command]
+pub async fn set_attachment_scanner(
+    app: AppHandle,
+    scanner: Option<Scanner>,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    os_gate::require(SensitiveAction::ChangeScanner).await?;
+    let store = storage::fs::settings_store(&app, ATTACHMENTS_STORE_FILE)
+        .map_err(|e| format!("Failed to access attachments store: {}", e))?;
+    store.set(SCANNER_KEY, serde_json::json!(scanner));
+    storage::fs::save_store(&app, ATTACHMENTS_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save attachments store: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_check() {
+        let policy = DownloadPolicy::default();
+        assert_eq!(policy.check("report.pdf", 1024), Ok(()));
+        assert_eq!(
+            policy.check("invoice.pdf.EXE", 1024),
+            Err(DownloadError::BlockedExtension {
+                extension: "exe".to_string()
+            })
+        );
+        assert_eq!(
+            policy.check("video.mov", 30 * 1024 * 1024),
+            Err(DownloadError::TooLarge {
+                size_bytes: 30 * 1024 * 1024,
+                max_bytes: 25 * 1024 * 1024,
+            })
+        );
+        let unlimited = DownloadPolicy {
+            max_size_mb: None,
+            blocked_extensions: vec![".zip".to_string()],
+            ..DownloadPolicy::default()
+        };
+        assert_eq!(unlimited.check("video.mov", u64::MAX), Ok(()));
+        assert!(unlimited.check("archive.zip", 1).is_err());
+        assert_eq!(unlimited.check("README", 1), Ok(()));
+        let huge = DownloadPolicy {
+            max_size_mb: Some(u64::MAX),
+            ..DownloadPolicy::default()
+        };
+        assert_eq!(huge.max_bytes(), Some(u64::MAX));
+        assert_eq!(huge.check("video.mov", u64::MAX), Ok(()));
+
+        // Only known scanners, which run with their own arguments
+        assert_eq!(Scanner::parse(" ClamScan "), Some(Scanner::Clamscan));
+        assert_eq!(Scanner::parse("/bin/sh"), None);
+        let (program, args) = Scanner::Clamdscan.command();
+        assert_eq!(program, PathBuf::from("clamdscan"));
+        assert_eq!(args, ["--no-summary", "--fdpass"]);
+
+        let json = serde_json::to_value(DownloadError::BlockedExtension {
+            extension: "exe".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["kind"], "blocked_extension");
+    }
+
+    #[test]
+    fn test_safe_filename_and_unique_path() {
+        assert_eq!(safe_filename("../../etc/passwd"), "passwd");
+        assert_eq!(
+            safe_filename("C:\\Users\\a\\q3:plan?.xlsx"),
+            "q3_plan_.xlsx"
+        );
+        assert_eq!(safe_filename(".hidden"), "hidden");
+        assert_eq!(safe_filename("\u{0}"), "attachment");
+
+        let dir = std::env::temp_dir().join(format!("rainyday-downloads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a.pdf"));
+        std::fs::write(dir.join("a.pdf"), b"x").unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a (1).pdf"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_attachment_text() {
+        // Wednesday
+        let now = chrono::NaiveDate::from_ymd_opt(2026, 1, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let text = "ACME Corp\n\nINVOICE #1042\n\nTotal: $1,200.00\nPayment due Friday.";
+        let result = analyze_text("invoice-1042.pdf", text.to_string(), now);
+        assert_eq!(
+            result.deadline.map(|d| d.date),
+            Some("2026-01-16".to_string())
+        );
+        assert_eq!(
+            result.suggested_task,
+            Some(SuggestedTask {
+                title: "Pay invoice-1042.pdf".to_string(),
+                due_date: "2026-01-16".to_string(),
+            })
+        );
+        assert!(result.snippet.starts_with("ACME Corp INVOICE #1042"));
+
+        let result = analyze_text("notes.pdf", "Meeting notes".to_string(), now);
+        assert!(result.deadline.is_none() && result.suggested_task.is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! Per-feature permissions
+//!
+//! Sign-in only asks for the read scopes in `SCOPES`. Features that write
+//! (sending mail, changing threads, writing to the calendar) need one more
+//! scope each, asked for when the user turns the feature on:
+//! `request_feature_access` explains the feature and returns a consent URL
+//! for just its missing scopes (`include_granted_scopes` keeps the earlier
+//! grants), finished with `wait_for_oauth_callback` like a sign-in.
+//!
+//! The commands behind each feature check `require` first and fail with
+//! `MISSING_PERMISSION_ERROR` until the scope is granted.
+
+use super::{AuthState, TokenStore};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+/// Prefix of the error returned by features whose scope isn't granted
+pub const MISSING_PERMISSION_ERROR: &str = "Missing permission";
+
+/// A feature that needs more than the sign-in scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Sending mail
+    Send,
+    /// Archiving, labeling and trashing threads
+    Modify,
+    /// Creating events and answering invitations
+    CalendarWrite,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [Feature::Send, Feature::Modify, Feature::CalendarWrite];
+
+    /// Name used in `MISSING_PERMISSION_ERROR`, as serialized
+    fn name(self) -> &'static str {
+        match self {
+            Feature::Send => "send",
+            Feature::Modify => "modify",
+            Feature::CalendarWrite => "calendar_write",
+        }
+    }
+
+    /// Scopes the feature needs
+    pub fn scopes(self) -> &'static [&'static str] {
+        match self {
+            Feature::Send => &["https://www.googleapis.com/auth/gmail.send"],
+            Feature::Modify => &["https://www.googleapis.com/auth/gmail.modify"],
+            Feature::CalendarWrite => &["https://www.googleapis.com/auth/calendar.events"],
+        }
+    }
+
+    /// Why the feature needs them, shown before the consent screen
+    fn explanation(self) -> &'static str {
+        match self {
+            Feature::Send => {
+                "Sending lets Rainy Day send the emails you write. It can't read or delete mail with this permission."
+            }
+            Feature::Modify => {
+                "Organizing lets Rainy Day archive, label and trash threads for you. It never deletes mail permanently."
+            }
+            Feature::CalendarWrite => {
+                "Calendar editing lets Rainy Day add events and answer invitations. It can't change your calendar settings or sharing."
+            }
+        }
+    }
+}
+
+/// Scopes of `feature` missing from `granted`
+///
+/// Sessions that didn't record their scopes signed in with just `SCOPES`.
+fn missing_scopes(feature: Feature, granted: &[String]) -> Vec<String> {
+    let has = |scope: &str| {
+        if granted.is_empty() {
+            super::SCOPES.contains(&scope)
+        } else {
+            granted.iter().any(|g| g == scope)
+        }
+    };
+    feature
+        .scopes()
+        .iter()
+        .filter(|scope| !has(scope))
+        .map(|scope| scope.to_string())
+        .collect()
+}
+
+/// Fail with `MISSING_PERMISSION_ERROR` unless `feature`'s scopes are granted
+pub async fn require(token_store: &TokenStore, feature: Feature) -> Result<(), String> {
+    let granted = token_store.granted_scopes().await?;
+    if missing_scopes(feature, &granted).is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", MISSING_PERMISSION_ERROR, feature.name()))
+    }
+}
+
+/// Whether a feature can be used, and what it would take
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureAccess {
+    pub feature: Feature,
+    pub granted: bool,
+    pub scopes: Vec<String>,
+    pub missing_scopes: Vec<String>,
+    pub explanation: String,
+}
+
+fn feature_access(feature: Feature, granted: &[String]) -> FeatureAccess {
+    let missing = missing_scopes(feature, granted);
+    FeatureAccess {
+        feature,
+        granted: missing.is_empty(),
+        scopes: feature.scopes().iter().map(|s| s.to_string()).collect(),
+        missing_scopes: missing,
+        explanation: feature.explanation().to_string(),
+    }
+}
+
+/// Consent step for turning a feature on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureConsent {
+    pub access: FeatureAccess,
+    /// Google consent URL for the missing scopes; `None` when already granted
+    pub auth_url: Option<String>,
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the scopes `feature` still needs
+#[tauri::command]
+pub async fn get_missing_scopes(
+    token_store: State<'_, TokenStore>,
+    feature: Feature,
+) -> Result<Vec<String>, String> {
+    crate::perf::trace_command!();
+    Ok(missing_scopes(
+        feature,
+        &token_store.granted_scopes().await?,
+    ))
+}
+
+/// Get the permission state of every feature
+#[tauri::command]
+pub async fn get_feature_access(
+    token_store: State<'_, TokenStore>,
+) -> Result<Vec<FeatureAccess>, String> {
+    crate::perf::trace_command!();
+    let granted = token_store.granted_scopes().await?;
+    Ok(Feature::ALL
+        .iter()
+        .map(|feature| feature_access(*feature, &granted))
+        .collect())
+}
+
+/// Start consent for `feature`'s missing scopes
+///
+/// Open `auth_url` and finish with `wait_for_oauth_callback`.
+#[tauri::command]
+pub async fn request_feature_access(
+    app: AppHandle,
+    state: State<'_, AuthState>,
+    token_store: State<'_, TokenStore>,
+    feature: Feature,
+) -> Result<FeatureConsent, String> {
+    crate::perf::trace_command!();
+    let access = feature_access(feature, &token_store.granted_scopes().await?);
+    if access.granted {
+        return Ok(FeatureConsent {
+            access,
+            auth_url: None,
+        });
+    }
+
+    let email = token_store.account_context().await?.email().to_string();
+    let scopes: Vec<&str> = access.missing_scopes.iter().map(String::as_str).collect();
+    let auth_url = super::authorize(
+        &app,
+        &state,
+        &scopes,
+        vec![
+            ("include_granted_scopes", "true".to_string()),
+            ("login_hint", email),
+        ],
+    )
+    .await?;
+    Ok(FeatureConsent {
+        access,
+        auth_url: Some(auth_url),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_scopes() {
+        // Sessions that didn't record their scopes only have the read scopes
+        for feature in Feature::ALL {
+            assert_eq!(missing_scopes(feature, &[]), feature.scopes());
+        }
+
+        let granted = vec![
+            "openid".to_string(),
+            "https://www.googleapis.com/auth/calendar.events".to_string(),
+        ];
+        assert!(missing_scopes(Feature::CalendarWrite, &granted).is_empty());
+        assert_eq!(missing_scopes(Feature::Send, &granted).len(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Device authorization grant (sign-in without a local browser)
+//!
+//! For machines without a browser, or when the app runs over SSH:
+//! `start_device_auth` asks Google for a user code and shows where to enter
+//! it from any other device, then `poll_device_auth` is called every
+//! `interval` seconds until the user approves. The session ends up in the same
+//! `TokenStore` as a browser sign-in.
+//!
+//! Google only grants a limited set of scopes to device clients; a client
+//! whose scopes are refused gets a clear error instead of a session.
+
+use super::{complete_sign_in, AuthState, AuthStatus, GoogleTokenResponse, TokenStore, SCOPES};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// A device sign-in waiting for the user's approval
+#[derive(Debug, Clone)]
+pub struct PendingDeviceAuth {
+    device_code: String,
+    /// Seconds between polls
+    interval: u64,
+    /// Unix seconds
+    expires_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    expires_in: i64,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// What to show the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAuthPrompt {
+    pub user_code: String,
+    pub verification_url: String,
+    /// Seconds until the code expires
+    pub expires_in: i64,
+    /// Seconds to wait between `poll_device_auth` calls
+    pub interval: u64,
+}
+
+/// Result of one poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DeviceAuthPoll {
+    /// Not approved yet; poll again after `interval` seconds
+    Pending {
+        interval: u64,
+    },
+    Complete {
+        status: AuthStatus,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Readable message of an OAuth error body
+fn oauth_error_message(body: &str) -> String {
+    match serde_json::from_str::<OAuthError>(body) {
+        Ok(e) if e.error == "invalid_scope" => {
+            "Google doesn't allow this app's scopes with device sign-in; sign in with a browser"
+                .to_string()
+        }
+        Ok(e) => e.error_description.unwrap_or(e.error),
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Start a device sign-in and get the code to enter on another device
+#[tauri::command]
+pub async fn start_device_auth(state: State<'_, AuthState>) -> Result<DeviceAuthPrompt, String> {
+    crate::perf::trace_command!();
+    let scope = SCOPES.join(" ");
+    let response = http_client()?
+        .post(DEVICE_CODE_URL)
+        .form(&[("client_id", state.client_id.as_str()), ("scope", &scope)])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request device code: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Device sign-in failed: {}",
+            oauth_error_message(&body)
+        ));
+    }
+    let code: DeviceCodeResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse device code response: {}", e))?;
+
+    *state.device.lock().await = Some(PendingDeviceAuth {
+        device_code: code.device_code,
+        interval: code.interval,
+        expires_at: chrono::Utc::now().timestamp() + code.expires_in,
+    });
+
+    Ok(DeviceAuthPrompt {
+        user_code: code.user_code,
+        verification_url: code.verification_url,
+        expires_in: code.expires_in,
+        interval: code.interval,
+    })
+}
+
+/// Check once whether the user approved the device sign-in
+#[tauri::command]
+pub async fn poll_device_auth(
+    state: State<'_, AuthState>,
+    token_store: State<'_, TokenStore>,
+) -> Result<DeviceAuthPoll, String> {
+    crate::perf::trace_command!();
+    let mut device = state.device.lock().await;
+    let pending = device
+        .as_mut()
+        .ok_or("No device sign-in in progress. Call start_device_auth first.")?;
+    if chrono::Utc::now().timestamp() >= pending.expires_at {
+        *device = None;
+        return Err("The device code expired; start again".to_string());
+    }
+
+    let mut form = vec![
+        ("client_id", state.client_id.as_str()),
+        ("device_code", pending.device_code.as_str()),
+        ("grant_type", DEVICE_GRANT_TYPE),
+    ];
+    if !state.client_secret.is_empty() {
+        form.push(("client_secret", state.client_secret.as_str()));
+    }
+    let response = http_client()?
+        .post(super::GOOGLE_TOKEN_URL)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to poll device sign-in: {}", e))?;
+
+    if response.status().is_success() {
+        let tokens: GoogleTokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse token response: {}", e))?;
+        *device = None;
+        drop(device);
+        let status = complete_sign_in(&token_store, tokens).await?;
+        return Ok(DeviceAuthPoll::Complete { status });
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let error = serde_json::from_str::<OAuthError>(&body)
+        .map(|e| e.error)
+        .unwrap_or_default();
+    match error.as_str() {
+        "authorization_pending" => Ok(DeviceAuthPoll::Pending {
+            interval: pending.interval,
+        }),
+        // Google asks to poll less often
+        "slow_down" => {
+            pending.interval += 5;
+            Ok(DeviceAuthPoll::Pending {
+                interval: pending.interval,
+            })
+        }
+        "access_denied" => {
+            *device = None;
+            Err("Sign-in was denied".to_string())
+        }
+        _ => {
+            *device = None;
+            Err(format!(
+                "Device sign-in failed: {}",
+                oauth_error_message(&body)
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oauth_error_message() {
+        assert_eq!(
+            oauth_error_message(r#"{"error": "expired_token", "error_description": "Expired"}"#),
+            "Expired"
+        );
+        assert!(oauth_error_message(r#"{"error": "invalid_scope"}"#).contains("browser"));
+        assert_eq!(oauth_error_message("Bad Gateway"), "Bad Gateway");
+    }
+}
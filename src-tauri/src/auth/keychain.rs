@@ -4,11 +4,86 @@
 //! - macOS: Keychain (AES-256-GCM encrypted)
 //! - Windows: Credential Manager
 //! - Linux: Secret Service (GNOME Keyring, KWallet)
+//!
+//! Entries are namespaced per profile so installs with different data dirs
+//! don't overwrite each other's secrets: the service name is suffixed with
+//! `RAINY_DAY_PROFILE` when set, else with a hash of the app data dir.
+//! Entries written before profiles are moved to the first profile that reads
+//! them.
 
 use keyring::Entry;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Service name of keychain entries from before profiles
+const LEGACY_SERVICE_NAME: &str = "com.enosislabs.rainyday";
+/// Environment variable naming the app profile
+const PROFILE_ENV: &str = "RAINY_DAY_PROFILE";
+
+/// Service name of this profile's entries, set by `use_profile`
+static SERVICE_NAME: OnceLock<String> = OnceLock::new();
+
+/// Service name for the profile `profile`, or else the install using `app_data_dir`
+fn profile_service_name(profile: Option<&str>, app_data_dir: &Path) -> String {
+    let profile: Option<String> = profile
+        .map(|p| {
+            p.chars()
+                .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+                .collect()
+        })
+        .filter(|p: &String| !p.is_empty());
+    let suffix = profile.unwrap_or_else(|| {
+        let digest = Sha256::digest(app_data_dir.to_string_lossy().as_bytes());
+        digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+    });
+    format!("{}.{}", LEGACY_SERVICE_NAME, suffix)
+}
+
+/// Keep this install's entries apart from other profiles'
+///
+/// Called once at startup, before the keychain is used.
+pub fn use_profile(app_data_dir: &Path) {
+    let profile = std::env::var(PROFILE_ENV).ok();
+    let name = profile_service_name(profile.as_deref(), app_data_dir);
+    println!("Using keychain service {}", name);
+    let _ = SERVICE_NAME.set(name);
+}
+
+fn service_name() -> &'static str {
+    SERVICE_NAME
+        .get()
+        .map(String::as_str)
+        .unwrap_or(LEGACY_SERVICE_NAME)
+}
+
+fn entry(key: &str) -> Result<Entry, String> {
+    Entry::new(service_name(), key).map_err(|e| format!("Keychain entry error: {}", e))
+}
+
+/// Read `entry`, moving a value stored before profiles into it
+///
+/// The first profile to read a legacy entry takes it over.
+fn read(entry: &Entry, key: &str) -> keyring::Result<String> {
+    match entry.get_password() {
+        Err(keyring::Error::NoEntry) if service_name() != LEGACY_SERVICE_NAME => {
+            let legacy = Entry::new(LEGACY_SERVICE_NAME, key)?;
+            let value = legacy.get_password()?;
+            entry.set_password(&value)?;
+            let _ = legacy.delete_credential();
+            Ok(value)
+        }
+        result => result,
+    }
+}
 
-/// Service name for keychain entries
-const SERVICE_NAME: &str = "com.enosislabs.rainyday";
+/// Delete `entry`, and a value stored before profiles so it isn't taken over later
+fn delete(entry: &Entry, key: &str) -> keyring::Result<()> {
+    if service_name() != LEGACY_SERVICE_NAME {
+        let _ = Entry::new(LEGACY_SERVICE_NAME, key).and_then(|legacy| legacy.delete_credential());
+    }
+    entry.delete_credential()
+}
 
 /// Key suffix for refresh tokens
 const REFRESH_TOKEN_KEY: &str = "refresh_token";
@@ -16,8 +91,7 @@ const REFRESH_TOKEN_KEY: &str = "refresh_token";
 /// Store refresh token in the OS keychain
 pub fn store_refresh_token(email: &str, token: &str) -> Result<(), String> {
     let key = format!("{}:{}", email, REFRESH_TOKEN_KEY);
-    let entry =
-        Entry::new(SERVICE_NAME, &key).map_err(|e| format!("Keychain entry error: {}", e))?;
+    let entry = entry(&key)?;
 
     entry
         .set_password(token)
@@ -33,10 +107,9 @@ pub fn store_refresh_token(email: &str, token: &str) -> Result<(), String> {
 /// Retrieve refresh token from the OS keychain
 pub fn get_refresh_token(email: &str) -> Result<Option<String>, String> {
     let key = format!("{}:{}", email, REFRESH_TOKEN_KEY);
-    let entry =
-        Entry::new(SERVICE_NAME, &key).map_err(|e| format!("Keychain entry error: {}", e))?;
+    let entry = entry(&key)?;
 
-    match entry.get_password() {
+    match read(&entry, &key) {
         Ok(token) => {
             println!("Refresh token retrieved from OS keychain for: {}", email);
             Ok(Some(token))
@@ -55,10 +128,9 @@ pub fn get_refresh_token(email: &str) -> Result<Option<String>, String> {
 /// Delete refresh token from the OS keychain
 pub fn delete_refresh_token(email: &str) -> Result<(), String> {
     let key = format!("{}:{}", email, REFRESH_TOKEN_KEY);
-    let entry =
-        Entry::new(SERVICE_NAME, &key).map_err(|e| format!("Keychain entry error: {}", e))?;
+    let entry = entry(&key)?;
 
-    match entry.delete_credential() {
+    match delete(&entry, &key) {
         Ok(()) => {
             println!("Refresh token deleted from OS keychain for: {}", email);
             Ok(())
@@ -74,6 +146,21 @@ pub fn delete_refresh_token(email: &str) -> Result<(), String> {
     }
 }
 
+/// Key used to probe keychain availability
+const PROBE_KEY: &str = "keychain_probe";
+
+/// Check that the OS keychain can be accessed
+///
+/// A missing entry counts as available; only platform errors are reported.
+pub fn probe_keychain() -> Result<(), String> {
+    let entry = entry(PROBE_KEY)?;
+
+    match entry.get_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Keychain unavailable: {}", e)),
+    }
+}
+
 // ============================================================================
 // Backend JWT Token Storage
 // ============================================================================
@@ -82,19 +169,19 @@ pub fn delete_refresh_token(email: &str) -> Result<(), String> {
 const BACKEND_ACCESS_KEY: &str = "backend_access_token";
 /// Key for backend refresh token
 const BACKEND_REFRESH_KEY: &str = "backend_refresh_token";
+/// Key for the secret signing backend pushes
+const BACKEND_PUSH_KEY: &str = "backend_push_secret";
 
 /// Store backend tokens in the OS keychain
 pub fn store_backend_tokens(access_token: &str, refresh_token: &str) -> Result<(), String> {
     // Store access token
-    let access_entry = Entry::new(SERVICE_NAME, BACKEND_ACCESS_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
+    let access_entry = entry(BACKEND_ACCESS_KEY)?;
     access_entry
         .set_password(access_token)
         .map_err(|e| format!("Failed to store backend access token: {}", e))?;
 
     // Store refresh token
-    let refresh_entry = Entry::new(SERVICE_NAME, BACKEND_REFRESH_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
+    let refresh_entry = entry(BACKEND_REFRESH_KEY)?;
     refresh_entry
         .set_password(refresh_token)
         .map_err(|e| format!("Failed to store backend refresh token: {}", e))?;
@@ -105,10 +192,9 @@ pub fn store_backend_tokens(access_token: &str, refresh_token: &str) -> Result<(
 
 /// Retrieve backend access token from the OS keychain
 pub fn get_backend_access_token() -> Result<Option<String>, String> {
-    let entry = Entry::new(SERVICE_NAME, BACKEND_ACCESS_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
+    let entry = entry(BACKEND_ACCESS_KEY)?;
 
-    match entry.get_password() {
+    match read(&entry, BACKEND_ACCESS_KEY) {
         Ok(token) => Ok(Some(token)),
         Err(keyring::Error::NoEntry) => Ok(None),
         Err(e) => Err(format!("Failed to retrieve backend access token: {}", e)),
@@ -117,10 +203,9 @@ pub fn get_backend_access_token() -> Result<Option<String>, String> {
 
 /// Retrieve backend refresh token from the OS keychain
 pub fn get_backend_refresh_token() -> Result<Option<String>, String> {
-    let entry = Entry::new(SERVICE_NAME, BACKEND_REFRESH_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
+    let entry = entry(BACKEND_REFRESH_KEY)?;
 
-    match entry.get_password() {
+    match read(&entry, BACKEND_REFRESH_KEY) {
         Ok(token) => Ok(Some(token)),
         Err(keyring::Error::NoEntry) => Ok(None),
         Err(e) => Err(format!("Failed to retrieve backend refresh token: {}", e)),
@@ -130,23 +215,207 @@ pub fn get_backend_refresh_token() -> Result<Option<String>, String> {
 /// Delete backend tokens from the OS keychain
 pub fn clear_backend_tokens() -> Result<(), String> {
     // Delete access token
-    let access_entry = Entry::new(SERVICE_NAME, BACKEND_ACCESS_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
-    let _ = access_entry.delete_credential(); // Ignore if not exists
+    let access_entry = entry(BACKEND_ACCESS_KEY)?;
+    let _ = delete(&access_entry, BACKEND_ACCESS_KEY); // Ignore if not exists
 
     // Delete refresh token
-    let refresh_entry = Entry::new(SERVICE_NAME, BACKEND_REFRESH_KEY)
-        .map_err(|e| format!("Keychain entry error: {}", e))?;
-    let _ = refresh_entry.delete_credential(); // Ignore if not exists
+    let refresh_entry = entry(BACKEND_REFRESH_KEY)?;
+    let _ = delete(&refresh_entry, BACKEND_REFRESH_KEY); // Ignore if not exists
+
+    // Delete push secret
+    let push_entry = entry(BACKEND_PUSH_KEY)?;
+    let _ = delete(&push_entry, BACKEND_PUSH_KEY); // Ignore if not exists
 
     println!("Backend tokens cleared from OS keychain");
     Ok(())
 }
 
+/// Store the secret the backend signs pushes with in the OS keychain
+pub fn store_backend_push_secret(secret: &str) -> Result<(), String> {
+    let entry = entry(BACKEND_PUSH_KEY)?;
+
+    entry
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store push secret: {}", e))
+}
+
+/// Retrieve the backend push secret from the OS keychain
+pub fn get_backend_push_secret() -> Result<Option<String>, String> {
+    let entry = entry(BACKEND_PUSH_KEY)?;
+
+    match read(&entry, BACKEND_PUSH_KEY) {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to retrieve push secret: {}", e)),
+    }
+}
+
+// ============================================================================
+// Pending Sign-in
+// ============================================================================
+
+/// Key for the PKCE state of a sign-in in progress
+const PENDING_AUTH_KEY: &str = "pending_auth";
+
+/// Store the state of a sign-in in progress (JSON) in the OS keychain
+pub fn store_pending_auth(state: &str) -> Result<(), String> {
+    let entry = entry(PENDING_AUTH_KEY)?;
+    entry
+        .set_password(state)
+        .map_err(|e| format!("Failed to store pending sign-in: {}", e))
+}
+
+/// Retrieve the state of a sign-in in progress from the OS keychain
+pub fn get_pending_auth() -> Result<Option<String>, String> {
+    let entry = entry(PENDING_AUTH_KEY)?;
+
+    match read(&entry, PENDING_AUTH_KEY) {
+        Ok(state) => Ok(Some(state)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to retrieve pending sign-in: {}", e)),
+    }
+}
+
+/// Delete the state of a sign-in in progress from the OS keychain
+pub fn delete_pending_auth() -> Result<(), String> {
+    let entry = entry(PENDING_AUTH_KEY)?;
+
+    match delete(&entry, PENDING_AUTH_KEY) {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete pending sign-in: {}", e)),
+    }
+}
+
+// ============================================================================
+// AI Provider Keys
+// ============================================================================
+
+/// Key prefix for AI provider API keys (one per provider)
+const AI_API_KEY_PREFIX: &str = "ai_api_key";
+
+/// Store an AI provider's API key in the OS keychain
+pub fn store_ai_api_key(provider: &str, api_key: &str) -> Result<(), String> {
+    let key = format!("{}:{}", AI_API_KEY_PREFIX, provider);
+    let entry = entry(&key)?;
+
+    entry
+        .set_password(api_key)
+        .map_err(|e| format!("Failed to store AI API key in keychain: {}", e))
+}
+
+/// Retrieve an AI provider's API key from the OS keychain
+pub fn get_ai_api_key(provider: &str) -> Result<Option<String>, String> {
+    let key = format!("{}:{}", AI_API_KEY_PREFIX, provider);
+    let entry = entry(&key)?;
+
+    match read(&entry, &key) {
+        Ok(api_key) => Ok(Some(api_key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to retrieve AI API key: {}", e)),
+    }
+}
+
+/// Delete an AI provider's API key from the OS keychain
+pub fn delete_ai_api_key(provider: &str) -> Result<(), String> {
+    let key = format!("{}:{}", AI_API_KEY_PREFIX, provider);
+    let entry = entry(&key)?;
+
+    match delete(&entry, &key) {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete AI API key: {}", e)),
+    }
+}
+
+// ============================================================================
+// Routing API Key
+// ============================================================================
+
+/// Key for the Google Maps API key used for travel estimates
+const ROUTING_API_KEY: &str = "routing_api_key";
+
+/// Store the Google Maps API key in the OS keychain
+pub fn store_routing_api_key(api_key: &str) -> Result<(), String> {
+    entry(ROUTING_API_KEY)?
+        .set_password(api_key)
+        .map_err(|e| format!("Failed to store routing API key in keychain: {}", e))
+}
+
+/// Retrieve the Google Maps API key from the OS keychain
+pub fn get_routing_api_key() -> Result<Option<String>, String> {
+    let entry = entry(ROUTING_API_KEY)?;
+
+    match read(&entry, ROUTING_API_KEY) {
+        Ok(api_key) => Ok(Some(api_key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to retrieve routing API key: {}", e)),
+    }
+}
+
+/// Delete the Google Maps API key from the OS keychain
+pub fn delete_routing_api_key() -> Result<(), String> {
+    let entry = entry(ROUTING_API_KEY)?;
+
+    match delete(&entry, ROUTING_API_KEY) {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete routing API key: {}", e)),
+    }
+}
+
+// ============================================================================
+// Record Encryption Secrets
+// ============================================================================
+
+/// Key prefix for per-account record encryption secrets
+const RECORD_SECRET_PREFIX: &str = "record_secret";
+
+/// Store an account's record encryption secret (base64) in the OS keychain
+pub fn store_record_secret(account_id: &str, secret: &str) -> Result<(), String> {
+    let key = format!("{}:{}", RECORD_SECRET_PREFIX, account_id);
+    let entry = entry(&key)?;
+
+    entry
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store encryption secret in keychain: {}", e))
+}
+
+/// Retrieve an account's record encryption secret from the OS keychain
+pub fn get_record_secret(account_id: &str) -> Result<Option<String>, String> {
+    let key = format!("{}:{}", RECORD_SECRET_PREFIX, account_id);
+    let entry = entry(&key)?;
+
+    match read(&entry, &key) {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to retrieve encryption secret: {}", e)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_profile_service_name() {
+        let work = profile_service_name(None, Path::new("/home/me/.rainy-work"));
+        let personal = profile_service_name(None, Path::new("/home/me/.rainy-personal"));
+        assert_ne!(work, personal);
+        assert!(work.starts_with("com.enosislabs.rainyday."));
+        assert_eq!(
+            work,
+            profile_service_name(None, Path::new("/home/me/.rainy-work"))
+        );
+
+        assert_eq!(
+            profile_service_name(Some("work/../x"), Path::new("/any")),
+            "com.enosislabs.rainyday.workx"
+        );
+        // A name with nothing usable falls back to the data dir
+        assert_eq!(
+            profile_service_name(Some("//"), Path::new("/home/me/.rainy-work")),
+            work
+        );
+    }
+
     // Note: These tests interact with the real OS keychain
     // Run with caution in CI environments
 
@@ -0,0 +1,77 @@
+//! Mobile OAuth redirect handling (iOS/Android)
+//!
+//! Phones can't run the loopback callback server, so Google redirects to the
+//! custom scheme of the mobile OAuth client instead
+//! (`com.googleusercontent.apps.<client id prefix>:/oauth2redirect`). The OS
+//! hands that URL to the app through the deep-link plugin, and
+//! `wait_for_oauth_callback` picks it up here. PKCE works exactly as on
+//! desktop.
+//!
+//! The scheme must be registered under `plugins.deep-link.mobile` in
+//! `tauri.conf.json` for the client ID used on phones.
+
+use super::{extract_param, AuthState, MOBILE_REDIRECT_PATH};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tokio::sync::mpsc;
+
+/// How long to wait for the user to finish signing in
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Forward OAuth redirects opened through the deep-link plugin to a waiting flow
+pub fn register_redirect_handler(app: &AppHandle) {
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            if url.as_str().contains(MOBILE_REDIRECT_PATH) {
+                handle
+                    .state::<AuthState>()
+                    .deliver_redirect(url.to_string());
+            }
+        }
+    });
+}
+
+/// Wait for the redirect URL carrying `expected_state` and extract
+/// `(code, state)` from it
+///
+/// Redirects with another or no state are ignored, so a stray deep link can't
+/// cancel or answer the flow.
+pub async fn wait_for_redirect(
+    state: &AuthState,
+    expected_state: &str,
+) -> Result<(String, String), String> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    *state
+        .redirect_tx
+        .lock()
+        .map_err(|_| "Auth state lock poisoned".to_string())? = Some(tx);
+
+    let result = tokio::time::timeout(REDIRECT_TIMEOUT, async {
+        while let Some(url) = rx.recv().await {
+            if extract_param(&url, "state").as_deref() != Some(expected_state) {
+                eprintln!("Ignoring OAuth redirect with a foreign state");
+                continue;
+            }
+            if let Some(error) = extract_param(&url, "error") {
+                return Err(format!("Authorization denied: {}", error));
+            }
+            let code = extract_param(&url, "code").ok_or("No authorization code in redirect")?;
+            return Ok((code, expected_state.to_string()));
+        }
+        Err("Sign-in was cancelled".to_string())
+    })
+    .await
+    .map_err(|_| "Timed out waiting for the sign-in redirect".to_string())
+    .and_then(|r| r);
+
+    // Leave the sender of a flow started since then in place
+    drop(rx);
+    if let Ok(mut tx) = state.redirect_tx.lock() {
+        if tx.as_ref().is_some_and(|tx| tx.is_closed()) {
+            *tx = None;
+        }
+    }
+    result
+}
@@ -1,33 +1,77 @@
 //! Authentication module for Google OAuth2 with PKCE
 //!
 //! For desktop apps, Google requires using a loopback redirect (http://127.0.0.1:port)
-//! instead of custom URI schemes. This module:
-//! - Starts a local HTTP server to receive the OAuth callback
+//! instead of custom URI schemes. On iOS/Android, where a loopback server can't
+//! run, the redirect goes to the mobile client's custom scheme and arrives
+//! through the deep-link plugin (see `mobile`). This module:
+//! - Receives the OAuth callback (loopback server or deep link)
 //! - Generates the authorization URL with PKCE
 //! - Exchanges the code for tokens
 //! - Stores tokens securely in the OS keychain
+//!
+//! Without `GOOGLE_CLIENT_SECRET` the app is a public client: every token
+//! request (code exchange, refresh, device flow) relies on PKCE alone and
+//! omits the secret. The Google OAuth client must then be of a type that
+//! issues no secret.
+//!
+//! Sign-in asks for read access only; writing features request their scopes
+//! when turned on (see `capabilities`).
+//!
+//! The loopback port range is configurable (see `ports`).
+//!
+//! Handing out the backend refresh token can require an OS confirmation
+//! (see `os_gate`).
+//!
+//! Machines without a browser can sign in with the device code flow instead
+//! (see `device`).
+//!
+//! The PKCE state of a sign-in in progress is kept in the keychain too, so a
+//! restart mid-consent doesn't lose it: `resume_pending_auth` restores it at
+//! startup and re-opens the loopback server on the same port. A flow finished
+//! that way is reported with an `auth:resumed` event.
 
+pub mod capabilities;
+pub mod device;
 mod keychain;
+#[cfg(mobile)]
+pub mod mobile;
+pub mod os_gate;
+pub mod ports;
 mod token_store;
 
+use crate::app_lock::AppLockState;
 use oauth2::{
     basic::BasicClient, AuthUrl, ClientId, CsrfToken, PkceCodeChallenge, RedirectUrl, Scope,
     TokenUrl,
 };
 use serde::{Deserialize, Serialize};
+#[cfg(desktop)]
 use std::io::{Read, Write};
+#[cfg(desktop)]
 use std::net::TcpListener;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::Mutex;
 
-pub use token_store::TokenStore;
+pub use keychain::{
+    delete_ai_api_key, delete_refresh_token, delete_routing_api_key, get_ai_api_key,
+    get_backend_access_token as backend_access_token,
+    get_backend_push_secret as backend_push_secret, get_record_secret, get_routing_api_key,
+    probe_keychain, store_ai_api_key, store_record_secret, store_routing_api_key,
+    use_profile as use_keychain_profile,
+};
+pub use token_store::{spawn_token_refresh, TokenStore};
+
+/// Settings store of the auth module (callback ports, OS gate)
+const AUTH_STORE_FILE: &str = "auth.json";
 
 /// Google OAuth2 configuration
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 pub const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 
-/// OAuth scopes for Google APIs (minimal, read-only where possible)
+/// OAuth scopes asked for at sign-in (minimal, read-only where possible)
+///
+/// Features that write ask for their own scopes later (see `capabilities`).
 pub const SCOPES: &[&str] = &[
     "https://www.googleapis.com/auth/gmail.readonly",
     "https://www.googleapis.com/auth/calendar.readonly",
@@ -37,19 +81,82 @@ pub const SCOPES: &[&str] = &[
     "profile",
 ];
 
+/// Event emitted when a sign-in resumed after a restart finishes
+pub const AUTH_RESUMED_EVENT: &str = "auth:resumed";
+
+/// How long a sign-in in progress survives a restart
+const PENDING_AUTH_TTL_SECS: i64 = 15 * 60;
+
 /// Pending OAuth state during authorization flow
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PendingAuth {
     pub pkce_verifier: String,
     pub csrf_token: String,
+    pub redirect_uri: String,
+    /// Loopback port of the callback server (desktop only)
     pub redirect_port: u16,
+    /// Unix seconds the flow started
+    pub started_at: i64,
+}
+
+impl PendingAuth {
+    fn expires_at(&self) -> i64 {
+        self.started_at + PENDING_AUTH_TTL_SECS
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at() <= now
+    }
+}
+
+/// Keep the pending state across restarts (failures only cost resumability)
+fn persist_pending(pending: &PendingAuth) {
+    let result = serde_json::to_string(pending)
+        .map_err(|e| format!("Failed to serialize pending sign-in: {}", e))
+        .and_then(|json| keychain::store_pending_auth(&json));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+fn forget_pending() {
+    if let Err(e) = keychain::delete_pending_auth() {
+        eprintln!("{}", e);
+    }
+}
+
+/// Outcome of a resumed sign-in, sent with `AUTH_RESUMED_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthResumed {
+    pub status: Option<AuthStatus>,
+    pub error: Option<String>,
 }
 
 /// Manages the OAuth2 authorization state
 pub struct AuthState {
     pub pending: Arc<Mutex<Option<PendingAuth>>>,
     pub client_id: String,
+    /// Empty for public clients
     pub client_secret: String,
+    /// Device code sign-in in progress
+    device: Mutex<Option<device::PendingDeviceAuth>>,
+    /// Receiver of the next deep-link OAuth redirect
+    #[cfg(mobile)]
+    redirect_tx: std::sync::Mutex<Option<tokio::sync::mpsc::UnboundedSender<String>>>,
+    /// Sign-in resumed at startup, whose loopback server is already listening
+    #[cfg(desktop)]
+    resumed: std::sync::Mutex<Option<ResumedFlow>>,
+}
+
+/// Result of a resumed sign-in, once its callback has been handled
+#[cfg(desktop)]
+type ResumedResult = Option<Result<AuthStatus, String>>;
+
+/// A resumed sign-in; its background task is the only owner of the port
+#[cfg(desktop)]
+struct ResumedFlow {
+    csrf_token: String,
+    result: tokio::sync::watch::Receiver<ResumedResult>,
 }
 
 impl AuthState {
@@ -58,6 +165,36 @@ impl AuthState {
             pending: Arc::new(Mutex::new(None)),
             client_id,
             client_secret,
+            device: Mutex::new(None),
+            #[cfg(mobile)]
+            redirect_tx: std::sync::Mutex::new(None),
+            #[cfg(desktop)]
+            resumed: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Result channel of the resumed flow with this CSRF token, if any
+    #[cfg(desktop)]
+    fn resumed_result(
+        &self,
+        csrf_token: &str,
+    ) -> Option<tokio::sync::watch::Receiver<ResumedResult>> {
+        let resumed = self.resumed.lock().ok()?;
+        resumed
+            .as_ref()
+            .filter(|flow| flow.csrf_token == csrf_token)
+            .map(|flow| flow.result.clone())
+    }
+
+    /// Hand a deep-link redirect URL to the flow waiting in `wait_for_oauth_callback`
+    #[cfg(mobile)]
+    pub fn deliver_redirect(&self, url: String) {
+        let sender = self.redirect_tx.lock().ok().and_then(|tx| tx.clone());
+        match sender {
+            Some(tx) => {
+                let _ = tx.send(url);
+            }
+            None => eprintln!("Ignoring OAuth redirect with no sign-in in progress"),
         }
     }
 }
@@ -76,31 +213,96 @@ pub struct AuthStatus {
     pub is_authenticated: bool,
     pub user: Option<UserInfo>,
     pub expires_at: Option<i64>,
+    /// Set when Google revoked the session; sign in again as this account
+    #[serde(default)]
+    pub reauth_required: Option<ReauthRequired>,
 }
 
-/// Find an available port for the OAuth callback server
-fn find_available_port() -> Result<u16, String> {
-    // Try ports in the range 8400-8500
-    for port in 8400..8500 {
-        if TcpListener::bind(format!("127.0.0.1:{}", port)).is_ok() {
-            return Ok(port);
-        }
-    }
-    Err("No available port found for OAuth callback".into())
+/// A session Google no longer accepts (refresh token revoked or expired)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReauthRequired {
+    pub email: String,
+    /// Google's error code, e.g. `invalid_grant`
+    pub reason: String,
 }
 
 /// Generates the OAuth2 authorization URL for Google sign-in
 /// Returns the URL to open in the browser
+///
+/// `login_hint` (an email) preselects a known account when re-authenticating;
+/// `select_account` forces Google's account chooser, e.g. to add a second
+/// account while signed in to the first.
 #[tauri::command]
-pub async fn start_google_auth(state: State<'_, AuthState>) -> Result<String, String> {
-    // Find an available port for the callback server
-    let port = find_available_port()?;
-    let redirect_uri = format!("http://127.0.0.1:{}", port);
+pub async fn start_google_auth(
+    app: AppHandle,
+    state: State<'_, AuthState>,
+    login_hint: Option<String>,
+    select_account: Option<bool>,
+) -> Result<String, String> {
+    crate::perf::trace_command!();
+    let mut params = Vec::new();
+    if let Some(hint) = login_hint
+        .as_deref()
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+    {
+        params.push(("login_hint", hint.to_string()));
+    }
+    if select_account == Some(true) {
+        params.push(("prompt", "select_account".to_string()));
+    }
+    authorize(&app, &state, SCOPES, params).await
+}
+
+/// Path Google appends to the reversed client ID scheme of mobile clients
+const MOBILE_REDIRECT_PATH: &str = ":/oauth2redirect";
+/// Suffix of Google OAuth client IDs
+const CLIENT_ID_SUFFIX: &str = ".apps.googleusercontent.com";
+
+/// Redirect URI of the desktop loopback callback server
+#[cfg_attr(mobile, allow(dead_code))]
+fn loopback_redirect_uri(port: u16) -> String {
+    format!("http://127.0.0.1:{}", port)
+}
+
+/// Custom-scheme redirect URI for a mobile (iOS/Android) OAuth client ID
+#[cfg_attr(desktop, allow(dead_code))]
+fn mobile_redirect_uri(client_id: &str) -> Result<String, String> {
+    let prefix = client_id.strip_suffix(CLIENT_ID_SUFFIX).ok_or(
+        "Mobile sign-in requires an iOS/Android OAuth client ID (*.apps.googleusercontent.com)",
+    )?;
+    Ok(format!(
+        "com.googleusercontent.apps.{}{}",
+        prefix, MOBILE_REDIRECT_PATH
+    ))
+}
+
+/// Start an authorization flow for `scopes` and return the URL to open
+///
+/// The flow is finished by `wait_for_oauth_callback`.
+async fn authorize(
+    app: &AppHandle,
+    state: &AuthState,
+    scopes: &[&str],
+    params: Vec<(&'static str, String)>,
+) -> Result<String, String> {
+    // Desktop: loopback callback server on an available port
+    #[cfg(desktop)]
+    let (port, redirect_uri) = {
+        let port = ports::find_available_port(app)?;
+        (port, loopback_redirect_uri(port))
+    };
+    // Mobile: custom-scheme redirect delivered through the deep-link plugin
+    #[cfg(mobile)]
+    let (port, redirect_uri) = {
+        let _ = app;
+        (0, mobile_redirect_uri(&state.client_id)?)
+    };
 
     let client = BasicClient::new(ClientId::new(state.client_id.clone()))
         .set_auth_uri(AuthUrl::new(GOOGLE_AUTH_URL.to_string()).map_err(|e| e.to_string())?)
         .set_token_uri(TokenUrl::new(GOOGLE_TOKEN_URL.to_string()).map_err(|e| e.to_string())?)
-        .set_redirect_uri(RedirectUrl::new(redirect_uri).map_err(|e| e.to_string())?);
+        .set_redirect_uri(RedirectUrl::new(redirect_uri.clone()).map_err(|e| e.to_string())?);
 
     // Generate PKCE challenge (required for public clients)
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
@@ -111,21 +313,28 @@ pub async fn start_google_auth(state: State<'_, AuthState>) -> Result<String, St
         .set_pkce_challenge(pkce_challenge);
 
     // Add scopes
-    for scope in SCOPES {
+    for scope in scopes {
         auth_request = auth_request.add_scope(Scope::new(scope.to_string()));
     }
+    for (name, value) in params {
+        auth_request = auth_request.add_extra_param(name, value);
+    }
 
     let (auth_url, csrf_token) = auth_request.url();
 
     // Store pending auth state (store secrets as strings for simplicity)
-    let mut pending = state.pending.lock().await;
-    *pending = Some(PendingAuth {
+    let flow = PendingAuth {
         pkce_verifier: pkce_verifier.secret().to_string(),
         csrf_token: csrf_token.secret().to_string(),
+        redirect_uri: redirect_uri.clone(),
         redirect_port: port,
-    });
+        started_at: chrono::Utc::now().timestamp(),
+    };
+    persist_pending(&flow);
+    let mut pending = state.pending.lock().await;
+    *pending = Some(flow);
 
-    println!("Generated auth URL for port {}", port);
+    println!("Generated auth URL for {}", redirect_uri);
     Ok(auth_url.to_string())
 }
 
@@ -135,6 +344,9 @@ struct GoogleTokenResponse {
     access_token: String,
     refresh_token: Option<String>,
     expires_in: Option<u64>,
+    /// Granted scopes, space separated
+    #[serde(default)]
+    scope: Option<String>,
     #[allow(dead_code)]
     token_type: String,
 }
@@ -145,6 +357,7 @@ pub async fn wait_for_oauth_callback(
     state: State<'_, AuthState>,
     token_store: State<'_, TokenStore>,
 ) -> Result<AuthStatus, String> {
+    crate::perf::trace_command!();
     // Get the pending auth state
     let pending_guard = state.pending.lock().await;
     let pending = pending_guard
@@ -152,37 +365,79 @@ pub async fn wait_for_oauth_callback(
         .ok_or("No pending OAuth flow. Call start_google_auth first.")?;
 
     let port = pending.redirect_port;
+    let redirect_uri = pending.redirect_uri.clone();
     let expected_state = pending.csrf_token.clone();
     let pkce_verifier = pending.pkce_verifier.clone();
     let client_id = state.client_id.clone();
     let client_secret = state.client_secret.clone();
     drop(pending_guard);
 
-    println!("Starting OAuth callback server on port {}...", port);
+    // A resumed flow's server already listens on the port: wait for its result
+    #[cfg(desktop)]
+    if let Some(mut result) = state.resumed_result(&expected_state) {
+        println!("Waiting for the resumed sign-in on port {}...", port);
+        return match result.wait_for(Option::is_some).await {
+            Ok(result) => result
+                .clone()
+                .unwrap_or_else(|| Err("Sign-in failed".into())),
+            Err(_) => Err("Sign-in was replaced by a newer one".into()),
+        };
+    }
 
-    // Run the blocking TCP server in a separate thread
-    let callback_result = tokio::task::spawn_blocking(move || wait_for_callback_sync(port))
-        .await
-        .map_err(|e| format!("Task join error: {}", e))?
-        .map_err(|e| format!("Callback error: {}", e))?;
+    #[cfg(desktop)]
+    let (code, received_state) = {
+        println!("Starting OAuth callback server on port {}...", port);
 
-    let (code, received_state) = callback_result;
+        // Run the blocking TCP server in a separate thread
+        let expected_state = expected_state.clone();
+        tokio::task::spawn_blocking(move || wait_for_callback_sync(port, &expected_state, None))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+            .map_err(|e| format!("Callback error: {}", e))?
+    };
+    #[cfg(mobile)]
+    let (code, received_state) = {
+        let _ = port;
+        println!("Waiting for OAuth redirect via deep link...");
+        mobile::wait_for_redirect(&state, &expected_state)
+            .await
+            .map_err(|e| format!("Callback error: {}", e))?
+    };
 
     println!("Received OAuth callback with code");
 
+    // Clear pending state
+    let mut pending_guard = state.pending.lock().await;
+    *pending_guard = None;
+    drop(pending_guard);
+    forget_pending();
+
     // Verify CSRF token
     if expected_state != received_state {
         return Err("CSRF token mismatch - possible attack".into());
     }
 
-    // Clear pending state
-    let mut pending_guard = state.pending.lock().await;
-    *pending_guard = None;
-    drop(pending_guard);
+    exchange_code(
+        &token_store,
+        &client_id,
+        &client_secret,
+        &redirect_uri,
+        &pkce_verifier,
+        &code,
+    )
+    .await
+}
 
+/// Exchange an authorization code for tokens and store them
+async fn exchange_code(
+    token_store: &TokenStore,
+    client_id: &str,
+    client_secret: &str,
+    redirect_uri: &str,
+    pkce_verifier: &str,
+    code: &str,
+) -> Result<AuthStatus, String> {
     // Exchange code for tokens using reqwest with timeout
-    let redirect_uri = format!("http://127.0.0.1:{}", port);
-
     let http_client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()
@@ -192,14 +447,17 @@ pub async fn wait_for_oauth_callback(
     println!("  redirect_uri: {}", redirect_uri);
     println!("  client_id: {}...", &client_id[..20.min(client_id.len())]);
 
-    let form_data = [
-        ("client_id", client_id.as_str()),
-        ("client_secret", client_secret.as_str()),
-        ("code", &code),
-        ("code_verifier", &pkce_verifier),
+    let mut form_data = vec![
+        ("client_id", client_id),
+        ("code", code),
+        ("code_verifier", pkce_verifier),
         ("grant_type", "authorization_code"),
-        ("redirect_uri", &redirect_uri),
+        ("redirect_uri", redirect_uri),
     ];
+    // Public clients (iOS/Android, or no secret configured) send none
+    if !client_secret.is_empty() {
+        form_data.push(("client_secret", client_secret));
+    }
 
     let token_response = http_client
         .post(GOOGLE_TOKEN_URL)
@@ -238,7 +496,14 @@ pub async fn wait_for_oauth_callback(
     })?;
 
     println!("Token exchange successful, fetching user info...");
+    complete_sign_in(token_store, tokens).await
+}
 
+/// Store the tokens of a finished sign-in with the account's profile
+async fn complete_sign_in(
+    token_store: &TokenStore,
+    tokens: GoogleTokenResponse,
+) -> Result<AuthStatus, String> {
     // Get user info from Google
     let user_info = fetch_user_info(&tokens.access_token).await?;
 
@@ -253,6 +518,12 @@ pub async fn wait_for_oauth_callback(
         refresh_token: tokens.refresh_token,
         expires_at: expires_at.unwrap_or(0),
         user_info: user_info.clone(),
+        scopes_granted: tokens
+            .scope
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
     };
     token_store.store_tokens(stored).await?;
 
@@ -262,43 +533,283 @@ pub async fn wait_for_oauth_callback(
         is_authenticated: true,
         user: Some(user_info),
         expires_at,
+        reauth_required: None,
     })
 }
 
-/// Synchronous function to wait for OAuth callback (runs in spawn_blocking)
-fn wait_for_callback_sync(port: u16) -> Result<(String, String), String> {
-    // Start a simple HTTP server to receive the callback
-    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
-        .map_err(|e| format!("Failed to start callback server on port {}: {}", port, e))?;
+/// Restore a sign-in interrupted by a restart and finish it in the background
+///
+/// On desktop the loopback server is re-opened on the flow's port until the
+/// flow expires, and a `wait_for_oauth_callback` for the same flow waits for
+/// its result instead of binding the port again; on mobile the restored state
+/// lets `wait_for_oauth_callback` pick up the redirect.
+pub fn resume_pending_auth(app: AppHandle) {
+    let pending = match keychain::get_pending_auth() {
+        Ok(Some(json)) => serde_json::from_str::<PendingAuth>(&json).ok(),
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let now = chrono::Utc::now().timestamp();
+    let Some(pending) = pending.filter(|p| !p.is_expired(now)) else {
+        forget_pending();
+        return;
+    };
 
-    println!("Listening on 127.0.0.1:{}...", port);
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AuthState>();
+        #[cfg(desktop)]
+        let (result_tx, result_rx) = tokio::sync::watch::channel(None);
+        #[cfg(desktop)]
+        if let Ok(mut resumed) = state.resumed.lock() {
+            *resumed = Some(ResumedFlow {
+                csrf_token: pending.csrf_token.clone(),
+                result: result_rx,
+            });
+        }
+        *state.pending.lock().await = Some(pending.clone());
+        println!("Resuming sign-in on {}", pending.redirect_uri);
+
+        #[cfg(desktop)]
+        {
+            let port = pending.redirect_port;
+            let deadline = std::time::Instant::now()
+                + std::time::Duration::from_secs((pending.expires_at() - now) as u64);
+            let expected_state = pending.csrf_token.clone();
+            let callback = tokio::task::spawn_blocking(move || {
+                wait_for_callback_sync(port, &expected_state, Some(deadline))
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))
+            .and_then(|r| r.map_err(|e| format!("Callback error: {}", e)));
+
+            if let Ok(mut resumed) = state.resumed.lock() {
+                if resumed.as_ref().map(|f| &f.csrf_token) == Some(&pending.csrf_token) {
+                    *resumed = None;
+                }
+            }
+
+            // A flow started since then owns the sign-in now
+            let mut pending_guard = state.pending.lock().await;
+            if pending_guard.as_ref().map(|p| &p.csrf_token) != Some(&pending.csrf_token) {
+                return;
+            }
+            *pending_guard = None;
+            drop(pending_guard);
+            forget_pending();
+
+            let result = match callback {
+                Ok((_, received_state)) if received_state != pending.csrf_token => {
+                    Err("CSRF token mismatch - possible attack".to_string())
+                }
+                Ok((code, _)) => {
+                    exchange_code(
+                        &app.state::<TokenStore>(),
+                        &state.client_id,
+                        &state.client_secret,
+                        &pending.redirect_uri,
+                        &pending.pkce_verifier,
+                        &code,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                eprintln!("Resumed sign-in failed: {}", e);
+            }
+            let _ = result_tx.send(Some(result.clone()));
+            let (status, error) = match result {
+                Ok(status) => (Some(status), None),
+                Err(e) => (None, Some(e)),
+            };
+            if let Err(e) = app.emit(AUTH_RESUMED_EVENT, AuthResumed { status, error }) {
+                eprintln!("Failed to emit {}: {}", AUTH_RESUMED_EVENT, e);
+            }
+        }
+    });
+}
+
+/// Largest callback request read from the browser
+#[cfg(desktop)]
+const MAX_CALLBACK_REQUEST: usize = 16 * 1024;
+
+/// What a request to the loopback server carries
+#[cfg(desktop)]
+#[derive(Debug, PartialEq, Eq)]
+enum LoopbackRequest {
+    /// The OAuth redirect with its code and state
+    Code { code: String, state: String },
+    /// The OAuth redirect reporting an error (e.g. `access_denied`)
+    Error(String),
+    /// A redirect whose state is missing or belongs to another flow
+    BadState,
+    /// Anything else, such as the browser's `/favicon.ico`
+    Other,
+}
 
-    // Accept one connection (blocking)
-    let (mut stream, addr) = listener
-        .accept()
-        .map_err(|e| format!("Failed to accept connection: {}", e))?;
+/// Route a request by its request line (`GET /?code=... HTTP/1.1`)
+///
+/// A code or error is only acted on when `state` matches `expected_state`, so
+/// another local page can't end or hijack the sign-in.
+#[cfg(desktop)]
+fn route_loopback_request(request: &str, expected_state: &str) -> LoopbackRequest {
+    let line = request.lines().next().unwrap_or_default();
+    let mut parts = line.split_whitespace();
+    let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+        return LoopbackRequest::Other;
+    };
+    if !target.starts_with("/?") {
+        return LoopbackRequest::Other;
+    }
 
-    println!("Received connection from {}", addr);
+    let code = extract_param(line, "code");
+    let error = extract_param(line, "error");
+    if code.is_none() && error.is_none() {
+        return LoopbackRequest::Other;
+    }
+    let state = extract_param(line, "state");
+    if state.as_deref() != Some(expected_state) {
+        return LoopbackRequest::BadState;
+    }
+    match (error, code) {
+        (Some(error), _) => LoopbackRequest::Error(error),
+        (None, Some(code)) => LoopbackRequest::Code {
+            code,
+            state: expected_state.to_string(),
+        },
+        (None, None) => LoopbackRequest::Other,
+    }
+}
 
-    // Read the request
+/// Wait for the next connection, giving up at `deadline` if any
+#[cfg(desktop)]
+fn accept_connection(
+    listener: &TcpListener,
+    deadline: Option<std::time::Instant>,
+) -> Result<(std::net::TcpStream, std::net::SocketAddr), String> {
+    let Some(deadline) = deadline else {
+        return listener
+            .accept()
+            .map_err(|e| format!("Failed to accept connection: {}", e));
+    };
+    loop {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                stream
+                    .set_nonblocking(false)
+                    .map_err(|e| format!("Failed to configure connection: {}", e))?;
+                return Ok((stream, addr));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if std::time::Instant::now() >= deadline {
+                    return Err("Sign-in expired".to_string());
+                }
+                std::thread::sleep(std::time::Duration::from_millis(250));
+            }
+            Err(e) => return Err(format!("Failed to accept connection: {}", e)),
+        }
+    }
+}
+
+/// Read a request's head (request line and headers)
+#[cfg(desktop)]
+fn read_request_head(stream: &mut std::net::TcpStream) -> Result<String, String> {
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .map_err(|e| format!("Failed to configure connection: {}", e))?;
+
+    let mut request = Vec::new();
     let mut buffer = [0; 4096];
-    let n = stream
-        .read(&mut buffer)
-        .map_err(|e| format!("Failed to read request: {}", e))?;
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        if request.len() >= MAX_CALLBACK_REQUEST {
+            return Err("Callback request too large".to_string());
+        }
+        let n = stream
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+    Ok(String::from_utf8_lossy(&request).into_owned())
+}
 
-    let request = String::from_utf8_lossy(&buffer[..n]);
+/// Send a small HTML page and close the connection
+#[cfg(desktop)]
+fn respond(stream: &mut std::net::TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).ok();
+    stream.flush().ok();
+}
 
-    // Parse the authorization code from the request
-    let code = extract_param(&request, "code").ok_or("No authorization code in callback")?;
-    let received_state =
-        extract_param(&request, "state").ok_or("No state parameter in callback")?;
+/// Synchronous function to wait for OAuth callback (runs in spawn_blocking)
+///
+/// Serves connections until the OAuth redirect carrying `expected_state`
+/// arrives; redirects with another or no state get a 400, other requests (the
+/// browser's favicon, probes) a 404. Gives up at `deadline`, if any.
+#[cfg(desktop)]
+fn wait_for_callback_sync(
+    port: u16,
+    expected_state: &str,
+    deadline: Option<std::time::Instant>,
+) -> Result<(String, String), String> {
+    // Start a simple HTTP server to receive the callback
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
+        .map_err(|e| format!("Failed to start callback server on port {}: {}", port, e))?;
+    if deadline.is_some() {
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure callback server: {}", e))?;
+    }
+
+    println!("Listening on 127.0.0.1:{}...", port);
 
-    // Send success response to browser
-    let response = r#"HTTP/1.1 200 OK
-Content-Type: text/html; charset=utf-8
-Connection: close
+    loop {
+        let (mut stream, addr) = accept_connection(&listener, deadline)?;
+        let request = match read_request_head(&mut stream) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Ignoring callback connection from {}: {}", addr, e);
+                respond(&mut stream, "400 Bad Request", "");
+                continue;
+            }
+        };
+
+        match route_loopback_request(&request, expected_state) {
+            LoopbackRequest::Code { code, state } => {
+                println!("Received OAuth callback from {}", addr);
+                respond(&mut stream, "200 OK", CALLBACK_SUCCESS_PAGE);
+                return Ok((code, state));
+            }
+            LoopbackRequest::Error(error) => {
+                respond(&mut stream, "200 OK", CALLBACK_ERROR_PAGE);
+                return Err(if error == "access_denied" {
+                    "Sign-in was cancelled in the browser".to_string()
+                } else {
+                    format!("Google sign-in failed: {}", error)
+                });
+            }
+            LoopbackRequest::BadState => {
+                eprintln!("Ignoring callback with a foreign state from {}", addr);
+                respond(&mut stream, "400 Bad Request", "");
+            }
+            LoopbackRequest::Other => respond(&mut stream, "404 Not Found", ""),
+        }
+    }
+}
 
-<!DOCTYPE html>
+/// Page shown in the browser once the callback is received
+#[cfg(desktop)]
+const CALLBACK_SUCCESS_PAGE: &str = r#"<!DOCTYPE html>
 <html>
 <head>
     <title>Rainy Day - Autenticación Exitosa</title>
@@ -317,11 +828,26 @@ Connection: close
 </body>
 </html>"#;
 
-    stream.write_all(response.as_bytes()).ok();
-    stream.flush().ok();
-
-    Ok((code, received_state))
-}
+/// Page shown in the browser when sign-in was denied or failed
+#[cfg(desktop)]
+const CALLBACK_ERROR_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Rainy Day - Autenticación Cancelada</title>
+    <style>
+        body { font-family: -apple-system, system-ui, sans-serif; display: flex; justify-content: center; align-items: center; min-height: 100vh; margin: 0; background: #020617; color: #f8fafc; }
+        .container { text-align: center; padding: 2rem; }
+        h1 { color: #f87171; margin-bottom: 1rem; }
+        p { color: #94a3b8; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>Autenticación Cancelada</h1>
+        <p>No se completó el inicio de sesión. Puedes cerrar esta ventana y volver a Rainy Day.</p>
+    </div>
+</body>
+</html>"#;
 
 /// Fetch user info from Google
 pub async fn fetch_user_info(access_token: &str) -> Result<UserInfo, String> {
@@ -373,15 +899,18 @@ fn extract_param(request: &str, param: &str) -> Option<String> {
     None
 }
 
-/// Check if user is currently authenticated
+/// Check if user is currently authenticated, refreshing an expiring access
+/// token first so a valid session isn't reported as signed out
 #[tauri::command]
 pub async fn is_authenticated(token_store: State<'_, TokenStore>) -> Result<AuthStatus, String> {
-    token_store.get_auth_status().await
+    crate::perf::trace_command!();
+    token_store.refreshed_auth_status().await
 }
 
 /// Log out the current user
 #[tauri::command]
 pub async fn logout(token_store: State<'_, TokenStore>) -> Result<(), String> {
+    crate::perf::trace_command!();
     token_store.clear_tokens().await
 }
 
@@ -392,23 +921,151 @@ pub async fn logout(token_store: State<'_, TokenStore>) -> Result<(), String> {
 /// Store backend JWT tokens in keychain
 #[tauri::command]
 pub fn store_backend_tokens(access_token: String, refresh_token: String) -> Result<(), String> {
+    crate::perf::trace_command!();
     keychain::store_backend_tokens(&access_token, &refresh_token)
 }
 
 /// Get backend access token from keychain
 #[tauri::command]
-pub fn get_backend_access_token() -> Result<Option<String>, String> {
+pub fn get_backend_access_token(
+    app_lock: State<'_, AppLockState>,
+) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     keychain::get_backend_access_token()
 }
 
-/// Get backend refresh token from keychain
+/// Get backend refresh token from keychain, after the OS gate if it is on
 #[tauri::command]
-pub fn get_backend_refresh_token() -> Result<Option<String>, String> {
+pub async fn get_backend_refresh_token(app: AppHandle) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    os_gate::confirm(&app, os_gate::SensitiveAction::RevealBackendToken).await?;
     keychain::get_backend_refresh_token()
 }
 
-/// Clear backend tokens from keychain
+/// Store the secret the backend signs pushes with (see `push`)
+#[tauri::command]
+pub fn store_backend_push_secret(secret: String) -> Result<(), String> {
+    crate::perf::trace_command!();
+    keychain::store_backend_push_secret(&secret)
+}
+
+/// Clear backend tokens and the push secret from keychain
 #[tauri::command]
 pub fn clear_backend_tokens() -> Result<(), String> {
+    crate::perf::trace_command!();
     keychain::clear_backend_tokens()
 }
+
+#[derive(Deserialize)]
+struct BackendTokens {
+    access_token: String,
+    refresh_token: String,
+}
+
+/// Trade the stored backend refresh token for new backend tokens, as the
+/// frontend does on a 401
+pub async fn refresh_backend_tokens(
+    http: &reqwest::Client,
+    backend_url: &str,
+) -> Result<(), String> {
+    let refresh_token =
+        keychain::get_backend_refresh_token()?.ok_or("Not signed in to the backend")?;
+    let response = http
+        .post(format!("{}/auth/refresh", backend_url))
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .map_err(|e| format!("Backend token refresh failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Backend token refresh rejected: {}",
+            response.status()
+        ));
+    }
+    let tokens: BackendTokens = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse backend tokens: {}", e))?;
+    keychain::store_backend_tokens(&tokens.access_token, &tokens.refresh_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_auth(started_at: i64) -> PendingAuth {
+        PendingAuth {
+            pkce_verifier: "verifier".to_string(),
+            csrf_token: "s1".to_string(),
+            redirect_uri: "http://127.0.0.1:8080".to_string(),
+            redirect_port: 8080,
+            started_at,
+        }
+    }
+
+    #[test]
+    fn test_loopback_redirect_uri() {
+        assert_eq!(loopback_redirect_uri(8080), "http://127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_mobile_redirect_uri() {
+        assert_eq!(
+            mobile_redirect_uri("123-abc.apps.googleusercontent.com").unwrap(),
+            "com.googleusercontent.apps.123-abc:/oauth2redirect"
+        );
+        assert!(mobile_redirect_uri("123-abc").is_err());
+        assert!(mobile_redirect_uri("").is_err());
+    }
+
+    #[test]
+    fn test_pending_auth_round_trip() {
+        let pending = pending_auth(1_700_000_000);
+        let json = serde_json::to_string(&pending).unwrap();
+        assert_eq!(serde_json::from_str::<PendingAuth>(&json).unwrap(), pending);
+    }
+
+    #[test]
+    fn test_pending_auth_expiry() {
+        let now = 1_700_000_000;
+        assert!(!pending_auth(now).is_expired(now));
+        assert!(!pending_auth(now - PENDING_AUTH_TTL_SECS + 1).is_expired(now));
+        assert!(pending_auth(now - PENDING_AUTH_TTL_SECS).is_expired(now));
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn test_route_loopback_request() {
+        let route = |request: &str| route_loopback_request(request, "s1");
+        assert_eq!(
+            route("GET /?state=s1&code=4%2Fabc&scope=email HTTP/1.1\r\nHost: x\r\n\r\n"),
+            LoopbackRequest::Code {
+                code: "4/abc".to_string(),
+                state: "s1".to_string(),
+            }
+        );
+        assert_eq!(
+            route("GET /?error=access_denied&state=s1 HTTP/1.1\r\n\r\n"),
+            LoopbackRequest::Error("access_denied".to_string())
+        );
+        assert_eq!(
+            route("GET /favicon.ico HTTP/1.1\r\n\r\n"),
+            LoopbackRequest::Other
+        );
+        assert_eq!(
+            route("POST /?code=abc&state=s1 HTTP/1.1\r\n\r\n"),
+            LoopbackRequest::Other
+        );
+
+        // Codes and errors of other flows are refused, not acted on
+        for request in [
+            "GET /?code=abc HTTP/1.1\r\n\r\n",
+            "GET /?code=abc&state=s2 HTTP/1.1\r\n\r\n",
+            "GET /?error=access_denied HTTP/1.1\r\n\r\n",
+            "GET /?error=access_denied&state=s2 HTTP/1.1\r\n\r\n",
+        ] {
+            assert_eq!(route(request), LoopbackRequest::BadState);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! OS authentication gate for sensitive actions
+//!
+//! Confirms the user with the operating system's own prompt: Touch ID (or the
+//! login password) on macOS, Windows Hello on Windows and a polkit agent on
+//! Linux. The app lock unlocks through it, and when the optional gate is on
+//! (`set_os_gate_settings`) `confirm` also guards handing out the backend
+//! refresh token and exporting data (everything, or a single thread).
+//!
+//! Changing the setting needs a confirmation too (unless the OS can no longer
+//! give one), so the gate can't be bypassed by turning it off. Actions that
+//! must never run on a plain invoke (`require`) are confirmed even with the
+//! gate off.
+
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const OS_GATE_KEY: &str = "os_gate";
+
+/// Actions guarded by the gate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitiveAction {
+    RevealBackendToken,
+    ExportData,
+    ExportThread,
+    ChangeGate,
+    ChangeScanner,
+}
+
+impl SensitiveAction {
+    /// Reason shown in the OS prompt
+    fn reason(self) -> &'static str {
+        match self {
+            SensitiveAction::RevealBackendToken => "Rainy Day wants to use your cloud session",
+            SensitiveAction::ExportData => "Rainy Day wants to export all your data",
+            SensitiveAction::ExportThread => "Rainy Day wants to export an email thread",
+            SensitiveAction::ChangeGate => "Rainy Day wants to change its security settings",
+            SensitiveAction::ChangeScanner => "Rainy Day wants to change its attachment scanner",
+        }
+    }
+}
+
+/// Persisted gate setting
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OsGateSettings {
+    pub enabled: bool,
+}
+
+/// Gate setting with what this device supports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsGateStatus {
+    pub enabled: bool,
+    pub available: bool,
+}
+
+fn load_settings(app: &AppHandle) -> Result<OsGateSettings, String> {
+    let store = storage::fs::settings_store(app, super::AUTH_STORE_FILE)
+        .map_err(|e| format!("Failed to access auth settings store: {}", e))?;
+
+    Ok(store
+        .get(OS_GATE_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Whether the OS can confirm the user on this device
+pub fn is_available() -> bool {
+    platform::is_available()
+}
+
+/// Show the OS prompt, blocking until the user answers
+pub fn verify(reason: &str) -> Result<(), String> {
+    platform::verify(reason)
+}
+
+/// Ask the OS to confirm the user, off the async runtime
+async fn verify_async(action: SensitiveAction) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || verify(action.reason()))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Confirm `action` with the OS prompt when the gate is on
+pub async fn confirm(app: &AppHandle, action: SensitiveAction) -> Result<(), String> {
+    if !load_settings(app)?.enabled {
+        return Ok(());
+    }
+    verify_async(action).await
+}
+
+/// Confirm `action` with the OS prompt whether or not the gate is on
+pub async fn require(action: SensitiveAction) -> Result<(), String> {
+    let available = tokio::task::spawn_blocking(is_available)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+    if !available {
+        return Err("OS authentication is not available on this device".to_string());
+    }
+    verify_async(action).await
+}
+
+// ============================================================================
+// Platform Verification
+// ============================================================================
+
+#[cfg(windows)]
+mod platform {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub fn is_available() -> bool {
+        UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|op| op.get())
+            .is_ok_and(|a| a == UserConsentVerifierAvailability::Available)
+    }
+
+    pub fn verify(reason: &str) -> Result<(), String> {
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+            .and_then(|op| op.get())
+            .map_err(|e| format!("Windows Hello failed: {}", e))?;
+
+        if result == UserConsentVerificationResult::Verified {
+            Ok(())
+        } else {
+            Err(format!("Windows Hello verification failed: {:?}", result))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use block2::RcBlock;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2::{class, msg_send};
+    use std::ffi::CString;
+    use std::sync::mpsc;
+
+    #[link(name = "LocalAuthentication", kind = "framework")]
+    extern "C" {}
+
+    /// `LAPolicyDeviceOwnerAuthentication`: Touch ID, else the login password
+    const DEVICE_OWNER_AUTHENTICATION: isize = 2;
+
+    fn context() -> Retained<AnyObject> {
+        unsafe { msg_send![class!(LAContext), new] }
+    }
+
+    pub fn is_available() -> bool {
+        let context = context();
+        let error: *mut *mut AnyObject = std::ptr::null_mut();
+        let available: Bool = unsafe {
+            msg_send![&context, canEvaluatePolicy: DEVICE_OWNER_AUTHENTICATION, error: error]
+        };
+        available.as_bool()
+    }
+
+    pub fn verify(reason: &str) -> Result<(), String> {
+        let reason = CString::new(reason).map_err(|e| format!("Invalid prompt: {}", e))?;
+        let reason: Retained<AnyObject> =
+            unsafe { msg_send![class!(NSString), stringWithUTF8String: reason.as_ptr()] };
+
+        let (tx, rx) = mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, _error: *mut AnyObject| {
+            let _ = tx.send(success.as_bool());
+        });
+        let context = context();
+        unsafe {
+            let _: () = msg_send![
+                &context,
+                evaluatePolicy: DEVICE_OWNER_AUTHENTICATION,
+                localizedReason: &*reason,
+                reply: &*reply
+            ];
+        }
+
+        match rx.recv() {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("Touch ID verification failed".to_string()),
+            Err(_) => Err("Touch ID prompt was dismissed".to_string()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::{Command, Stdio};
+
+    /// Action whose policy makes polkit ask for the user's password
+    const POLKIT_ACTION: &str = "org.freedesktop.policykit.exec";
+
+    pub fn is_available() -> bool {
+        Command::new("pkcheck")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// polkit agents show their own message; `reason` is not passed through
+    pub fn verify(_reason: &str) -> Result<(), String> {
+        let output = Command::new("pkcheck")
+            .args(["--action-id", POLKIT_ACTION, "--allow-user-interaction"])
+            .args(["--process", &std::process::id().to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run pkcheck: {}", e))?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "polkit authentication failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    pub fn is_available() -> bool {
+        false
+    }
+
+    pub fn verify(_reason: &str) -> Result<(), String> {
+        Err("OS authentication is not available on this platform".to_string())
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get whether sensitive actions need an OS confirmation
+#[tauri::command]
+pub async fn get_os_gate_settings(app: AppHandle) -> Result<OsGateStatus, String> {
+    crate::perf::trace_command!();
+    Ok(OsGateStatus {
+        enabled: load_settings(&app)?.enabled,
+        available: tokio::task::spawn_blocking(is_available)
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?,
+    })
+}
+
+/// Turn the OS confirmation of sensitive actions on or off
+///
+/// Either way the change itself is confirmed with the OS prompt.
+#[tauri::command]
+pub async fn set_os_gate_settings(
+    app: AppHandle,
+    settings: OsGateSettings,
+) -> Result<OsGateStatus, String> {
+    crate::perf::trace_command!();
+    let available = tokio::task::spawn_blocking(is_available)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+    if settings.enabled && !available {
+        return Err("OS authentication is not available on this device".to_string());
+    }
+    // A gate the OS can no longer confirm can still be turned off
+    if available {
+        verify_async(SensitiveAction::ChangeGate).await?;
+    }
+
+    let store = storage::fs::settings_store(&app, super::AUTH_STORE_FILE)
+        .map_err(|e| format!("Failed to access auth settings store: {}", e))?;
+    store.set(OS_GATE_KEY, serde_json::json!(settings));
+    storage::fs::save_store(&app, super::AUTH_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save auth settings store: {}", e))?;
+
+    get_os_gate_settings(app).await
+}
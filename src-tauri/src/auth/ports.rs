@@ -0,0 +1,193 @@
+//! OAuth loopback port selection
+//!
+//! The desktop sign-in listens on the first free port of a range (8400-8499
+//! by default). Users who whitelist the port in a firewall, or register the
+//! redirect URI in their own OAuth client, can narrow the range or pin a
+//! single port. The range comes from the `auth.json` settings store, and the
+//! `RAINY_DAY_OAUTH_PORTS` environment variable (`8400` or `8400-8499`) takes
+//! precedence.
+
+use super::AUTH_STORE_FILE;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const CALLBACK_PORTS_KEY: &str = "callback_ports";
+const CALLBACK_PORTS_ENV: &str = "RAINY_DAY_OAUTH_PORTS";
+
+/// Ports the loopback server may listen on (inclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallbackPorts {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl Default for CallbackPorts {
+    fn default() -> Self {
+        Self {
+            start: 8400,
+            end: 8499,
+        }
+    }
+}
+
+impl CallbackPorts {
+    /// Parse `8400` (pinned) or `8400-8499`
+    fn parse(value: &str) -> Result<Self, String> {
+        let port = |p: &str| {
+            p.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid OAuth callback port: {}", p.trim()))
+        };
+        let ports = match value.split_once('-') {
+            Some((start, end)) => Self {
+                start: port(start)?,
+                end: port(end)?,
+            },
+            None => {
+                let port = port(value)?;
+                Self {
+                    start: port,
+                    end: port,
+                }
+            }
+        };
+        ports.validated()
+    }
+
+    fn validated(self) -> Result<Self, String> {
+        if self.start < 1024 {
+            return Err(format!(
+                "OAuth callback ports must be 1024 or above: {}",
+                self.start
+            ));
+        }
+        if self.start > self.end {
+            return Err(format!(
+                "Invalid OAuth callback port range: {}-{}",
+                self.start, self.end
+            ));
+        }
+        Ok(self)
+    }
+
+    fn is_pinned(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Port setting in effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallbackPortSettings {
+    pub ports: CallbackPorts,
+    /// Set by the environment variable, which overrides the saved range
+    pub from_env: bool,
+}
+
+fn from_env() -> Result<Option<CallbackPorts>, String> {
+    match std::env::var(CALLBACK_PORTS_ENV) {
+        Ok(value) if !value.trim().is_empty() => CallbackPorts::parse(&value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn load_stored(app: &AppHandle) -> Result<Option<CallbackPorts>, String> {
+    let store = storage::fs::settings_store(app, AUTH_STORE_FILE)
+        .map_err(|e| format!("Failed to access auth settings store: {}", e))?;
+
+    Ok(store
+        .get(CALLBACK_PORTS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok()))
+}
+
+fn settings(app: &AppHandle) -> Result<CallbackPortSettings, String> {
+    Ok(match from_env()? {
+        Some(ports) => CallbackPortSettings {
+            ports,
+            from_env: true,
+        },
+        None => CallbackPortSettings {
+            ports: load_stored(app)?.unwrap_or_default(),
+            from_env: false,
+        },
+    })
+}
+
+/// Find an available port for the OAuth callback server
+#[cfg(desktop)]
+pub(super) fn find_available_port(app: &AppHandle) -> Result<u16, String> {
+    let ports = settings(app)?.ports;
+    for port in ports.start..=ports.end {
+        if std::net::TcpListener::bind(format!("127.0.0.1:{}", port)).is_ok() {
+            return Ok(port);
+        }
+    }
+    if ports.is_pinned() {
+        Err(format!(
+            "OAuth callback port {} is in use by another program",
+            ports.start
+        ))
+    } else {
+        Err(format!(
+            "No available port for OAuth callback in {}-{}",
+            ports.start, ports.end
+        ))
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the OAuth callback ports in effect
+#[tauri::command]
+pub async fn get_callback_ports(app: AppHandle) -> Result<CallbackPortSettings, String> {
+    crate::perf::trace_command!();
+    settings(&app)
+}
+
+/// Save the OAuth callback port range, or reset it to the default with `None`
+/// (the environment variable still takes precedence)
+#[tauri::command]
+pub async fn set_callback_ports(
+    app: AppHandle,
+    ports: Option<CallbackPorts>,
+) -> Result<CallbackPortSettings, String> {
+    crate::perf::trace_command!();
+    let store = storage::fs::settings_store(&app, AUTH_STORE_FILE)
+        .map_err(|e| format!("Failed to access auth settings store: {}", e))?;
+    match ports {
+        Some(ports) => {
+            store.set(CALLBACK_PORTS_KEY, serde_json::json!(ports.validated()?));
+        }
+        None => {
+            store.delete(CALLBACK_PORTS_KEY);
+        }
+    }
+    storage::fs::save_store(&app, AUTH_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save auth settings store: {}", e))?;
+
+    settings(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback_ports() {
+        assert_eq!(
+            CallbackPorts::parse("8400-8410"),
+            Ok(CallbackPorts {
+                start: 8400,
+                end: 8410
+            })
+        );
+        let pinned = CallbackPorts::parse(" 9123 ").unwrap();
+        assert!(pinned.is_pinned());
+        assert_eq!(pinned.start, 9123);
+        assert!(CallbackPorts::parse("8500-8400").is_err());
+        assert!(CallbackPorts::parse("80").is_err());
+        assert!(CallbackPorts::parse("http").is_err());
+    }
+}
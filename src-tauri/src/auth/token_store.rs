@@ -4,12 +4,31 @@
 //! - refresh_token: Stored in OS Keychain (encrypted by OS)
 //! - access_token: In-memory only (short-lived, not persisted)
 //! - metadata: Stored in JSON (email, expires_at, scopes - not sensitive)
-
-use crate::auth::{keychain, AuthStatus, UserInfo, GOOGLE_TOKEN_URL};
+//!
+//! Refresh failures are classified: Google rejecting the refresh token
+//! (`invalid_grant`, ...) ends the session and reports `reauth_required` in
+//! the auth status, while network and server errors keep it for a retry.
+//! Refresh tokens Google rotates are saved back to the keychain.
+//!
+//! `spawn_token_refresh` renews the access token a few minutes before it
+//! expires, so the first request after idle doesn't wait for a refresh.
+//!
+//! Refreshes are single-flight (`refresh_lock`): with rotating refresh tokens
+//! a second concurrent refresh would present a used token and be rejected.
+//! Everything that changes the persisted session (sign-in, sign-out, the end
+//! of a refresh) holds the session write lock, and a refresh whose session
+//! was replaced or cleared meanwhile is dropped.
+
+use crate::account::AccountContext;
+use crate::auth::capabilities::Feature;
+use crate::auth::{keychain, AuthStatus, ReauthRequired, UserInfo, GOOGLE_TOKEN_URL, SCOPES};
+use crate::storage;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{Mutex, RwLock};
 
 /// Session metadata (non-sensitive, stored in JSON)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +47,8 @@ pub struct ActiveSession {
     pub refresh_token: String,
     pub expires_at: i64,
     pub user_info: UserInfo,
+    /// Empty for sessions that didn't record them
+    pub scopes_granted: Vec<String>,
 }
 
 /// Stored tokens format (for migration from old format)
@@ -37,6 +58,8 @@ pub struct StoredTokens {
     pub refresh_token: Option<String>,
     pub expires_at: i64,
     pub user_info: UserInfo,
+    #[serde(default)]
+    pub scopes_granted: Vec<String>,
 }
 
 /// Token store with OS keychain for secrets
@@ -45,11 +68,98 @@ pub struct TokenStore {
     metadata_path: Arc<RwLock<Option<PathBuf>>>,
     client_id: Arc<RwLock<Option<String>>>,
     client_secret: Arc<RwLock<Option<String>>>,
+    /// OAuth token endpoint (overridable for the fake-server test harness)
+    token_url: String,
+    /// Session Google revoked, until the user signs in again
+    reauth: Arc<RwLock<Option<ReauthRequired>>>,
+    /// Held for the whole of a refresh
+    refresh_lock: Arc<Mutex<()>>,
+}
+
+/// Why a token refresh failed
+#[derive(Debug, Clone, PartialEq)]
+enum RefreshError {
+    /// Google rejected the refresh token; only a new sign-in helps
+    Rejected { reason: String, message: String },
+    /// Network or server trouble; worth retrying
+    Transient(String),
+    /// Google refused this build's OAuth client; the session itself is fine
+    Misconfigured(String),
+}
+
+impl std::fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefreshError::Rejected { message, .. }
+            | RefreshError::Transient(message)
+            | RefreshError::Misconfigured(message) => f.write_str(message),
+        }
+    }
+}
+
+/// OAuth error codes meaning the refresh token will never work again
+const REJECTED_GRANT_ERRORS: &[&str] = &["invalid_grant"];
+/// OAuth error codes blaming the client ID or secret, not the refresh token
+const CLIENT_CONFIG_ERRORS: &[&str] = &["invalid_client", "unauthorized_client"];
+
+/// Form of a refresh request; public clients send no secret
+fn refresh_form<'a>(
+    client_id: &'a str,
+    client_secret: Option<&'a str>,
+    refresh_token: &'a str,
+) -> Vec<(&'static str, &'a str)> {
+    let mut form = vec![
+        ("client_id", client_id),
+        ("refresh_token", refresh_token),
+        ("grant_type", "refresh_token"),
+    ];
+    if let Some(secret) = client_secret {
+        form.push(("client_secret", secret));
+    }
+    form
+}
+
+/// Classify an unsuccessful token endpoint response
+fn classify_refresh_failure(status: u16, body: &str) -> RefreshError {
+    #[derive(Deserialize)]
+    struct OAuthError {
+        error: String,
+    }
+
+    let message = format!("Token refresh failed: {}", body);
+    match serde_json::from_str::<OAuthError>(body) {
+        Ok(e)
+            if (400..500).contains(&status)
+                && REJECTED_GRANT_ERRORS.contains(&e.error.as_str()) =>
+        {
+            RefreshError::Rejected {
+                reason: e.error,
+                message,
+            }
+        }
+        Ok(e)
+            if (400..500).contains(&status)
+                && CLIENT_CONFIG_ERRORS.contains(&e.error.as_str()) =>
+        {
+            RefreshError::Misconfigured(format!(
+                "OAuth client configuration error ({}): check the client ID and secret of this build",
+                e.error
+            ))
+        }
+        _ => RefreshError::Transient(message),
+    }
 }
 
 const METADATA_FILENAME: &str = "session_metadata.json";
 const OLD_SESSION_FILENAME: &str = "auth_session.json";
 
+/// How long before expiry the background task refreshes the access token
+const REFRESH_LEAD_SECS: i64 = 5 * 60;
+/// Random extra lead so several instances don't refresh in lockstep
+const REFRESH_JITTER_SECS: i64 = 90;
+/// Longest sleep of the refresh task, so new sessions are noticed
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 impl TokenStore {
     pub fn new() -> Self {
         Self {
@@ -57,6 +167,9 @@ impl TokenStore {
             metadata_path: Arc::new(RwLock::new(None)),
             client_id: Arc::new(RwLock::new(None)),
             client_secret: Arc::new(RwLock::new(None)),
+            token_url: GOOGLE_TOKEN_URL.to_string(),
+            reauth: Arc::new(RwLock::new(None)),
+            refresh_lock: Arc::new(Mutex::new(())),
         }
     }
 
@@ -73,8 +186,9 @@ impl TokenStore {
             *id_guard = Some(client_id);
         }
         {
+            // Without a secret the app signs in as a public (PKCE-only) client
             let mut secret_guard = self.client_secret.write().await;
-            *secret_guard = Some(client_secret);
+            *secret_guard = Some(client_secret).filter(|s| !s.is_empty());
         }
 
         // Ensure app data directory exists
@@ -94,7 +208,8 @@ impl TokenStore {
 
         // Migration: Check for old auth_session.json with refresh_token
         if old_session_path.exists() {
-            self.migrate_from_old_format(&old_session_path, &metadata_path).await?;
+            self.migrate_from_old_format(&old_session_path, &metadata_path)
+                .await?;
         } else if metadata_path.exists() {
             // Load from new format
             self.load_from_metadata(&metadata_path).await?;
@@ -107,7 +222,7 @@ impl TokenStore {
     async fn migrate_from_old_format(
         &self,
         old_path: &PathBuf,
-        metadata_path: &PathBuf,
+        metadata_path: &Path,
     ) -> Result<(), String> {
         println!("Migrating session from old format to secure keychain...");
 
@@ -134,11 +249,7 @@ impl TokenStore {
             scopes_granted: vec![], // We don't have this info from old format
         };
 
-        let metadata_json = serde_json::to_string_pretty(&metadata)
-            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-
-        std::fs::write(metadata_path, metadata_json)
-            .map_err(|e| format!("Failed to write metadata: {}", e))?;
+        storage::fs::write_json(metadata_path, &metadata)?;
 
         // Delete old session file (contains secrets)
         std::fs::remove_file(old_path)
@@ -151,14 +262,11 @@ impl TokenStore {
     }
 
     /// Load session from metadata + keychain
-    async fn load_from_metadata(&self, metadata_path: &PathBuf) -> Result<(), String> {
-        let content = std::fs::read_to_string(metadata_path)
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-        let metadata: SessionMetadata = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-        let now = chrono::Utc::now().timestamp();
+    async fn load_from_metadata(&self, metadata_path: &Path) -> Result<(), String> {
+        // A corrupted file is quarantined; without a backup the user signs in again
+        let Some(metadata) = storage::fs::read_json::<SessionMetadata>(metadata_path)? else {
+            return Ok(());
+        };
 
         // Get refresh_token from keychain
         let refresh_token = match keychain::get_refresh_token(&metadata.email)? {
@@ -166,118 +274,173 @@ impl TokenStore {
             None => {
                 println!("No refresh token in keychain for: {}", metadata.email);
                 // Clean up orphaned metadata
-                let _ = std::fs::remove_file(metadata_path);
+                let _ = storage::fs::remove(metadata_path);
                 return Ok(());
             }
         };
 
-        // Check if we need to refresh
-        if metadata.expires_at <= now {
-            println!("Session expired, attempting refresh for: {}", metadata.email);
-            match self.refresh_token_internal(&refresh_token, &metadata).await {
-                Ok(session) => {
-                    let mut guard = self.session.write().await;
-                    *guard = Some(session);
-                    println!("Session refreshed successfully");
-                }
-                Err(e) => {
-                    eprintln!("Failed to refresh session: {}", e);
-                    // Clear stale data
-                    let _ = keychain::delete_refresh_token(&metadata.email);
-                    let _ = std::fs::remove_file(metadata_path);
+        // The access token is never persisted, so a session always starts
+        // with a refresh
+        println!(
+            "Loading session, refreshing access token for: {}",
+            metadata.email
+        );
+        let _refreshing = self.refresh_lock.lock().await;
+        let result = self.refresh_token_internal(&refresh_token, &metadata).await;
+        let mut guard = self.session.write().await;
+        match result {
+            Ok(session) => {
+                if let Err(e) = self.persist_refresh(&refresh_token, &session).await {
+                    eprintln!("Failed to save refreshed session: {}", e);
                 }
+                *guard = Some(session);
+                println!("Session refreshed successfully");
             }
-        } else {
-            // Session still valid - we don't have access_token, need to refresh
-            // In practice, access_token is short-lived so we always refresh on startup
-            println!("Loading session, refreshing access token for: {}", metadata.email);
-            match self.refresh_token_internal(&refresh_token, &metadata).await {
-                Ok(session) => {
-                    let mut guard = self.session.write().await;
-                    *guard = Some(session);
-                }
-                Err(e) => {
-                    eprintln!("Failed to refresh on load: {}", e);
-                }
+            Err(RefreshError::Rejected { reason, message }) => {
+                eprintln!("Failed to refresh session: {}", message);
+                self.end_rejected_session(&mut guard, &metadata.email, reason)
+                    .await;
+            }
+            Err(RefreshError::Transient(e) | RefreshError::Misconfigured(e)) => {
+                // Keep the session; the next request or the background
+                // refresh tries again
+                eprintln!("Failed to refresh on load: {}", e);
+                *guard = Some(ActiveSession {
+                    access_token: String::new(),
+                    refresh_token,
+                    expires_at: 0,
+                    user_info: UserInfo {
+                        email: metadata.email.clone(),
+                        name: metadata.name.clone(),
+                        picture: metadata.picture.clone(),
+                    },
+                    scopes_granted: metadata.scopes_granted.clone(),
+                });
             }
         }
 
         Ok(())
     }
 
+    /// Forget a session Google rejected and remember to ask for a new sign-in
+    ///
+    /// `session` is the held session lock, so a sign-in can't interleave.
+    async fn end_rejected_session(
+        &self,
+        session: &mut Option<ActiveSession>,
+        email: &str,
+        reason: String,
+    ) {
+        let _ = keychain::delete_refresh_token(email);
+        let metadata_path = self.metadata_path.read().await.clone();
+        if let Some(path) = metadata_path {
+            let _ = storage::fs::remove(&path);
+        }
+        *session = None;
+        *self.reauth.write().await = Some(ReauthRequired {
+            email: email.to_string(),
+            reason,
+        });
+    }
+
     /// Refresh token using the refresh_token
+    ///
+    /// Nothing is persisted; see `persist_refresh`.
     async fn refresh_token_internal(
         &self,
         refresh_token: &str,
         metadata: &SessionMetadata,
-    ) -> Result<ActiveSession, String> {
+    ) -> Result<ActiveSession, RefreshError> {
         let client_id = {
             let guard = self.client_id.read().await;
-            guard.clone().ok_or("Client ID not initialized")?
-        };
-        let client_secret = {
-            let guard = self.client_secret.read().await;
-            guard.clone().ok_or("Client secret not initialized")?
+            guard
+                .clone()
+                .ok_or_else(|| RefreshError::Transient("Client ID not initialized".into()))?
         };
+        let client_secret = self.client_secret.read().await.clone();
 
         let http_client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(30))
             .build()
-            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+            .map_err(|e| RefreshError::Transient(format!("Failed to create HTTP client: {}", e)))?;
 
-        let form_data = [
-            ("client_id", client_id.as_str()),
-            ("client_secret", client_secret.as_str()),
-            ("refresh_token", refresh_token),
-            ("grant_type", "refresh_token"),
-        ];
+        let form_data = refresh_form(&client_id, client_secret.as_deref(), refresh_token);
 
         let response = http_client
-            .post(GOOGLE_TOKEN_URL)
+            .post(&self.token_url)
             .form(&form_data)
             .send()
             .await
-            .map_err(|e| format!("Refresh request failed: {}", e))?;
+            .map_err(|e| RefreshError::Transient(format!("Refresh request failed: {}", e)))?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Token refresh failed: {}", error_text));
+            return Err(classify_refresh_failure(status.as_u16(), &error_text));
         }
 
         #[derive(Deserialize)]
         struct RefreshResponse {
             access_token: String,
             expires_in: Option<u64>,
+            /// Present when Google rotates the refresh token
+            refresh_token: Option<String>,
+            /// Granted scopes, space separated
+            scope: Option<String>,
         }
 
-        let refresh_resp: RefreshResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse refresh response: {}", e))?;
+        let refresh_resp: RefreshResponse = response.json().await.map_err(|e| {
+            RefreshError::Transient(format!("Failed to parse refresh response: {}", e))
+        })?;
+
+        let refresh_token = refresh_resp
+            .refresh_token
+            .unwrap_or_else(|| refresh_token.to_string());
 
         let expires_at = refresh_resp
             .expires_in
             .map(|d| chrono::Utc::now().timestamp() + d as i64)
             .unwrap_or(chrono::Utc::now().timestamp() + 3600);
 
-        // Update metadata with new expiry
-        self.save_metadata(&SessionMetadata {
-            expires_at,
-            ..metadata.clone()
-        }).await?;
+        let scopes_granted = match refresh_resp.scope {
+            Some(scope) => scope.split_whitespace().map(str::to_string).collect(),
+            None => metadata.scopes_granted.clone(),
+        };
 
         Ok(ActiveSession {
             access_token: refresh_resp.access_token,
-            refresh_token: refresh_token.to_string(),
+            refresh_token,
             expires_at,
             user_info: UserInfo {
                 email: metadata.email.clone(),
                 name: metadata.name.clone(),
                 picture: metadata.picture.clone(),
             },
+            scopes_granted,
         })
     }
 
+    /// Save a refreshed session: the refresh token if Google rotated it, and
+    /// the new expiry
+    async fn persist_refresh(
+        &self,
+        previous_refresh_token: &str,
+        session: &ActiveSession,
+    ) -> Result<(), String> {
+        let email = &session.user_info.email;
+        if session.refresh_token != previous_refresh_token {
+            keychain::store_refresh_token(email, &session.refresh_token)?;
+        }
+        self.save_metadata(&SessionMetadata {
+            email: email.clone(),
+            name: session.user_info.name.clone(),
+            picture: session.user_info.picture.clone(),
+            expires_at: session.expires_at,
+            scopes_granted: session.scopes_granted.clone(),
+        })
+        .await
+    }
+
     /// Save metadata to JSON file
     async fn save_metadata(&self, metadata: &SessionMetadata) -> Result<(), String> {
         let path = {
@@ -285,23 +448,23 @@ impl TokenStore {
             guard.clone().ok_or("Metadata path not initialized")?
         };
 
-        let json = serde_json::to_string_pretty(metadata)
-            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-
-        std::fs::write(&path, json)
-            .map_err(|e| format!("Failed to write metadata: {}", e))?;
-
-        Ok(())
+        storage::fs::write_json(&path, metadata)
     }
 
     /// Store new tokens after successful OAuth exchange
     pub async fn store_tokens(&self, tokens: StoredTokens) -> Result<(), String> {
+        let mut guard = self.session.write().await;
         let email = &tokens.user_info.email;
 
         // Store refresh_token in keychain (if present)
         if let Some(ref refresh_token) = tokens.refresh_token {
             keychain::store_refresh_token(email, refresh_token)?;
         }
+        // Incremental consent for a signed-in account returns no new one
+        let refresh_token = match tokens.refresh_token {
+            Some(token) => token,
+            None => keychain::get_refresh_token(email)?.unwrap_or_default(),
+        };
 
         // Save metadata (no secrets)
         let metadata = SessionMetadata {
@@ -309,20 +472,21 @@ impl TokenStore {
             name: tokens.user_info.name.clone(),
             picture: tokens.user_info.picture.clone(),
             expires_at: tokens.expires_at,
-            scopes_granted: vec![], // TODO: Store granted scopes
+            scopes_granted: tokens.scopes_granted.clone(),
         };
         self.save_metadata(&metadata).await?;
 
         // Update in-memory session
         let session = ActiveSession {
             access_token: tokens.access_token,
-            refresh_token: tokens.refresh_token.unwrap_or_default(),
+            refresh_token,
             expires_at: tokens.expires_at,
             user_info: tokens.user_info,
+            scopes_granted: tokens.scopes_granted,
         };
 
-        let mut guard = self.session.write().await;
         *guard = Some(session);
+        *self.reauth.write().await = None;
 
         Ok(())
     }
@@ -341,16 +505,80 @@ impl TokenStore {
                     is_authenticated: is_valid,
                     user: Some(session.user_info.clone()),
                     expires_at: Some(session.expires_at),
+                    reauth_required: None,
                 })
             }
             None => Ok(AuthStatus {
                 is_authenticated: false,
                 user: None,
                 expires_at: None,
+                reauth_required: self.reauth.read().await.clone(),
             }),
         }
     }
 
+    /// Auth status after refreshing an expiring access token
+    ///
+    /// A session whose access token is (nearly) expired is still usable while
+    /// its refresh token works, so refresh before reporting it signed out. A
+    /// rejected refresh token ends the session (see `reauth_required`).
+    pub async fn refreshed_auth_status(&self) -> Result<AuthStatus, String> {
+        let needs_refresh = {
+            let guard = self.session.read().await;
+            let now = chrono::Utc::now().timestamp();
+            guard
+                .as_ref()
+                .is_some_and(|s| !s.refresh_token.is_empty() && s.expires_at <= now + 300)
+        };
+        if needs_refresh {
+            if let Err(e) = self.refresh_access_token().await {
+                eprintln!("Silent token refresh failed: {}", e);
+            }
+        }
+        self.get_auth_status().await
+    }
+
+    /// Install an in-memory session for mock provider mode (nothing is persisted)
+    pub async fn use_mock_session(&self, email: &str) {
+        let session = ActiveSession {
+            access_token: "mock-access-token".to_string(),
+            refresh_token: String::new(),
+            expires_at: i64::MAX / 2,
+            user_info: UserInfo {
+                email: email.to_string(),
+                name: Some("Mock User".to_string()),
+                picture: None,
+            },
+            // Every feature works against the mock provider
+            scopes_granted: SCOPES
+                .iter()
+                .chain(Feature::ALL.iter().flat_map(|f| f.scopes()))
+                .map(|s| s.to_string())
+                .collect(),
+        };
+
+        let mut guard = self.session.write().await;
+        *guard = Some(session);
+    }
+
+    /// Scopes granted to the session (empty when it didn't record them)
+    pub async fn granted_scopes(&self) -> Result<Vec<String>, String> {
+        let guard = self.session.read().await;
+        guard
+            .as_ref()
+            .map(|s| s.scopes_granted.clone())
+            .ok_or_else(|| "Not authenticated".to_string())
+    }
+
+    /// Get the signed-in account used to scope local data
+    pub async fn account_context(&self) -> Result<AccountContext, String> {
+        let guard = self.session.read().await;
+        guard
+            .as_ref()
+            .map(|s| AccountContext::new(&s.user_info.email))
+            .ok_or_else(|| "Not authenticated".to_string())
+    }
+
     /// Get current access token (refreshing if needed)
     pub async fn get_access_token(&self) -> Result<String, String> {
         let session = {
@@ -365,22 +593,7 @@ impl TokenStore {
                 // Check if token is expired or about to expire (5 min buffer)
                 if s.expires_at <= (now + 300) {
                     println!("Access token expired, refreshing...");
-                    
-                    let metadata = SessionMetadata {
-                        email: s.user_info.email.clone(),
-                        name: s.user_info.name.clone(),
-                        picture: s.user_info.picture.clone(),
-                        expires_at: s.expires_at,
-                        scopes_granted: vec![],
-                    };
-
-                    let new_session = self.refresh_token_internal(&s.refresh_token, &metadata).await?;
-                    let access_token = new_session.access_token.clone();
-
-                    let mut guard = self.session.write().await;
-                    *guard = Some(new_session);
-
-                    return Ok(access_token);
+                    return self.refresh_access_token().await;
                 }
 
                 Ok(s.access_token.clone())
@@ -389,13 +602,77 @@ impl TokenStore {
         }
     }
 
-    /// Clear all stored tokens (logout)
-    pub async fn clear_tokens(&self) -> Result<(), String> {
-        // Get email before clearing
-        let email = {
+    /// Expiry of the current access token; None when signed out or in mock mode
+    pub async fn access_token_expiry(&self) -> Option<i64> {
+        let guard = self.session.read().await;
+        guard
+            .as_ref()
+            .filter(|s| !s.refresh_token.is_empty())
+            .map(|s| s.expires_at)
+    }
+
+    /// Refresh the access token now, e.g. after the API rejected it with 401
+    ///
+    /// A caller that waited for another refresh gets that refresh's token.
+    pub async fn refresh_access_token(&self) -> Result<String, String> {
+        let stale = {
+            let guard = self.session.read().await;
+            guard.as_ref().map(|s| s.access_token.clone())
+        };
+        let _refreshing = self.refresh_lock.lock().await;
+
+        let s = {
             let guard = self.session.read().await;
-            guard.as_ref().map(|s| s.user_info.email.clone())
+            guard.clone().ok_or("Not authenticated")?
         };
+        let now = chrono::Utc::now().timestamp();
+        if stale.as_deref() != Some(s.access_token.as_str()) && s.expires_at > now + 300 {
+            return Ok(s.access_token);
+        }
+
+        let metadata = SessionMetadata {
+            email: s.user_info.email.clone(),
+            name: s.user_info.name.clone(),
+            picture: s.user_info.picture.clone(),
+            expires_at: s.expires_at,
+            scopes_granted: s.scopes_granted.clone(),
+        };
+
+        let result = self
+            .refresh_token_internal(&s.refresh_token, &metadata)
+            .await;
+
+        let mut guard = self.session.write().await;
+        // Signed out, or in again, while the request was out
+        let unchanged = guard.as_ref().is_some_and(|current| {
+            current.user_info.email == s.user_info.email && current.refresh_token == s.refresh_token
+        });
+        if !unchanged {
+            return Err("Session changed during token refresh".to_string());
+        }
+
+        match result {
+            Ok(new_session) => {
+                self.persist_refresh(&s.refresh_token, &new_session).await?;
+                let access_token = new_session.access_token.clone();
+                *guard = Some(new_session);
+                Ok(access_token)
+            }
+            Err(RefreshError::Rejected { reason, message }) => {
+                eprintln!("Session rejected by Google: {}", message);
+                self.end_rejected_session(&mut guard, &s.user_info.email, reason)
+                    .await;
+                Err(format!("Reauthentication required: {}", message))
+            }
+            Err(RefreshError::Transient(e) | RefreshError::Misconfigured(e)) => Err(e),
+        }
+    }
+
+    /// Clear all stored tokens (logout)
+    pub async fn clear_tokens(&self) -> Result<(), String> {
+        // Held until the end, so a refresh finishing now can't write back
+        let mut guard = self.session.write().await;
+        let email = guard.as_ref().map(|s| s.user_info.email.clone());
 
         // Clear from keychain
         if let Some(email) = email {
@@ -408,16 +685,12 @@ impl TokenStore {
             guard.clone()
         };
         if let Some(path) = metadata_path {
-            if path.exists() {
-                let _ = std::fs::remove_file(&path);
-            }
+            let _ = storage::fs::remove(&path);
         }
 
         // Clear from memory
-        {
-            let mut guard = self.session.write().await;
-            *guard = None;
-        }
+        *guard = None;
+        *self.reauth.write().await = None;
 
         println!("Session cleared (keychain + metadata)");
         Ok(())
@@ -429,3 +702,115 @@ impl Default for TokenStore {
         Self::new()
     }
 }
+
+/// Seconds until a token expiring at `expires_at` should be refreshed
+fn refresh_delay(expires_at: i64, now: i64, jitter_secs: i64) -> u64 {
+    (expires_at - REFRESH_LEAD_SECS - jitter_secs - now).max(0) as u64
+}
+
+/// Start the background access token refresh
+pub fn spawn_token_refresh(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut jitter = rand::random::<u32>() as i64 % REFRESH_JITTER_SECS;
+        loop {
+            let token_store = app.state::<TokenStore>();
+            let Some(expires_at) = token_store.access_token_expiry().await else {
+                tokio::time::sleep(REFRESH_CHECK_INTERVAL).await;
+                continue;
+            };
+
+            let delay = refresh_delay(expires_at, chrono::Utc::now().timestamp(), jitter);
+            if delay > 0 {
+                tokio::time::sleep(Duration::from_secs(delay).min(REFRESH_CHECK_INTERVAL)).await;
+                continue;
+            }
+
+            match token_store.refresh_access_token().await {
+                Ok(_) => jitter = rand::random::<u32>() as i64 % REFRESH_JITTER_SECS,
+                Err(e) => {
+                    eprintln!("Background token refresh failed: {}", e);
+                    tokio::time::sleep(REFRESH_CHECK_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+impl TokenStore {
+    /// Store signed in as `email` that refreshes against `token_url`
+    pub(crate) async fn for_fake_server(
+        token_url: &str,
+        metadata_path: PathBuf,
+        email: &str,
+        access_token: &str,
+        expires_at: i64,
+    ) -> Self {
+        let store = Self {
+            token_url: token_url.to_string(),
+            ..Self::new()
+        };
+        *store.metadata_path.write().await = Some(metadata_path);
+        *store.client_id.write().await = Some("test-client-id".to_string());
+        *store.client_secret.write().await = Some("test-client-secret".to_string());
+        *store.session.write().await = Some(ActiveSession {
+            access_token: access_token.to_string(),
+            refresh_token: "test-refresh-token".to_string(),
+            expires_at,
+            user_info: UserInfo {
+                email: email.to_string(),
+                name: None,
+                picture: None,
+            },
+            scopes_granted: vec![],
+        });
+        store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_refresh_failure() {
+        let body = r#"{"error": "invalid_grant", "error_description": "Token has been expired or revoked."}"#;
+        assert!(matches!(
+            classify_refresh_failure(400, body),
+            RefreshError::Rejected { reason, .. } if reason == "invalid_grant"
+        ));
+        // A misconfigured client must not end the session
+        for error in ["invalid_client", "unauthorized_client"] {
+            let body = format!(r#"{{"error": "{}"}}"#, error);
+            assert!(matches!(
+                classify_refresh_failure(401, &body),
+                RefreshError::Misconfigured(_)
+            ));
+        }
+        assert!(matches!(
+            classify_refresh_failure(503, r#"{"error": "backend_error"}"#),
+            RefreshError::Transient(_)
+        ));
+        assert!(matches!(
+            classify_refresh_failure(400, "<html>Bad Request</html>"),
+            RefreshError::Transient(_)
+        ));
+    }
+
+    #[test]
+    fn test_refresh_form() {
+        let form = refresh_form("id", None, "rt");
+        assert!(!form.iter().any(|(key, _)| *key == "client_secret"));
+        let form = refresh_form("id", Some("secret"), "rt");
+        assert!(form.contains(&("client_secret", "secret")));
+    }
+
+    #[test]
+    fn test_refresh_delay() {
+        let now = 1_000_000;
+        assert_eq!(refresh_delay(now + 3600, now, 0), 3300);
+        assert_eq!(refresh_delay(now + 3600, now, 60), 3240);
+        assert_eq!(refresh_delay(now + 120, now, 0), 0);
+        assert_eq!(refresh_delay(now - 10, now, 30), 0);
+    }
+}
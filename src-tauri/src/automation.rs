@@ -0,0 +1,452 @@
+//! User automation scripts
+//!
+//! Scripts are Rhai files in `<app config dir>/scripts/` (`<name>.rhai`). A
+//! script subscribes to an event by defining its handler, which receives the
+//! event payload as a map:
+//!
+//! ```rhai
+//! fn on_priority_email(email) {
+//!     create_task("Reply: " + email.snippet);
+//! }
+//! fn on_task_overdue(task) {
+//!     send_notification("Overdue", task.title);
+//! }
+//! ```
+//!
+//! Events come from the background plan sync (see `planner`): a thread that
+//! became urgent, and a task that turned overdue.
+//!
+//! Scripts run sandboxed: no file or module access, no `eval`, bounded
+//! operations, call depth and sizes, and a wall-clock timeout. The only
+//! effects they can have are the whitelisted functions below, which queue
+//! actions the app performs after the handler returns:
+//! - `create_task(title)` / `create_task(title, "YYYY-MM-DD")` in the default
+//!   task list
+//! - `send_notification(title, body)`
+//! - `print(message)` to the app log
+//!
+//! New scripts are disabled until enabled with `set_script_enabled`.
+
+use crate::auth::TokenStore;
+use crate::events::DataEvent;
+use crate::google::types::{NewTask, Task};
+use crate::google::{invalidation, GoogleClient, TASKS_API_BASE};
+use crate::notifications;
+use crate::setup;
+use crate::storage::{self, LocalStorage};
+use chrono::NaiveDate;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+const AUTOMATION_STORE_FILE: &str = "automation.json";
+const ENABLED_SCRIPTS_KEY: &str = "enabled_scripts";
+const SCRIPTS_DIR: &str = "scripts";
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Wall-clock limit of one handler run
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_OPERATIONS: u64 = 500_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 10_000;
+const MAX_COLLECTION_SIZE: usize = 1_000;
+/// Actions one handler run may queue
+const MAX_ACTIONS: usize = 10;
+
+/// An event scripts can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptEvent {
+    /// An inbox thread became urgent
+    PriorityEmail,
+    /// A dated task turned overdue
+    TaskOverdue,
+}
+
+impl ScriptEvent {
+    const ALL: [ScriptEvent; 2] = [ScriptEvent::PriorityEmail, ScriptEvent::TaskOverdue];
+
+    /// Name of the script function handling the event
+    fn handler(self) -> &'static str {
+        match self {
+            ScriptEvent::PriorityEmail => "on_priority_email",
+            ScriptEvent::TaskOverdue => "on_task_overdue",
+        }
+    }
+}
+
+/// A script in the scripts directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptInfo {
+    pub name: String,
+    pub enabled: bool,
+    /// Events the script handles
+    pub events: Vec<ScriptEvent>,
+    /// Compile error, if the script doesn't parse
+    pub error: Option<String>,
+}
+
+/// Something a script asked the app to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScriptAction {
+    CreateTask { title: String, due: Option<String> },
+    Notify { title: String, body: String },
+}
+
+type ActionQueue = Arc<Mutex<Vec<ScriptAction>>>;
+
+fn queue(actions: &ActionQueue, action: ScriptAction) -> Result<(), Box<EvalAltResult>> {
+    let mut actions = actions.lock().map_err(|_| "Action queue poisoned")?;
+    if actions.len() >= MAX_ACTIONS {
+        return Err(format!("A handler may take at most {} actions", MAX_ACTIONS).into());
+    }
+    actions.push(action);
+    Ok(())
+}
+
+/// A sandboxed engine whose whitelisted functions push onto `actions`
+fn engine(script: &str, actions: &ActionQueue) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let started = Instant::now();
+    engine.on_progress(move |_| (started.elapsed() > SCRIPT_TIMEOUT).then_some(Dynamic::UNIT));
+    let name = script.to_string();
+    engine.on_print(move |message| println!("[script {}] {}", name, message));
+    let name = script.to_string();
+    engine.on_debug(move |message, _, _| println!("[script {}] {}", name, message));
+
+    let create = actions.clone();
+    engine.register_fn("create_task", move |title: &str| {
+        queue(
+            &create,
+            ScriptAction::CreateTask {
+                title: title.to_string(),
+                due: None,
+            },
+        )
+    });
+    let create = actions.clone();
+    engine.register_fn("create_task", move |title: &str, due: &str| {
+        if NaiveDate::parse_from_str(due, "%Y-%m-%d").is_err() {
+            return Err(format!("Invalid due date: {} (expected YYYY-MM-DD)", due).into());
+        }
+        queue(
+            &create,
+            ScriptAction::CreateTask {
+                title: title.to_string(),
+                due: Some(due.to_string()),
+            },
+        )
+    });
+    let notify = actions.clone();
+    engine.register_fn("send_notification", move |title: &str, body: &str| {
+        queue(
+            &notify,
+            ScriptAction::Notify {
+                title: title.to_string(),
+                body: body.to_string(),
+            },
+        )
+    });
+    engine
+}
+
+fn compile(source: &str) -> Result<AST, String> {
+    engine("", &ActionQueue::default())
+        .compile(source)
+        .map_err(|e| e.to_string())
+}
+
+fn handled_events(ast: &AST) -> Vec<ScriptEvent> {
+    ScriptEvent::ALL
+        .into_iter()
+        .filter(|event| {
+            ast.iter_functions()
+                .any(|f| f.name == event.handler() && f.params.len() == 1)
+        })
+        .collect()
+}
+
+/// Run a script's handler for `event`, returning the actions it queued
+///
+/// Top-level statements are not evaluated, only the handler. Nothing is
+/// returned when the handler fails: a failing script has no effects.
+fn run_handler(
+    name: &str,
+    source: &str,
+    event: ScriptEvent,
+    payload: Dynamic,
+) -> Result<Vec<ScriptAction>, String> {
+    let actions = ActionQueue::default();
+    let engine = engine(name, &actions);
+    let ast = engine.compile(source).map_err(|e| e.to_string())?;
+    if !handled_events(&ast).contains(&event) {
+        return Ok(Vec::new());
+    }
+
+    // The handler's return value is ignored
+    let options = CallFnOptions::new().eval_ast(false).rewind_scope(true);
+    let _: Dynamic = engine
+        .call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &ast,
+            event.handler(),
+            (payload,),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let actions = actions.lock().map_err(|_| "Action queue poisoned")?;
+    Ok(actions.clone())
+}
+
+// ============================================================================
+// Script Files
+// ============================================================================
+
+fn scripts_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(SCRIPTS_DIR))
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))
+}
+
+/// `(name, source)` of every script, by name
+fn read_scripts(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<(String, String)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION))
+        .filter_map(|p| {
+            let name = p.file_stem()?.to_string_lossy().into_owned();
+            match std::fs::read_to_string(&p) {
+                Ok(source) => Some((name, source)),
+                Err(e) => {
+                    eprintln!("Failed to read script {}: {}", p.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn enabled_scripts(app: &AppHandle) -> Result<Vec<String>, String> {
+    let store = storage::fs::settings_store(app, AUTOMATION_STORE_FILE)
+        .map_err(|e| format!("Failed to access automation store: {}", e))?;
+
+    Ok(store
+        .get(ENABLED_SCRIPTS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+// ============================================================================
+// Dispatch
+// ============================================================================
+
+async fn perform(app: &AppHandle, script: &str, action: ScriptAction) -> Result<(), String> {
+    match action {
+        ScriptAction::CreateTask { title, due } => {
+            let task = NewTask {
+                title,
+                notes: Some(format!("Created by script \"{}\"", script)),
+                due: due.map(|d| format!("{}T00:00:00.000Z", d)),
+            };
+            let list_id = setup::default_task_list(app);
+            let url = format!("{}/lists/{}/tasks", TASKS_API_BASE, list_id);
+            let created: Task = app
+                .state::<GoogleClient>()
+                .post(&url, &app.state::<TokenStore>(), &task)
+                .await?;
+            if let Some(task_id) = created.id {
+                invalidation::mutated(app, DataEvent::TaskCreated { list_id, task_id }).await;
+            }
+        }
+        ScriptAction::Notify { title, body } => {
+            app.notification()
+                .builder()
+                .title(&title)
+                .body(&body)
+                .show()
+                .map_err(|e| e.to_string())?;
+            notifications::record_history(
+                &app.state::<TokenStore>(),
+                &app.state::<LocalStorage>(),
+                Some("automation"),
+                &title,
+                Some(&body),
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Run every enabled script handling `event` and perform what they asked for
+///
+/// Script failures are logged and never affect the caller.
+pub async fn dispatch<T: Serialize>(app: &AppHandle, event: ScriptEvent, payload: &T) {
+    let enabled = match enabled_scripts(app) {
+        Ok(enabled) if !enabled.is_empty() => enabled,
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("Failed to load enabled scripts: {}", e);
+            return;
+        }
+    };
+    let payload = match rhai::serde::to_dynamic(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            eprintln!("Failed to convert {:?} payload: {}", event, e);
+            return;
+        }
+    };
+    let Ok(dir) = scripts_dir(app) else {
+        return;
+    };
+
+    for (name, source) in read_scripts(&dir) {
+        if !enabled.contains(&name) {
+            continue;
+        }
+        let payload = payload.clone();
+        let script = name.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            run_handler(&script, &source, event, payload)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+
+        match result {
+            Ok(actions) => {
+                for action in actions {
+                    if let Err(e) = perform(app, &name, action).await {
+                        eprintln!("Script {} action failed: {}", name, e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Script {} failed on {:?}: {}", name, event, e),
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// List the scripts in the scripts directory
+#[tauri::command]
+pub async fn list_scripts(app: AppHandle) -> Result<Vec<ScriptInfo>, String> {
+    crate::perf::trace_command!();
+    let enabled = enabled_scripts(&app)?;
+    Ok(read_scripts(&scripts_dir(&app)?)
+        .into_iter()
+        .map(|(name, source)| {
+            let (events, error) = match compile(&source) {
+                Ok(ast) => (handled_events(&ast), None),
+                Err(e) => (Vec::new(), Some(e)),
+            };
+            ScriptInfo {
+                enabled: enabled.contains(&name),
+                name,
+                events,
+                error,
+            }
+        })
+        .collect())
+}
+
+/// Enable or disable a script; a script must compile to be enabled
+#[tauri::command]
+pub async fn set_script_enabled(app: AppHandle, name: String, enabled: bool) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if enabled {
+        let (_, source) = read_scripts(&scripts_dir(&app)?)
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| format!("Script not found: {}", name))?;
+        compile(&source).map_err(|e| format!("Script {} doesn't compile: {}", name, e))?;
+    }
+
+    let mut scripts = enabled_scripts(&app)?;
+    scripts.retain(|n| *n != name);
+    if enabled {
+        scripts.push(name);
+    }
+    let store = storage::fs::settings_store(&app, AUTOMATION_STORE_FILE)
+        .map_err(|e| format!("Failed to access automation store: {}", e))?;
+    store.set(ENABLED_SCRIPTS_KEY, serde_json::json!(scripts));
+    storage::fs::save_store(&app, AUTOMATION_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save automation store: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str, event: ScriptEvent) -> Result<Vec<ScriptAction>, String> {
+        let payload = rhai::serde::to_dynamic(serde_json::json!({
+            "title": "Send invoice",
+            "snippet": "Contract due",
+        }))
+        .unwrap();
+        run_handler("test", source, event, payload)
+    }
+
+    #[test]
+    fn test_run_handler_sandbox() {
+        let source = r#"
+            create_task("never runs: top-level code is skipped");
+            fn on_task_overdue(task) {
+                send_notification("Overdue", task.title);
+                create_task("Follow up: " + task.title, "2026-01-16");
+            }
+        "#;
+        let ast = compile(source).unwrap();
+        assert_eq!(handled_events(&ast), [ScriptEvent::TaskOverdue]);
+        assert_eq!(
+            run(source, ScriptEvent::TaskOverdue).unwrap(),
+            [
+                ScriptAction::Notify {
+                    title: "Overdue".to_string(),
+                    body: "Send invoice".to_string(),
+                },
+                ScriptAction::CreateTask {
+                    title: "Follow up: Send invoice".to_string(),
+                    due: Some("2026-01-16".to_string()),
+                },
+            ]
+        );
+        assert_eq!(run(source, ScriptEvent::PriorityEmail).unwrap(), []);
+
+        // Runaway, disallowed and over-eager scripts fail without effects
+        let looping = "fn on_task_overdue(task) { loop { } }";
+        assert!(run(looping, ScriptEvent::TaskOverdue).is_err());
+        assert!(compile(r#"fn on_task_overdue(t) { eval("1") }"#).is_err());
+        let importing = r#"fn on_task_overdue(t) { import "secrets" as s; }"#;
+        assert!(run(importing, ScriptEvent::TaskOverdue).is_err());
+        let spamming = r#"fn on_task_overdue(t) { for i in 0..20 { create_task("x") } }"#;
+        assert!(run(spamming, ScriptEvent::TaskOverdue).is_err());
+        let bad_date = r#"fn on_task_overdue(t) { create_task("x", "tomorrow") }"#;
+        assert!(run(bad_date, ScriptEvent::TaskOverdue).is_err());
+    }
+}
@@ -8,6 +8,23 @@ use std::collections::HashMap;
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
 
+// ============================================================================
+// Well-known Keys
+// ============================================================================
+//
+// Keys are scoped per account with `AccountContext::cache_key`; the frontend's
+// cache commands scope theirs to the signed-in account.
+
+/// Inbox summary written by `get_inbox_summary`
+pub const INBOX_SUMMARY_KEY: &str = "gmail:inbox_summary";
+/// Today's processed events written by `get_today_events`
+pub const TODAY_EVENTS_KEY: &str = "calendar:today_events";
+/// Prefix for per-list tasks written by `get_tasks` (suffixed with the list ID)
+pub const TASKS_KEY_PREFIX: &str = "tasks:list:";
+
+/// TTL for API responses cached by the backend
+pub const API_RESPONSE_TTL_SECS: u64 = 1800;
+
 /// A cache entry with expiration
 #[derive(Debug)]
 struct CacheEntry {
@@ -82,6 +99,30 @@ impl RustCache {
         self.store.write().ok()?.remove(key).map(|e| e.value)
     }
 
+    /// Get all live values whose key starts with `prefix`
+    pub fn get_prefix(&self, prefix: &str) -> Vec<(String, String)> {
+        match self.store.read() {
+            Ok(store) => store
+                .iter()
+                .filter(|(k, e)| k.starts_with(prefix) && !e.is_expired())
+                .map(|(k, e)| (k.clone(), e.value.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Serialize and store a value under `key`
+    pub fn set_json<T: Serialize>(&self, key: &str, value: &T, ttl_seconds: u64) {
+        if let Ok(json) = serde_json::to_string(value) {
+            self.set(key, json, ttl_seconds);
+        }
+    }
+
+    /// Get and deserialize a value stored under `key`
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_str(&self.get(key)?).ok()
+    }
+
     /// Invalidate all entries matching a pattern
     /// Pattern supports simple prefix matching with *
     pub fn invalidate_pattern(&self, pattern: &str) -> usize {
@@ -139,6 +180,7 @@ impl RustCache {
     }
 
     /// Clear all entries
+    #[allow(dead_code)]
     pub fn clear(&self) {
         if let Ok(mut store) = self.store.write() {
             store.clear();
@@ -179,6 +221,9 @@ pub struct CacheStats {
 // Tauri Commands
 // ============================================================================
 
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
 use tauri::State;
 
 /// Global cache state managed by Tauri
@@ -190,45 +235,93 @@ impl Default for CacheState {
     }
 }
 
-/// Get a value from the cache
+/// The signed-in account's key for a frontend `key`; signed out, keys share
+/// an account-less namespace
+async fn frontend_key(token_store: &TokenStore, key: &str) -> String {
+    token_store
+        .account_context()
+        .await
+        .unwrap_or_else(|_| AccountContext::new(""))
+        .cache_key(key)
+}
+
+/// Get a value from the cache (fails while the app is locked)
 #[tauri::command]
-pub fn cache_get(cache: State<'_, CacheState>, key: &str) -> Option<String> {
-    cache.0.get(key)
+pub async fn cache_get(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+    key: String,
+) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    Ok(cache.0.get(&frontend_key(&token_store, &key).await))
 }
 
 /// Set a value in the cache with TTL (in seconds)
 #[tauri::command]
-pub fn cache_set(cache: State<'_, CacheState>, key: &str, value: String, ttl_seconds: u64) {
-    cache.0.set(key, value, ttl_seconds);
+pub async fn cache_set(
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+    key: String,
+    value: String,
+    ttl_seconds: u64,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    cache
+        .0
+        .set(&frontend_key(&token_store, &key).await, value, ttl_seconds);
+    Ok(())
 }
 
 /// Remove a value from the cache
 #[tauri::command]
-pub fn cache_remove(cache: State<'_, CacheState>, key: &str) -> Option<String> {
-    cache.0.remove(key)
+pub async fn cache_remove(
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+    key: String,
+) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    Ok(cache.0.remove(&frontend_key(&token_store, &key).await))
 }
 
 /// Invalidate entries matching a pattern (supports * for prefix matching)
 #[tauri::command]
-pub fn cache_invalidate(cache: State<'_, CacheState>, pattern: &str) -> usize {
-    cache.0.invalidate_pattern(pattern)
+pub async fn cache_invalidate(
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+    pattern: String,
+) -> Result<usize, String> {
+    crate::perf::trace_command!();
+    Ok(cache
+        .0
+        .invalidate_pattern(&frontend_key(&token_store, &pattern).await))
 }
 
-/// Clear all cache entries
+/// Clear the signed-in account's cache entries
 #[tauri::command]
-pub fn cache_clear(cache: State<'_, CacheState>) {
-    cache.0.clear();
+pub async fn cache_clear(
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    cache
+        .0
+        .invalidate_pattern(&frontend_key(&token_store, "*").await);
+    Ok(())
 }
 
 /// Get cache statistics
 #[tauri::command]
 pub fn cache_stats(cache: State<'_, CacheState>) -> CacheStats {
+    crate::perf::trace_command!();
     cache.0.stats()
 }
 
 /// Cleanup expired entries (called periodically)
 #[tauri::command]
 pub fn cache_cleanup(cache: State<'_, CacheState>) -> usize {
+    crate::perf::trace_command!();
     cache.0.cleanup_expired()
 }
 
@@ -266,4 +359,22 @@ mod tests {
         assert_eq!(cache.get("plan:2026-01-13"), None);
         assert_eq!(cache.get("email:123"), Some("email1".to_string()));
     }
+
+    #[test]
+    fn test_cache_get_prefix() {
+        let cache = RustCache::new();
+        cache.set("tasks:list:a", "a".to_string(), 60);
+        cache.set("tasks:list:b", "b".to_string(), 60);
+        cache.set("email:123", "email1".to_string(), 60);
+
+        let mut values = cache.get_prefix("tasks:list:");
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                ("tasks:list:a".to_string(), "a".to_string()),
+                ("tasks:list:b".to_string(), "b".to_string())
+            ]
+        );
+    }
 }
@@ -0,0 +1,401 @@
+//! Registry of user-invokable actions
+//!
+//! `list_available_actions` describes the commands the command palette (and
+//! future scripting) can invoke: the command name to pass to `invoke`, a
+//! JSON Schema of its arguments, and search keywords. Argument names are the
+//! `invoke` keys, i.e. the camelCase form of the Rust parameter names.
+//!
+//! Actions acting on a selected thread, task or event are only listed when
+//! the context has such a selection; arguments marked `from_selection` are
+//! filled from it. A test checks every action against the handlers registered
+//! in `lib.rs`, so the registry can't drift from the command set.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// What an action operates on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    Thread,
+    Task,
+    Event,
+}
+
+/// Where the palette was opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionContext {
+    /// The selected item, if any
+    #[serde(default)]
+    pub selection: Option<Selection>,
+}
+
+/// Type of an action argument
+#[derive(Debug, Clone, Copy)]
+enum ArgType {
+    String,
+    Integer,
+    Boolean,
+    /// One of the listed strings
+    Enum(&'static [&'static str]),
+    /// A structured value (see the command's documentation)
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ArgSpec {
+    name: &'static str,
+    kind: ArgType,
+    required: bool,
+    /// Filled from the selected item
+    from_selection: bool,
+    description: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActionSpec {
+    /// Handler path as registered in `lib.rs`
+    path: &'static str,
+    title: &'static str,
+    keywords: &'static [&'static str],
+    /// Required selection (None for global actions)
+    selection: Option<Selection>,
+    args: &'static [ArgSpec],
+}
+
+/// An invokable action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableAction {
+    /// Command name to pass to `invoke`
+    pub name: String,
+    pub title: String,
+    pub keywords: Vec<String>,
+    pub selection: Option<Selection>,
+    /// JSON Schema of the `invoke` arguments
+    pub args_schema: Value,
+    /// Arguments the palette fills from the selection
+    pub selection_args: Vec<String>,
+}
+
+const fn arg(name: &'static str, kind: ArgType, description: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        kind,
+        required: true,
+        from_selection: false,
+        description,
+    }
+}
+
+const fn optional(name: &'static str, kind: ArgType, description: &'static str) -> ArgSpec {
+    ArgSpec {
+        required: false,
+        ..arg(name, kind, description)
+    }
+}
+
+const fn selected(name: &'static str, description: &'static str) -> ArgSpec {
+    ArgSpec {
+        from_selection: true,
+        ..arg(name, ArgType::String, description)
+    }
+}
+
+const LIST_ID: ArgSpec = selected("listId", "Task list of the selected task");
+const TASK_ID: ArgSpec = selected("taskId", "Selected task");
+const THREAD_ID: ArgSpec = selected("threadId", "Selected thread");
+const EVENT_ID: ArgSpec = selected("eventId", "Selected event");
+const DATE: ArgSpec = arg("date", ArgType::String, "Day, YYYY-MM-DD");
+
+const ACTIONS: &[ActionSpec] = &[
+    // Global
+    ActionSpec {
+        path: "google::tasks::create_task",
+        title: "New task",
+        keywords: &["add", "todo", "create"],
+        selection: None,
+        args: &[
+            arg("listId", ArgType::String, "Task list"),
+            arg("task", ArgType::Object, "{ title, notes?, due? }"),
+            optional(
+                "sourceThreadId",
+                ArgType::String,
+                "Email thread the task came from",
+            ),
+        ],
+    },
+    ActionSpec {
+        path: "google::tasks::get_smart_list",
+        title: "Open smart list",
+        keywords: &["today", "overdue", "waiting", "priority"],
+        selection: None,
+        args: &[arg(
+            "name",
+            ArgType::Enum(&["today", "overdue", "waiting_on_others", "high_priority"]),
+            "Smart list",
+        )],
+    },
+    ActionSpec {
+        path: "outbox::send_message",
+        title: "Compose email",
+        keywords: &["send", "mail", "write", "new"],
+        selection: None,
+        args: &[arg(
+            "message",
+            ArgType::Object,
+            "{ to, cc?, subject, body, thread_id?, in_reply_to? }",
+        )],
+    },
+    ActionSpec {
+        path: "triage::start_session",
+        title: "Start inbox triage",
+        keywords: &["inbox", "zero", "process", "review"],
+        selection: None,
+        args: &[
+            optional("query", ArgType::String, "Gmail search query"),
+            optional("maxItems", ArgType::Integer, "Threads in the session"),
+        ],
+    },
+    ActionSpec {
+        path: "planner::auto_schedule_tasks",
+        title: "Time-block tasks",
+        keywords: &["schedule", "calendar", "plan", "focus"],
+        selection: None,
+        args: &[
+            DATE,
+            arg("tasks", ArgType::Array, "Tasks to place"),
+            optional("workingHours", ArgType::Object, "{ start_hour, end_hour }"),
+            optional("confirm", ArgType::Boolean, "Create the events"),
+            optional("blocks", ArgType::Array, "Previewed blocks to create"),
+        ],
+    },
+    ActionSpec {
+        path: "daily_note::get_daily_note",
+        title: "Open daily note",
+        keywords: &["note", "summary", "today", "briefing"],
+        selection: None,
+        args: &[DATE],
+    },
+    ActionSpec {
+        path: "sync::request_sync",
+        title: "Sync now",
+        keywords: &["refresh", "reload", "update"],
+        selection: None,
+        args: &[],
+    },
+    ActionSpec {
+        path: "ical::refresh_ical_feeds",
+        title: "Refresh calendar subscriptions",
+        keywords: &["ical", "feeds", "refresh"],
+        selection: None,
+        args: &[],
+    },
+    ActionSpec {
+        path: "rules::suggest_rules",
+        title: "Suggest inbox rules",
+        keywords: &["filters", "automate", "cleanup"],
+        selection: None,
+        args: &[],
+    },
+    ActionSpec {
+        path: "theme::set_theme",
+        title: "Change theme",
+        keywords: &["dark", "light", "appearance", "night", "day"],
+        selection: None,
+        args: &[
+            arg(
+                "mode",
+                ArgType::Enum(&["day", "night", "automatic"]),
+                "Theme mode",
+            ),
+            arg("name", ArgType::String, "Theme name"),
+        ],
+    },
+    ActionSpec {
+        path: "app_lock::lock_app",
+        title: "Lock app",
+        keywords: &["privacy", "passcode", "away"],
+        selection: None,
+        args: &[],
+    },
+    ActionSpec {
+        path: "updates::check_for_updates",
+        title: "Check for updates",
+        keywords: &["version", "upgrade", "release"],
+        selection: None,
+        args: &[],
+    },
+    ActionSpec {
+        path: "diagnostics::run_checks",
+        title: "Run diagnostics",
+        keywords: &["debug", "health", "troubleshoot"],
+        selection: None,
+        args: &[],
+    },
+    // Tasks
+    ActionSpec {
+        path: "google::tasks::complete_task",
+        title: "Complete task",
+        keywords: &["done", "finish", "check"],
+        selection: Some(Selection::Task),
+        args: &[LIST_ID, TASK_ID],
+    },
+    ActionSpec {
+        path: "google::tasks::reopen_task",
+        title: "Reopen task",
+        keywords: &["undo", "uncheck"],
+        selection: Some(Selection::Task),
+        args: &[LIST_ID, TASK_ID],
+    },
+    ActionSpec {
+        path: "google::tasks::delete_task",
+        title: "Delete task",
+        keywords: &["remove", "trash"],
+        selection: Some(Selection::Task),
+        args: &[LIST_ID, TASK_ID],
+    },
+    ActionSpec {
+        path: "google::tasks::archive_linked_thread",
+        title: "Archive source email",
+        keywords: &["thread", "inbox", "done"],
+        selection: Some(Selection::Task),
+        args: &[LIST_ID, TASK_ID],
+    },
+    // Threads
+    ActionSpec {
+        path: "google::calendar::create_event_from_thread",
+        title: "Create event from email",
+        keywords: &["meeting", "calendar", "invite", "schedule"],
+        selection: Some(Selection::Thread),
+        args: &[
+            THREAD_ID,
+            optional("durationMinutes", ArgType::Integer, "Event length"),
+        ],
+    },
+    ActionSpec {
+        path: "google::gmail::get_thread_timeline",
+        title: "Show thread timeline",
+        keywords: &["history", "replies", "latency"],
+        selection: Some(Selection::Thread),
+        args: &[THREAD_ID],
+    },
+    ActionSpec {
+        path: "google::gmail::open_thread_in_gmail",
+        title: "Open in Gmail",
+        keywords: &["browser", "web"],
+        selection: Some(Selection::Thread),
+        args: &[THREAD_ID],
+    },
+    // Events
+    ActionSpec {
+        path: "google::calendar::get_rsvp_rollup",
+        title: "Show RSVPs",
+        keywords: &["attendees", "accepted", "declined", "responses"],
+        selection: Some(Selection::Event),
+        args: &[EVENT_ID],
+    },
+];
+
+impl ArgType {
+    fn schema(self) -> Value {
+        match self {
+            ArgType::String => json!({ "type": "string" }),
+            ArgType::Integer => json!({ "type": "integer", "minimum": 0 }),
+            ArgType::Boolean => json!({ "type": "boolean" }),
+            ArgType::Enum(values) => json!({ "type": "string", "enum": values }),
+            ArgType::Object => json!({ "type": "object" }),
+            ArgType::Array => json!({ "type": "array" }),
+        }
+    }
+}
+
+impl ActionSpec {
+    /// Command name: the last segment of the handler path
+    fn name(&self) -> &'static str {
+        self.path.rsplit("::").next().unwrap_or(self.path)
+    }
+
+    fn args_schema(&self) -> Value {
+        let mut properties = Map::new();
+        for arg in self.args {
+            let mut schema = arg.kind.schema();
+            schema["description"] = json!(arg.description);
+            properties.insert(arg.name.to_string(), schema);
+        }
+        let required: Vec<&str> = self
+            .args
+            .iter()
+            .filter(|a| a.required)
+            .map(|a| a.name)
+            .collect();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+
+    fn available(&self) -> AvailableAction {
+        AvailableAction {
+            name: self.name().to_string(),
+            title: self.title.to_string(),
+            keywords: self.keywords.iter().map(|k| k.to_string()).collect(),
+            selection: self.selection,
+            args_schema: self.args_schema(),
+            selection_args: self
+                .args
+                .iter()
+                .filter(|a| a.from_selection)
+                .map(|a| a.name.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// List the actions invokable in `context`: global ones, plus the ones for
+/// the selected item
+#[tauri::command]
+pub fn list_available_actions(context: Option<ActionContext>) -> Vec<AvailableAction> {
+    crate::perf::trace_command!();
+    let selection = context.unwrap_or_default().selection;
+    ACTIONS
+        .iter()
+        .filter(|a| a.selection.is_none() || a.selection == selection)
+        .map(ActionSpec::available)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_match_registered_commands() {
+        let lib = include_str!("lib.rs");
+        let mut names = std::collections::HashSet::new();
+        for action in ACTIONS {
+            assert!(
+                lib.contains(&format!("            {},\n", action.path)),
+                "{} is not a registered command",
+                action.path
+            );
+            assert!(names.insert(action.name()), "duplicate {}", action.name());
+        }
+
+        let global = list_available_actions(None);
+        assert!(global.iter().all(|a| a.selection.is_none()));
+        let task = list_available_actions(Some(ActionContext {
+            selection: Some(Selection::Task),
+        }));
+        let complete = task.iter().find(|a| a.name == "complete_task").unwrap();
+        assert_eq!(complete.selection_args, ["listId", "taskId"]);
+        assert_eq!(
+            complete.args_schema["required"],
+            json!(["listId", "taskId"])
+        );
+        assert!(!task.iter().any(|a| a.name == "get_rsvp_rollup"));
+        assert_eq!(task.len(), global.len() + 4);
+    }
+}
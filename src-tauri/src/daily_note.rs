@@ -0,0 +1,758 @@
+//! Scheduled daily note generation
+//!
+//! `spawn_note_schedule` runs the whole note pipeline at the configured
+//! morning time, even while no window is open: sync today's unread inbox,
+//! dated tasks and meetings, `prepare_note_context`, generate the note and
+//! save it, then fire the "plan_ready" notification. The note is ready when
+//! the user sits down instead of being generated on first open.
+//!
+//! The background run writes the note locally from the prepared context. The
+//! AI-written note comes from the selected AI provider (`ai::generate_ai_note`)
+//! through the frontend, which stores it with `save_daily_note`, or is pushed by
+//! the backend (see `push`); a day that already has a note is never
+//! regenerated, and an AI note replaces a local one.
+//!
+//! If the app starts after the scheduled time, the note is generated right
+//! away.
+//!
+//! Notes can be encrypted at rest (`set_note_encryption`) with a key derived
+//! from a per-account secret in the OS keychain (see `storage::crypto`).
+//! Reads open sealed and plaintext notes alike, so the commands don't change;
+//! turning encryption on seals the existing notes, and a plaintext note read
+//! while it is on (e.g. another account's) is sealed then.
+
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::cache::CacheState;
+use crate::data_pipeline::{
+    self, EmailSummary, EventSummary, NoteGenerationContext, TaskSummary, ValidatedNote,
+    ValidatedSection,
+};
+use crate::events::{self, DataEvent};
+use crate::google::mailbox::Mailbox;
+use crate::google::types::{CalendarEvent, GmailThreadDetail};
+use crate::google::{calendar, gmail, GoogleClient, GMAIL_API_BASE};
+use crate::planner::{self, PlanSegment};
+use crate::processing::{self, PriorityInput};
+use crate::storage::crypto::{self, RecordKey};
+use crate::storage::{self, LocalStorage, DAILY_NOTES};
+use crate::{analytics, holidays, notifications};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+const DAILY_NOTE_STORE_FILE: &str = "daily_note.json";
+const SCHEDULE_KEY: &str = "schedule";
+const ENCRYPTION_KEY: &str = "encryption";
+
+/// How often the scheduler checks whether today's note is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Wait before retrying a failed run
+const RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+/// Unread threads synced for the note
+const NOTE_EMAILS: u32 = 20;
+
+/// When the daily note is generated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteSchedule {
+    pub enabled: bool,
+    /// Local time, HH:MM
+    pub time: String,
+}
+
+impl Default for NoteSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            time: "07:30".to_string(),
+        }
+    }
+}
+
+/// Who wrote a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteSource {
+    /// Generated on this device from the prepared context
+    Local,
+    /// Written by the AI backend
+    Ai,
+}
+
+/// A saved daily note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyNote {
+    pub date: String,
+    pub source: NoteSource,
+    pub note: ValidatedNote,
+    pub generated_at_ms: i64,
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| format!("Invalid time: {} (expected HH:MM)", time))
+}
+
+/// The day whose note should exist by `now`, if the scheduled time has passed
+fn due_date(schedule: &NoteSchedule, now: NaiveDateTime) -> Option<NaiveDate> {
+    let time = parse_time(&schedule.time).ok()?;
+    (schedule.enabled && now.time() >= time).then(|| now.date())
+}
+
+fn load_schedule(app: &AppHandle) -> Result<NoteSchedule, String> {
+    let store = storage::fs::settings_store(app, DAILY_NOTE_STORE_FILE)
+        .map_err(|e| format!("Failed to access daily note store: {}", e))?;
+
+    Ok(store
+        .get(SCHEDULE_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+// ============================================================================
+// Pipeline
+// ============================================================================
+
+/// Owned page of inbox thread ids
+#[derive(Debug, Deserialize)]
+struct ThreadPage {
+    #[serde(default)]
+    threads: Vec<ThreadRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreadRef {
+    id: String,
+}
+
+/// Summary of a thread's latest message
+fn email_summary(thread: &GmailThreadDetail, own_email: &str, now_ms: i64) -> Option<EmailSummary> {
+    let messages = thread.messages.as_deref()?;
+    let message = messages.last()?;
+    let (from_name, from_email) = analytics::parse_from_header(analytics::header(message, "From")?);
+    let subject = analytics::header(message, "Subject")
+        .unwrap_or("(No subject)")
+        .to_string();
+    let timestamp_ms = message.internal_date.as_deref()?.parse().ok()?;
+    let is_unread = message
+        .label_ids
+        .as_ref()
+        .is_some_and(|labels| labels.iter().any(|l| l == "UNREAD"));
+
+    let to = analytics::header(message, "To").unwrap_or_default();
+    let cc = analytics::header(message, "Cc").unwrap_or_default();
+    let recipient_count = calendar::split_addresses(to).len() + calendar::split_addresses(cc).len();
+    let priority_score = processing::calculate_priority_score(PriorityInput {
+        is_unread,
+        age_hours: (now_ms - timestamp_ms) as f64 / 3_600_000.0,
+        from_known_contact: false,
+        has_urgent_keywords: processing::has_urgent_keywords(format!(
+            "{} {}",
+            subject, message.snippet
+        )),
+        recipient_count,
+        is_direct: to.to_lowercase().contains(own_email),
+        thread_size: messages.len(),
+    });
+
+    Some(EmailSummary {
+        id: thread.id.clone(),
+        subject,
+        from_name: if from_name.is_empty() {
+            from_email.clone()
+        } else {
+            from_name
+        },
+        from_email,
+        snippet: message.snippet.clone(),
+        timestamp_ms,
+        is_unread,
+        priority_score: Some(priority_score),
+    })
+}
+
+/// Start/end of an event in epoch ms, and whether it is all-day
+fn event_bounds(event: &CalendarEvent) -> Option<(i64, i64, bool)> {
+    let ms = |dt: &crate::google::types::EventDateTime| -> Option<i64> {
+        if let Some(date_time) = dt.date_time.as_deref() {
+            return DateTime::parse_from_rfc3339(date_time)
+                .ok()
+                .map(|d| d.timestamp_millis());
+        }
+        let date = NaiveDate::parse_from_str(dt.date.as_deref()?, "%Y-%m-%d").ok()?;
+        Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()
+            .map(|d| d.timestamp_millis())
+    };
+    let start = event.start.as_ref()?;
+    let start_ms = ms(start)?;
+    let end_ms = event.end.as_ref().and_then(ms).unwrap_or(start_ms);
+    Some((start_ms, end_ms, start.date_time.is_none()))
+}
+
+pub(crate) fn event_summary(event: CalendarEvent) -> Option<EventSummary> {
+    let (start_ms, end_ms, is_all_day) = event_bounds(&event)?;
+    let has_meeting_link = event.hangout_link.is_some()
+        || event
+            .conference_data
+            .as_ref()
+            .and_then(|c| c.entry_points.as_ref())
+            .is_some_and(|points| !points.is_empty())
+        || event
+            .location
+            .as_deref()
+            .and_then(processing::find_meeting_link)
+            .is_some();
+    Some(EventSummary {
+        id: event.id,
+        title: event.summary.unwrap_or_else(|| "(No title)".to_string()),
+        start_ms,
+        end_ms,
+        is_all_day,
+        has_meeting_link,
+        attendee_count: event.attendees.map(|a| a.len()).unwrap_or(0),
+    })
+}
+
+/// Sync today's data and prepare the note context
+async fn note_context(app: &AppHandle, today: NaiveDate) -> Result<NoteGenerationContext, String> {
+    let token_store = app.state::<TokenStore>();
+    let client = app.state::<GoogleClient>();
+    let account = token_store.account_context().await?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+
+    let url = format!(
+        "{}/users/me/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        NOTE_EMAILS,
+        urlencoding::encode("in:inbox is:unread")
+    );
+    let page: ThreadPage = client.get_background(&url, &token_store).await?;
+    let thread_ids: Vec<String> = page.threads.into_iter().map(|t| t.id).collect();
+    let hydration = gmail::hydrate_threads(
+        &token_store,
+        &client,
+        &Mailbox::Own,
+        &thread_ids,
+        gmail::DEFAULT_HYDRATION_PARALLELISM,
+    )
+    .await;
+    let own_email = account.email().to_lowercase();
+    let emails = hydration
+        .threads
+        .iter()
+        .filter_map(|t| email_summary(t, &own_email, now_ms))
+        .collect();
+
+    let tasks = planner::due_task_items(&token_store, &client)
+        .await?
+        .into_iter()
+        .map(|item| TaskSummary {
+            id: item.id,
+            title: item.title,
+            due_ms: Some(item.at_ms),
+            completed: false,
+            list_name: None,
+        })
+        .collect();
+
+    let window = planner::current_plan_window(app)?;
+    let events =
+        calendar::events_between(&token_store, &client, window.start_ms, window.end_ms, None)
+            .await?
+            .into_iter()
+            .filter(|e| e.status.as_deref() != Some("cancelled"))
+            .filter(|e| calendar::working_location_of(e).is_none())
+            .filter_map(event_summary)
+            .collect();
+
+    // A missing holiday notice never blocks the note
+    let holiday_notice = match today.succ_opt() {
+        Some(tomorrow) => holidays::holiday_on(
+            app,
+            &client,
+            &app.state::<CacheState>(),
+            &token_store,
+            tomorrow,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to check tomorrow's holidays: {}", e);
+            None
+        })
+        .as_ref()
+        .map(holidays::tomorrow_notice),
+        None => None,
+    };
+
+    Ok(data_pipeline::prepare_note_context(
+        emails,
+        tasks,
+        events,
+        holiday_notice,
+        Some(window),
+    ))
+}
+
+fn note_section(id: &str, section_type: &str, title: &str, lines: Vec<String>) -> ValidatedSection {
+    ValidatedSection {
+        id: id.to_string(),
+        section_type: section_type.to_string(),
+        title: title.to_string(),
+        content: lines.join("\n"),
+    }
+}
+
+/// Write the day's note from the prepared context, without the AI backend
+pub fn generate_local_note(date: &str, context: &NoteGenerationContext) -> ValidatedNote {
+    let mut sections = Vec::new();
+
+    if let Some(notice) = &context.holiday_notice {
+        sections.push(note_section(
+            "heads_up",
+            "custom",
+            "Heads up",
+            vec![notice.clone()],
+        ));
+    }
+
+    let mut schedule = vec![format!(
+        "{} meetings, {:.1} h booked",
+        context.meeting_count, context.total_event_hours
+    )];
+    schedule.extend(context.todays_events.iter().map(|e| match e.segment {
+        PlanSegment::Overnight => format!("- {} (overnight): {}", e.time, e.title),
+        PlanSegment::Today => format!("- {}: {}", e.time, e.title),
+        PlanSegment::Tomorrow => format!("- Tomorrow {}: {}", e.time, e.title),
+    }));
+    sections.push(note_section(
+        "schedule",
+        "meeting_notes",
+        "Schedule",
+        schedule,
+    ));
+
+    let mut tasks = vec![format!(
+        "{} outstanding, {} overdue",
+        context.outstanding_tasks.len(),
+        context.overdue_count
+    )];
+    tasks.extend(context.outstanding_tasks.iter().map(|t| match &t.due {
+        Some(due) => format!("- {} ({})", t.title, due),
+        None => format!("- {}", t.title),
+    }));
+    sections.push(note_section("tasks", "task_recap", "Tasks", tasks));
+
+    let mut inbox = vec![format!(
+        "{} unread of {}",
+        context.unread_count, context.total_emails
+    )];
+    inbox.extend(context.priority_emails.iter().map(|e| {
+        let reply = if e.needs_reply { ", needs reply" } else { "" };
+        format!("- {} from {} ({}{})", e.subject, e.from, e.age, reply)
+    }));
+    sections.push(note_section("inbox", "email_summary", "Inbox", inbox));
+
+    ValidatedNote {
+        id: format!("local-{}", date),
+        date: date.to_string(),
+        sections,
+    }
+}
+
+/// Show the "plan_ready" notification for a generated note
+async fn notify_plan_ready(app: &AppHandle, context: &NoteGenerationContext) {
+    let title = "Your day is ready";
+    let body = format!(
+        "{} meetings, {} tasks, {} unread emails",
+        context.meeting_count,
+        context.outstanding_tasks.len(),
+        context.unread_count
+    );
+    let shown = app
+        .notification()
+        .builder()
+        .title(title)
+        .body(&body)
+        .sound("Glass")
+        .show();
+    if let Err(e) = shown {
+        eprintln!("Failed to show plan ready notification: {}", e);
+        return;
+    }
+    notifications::record_history(
+        &app.state::<TokenStore>(),
+        &app.state::<LocalStorage>(),
+        Some("plan_ready"),
+        title,
+        Some(&body),
+    )
+    .await;
+}
+
+fn save_note(app: &AppHandle, account: &AccountContext, note: &DailyNote) -> Result<(), String> {
+    let value =
+        serde_json::to_value(note).map_err(|e| format!("Failed to serialize note: {}", e))?;
+    let value = if load_encryption(app)?.enabled {
+        crypto::encrypt(&note_key(account, true)?.ok_or("Missing note key")?, &value)?
+    } else {
+        value
+    };
+    app.state::<LocalStorage>()
+        .put(account, DAILY_NOTES, &note.date, value)
+}
+
+/// Validate and save an AI-written note, replacing any note of the same day
+pub(crate) fn save_ai_note(
+    app: &AppHandle,
+    account: &AccountContext,
+    note: serde_json::Value,
+) -> Result<DailyNote, String> {
+    let note = data_pipeline::validate_note_schema(note)?;
+    let note = DailyNote {
+        date: note.date.clone(),
+        source: NoteSource::Ai,
+        note,
+        generated_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    save_note(app, account, &note)?;
+    Ok(note)
+}
+
+/// Run the pipeline for `today` unless its note already exists
+///
+/// Returns whether a note was generated.
+pub(crate) async fn run_pipeline(app: &AppHandle, today: NaiveDate) -> Result<bool, String> {
+    let account = app.state::<TokenStore>().account_context().await?;
+    let date = today.format("%Y-%m-%d").to_string();
+    if app
+        .state::<LocalStorage>()
+        .get(&account, DAILY_NOTES, &date)?
+        .is_some()
+    {
+        return Ok(false);
+    }
+
+    let context = note_context(app, today).await?;
+    let note = DailyNote {
+        date: date.clone(),
+        source: NoteSource::Local,
+        note: generate_local_note(&date, &context),
+        generated_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    save_note(app, &account, &note)?;
+
+    notify_plan_ready(app, &context).await;
+    events::emit(app, DataEvent::NoteReady { date });
+    Ok(true)
+}
+
+/// Start the daily note scheduler
+pub fn spawn_note_schedule(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut failed: Option<(NaiveDate, Instant)> = None;
+        loop {
+            let schedule = load_schedule(&app).unwrap_or_else(|e| {
+                eprintln!("Failed to load daily note schedule: {}", e);
+                NoteSchedule::default()
+            });
+            if let Some(today) = due_date(&schedule, Local::now().naive_local()) {
+                // Signed out: nothing to generate until someone signs in
+                let signed_in = app.state::<TokenStore>().account_context().await.is_ok();
+                let retry_pending =
+                    failed.is_some_and(|(date, at)| date == today && at.elapsed() < RETRY_AFTER);
+                if signed_in && !retry_pending {
+                    match run_pipeline(&app, today).await {
+                        Ok(_) => failed = None,
+                        Err(e) => {
+                            eprintln!("Daily note generation failed: {}", e);
+                            failed = Some((today, Instant::now()));
+                        }
+                    }
+                }
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// ============================================================================
+// Encryption
+// ============================================================================
+
+/// Whether notes are encrypted at rest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteEncryption {
+    pub enabled: bool,
+}
+
+/// Encryption setting and the state of the account's saved notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteEncryptionStatus {
+    pub enabled: bool,
+    pub encrypted_notes: usize,
+    pub plaintext_notes: usize,
+}
+
+fn load_encryption(app: &AppHandle) -> Result<NoteEncryption, String> {
+    let store = storage::fs::settings_store(app, DAILY_NOTE_STORE_FILE)
+        .map_err(|e| format!("Failed to access daily note store: {}", e))?;
+
+    Ok(store
+        .get(ENCRYPTION_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// The account's note key, creating its keychain secret when `create` is set
+fn note_key(account: &AccountContext, create: bool) -> Result<Option<RecordKey>, String> {
+    let account_id = account.storage_id();
+    let secret = match crate::auth::get_record_secret(&account_id)? {
+        Some(secret) => STANDARD
+            .decode(secret)
+            .map_err(|e| format!("Invalid note encryption secret: {}", e))?,
+        None if create => {
+            let secret = crypto::new_secret();
+            crate::auth::store_record_secret(&account_id, &STANDARD.encode(secret))?;
+            secret.to_vec()
+        }
+        None => return Ok(None),
+    };
+    Ok(Some(crypto::derive_key(&secret, DAILY_NOTES)))
+}
+
+/// Open a stored note value, sealed or not
+fn open_note(key: Option<&RecordKey>, value: serde_json::Value) -> Result<DailyNote, String> {
+    let value = if crypto::is_encrypted(&value) {
+        crypto::decrypt(key.ok_or("Note encryption key is missing")?, value)?
+    } else {
+        value
+    };
+    serde_json::from_value(value).map_err(|e| format!("Invalid saved note: {}", e))
+}
+
+/// Read the note of `date`, sealing it if it is plaintext and encryption is on
+fn read_note(
+    app: &AppHandle,
+    account: &AccountContext,
+    date: &str,
+) -> Result<Option<DailyNote>, String> {
+    let storage = app.state::<LocalStorage>();
+    let Some(record) = storage.get(account, DAILY_NOTES, date)? else {
+        return Ok(None);
+    };
+    let sealed = crypto::is_encrypted(&record.value);
+    let encrypt = load_encryption(app)?.enabled;
+    let key = note_key(account, encrypt)?;
+    let note = open_note(key.as_ref(), record.value)?;
+
+    if encrypt && !sealed {
+        if let Err(e) = save_note(app, account, &note) {
+            eprintln!("Failed to encrypt note {}: {}", date, e);
+        }
+    }
+    Ok(Some(note))
+}
+
+/// Seal (or open, when `encrypt` is off) every saved note of the account
+fn migrate_notes(
+    app: &AppHandle,
+    account: &AccountContext,
+    encrypt: bool,
+) -> Result<NoteEncryptionStatus, String> {
+    let storage = app.state::<LocalStorage>();
+    let key = note_key(account, encrypt)?;
+    let mut status = NoteEncryptionStatus {
+        enabled: encrypt,
+        encrypted_notes: 0,
+        plaintext_notes: 0,
+    };
+    let mut migrated = Vec::new();
+    for (date, record) in storage.list(account, DAILY_NOTES)? {
+        let sealed = crypto::is_encrypted(&record.value);
+        if sealed == encrypt {
+            if sealed {
+                status.encrypted_notes += 1;
+            } else {
+                status.plaintext_notes += 1;
+            }
+            continue;
+        }
+        let value = if encrypt {
+            crypto::encrypt(key.as_ref().ok_or("Missing note key")?, &record.value)?
+        } else {
+            let note = open_note(key.as_ref(), record.value)?;
+            serde_json::to_value(note).map_err(|e| format!("Failed to serialize note: {}", e))?
+        };
+        migrated.push((date, value));
+    }
+
+    if encrypt {
+        status.encrypted_notes += migrated.len();
+    } else {
+        status.plaintext_notes += migrated.len();
+    }
+    storage.put_many(account, DAILY_NOTES, migrated)?;
+    Ok(status)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the daily note schedule
+#[tauri::command]
+pub async fn get_note_schedule(app: AppHandle) -> Result<NoteSchedule, String> {
+    crate::perf::trace_command!();
+    load_schedule(&app)
+}
+
+/// Save the daily note schedule
+#[tauri::command]
+pub async fn set_note_schedule(app: AppHandle, schedule: NoteSchedule) -> Result<(), String> {
+    crate::perf::trace_command!();
+    parse_time(&schedule.time)?;
+
+    let store = storage::fs::settings_store(&app, DAILY_NOTE_STORE_FILE)
+        .map_err(|e| format!("Failed to access daily note store: {}", e))?;
+    store.set(SCHEDULE_KEY, serde_json::json!(schedule));
+    storage::fs::save_store(&app, DAILY_NOTE_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save daily note store: {}", e))
+}
+
+/// Get the saved note of `date` (YYYY-MM-DD), if any
+#[tauri::command]
+pub async fn get_daily_note(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    date: String,
+) -> Result<Option<DailyNote>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    read_note(&app, &account, &date)
+}
+
+/// Save an AI-written note, replacing any note of the same day
+#[tauri::command]
+pub async fn save_daily_note(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    note: serde_json::Value,
+) -> Result<DailyNote, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    save_ai_note(&app, &account, note)
+}
+
+/// Get whether notes are encrypted at rest, with the account's note counts
+#[tauri::command]
+pub async fn get_note_encryption(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+) -> Result<NoteEncryptionStatus, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let notes = app.state::<LocalStorage>().list(&account, DAILY_NOTES)?;
+    let encrypted_notes = notes
+        .values()
+        .filter(|record| crypto::is_encrypted(&record.value))
+        .count();
+    Ok(NoteEncryptionStatus {
+        enabled: load_encryption(&app)?.enabled,
+        encrypted_notes,
+        plaintext_notes: notes.len() - encrypted_notes,
+    })
+}
+
+/// Turn note encryption on or off, migrating the account's saved notes
+#[tauri::command]
+pub async fn set_note_encryption(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    enabled: bool,
+) -> Result<NoteEncryptionStatus, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+
+    let store = storage::fs::settings_store(&app, DAILY_NOTE_STORE_FILE)
+        .map_err(|e| format!("Failed to access daily note store: {}", e))?;
+    store.set(
+        ENCRYPTION_KEY,
+        serde_json::json!(NoteEncryption { enabled }),
+    );
+    storage::fs::save_store(&app, DAILY_NOTE_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save daily note store: {}", e))?;
+
+    migrate_notes(&app, &account, enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_date_and_local_note() {
+        let at = |time: &str| {
+            NaiveDateTime::parse_from_str(&format!("2026-01-15 {}", time), "%Y-%m-%d %H:%M")
+                .unwrap()
+        };
+        let schedule = NoteSchedule::default();
+        assert_eq!(due_date(&schedule, at("07:29")), None);
+        assert_eq!(
+            due_date(&schedule, at("07:30")),
+            NaiveDate::from_ymd_opt(2026, 1, 15)
+        );
+        assert_eq!(
+            due_date(&schedule, at("18:00")),
+            NaiveDate::from_ymd_opt(2026, 1, 15)
+        );
+        let disabled = NoteSchedule {
+            enabled: false,
+            ..NoteSchedule::default()
+        };
+        assert_eq!(due_date(&disabled, at("09:00")), None);
+
+        let context = data_pipeline::prepare_note_context(
+            Vec::new(),
+            vec![TaskSummary {
+                id: "t1".to_string(),
+                title: "Send invoice".to_string(),
+                due_ms: None,
+                completed: false,
+                list_name: None,
+            }],
+            Vec::new(),
+            Some("Tomorrow is a public holiday: Epiphany".to_string()),
+            None,
+        );
+        let note = generate_local_note("2026-01-15", &context);
+        let types: Vec<&str> = note
+            .sections
+            .iter()
+            .map(|s| s.section_type.as_str())
+            .collect();
+        assert_eq!(
+            types,
+            ["custom", "meeting_notes", "task_recap", "email_summary"]
+        );
+        assert_eq!(
+            note.sections[2].content,
+            "1 outstanding, 0 overdue\n- Send invoice"
+        );
+    }
+}
@@ -5,9 +5,15 @@
 //!
 //! @since v0.5.20
 
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::google::batch::BatchCall;
+use crate::google::{GoogleApiError, GoogleClient};
+use crate::planner::{PlanSegment, PlanWindow, PlanWindowSettings};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tauri::State;
 
 // ============================================================================
 // Note Context Preparation
@@ -67,13 +73,16 @@ pub struct NoteGenerationContext {
     /// Overdue task count
     pub overdue_count: usize,
 
-    /// Today's events
+    /// Events in the plan window
     pub todays_events: Vec<ProcessedEventContext>,
     /// Meeting count
     pub meeting_count: usize,
     /// Total event hours
     pub total_event_hours: f64,
 
+    /// Day-off notice for tomorrow (e.g. "Tomorrow is a public holiday: ...")
+    pub holiday_notice: Option<String>,
+
     /// Processing metadata
     pub processed_at_ms: i64,
     pub context_tokens_estimate: usize,
@@ -105,16 +114,24 @@ pub struct ProcessedEventContext {
     pub time: String, // "10:00 AM - 11:00 AM"
     pub is_meeting: bool,
     pub attendees: Option<usize>,
+    pub segment: PlanSegment,
 }
 
 /// Prepare context for Note AI generation (parallelized)
+///
+/// Events are limited to `window` (see `get_plan_window`), today by default.
 #[tauri::command]
 pub fn prepare_note_context(
     emails: Vec<EmailSummary>,
     tasks: Vec<TaskSummary>,
     events: Vec<EventSummary>,
+    holiday_notice: Option<String>,
+    window: Option<PlanWindow>,
 ) -> NoteGenerationContext {
+    crate::perf::trace_command!();
     let now = chrono::Utc::now().timestamp_millis();
+    let window = window
+        .or_else(|| PlanWindow::at(&PlanWindowSettings::default(), chrono::Local::now()).ok());
     let today_start = chrono::Local::now()
         .date_naive()
         .and_hms_opt(0, 0, 0)
@@ -203,7 +220,7 @@ pub fn prepare_note_context(
 
     let todays_events: Vec<ProcessedEventContext> = events
         .par_iter()
-        .filter(|e| e.start_ms >= today_start && e.start_ms < today_end)
+        .filter(|e| window.is_none_or(|w| w.contains(e.start_ms)))
         .map(|e| ProcessedEventContext {
             title: truncate_string(&e.title, 60),
             time: if e.is_all_day {
@@ -217,6 +234,9 @@ pub fn prepare_note_context(
             } else {
                 None
             },
+            segment: window
+                .map(|w| w.segment(e.start_ms, e.is_all_day))
+                .unwrap_or(PlanSegment::Today),
         })
         .collect();
 
@@ -235,6 +255,7 @@ pub fn prepare_note_context(
         todays_events,
         meeting_count,
         total_event_hours,
+        holiday_notice,
         processed_at_ms: now,
         context_tokens_estimate,
     }
@@ -263,6 +284,7 @@ pub struct ValidatedSection {
 /// Validate note schema
 #[tauri::command]
 pub fn validate_note_schema(note: Value) -> Result<ValidatedNote, String> {
+    crate::perf::trace_command!();
     // Check required fields
     let id = note
         .get("id")
@@ -348,6 +370,7 @@ pub struct ResponseMetadata {
 /// Normalize an API response with metadata
 #[tauri::command]
 pub fn normalize_response(data: Value, source: String) -> NormalizedResponse<Value> {
+    crate::perf::trace_command!();
     let item_count = if let Some(arr) = data.as_array() {
         arr.len()
     } else if data.is_object() {
@@ -384,12 +407,70 @@ pub struct SingleRequest {
     pub body: Option<Value>,
 }
 
+/// Result of one request of an executed batch
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResponseItem {
+    pub id: String,
+    pub body: Option<Value>,
+    pub error: Option<GoogleApiError>,
+}
+
 /// Prepare batch API requests
 #[tauri::command]
 pub fn prepare_batch_requests(requests: Vec<SingleRequest>) -> BatchRequest {
+    crate::perf::trace_command!();
     BatchRequest { requests }
 }
 
+/// Run a prepared batch through Google's batch endpoint
+///
+/// Only GET requests are accepted, all to the same API; `endpoint` is the full
+/// API URL. Results are returned in request order.
+#[tauri::command]
+pub async fn execute_batch_requests(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    batch: BatchRequest,
+) -> Result<Vec<BatchResponseItem>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if let Some(request) = batch
+        .requests
+        .iter()
+        .find(|r| !r.method.eq_ignore_ascii_case("GET"))
+    {
+        return Err(format!("Only GET requests can be batched: {}", request.id).into());
+    }
+
+    let calls: Vec<BatchCall> = batch
+        .requests
+        .into_iter()
+        .map(|request| BatchCall {
+            id: request.id,
+            method: request.method,
+            url: request.endpoint,
+            body: request.body,
+        })
+        .collect();
+    let outcomes = client.batch(&calls, &token_store).await?;
+    Ok(outcomes
+        .into_iter()
+        .map(|outcome| match outcome.result {
+            Ok(body) => BatchResponseItem {
+                id: outcome.id,
+                body: Some(body),
+                error: None,
+            },
+            Err(error) => BatchResponseItem {
+                id: outcome.id,
+                body: None,
+                error: Some(error),
+            },
+        })
+        .collect())
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -523,7 +604,7 @@ mod tests {
 
         let events = vec![];
 
-        let context = prepare_note_context(emails, tasks, events);
+        let context = prepare_note_context(emails, tasks, events, None, None);
         assert_eq!(context.total_emails, 1);
         assert_eq!(context.unread_count, 1);
         assert_eq!(context.total_tasks, 1);
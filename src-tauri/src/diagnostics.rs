@@ -0,0 +1,490 @@
+//! Onboarding diagnostics
+//!
+//! Runs a set of environment checks (keychain, OAuth credentials, network,
+//! granted scopes, clock skew, notification permission) and returns a
+//! structured pass/fail report the setup wizard can render.
+//!
+//! `auth_diagnostics` looks at the session alone, to explain failing API
+//! calls (401s): keychain, token validity, scopes and expiry, with hints.
+
+use crate::auth::{self, AuthState, ReauthRequired, TokenStore, SCOPES};
+use crate::google::{CALENDAR_API_BASE, GMAIL_API_BASE, TASKS_API_BASE};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tauri_plugin_notification::NotificationExt;
+
+/// Google endpoint used to inspect the scopes of an access token
+const TOKEN_INFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+
+/// Clock skew (seconds) above which a warning is reported
+const CLOCK_SKEW_WARN_SECS: i64 = 60;
+/// Clock skew (seconds) above which token validation is likely to fail
+const CLOCK_SKEW_FAIL_SECS: i64 = 300;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+/// A single diagnostic check result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    pub id: String,
+    pub label: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DiagnosticCheck {
+    fn new(id: &str, label: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Full diagnostics report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+    /// True when no check failed (warnings and skips are allowed)
+    pub passed: bool,
+    pub ran_at_ms: i64,
+}
+
+// ============================================================================
+// Individual Checks
+// ============================================================================
+
+fn check_keychain() -> DiagnosticCheck {
+    match auth::probe_keychain() {
+        Ok(()) => DiagnosticCheck::new(
+            "keychain",
+            "OS keychain",
+            CheckStatus::Pass,
+            "Keychain is available",
+        ),
+        Err(e) => DiagnosticCheck::new("keychain", "OS keychain", CheckStatus::Fail, e),
+    }
+}
+
+fn check_credentials(state: &AuthState) -> DiagnosticCheck {
+    if state.client_id.is_empty() {
+        DiagnosticCheck::new(
+            "oauth_credentials",
+            "OAuth credentials",
+            CheckStatus::Fail,
+            "Missing: GOOGLE_CLIENT_ID",
+        )
+    } else if state.client_secret.is_empty() {
+        // A public client signs in with PKCE alone
+        DiagnosticCheck::new(
+            "oauth_credentials",
+            "OAuth credentials",
+            CheckStatus::Pass,
+            "Client ID is configured (public client, no secret)",
+        )
+    } else {
+        DiagnosticCheck::new(
+            "oauth_credentials",
+            "OAuth credentials",
+            CheckStatus::Pass,
+            "Client ID and secret are configured",
+        )
+    }
+}
+
+/// Check that each Google API host answers, and measure clock skew from the
+/// `Date` header of the first response
+async fn check_network(http: &reqwest::Client) -> (DiagnosticCheck, DiagnosticCheck) {
+    let endpoints = [
+        ("Gmail", GMAIL_API_BASE),
+        ("Calendar", CALENDAR_API_BASE),
+        ("Tasks", TASKS_API_BASE),
+    ];
+
+    let mut unreachable = Vec::new();
+    let mut skew_secs: Option<i64> = None;
+
+    for (name, url) in endpoints {
+        // Any HTTP response (even 401/404) means the host is reachable
+        match http.get(url).send().await {
+            Ok(response) => {
+                if skew_secs.is_none() {
+                    skew_secs = response
+                        .headers()
+                        .get(reqwest::header::DATE)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+                        .map(|server| chrono::Utc::now().timestamp() - server.timestamp());
+                }
+            }
+            Err(e) => unreachable.push(format!("{} ({})", name, e)),
+        }
+    }
+
+    let network = if unreachable.is_empty() {
+        DiagnosticCheck::new(
+            "network",
+            "Google API reachability",
+            CheckStatus::Pass,
+            "Gmail, Calendar and Tasks are reachable",
+        )
+    } else {
+        DiagnosticCheck::new(
+            "network",
+            "Google API reachability",
+            CheckStatus::Fail,
+            format!("Unreachable: {}", unreachable.join(", ")),
+        )
+    };
+
+    let clock = match skew_secs {
+        Some(skew) => {
+            let status = match skew.abs() {
+                s if s >= CLOCK_SKEW_FAIL_SECS => CheckStatus::Fail,
+                s if s >= CLOCK_SKEW_WARN_SECS => CheckStatus::Warn,
+                _ => CheckStatus::Pass,
+            };
+            DiagnosticCheck::new(
+                "clock_skew",
+                "System clock",
+                status,
+                format!("Local clock differs from Google by {}s", skew),
+            )
+        }
+        None => DiagnosticCheck::new(
+            "clock_skew",
+            "System clock",
+            CheckStatus::Skipped,
+            "No server time available",
+        ),
+    };
+
+    (network, clock)
+}
+
+/// What Google's tokeninfo endpoint says about an access token
+#[derive(Debug, Deserialize)]
+struct TokenInfo {
+    scope: Option<String>,
+    /// OAuth client the token was issued to
+    aud: Option<String>,
+    /// Seconds left, as a string
+    expires_in: Option<String>,
+    email: Option<String>,
+}
+
+async fn token_info(http: &reqwest::Client, token: &str) -> Result<TokenInfo, String> {
+    let response = http
+        .get(TOKEN_INFO_URL)
+        .query(&[("access_token", token)])
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Token info error {}", response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse token info: {}", e))
+}
+
+async fn check_scopes(http: &reqwest::Client, token_store: &TokenStore) -> DiagnosticCheck {
+    let token = match token_store.get_access_token().await {
+        Ok(token) => token,
+        Err(_) => {
+            return DiagnosticCheck::new(
+                "scopes",
+                "Granted scopes",
+                CheckStatus::Skipped,
+                "Not signed in",
+            )
+        }
+    };
+
+    let info = token_info(http, &token).await;
+
+    match info {
+        Ok(info) => {
+            let granted = info.scope.unwrap_or_default();
+            let granted: Vec<&str> = granted.split_whitespace().collect();
+            let missing = missing_scopes(&granted);
+
+            if missing.is_empty() {
+                DiagnosticCheck::new(
+                    "scopes",
+                    "Granted scopes",
+                    CheckStatus::Pass,
+                    "All required scopes granted",
+                )
+            } else {
+                DiagnosticCheck::new(
+                    "scopes",
+                    "Granted scopes",
+                    CheckStatus::Fail,
+                    format!("Missing: {}", missing.join(", ")),
+                )
+            }
+        }
+        Err(e) => DiagnosticCheck::new("scopes", "Granted scopes", CheckStatus::Warn, e),
+    }
+}
+
+/// Required API scopes that are not present in `granted`
+///
+/// OpenID scopes (`openid`, `email`, `profile`) are reported by Google under
+/// different names and are not checked here.
+fn missing_scopes(granted: &[&str]) -> Vec<String> {
+    SCOPES
+        .iter()
+        .filter(|s| s.starts_with("https://"))
+        .filter(|s| !granted.contains(s))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn check_notifications(app: &AppHandle) -> DiagnosticCheck {
+    match app.notification().permission_state() {
+        Ok(tauri_plugin_notification::PermissionState::Granted) => DiagnosticCheck::new(
+            "notifications",
+            "Notification permission",
+            CheckStatus::Pass,
+            "Granted",
+        ),
+        Ok(tauri_plugin_notification::PermissionState::Denied) => DiagnosticCheck::new(
+            "notifications",
+            "Notification permission",
+            CheckStatus::Warn,
+            "Denied",
+        ),
+        Ok(_) => DiagnosticCheck::new(
+            "notifications",
+            "Notification permission",
+            CheckStatus::Warn,
+            "Not yet requested",
+        ),
+        Err(e) => DiagnosticCheck::new(
+            "notifications",
+            "Notification permission",
+            CheckStatus::Fail,
+            e.to_string(),
+        ),
+    }
+}
+
+// ============================================================================
+// Auth Diagnostics
+// ============================================================================
+
+/// State of the session, for debugging rejected API calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthDiagnostics {
+    pub keychain: DiagnosticCheck,
+    pub signed_in: bool,
+    pub email: Option<String>,
+    /// Whether Google accepts the access token (None when not checked)
+    pub token_valid: Option<bool>,
+    pub token_error: Option<String>,
+    /// Whether the token was issued to this app's OAuth client
+    pub audience_matches: Option<bool>,
+    pub required_scopes: Vec<String>,
+    pub granted_scopes: Vec<String>,
+    pub missing_scopes: Vec<String>,
+    /// Unix seconds, as tracked locally
+    pub expires_at: Option<i64>,
+    /// Seconds left according to Google
+    pub expires_in_secs: Option<i64>,
+    pub reauth_required: Option<ReauthRequired>,
+    /// What to do about each problem found
+    pub hints: Vec<String>,
+    pub checked_at_ms: i64,
+}
+
+fn auth_hints(report: &AuthDiagnostics) -> Vec<String> {
+    let mut hints = Vec::new();
+    if report.keychain.status == CheckStatus::Fail {
+        hints.push(
+            "The OS keychain is unavailable, so the session can't be restored or refreshed"
+                .to_string(),
+        );
+    }
+    if let Some(reauth) = &report.reauth_required {
+        hints.push(format!(
+            "Google rejected the saved session ({}); sign in again",
+            reauth.reason
+        ));
+    } else if !report.signed_in {
+        hints.push("Not signed in".to_string());
+    }
+    if let Some(error) = &report.token_error {
+        hints.push(format!(
+            "Google doesn't accept the access token ({}); sign out and in again",
+            error
+        ));
+    }
+    if report.audience_matches == Some(false) {
+        hints.push(
+            "The token was issued to another OAuth client; check GOOGLE_CLIENT_ID".to_string(),
+        );
+    }
+    if !report.missing_scopes.is_empty() {
+        hints.push(format!(
+            "Sign in again and allow every requested permission (missing: {})",
+            report.missing_scopes.join(", ")
+        ));
+    }
+    hints
+}
+
+/// Check the session: keychain, token validity, granted vs required scopes
+/// and expiry, with hints on fixing what's wrong
+#[tauri::command]
+pub async fn auth_diagnostics(
+    auth_state: State<'_, AuthState>,
+    token_store: State<'_, TokenStore>,
+) -> Result<AuthDiagnostics, String> {
+    crate::perf::trace_command!();
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let status = token_store.get_auth_status().await?;
+    let mut report = AuthDiagnostics {
+        keychain: check_keychain(),
+        signed_in: status.user.is_some(),
+        email: status.user.map(|u| u.email),
+        token_valid: None,
+        token_error: None,
+        audience_matches: None,
+        required_scopes: SCOPES.iter().map(|s| s.to_string()).collect(),
+        granted_scopes: Vec::new(),
+        missing_scopes: Vec::new(),
+        expires_at: status.expires_at,
+        expires_in_secs: None,
+        reauth_required: status.reauth_required,
+        hints: Vec::new(),
+        checked_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+
+    if report.signed_in {
+        // The token API calls would use, refreshed if it is expiring
+        let info = match token_store.get_access_token().await {
+            Ok(token) => token_info(&http, &token).await,
+            Err(e) => Err(format!("No usable access token: {}", e)),
+        };
+        match info {
+            Ok(info) => {
+                report.token_valid = Some(true);
+                report.audience_matches = info.aud.map(|aud| aud == auth_state.client_id);
+                report.expires_in_secs = info.expires_in.and_then(|s| s.parse().ok());
+                report.email = info.email.or(report.email);
+                report.granted_scopes = info
+                    .scope
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect();
+                let granted: Vec<&str> = report.granted_scopes.iter().map(String::as_str).collect();
+                report.missing_scopes = missing_scopes(&granted);
+                report.expires_at = token_store.get_auth_status().await?.expires_at;
+            }
+            Err(e) => {
+                report.token_valid = Some(false);
+                report.token_error = Some(e);
+                // A rejected refresh ends the session
+                report.reauth_required = token_store.get_auth_status().await?.reauth_required;
+            }
+        }
+    }
+
+    report.hints = auth_hints(&report);
+    Ok(report)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Run all onboarding checks and return a structured report
+#[tauri::command]
+pub async fn run_checks(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    token_store: State<'_, TokenStore>,
+) -> Result<DiagnosticsReport, String> {
+    crate::perf::trace_command!();
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut checks = vec![check_keychain(), check_credentials(&auth_state)];
+
+    let (network, clock) = check_network(&http).await;
+    checks.push(network);
+    checks.push(check_scopes(&http, &token_store).await);
+    checks.push(clock);
+    checks.push(check_notifications(&app));
+
+    let passed = checks.iter().all(|c| c.status != CheckStatus::Fail);
+
+    Ok(DiagnosticsReport {
+        checks,
+        passed,
+        ran_at_ms: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_scopes() {
+        let all: Vec<&str> = SCOPES.to_vec();
+        assert!(missing_scopes(&all).is_empty());
+
+        let missing = missing_scopes(&["https://www.googleapis.com/auth/tasks"]);
+        assert!(missing.contains(&"https://www.googleapis.com/auth/gmail.readonly".to_string()));
+        assert!(!missing.contains(&"openid".to_string()));
+    }
+
+    #[test]
+    fn test_auth_hints() {
+        let mut report = AuthDiagnostics {
+            keychain: DiagnosticCheck::new("keychain", "OS keychain", CheckStatus::Pass, "ok"),
+            signed_in: true,
+            email: Some("user@example.com".to_string()),
+            token_valid: Some(true),
+            token_error: None,
+            audience_matches: Some(true),
+            required_scopes: Vec::new(),
+            granted_scopes: Vec::new(),
+            missing_scopes: Vec::new(),
+            expires_at: None,
+            expires_in_secs: Some(3000),
+            reauth_required: None,
+            hints: Vec::new(),
+            checked_at_ms: 0,
+        };
+        assert!(auth_hints(&report).is_empty());
+
+        report.audience_matches = Some(false);
+        report.missing_scopes = vec!["https://www.googleapis.com/auth/tasks".to_string()];
+        let hints = auth_hints(&report);
+        assert_eq!(hints.len(), 2);
+        assert!(hints[1].contains("auth/tasks"));
+    }
+}
@@ -0,0 +1,482 @@
+//! Reading digest of the day's bulk mail
+//!
+//! Newsletters and notifications (a promotions/updates/social/forums category
+//! or a List-Unsubscribe header) would otherwise each take a low-priority row
+//! in the plan. `get_reading_digest` groups the day's ones by sender, asks the
+//! AI provider for a one-line headline per email and returns them as a single
+//! "Reading digest" plan item. Without a usable provider (none set up, budget
+//! reached, unparsable reply) the subjects serve as headlines.
+//!
+//! `archive_digest` archives the digest's threads in one go.
+
+use crate::ai::{self, usage, GenerateRequest};
+use crate::analytics;
+use crate::app_lock::AppLockState;
+use crate::auth::capabilities::{self, Feature};
+use crate::auth::TokenStore;
+use crate::events::DataEvent;
+use crate::google::gmail::{self, DEFAULT_HYDRATION_PARALLELISM};
+use crate::google::mailbox::Mailbox;
+use crate::google::types::{GmailMessage, GmailThreadDetail, GmailThreadsPage};
+use crate::google::{fan_out, invalidation, GoogleClient, GMAIL_API_BASE, MAX_RESPONSE_BYTES};
+use crate::planner::PlanTaskItem;
+use crate::processing;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+/// Inbox threads of the day looked at
+const MAX_DAY_THREADS: u32 = 100;
+/// Threads archived by one `archive_digest` call
+const MAX_ARCHIVE_THREADS: usize = 200;
+/// Gmail categories of bulk mail
+const BULK_CATEGORIES: &[&str] = &[
+    "CATEGORY_PROMOTIONS",
+    "CATEGORY_UPDATES",
+    "CATEGORY_SOCIAL",
+    "CATEGORY_FORUMS",
+];
+/// Reading time planned per email, and at most for the whole digest
+const MINUTES_PER_EMAIL: u32 = 2;
+const MAX_DIGEST_MINUTES: u32 = 30;
+
+const HEADLINE_SYSTEM_PROMPT: &str =
+    "You write one-line headlines for newsletter and notification emails. For each email, \
+give what it is about in at most 12 words, without the sender's name. Reply with JSON only: \
+an object mapping each email id to its headline.";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// An email of the digest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestEntry {
+    pub thread_id: String,
+    pub subject: String,
+    /// AI headline, or the subject
+    pub headline: String,
+    pub date_ms: i64,
+    pub is_unread: bool,
+}
+
+/// A sender's emails, newest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestSender {
+    pub from_name: String,
+    pub from_email: String,
+    pub entries: Vec<DigestEntry>,
+}
+
+/// The day's bulk mail as one plan item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingDigest {
+    pub date: String,
+    /// Senders with the most emails first
+    pub senders: Vec<DigestSender>,
+    /// Every thread of the digest, for `archive_digest`
+    pub thread_ids: Vec<String>,
+    /// False when the headlines are the subjects
+    pub ai_headlines: bool,
+    /// None when the day had no bulk mail
+    pub item: Option<PlanTaskItem>,
+}
+
+/// A thread `archive_digest` couldn't archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestArchiveFailure {
+    pub thread_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestArchiveResult {
+    pub archived: Vec<String>,
+    pub failures: Vec<DigestArchiveFailure>,
+}
+
+// ============================================================================
+// Grouping
+// ============================================================================
+
+fn has_label(message: &GmailMessage, labels: &[&str]) -> bool {
+    message
+        .label_ids
+        .as_ref()
+        .is_some_and(|ids| ids.iter().any(|id| labels.contains(&id.as_str())))
+}
+
+/// Whether a thread is bulk mail the digest takes (starred threads stay out)
+fn is_bulk_thread(thread: &GmailThreadDetail) -> bool {
+    let messages = thread.messages.as_deref().unwrap_or_default();
+    !messages.iter().any(|m| has_label(m, &["STARRED"]))
+        && messages.iter().any(|m| {
+            has_label(m, BULK_CATEGORIES) || analytics::header(m, "List-Unsubscribe").is_some()
+        })
+}
+
+/// Group the bulk threads by the sender of their latest message
+fn group_by_sender(threads: &[GmailThreadDetail]) -> Vec<DigestSender> {
+    let mut senders: Vec<DigestSender> = Vec::new();
+    for thread in threads.iter().filter(|t| is_bulk_thread(t)) {
+        let Some(latest) = thread.messages.as_deref().and_then(|m| m.last()) else {
+            continue;
+        };
+        let (from_name, from_email) =
+            analytics::parse_from_header(analytics::header(latest, "From").unwrap_or_default());
+        let subject = analytics::header(latest, "Subject")
+            .unwrap_or("(no subject)")
+            .to_string();
+        let entry = DigestEntry {
+            thread_id: thread.id.clone(),
+            headline: subject.clone(),
+            subject,
+            date_ms: latest
+                .internal_date
+                .as_deref()
+                .and_then(|d| d.parse().ok())
+                .unwrap_or_default(),
+            is_unread: thread
+                .messages
+                .iter()
+                .flatten()
+                .any(|m| has_label(m, &["UNREAD"])),
+        };
+
+        match senders.iter_mut().find(|s| s.from_email == from_email) {
+            Some(sender) => sender.entries.push(entry),
+            None => senders.push(DigestSender {
+                from_name,
+                from_email,
+                entries: vec![entry],
+            }),
+        }
+    }
+
+    for sender in &mut senders {
+        sender.entries.sort_by_key(|e| std::cmp::Reverse(e.date_ms));
+    }
+    senders.sort_by(|a, b| {
+        b.entries
+            .len()
+            .cmp(&a.entries.len())
+            .then_with(|| a.from_email.cmp(&b.from_email))
+    });
+    senders
+}
+
+/// The plan item standing in for the digest's emails
+fn digest_item(date: &str, senders: &[DigestSender]) -> Option<PlanTaskItem> {
+    let count: usize = senders.iter().map(|s| s.entries.len()).sum();
+    if count == 0 {
+        return None;
+    }
+    let mut extra = serde_json::Map::new();
+    extra.insert("digest".to_string(), serde_json::json!(true));
+    extra.insert("email_count".to_string(), serde_json::json!(count));
+    extra.insert("sender_count".to_string(), serde_json::json!(senders.len()));
+
+    Some(PlanTaskItem {
+        id: format!("digest:{}", date),
+        title: format!(
+            "Reading digest ({} {})",
+            count,
+            if count == 1 { "email" } else { "emails" }
+        ),
+        kind: "email".to_string(),
+        duration_minutes: (count as u32 * MINUTES_PER_EMAIL).min(MAX_DIGEST_MINUTES),
+        source_id: None,
+        source_type: Some("email".to_string()),
+        extra,
+    })
+}
+
+// ============================================================================
+// Headlines
+// ============================================================================
+
+fn headline_request(threads: &[GmailThreadDetail], senders: &[DigestSender]) -> GenerateRequest {
+    let snippets: HashMap<&str, &str> = threads
+        .iter()
+        .filter_map(|t| {
+            Some((
+                t.id.as_str(),
+                t.messages.as_deref()?.last()?.snippet.as_str(),
+            ))
+        })
+        .collect();
+    let emails: Vec<serde_json::Value> = senders
+        .iter()
+        .flat_map(|sender| {
+            sender.entries.iter().map(|entry| {
+                serde_json::json!({
+                    "id": entry.thread_id,
+                    "from": sender.from_name,
+                    "subject": entry.subject,
+                    "snippet": processing::clean_snippet(
+                        snippets.get(entry.thread_id.as_str()).unwrap_or(&"").to_string(),
+                        Some(200),
+                    ),
+                })
+            })
+        })
+        .collect();
+
+    GenerateRequest {
+        system: Some(HEADLINE_SYSTEM_PROMPT.to_string()),
+        prompt: serde_json::Value::Array(emails).to_string(),
+        max_tokens: Some(1500),
+        temperature: Some(0.2),
+    }
+}
+
+/// Headlines by thread ID from a model reply, ignoring text around the JSON
+fn parse_headlines(reply: &str) -> Option<HashMap<String, String>> {
+    let start = reply.find('{')?;
+    let end = reply.rfind('}')?;
+    serde_json::from_str(reply.get(start..=end)?).ok()
+}
+
+/// Replace subjects with AI headlines; false when the provider wasn't usable
+async fn apply_headlines(
+    app: &AppHandle,
+    threads: &[GmailThreadDetail],
+    senders: &mut [DigestSender],
+) -> bool {
+    let request = headline_request(threads, senders);
+    let result = async {
+        let provider = ai::current_provider(app)?;
+        usage::check_budget(app).await?;
+        let reply = provider.generate(&request).await?;
+        usage::record_call(app, provider.as_ref(), &request, &reply).await;
+        parse_headlines(&reply).ok_or_else(|| "The AI reply has no headlines".to_string())
+    }
+    .await;
+
+    let headlines = match result {
+        Ok(headlines) => headlines,
+        Err(e) => {
+            eprintln!("Reading digest without AI headlines: {}", e);
+            return false;
+        }
+    };
+    for entry in senders.iter_mut().flat_map(|s| &mut s.entries) {
+        if let Some(headline) = headlines.get(&entry.thread_id) {
+            let headline = headline.trim();
+            if !headline.is_empty() {
+                entry.headline = headline.to_string();
+            }
+        }
+    }
+    true
+}
+
+/// Build the digest of `day`; AI headlines are only requested when
+/// `headlines` is set
+pub(crate) async fn reading_digest(
+    app: &AppHandle,
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    day: NaiveDate,
+    headlines: bool,
+) -> Result<ReadingDigest, String> {
+    let start_secs = processing::local_midnight_ms(day)? / 1000;
+    let end_secs = processing::local_midnight_ms(day + Duration::days(1))? / 1000;
+    let query = format!("in:inbox after:{} before:{}", start_secs, end_secs);
+    let url = format!(
+        "{}/users/me/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        MAX_DAY_THREADS,
+        urlencoding::encode(&query)
+    );
+    let thread_ids: Vec<String> = client
+        .get_with(&url, token_store, MAX_RESPONSE_BYTES, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            Ok(page
+                .threads
+                .into_iter()
+                .map(|t| t.id.into_owned())
+                .collect())
+        })
+        .await?;
+
+    let hydration = gmail::hydrate_threads(
+        token_store,
+        client,
+        &Mailbox::Own,
+        &thread_ids,
+        DEFAULT_HYDRATION_PARALLELISM,
+    )
+    .await;
+    for error in &hydration.errors {
+        eprintln!(
+            "Skipping thread {} in reading digest: {}",
+            error.thread_id, error.error
+        );
+    }
+
+    let date = day.format("%Y-%m-%d").to_string();
+    let mut senders = group_by_sender(&hydration.threads);
+    let ai_headlines = headlines
+        && !senders.is_empty()
+        && apply_headlines(app, &hydration.threads, &mut senders).await;
+    Ok(ReadingDigest {
+        item: digest_item(&date, &senders),
+        thread_ids: senders
+            .iter()
+            .flat_map(|s| s.entries.iter().map(|e| e.thread_id.clone()))
+            .collect(),
+        date,
+        senders,
+        ai_headlines,
+    })
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the reading digest of `date` (YYYY-MM-DD, today by default)
+#[tauri::command]
+pub async fn get_reading_digest(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    date: Option<String>,
+) -> Result<ReadingDigest, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let day = processing::date_or_today(date.as_deref())?;
+    reading_digest(&app, &token_store, &client, day, true).await
+}
+
+/// Archive the threads of a reading digest
+#[tauri::command]
+pub async fn archive_digest(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    thread_ids: Vec<String>,
+) -> Result<DigestArchiveResult, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    capabilities::require(&token_store, Feature::Modify).await?;
+    if thread_ids.len() > MAX_ARCHIVE_THREADS {
+        return Err(format!(
+            "Too many threads to archive at once (max {})",
+            MAX_ARCHIVE_THREADS
+        ));
+    }
+
+    let body = serde_json::json!({ "removeLabelIds": ["INBOX"] });
+    let outcome = fan_out(&thread_ids, DEFAULT_HYDRATION_PARALLELISM, |thread_id| {
+        let url = format!("{}/users/me/threads/{}/modify", GMAIL_API_BASE, thread_id);
+        let (client, token_store, body) = (&client, &token_store, &body);
+        async move {
+            let _: serde::de::IgnoredAny = client.post(&url, token_store, body).await?;
+            Ok::<(), String>(())
+        }
+    })
+    .await;
+
+    let archived: Vec<String> = outcome
+        .succeeded
+        .into_iter()
+        .map(|(thread_id, ())| thread_id.clone())
+        .collect();
+    let failures: Vec<DigestArchiveFailure> = outcome
+        .failed
+        .into_iter()
+        .map(|(thread_id, error)| DigestArchiveFailure {
+            thread_id: thread_id.clone(),
+            error,
+        })
+        .collect();
+
+    if !archived.is_empty() {
+        invalidation::mutated(
+            &app,
+            DataEvent::ThreadsModified {
+                thread_ids: archived.clone(),
+            },
+        )
+        .await;
+    }
+    Ok(DigestArchiveResult { archived, failures })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{GmailHeader, GmailPayload};
+
+    fn thread(id: &str, from: &str, labels: &[&str], date_ms: i64) -> GmailThreadDetail {
+        GmailThreadDetail {
+            id: id.to_string(),
+            messages: Some(vec![GmailMessage {
+                id: id.to_string(),
+                thread_id: id.to_string(),
+                label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+                snippet: String::new(),
+                payload: Some(GmailPayload {
+                    headers: Some(vec![
+                        GmailHeader {
+                            name: "From".to_string(),
+                            value: from.to_string(),
+                        },
+                        GmailHeader {
+                            name: "Subject".to_string(),
+                            value: format!("Issue {}", id),
+                        },
+                    ]),
+                    mime_type: None,
+                    filename: None,
+                    body: None,
+                    parts: None,
+                }),
+                internal_date: Some(date_ms.to_string()),
+                size_estimate: None,
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_group_by_sender() {
+        let weekly = "Weekly <news@weekly.example>";
+        let threads = vec![
+            thread("a", weekly, &["INBOX", "CATEGORY_UPDATES"], 1),
+            thread("b", "Ann <ann@example.com>", &["INBOX"], 2),
+            thread(
+                "c",
+                "Shop <deals@shop.example>",
+                &["CATEGORY_PROMOTIONS"],
+                3,
+            ),
+            thread("d", weekly, &["CATEGORY_UPDATES", "UNREAD"], 4),
+            thread("e", weekly, &["CATEGORY_UPDATES", "STARRED"], 5),
+        ];
+
+        let senders = group_by_sender(&threads);
+        assert_eq!(senders.len(), 2);
+        assert_eq!(senders[0].from_email, "news@weekly.example");
+        let ids: Vec<&str> = senders[0]
+            .entries
+            .iter()
+            .map(|e| e.thread_id.as_str())
+            .collect();
+        assert_eq!(ids, ["d", "a"]);
+        assert!(senders[0].entries[0].is_unread);
+        assert_eq!(senders[1].entries[0].headline, "Issue c");
+
+        let item = digest_item("2026-10-16", &senders).unwrap();
+        assert_eq!(item.title, "Reading digest (3 emails)");
+        assert_eq!(item.duration_minutes, 6);
+        assert!(digest_item("2026-10-16", &[]).is_none());
+
+        let headlines = parse_headlines("Sure:\n{\"a\": \"Rust 2.0 ships\"}").unwrap();
+        assert_eq!(headlines["a"], "Rust 2.0 ships");
+    }
+}
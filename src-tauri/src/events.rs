@@ -0,0 +1,174 @@
+//! Data-change event bus
+//!
+//! Every mutating command and background sync cycle reports what changed
+//! through `emit`, so all windows (and the tray) can patch their state instead
+//! of refetching. Each change is broadcast twice:
+//! - on its own channel (e.g. `task:completed`) with the event payload
+//! - on `data:changed` wrapped in a `DataChange` envelope, for listeners that
+//!   want a single subscription
+//!
+//! Google mutations go through `google::invalidation::mutated`, which clears
+//! the cache entries the change made stale before emitting it.
+//!
+//! Windows that registered topics (see `windows`) only get the changes they
+//! subscribed to on their window-scoped listeners.
+//!
+//! The envelope carries a monotonically increasing `seq` so listeners can
+//! drop duplicates and detect missed events.
+//!
+//! Nothing is broadcast while the app is locked (see `app_lock`); windows
+//! refetch after unlocking.
+
+use crate::app_lock::AppLockState;
+use crate::windows::WindowRegistry;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+
+/// Channel carrying every change wrapped in a `DataChange`
+pub const DATA_CHANGED_EVENT: &str = "data:changed";
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A fine-grained data change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DataEvent {
+    TaskCreated {
+        list_id: String,
+        task_id: String,
+    },
+    TaskUpdated {
+        list_id: String,
+        task_id: String,
+    },
+    TaskCompleted {
+        list_id: String,
+        task_id: String,
+    },
+    TaskReopened {
+        list_id: String,
+        task_id: String,
+    },
+    TaskDeleted {
+        list_id: String,
+        task_id: String,
+    },
+    EventCreated {
+        calendar_id: String,
+        event_id: String,
+    },
+    /// The user answered an invitation
+    EventResponded {
+        event_id: String,
+        response: String,
+    },
+    PlanRegenerated {
+        date: String,
+    },
+    PlanChanged {
+        date: String,
+        summary: String,
+    },
+    FeedRefreshed {
+        subscription_id: String,
+        event_count: usize,
+    },
+    ThreadsModified {
+        thread_ids: Vec<String>,
+    },
+    NoteReady {
+        date: String,
+    },
+    /// A completed task's source thread is still in the inbox
+    ThreadFollowUpOffered {
+        list_id: String,
+        task_id: String,
+        thread_id: String,
+    },
+}
+
+impl DataEvent {
+    /// Channel name of this event
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataEvent::TaskCreated { .. } => "task:created",
+            DataEvent::TaskUpdated { .. } => "task:updated",
+            DataEvent::TaskCompleted { .. } => "task:completed",
+            DataEvent::TaskReopened { .. } => "task:reopened",
+            DataEvent::TaskDeleted { .. } => "task:deleted",
+            DataEvent::EventCreated { .. } => "event:created",
+            DataEvent::EventResponded { .. } => "event:responded",
+            DataEvent::PlanRegenerated { .. } => "plan:regenerated",
+            DataEvent::PlanChanged { .. } => "plan:changed",
+            DataEvent::FeedRefreshed { .. } => "feed:refreshed",
+            DataEvent::ThreadsModified { .. } => "threads:modified",
+            DataEvent::NoteReady { .. } => "note:ready",
+            DataEvent::ThreadFollowUpOffered { .. } => "task:thread_follow_up",
+        }
+    }
+}
+
+/// Envelope sent on `data:changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct DataChange {
+    pub seq: u64,
+    pub kind: String,
+    pub payload: DataEvent,
+    pub emitted_at_ms: i64,
+}
+
+fn envelope(event: DataEvent) -> DataChange {
+    DataChange {
+        seq: SEQ.fetch_add(1, Ordering::Relaxed) + 1,
+        kind: event.name().to_string(),
+        payload: event,
+        emitted_at_ms: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+/// Broadcast a data change to all windows
+///
+/// Delivery failures are logged and never fail the mutating command.
+pub fn emit(app: &AppHandle, event: DataEvent) {
+    if app
+        .try_state::<AppLockState>()
+        .is_some_and(|lock| lock.is_locked())
+    {
+        return;
+    }
+    let name = event.name();
+    let registry = app.try_state::<WindowRegistry>();
+    let delivers =
+        |target: &EventTarget| registry.as_ref().is_none_or(|r| r.delivers(target, name));
+    if let Err(e) = app.emit_filter(name, &event, delivers) {
+        eprintln!("Failed to emit {}: {}", name, e);
+    }
+    if let Err(e) = app.emit_filter(DATA_CHANGED_EVENT, envelope(event), delivers) {
+        eprintln!("Failed to emit {}: {}", DATA_CHANGED_EVENT, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_serialization() {
+        let first = envelope(DataEvent::TaskCompleted {
+            list_id: "l1".to_string(),
+            task_id: "t1".to_string(),
+        });
+        let second = envelope(DataEvent::PlanRegenerated {
+            date: "2026-01-15".to_string(),
+        });
+        assert!(second.seq > first.seq);
+
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["kind"], "task:completed");
+        assert_eq!(
+            json["payload"],
+            serde_json::json!({"list_id": "l1", "task_id": "t1"})
+        );
+    }
+}
@@ -0,0 +1,537 @@
+//! Conversation export
+//!
+//! `export_thread` writes a thread as a standalone Markdown or PDF document,
+//! for sharing with people outside the mailbox: messages oldest first, each
+//! with its sender, recipients and date, and its body sanitized (see
+//! `processing::sanitize_html_with`, remote content dropped) and cleaned of
+//! the quoted history the earlier messages already show.
+//!
+//! The PDF is plain text set in the standard Helvetica fonts, so characters
+//! outside Latin-1 are replaced.
+
+use crate::analytics;
+use crate::app_lock::AppLockState;
+use crate::auth::os_gate::{self, SensitiveAction};
+use crate::auth::TokenStore;
+use crate::google::gmail::{self, MessageBody};
+use crate::google::mailbox;
+use crate::google::types::GmailMessage;
+use crate::google::GoogleClient;
+use crate::processing;
+use crate::storage::{self, LocalStorage};
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+/// Longest file name stem derived from the subject
+const MAX_FILE_STEM: usize = 60;
+
+/// Document format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Pdf,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// A written export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadExport {
+    pub path: String,
+    pub format: ExportFormat,
+    pub message_count: usize,
+    pub bytes_written: u64,
+}
+
+/// One message, ready to render
+#[derive(Debug, Clone, PartialEq)]
+struct ExportedMessage {
+    from: String,
+    to: Option<String>,
+    cc: Option<String>,
+    date: Option<String>,
+    body: String,
+}
+
+// ============================================================================
+// Body Text
+// ============================================================================
+
+/// How formatting survives the conversion to text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextStyle {
+    Markdown,
+    Plain,
+}
+
+fn collapse_whitespace(text: &str, out: &mut String) {
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+}
+
+fn write_children(node: &NodeRef, style: TextStyle, out: &mut String) {
+    for child in node.children() {
+        write_node(&child, style, out);
+    }
+}
+
+fn write_node(node: &NodeRef, style: TextStyle, out: &mut String) {
+    if let Some(text) = node.as_text() {
+        collapse_whitespace(&text.borrow(), out);
+        return;
+    }
+    let Some(element) = node.as_element() else {
+        write_children(node, style, out);
+        return;
+    };
+    let markdown = style == TextStyle::Markdown;
+    let tag = element.name.local.as_ref();
+    match tag {
+        "br" => out.push('\n'),
+        "hr" => out.push_str("\n\n---\n\n"),
+        // Quoted earlier messages
+        "blockquote" => {}
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            out.push_str("\n\n");
+            if markdown {
+                let level = tag[1..].parse().unwrap_or(1);
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            write_children(node, style, out);
+            out.push_str("\n\n");
+        }
+        "li" => {
+            out.push_str("\n- ");
+            write_children(node, style, out);
+        }
+        "td" | "th" => {
+            write_children(node, style, out);
+            out.push(' ');
+        }
+        "b" | "strong" | "i" | "em" if markdown => {
+            let marker = if matches!(tag, "b" | "strong") {
+                "**"
+            } else {
+                "*"
+            };
+            let mut inner = String::new();
+            write_children(node, style, &mut inner);
+            let trimmed = inner.trim();
+            if trimmed.is_empty() {
+                out.push_str(&inner);
+            } else {
+                out.push_str(&format!("{}{}{}", marker, trimmed, marker));
+            }
+        }
+        "a" => {
+            let href = element.attributes.borrow().get("href").map(str::to_string);
+            let mut inner = String::new();
+            write_children(node, style, &mut inner);
+            let text = inner.trim();
+            match href.filter(|h| !h.starts_with('#') && h != text && !text.is_empty()) {
+                Some(href) if markdown => out.push_str(&format!("[{}]({})", text, href)),
+                Some(href) => out.push_str(&format!("{} ({})", text, href)),
+                None => out.push_str(&inner),
+            }
+        }
+        "pre" => {
+            let fence = if markdown { "```\n" } else { "" };
+            out.push_str(&format!("\n\n{}", fence));
+            out.push_str(node.text_contents().trim_end());
+            out.push_str(&format!("\n{}\n", fence));
+        }
+        "p" | "div" | "table" | "tr" | "ul" | "ol" | "center" | "dl" | "dt" | "dd" => {
+            out.push_str("\n\n");
+            write_children(node, style, out);
+            out.push_str("\n\n");
+        }
+        _ => write_children(node, style, out),
+    }
+}
+
+/// Trim lines and collapse runs of blank lines
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Sanitized message HTML as text
+fn html_text(html: &str, style: TextStyle) -> String {
+    use kuchikiki::traits::TendrilSink;
+
+    let sanitized = processing::sanitize_html_with(html, false);
+    let document = kuchikiki::parse_html().one(sanitized.html).document_node;
+    let mut out = String::new();
+    match document.select_first("body") {
+        Ok(body) => write_children(body.as_node(), style, &mut out),
+        Err(()) => write_children(&document, style, &mut out),
+    }
+    tidy(&out)
+}
+
+/// A message's own text, without the quoted history
+fn body_text(message: &GmailMessage, style: TextStyle) -> String {
+    let text = match message.payload.as_ref().and_then(gmail::message_body) {
+        Some(MessageBody::Html(html)) => html_text(&html, style),
+        Some(MessageBody::Text(text)) => tidy(&text),
+        None => message.snippet.clone(),
+    };
+    processing::latest_message(&text).trim().to_string()
+}
+
+fn exported_messages(messages: &[GmailMessage], style: TextStyle) -> Vec<ExportedMessage> {
+    let mut messages: Vec<&GmailMessage> = messages.iter().collect();
+    messages.sort_by_key(|m| {
+        m.internal_date
+            .as_deref()
+            .and_then(|d| d.parse::<i64>().ok())
+            .unwrap_or_default()
+    });
+    let header = |m: &GmailMessage, name: &str| {
+        analytics::header(m, name)
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    messages
+        .into_iter()
+        .map(|m| ExportedMessage {
+            from: header(m, "From").unwrap_or_else(|| "Unknown sender".to_string()),
+            to: header(m, "To"),
+            cc: header(m, "Cc"),
+            date: header(m, "Date"),
+            body: body_text(m, style),
+        })
+        .collect()
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+fn render_markdown(subject: &str, messages: &[ExportedMessage]) -> String {
+    let mut out = format!("# {}\n", subject);
+    for message in messages {
+        out.push_str(&format!("\n---\n\n**From:** {}  \n", message.from));
+        if let Some(to) = &message.to {
+            out.push_str(&format!("**To:** {}  \n", to));
+        }
+        if let Some(cc) = &message.cc {
+            out.push_str(&format!("**Cc:** {}  \n", cc));
+        }
+        if let Some(date) = &message.date {
+            out.push_str(&format!("**Date:** {}  \n", date));
+        }
+        out.push_str(&format!("\n{}\n", message.body));
+    }
+    out
+}
+
+/// Characters per line at the PDF's font size
+const PDF_LINE_CHARS: usize = 95;
+/// Lines per PDF page
+const PDF_PAGE_LINES: usize = 52;
+
+/// A PDF text line, bold or regular
+type PdfLine = (String, bool);
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            // Words longer than a line (URLs) are split
+            while word.chars().count() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word
+                    .char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(i, _)| i);
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn pdf_lines(subject: &str, messages: &[ExportedMessage]) -> Vec<PdfLine> {
+    let mut lines: Vec<PdfLine> = wrap(subject, PDF_LINE_CHARS)
+        .into_iter()
+        .map(|line| (line, true))
+        .collect();
+    for message in messages {
+        lines.push((String::new(), false));
+        lines.push(("_".repeat(PDF_LINE_CHARS / 2), false));
+        lines.push((String::new(), false));
+        let headers = [
+            ("From", Some(&message.from)),
+            ("To", message.to.as_ref()),
+            ("Cc", message.cc.as_ref()),
+            ("Date", message.date.as_ref()),
+        ];
+        for (name, value) in headers {
+            if let Some(value) = value {
+                let text = format!("{}: {}", name, value);
+                lines.extend(
+                    wrap(&text, PDF_LINE_CHARS)
+                        .into_iter()
+                        .map(|line| (line, name == "From")),
+                );
+            }
+        }
+        lines.push((String::new(), false));
+        lines.extend(
+            wrap(&message.body, PDF_LINE_CHARS)
+                .into_iter()
+                .map(|line| (line, false)),
+        );
+    }
+    lines
+}
+
+/// A PDF string literal in WinAnsi encoding
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        let c = match c {
+            '\u{2018}' | '\u{2019}' => '\'',
+            '\u{201C}' | '\u{201D}' => '"',
+            '\u{2013}' | '\u{2014}' => '-',
+            '\u{2022}' => '*',
+            '\u{2026}' => '.',
+            c => c,
+        };
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{A0}'..='\u{FF}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// A text-only PDF (US Letter, Helvetica 10pt)
+fn render_pdf(lines: &[PdfLine]) -> Vec<u8> {
+    let pages: Vec<&[PdfLine]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(PDF_PAGE_LINES).collect()
+    };
+
+    // 1: catalog, 2: page tree, 3-4: fonts, then a page and its content per page
+    let page_id = |i: usize| 5 + 2 * i;
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", page_id(i)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = String::from("BT\n13 TL\n54 738 Td\n");
+        for (text, bold) in page.iter() {
+            let font = if *bold { "F2" } else { "F1" };
+            content.push_str(&format!("/{} 10 Tf {} Tj T*\n", font, pdf_string(text)));
+        }
+        content.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            page_id(i) + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+/// File name stem from the subject (`thread-<id>` without one)
+fn file_stem(subject: &str, thread_id: &str) -> String {
+    let mut stem = String::new();
+    for c in subject.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+        if stem.chars().count() >= MAX_FILE_STEM {
+            break;
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    if stem.is_empty() {
+        format!("thread-{}", thread_id)
+    } else {
+        stem.to_string()
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Export a conversation as a Markdown or PDF document in the `dest` directory
+///
+/// Sensitive: confirmed with the OS prompt when the gate is on.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_thread(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    format: ExportFormat,
+    dest: String,
+    mailbox: Option<String>,
+) -> Result<ThreadExport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    os_gate::confirm(&app, SensitiveAction::ExportThread).await?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let thread = gmail::fetch_thread_full(&token_store, &client, &mailbox, &thread_id).await?;
+    let raw = thread.messages.unwrap_or_default();
+    let subject = raw
+        .iter()
+        .find_map(|m| analytics::header(m, "Subject"))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("(no subject)")
+        .to_string();
+
+    let bytes = match format {
+        ExportFormat::Markdown => {
+            let messages = exported_messages(&raw, TextStyle::Markdown);
+            render_markdown(&subject, &messages).into_bytes()
+        }
+        ExportFormat::Pdf => {
+            let messages = exported_messages(&raw, TextStyle::Plain);
+            render_pdf(&pdf_lines(&subject, &messages))
+        }
+    };
+
+    let path = Path::new(&dest).join(format!(
+        "{}.{}",
+        file_stem(&subject, &thread_id),
+        format.extension()
+    ));
+    storage::fs::write_atomic(&path, &bytes)?;
+
+    Ok(ThreadExport {
+        path: path.to_string_lossy().into_owned(),
+        format,
+        message_count: raw.len(),
+        bytes_written: bytes.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_text_and_pdf() {
+        let html = r#"<h2>Plan</h2><p>Hi <b>team</b>, see <a href="https://example.com/doc">the doc</a>.</p>
+            <ul><li>One</li><li>Two</li></ul>
+            <blockquote>On Mon, Ann wrote: old text</blockquote>"#;
+        assert_eq!(
+            html_text(html, TextStyle::Markdown),
+            "## Plan\n\nHi **team**, see [the doc](https://example.com/doc).\n\n- One\n- Two"
+        );
+        assert_eq!(
+            html_text(html, TextStyle::Plain),
+            "Plan\n\nHi team, see the doc (https://example.com/doc).\n\n- One\n- Two"
+        );
+
+        assert_eq!(pdf_string("a (b) \\ é ✓"), "(a \\(b\\) \\\\ \\351 ?)");
+        let pdf = render_pdf(&[("Title".to_string(), true)]);
+        let pdf = String::from_utf8(pdf).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/F2 10 Tf (Title) Tj T*"));
+        assert!(pdf.ends_with("%%EOF\n"));
+
+        assert_eq!(
+            file_stem("Re: Q3 budget / review!", "t1"),
+            "re-q3-budget-review"
+        );
+        assert_eq!(file_stem("???", "t1"), "thread-t1");
+    }
+}
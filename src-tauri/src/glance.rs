@@ -0,0 +1,142 @@
+//! Glance data provider for the menubar/widget mini-view
+//!
+//! Reads only from the in-memory cache populated by the Google commands and
+//! never touches the network, so it stays fast enough for a tiny
+//! always-available window.
+
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::cache::{CacheState, INBOX_SUMMARY_KEY, TASKS_KEY_PREFIX, TODAY_EVENTS_KEY};
+use crate::google::types::{ProcessedEvent, Task, ThreadSummary};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Number of tasks shown in the glance view
+const TOP_TASKS_LIMIT: usize = 3;
+/// Priority score above which an unread thread counts as priority
+const PRIORITY_THRESHOLD: f32 = 0.6;
+
+/// Compact data for the mini-view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlanceData {
+    pub next_event: Option<ProcessedEvent>,
+    pub top_tasks: Vec<Task>,
+    pub priority_unread_count: usize,
+    /// False when nothing has been cached yet (e.g., right after launch)
+    pub has_data: bool,
+    pub generated_at_ms: i64,
+}
+
+/// Pick the first event that has not ended yet
+fn next_event(events: Vec<ProcessedEvent>, now_ms: i64) -> Option<ProcessedEvent> {
+    events
+        .into_iter()
+        .filter_map(|e| {
+            // All-day events only carry a date and are skipped here
+            let start = chrono::DateTime::parse_from_rfc3339(&e.start_time).ok()?;
+            let end = chrono::DateTime::parse_from_rfc3339(&e.end_time).ok()?;
+            (end.timestamp_millis() > now_ms).then_some((start.timestamp_millis(), e))
+        })
+        .min_by_key(|(start, _)| *start)
+        .map(|(_, e)| e)
+}
+
+/// Outstanding tasks ordered by due date (undated last)
+fn top_tasks(mut tasks: Vec<Task>) -> Vec<Task> {
+    tasks.retain(|t| t.status.as_deref() != Some("completed"));
+    tasks.sort_by(|a, b| match (&a.due, &b.due) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    tasks.truncate(TOP_TASKS_LIMIT);
+    tasks
+}
+
+/// Get glance data for the signed-in account from cache only (never hits the network)
+#[tauri::command]
+pub async fn get_glance_data(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    cache: State<'_, CacheState>,
+) -> Result<GlanceData, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let account = token_store.account_context().await?;
+
+    let events: Option<Vec<ProcessedEvent>> =
+        cache.0.get_json(&account.cache_key(TODAY_EVENTS_KEY));
+    let threads: Option<Vec<ThreadSummary>> =
+        cache.0.get_json(&account.cache_key(INBOX_SUMMARY_KEY));
+    let task_lists = cache.0.get_prefix(&account.cache_key(TASKS_KEY_PREFIX));
+
+    let has_data = events.is_some() || threads.is_some() || !task_lists.is_empty();
+
+    let tasks: Vec<Task> = task_lists
+        .into_iter()
+        .filter_map(|(_, json)| serde_json::from_str::<Vec<Task>>(&json).ok())
+        .flatten()
+        .collect();
+
+    let priority_unread_count = threads
+        .unwrap_or_default()
+        .iter()
+        .filter(|t| t.is_unread && t.priority_score > PRIORITY_THRESHOLD)
+        .count();
+
+    Ok(GlanceData {
+        next_event: next_event(events.unwrap_or_default(), now),
+        top_tasks: top_tasks(tasks),
+        priority_unread_count,
+        has_data,
+        generated_at_ms: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: &str, start: &str, end: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            id: id.to_string(),
+            title: id.to_string(),
+            start_time: start.to_string(),
+            end_time: end.to_string(),
+            location: None,
+            meeting_link: None,
+            attendees_count: 0,
+            source: "google".to_string(),
+            ical_uid: None,
+            also_on: Vec::new(),
+            color_id: None,
+            background_color: None,
+            foreground_color: None,
+            transparency: None,
+            visibility: None,
+            links: Vec::new(),
+            agenda: Vec::new(),
+            dial_ins: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_next_event_skips_finished_and_all_day() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-15T10:30:00Z")
+            .unwrap()
+            .timestamp_millis();
+        let events = vec![
+            event("all-day", "2026-01-15", "2026-01-16"),
+            event("past", "2026-01-15T08:00:00Z", "2026-01-15T09:00:00Z"),
+            event("later", "2026-01-15T14:00:00Z", "2026-01-15T15:00:00Z"),
+            event("ongoing", "2026-01-15T10:00:00Z", "2026-01-15T11:00:00Z"),
+        ];
+
+        assert_eq!(
+            next_event(events, now).map(|e| e.id),
+            Some("ongoing".into())
+        );
+    }
+}
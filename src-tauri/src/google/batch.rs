@@ -0,0 +1,250 @@
+//! Google batch requests (multipart/mixed)
+//!
+//! A batch sends up to `MAX_BATCH_SIZE` API calls in one HTTP request to the
+//! API's `/batch` endpoint: each call is an `application/http` part, and the
+//! response has one part per call carrying its own status line and body. Parts
+//! are tagged with a Content-ID so results map back to the calls even if Google
+//! reorders them.
+//!
+//! This module only encodes and decodes batches; `GoogleClient::batch` sends
+//! them.
+
+use super::GoogleApiError;
+use rand::RngCore;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+/// Calls Google accepts in one batch
+pub const MAX_BATCH_SIZE: usize = 50;
+
+/// A call sent as part of a batch
+#[derive(Debug, Clone)]
+pub struct BatchCall {
+    /// Caller's ID, returned with the result
+    pub id: String,
+    pub method: String,
+    /// Full API URL, as for the other `GoogleClient` methods
+    pub url: String,
+    pub body: Option<Value>,
+}
+
+/// Result of a batched call
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub id: String,
+    pub result: Result<Value, GoogleApiError>,
+}
+
+/// Batch endpoint serving `api_base` (e.g. `.../gmail/v1` -> `.../batch/gmail/v1`)
+pub fn batch_url(api_base: &str) -> String {
+    let mut parts = api_base.splitn(4, '/');
+    let origin: Vec<&str> = parts.by_ref().take(3).collect();
+    match parts.next() {
+        Some(path) => format!("{}/batch/{}", origin.join("/"), path),
+        None => format!("{}/batch", api_base),
+    }
+}
+
+/// Origin-relative path of an API URL
+fn request_path(url: &str) -> &str {
+    url.splitn(4, '/')
+        .nth(3)
+        .map_or("/", |path| &url[url.len() - path.len() - 1..])
+}
+
+pub fn new_boundary() -> String {
+    let mut bytes = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let suffix: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("batch_{}", suffix)
+}
+
+/// Multipart body of a batch; part `i` gets Content-ID `<item{i}>`
+pub fn encode(boundary: &str, calls: &[BatchCall]) -> Result<Vec<u8>, GoogleApiError> {
+    let mut body = String::new();
+    for (i, call) in calls.iter().enumerate() {
+        body.push_str(&format!(
+            "--{}\r\nContent-Type: application/http\r\nContent-ID: <item{}>\r\n\r\n{} {} HTTP/1.1\r\n",
+            boundary,
+            i,
+            call.method.to_uppercase(),
+            request_path(&call.url)
+        ));
+        match &call.body {
+            Some(json) => {
+                let json = serde_json::to_string(json).map_err(|e| {
+                    GoogleApiError::Other(format!("Failed to serialize batch call: {}", e))
+                })?;
+                body.push_str(&format!(
+                    "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}\r\n",
+                    json.len(),
+                    json
+                ));
+            }
+            None => body.push_str("\r\n"),
+        }
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    Ok(body.into_bytes())
+}
+
+/// Boundary declared by a multipart Content-Type header
+pub fn response_boundary(content_type: &str) -> Option<String> {
+    content_type.split(';').find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        name.eq_ignore_ascii_case("boundary")
+            .then(|| value.trim_matches('"').to_string())
+    })
+}
+
+/// Split `text` at its first blank line into (headers, rest)
+fn split_headers(text: &str) -> (&str, &str) {
+    let crlf = text.find("\r\n\r\n").map(|i| (i, 4));
+    let lf = text.find("\n\n").map(|i| (i, 2));
+    match [crlf, lf].into_iter().flatten().min_by_key(|(i, _)| *i) {
+        Some((i, len)) => (&text[..i], &text[i + len..]),
+        None => (text, ""),
+    }
+}
+
+fn header_value<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Index of the call a response part answers, from `<response-item{i}>`
+fn part_index(content_id: &str) -> Option<usize> {
+    content_id
+        .trim_matches(|c| c == '<' || c == '>')
+        .strip_prefix("response-item")?
+        .parse()
+        .ok()
+}
+
+/// Decode one response part into (call index, result)
+fn decode_part(part: &str) -> Option<(usize, Result<Value, GoogleApiError>)> {
+    let (part_headers, http) = split_headers(part);
+    let index = part_index(header_value(part_headers, "Content-ID")?)?;
+    let (head, body) = split_headers(http);
+    let status = head
+        .lines()
+        .next()?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u16>()
+        .ok()
+        .and_then(|code| StatusCode::from_u16(code).ok())?;
+    let body = body.trim();
+
+    let result = if !status.is_success() {
+        Err(GoogleApiError::from_response(status, body.to_string()))
+    } else if body.is_empty() {
+        Ok(Value::Null)
+    } else {
+        serde_json::from_str(body).map_err(|e| {
+            GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })
+    };
+    Some((index, result))
+}
+
+/// Match the parts of a batch response with `calls`
+///
+/// Calls without a part in the response fail with `InvalidResponse`.
+pub fn decode(boundary: &str, body: &str, calls: &[BatchCall]) -> Vec<BatchOutcome> {
+    let mut results: Vec<Option<Result<Value, GoogleApiError>>> =
+        calls.iter().map(|_| None).collect();
+    let delimiter = format!("--{}", boundary);
+    for part in body.split(&delimiter).skip(1) {
+        if part.starts_with("--") {
+            break;
+        }
+        if let Some((index, result)) = decode_part(part.trim_start()) {
+            if let Some(slot) = results.get_mut(index) {
+                *slot = Some(result);
+            }
+        }
+    }
+
+    calls
+        .iter()
+        .zip(results)
+        .map(|(call, result)| BatchOutcome {
+            id: call.id.clone(),
+            result: result.unwrap_or_else(|| {
+                Err(GoogleApiError::InvalidResponse(
+                    "Missing from the batch response".to_string(),
+                ))
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::GMAIL_API_BASE;
+
+    fn call(id: &str, url: &str) -> BatchCall {
+        BatchCall {
+            id: id.to_string(),
+            method: "get".to_string(),
+            url: url.to_string(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_round_trip() {
+        assert_eq!(
+            batch_url(GMAIL_API_BASE),
+            "https://gmail.googleapis.com/batch/gmail/v1"
+        );
+        let calls = vec![
+            call(
+                "t1",
+                &format!("{}/users/me/threads/t1?format=metadata", GMAIL_API_BASE),
+            ),
+            call("t2", &format!("{}/users/me/threads/t2", GMAIL_API_BASE)),
+            call("t3", &format!("{}/users/me/threads/t3", GMAIL_API_BASE)),
+        ];
+
+        let encoded = String::from_utf8(encode("b0", &calls).unwrap()).unwrap();
+        assert!(encoded.contains(
+            "Content-ID: <item0>\r\n\r\nGET /gmail/v1/users/me/threads/t1?format=metadata HTTP/1.1\r\n"
+        ));
+        assert!(encoded.ends_with("--b0--\r\n"));
+
+        assert_eq!(
+            response_boundary("multipart/mixed; boundary=batch_xyz").as_deref(),
+            Some("batch_xyz")
+        );
+        // Parts come back out of order; t3 is missing
+        let response = "--batch_xyz\r\n\
+Content-Type: application/http\r\n\
+Content-ID: <response-item1>\r\n\r\n\
+HTTP/1.1 404 Not Found\r\n\
+Content-Type: application/json\r\n\r\n\
+{\"error\": {\"code\": 404, \"message\": \"Not Found\"}}\r\n\
+--batch_xyz\r\n\
+Content-Type: application/http\r\n\
+Content-ID: <response-item0>\r\n\r\n\
+HTTP/1.1 200 OK\r\n\
+Content-Type: application/json; charset=UTF-8\r\n\r\n\
+{\"id\": \"t1\"}\r\n\
+--batch_xyz--\r\n";
+
+        let outcomes = decode("batch_xyz", response, &calls);
+        assert_eq!(outcomes[0].id, "t1");
+        assert_eq!(outcomes[0].result.as_ref().unwrap()["id"], "t1");
+        assert_eq!(outcomes[1].result.as_ref().unwrap_err().status(), Some(404));
+        assert!(matches!(
+            outcomes[2].result,
+            Err(GoogleApiError::InvalidResponse(_))
+        ));
+    }
+}
@@ -2,21 +2,381 @@
 //!
 //! Endpoints:
 //! - events.list: List calendar events for a time range
+//! - colors.get / calendarList.get: Event and calendar colors for the agenda
+//! - events.list (workingLocation): Office / home day of a date
+//! - events.insert: Create an event proposed in an email thread
+//! - events.get: RSVP rollup of a meeting
 
-use super::types::{CalendarEvent, CalendarEventsResponse, ProcessedEvent};
-use super::{GoogleClient, CALENDAR_API_BASE};
+use super::fields::Fields;
+use super::gmail;
+use super::mailbox::Mailbox;
+use super::types::{
+    CalendarColors, CalendarEvent, CalendarEventsResponse, CalendarListEntry, ColorDefinition,
+    EventAttendee, EventDateTime, EventLinkKind, GmailThreadDetail, LabeledLocation,
+    NewCalendarEvent, ProcessedEvent,
+};
+use super::{deadline, invalidation, GoogleApiError, GoogleClient, CALENDAR_API_BASE};
+use crate::analytics;
+use crate::app_lock::AppLockState;
+use crate::auth::capabilities::{self, Feature};
 use crate::auth::TokenStore;
-use chrono::{Local, TimeZone};
-use tauri::State;
+use crate::cache::{CacheState, API_RESPONSE_TTL_SECS, TODAY_EVENTS_KEY};
+use crate::events::DataEvent;
+use crate::ical::{self, IcalState};
+use crate::natural_date::{self, DateMention};
+use crate::processing::{self, EventDescription};
+use crate::storage::{LocalStorage, THREAD_EVENT_LINKS};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
 
-/// Get today's calendar events
+/// Length of events created from threads when none is given
+const DEFAULT_EVENT_MINUTES: u32 = 30;
+/// Event and calendar color palette, scoped per account
+const PALETTE_CACHE_KEY: &str = "calendar:palette";
+/// Color definitions rarely change
+const PALETTE_CACHE_TTL_SECS: u64 = 86_400;
+/// `eventType` of working-location events
+const WORKING_LOCATION_EVENT_TYPE: &str = "workingLocation";
+/// Parts of events.list responses `CalendarEventsResponse` reads
+pub const EVENT_LIST_FIELDS: Fields<CalendarEventsResponse> = Fields::new(
+    "items(id,iCalUID,summary,description,location,start,end,\
+attendees(email,displayName,responseStatus,self,resource),organizer(email,displayName,self),\
+hangoutLink,htmlLink,status,colorId,transparency,visibility,eventType,\
+workingLocationProperties,conferenceData/entryPoints(entryPointType,uri)),\
+nextPageToken,timeZone",
+);
+
+/// Sort key for an event start: RFC3339 date-time or all-day date
+pub(crate) fn start_sort_key(start_time: &str) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(start_time)
+        .map(|d| d.timestamp_millis())
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(start_time, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .and_then(|d| Local.from_local_datetime(&d).earliest())
+                .map(|d| d.timestamp_millis())
+        })
+        .unwrap_or(i64::MAX)
+}
+
+// ============================================================================
+// Working Location
+// ============================================================================
+
+/// Where the user works on a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkingLocationKind {
+    Home,
+    Office,
+    Custom,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingLocation {
+    pub kind: WorkingLocationKind,
+    /// Office or custom location label
+    pub label: Option<String>,
+}
+
+/// Working location of a day ("office day" / "home day")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkingDay {
+    pub date: String,
+    pub location: Option<WorkingLocation>,
+}
+
+/// Working location set by a working-location event
+pub fn working_location_of(event: &CalendarEvent) -> Option<WorkingLocation> {
+    if event.event_type.as_deref() != Some(WORKING_LOCATION_EVENT_TYPE)
+        || event.status.as_deref() == Some("cancelled")
+    {
+        return None;
+    }
+    let properties = event.working_location_properties.as_ref()?;
+    let label = |l: &Option<LabeledLocation>| l.as_ref().and_then(|l| l.label.clone());
+    let (kind, label) = match properties.kind.as_deref()? {
+        "homeOffice" => (WorkingLocationKind::Home, None),
+        "officeLocation" => (
+            WorkingLocationKind::Office,
+            label(&properties.office_location),
+        ),
+        "customLocation" => (
+            WorkingLocationKind::Custom,
+            label(&properties.custom_location),
+        ),
+        _ => return None,
+    };
+    Some(WorkingLocation { kind, label })
+}
+
+/// The day's working location: an all-day entry wins, otherwise the earliest
+pub fn day_working_location(events: &[CalendarEvent]) -> Option<WorkingLocation> {
+    let mut located: Vec<(&CalendarEvent, WorkingLocation)> = events
+        .iter()
+        .filter_map(|e| Some((e, working_location_of(e)?)))
+        .collect();
+    located.sort_by_key(|(e, _)| {
+        let start = e.start.as_ref();
+        let all_day = start.is_some_and(|s| s.date.is_some());
+        let start_time = start
+            .and_then(|s| s.date_time.as_deref())
+            .map(start_sort_key)
+            .unwrap_or(i64::MIN);
+        (!all_day, start_time)
+    });
+    located.into_iter().next().map(|(_, location)| location)
+}
+
+/// Primary-calendar events on a local day, optionally of one event type
+pub async fn events_on(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    date: NaiveDate,
+    event_type: Option<&str>,
+) -> Result<Vec<CalendarEvent>, GoogleApiError> {
+    let bound = |d: NaiveDate| {
+        d.and_hms_opt(0, 0, 0)
+            .and_then(|d| Local.from_local_datetime(&d).earliest())
+            .map(|d| d.timestamp_millis())
+            .ok_or_else(|| "Failed to create date".to_string())
+    };
+    let end = date.succ_opt().ok_or("Failed to create date")?;
+    events_between(token_store, client, bound(date)?, bound(end)?, event_type).await
+}
+
+/// Primary-calendar events overlapping `[start_ms, end_ms)` (at most 2500)
+pub async fn events_between(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    start_ms: i64,
+    end_ms: i64,
+    event_type: Option<&str>,
+) -> Result<Vec<CalendarEvent>, GoogleApiError> {
+    let bound = |ms: i64| {
+        chrono::DateTime::from_timestamp_millis(ms)
+            .map(|d| d.with_timezone(&Local).to_rfc3339())
+            .ok_or_else(|| "Invalid timestamp".to_string())
+    };
+    let mut url = format!(
+        "{}/calendars/primary/events?timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime&maxResults=2500",
+        CALENDAR_API_BASE,
+        urlencoding::encode(&bound(start_ms)?),
+        urlencoding::encode(&bound(end_ms)?)
+    );
+    if let Some(event_type) = event_type {
+        url.push_str(&format!("&eventTypes={}", event_type));
+    }
+    let response = client
+        .get_fields(&url, token_store, EVENT_LIST_FIELDS)
+        .await?;
+    Ok(response.items.unwrap_or_default())
+}
+
+// ============================================================================
+// Colors
+// ============================================================================
+
+/// Event colors and the primary calendar's own color
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EventPalette {
+    event: HashMap<String, ColorDefinition>,
+    calendar: Option<ColorDefinition>,
+}
+
+impl EventPalette {
+    /// The event's own color, else its calendar's
+    fn resolve(&self, color_id: Option<&str>) -> Option<&ColorDefinition> {
+        color_id
+            .and_then(|id| self.event.get(id))
+            .or(self.calendar.as_ref())
+    }
+}
+
+/// Fetch (or read from cache) the color palette for the primary calendar
+async fn event_palette(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    cache: &CacheState,
+) -> Result<EventPalette, GoogleApiError> {
+    let account = token_store.account_context().await?;
+    let cache_key = account.cache_key(PALETTE_CACHE_KEY);
+    if let Some(cached) = cache.0.get_json::<EventPalette>(&cache_key) {
+        return Ok(cached);
+    }
+
+    let colors: CalendarColors = client
+        .get(&format!("{}/colors", CALENDAR_API_BASE), token_store)
+        .await?;
+    let entry: CalendarListEntry = client
+        .get(
+            &format!("{}/users/me/calendarList/primary", CALENDAR_API_BASE),
+            token_store,
+        )
+        .await?;
+
+    // Custom calendar colors only exist as hex values on the list entry
+    let calendar = match (entry.background_color, entry.foreground_color) {
+        (Some(background), Some(foreground)) => Some(ColorDefinition {
+            background,
+            foreground,
+        }),
+        _ => entry
+            .color_id
+            .and_then(|id| colors.calendar.get(&id).cloned()),
+    };
+    let palette = EventPalette {
+        event: colors.event,
+        calendar,
+    };
+    cache
+        .0
+        .set_json(&cache_key, &palette, PALETTE_CACHE_TTL_SECS);
+    Ok(palette)
+}
+
+/// Join link for an event, for Meet as well as Zoom/Teams/Webex/Jitsi
+///
+/// Checked in order: the Meet link, the conference video entry point (set by
+/// conferencing add-ons), then meeting URLs pasted in the location or the
+/// description.
+fn meeting_link(event: &CalendarEvent, description: &EventDescription) -> Option<String> {
+    let conference = || {
+        event
+            .conference_data
+            .as_ref()?
+            .entry_points
+            .as_ref()?
+            .iter()
+            .find(|e| e.entry_point_type.as_deref() == Some("video"))?
+            .uri
+            .clone()
+    };
+    event
+        .hangout_link
+        .clone()
+        .or_else(conference)
+        .or_else(|| {
+            event
+                .location
+                .as_deref()
+                .and_then(processing::find_meeting_link)
+        })
+        .or_else(|| {
+            description
+                .links
+                .iter()
+                .find(|l| l.kind == EventLinkKind::Meeting)
+                .map(|l| l.url.clone())
+        })
+}
+
+fn process_event(event: CalendarEvent, palette: &EventPalette) -> ProcessedEvent {
+    let time = |t: Option<&EventDateTime>| {
+        t.and_then(|t| t.date_time.clone().or(t.date.clone()))
+            .unwrap_or_default()
+    };
+    let color = palette.resolve(event.color_id.as_deref()).cloned();
+    let description = event
+        .description
+        .as_deref()
+        .map(processing::parse_event_description)
+        .unwrap_or_default();
+    let meeting_link = meeting_link(&event, &description);
+
+    ProcessedEvent {
+        title: event.summary.unwrap_or_else(|| "(No title)".to_string()),
+        start_time: time(event.start.as_ref()),
+        end_time: time(event.end.as_ref()),
+        location: event.location,
+        meeting_link,
+        attendees_count: event.attendees.map(|a| a.len() as u32).unwrap_or(0),
+        source: "google".to_string(),
+        ical_uid: event.ical_uid,
+        also_on: Vec::new(),
+        color_id: event.color_id,
+        background_color: color.as_ref().map(|c| c.background.clone()),
+        foreground_color: color.map(|c| c.foreground),
+        transparency: event.transparency,
+        visibility: event.visibility,
+        links: description.links,
+        agenda: description.agenda,
+        dial_ins: description.dial_ins,
+        id: event.id,
+    }
+}
+
+fn normalized_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn same_time(a: &str, b: &str) -> bool {
+    a == b || (start_sort_key(a) == start_sort_key(b) && start_sort_key(a) != i64::MAX)
+}
+
+/// Copies of one meeting: same iCalUID or same title, at the same time
+fn is_duplicate(a: &ProcessedEvent, b: &ProcessedEvent) -> bool {
+    if !same_time(&a.start_time, &b.start_time) {
+        return false;
+    }
+    match (&a.ical_uid, &b.ical_uid) {
+        (Some(x), Some(y)) if x == y => true,
+        _ => {
+            same_time(&a.end_time, &b.end_time)
+                && normalized_title(&a.title) == normalized_title(&b.title)
+        }
+    }
+}
+
+/// Merge copies of the same meeting from different calendars
+///
+/// The first copy is kept (Google events come before subscriptions) and
+/// records the other copies' sources in `also_on`, borrowing any meeting link
+/// or location it lacks.
+fn merge_duplicate_events(events: Vec<ProcessedEvent>) -> Vec<ProcessedEvent> {
+    let mut merged: Vec<ProcessedEvent> = Vec::with_capacity(events.len());
+    for event in events {
+        let Some(kept) = merged.iter_mut().find(|kept| is_duplicate(kept, &event)) else {
+            merged.push(event);
+            continue;
+        };
+        if event.source != kept.source && !kept.also_on.contains(&event.source) {
+            kept.also_on.push(event.source);
+        }
+        if kept.meeting_link.is_none() {
+            kept.meeting_link = event.meeting_link;
+        }
+        if kept.location.is_none() {
+            kept.location = event.location;
+        }
+    }
+    merged
+}
+
+/// Get today's calendar events, merged with iCal subscription events
+///
+/// A meeting found on several calendars is returned once, annotated with the
+/// other calendars in `also_on`.
 #[tauri::command]
 pub async fn get_today_events(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
-) -> Result<Vec<ProcessedEvent>, String> {
-    let token = token_store.get_access_token().await?;
-
+    cache: State<'_, CacheState>,
+    ical_state: State<'_, IcalState>,
+    deadline_ms: Option<u64>,
+) -> Result<Vec<ProcessedEvent>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     // Get start and end of today in RFC3339 format
     let now = Local::now();
     let today_start = now
@@ -48,50 +408,88 @@ pub async fn get_today_events(
         urlencoding::encode(&time_max)
     );
 
-    let response: CalendarEventsResponse = client.get(&url, &token).await?;
+    let (response, palette) = deadline::within(deadline_ms, async {
+        let response = client
+            .get_fields(&url, &token_store, EVENT_LIST_FIELDS)
+            .await?;
+        // Colors are cosmetic: a palette failure only leaves events uncolored
+        let palette = event_palette(&token_store, &client, &cache)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load calendar colors: {}", e);
+                EventPalette::default()
+            });
+        Ok::<_, GoogleApiError>((response, palette))
+    })
+    .await?;
 
     let events = response.items.unwrap_or_default();
 
-    let processed: Vec<ProcessedEvent> = events
+    // Working-location events describe the day, not a meeting
+    let mut processed: Vec<ProcessedEvent> = events
         .into_iter()
         .filter(|e| e.status.as_deref() != Some("cancelled"))
-        .map(|e| {
-            let start_time = e
-                .start
-                .as_ref()
-                .and_then(|s| s.date_time.clone().or(s.date.clone()))
-                .unwrap_or_default();
-            let end_time = e
-                .end
-                .as_ref()
-                .and_then(|s| s.date_time.clone().or(s.date.clone()))
-                .unwrap_or_default();
-
-            ProcessedEvent {
-                id: e.id,
-                title: e.summary.unwrap_or_else(|| "(No title)".to_string()),
-                start_time,
-                end_time,
-                location: e.location,
-                meeting_link: e.hangout_link,
-                attendees_count: e.attendees.map(|a| a.len() as u32).unwrap_or(0),
-            }
-        })
+        .filter(|e| e.event_type.as_deref() != Some(WORKING_LOCATION_EVENT_TYPE))
+        .map(|e| process_event(e, &palette))
         .collect();
 
+    // Merge external iCal subscriptions (a store failure only drops them)
+    if let Ok(subscriptions) = ical::load_subscriptions(&app) {
+        processed.extend(ical_state.today_events(&subscriptions));
+    }
+    let mut processed = merge_duplicate_events(processed);
+    processed.sort_by_key(|e| start_sort_key(&e.start_time));
+
+    let account = token_store.account_context().await?;
+    cache.0.set_json(
+        &account.cache_key(TODAY_EVENTS_KEY),
+        &processed,
+        API_RESPONSE_TTL_SECS,
+    );
+    crate::perf::mark_plan_part(crate::perf::PlanPart::Events);
+
     Ok(processed)
 }
 
+/// Get the user's working location on `date` (YYYY-MM-DD)
+///
+/// `location` is None when no working location is set for the day.
+#[tauri::command]
+pub async fn get_working_location(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    date: String,
+) -> Result<WorkingDay, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let day = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|_| "Invalid date, expected YYYY-MM-DD".to_string())?;
+    let events = events_on(
+        &token_store,
+        &client,
+        day,
+        Some(WORKING_LOCATION_EVENT_TYPE),
+    )
+    .await?;
+    Ok(WorkingDay {
+        date,
+        location: day_working_location(&events),
+    })
+}
+
 /// Get events for a specific date range
 #[tauri::command]
 pub async fn get_events_range(
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
     time_min: String,
     time_max: String,
-) -> Result<Vec<CalendarEvent>, String> {
-    let token = token_store.get_access_token().await?;
-
+    deadline_ms: Option<u64>,
+) -> Result<Vec<CalendarEvent>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     let url = format!(
         "{}/calendars/primary/events?timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime",
         CALENDAR_API_BASE,
@@ -99,7 +497,846 @@ pub async fn get_events_range(
         urlencoding::encode(&time_max)
     );
 
-    let response: CalendarEventsResponse = client.get(&url, &token).await?;
+    let response = deadline::within(
+        deadline_ms,
+        client.get_fields(&url, &token_store, EVENT_LIST_FIELDS),
+    )
+    .await?;
 
     Ok(response.items.unwrap_or_default())
 }
+
+/// Get the events of the week containing `date` (YYYY-MM-DD, default today),
+/// starting on the configured first day of the week
+#[tauri::command]
+pub async fn get_week_events(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    date: Option<String>,
+    deadline_ms: Option<u64>,
+) -> Result<Vec<CalendarEvent>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let week = processing::week_info(
+        processing::date_or_today(date.as_deref())?,
+        processing::first_day_of_week(&app),
+    )?;
+    let bound = |ms: i64| {
+        Local
+            .timestamp_millis_opt(ms)
+            .single()
+            .map(|d| d.to_rfc3339())
+            .ok_or_else(|| "Failed to create date".to_string())
+    };
+    let url = format!(
+        "{}/calendars/primary/events?timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime",
+        CALENDAR_API_BASE,
+        urlencoding::encode(&bound(week.start_ms)?),
+        urlencoding::encode(&bound(week.end_ms)?)
+    );
+
+    let response = deadline::within(
+        deadline_ms,
+        client.get_fields(&url, &token_store, EVENT_LIST_FIELDS),
+    )
+    .await?;
+
+    Ok(response.items.unwrap_or_default())
+}
+
+// ============================================================================
+// Events from Email Threads
+// ============================================================================
+
+/// Link between an email thread and the event created from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadEventLink {
+    pub thread_id: String,
+    pub event_id: String,
+    pub html_link: Option<String>,
+    pub summary: String,
+    /// RFC3339 date-time, or `YYYY-MM-DD` for all-day events
+    pub start: String,
+    pub end: String,
+    pub all_day: bool,
+    pub attendees: Vec<String>,
+    /// The words the date was parsed from
+    pub matched_text: String,
+}
+
+/// Split an address list header on commas outside quoted display names
+pub(crate) fn split_addresses(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+/// Everyone on the thread (From, To, Cc) except `own_email`, first appearance first
+fn thread_participants(thread: &GmailThreadDetail, own_email: &str) -> Vec<EventAttendee> {
+    let mut attendees: Vec<EventAttendee> = Vec::new();
+    for message in thread.messages.iter().flatten() {
+        for name in ["From", "To", "Cc"] {
+            let Some(value) = analytics::header(message, name) else {
+                continue;
+            };
+            for address in split_addresses(value) {
+                let (display_name, email) = analytics::parse_from_header(address);
+                if !email.contains('@')
+                    || email.eq_ignore_ascii_case(own_email)
+                    || attendees.iter().any(|a| a.email == email)
+                {
+                    continue;
+                }
+                attendees.push(EventAttendee {
+                    email,
+                    display_name: (!display_name.is_empty()).then_some(display_name),
+                    response_status: None,
+                    is_self: None,
+                    resource: None,
+                });
+            }
+        }
+    }
+    attendees
+}
+
+/// The proposed time in the thread, preferring the latest message that has one
+fn thread_proposal(thread: &GmailThreadDetail, now: NaiveDateTime) -> Option<DateMention> {
+    let messages = thread.messages.as_deref().unwrap_or_default();
+    messages
+        .iter()
+        .rev()
+        .find_map(|m| natural_date::first_proposal(&m.snippet, now))
+        .or_else(|| {
+            let subject = messages
+                .first()
+                .and_then(|m| analytics::header(m, "Subject"))?;
+            natural_date::first_proposal(subject, now)
+        })
+}
+
+fn local_rfc3339(value: NaiveDateTime) -> Result<String, String> {
+    Local
+        .from_local_datetime(&value)
+        .earliest()
+        .map(|d| d.to_rfc3339())
+        .ok_or_else(|| "Invalid local time".to_string())
+}
+
+/// Create an event at the date/time proposed in an email thread
+///
+/// Participants are invited as attendees and the thread is linked to the
+/// event in local storage.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_event_from_thread(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    duration_minutes: Option<u32>,
+) -> Result<ThreadEventLink, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    capabilities::require(&token_store, Feature::CalendarWrite).await?;
+    let account = token_store.account_context().await?;
+    let thread =
+        gmail::fetch_thread_detail(&token_store, &client, &Mailbox::Own, &thread_id).await?;
+
+    let proposal = thread_proposal(&thread, Local::now().naive_local())
+        .ok_or("No proposed date or time found in this thread")?;
+    let attendees = thread_participants(&thread, account.email());
+
+    let (start, end) = if proposal.has_time {
+        let minutes = duration_minutes.unwrap_or(DEFAULT_EVENT_MINUTES).max(1);
+        let end = proposal.start + Duration::minutes(minutes as i64);
+        (
+            EventDateTime {
+                date: None,
+                date_time: Some(local_rfc3339(proposal.start)?),
+                time_zone: None,
+            },
+            EventDateTime {
+                date: None,
+                date_time: Some(local_rfc3339(end)?),
+                time_zone: None,
+            },
+        )
+    } else {
+        let day = proposal.start.date();
+        (
+            EventDateTime {
+                date: Some(day.to_string()),
+                date_time: None,
+                time_zone: None,
+            },
+            EventDateTime {
+                date: Some((day + Duration::days(1)).to_string()),
+                date_time: None,
+                time_zone: None,
+            },
+        )
+    };
+
+    let summary = thread
+        .messages
+        .as_ref()
+        .and_then(|m| m.first())
+        .and_then(|m| analytics::header(m, "Subject"))
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or("(No subject)")
+        .to_string();
+
+    let event = NewCalendarEvent {
+        summary: summary.clone(),
+        description: Some(format!(
+            "Created from email thread: {}",
+            gmail::open_thread_in_gmail(thread_id.clone())
+        )),
+        start: start.clone(),
+        end: end.clone(),
+        attendees: (!attendees.is_empty()).then(|| attendees.clone()),
+        extended_properties: Some(serde_json::json!({
+            "private": { "rainydayThreadId": thread_id }
+        })),
+    };
+
+    let url = format!("{}/calendars/primary/events", CALENDAR_API_BASE);
+    let created: CalendarEvent = client.post(&url, &token_store, &event).await?;
+
+    let link = ThreadEventLink {
+        thread_id: thread_id.clone(),
+        event_id: created.id.clone(),
+        html_link: created.html_link,
+        summary,
+        start: start.date_time.or(start.date).unwrap_or_default(),
+        end: end.date_time.or(end.date).unwrap_or_default(),
+        all_day: !proposal.has_time,
+        attendees: attendees.into_iter().map(|a| a.email).collect(),
+        matched_text: proposal.text,
+    };
+
+    let value = serde_json::to_value(&link)
+        .map_err(|e| format!("Failed to serialize thread event link: {}", e))?;
+    storage.put(&account, THREAD_EVENT_LINKS, &thread_id, value)?;
+
+    invalidation::mutated(
+        &app,
+        DataEvent::EventCreated {
+            calendar_id: "primary".to_string(),
+            event_id: created.id,
+        },
+    )
+    .await;
+
+    Ok(link)
+}
+
+// ============================================================================
+// RSVP Rollup
+// ============================================================================
+
+/// An attendee who hasn't answered the invitation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RsvpAttendee {
+    pub email: String,
+    pub display_name: Option<String>,
+}
+
+/// Invitation responses of a meeting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RsvpRollup {
+    pub event_id: String,
+    pub summary: Option<String>,
+    /// Whether the signed-in user organizes the meeting (and can nudge)
+    pub organized_by_me: bool,
+    /// Invited people, rooms excluded
+    pub total: u32,
+    pub accepted: u32,
+    pub declined: u32,
+    pub tentative: u32,
+    pub no_response: u32,
+    /// Other attendees who haven't responded yet
+    pub non_responders: Vec<RsvpAttendee>,
+    /// e.g. "3 of 6 accepted"
+    pub label: String,
+}
+
+fn rsvp_rollup(event: &CalendarEvent) -> RsvpRollup {
+    let mut rollup = RsvpRollup {
+        event_id: event.id.clone(),
+        summary: event.summary.clone(),
+        organized_by_me: event
+            .organizer
+            .as_ref()
+            .and_then(|o| o.is_self)
+            .unwrap_or(false),
+        total: 0,
+        accepted: 0,
+        declined: 0,
+        tentative: 0,
+        no_response: 0,
+        non_responders: Vec::new(),
+        label: String::new(),
+    };
+
+    let people = event
+        .attendees
+        .iter()
+        .flatten()
+        .filter(|a| a.resource != Some(true));
+    for attendee in people {
+        rollup.total += 1;
+        match attendee.response_status.as_deref() {
+            Some("accepted") => rollup.accepted += 1,
+            Some("declined") => rollup.declined += 1,
+            Some("tentative") => rollup.tentative += 1,
+            // "needsAction", or no status at all
+            _ => {
+                rollup.no_response += 1;
+                if attendee.is_self != Some(true) {
+                    rollup.non_responders.push(RsvpAttendee {
+                        email: attendee.email.clone(),
+                        display_name: attendee.display_name.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    rollup.label = format!("{} of {} accepted", rollup.accepted, rollup.total);
+    rollup
+}
+
+/// Get accepted/declined/tentative/no-response counts of a meeting
+///
+/// Non-responders are listed by name so the organizer can nudge them.
+#[tauri::command]
+pub async fn get_rsvp_rollup(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    event_id: String,
+) -> Result<RsvpRollup, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    capabilities::require(&token_store, Feature::CalendarWrite).await?;
+    let url = format!(
+        "{}/calendars/primary/events/{}",
+        CALENDAR_API_BASE,
+        urlencoding::encode(&event_id)
+    );
+    let event: CalendarEvent = client.get(&url, &token_store).await?;
+    Ok(rsvp_rollup(&event))
+}
+
+// ============================================================================
+// Invitations
+// ============================================================================
+
+/// Days ahead searched for invitations awaiting a response
+const INVITE_HORIZON_DAYS: i64 = 30;
+
+/// Answer to an invitation (Calendar's `responseStatus` values)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpResponse {
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl RsvpResponse {
+    fn as_str(self) -> &'static str {
+        match self {
+            RsvpResponse::Accepted => "accepted",
+            RsvpResponse::Declined => "declined",
+            RsvpResponse::Tentative => "tentative",
+        }
+    }
+}
+
+/// A meeting the user was invited to and hasn't answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingInvite {
+    pub event_id: String,
+    pub summary: String,
+    pub organizer: Option<String>,
+    /// Answer before this: the meeting's start
+    pub deadline_ms: i64,
+    pub all_day: bool,
+    pub html_link: Option<String>,
+    /// The invitation email, when it's still in the inbox
+    pub thread_id: Option<String>,
+}
+
+/// The invitation the user still has to answer, if `event` is one
+fn pending_invite(event: &CalendarEvent) -> Option<PendingInvite> {
+    if event.status.as_deref() == Some("cancelled")
+        || event.organizer.as_ref().and_then(|o| o.is_self) == Some(true)
+    {
+        return None;
+    }
+    let me = event
+        .attendees
+        .iter()
+        .flatten()
+        .find(|a| a.is_self == Some(true))?;
+    if !matches!(me.response_status.as_deref(), None | Some("needsAction")) {
+        return None;
+    }
+
+    let start = event.start.as_ref()?;
+    let deadline_ms = start
+        .date_time
+        .as_deref()
+        .map(start_sort_key)
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(start.date.as_deref()?, "%Y-%m-%d").ok()?;
+            let midnight = Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()?;
+            Some(midnight.timestamp_millis())
+        })
+        .filter(|ms| *ms != i64::MAX)?;
+
+    Some(PendingInvite {
+        event_id: event.id.clone(),
+        summary: event
+            .summary
+            .clone()
+            .unwrap_or_else(|| "(No title)".to_string()),
+        organizer: event
+            .organizer
+            .as_ref()
+            .and_then(|o| o.display_name.clone().or_else(|| o.email.clone())),
+        deadline_ms,
+        all_day: start.date_time.is_none(),
+        html_link: event.html_link.clone(),
+        thread_id: None,
+    })
+}
+
+/// Invitations awaiting a response from now on, soonest deadline first
+pub(crate) async fn pending_invites(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    now_ms: i64,
+) -> Result<Vec<PendingInvite>, GoogleApiError> {
+    let end_ms = now_ms + INVITE_HORIZON_DAYS * 24 * 60 * 60 * 1000;
+    let mut invites: Vec<PendingInvite> = events_between(token_store, client, now_ms, end_ms, None)
+        .await?
+        .iter()
+        .filter_map(pending_invite)
+        .collect();
+    invites.sort_by_key(|i| i.deadline_ms);
+    Ok(invites)
+}
+
+/// Attach invitation emails to their events by subject
+/// (`Invitation: Design review @ Thu Jan 15, 2026 ...`)
+pub(crate) fn link_invite_threads(invites: &mut [PendingInvite], threads: &[(String, String)]) {
+    for invite in invites.iter_mut() {
+        let title = invite.summary.to_lowercase();
+        invite.thread_id = threads
+            .iter()
+            .find(|(_, subject)| {
+                let subject = subject.to_lowercase();
+                let rest = subject
+                    .strip_prefix("invitation:")
+                    .or_else(|| subject.strip_prefix("updated invitation:"));
+                rest.is_some_and(|rest| {
+                    let rest = rest.trim_start();
+                    rest == title || rest.starts_with(&format!("{} @", title))
+                })
+            })
+            .map(|(id, _)| id.clone());
+    }
+}
+
+/// Answer an invitation; the organizer is notified
+#[tauri::command]
+pub async fn respond_to_event(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    event_id: String,
+    response: RsvpResponse,
+) -> Result<RsvpRollup, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let url = format!(
+        "{}/calendars/primary/events/{}",
+        CALENDAR_API_BASE,
+        urlencoding::encode(&event_id)
+    );
+    // Patch the raw attendees so fields this app doesn't model survive
+    let event: serde_json::Value = client.get(&url, &token_store).await?;
+    let mut attendees = event
+        .get("attendees")
+        .and_then(|a| a.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let me = attendees
+        .iter_mut()
+        .find(|a| a.get("self").and_then(|s| s.as_bool()) == Some(true))
+        .ok_or("You are not invited to this event")?;
+    me["responseStatus"] = serde_json::json!(response.as_str());
+
+    let updated: CalendarEvent = client
+        .patch(
+            &format!("{}?sendUpdates=all", url),
+            &token_store,
+            &serde_json::json!({ "attendees": attendees }),
+        )
+        .await?;
+    invalidation::mutated(
+        &app,
+        DataEvent::EventResponded {
+            event_id: event_id.clone(),
+            response: response.as_str().to_string(),
+        },
+    )
+    .await;
+    Ok(rsvp_rollup(&updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{GmailHeader, GmailMessage, GmailPayload};
+
+    fn message(headers: &[(&str, &str)]) -> GmailMessage {
+        GmailMessage {
+            id: "m1".to_string(),
+            thread_id: "t1".to_string(),
+            label_ids: None,
+            snippet: String::new(),
+            payload: Some(GmailPayload {
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(name, value)| GmailHeader {
+                            name: name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                ),
+                mime_type: None,
+                filename: None,
+                body: None,
+                parts: None,
+            }),
+            internal_date: None,
+            size_estimate: None,
+        }
+    }
+
+    #[test]
+    fn test_thread_participants() {
+        let thread = GmailThreadDetail {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                message(&[
+                    ("From", "\"Doe, Jane\" <jane@example.com>"),
+                    ("To", "me@example.com, Bob <BOB@example.com>"),
+                ]),
+                message(&[
+                    ("From", "Bob <bob@example.com>"),
+                    ("Cc", "carol@example.com, undisclosed-recipients:;"),
+                ]),
+            ]),
+        };
+
+        let attendees = thread_participants(&thread, "Me@Example.com");
+        let emails: Vec<&str> = attendees.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(
+            emails,
+            ["jane@example.com", "bob@example.com", "carol@example.com"]
+        );
+        assert_eq!(attendees[0].display_name.as_deref(), Some("Doe, Jane"));
+        assert_eq!(attendees[2].display_name, None);
+    }
+
+    #[test]
+    fn test_process_event_colors_and_availability() {
+        let palette = EventPalette {
+            event: HashMap::from([(
+                "11".to_string(),
+                ColorDefinition {
+                    background: "#dc2127".to_string(),
+                    foreground: "#1d1d1d".to_string(),
+                },
+            )]),
+            calendar: Some(ColorDefinition {
+                background: "#9fe1e7".to_string(),
+                foreground: "#000000".to_string(),
+            }),
+        };
+        let event: CalendarEvent = serde_json::from_value(serde_json::json!({
+            "id": "e1",
+            "start": { "dateTime": "2026-01-15T09:00:00Z" },
+            "end": { "dateTime": "2026-01-15T09:30:00Z" },
+            "colorId": "11",
+            "transparency": "transparent",
+            "visibility": "private",
+        }))
+        .unwrap();
+
+        let processed = process_event(event, &palette);
+        assert_eq!(processed.background_color.as_deref(), Some("#dc2127"));
+        assert_eq!(processed.transparency.as_deref(), Some("transparent"));
+        assert_eq!(processed.visibility.as_deref(), Some("private"));
+
+        // Unknown or missing event colors fall back to the calendar color
+        let plain: CalendarEvent =
+            serde_json::from_value(serde_json::json!({ "id": "e2" })).unwrap();
+        let processed = process_event(plain, &palette);
+        assert_eq!(processed.background_color.as_deref(), Some("#9fe1e7"));
+        assert_eq!(processed.title, "(No title)");
+    }
+
+    #[test]
+    fn test_meeting_link_detection() {
+        let link = |value: serde_json::Value| {
+            let event: CalendarEvent = serde_json::from_value(value).unwrap();
+            process_event(event, &EventPalette::default()).meeting_link
+        };
+
+        assert_eq!(
+            link(serde_json::json!({
+                "id": "zoom-addon",
+                "location": "https://other.zoom.us/j/1",
+                "conferenceData": { "entryPoints": [
+                    { "entryPointType": "phone", "uri": "tel:+1-555-0100" },
+                    { "entryPointType": "video", "uri": "https://acme.zoom.us/j/42" },
+                ]},
+            }))
+            .as_deref(),
+            Some("https://acme.zoom.us/j/42")
+        );
+        assert_eq!(
+            link(serde_json::json!({
+                "id": "jitsi-location",
+                "location": "Online: https://meet.jit.si/standup",
+            }))
+            .as_deref(),
+            Some("https://meet.jit.si/standup")
+        );
+        assert_eq!(
+            link(serde_json::json!({
+                "id": "teams-description",
+                "location": "Room 4",
+                "description": "Notes https://docs.google.com/d/1 <a href=\"https://teams.microsoft.com/l/meetup-join/abc\">Join</a>",
+            }))
+            .as_deref(),
+            Some("https://teams.microsoft.com/l/meetup-join/abc")
+        );
+        assert_eq!(
+            link(serde_json::json!({ "id": "none", "location": "Room 4" })),
+            None
+        );
+    }
+
+    #[test]
+    fn test_day_working_location() {
+        let event =
+            |value: serde_json::Value| -> CalendarEvent { serde_json::from_value(value).unwrap() };
+        let events = vec![
+            event(serde_json::json!({ "id": "standup", "eventType": "default" })),
+            event(serde_json::json!({
+                "id": "afternoon",
+                "eventType": "workingLocation",
+                "start": { "dateTime": "2026-01-15T13:00:00Z" },
+                "workingLocationProperties": { "type": "homeOffice", "homeOffice": {} },
+            })),
+            event(serde_json::json!({
+                "id": "morning",
+                "eventType": "workingLocation",
+                "start": { "dateTime": "2026-01-15T08:00:00Z" },
+                "workingLocationProperties": {
+                    "type": "officeLocation",
+                    "officeLocation": { "label": "HQ Madrid" },
+                },
+            })),
+        ];
+
+        assert_eq!(
+            day_working_location(&events),
+            Some(WorkingLocation {
+                kind: WorkingLocationKind::Office,
+                label: Some("HQ Madrid".to_string()),
+            })
+        );
+        assert_eq!(working_location_of(&events[0]), None);
+
+        let all_day = event(serde_json::json!({
+            "id": "all-day",
+            "eventType": "workingLocation",
+            "start": { "date": "2026-01-15" },
+            "workingLocationProperties": { "type": "homeOffice", "homeOffice": {} },
+        }));
+        let mut events = events;
+        events.push(all_day);
+        assert_eq!(
+            day_working_location(&events).map(|l| l.kind),
+            Some(WorkingLocationKind::Home)
+        );
+    }
+
+    #[test]
+    fn test_rsvp_rollup() {
+        let event: CalendarEvent = serde_json::from_value(serde_json::json!({
+            "id": "sync",
+            "summary": "Weekly sync",
+            "organizer": { "email": "me@example.com", "self": true },
+            "attendees": [
+                { "email": "me@example.com", "responseStatus": "accepted", "self": true },
+                { "email": "ana@example.com", "responseStatus": "accepted" },
+                { "email": "bo@example.com", "displayName": "Bo", "responseStatus": "needsAction" },
+                { "email": "cy@example.com", "responseStatus": "declined" },
+                { "email": "di@example.com", "responseStatus": "tentative" },
+                { "email": "ed@example.com" },
+                { "email": "room-4@resource.calendar.google.com", "resource": true, "responseStatus": "accepted" },
+            ],
+        }))
+        .unwrap();
+
+        let rollup = rsvp_rollup(&event);
+        assert!(rollup.organized_by_me);
+        assert_eq!(
+            (
+                rollup.total,
+                rollup.accepted,
+                rollup.declined,
+                rollup.tentative,
+                rollup.no_response
+            ),
+            (6, 2, 1, 1, 2)
+        );
+        assert_eq!(rollup.label, "2 of 6 accepted");
+        let names: Vec<_> = rollup
+            .non_responders
+            .iter()
+            .map(|a| a.email.as_str())
+            .collect();
+        assert_eq!(names, ["bo@example.com", "ed@example.com"]);
+    }
+
+    #[test]
+    fn test_merge_duplicate_events() {
+        let event = |id: &str, title: &str, start: &str, source: &str, uid: Option<&str>| {
+            let mut event: ProcessedEvent = serde_json::from_value(serde_json::json!({
+                "id": id,
+                "title": title,
+                "start_time": start,
+                "end_time": "2026-01-15T11:00:00Z",
+                "location": null,
+                "meeting_link": null,
+                "attendees_count": 0,
+                "source": source,
+            }))
+            .unwrap();
+            event.ical_uid = uid.map(str::to_string);
+            event
+        };
+        let mut personal = event(
+            "p1",
+            "Design review",
+            "2026-01-15T11:00:00+01:00",
+            "ical:Personal",
+            Some("abc@google.com"),
+        );
+        personal.meeting_link = Some("https://meet.google.com/abc".to_string());
+        let events = vec![
+            event(
+                "w1",
+                "Design review",
+                "2026-01-15T10:00:00Z",
+                "google",
+                Some("abc@google.com"),
+            ),
+            // Same UID, at the same instant written in another zone
+            personal,
+            // No UID in common, but same title and time
+            event(
+                "f1",
+                "design  Review",
+                "2026-01-15T10:00:00Z",
+                "ical:Family",
+                None,
+            ),
+            // Same title at another time
+            event(
+                "w2",
+                "Design review",
+                "2026-01-15T09:00:00Z",
+                "google",
+                None,
+            ),
+        ];
+
+        let merged = merge_duplicate_events(events);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].id, "w1");
+        assert_eq!(merged[0].also_on, ["ical:Personal", "ical:Family"]);
+        assert_eq!(
+            merged[0].meeting_link.as_deref(),
+            Some("https://meet.google.com/abc")
+        );
+        assert!(merged[1].also_on.is_empty());
+    }
+
+    #[test]
+    fn test_pending_invites() {
+        let event = |organizer_self: bool, status: &str| -> CalendarEvent {
+            serde_json::from_value(serde_json::json!({
+                "id": "e1",
+                "summary": "Design review",
+                "start": { "dateTime": "2026-01-15T10:00:00Z" },
+                "organizer": { "email": "ana@example.com", "self": organizer_self },
+                "attendees": [
+                    { "email": "ana@example.com", "responseStatus": "accepted" },
+                    { "email": "me@example.com", "self": true, "responseStatus": status },
+                ],
+            }))
+            .unwrap()
+        };
+        let invite = pending_invite(&event(false, "needsAction")).unwrap();
+        assert_eq!(invite.organizer.as_deref(), Some("ana@example.com"));
+        assert_eq!(invite.deadline_ms, 1_768_471_200_000);
+        assert!(pending_invite(&event(false, "accepted")).is_none());
+        assert!(pending_invite(&event(true, "needsAction")).is_none());
+
+        let mut invites = vec![invite];
+        let threads = vec![
+            (
+                "t1".to_string(),
+                "Accepted: Design review @ Thu".to_string(),
+            ),
+            (
+                "t2".to_string(),
+                "Invitation: Design review @ Thu Jan 15, 2026 10am - 11am (UTC)".to_string(),
+            ),
+        ];
+        link_invite_threads(&mut invites, &threads);
+        assert_eq!(invites[0].thread_id.as_deref(), Some("t2"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Circuit breaker for Google API hosts
+//!
+//! While a Google service is down, every widget keeps retrying and each
+//! attempt waits for its own network error. `CircuitBreaker` counts the
+//! consecutive failures (transport errors and 5xx responses) per host; after
+//! `FAILURE_THRESHOLD` of them the circuit opens and requests to that host
+//! fail right away with `GoogleApiError::Unavailable`, which carries the time
+//! to retry at.
+//!
+//! Once the cooldown has passed the circuit is half-open: requests go through
+//! again, and the first outcome decides. A failure reopens it with twice the
+//! cooldown (up to `MAX_COOLDOWN`); a success closes it, which is broadcast so
+//! `spawn_events` can emit `circuit:closed` and wake the sync loops.
+
+use super::{GoogleApiError, GoogleClient};
+use crate::sync::SyncScheduler;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast;
+
+/// Emitted with a `CircuitClosed` when a host recovers
+pub const CIRCUIT_CLOSED_EVENT: &str = "circuit:closed";
+
+/// Consecutive failures that open a host's circuit
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long a circuit stays open the first time
+const BASE_COOLDOWN: Duration = Duration::from_secs(30);
+/// Upper bound for the cooldown after repeated trips
+const MAX_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// Payload of `circuit:closed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CircuitClosed {
+    pub host: String,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Consecutive failures
+    failures: u32,
+    /// Times the circuit opened without closing in between
+    trips: u32,
+    /// Set while open (until then) and half-open (in the past)
+    open_until: Option<Instant>,
+}
+
+/// Per-host circuits shared by every request of a `GoogleClient`
+pub struct CircuitBreaker {
+    circuits: Mutex<HashMap<String, Circuit>>,
+    closed: broadcast::Sender<CircuitClosed>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self {
+            circuits: Mutex::new(HashMap::new()),
+            closed: broadcast::channel(16).0,
+        }
+    }
+
+    /// Receiver of the circuits closing from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<CircuitClosed> {
+        self.closed.subscribe()
+    }
+
+    /// When `host` may be called again, if its circuit is open at `now`
+    fn open_until(&self, host: &str, now: Instant) -> Option<Instant> {
+        let circuits = self.circuits.lock().ok()?;
+        circuits
+            .get(host)
+            .and_then(|c| c.open_until)
+            .filter(|until| *until > now)
+    }
+
+    /// Fail fast while `host`'s circuit is open
+    pub fn check(&self, host: &str) -> Result<(), GoogleApiError> {
+        let now = Instant::now();
+        match self.open_until(host, now) {
+            Some(until) => Err(GoogleApiError::Unavailable {
+                host: host.to_string(),
+                retry_at_ms: chrono::Utc::now().timestamp_millis()
+                    + (until - now).as_millis() as i64,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, host: &str, now: Instant) {
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return;
+        };
+        let circuit = circuits.entry(host.to_string()).or_default();
+        circuit.failures += 1;
+        let half_open = circuit.open_until.is_some_and(|until| until <= now);
+        if half_open || (circuit.open_until.is_none() && circuit.failures >= FAILURE_THRESHOLD) {
+            let cooldown = BASE_COOLDOWN * 2u32.saturating_pow(circuit.trips.min(16));
+            circuit.trips += 1;
+            circuit.open_until = Some(now + cooldown.min(MAX_COOLDOWN));
+            eprintln!(
+                "{} is failing, pausing requests for {:?}",
+                host,
+                cooldown.min(MAX_COOLDOWN)
+            );
+        }
+    }
+
+    /// Count a transport error or 5xx from `host`
+    pub fn record_failure(&self, host: &str) {
+        self.record_failure_at(host, Instant::now());
+    }
+
+    /// Count a response from `host` showing the service works; closes its
+    /// circuit
+    pub fn record_success(&self, host: &str) {
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return;
+        };
+        let Some(circuit) = circuits.remove(host) else {
+            return;
+        };
+        if circuit.open_until.is_some() {
+            // No receiver just means nobody listens yet
+            let _ = self.closed.send(CircuitClosed {
+                host: host.to_string(),
+            });
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Host a request URL goes to (the whole URL if it doesn't parse)
+pub fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Forward closing circuits to the frontend and resync right away
+pub fn spawn_events(app: AppHandle) {
+    let mut closed = app.state::<GoogleClient>().circuit().subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match closed.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            if let Err(e) = app.emit(CIRCUIT_CLOSED_EVENT, &event) {
+                eprintln!("Failed to emit {}: {}", CIRCUIT_CLOSED_EVENT, e);
+            }
+            app.state::<SyncScheduler>().resync_now();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_and_closes() {
+        let breaker = CircuitBreaker::new();
+        let mut closed = breaker.subscribe();
+        let host = "gmail.googleapis.com";
+        let start = Instant::now();
+
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            breaker.record_failure_at(host, start);
+        }
+        assert_eq!(breaker.open_until(host, start), None);
+        breaker.record_failure_at(host, start);
+        assert_eq!(breaker.open_until(host, start), Some(start + BASE_COOLDOWN));
+        let error = breaker.check(host).unwrap_err();
+        assert!(error.retryable());
+        // Other hosts are unaffected
+        assert!(breaker.check("tasks.googleapis.com").is_ok());
+
+        // Half-open: a single failure reopens with a longer cooldown
+        let later = start + BASE_COOLDOWN;
+        assert_eq!(breaker.open_until(host, later), None);
+        breaker.record_failure_at(host, later);
+        assert_eq!(
+            breaker.open_until(host, later),
+            Some(later + BASE_COOLDOWN * 2)
+        );
+
+        breaker.record_success(host);
+        assert!(breaker.check(host).is_ok());
+        assert_eq!(
+            closed.try_recv().unwrap(),
+            CircuitClosed {
+                host: host.to_string()
+            }
+        );
+        // Successes on a closed circuit emit nothing
+        breaker.record_success(host);
+        assert!(closed.try_recv().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Deduplication of identical in-flight requests
+//!
+//! Two components asking for the same data at once (e.g. both calling
+//! `get_today_events`) would otherwise send the same GET twice. `Coalescer`
+//! lets the first caller for a key do the work while later callers with the
+//! same key wait for and share its result. The key is forgotten once the work
+//! finishes, so nothing is cached beyond the request itself.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Shares the result of concurrent work with the same key
+pub struct Coalescer<K, V> {
+    in_flight: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K, V> Default for Coalescer<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Coalescer<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` for `key`, or wait for the run already in flight
+    ///
+    /// If the caller doing the work is dropped, a waiting caller takes over.
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let cell = self
+            .in_flight
+            .lock()
+            .ok()
+            .map(|mut in_flight| in_flight.entry(key.clone()).or_default().clone());
+        let Some(cell) = cell else {
+            return work().await;
+        };
+        let value = cell.get_or_init(work).await.clone();
+
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            if in_flight
+                .get(&key)
+                .is_some_and(|current| Arc::ptr_eq(current, &cell))
+            {
+                in_flight.remove(&key);
+            }
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_runs_share_work() {
+        let coalescer: Coalescer<&str, u32> = Coalescer::new();
+        let calls = AtomicU32::new(0);
+        let work = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            calls.fetch_add(1, Ordering::SeqCst) + 1
+        };
+
+        let (a, b, c) = tokio::join!(
+            coalescer.run("events", work),
+            coalescer.run("events", work),
+            coalescer.run("tasks", work),
+        );
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Finished work isn't reused
+        assert_eq!(coalescer.run("events", work).await, 3);
+    }
+}
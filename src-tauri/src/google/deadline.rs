@@ -0,0 +1,72 @@
+//! Per-command deadlines
+//!
+//! A command taking a `deadline_ms` runs its body through `within`, which sets
+//! the deadline for every Google request made by that task, however deep in
+//! the fetchers it happens. `GoogleClient::send` gives up once the deadline
+//! passes, including the rate limiter and Retry-After waits, and bounds each
+//! request by the time left. The calls `fan_out` runs side by side share the
+//! deadline, as they run in the same task; spawned tasks don't.
+//!
+//! A nested `within` can only shorten the deadline.
+
+use super::GoogleApiError;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Run `fut` with a deadline `deadline_ms` from now (no deadline if None)
+pub async fn within<F: Future>(deadline_ms: Option<u64>, fut: F) -> F::Output {
+    let Some(ms) = deadline_ms else {
+        return fut.await;
+    };
+    let at = Instant::now() + Duration::from_millis(ms);
+    let at = DEADLINE.try_with(|outer| (*outer).min(at)).unwrap_or(at);
+    DEADLINE.scope(at, fut).await
+}
+
+/// The current deadline, if one is set
+pub fn current() -> Option<Instant> {
+    DEADLINE.try_with(|at| *at).ok()
+}
+
+/// Time left before the current deadline, if one is set
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|at| at.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+pub fn exceeded() -> GoogleApiError {
+    GoogleApiError::Timeout("Deadline exceeded".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_within_sets_and_narrows_deadline() {
+        assert_eq!(remaining(), None);
+        assert_eq!(within(None, async { remaining() }).await, None);
+        assert_eq!(current(), None);
+
+        let left = within(Some(60_000), async {
+            let outer = remaining().unwrap();
+            // The inner scope can't extend the outer deadline
+            let inner = within(Some(600_000), async { remaining().unwrap() }).await;
+            let shorter = within(Some(1_000), async { remaining().unwrap() }).await;
+            (outer, inner, shorter)
+        })
+        .await;
+        assert!(left.0 <= Duration::from_secs(60) && left.0 > Duration::from_secs(59));
+        assert!(left.1 <= left.0);
+        assert!(left.2 <= Duration::from_secs(1));
+
+        // Calls in the same scope see the same deadline
+        let (a, b) = within(Some(1_000), async { (current(), current()) }).await;
+        assert!(a.is_some() && a == b);
+    }
+}
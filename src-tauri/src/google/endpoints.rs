@@ -0,0 +1,180 @@
+//! Runtime-configurable API base URLs
+//!
+//! Requests are always built from `GMAIL_API_BASE`, `CALENDAR_API_BASE` and
+//! `TASKS_API_BASE`; `GoogleClient` rewrites the base just before sending, so
+//! health and quota tracking keep working per API. Overrides come from the
+//! `api.json` settings store, and environment variables take precedence:
+//! - `RAINY_DAY_GMAIL_API_BASE`
+//! - `RAINY_DAY_CALENDAR_API_BASE`
+//! - `RAINY_DAY_TASKS_API_BASE`
+//!
+//! Pointing these at a proxy or test server lets recorded traffic (see
+//! `recording`) be replayed.
+
+use super::{GoogleClient, CALENDAR_API_BASE, GMAIL_API_BASE, TASKS_API_BASE};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+const API_STORE_FILE: &str = "api.json";
+const ENDPOINTS_KEY: &str = "endpoints";
+
+const GMAIL_ENV: &str = "RAINY_DAY_GMAIL_API_BASE";
+const CALENDAR_ENV: &str = "RAINY_DAY_CALENDAR_API_BASE";
+const TASKS_ENV: &str = "RAINY_DAY_TASKS_API_BASE";
+
+/// Base URL overrides per API (None uses Google's)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiEndpoints {
+    pub gmail: Option<String>,
+    pub calendar: Option<String>,
+    pub tasks: Option<String>,
+}
+
+impl ApiEndpoints {
+    fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            gmail: var(GMAIL_ENV),
+            calendar: var(CALENDAR_ENV),
+            tasks: var(TASKS_ENV),
+        }
+    }
+
+    /// Fill unset overrides from `fallback`
+    fn or(self, fallback: ApiEndpoints) -> Self {
+        Self {
+            gmail: self.gmail.or(fallback.gmail),
+            calendar: self.calendar.or(fallback.calendar),
+            tasks: self.tasks.or(fallback.tasks),
+        }
+    }
+
+    fn entries(&self) -> [(&'static str, &Option<String>); 3] {
+        [
+            (GMAIL_API_BASE, &self.gmail),
+            (CALENDAR_API_BASE, &self.calendar),
+            (TASKS_API_BASE, &self.tasks),
+        ]
+    }
+
+    /// Trim trailing slashes and check every override is an http(s) URL
+    fn normalized(self) -> Result<Self, String> {
+        let check = |base: Option<String>| -> Result<Option<String>, String> {
+            let Some(base) = base.map(|b| b.trim().trim_end_matches('/').to_string()) else {
+                return Ok(None);
+            };
+            if base.is_empty() {
+                return Ok(None);
+            }
+            if !(base.starts_with("https://") || base.starts_with("http://")) {
+                return Err(format!(
+                    "API base must start with https:// or http://: {}",
+                    base
+                ));
+            }
+            Ok(Some(base))
+        };
+        Ok(Self {
+            gmail: check(self.gmail)?,
+            calendar: check(self.calendar)?,
+            tasks: check(self.tasks)?,
+        })
+    }
+
+    /// Rewrite a URL built from one of the default bases
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        self.entries().into_iter().find_map(|(default, custom)| {
+            let rest = url.strip_prefix(default)?;
+            custom.as_ref().map(|base| format!("{}{}", base, rest))
+        })
+    }
+}
+
+fn load_stored(app: &AppHandle) -> Result<ApiEndpoints, String> {
+    let store = storage::fs::settings_store(app, API_STORE_FILE)
+        .map_err(|e| format!("Failed to access API settings store: {}", e))?;
+
+    Ok(store
+        .get(ENDPOINTS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Apply stored and environment overrides to the shared client
+pub fn initialize(app: &AppHandle) -> Result<(), String> {
+    let endpoints = ApiEndpoints::from_env()
+        .or(load_stored(app)?)
+        .normalized()?;
+    if endpoints != ApiEndpoints::default() {
+        println!("Using custom API endpoints: {:?}", endpoints);
+    }
+    app.state::<GoogleClient>().set_endpoints(endpoints);
+    Ok(())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the API base overrides in effect
+#[tauri::command]
+pub fn get_api_endpoints(client: State<'_, GoogleClient>) -> ApiEndpoints {
+    crate::perf::trace_command!();
+    client.endpoints()
+}
+
+/// Save API base overrides (environment variables still take precedence)
+#[tauri::command]
+pub async fn set_api_endpoints(
+    app: AppHandle,
+    client: State<'_, GoogleClient>,
+    endpoints: ApiEndpoints,
+) -> Result<ApiEndpoints, String> {
+    crate::perf::trace_command!();
+    let endpoints = endpoints.normalized()?;
+
+    let store = storage::fs::settings_store(&app, API_STORE_FILE)
+        .map_err(|e| format!("Failed to access API settings store: {}", e))?;
+    store.set(ENDPOINTS_KEY, serde_json::json!(endpoints));
+    storage::fs::save_store(&app, API_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save API endpoints: {}", e))?;
+
+    let effective = ApiEndpoints::from_env().or(endpoints).normalized()?;
+    client.set_endpoints(effective.clone());
+    Ok(effective)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_and_validation() {
+        let endpoints = ApiEndpoints {
+            gmail: Some("http://localhost:8080/gmail/".to_string()),
+            calendar: None,
+            tasks: Some(" ".to_string()),
+        }
+        .normalized()
+        .unwrap();
+
+        assert_eq!(endpoints.tasks, None);
+        assert_eq!(
+            endpoints
+                .rewrite(&format!("{}/users/me/threads", GMAIL_API_BASE))
+                .as_deref(),
+            Some("http://localhost:8080/gmail/users/me/threads")
+        );
+        assert_eq!(
+            endpoints.rewrite(&format!("{}/calendars/primary/events", CALENDAR_API_BASE)),
+            None
+        );
+
+        let invalid = ApiEndpoints {
+            calendar: Some("ftp://proxy".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.normalized().is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Errors of Google API requests
+//!
+//! `GoogleClient` and the gmail, calendar and tasks commands fail with a
+//! `GoogleApiError` instead of a plain message, serialized for the frontend as
+//! `{kind, status, reason, retryable, message, body}` so it can tell an expired
+//! session (`auth`) from a rate limit (`rate_limited`) or a dropped connection
+//! (`network`) without parsing text. Requests to a host whose circuit is open
+//! (see `circuit`) fail as `unavailable` with a `retry_at_ms`, and requests
+//! that hit a timeout or the command's deadline as `timeout`.
+//!
+//! Code returning `Result<_, String>` keeps using `?`: the error converts to
+//! its display text, and a `String` error converts back as `Other`.
+
+use reqwest::StatusCode;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Reasons Google uses for per-user and per-project rate limits (sent with 403)
+const RATE_LIMIT_REASONS: &[&str] = &[
+    "rateLimitExceeded",
+    "userRateLimitExceeded",
+    "quotaExceeded",
+    "dailyLimitExceeded",
+    "RESOURCE_EXHAUSTED",
+];
+
+/// Why a Google API request failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoogleApiError {
+    /// No usable access token (signed out, or the refresh failed)
+    Auth(String),
+    /// Google answered with an error status
+    Status {
+        status: u16,
+        /// First `errors[].reason` of Google's error body (else its `status`)
+        reason: Option<String>,
+        /// Google's `error.message`, else the raw body
+        message: String,
+        body: String,
+    },
+    /// No response (DNS, TLS, connection reset)
+    Network(String),
+    /// No response in time: a connect/read timeout or the command's deadline
+    Timeout(String),
+    /// Not sent: the host kept failing and its circuit is open
+    Unavailable { host: String, retry_at_ms: i64 },
+    /// A response that couldn't be read or parsed
+    InvalidResponse(String),
+    /// Anything else, e.g. local storage failures inside a command
+    Other(String),
+}
+
+/// Kind of failure, for the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoogleErrorKind {
+    Auth,
+    Permission,
+    RateLimited,
+    NotFound,
+    Server,
+    Api,
+    Network,
+    Timeout,
+    Unavailable,
+    InvalidResponse,
+    Other,
+}
+
+impl GoogleApiError {
+    /// Error for a non-success response and its body
+    pub fn from_response(status: StatusCode, body: String) -> Self {
+        let parsed: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+        let error = parsed.as_ref().and_then(|v| v.get("error"));
+        let reason = error
+            .and_then(|e| e.pointer("/errors/0/reason").or_else(|| e.get("status")))
+            .and_then(|r| r.as_str())
+            .map(str::to_string);
+        let message = error
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| body.clone());
+        GoogleApiError::Status {
+            status: status.as_u16(),
+            reason,
+            message,
+            body,
+        }
+    }
+
+    /// HTTP status, for errors Google answered
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            GoogleApiError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> GoogleErrorKind {
+        match self {
+            GoogleApiError::Auth(_) => GoogleErrorKind::Auth,
+            GoogleApiError::Status { status, reason, .. } => {
+                let rate_limited = reason
+                    .as_deref()
+                    .is_some_and(|r| RATE_LIMIT_REASONS.contains(&r));
+                match status {
+                    429 => GoogleErrorKind::RateLimited,
+                    403 if rate_limited => GoogleErrorKind::RateLimited,
+                    401 => GoogleErrorKind::Auth,
+                    403 => GoogleErrorKind::Permission,
+                    404 | 410 => GoogleErrorKind::NotFound,
+                    500..=599 => GoogleErrorKind::Server,
+                    _ => GoogleErrorKind::Api,
+                }
+            }
+            GoogleApiError::Network(_) => GoogleErrorKind::Network,
+            GoogleApiError::Timeout(_) => GoogleErrorKind::Timeout,
+            GoogleApiError::Unavailable { .. } => GoogleErrorKind::Unavailable,
+            GoogleApiError::InvalidResponse(_) => GoogleErrorKind::InvalidResponse,
+            GoogleApiError::Other(_) => GoogleErrorKind::Other,
+        }
+    }
+
+    /// Whether the same request may succeed later
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            GoogleErrorKind::RateLimited
+                | GoogleErrorKind::Server
+                | GoogleErrorKind::Network
+                | GoogleErrorKind::Timeout
+                | GoogleErrorKind::Unavailable
+        )
+    }
+}
+
+impl fmt::Display for GoogleApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoogleApiError::Status { status, body, .. } => match StatusCode::from_u16(*status) {
+                Ok(status) => write!(f, "API error {}: {}", status, body),
+                Err(_) => write!(f, "API error {}: {}", status, body),
+            },
+            GoogleApiError::Unavailable { host, retry_at_ms } => {
+                match chrono::DateTime::from_timestamp_millis(*retry_at_ms) {
+                    Some(at) => write!(
+                        f,
+                        "{} is unavailable, retry at {}",
+                        host,
+                        at.with_timezone(&chrono::Local).format("%H:%M:%S")
+                    ),
+                    None => write!(f, "{} is unavailable", host),
+                }
+            }
+            GoogleApiError::Auth(message)
+            | GoogleApiError::Network(message)
+            | GoogleApiError::Timeout(message)
+            | GoogleApiError::InvalidResponse(message)
+            | GoogleApiError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for GoogleApiError {}
+
+impl Serialize for GoogleApiError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wire<'a> {
+            kind: GoogleErrorKind,
+            status: Option<u16>,
+            reason: Option<&'a str>,
+            retryable: bool,
+            message: String,
+            body: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            retry_at_ms: Option<i64>,
+        }
+
+        let (reason, message, body) = match self {
+            GoogleApiError::Status {
+                reason,
+                message,
+                body,
+                ..
+            } => (reason.as_deref(), message.clone(), Some(body.as_str())),
+            other => (None, other.to_string(), None),
+        };
+        Wire {
+            kind: self.kind(),
+            status: self.status(),
+            reason,
+            retryable: self.retryable(),
+            message,
+            body,
+            retry_at_ms: match self {
+                GoogleApiError::Unavailable { retry_at_ms, .. } => Some(*retry_at_ms),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<String> for GoogleApiError {
+    fn from(message: String) -> Self {
+        GoogleApiError::Other(message)
+    }
+}
+
+impl From<&str> for GoogleApiError {
+    fn from(message: &str) -> Self {
+        GoogleApiError::Other(message.to_string())
+    }
+}
+
+impl From<GoogleApiError> for String {
+    fn from(error: GoogleApiError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_google_api_error() {
+        let body = r#"{"error":{"code":403,"message":"Rate Limit Exceeded","errors":[{"reason":"userRateLimitExceeded"}]}}"#;
+        let error = GoogleApiError::from_response(StatusCode::FORBIDDEN, body.to_string());
+        assert_eq!(error.kind(), GoogleErrorKind::RateLimited);
+        assert!(error.retryable());
+        assert!(error.to_string().starts_with("API error 403 Forbidden: {"));
+
+        let wire = serde_json::to_value(&error).unwrap();
+        assert_eq!(wire["kind"], "rate_limited");
+        assert_eq!(wire["status"], 403);
+        assert_eq!(wire["reason"], "userRateLimitExceeded");
+        assert_eq!(wire["message"], "Rate Limit Exceeded");
+
+        let denied = GoogleApiError::from_response(StatusCode::FORBIDDEN, "nope".to_string());
+        assert_eq!(denied.kind(), GoogleErrorKind::Permission);
+        assert!(!denied.retryable());
+        assert_eq!(serde_json::to_value(&denied).unwrap()["message"], "nope");
+
+        let unavailable = GoogleApiError::Unavailable {
+            host: "gmail.googleapis.com".to_string(),
+            retry_at_ms: 1_700_000_000_000,
+        };
+        let wire = serde_json::to_value(&unavailable).unwrap();
+        assert_eq!(wire["kind"], "unavailable");
+        assert_eq!(wire["retryable"], true);
+        assert_eq!(wire["retry_at_ms"], 1_700_000_000_000i64);
+        assert!(wire["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("gmail.googleapis.com is unavailable, retry at "));
+
+        let timeout = GoogleApiError::Timeout("Deadline exceeded".to_string());
+        assert_eq!(serde_json::to_value(&timeout).unwrap()["kind"], "timeout");
+        assert!(timeout.retryable());
+
+        let local: GoogleApiError = "Failed to access store".into();
+        assert_eq!(serde_json::to_value(&local).unwrap()["kind"], "other");
+        assert_eq!(String::from(local), "Failed to access store");
+    }
+}
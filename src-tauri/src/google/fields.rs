@@ -0,0 +1,100 @@
+//! Partial responses through Google's `fields` parameter
+//!
+//! Google APIs return whole resources by default, most of which the app never
+//! reads. A `Fields<T>` selector names the parts of a response that `T`
+//! deserializes; `GoogleClient::get_fields` appends it to the request so Google
+//! only sends those. The type parameter keeps a selector from being used with
+//! a response type it wasn't written for.
+//!
+//! A selector must include every required (non-`Option`) field of `T`,
+//! otherwise the trimmed response no longer parses.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A `fields` selector for responses parsed as `T`
+pub struct Fields<T> {
+    selector: &'static str,
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> Fields<T> {
+    pub const fn new(selector: &'static str) -> Self {
+        Self {
+            selector,
+            response: PhantomData,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.selector
+    }
+
+    /// `url` with this selector as its `fields` parameter
+    pub fn apply(&self, url: &str) -> String {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!(
+            "{}{}fields={}",
+            url,
+            separator,
+            urlencoding::encode(self.as_str())
+        )
+    }
+}
+
+impl<T> Clone for Fields<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Fields<T> {}
+
+impl<T> fmt::Debug for Fields<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Fields").field(&self.selector).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{CalendarEventsResponse, GmailThreadDetail, TasksResponse};
+    use crate::google::{calendar, gmail, tasks};
+
+    /// Whether the selector's parentheses pair up
+    fn balanced(selector: &str) -> bool {
+        let mut depth = 0i32;
+        for c in selector.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth < 0 {
+                return false;
+            }
+        }
+        depth == 0
+    }
+
+    #[test]
+    fn test_fields_selectors() {
+        let fields: Fields<TasksResponse> = Fields::new("items(id,title),nextPageToken");
+        assert_eq!(
+            fields.apply("https://example.com/tasks"),
+            "https://example.com/tasks?fields=items%28id%2Ctitle%29%2CnextPageToken"
+        );
+        assert!(fields
+            .apply("https://example.com/tasks?maxResults=100")
+            .contains("?maxResults=100&fields="));
+
+        let thread: Fields<GmailThreadDetail> = gmail::THREAD_METADATA_FIELDS;
+        let events: Fields<CalendarEventsResponse> = calendar::EVENT_LIST_FIELDS;
+        let tasks: Fields<TasksResponse> = tasks::TASK_LIST_FIELDS;
+        for selector in [thread.as_str(), events.as_str(), tasks.as_str()] {
+            assert!(balanced(selector), "{}", selector);
+            assert!(!selector.contains(' '), "{}", selector);
+        }
+    }
+}
@@ -2,78 +2,1963 @@
 //!
 //! Endpoints:
 //! - threads.list: List email threads
-//! - threads.get: Get thread detail with messages
+//! - threads.get: Get thread detail with messages (one, or a batch with
+//!   bounded concurrency)
+//! - threads.get (full): Attachments of recent threads
+//!
+//! Thread participants (`get_thread_participants`) come from the metadata
+//! headers, so the UI never parses From/To/Cc itself. The same headers drive
+//! the thread timeline (`get_thread_timeline`): who answered whom, how fast,
+//! and which message has waited longest for a reply.
+//!
+//! Gmail only tracks read state per thread, so the app keeps its own per
+//! message: the detail view reports the messages scrolled past
+//! (`report_read_position`) and inbox summaries count the messages not seen
+//! yet in `unseen_message_count`.
+//!
+//! `get_thread_content` returns message bodies sanitized for rendering, with
+//! remote images blocked unless the sender is allowlisted (`privacy`).
+//!
+//! `get_storage_insights` samples large messages by Gmail's size estimate and
+//! groups them by sender and age to suggest what to delete first.
+//!
+//! Commands read the signed-in user's mailbox unless a delegated `mailbox` is
+//! given (see `mailbox`).
 
-use super::types::{GmailThreadDetail, GmailThreadsResponse, ThreadSummary};
-use super::{GoogleClient, GMAIL_API_BASE};
+use super::calendar::split_addresses;
+use super::fields::Fields;
+use super::mailbox::{self, Mailbox};
+use super::types::{
+    GmailMessage, GmailPayload, GmailThreadDetail, GmailThreadsPage, ThreadFetchError,
+    ThreadHydration, ThreadSummary,
+};
+use super::{deadline, fan_out, GoogleApiError, GoogleClient, GMAIL_API_BASE, MAX_RESPONSE_BYTES};
+use crate::account::AccountContext;
+use crate::analytics;
+use crate::app_lock::AppLockState;
 use crate::auth::TokenStore;
-use tauri::State;
+use crate::cache::{CacheState, API_RESPONSE_TTL_SECS, INBOX_SUMMARY_KEY};
+use crate::privacy;
+use crate::processing;
+use crate::search;
+use crate::storage::{LocalStorage, EMAIL_METADATA, READ_POSITIONS};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use tauri::{AppHandle, State};
+
+/// Concurrent `threads.get` calls when hydrating a batch
+pub const DEFAULT_HYDRATION_PARALLELISM: usize = 5;
+/// Lookback of `list_recent_attachments` when none is given
+const DEFAULT_ATTACHMENT_DAYS: u32 = 14;
+const DEFAULT_ATTACHMENT_THREADS: u32 = 25;
+/// Each scanned thread is fetched in full, so keep the scan bounded
+const MAX_ATTACHMENT_THREADS: u32 = 50;
+/// Extensions searched for when filtering on images
+/// Default lower bound of the storage sample, in MB
+const DEFAULT_STORAGE_MIN_MB: u32 = 5;
+/// Threads sampled by `get_storage_insights` by default, and at most
+const DEFAULT_STORAGE_THREADS: u32 = 100;
+const MAX_STORAGE_THREADS: u32 = 500;
+/// Senders and deletion candidates returned
+const STORAGE_TOP_SENDERS: usize = 20;
+const STORAGE_TOP_CANDIDATES: usize = 25;
+/// Sampled large messages from one sender that make it a repeat sender
+const REPEAT_SENDER_MESSAGES: usize = 3;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "heic", "webp"];
+
+/// Parts of metadata-format threads.get responses `GmailThreadDetail` reads
+pub const THREAD_METADATA_FIELDS: Fields<GmailThreadDetail> = Fields::new(
+    "id,messages(id,threadId,labelIds,snippet,internalDate,sizeEstimate,payload(mimeType,headers))",
+);
 
 /// List email threads from inbox
 ///
-/// Uses Gmail query syntax for filtering (same as Gmail search)
+/// Uses Gmail query syntax for filtering (same as Gmail search). Huge
+/// mailboxes are better shown with `get_inbox_sample`.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_inbox_summary(
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
+    storage: State<'_, LocalStorage>,
     max_items: Option<u32>,
     query: Option<String>,
-) -> Result<Vec<ThreadSummary>, String> {
-    let token = token_store.get_access_token().await?;
-
+    mailbox: Option<String>,
+    deadline_ms: Option<u64>,
+) -> Result<Vec<ThreadSummary>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
     let max = max_items.unwrap_or(20).min(50);
+    let is_default_query = query.is_none();
     let q = query.unwrap_or_else(|| "in:inbox is:unread".to_string());
 
     let url = format!(
-        "{}/users/me/threads?maxResults={}&q={}",
+        "{}/{}/threads?maxResults={}&q={}",
         GMAIL_API_BASE,
+        mailbox.user_path(),
         max,
         urlencoding::encode(&q)
     );
 
-    let response: GmailThreadsResponse = client.get(&url, &token).await?;
-
     // For now, return basic thread info. Full processing requires threads.get for each
-    let threads = response.threads.unwrap_or_default();
+    let listing = client.get_with(&url, &token_store, MAX_RESPONSE_BYTES, |body| {
+        let page: GmailThreadsPage = serde_json::from_slice(body)?;
+        Ok(page
+            .threads
+            .into_iter()
+            .map(|t| ThreadSummary {
+                id: t.id.into_owned(),
+                subject: String::new(), // Would need threads.get for this
+                snippet: t.snippet.into_owned(),
+                from_name: String::new(),
+                from_email: String::new(),
+                date: String::new(),
+                is_unread: true,
+                message_count: 1,
+                priority_score: 0.5,
+                unseen_message_count: 1,
+            })
+            .collect())
+    });
+    let mut summaries: Vec<ThreadSummary> = deadline::within(deadline_ms, listing).await?;
 
-    let summaries: Vec<ThreadSummary> = threads
-        .into_iter()
-        .map(|t| ThreadSummary {
-            id: t.id,
-            subject: String::new(), // Would need threads.get for this
-            snippet: t.snippet,
-            from_name: String::new(),
-            from_email: String::new(),
-            date: String::new(),
-            is_unread: true,
-            message_count: 1,
-            priority_score: 0.5,
-        })
-        .collect();
+    if mailbox.is_own() {
+        let account = token_store.account_context().await?;
+        let positions = read_positions(&storage, &account)?;
+        for summary in &mut summaries {
+            if let Some(position) = positions.get(&summary.id) {
+                summary.unseen_message_count = position.unseen_count();
+            }
+        }
+    }
+
+    // Only the default inbox view backs the glance cache
+    if is_default_query {
+        let account = token_store.account_context().await?;
+        cache.0.set_json(
+            &account.cache_key(&mailbox.cache_key(INBOX_SUMMARY_KEY)),
+            &summaries,
+            API_RESPONSE_TTL_SECS,
+        );
+        crate::perf::mark_plan_part(crate::perf::PlanPart::Inbox);
+    }
 
     Ok(summaries)
 }
 
+/// Fetch a thread with message metadata (Subject, From, To, Cc, Date, List-Unsubscribe headers)
+pub async fn fetch_thread_detail(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    mailbox: &Mailbox,
+    thread_id: &str,
+) -> Result<GmailThreadDetail, GoogleApiError> {
+    let url = format!(
+        "{}/{}/threads/{}?format=metadata&metadataHeaders=Subject&metadataHeaders=From&metadataHeaders=To&metadataHeaders=Cc&metadataHeaders=Date&metadataHeaders=List-Unsubscribe",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        thread_id
+    );
+
+    client
+        .get_fields(&url, token_store, THREAD_METADATA_FIELDS)
+        .await
+}
+
 /// Get detailed thread information including all messages
 #[tauri::command]
 pub async fn get_thread_detail(
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
     thread_id: String,
-) -> Result<GmailThreadDetail, String> {
-    let token = token_store.get_access_token().await?;
+    mailbox: Option<String>,
+    deadline_ms: Option<u64>,
+) -> Result<GmailThreadDetail, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let thread = deadline::within(
+        deadline_ms,
+        fetch_thread_detail(&token_store, &client, &mailbox, &thread_id),
+    )
+    .await?;
+    if mailbox.is_own() {
+        record_metadata(&token_store, &storage, std::slice::from_ref(&thread)).await;
+    }
+    Ok(thread)
+}
+
+/// Feed fetched threads into the analytics history and the search index
+/// (failures are only logged)
+async fn record_metadata(
+    token_store: &TokenStore,
+    storage: &LocalStorage,
+    threads: &[GmailThreadDetail],
+) {
+    let result = match token_store.account_context().await {
+        Ok(account) => analytics::record_threads(storage, &account, threads)
+            .and_then(|_| search::index_threads(storage, &account, threads))
+            .and_then(|_| record_thread_messages(storage, &account, threads)),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to record email metadata: {}", e);
+    }
+}
+
+/// Fetch many threads with at most `parallelism` requests in flight
+///
+/// Never fails as a whole: threads that couldn't be fetched are reported in
+/// `ThreadHydration::errors` and the rest are returned in request order.
+pub async fn hydrate_threads(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    mailbox: &Mailbox,
+    thread_ids: &[String],
+    parallelism: usize,
+) -> ThreadHydration {
+    hydrate_with(thread_ids, parallelism, |thread_id| {
+        fetch_thread_detail(token_store, client, mailbox, thread_id)
+    })
+    .await
+}
+
+/// Run `fetch` for every thread with at most `parallelism` calls in flight
+async fn hydrate_with<'a, F, Fut>(
+    thread_ids: &'a [String],
+    parallelism: usize,
+    fetch: F,
+) -> ThreadHydration
+where
+    F: Fn(&'a String) -> Fut,
+    Fut: Future<Output = Result<GmailThreadDetail, GoogleApiError>>,
+{
+    let outcome = fan_out(thread_ids, parallelism, fetch).await;
+    ThreadHydration {
+        threads: outcome
+            .succeeded
+            .into_iter()
+            .map(|(_, thread)| thread)
+            .collect(),
+        errors: outcome
+            .failed
+            .into_iter()
+            .map(|(thread_id, error)| ThreadFetchError {
+                thread_id: thread_id.clone(),
+                error,
+            })
+            .collect(),
+    }
+}
+
+/// Get detailed information for many threads at once
+///
+/// `parallelism` defaults to `DEFAULT_HYDRATION_PARALLELISM` to stay clear of
+/// Gmail's per-user rate limits. Threads not fetched by `deadline_ms` are
+/// reported as failed.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_thread_details(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_ids: Vec<String>,
+    parallelism: Option<usize>,
+    mailbox: Option<String>,
+    deadline_ms: Option<u64>,
+) -> Result<ThreadHydration, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let hydration = deadline::within(
+        deadline_ms,
+        hydrate_threads(
+            &token_store,
+            &client,
+            &mailbox,
+            &thread_ids,
+            parallelism.unwrap_or(DEFAULT_HYDRATION_PARALLELISM),
+        ),
+    )
+    .await;
+    if mailbox.is_own() {
+        record_metadata(&token_store, &storage, &hydration.threads).await;
+    }
+    Ok(hydration)
+}
+
+// ============================================================================
+// Inbox Sampling
+// ============================================================================
+
+/// Threads shown by `get_inbox_sample` when no size is given
+const DEFAULT_SAMPLE_SIZE: usize = 200;
+const MAX_SAMPLE_SIZE: usize = 500;
+/// Threads kept per sender when no cap is given
+const DEFAULT_PER_SENDER: usize = 3;
+/// Candidates listed per stratum, as a multiple of its quota (so the sender
+/// cap has threads to skip)
+const SAMPLE_CANDIDATE_FACTOR: usize = 3;
+/// Largest `maxResults` Gmail accepts for threads.list
+const MAX_LIST_RESULTS: usize = 500;
+/// Gmail inbox categories the sample is stratified by
+const SAMPLE_CATEGORIES: &[&str] = &["primary", "updates", "social", "promotions", "forums"];
+
+/// Sampled threads of one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxStratum {
+    pub category: String,
+    pub shown: usize,
+    /// Gmail's estimate of the category's matching threads
+    pub estimated_total: u32,
+}
+
+/// A sample of a mailbox too large to show in full
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxSample {
+    /// Newest first within each category, categories in `SAMPLE_CATEGORIES` order
+    pub threads: Vec<ThreadSummary>,
+    pub shown: usize,
+    /// Gmail's estimate of all matching threads (approximate)
+    pub estimated_total: u32,
+    /// Whether some matching threads were left out
+    pub sampled: bool,
+    pub strata: Vec<InboxStratum>,
+    /// "Showing 200 of ~4,300"
+    pub label: String,
+}
+
+/// Split `size` across strata in proportion to their estimates (largest
+/// remainder), giving every non-empty stratum at least one slot
+fn allocate_sample(size: usize, estimates: &[u32]) -> Vec<usize> {
+    let total: u64 = estimates.iter().map(|e| *e as u64).sum();
+    if total <= size as u64 {
+        return estimates.iter().map(|e| *e as usize).collect();
+    }
+
+    let mut quotas: Vec<usize> = estimates
+        .iter()
+        .map(|e| ((*e as u64 * size as u64) / total) as usize)
+        .collect();
+    for (quota, estimate) in quotas.iter_mut().zip(estimates) {
+        if *quota == 0 && *estimate > 0 {
+            *quota = 1;
+        }
+    }
+    let mut order: Vec<usize> = (0..estimates.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((estimates[i] as u64 * size as u64) % total));
+    let mut assigned: usize = quotas.iter().sum();
+    for i in order.into_iter().cycle().take(estimates.len() * 2) {
+        if assigned >= size {
+            break;
+        }
+        if quotas[i] < estimates[i] as usize {
+            quotas[i] += 1;
+            assigned += 1;
+        }
+    }
+    quotas
+}
+
+/// Newest threads of a stratum, at most `per_sender` per known sender
+///
+/// Threads whose sender isn't in the local history count as distinct senders.
+fn pick_stratum(
+    candidates: Vec<ThreadSummary>,
+    quota: usize,
+    per_sender: usize,
+) -> Vec<ThreadSummary> {
+    let mut per_sender_count: HashMap<String, usize> = HashMap::new();
+    let mut picked = Vec::new();
+    for thread in candidates {
+        if picked.len() >= quota {
+            break;
+        }
+        if !thread.from_email.is_empty() {
+            let count = per_sender_count
+                .entry(thread.from_email.clone())
+                .or_default();
+            if *count >= per_sender {
+                continue;
+            }
+            *count += 1;
+        }
+        picked.push(thread);
+    }
+    picked
+}
+
+/// "Showing 200 of ~4,300" (or "Showing all 12")
+fn sample_label(shown: usize, estimated_total: u32, sampled: bool) -> String {
+    let grouped = |n: u64| {
+        let digits = n.to_string();
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(',');
+            }
+            out.push(c);
+        }
+        out
+    };
+    if !sampled {
+        return format!("Showing all {}", grouped(shown as u64));
+    }
+    // Gmail's estimate is rough: round to two significant digits
+    let estimate = estimated_total as u64;
+    let unit = 10u64.pow(estimate.to_string().len().saturating_sub(2) as u32);
+    let rounded = ((estimate + unit / 2) / unit * unit).max(shown as u64);
+    format!("Showing {} of ~{}", grouped(shown as u64), grouped(rounded))
+}
+
+/// Latest known sender of every thread in the local history
+fn known_senders(
+    storage: &LocalStorage,
+    account: &AccountContext,
+) -> Result<HashMap<String, analytics::MessageMetadata>, String> {
+    let mut senders: HashMap<String, analytics::MessageMetadata> = HashMap::new();
+    for record in storage.list(account, EMAIL_METADATA)?.into_values() {
+        let Ok(metadata) = serde_json::from_value::<analytics::MessageMetadata>(record.value)
+        else {
+            continue;
+        };
+        match senders.get(&metadata.thread_id) {
+            Some(known) if known.date_ms >= metadata.date_ms => {}
+            _ => {
+                senders.insert(metadata.thread_id.clone(), metadata);
+            }
+        }
+    }
+    Ok(senders)
+}
+
+/// Sample a huge inbox instead of listing it all
+///
+/// Threads are stratified by Gmail category in proportion to each category's
+/// size, and each category keeps its newest threads with at most `per_sender`
+/// per sender (senders come from the local history, so no thread has to be
+/// hydrated). The result says how much was left out.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_inbox_sample(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    sample_size: Option<usize>,
+    per_sender: Option<usize>,
+    query: Option<String>,
+    mailbox: Option<String>,
+) -> Result<InboxSample, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let size = sample_size
+        .unwrap_or(DEFAULT_SAMPLE_SIZE)
+        .clamp(1, MAX_SAMPLE_SIZE);
+    let per_sender = per_sender.unwrap_or(DEFAULT_PER_SENDER).max(1);
+    let q = query.unwrap_or_else(|| "in:inbox".to_string());
+    let max_results = (size * SAMPLE_CANDIDATE_FACTOR).min(MAX_LIST_RESULTS);
+
+    let pages = fan_out(SAMPLE_CATEGORIES, SAMPLE_CATEGORIES.len(), |category| {
+        let url = format!(
+            "{}/{}/threads?maxResults={}&q={}",
+            GMAIL_API_BASE,
+            mailbox.user_path(),
+            max_results,
+            urlencoding::encode(&format!("{} category:{}", q, category))
+        );
+        let client = &client;
+        let token_store = &token_store;
+        async move {
+            client
+                .get_with(&url, token_store, MAX_RESPONSE_BYTES, |body| {
+                    let page: GmailThreadsPage = serde_json::from_slice(body)?;
+                    let ids: Vec<(String, String)> = page
+                        .threads
+                        .into_iter()
+                        .map(|t| (t.id.into_owned(), t.snippet.into_owned()))
+                        .collect();
+                    Ok((ids, page.result_size_estimate))
+                })
+                .await
+        }
+    })
+    .await
+    .into_result()?;
+
+    let senders = if mailbox.is_own() {
+        known_senders(&storage, &token_store.account_context().await?)?
+    } else {
+        HashMap::new()
+    };
+
+    let estimates: Vec<u32> = pages
+        .iter()
+        .map(|(ids, estimate)| (*estimate).max(ids.len() as u32))
+        .collect();
+    let quotas = allocate_sample(size, &estimates);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut threads = Vec::new();
+    let mut strata = Vec::new();
+    for ((category, (ids, _)), (quota, estimate)) in SAMPLE_CATEGORIES
+        .iter()
+        .zip(pages)
+        .zip(quotas.into_iter().zip(&estimates))
+    {
+        let candidates: Vec<ThreadSummary> = ids
+            .into_iter()
+            .filter(|(id, _)| seen.insert(id.clone()))
+            .map(|(id, snippet)| {
+                let sender = senders.get(&id);
+                ThreadSummary {
+                    subject: String::new(),
+                    snippet,
+                    from_name: sender.map(|m| m.from_name.clone()).unwrap_or_default(),
+                    from_email: sender.map(|m| m.from_email.clone()).unwrap_or_default(),
+                    date: String::new(),
+                    is_unread: sender.is_none_or(|m| !m.opened),
+                    message_count: 1,
+                    priority_score: 0.5,
+                    unseen_message_count: 1,
+                    id,
+                }
+            })
+            .collect();
+        let picked = pick_stratum(candidates, quota, per_sender);
+        strata.push(InboxStratum {
+            category: category.to_string(),
+            shown: picked.len(),
+            estimated_total: *estimate,
+        });
+        threads.extend(picked);
+    }
+
+    let shown = threads.len();
+    let estimated_total: u32 = estimates.iter().sum();
+    let sampled = shown < estimated_total as usize;
+    Ok(InboxSample {
+        label: sample_label(shown, estimated_total, sampled),
+        threads,
+        shown,
+        estimated_total,
+        sampled,
+        strata,
+    })
+}
+
+// ============================================================================
+// Read Positions
+// ============================================================================
+
+/// Which messages of a thread were seen in the app
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadPosition {
+    pub thread_id: String,
+    /// Messages of the thread as last fetched, oldest first
+    pub message_ids: Vec<String>,
+    pub seen_message_ids: Vec<String>,
+    /// The furthest message scrolled to
+    pub last_seen_message_id: Option<String>,
+}
+
+impl ReadPosition {
+    /// Mark `message_ids` seen, along with every message before the furthest one
+    fn mark_seen(&mut self, message_ids: &[String]) {
+        let furthest = message_ids
+            .iter()
+            .filter_map(|id| self.message_ids.iter().position(|m| m == id))
+            .max();
+        let mut seen: Vec<String> = message_ids.to_vec();
+        if let Some(index) = furthest {
+            seen.extend(self.message_ids[..=index].iter().cloned());
+            self.last_seen_message_id = Some(self.message_ids[index].clone());
+        } else if let Some(last) = message_ids.last() {
+            self.last_seen_message_id = Some(last.clone());
+        }
+        for id in seen {
+            if !self.seen_message_ids.contains(&id) {
+                self.seen_message_ids.push(id);
+            }
+        }
+    }
+
+    pub fn unseen_count(&self) -> u32 {
+        self.message_ids
+            .iter()
+            .filter(|id| !self.seen_message_ids.contains(id))
+            .count() as u32
+    }
+}
+
+fn read_positions(
+    storage: &LocalStorage,
+    account: &AccountContext,
+) -> Result<HashMap<String, ReadPosition>, String> {
+    Ok(storage
+        .list(account, READ_POSITIONS)?
+        .into_iter()
+        .filter_map(|(id, record)| Some((id, serde_json::from_value(record.value).ok()?)))
+        .collect())
+}
+
+fn position_record(position: &ReadPosition) -> Result<(String, serde_json::Value), String> {
+    let value = serde_json::to_value(position)
+        .map_err(|e| format!("Failed to serialize read position: {}", e))?;
+    Ok((position.thread_id.clone(), value))
+}
+
+/// Keep the message list of fetched threads, so new replies count as unseen
+fn record_thread_messages(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    threads: &[GmailThreadDetail],
+) -> Result<(), String> {
+    let mut positions = read_positions(storage, account)?;
+    let mut records = Vec::new();
+    for thread in threads {
+        let message_ids: Vec<String> = thread
+            .messages
+            .iter()
+            .flatten()
+            .map(|m| m.id.clone())
+            .collect();
+        let position = positions
+            .entry(thread.id.clone())
+            .or_insert_with(|| ReadPosition {
+                thread_id: thread.id.clone(),
+                ..ReadPosition::default()
+            });
+        if position.message_ids != message_ids {
+            position.message_ids = message_ids;
+            records.push(position_record(position)?);
+        }
+    }
+    storage.put_many(account, READ_POSITIONS, records)
+}
+
+/// Record the messages the thread detail view scrolled past
+#[tauri::command]
+pub async fn report_read_position(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    message_ids: Vec<String>,
+) -> Result<ReadPosition, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let mut position = storage
+        .get(&account, READ_POSITIONS, &thread_id)?
+        .and_then(|record| serde_json::from_value(record.value).ok())
+        .unwrap_or_else(|| ReadPosition {
+            thread_id: thread_id.clone(),
+            ..ReadPosition::default()
+        });
+    position.mark_seen(&message_ids);
+
+    let (id, value) = position_record(&position)?;
+    storage.put(&account, READ_POSITIONS, &id, value)?;
+    Ok(position)
+}
+
+/// Get the in-app reading state of a thread
+#[tauri::command]
+pub async fn get_read_position(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+) -> Result<Option<ReadPosition>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    Ok(storage
+        .get(&account, READ_POSITIONS, &thread_id)?
+        .and_then(|record| serde_json::from_value(record.value).ok()))
+}
+
+/// Open a thread in Gmail web
+#[tauri::command]
+pub fn open_thread_in_gmail(thread_id: String) -> String {
+    crate::perf::trace_command!();
+    thread_link(&thread_id)
+}
+
+fn thread_link(thread_id: &str) -> String {
+    format!("https://mail.google.com/mail/u/0/#inbox/{}", thread_id)
+}
+
+// ============================================================================
+// Participants
+// ============================================================================
+
+/// Who sent the newest message of a thread, relative to the mailbox owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Someone on a thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadParticipant {
+    pub email: String,
+    pub name: String,
+    /// Messages this participant sent in the thread
+    pub messages_sent: u32,
+    /// When they last sent a message (None if they were only a recipient)
+    pub last_sent_ms: Option<i64>,
+    pub is_self: bool,
+}
+
+/// Participants of a thread and who is waiting on whom
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadParticipants {
+    pub thread_id: String,
+    /// First appearance first
+    pub participants: Vec<ThreadParticipant>,
+    pub last_message_direction: Option<MessageDirection>,
+    pub last_message_ms: Option<i64>,
+    /// Recipients of the owner's unanswered last message
+    pub waiting_on: Vec<String>,
+}
+
+fn message_date_ms(message: &GmailMessage) -> Option<i64> {
+    message.internal_date.as_deref()?.parse().ok()
+}
+
+/// Deduplicate a thread's senders and recipients
+fn summarize_participants(thread: &GmailThreadDetail, own_email: &str) -> ThreadParticipants {
+    let own_email = own_email.to_lowercase();
+    let mut participants: Vec<ThreadParticipant> = Vec::new();
+    let index = |participants: &mut Vec<ThreadParticipant>, address: &str| {
+        let (name, email) = analytics::parse_from_header(address);
+        if !email.contains('@') {
+            return None;
+        }
+        let position = match participants.iter().position(|p| p.email == email) {
+            Some(position) => position,
+            None => {
+                participants.push(ThreadParticipant {
+                    is_self: email == own_email,
+                    email,
+                    name: String::new(),
+                    messages_sent: 0,
+                    last_sent_ms: None,
+                });
+                participants.len() - 1
+            }
+        };
+        if participants[position].name.is_empty() {
+            participants[position].name = name;
+        }
+        Some(position)
+    };
+
+    let messages = thread.messages.as_deref().unwrap_or_default();
+    for message in messages {
+        if let Some(sender) =
+            analytics::header(message, "From").and_then(|from| index(&mut participants, from))
+        {
+            let sender = &mut participants[sender];
+            sender.messages_sent += 1;
+            sender.last_sent_ms = message_date_ms(message).max(sender.last_sent_ms);
+        }
+        for name in ["To", "Cc"] {
+            for address in split_addresses(analytics::header(message, name).unwrap_or_default()) {
+                index(&mut participants, address);
+            }
+        }
+    }
+
+    let last = messages.last();
+    let last_from = last
+        .and_then(|m| analytics::header(m, "From"))
+        .map(|from| analytics::parse_from_header(from).1);
+    let last_message_direction = last_from.as_ref().map(|from| {
+        if *from == own_email {
+            MessageDirection::Outgoing
+        } else {
+            MessageDirection::Incoming
+        }
+    });
+    let waiting_on = match (last, last_message_direction) {
+        (Some(message), Some(MessageDirection::Outgoing)) => ["To", "Cc"]
+            .iter()
+            .flat_map(|name| split_addresses(analytics::header(message, name).unwrap_or_default()))
+            .map(|address| analytics::parse_from_header(address).1)
+            .filter(|email| email.contains('@') && *email != own_email)
+            .fold(Vec::new(), |mut emails, email| {
+                if !emails.contains(&email) {
+                    emails.push(email);
+                }
+                emails
+            }),
+        _ => Vec::new(),
+    };
+
+    ThreadParticipants {
+        thread_id: thread.id.clone(),
+        participants,
+        last_message_direction,
+        last_message_ms: last.and_then(message_date_ms),
+        waiting_on,
+    }
+}
+
+/// Get a thread's deduplicated participants with message counts and whether
+/// the owner is waiting on a reply
+#[tauri::command]
+pub async fn get_thread_participants(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    mailbox: Option<String>,
+) -> Result<ThreadParticipants, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let own_email = match &mailbox {
+        Mailbox::Own => token_store.account_context().await?.email().to_string(),
+        Mailbox::Delegated(email) => email.clone(),
+    };
+    let thread = fetch_thread_detail(&token_store, &client, &mailbox, &thread_id).await?;
+    Ok(summarize_participants(&thread, &own_email))
+}
+
+// ============================================================================
+// Timeline
+// ============================================================================
+
+/// One message on a thread's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub message_id: String,
+    pub from_email: String,
+    pub from_name: String,
+    pub sent_ms: Option<i64>,
+    pub direction: MessageDirection,
+    pub snippet: String,
+    /// Message this one answers: the latest earlier message from someone else
+    pub replied_to: Option<String>,
+    /// Time between `replied_to` and this message
+    pub response_latency_ms: Option<i64>,
+    /// Whether someone else wrote after this message
+    pub answered: bool,
+}
+
+/// How quickly one participant answers another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseLatency {
+    pub responder: String,
+    pub replied_to: String,
+    pub replies: u32,
+    pub average_ms: i64,
+    pub longest_ms: i64,
+}
+
+/// The unanswered message that has waited longest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutstandingMessage {
+    pub message_id: String,
+    pub from_email: String,
+    pub direction: MessageDirection,
+    pub waiting_ms: i64,
+}
+
+/// A thread's messages in order with response times between participants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadTimeline {
+    pub thread_id: String,
+    /// Oldest first
+    pub entries: Vec<TimelineEntry>,
+    /// Per responder/sender pair, slowest average first
+    pub latencies: Vec<ResponseLatency>,
+    pub longest_outstanding: Option<OutstandingMessage>,
+}
+
+/// Order a thread's messages and work out who answered whom and how fast
+fn build_timeline(thread: &GmailThreadDetail, own_email: &str, now_ms: i64) -> ThreadTimeline {
+    let own_email = own_email.to_lowercase();
+    let mut entries: Vec<TimelineEntry> = thread
+        .messages
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|message| {
+            let (from_name, from_email) = analytics::parse_from_header(
+                analytics::header(message, "From").unwrap_or_default(),
+            );
+            TimelineEntry {
+                message_id: message.id.clone(),
+                direction: if from_email == own_email {
+                    MessageDirection::Outgoing
+                } else {
+                    MessageDirection::Incoming
+                },
+                from_email,
+                from_name,
+                sent_ms: message_date_ms(message),
+                snippet: message.snippet.clone(),
+                replied_to: None,
+                response_latency_ms: None,
+                answered: false,
+            }
+        })
+        .collect();
+    // Undated messages keep their API position at the end
+    entries.sort_by_key(|e| e.sent_ms.unwrap_or(i64::MAX));
+
+    let mut latencies: Vec<ResponseLatency> = Vec::new();
+    for i in 0..entries.len() {
+        let Some(j) = (0..i)
+            .rev()
+            .find(|&j| entries[j].from_email != entries[i].from_email)
+        else {
+            continue;
+        };
+        let (earlier, rest) = entries.split_at_mut(i);
+        for entry in earlier {
+            if entry.from_email != rest[0].from_email {
+                entry.answered = true;
+            }
+        }
+        entries[i].replied_to = Some(entries[j].message_id.clone());
+        let (Some(sent), Some(original)) = (entries[i].sent_ms, entries[j].sent_ms) else {
+            continue;
+        };
+        let latency = (sent - original).max(0);
+        entries[i].response_latency_ms = Some(latency);
 
+        let (responder, replied_to) = (&entries[i].from_email, &entries[j].from_email);
+        match latencies
+            .iter_mut()
+            .find(|l| l.responder == *responder && l.replied_to == *replied_to)
+        {
+            Some(pair) => {
+                pair.average_ms =
+                    (pair.average_ms * pair.replies as i64 + latency) / (pair.replies as i64 + 1);
+                pair.replies += 1;
+                pair.longest_ms = pair.longest_ms.max(latency);
+            }
+            None => latencies.push(ResponseLatency {
+                responder: responder.clone(),
+                replied_to: replied_to.clone(),
+                replies: 1,
+                average_ms: latency,
+                longest_ms: latency,
+            }),
+        }
+    }
+    latencies.sort_by_key(|l| std::cmp::Reverse(l.average_ms));
+
+    let longest_outstanding = entries
+        .iter()
+        .filter(|e| !e.answered)
+        .filter_map(|e| Some((e, e.sent_ms?)))
+        .min_by_key(|(_, sent)| *sent)
+        .map(|(e, sent)| OutstandingMessage {
+            message_id: e.message_id.clone(),
+            from_email: e.from_email.clone(),
+            direction: e.direction,
+            waiting_ms: (now_ms - sent).max(0),
+        });
+
+    ThreadTimeline {
+        thread_id: thread.id.clone(),
+        entries,
+        latencies,
+        longest_outstanding,
+    }
+}
+
+/// Get a thread's message timeline with response latencies and the longest
+/// unanswered message
+#[tauri::command]
+pub async fn get_thread_timeline(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    mailbox: Option<String>,
+) -> Result<ThreadTimeline, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let own_email = match &mailbox {
+        Mailbox::Own => token_store.account_context().await?.email().to_string(),
+        Mailbox::Delegated(email) => email.clone(),
+    };
+    let thread = fetch_thread_detail(&token_store, &client, &mailbox, &thread_id).await?;
+    Ok(build_timeline(
+        &thread,
+        &own_email,
+        chrono::Utc::now().timestamp_millis(),
+    ))
+}
+
+// ============================================================================
+// Attachments
+// ============================================================================
+
+/// An attachment received in a recent thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    pub attachment_id: Option<String>,
+    pub message_id: String,
+    pub thread_id: String,
+    pub thread_link: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub received_ms: i64,
+}
+
+/// Whether a file matches a type filter
+///
+/// A filter is a file extension (`pdf`, `.xlsx`), a MIME type
+/// (`application/pdf`) or `image`/`images` for any image.
+fn matches_type(filter: &str, filename: &str, mime_type: &str) -> bool {
+    let filter = filter.trim().trim_start_matches('.').to_lowercase();
+    match filter.as_str() {
+        "image" | "images" => mime_type.starts_with("image/"),
+        f if f.contains('/') => mime_type.eq_ignore_ascii_case(f),
+        f => filename
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(f)),
+    }
+}
+
+/// Gmail search narrowing the threads to the filtered file types
+fn attachment_query(days: u32, types: &[String]) -> String {
+    let mut terms: Vec<String> = Vec::new();
+    for filter in types {
+        let filter = filter.trim().trim_start_matches('.').to_lowercase();
+        match filter.as_str() {
+            "image" | "images" => terms.extend(
+                IMAGE_EXTENSIONS
+                    .iter()
+                    .map(|ext| format!("filename:{}", ext)),
+            ),
+            // MIME filters can't be searched for; don't narrow at all
+            f if f.contains('/') => {
+                terms.clear();
+                break;
+            }
+            f => terms.push(format!("filename:{}", f)),
+        }
+    }
+
+    let mut query = format!("has:attachment newer_than:{}d", days);
+    if !terms.is_empty() {
+        query.push_str(&format!(" {{{}}}", terms.join(" ")));
+    }
+    query
+}
+
+/// Attachments in a thread sent by someone other than `own_email`
+fn collect_attachments(
+    thread: &GmailThreadDetail,
+    own_email: &str,
+    types: &[String],
+) -> Vec<AttachmentInfo> {
+    fn walk<'a>(part: &'a GmailPayload, found: &mut Vec<&'a GmailPayload>) {
+        if part.filename.as_deref().is_some_and(|f| !f.is_empty()) {
+            found.push(part);
+        }
+        for child in part.parts.iter().flatten() {
+            walk(child, found);
+        }
+    }
+
+    let mut attachments = Vec::new();
+    for message in thread.messages.iter().flatten() {
+        let (from_name, from_email) =
+            analytics::parse_from_header(analytics::header(message, "From").unwrap_or_default());
+        if from_email.eq_ignore_ascii_case(own_email) {
+            continue;
+        }
+        let Some(payload) = &message.payload else {
+            continue;
+        };
+
+        let mut parts = Vec::new();
+        walk(payload, &mut parts);
+        for part in parts {
+            let filename = part.filename.clone().unwrap_or_default();
+            let mime_type = part.mime_type.clone().unwrap_or_default();
+            if !types.is_empty() && !types.iter().any(|t| matches_type(t, &filename, &mime_type)) {
+                continue;
+            }
+            let body = part.body.as_ref();
+            attachments.push(AttachmentInfo {
+                filename,
+                mime_type,
+                size_bytes: body.and_then(|b| b.size).unwrap_or(0),
+                attachment_id: body.and_then(|b| b.attachment_id.clone()),
+                message_id: message.id.clone(),
+                thread_id: thread.id.clone(),
+                thread_link: thread_link(&thread.id),
+                from_name: from_name.clone(),
+                from_email: from_email.clone(),
+                received_ms: message
+                    .internal_date
+                    .as_deref()
+                    .and_then(|d| d.parse().ok())
+                    .unwrap_or(0),
+            });
+        }
+    }
+    attachments
+}
+
+/// Fetch a thread with its full MIME structure
+pub(crate) async fn fetch_thread_full(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    mailbox: &Mailbox,
+    thread_id: &str,
+) -> Result<GmailThreadDetail, GoogleApiError> {
     let url = format!(
-        "{}/users/me/threads/{}?format=metadata&metadataHeaders=Subject&metadataHeaders=From&metadataHeaders=Date",
+        "{}/{}/threads/{}?format=full",
         GMAIL_API_BASE,
+        mailbox.user_path(),
         thread_id
     );
+    client.get(&url, token_store).await
+}
 
-    client.get(&url, &token).await
+/// List attachments others sent in the last `days` days, newest first
+///
+/// `types` filters by extension (`pdf`, `xlsx`), MIME type or `images`; with
+/// no filter every attachment is returned. Scans at most `max_threads` threads.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn list_recent_attachments(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    days: Option<u32>,
+    types: Option<Vec<String>>,
+    max_threads: Option<u32>,
+    mailbox: Option<String>,
+) -> Result<Vec<AttachmentInfo>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let own_email = match &mailbox {
+        Mailbox::Own => token_store.account_context().await?.email().to_string(),
+        Mailbox::Delegated(email) => email.clone(),
+    };
+    let types = types.unwrap_or_default();
+    let days = days.unwrap_or(DEFAULT_ATTACHMENT_DAYS).clamp(1, 365);
+    let max = max_threads
+        .unwrap_or(DEFAULT_ATTACHMENT_THREADS)
+        .min(MAX_ATTACHMENT_THREADS);
+
+    let url = format!(
+        "{}/{}/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        max,
+        urlencoding::encode(&attachment_query(days, &types))
+    );
+    let thread_ids: Vec<String> = client
+        .get_with(&url, &token_store, MAX_RESPONSE_BYTES, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            Ok(page
+                .threads
+                .into_iter()
+                .map(|t| t.id.into_owned())
+                .collect())
+        })
+        .await?;
+
+    let hydration = hydrate_with(&thread_ids, DEFAULT_HYDRATION_PARALLELISM, |thread_id| {
+        fetch_thread_full(&token_store, &client, &mailbox, thread_id)
+    })
+    .await;
+    for error in &hydration.errors {
+        eprintln!(
+            "Skipping thread {} in attachment scan: {}",
+            error.thread_id, error.error
+        );
+    }
+
+    let since_ms = chrono::Utc::now().timestamp_millis() - days as i64 * 86_400_000;
+    let mut attachments: Vec<AttachmentInfo> = hydration
+        .threads
+        .iter()
+        .flat_map(|thread| collect_attachments(thread, &own_email, &types))
+        .filter(|a| a.received_ms >= since_ms)
+        .collect();
+    attachments.sort_by_key(|a| std::cmp::Reverse(a.received_ms));
+    Ok(attachments)
 }
 
-/// Open a thread in Gmail web
+// ============================================================================
+// Message Content
+// ============================================================================
+
+/// A message body ready to render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageContent {
+    pub message_id: String,
+    pub from_email: String,
+    /// Sanitized HTML (plain-text bodies are escaped)
+    pub html: String,
+    /// The sender is on the image allowlist
+    pub images_allowed: bool,
+    pub blocked_remote: usize,
+    pub blocked_trackers: usize,
+    pub inline_images: Vec<String>,
+}
+
+/// Sanitized bodies of a thread's messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadContent {
+    pub thread_id: String,
+    pub messages: Vec<MessageContent>,
+    /// Tracking pixels removed across the thread
+    pub blocked_trackers: usize,
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A message body as sent
+pub(crate) enum MessageBody {
+    Html(String),
+    Text(String),
+}
+
+/// The message body: the HTML part, else the plain text one
+pub(crate) fn message_body(payload: &GmailPayload) -> Option<MessageBody> {
+    fn find<'a>(part: &'a GmailPayload, mime_type: &str) -> Option<&'a str> {
+        if part.mime_type.as_deref() == Some(mime_type)
+            && part.filename.as_deref().unwrap_or_default().is_empty()
+        {
+            if let Some(data) = part.body.as_ref().and_then(|b| b.data.as_deref()) {
+                return Some(data);
+            }
+        }
+        part.parts.iter().flatten().find_map(|p| find(p, mime_type))
+    }
+    let decode = |data: &str| {
+        URL_SAFE_NO_PAD
+            .decode(data.trim_end_matches('='))
+            .ok()
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    if let Some(html) = find(payload, "text/html").and_then(decode) {
+        return Some(MessageBody::Html(html));
+    }
+    find(payload, "text/plain")
+        .and_then(decode)
+        .map(MessageBody::Text)
+}
+
+/// The message body as HTML: the HTML part, else the escaped plain text
+fn body_html(payload: &GmailPayload) -> Option<String> {
+    message_body(payload).map(|body| match body {
+        MessageBody::Html(html) => html,
+        MessageBody::Text(text) => format!("<pre>{}</pre>", escape_html(&text)),
+    })
+}
+
+/// Get a thread's message bodies, sanitized for rendering
+///
+/// Remote images only load for allowlisted senders (see `privacy`), and the
+/// number of blocked tracking pixels is reported per message and in total.
 #[tauri::command]
-pub fn open_thread_in_gmail(thread_id: String) -> String {
-    format!("https://mail.google.com/mail/u/0/#inbox/{}", thread_id)
+pub async fn get_thread_content(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    thread_id: String,
+    mailbox: Option<String>,
+) -> Result<ThreadContent, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let thread = fetch_thread_full(&token_store, &client, &mailbox, &thread_id).await?;
+
+    let mut messages = Vec::new();
+    for message in thread.messages.iter().flatten() {
+        let (_, from_email) =
+            analytics::parse_from_header(analytics::header(message, "From").unwrap_or_default());
+        let html = message
+            .payload
+            .as_ref()
+            .and_then(body_html)
+            .unwrap_or_else(|| escape_html(&message.snippet));
+        let images_allowed = privacy::images_allowed(&app, &from_email);
+        let sanitized = processing::sanitize_html_with(&html, images_allowed);
+        messages.push(MessageContent {
+            message_id: message.id.clone(),
+            from_email,
+            html: sanitized.html,
+            images_allowed,
+            blocked_remote: sanitized.blocked_remote,
+            blocked_trackers: sanitized.tracking_pixels,
+            inline_images: sanitized.inline_images,
+        });
+    }
+
+    Ok(ThreadContent {
+        blocked_trackers: messages.iter().map(|m| m.blocked_trackers).sum(),
+        thread_id: thread.id,
+        messages,
+    })
+}
+
+// ============================================================================
+// Storage Insights
+// ============================================================================
+
+/// A large message in the storage sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeMessage {
+    pub message_id: String,
+    pub thread_id: String,
+    pub thread_link: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub size_bytes: u64,
+    pub received_ms: i64,
+    pub starred: bool,
+    pub bulk: bool,
+}
+
+/// Space used by one sender's sampled messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SenderUsage {
+    pub name: String,
+    pub email: String,
+    pub message_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Space used by sampled messages of an age range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgeUsage {
+    pub label: String,
+    /// Youngest message age in the bucket, in days
+    pub min_days: i64,
+    pub message_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Why a message is suggested for deletion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupReason {
+    /// Older than a year
+    Old,
+    /// Newsletter, promotion or social notification
+    Bulk,
+    /// The sender has several large messages in the sample
+    RepeatSender,
+}
+
+/// A suggested deletion (starred messages are never suggested)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionCandidate {
+    pub message: LargeMessage,
+    pub reasons: Vec<CleanupReason>,
+}
+
+/// Storage breakdown of the sampled large messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageInsights {
+    pub min_size_mb: u32,
+    pub sampled_messages: usize,
+    pub sampled_bytes: u64,
+    /// More large threads exist than were sampled
+    pub truncated: bool,
+    pub by_sender: Vec<SenderUsage>,
+    pub by_age: Vec<AgeUsage>,
+    /// Biggest wins first
+    pub candidates: Vec<DeletionCandidate>,
+}
+
+/// Age buckets (label, youngest age in days), youngest first
+const AGE_BUCKETS: &[(&str, i64)] = &[
+    ("Last 30 days", 0),
+    ("1-6 months", 30),
+    ("6-12 months", 182),
+    ("1-2 years", 365),
+    ("Older than 2 years", 730),
+];
+
+/// Messages of a thread at least `min_bytes` large
+fn large_messages(thread: &GmailThreadDetail, min_bytes: u64) -> Vec<LargeMessage> {
+    thread
+        .messages
+        .iter()
+        .flatten()
+        .filter(|m| m.size_estimate.unwrap_or(0) >= min_bytes)
+        .map(|message| {
+            let (from_name, from_email) = analytics::parse_from_header(
+                analytics::header(message, "From").unwrap_or_default(),
+            );
+            let labels = message.label_ids.as_deref().unwrap_or_default();
+            let has_label = |label: &str| labels.iter().any(|l| l == label);
+            LargeMessage {
+                message_id: message.id.clone(),
+                thread_id: thread.id.clone(),
+                thread_link: thread_link(&thread.id),
+                subject: analytics::header(message, "Subject")
+                    .unwrap_or("(No subject)")
+                    .to_string(),
+                from_name,
+                from_email: from_email.to_lowercase(),
+                size_bytes: message.size_estimate.unwrap_or(0),
+                received_ms: message
+                    .internal_date
+                    .as_deref()
+                    .and_then(|d| d.parse().ok())
+                    .unwrap_or(0),
+                starred: has_label("STARRED"),
+                bulk: analytics::header(message, "List-Unsubscribe").is_some()
+                    || has_label("CATEGORY_PROMOTIONS")
+                    || has_label("CATEGORY_SOCIAL"),
+            }
+        })
+        .collect()
+}
+
+/// Aggregate sampled messages by sender and age and pick deletion candidates
+fn build_storage_insights(
+    mut messages: Vec<LargeMessage>,
+    min_size_mb: u32,
+    truncated: bool,
+    now_ms: i64,
+) -> StorageInsights {
+    messages.sort_by_key(|m| std::cmp::Reverse(m.size_bytes));
+
+    let mut senders: HashMap<String, SenderUsage> = HashMap::new();
+    for message in &messages {
+        let usage = senders
+            .entry(message.from_email.clone())
+            .or_insert_with(|| SenderUsage {
+                name: message.from_name.clone(),
+                email: message.from_email.clone(),
+                message_count: 0,
+                total_bytes: 0,
+            });
+        usage.message_count += 1;
+        usage.total_bytes += message.size_bytes;
+    }
+    let mut by_sender: Vec<SenderUsage> = senders.into_values().collect();
+    by_sender.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.email.cmp(&b.email))
+    });
+
+    let age_days = |m: &LargeMessage| (now_ms - m.received_ms).max(0) / 86_400_000;
+    let mut by_age: Vec<AgeUsage> = AGE_BUCKETS
+        .iter()
+        .map(|(label, min_days)| AgeUsage {
+            label: label.to_string(),
+            min_days: *min_days,
+            message_count: 0,
+            total_bytes: 0,
+        })
+        .collect();
+    for message in &messages {
+        let days = age_days(message);
+        if let Some(bucket) = by_age.iter_mut().rev().find(|b| days >= b.min_days) {
+            bucket.message_count += 1;
+            bucket.total_bytes += message.size_bytes;
+        }
+    }
+
+    let candidates = messages
+        .iter()
+        .filter(|m| !m.starred)
+        .filter_map(|message| {
+            let repeat = by_sender.iter().any(|s| {
+                s.email == message.from_email && s.message_count >= REPEAT_SENDER_MESSAGES
+            });
+            let reasons: Vec<CleanupReason> = [
+                (age_days(message) >= 365, CleanupReason::Old),
+                (message.bulk, CleanupReason::Bulk),
+                (repeat, CleanupReason::RepeatSender),
+            ]
+            .into_iter()
+            .filter(|(applies, _)| *applies)
+            .map(|(_, reason)| reason)
+            .collect();
+            if reasons.is_empty() {
+                return None;
+            }
+            Some(DeletionCandidate {
+                message: message.clone(),
+                reasons,
+            })
+        })
+        .take(STORAGE_TOP_CANDIDATES)
+        .collect();
+
+    by_sender.truncate(STORAGE_TOP_SENDERS);
+    StorageInsights {
+        min_size_mb,
+        sampled_messages: messages.len(),
+        sampled_bytes: messages.iter().map(|m| m.size_bytes).sum(),
+        truncated,
+        by_sender,
+        by_age,
+        candidates,
+    }
+}
+
+/// Sample messages of at least `min_size_mb` (Gmail's size estimate) and
+/// break their storage down by sender and age, with deletion suggestions
+///
+/// Scans at most `max_threads` of the matching threads.
+#[tauri::command]
+pub async fn get_storage_insights(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    min_size_mb: Option<u32>,
+    max_threads: Option<u32>,
+    mailbox: Option<String>,
+) -> Result<StorageInsights, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let min_size_mb = min_size_mb.unwrap_or(DEFAULT_STORAGE_MIN_MB).max(1);
+    let max = max_threads
+        .unwrap_or(DEFAULT_STORAGE_THREADS)
+        .clamp(1, MAX_STORAGE_THREADS);
+
+    let url = format!(
+        "{}/{}/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        max,
+        urlencoding::encode(&format!("larger:{}M", min_size_mb))
+    );
+    let (thread_ids, truncated): (Vec<String>, bool) = client
+        .get_with(&url, &token_store, MAX_RESPONSE_BYTES, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            Ok((
+                page.threads
+                    .into_iter()
+                    .map(|t| t.id.into_owned())
+                    .collect(),
+                page.next_page_token.is_some(),
+            ))
+        })
+        .await?;
+
+    let hydration = hydrate_threads(
+        &token_store,
+        &client,
+        &mailbox,
+        &thread_ids,
+        DEFAULT_HYDRATION_PARALLELISM,
+    )
+    .await;
+    for error in &hydration.errors {
+        eprintln!(
+            "Skipping thread {} in storage scan: {}",
+            error.thread_id, error.error
+        );
+    }
+
+    let min_bytes = min_size_mb as u64 * 1024 * 1024;
+    let messages = hydration
+        .threads
+        .iter()
+        .flat_map(|thread| large_messages(thread, min_bytes))
+        .collect();
+    Ok(build_storage_insights(
+        messages,
+        min_size_mb,
+        truncated,
+        chrono::Utc::now().timestamp_millis(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{GmailHeader, GmailPartBody};
+
+    fn part(filename: &str, mime_type: &str, size: u64) -> GmailPayload {
+        GmailPayload {
+            headers: None,
+            mime_type: Some(mime_type.to_string()),
+            filename: Some(filename.to_string()),
+            body: Some(GmailPartBody {
+                attachment_id: (!filename.is_empty()).then(|| format!("att-{}", filename)),
+                size: Some(size),
+                data: None,
+            }),
+            parts: None,
+        }
+    }
+
+    fn message(id: &str, from: &str, parts: Vec<GmailPayload>) -> GmailMessage {
+        GmailMessage {
+            id: id.to_string(),
+            thread_id: "t1".to_string(),
+            label_ids: None,
+            snippet: String::new(),
+            payload: Some(GmailPayload {
+                headers: Some(vec![GmailHeader {
+                    name: "From".to_string(),
+                    value: from.to_string(),
+                }]),
+                mime_type: Some("multipart/mixed".to_string()),
+                filename: Some(String::new()),
+                body: None,
+                parts: Some(parts),
+            }),
+            internal_date: Some("1768000000000".to_string()),
+            size_estimate: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_attachments() {
+        let mut nested = part("", "multipart/alternative", 0);
+        nested.parts = Some(vec![
+            part("", "text/plain", 120),
+            part("photo.JPG", "image/jpeg", 2048),
+        ]);
+        let thread = GmailThreadDetail {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                message(
+                    "m1",
+                    "Marta <marta@example.com>",
+                    vec![
+                        nested,
+                        part("budget.xlsx", "application/vnd.ms-excel", 4096),
+                    ],
+                ),
+                message(
+                    "m2",
+                    "me@example.com",
+                    vec![part("reply.pdf", "application/pdf", 1)],
+                ),
+            ]),
+        };
+
+        let all = collect_attachments(&thread, "me@example.com", &[]);
+        let names: Vec<&str> = all.iter().map(|a| a.filename.as_str()).collect();
+        assert_eq!(names, ["photo.JPG", "budget.xlsx"]);
+        assert_eq!(all[1].size_bytes, 4096);
+        assert_eq!(all[1].from_name, "Marta");
+        assert_eq!(all[1].attachment_id.as_deref(), Some("att-budget.xlsx"));
+
+        let images = collect_attachments(&thread, "me@example.com", &["images".to_string()]);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].filename, "photo.JPG");
+    }
+
+    #[test]
+    fn test_attachment_query() {
+        assert_eq!(
+            attachment_query(7, &["PDF".to_string(), ".xlsx".to_string()]),
+            "has:attachment newer_than:7d {filename:pdf filename:xlsx}"
+        );
+        assert_eq!(
+            attachment_query(7, &["application/pdf".to_string()]),
+            "has:attachment newer_than:7d"
+        );
+    }
+
+    fn headers_message(headers: &[(&str, &str)], date_ms: i64) -> GmailMessage {
+        let mut message = message("m", "", Vec::new());
+        message.internal_date = Some(date_ms.to_string());
+        if let Some(payload) = message.payload.as_mut() {
+            payload.headers = Some(
+                headers
+                    .iter()
+                    .map(|(name, value)| GmailHeader {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+            );
+        }
+        message
+    }
+
+    #[test]
+    fn test_summarize_participants() {
+        let thread = GmailThreadDetail {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                headers_message(
+                    &[
+                        ("From", "Marta <marta@example.com>"),
+                        ("To", "me@example.com"),
+                        ("Cc", "Leo <leo@example.com>"),
+                    ],
+                    1_000,
+                ),
+                headers_message(
+                    &[
+                        ("From", "Me <ME@example.com>"),
+                        ("To", "marta@example.com, \"Leo\" <leo@example.com>"),
+                    ],
+                    2_000,
+                ),
+            ]),
+        };
+
+        let summary = summarize_participants(&thread, "me@example.com");
+        let emails: Vec<&str> = summary
+            .participants
+            .iter()
+            .map(|p| p.email.as_str())
+            .collect();
+        assert_eq!(
+            emails,
+            ["marta@example.com", "me@example.com", "leo@example.com"]
+        );
+        assert_eq!(summary.participants[0].messages_sent, 1);
+        assert_eq!(summary.participants[1].name, "Me");
+        assert!(summary.participants[1].is_self);
+        assert_eq!(summary.participants[2].last_sent_ms, None);
+
+        assert_eq!(
+            summary.last_message_direction,
+            Some(MessageDirection::Outgoing)
+        );
+        assert_eq!(summary.last_message_ms, Some(2_000));
+        assert_eq!(summary.waiting_on, ["marta@example.com", "leo@example.com"]);
+    }
+
+    #[test]
+    fn test_build_timeline() {
+        let message = |id: &str, from: &str, date_ms: i64| {
+            let mut message = headers_message(&[("From", from)], date_ms);
+            message.id = id.to_string();
+            message
+        };
+        let thread = GmailThreadDetail {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                message("m3", "Me <me@example.com>", 5_000),
+                message("m1", "Marta <marta@example.com>", 1_000),
+                message("m2", "marta@example.com", 2_000),
+                message("m4", "Leo <leo@example.com>", 9_000),
+                message("m5", "me@example.com", 10_000),
+            ]),
+        };
+
+        let timeline = build_timeline(&thread, "ME@example.com", 60_000);
+        let ids: Vec<&str> = timeline
+            .entries
+            .iter()
+            .map(|e| e.message_id.as_str())
+            .collect();
+        assert_eq!(ids, ["m1", "m2", "m3", "m4", "m5"]);
+
+        // My reply answers Marta's follow-up, 3s after it
+        let reply = &timeline.entries[2];
+        assert_eq!(reply.replied_to.as_deref(), Some("m2"));
+        assert_eq!(reply.response_latency_ms, Some(3_000));
+        assert_eq!(reply.direction, MessageDirection::Outgoing);
+        assert!(timeline.entries[0].answered && timeline.entries[1].answered);
+        assert_eq!(timeline.entries[0].replied_to, None);
+
+        assert_eq!(
+            timeline.latencies[0],
+            ResponseLatency {
+                responder: "leo@example.com".to_string(),
+                replied_to: "me@example.com".to_string(),
+                replies: 1,
+                average_ms: 4_000,
+                longest_ms: 4_000,
+            }
+        );
+        assert_eq!(
+            timeline.longest_outstanding,
+            Some(OutstandingMessage {
+                message_id: "m5".to_string(),
+                from_email: "me@example.com".to_string(),
+                direction: MessageDirection::Outgoing,
+                waiting_ms: 50_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_position() {
+        let ids = |ids: &[&str]| -> Vec<String> { ids.iter().map(|s| s.to_string()).collect() };
+        let mut position = ReadPosition {
+            thread_id: "t1".to_string(),
+            message_ids: ids(&["m1", "m2", "m3", "m4"]),
+            ..ReadPosition::default()
+        };
+        assert_eq!(position.unseen_count(), 4);
+
+        // Scrolling to m3 passes m1 and m2
+        position.mark_seen(&ids(&["m3"]));
+        assert_eq!(position.unseen_count(), 1);
+        assert_eq!(position.last_seen_message_id.as_deref(), Some("m3"));
+
+        // A new reply is unseen
+        position.message_ids.push("m5".to_string());
+        assert_eq!(position.unseen_count(), 2);
+        position.mark_seen(&ids(&["m5", "m4"]));
+        assert_eq!(position.unseen_count(), 0);
+        assert_eq!(position.seen_message_ids.len(), 5);
+    }
+
+    #[test]
+    fn test_body_html() {
+        let body = |mime_type: &str, text: &str| GmailPayload {
+            headers: None,
+            mime_type: Some(mime_type.to_string()),
+            filename: Some(String::new()),
+            body: Some(GmailPartBody {
+                attachment_id: None,
+                size: Some(text.len() as u64),
+                data: Some(URL_SAFE_NO_PAD.encode(text)),
+            }),
+            parts: None,
+        };
+        let mut payload = part("", "multipart/alternative", 0);
+        payload.parts = Some(vec![
+            body("text/plain", "a < b"),
+            body("text/html", "<p>Hi</p>"),
+        ]);
+        assert_eq!(body_html(&payload).as_deref(), Some("<p>Hi</p>"));
+
+        payload.parts = Some(vec![body("text/plain", "a < b")]);
+        assert_eq!(body_html(&payload).as_deref(), Some("<pre>a &lt; b</pre>"));
+    }
+
+    #[test]
+    fn test_storage_insights() {
+        const MB: u64 = 1024 * 1024;
+        const DAY: i64 = 86_400_000;
+        let now = 1_000 * DAY;
+        let sized = |id: &str, from: &str, mb: u64, age_days: i64, labels: &[&str]| {
+            let mut message = headers_message(&[("From", from)], now - age_days * DAY);
+            message.id = id.to_string();
+            message.size_estimate = Some(mb * MB);
+            message.label_ids = Some(labels.iter().map(|l| l.to_string()).collect());
+            message
+        };
+        let thread = GmailThreadDetail {
+            id: "t1".to_string(),
+            messages: Some(vec![
+                sized("small", "Ana <ana@example.com>", 1, 2, &[]),
+                sized("recent", "Ana <ana@example.com>", 8, 2, &[]),
+                sized("old", "Ana <ana@example.com>", 6, 400, &[]),
+                sized("starred", "Ana <ana@example.com>", 20, 800, &["STARRED"]),
+                sized(
+                    "promo",
+                    "Shop <deals@shop.com>",
+                    5,
+                    40,
+                    &["CATEGORY_PROMOTIONS"],
+                ),
+            ]),
+        };
+
+        let messages = large_messages(&thread, 5 * MB);
+        assert_eq!(messages.len(), 4);
+        let insights = build_storage_insights(messages, 5, false, now);
+        assert_eq!(insights.sampled_bytes, 39 * MB);
+        assert_eq!(insights.by_sender[0].email, "ana@example.com");
+        assert_eq!(insights.by_sender[0].message_count, 3);
+        let ages: Vec<usize> = insights.by_age.iter().map(|b| b.message_count).collect();
+        assert_eq!(ages, vec![1, 1, 0, 1, 1]);
+
+        let candidates: Vec<(&str, &[CleanupReason])> = insights
+            .candidates
+            .iter()
+            .map(|c| (c.message.message_id.as_str(), c.reasons.as_slice()))
+            .collect();
+        assert_eq!(
+            candidates,
+            vec![
+                ("recent", &[CleanupReason::RepeatSender][..]),
+                (
+                    "old",
+                    &[CleanupReason::Old, CleanupReason::RepeatSender][..]
+                ),
+                ("promo", &[CleanupReason::Bulk][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inbox_sampling() {
+        assert_eq!(
+            allocate_sample(200, &[3_000, 900, 0, 400, 0]),
+            [139, 42, 0, 19, 0]
+        );
+        assert_eq!(allocate_sample(10, &[1_000, 1, 0]), [9, 1, 0]);
+        assert_eq!(allocate_sample(200, &[12, 5]), [12, 5]);
+
+        let thread = |id: &str, from: &str| ThreadSummary {
+            id: id.to_string(),
+            subject: String::new(),
+            snippet: String::new(),
+            from_name: String::new(),
+            from_email: from.to_string(),
+            date: String::new(),
+            is_unread: true,
+            message_count: 1,
+            priority_score: 0.5,
+            unseen_message_count: 1,
+        };
+        let picked = pick_stratum(
+            vec![
+                thread("t1", "news@shop.com"),
+                thread("t2", "news@shop.com"),
+                thread("t3", ""),
+                thread("t4", ""),
+                thread("t5", "jane@example.com"),
+            ],
+            3,
+            1,
+        );
+        let ids: Vec<&str> = picked.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["t1", "t3", "t4"]);
+
+        assert_eq!(sample_label(200, 4_312, true), "Showing 200 of ~4,300");
+        assert_eq!(sample_label(12, 12, false), "Showing all 12");
+    }
 }
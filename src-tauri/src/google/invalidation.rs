@@ -0,0 +1,110 @@
+//! Cache invalidation after mutations
+//!
+//! Maps each `DataEvent` to the backend cache entries it makes stale.
+//! Mutating commands report a successful write through `mutated`, which
+//! clears those entries for the signed-in account and then emits the event,
+//! so the frontend never has to clean up the cache itself.
+
+use super::mailbox::Mailbox;
+use crate::account::AccountContext;
+use crate::auth::TokenStore;
+use crate::cache::{CacheState, RustCache, INBOX_SUMMARY_KEY, TASKS_KEY_PREFIX, TODAY_EVENTS_KEY};
+use crate::events::{self, DataEvent};
+use tauri::{AppHandle, Manager};
+
+/// Account-relative cache keys made stale by a change
+///
+/// A `*` stands for any run of characters, e.g. every delegated mailbox.
+pub fn stale_keys(event: &DataEvent) -> Vec<String> {
+    match event {
+        DataEvent::TaskCreated { list_id, .. }
+        | DataEvent::TaskUpdated { list_id, .. }
+        | DataEvent::TaskCompleted { list_id, .. }
+        | DataEvent::TaskReopened { list_id, .. }
+        | DataEvent::TaskDeleted { list_id, .. } => {
+            vec![format!("{}{}", TASKS_KEY_PREFIX, list_id)]
+        }
+        // Today's events include iCal subscriptions
+        DataEvent::EventCreated { .. }
+        | DataEvent::EventResponded { .. }
+        | DataEvent::FeedRefreshed { .. } => {
+            vec![TODAY_EVENTS_KEY.to_string()]
+        }
+        DataEvent::ThreadsModified { .. } => vec![
+            INBOX_SUMMARY_KEY.to_string(),
+            Mailbox::Delegated("*".to_string()).cache_key(INBOX_SUMMARY_KEY),
+        ],
+        DataEvent::PlanRegenerated { .. }
+        | DataEvent::PlanChanged { .. }
+        | DataEvent::NoteReady { .. }
+        | DataEvent::ThreadFollowUpOffered { .. } => Vec::new(),
+    }
+}
+
+/// Remove an account's entries matching `pattern`; returns how many were removed
+fn invalidate(cache: &RustCache, account: &AccountContext, pattern: &str) -> usize {
+    match pattern.split_once('*') {
+        None => usize::from(cache.remove(&account.cache_key(pattern)).is_some()),
+        Some((prefix, suffix)) => cache
+            .get_prefix(&account.cache_key(prefix))
+            .into_iter()
+            .filter(|(key, _)| key.ends_with(suffix))
+            .filter(|(key, _)| cache.remove(key).is_some())
+            .count(),
+    }
+}
+
+/// Clear the cache entries a successful mutation made stale, then emit it
+pub async fn mutated(app: &AppHandle, event: DataEvent) {
+    // Signed out: nothing of this account is cached
+    if let Ok(account) = app.state::<TokenStore>().account_context().await {
+        let cache = app.state::<CacheState>();
+        for pattern in stale_keys(&event) {
+            invalidate(&cache.0, &account, &pattern);
+        }
+    }
+    events::emit(app, event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalidate_stale_keys() {
+        let cache = RustCache::new();
+        let account = AccountContext::new("me@example.com");
+        let other = AccountContext::new("other@example.com");
+        let delegated = Mailbox::Delegated("team@example.com".to_string());
+        for key in [
+            account.cache_key(INBOX_SUMMARY_KEY),
+            account.cache_key(&delegated.cache_key(INBOX_SUMMARY_KEY)),
+            account.cache_key(TODAY_EVENTS_KEY),
+            account.cache_key("tasks:list:l1"),
+            account.cache_key("tasks:list:l2"),
+            other.cache_key(INBOX_SUMMARY_KEY),
+        ] {
+            cache.set(&key, "[]".to_string(), 60);
+        }
+
+        let threads = DataEvent::ThreadsModified {
+            thread_ids: vec!["t1".to_string()],
+        };
+        let removed: usize = stale_keys(&threads)
+            .iter()
+            .map(|pattern| invalidate(&cache, &account, pattern))
+            .sum();
+        assert_eq!(removed, 2);
+        assert!(cache.get(&account.cache_key(TODAY_EVENTS_KEY)).is_some());
+        assert!(cache.get(&other.cache_key(INBOX_SUMMARY_KEY)).is_some());
+
+        let completed = DataEvent::TaskCompleted {
+            list_id: "l1".to_string(),
+            task_id: "t1".to_string(),
+        };
+        assert_eq!(stale_keys(&completed), ["tasks:list:l1"]);
+        invalidate(&cache, &account, &stale_keys(&completed)[0]);
+        assert!(cache.get(&account.cache_key("tasks:list:l1")).is_none());
+        assert!(cache.get(&account.cache_key("tasks:list:l2")).is_some());
+    }
+}
@@ -0,0 +1,208 @@
+//! Delegated and shared mailboxes
+//!
+//! Gmail delegation lets a user read another mailbox through
+//! `users/<delegator>` with their own credentials. Gmail has no endpoint
+//! listing the mailboxes delegated *to* a user, so they are added explicitly:
+//! `add_delegated_mailbox` checks access via `users/<email>/profile` before
+//! saving the mailbox in the signed-in account's storage.
+//!
+//! Gmail commands take an optional `mailbox`. Anything other than the user's
+//! own address must be a registered delegated mailbox; its cache keys are
+//! namespaced with the mailbox and its messages stay out of the user's own
+//! analytics history.
+
+use super::{GoogleClient, GMAIL_API_BASE};
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::storage::{LocalStorage, DELEGATED_MAILBOXES};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Mailbox a Gmail request targets
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Mailbox {
+    /// The signed-in user's mailbox (`users/me`)
+    #[default]
+    Own,
+    /// A mailbox delegated to the signed-in user
+    Delegated(String),
+}
+
+impl Mailbox {
+    /// Gmail API user path (`users/me` or `users/<email>`)
+    pub fn user_path(&self) -> String {
+        match self {
+            Mailbox::Own => "users/me".to_string(),
+            Mailbox::Delegated(email) => format!("users/{}", urlencoding::encode(email)),
+        }
+    }
+
+    /// Namespace a per-account cache key with this mailbox
+    pub fn cache_key(&self, key: &str) -> String {
+        match self {
+            Mailbox::Own => key.to_string(),
+            Mailbox::Delegated(email) => format!("mailbox:{}:{}", email, key),
+        }
+    }
+
+    pub fn is_own(&self) -> bool {
+        matches!(self, Mailbox::Own)
+    }
+}
+
+/// A mailbox the signed-in user can access by delegation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegatedMailbox {
+    pub email: String,
+    pub messages_total: Option<u64>,
+    pub threads_total: Option<u64>,
+    pub verified_at_ms: i64,
+}
+
+/// Gmail profile (from users.getProfile)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GmailProfile {
+    email_address: String,
+    messages_total: Option<u64>,
+    threads_total: Option<u64>,
+}
+
+fn normalize_email(email: &str) -> Result<String, String> {
+    let email = email.trim().to_lowercase();
+    if email.len() < 3 || email.matches('@').count() != 1 || email.contains('/') {
+        return Err(format!("Invalid mailbox address: {}", email));
+    }
+    Ok(email)
+}
+
+/// Resolve a command's `mailbox` argument
+///
+/// `None`, `"me"` and the user's own address mean the own mailbox; other
+/// addresses must have been added with `add_delegated_mailbox`.
+pub async fn resolve(
+    token_store: &TokenStore,
+    storage: &LocalStorage,
+    mailbox: Option<String>,
+) -> Result<Mailbox, String> {
+    let Some(mailbox) = mailbox.filter(|m| !m.trim().is_empty() && m.trim() != "me") else {
+        return Ok(Mailbox::Own);
+    };
+    let email = normalize_email(&mailbox)?;
+    let account = token_store.account_context().await?;
+    if email == account.email() {
+        return Ok(Mailbox::Own);
+    }
+
+    if storage
+        .get(&account, DELEGATED_MAILBOXES, &email)?
+        .is_none()
+    {
+        return Err(format!(
+            "Mailbox {} has not been added as a delegated mailbox",
+            email
+        ));
+    }
+    Ok(Mailbox::Delegated(email))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// List the delegated mailboxes added for the signed-in account
+#[tauri::command]
+pub async fn list_delegated_mailboxes(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<Vec<DelegatedMailbox>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let mut mailboxes: Vec<DelegatedMailbox> = storage
+        .list(&account, DELEGATED_MAILBOXES)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok())
+        .collect();
+    mailboxes.sort_by(|a, b| a.email.cmp(&b.email));
+    Ok(mailboxes)
+}
+
+/// Add a mailbox delegated to the signed-in user, after checking access to it
+#[tauri::command]
+pub async fn add_delegated_mailbox(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    email: String,
+) -> Result<DelegatedMailbox, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let email = normalize_email(&email)?;
+    let account = token_store.account_context().await?;
+    if email == account.email() {
+        return Err("Your own mailbox is always available".to_string());
+    }
+
+    let url = format!(
+        "{}/{}/profile",
+        GMAIL_API_BASE,
+        Mailbox::Delegated(email.clone()).user_path()
+    );
+    let profile: GmailProfile = client
+        .get(&url, &token_store)
+        .await
+        .map_err(|e| format!("No delegated access to {}: {}", email, e))?;
+
+    let mailbox = DelegatedMailbox {
+        email: profile.email_address.to_lowercase(),
+        messages_total: profile.messages_total,
+        threads_total: profile.threads_total,
+        verified_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    let value = serde_json::to_value(&mailbox)
+        .map_err(|e| format!("Failed to serialize mailbox: {}", e))?;
+    storage.put(&account, DELEGATED_MAILBOXES, &email, value)?;
+    Ok(mailbox)
+}
+
+/// Remove a delegated mailbox; returns false if it wasn't added
+#[tauri::command]
+pub async fn remove_delegated_mailbox(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    email: String,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let email = normalize_email(&email)?;
+    let account = token_store.account_context().await?;
+    storage.remove(&account, DELEGATED_MAILBOXES, &email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mailbox_paths_and_cache_keys() {
+        assert_eq!(Mailbox::Own.user_path(), "users/me");
+        assert_eq!(Mailbox::Own.cache_key("inbox"), "inbox");
+
+        let shared = Mailbox::Delegated("team+ops@example.com".to_string());
+        assert_eq!(shared.user_path(), "users/team%2Bops%40example.com");
+        assert_eq!(
+            shared.cache_key("inbox"),
+            "mailbox:team+ops@example.com:inbox"
+        );
+
+        assert_eq!(
+            normalize_email(" Boss@Example.com ").unwrap(),
+            "boss@example.com"
+        );
+        assert!(normalize_email("users/other").is_err());
+    }
+}
@@ -0,0 +1,420 @@
+//! Mock Google provider for development and testing
+//!
+//! When enabled, `GoogleClient` answers every request from deterministic
+//! fixtures instead of calling Google, so the UI can be developed and tested
+//! without live credentials or quota. Latency and failures can be injected.
+//!
+//! Enabled at compile time with the `mock` cargo feature, or at runtime with:
+//! - `RAINY_DAY_MOCK=1`
+//! - `RAINY_DAY_MOCK_LATENCY_MS` - delay added to every request (default 0)
+//! - `RAINY_DAY_MOCK_FAIL_EVERY` - fail every Nth request (default never)
+//! - `RAINY_DAY_MOCK_FAIL_STATUS` - HTTP status of injected failures (default 503)
+
+use super::{GoogleApiError, CALENDAR_API_BASE, GMAIL_API_BASE, TASKS_API_BASE};
+use chrono::{Local, NaiveTime, TimeZone};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const MOCK_ENV: &str = "RAINY_DAY_MOCK";
+const LATENCY_ENV: &str = "RAINY_DAY_MOCK_LATENCY_MS";
+const FAIL_EVERY_ENV: &str = "RAINY_DAY_MOCK_FAIL_EVERY";
+const FAIL_STATUS_ENV: &str = "RAINY_DAY_MOCK_FAIL_STATUS";
+
+/// Email of the fake signed-in account used in mock mode
+pub const MOCK_ACCOUNT_EMAIL: &str = "mock@rainyday.local";
+
+/// Latency and error injection settings
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub latency: Duration,
+    /// Fail every Nth request (deterministic); None never fails
+    pub fail_every: Option<u64>,
+    pub fail_status: u16,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            fail_every: None,
+            fail_status: 503,
+        }
+    }
+}
+
+impl MockConfig {
+    /// Mock settings from the environment, or None when mock mode is off
+    pub fn from_env() -> Option<Self> {
+        let enabled = cfg!(feature = "mock")
+            || std::env::var(MOCK_ENV).is_ok_and(|v| v == "1" || v == "true");
+        if !enabled {
+            return None;
+        }
+
+        let env_num = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        let defaults = Self::default();
+
+        Some(Self {
+            latency: env_num(LATENCY_ENV)
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.latency),
+            fail_every: env_num(FAIL_EVERY_ENV).filter(|n| *n > 0),
+            fail_status: env_num(FAIL_STATUS_ENV)
+                .and_then(|s| u16::try_from(s).ok())
+                .unwrap_or(defaults.fail_status),
+        })
+    }
+}
+
+/// Whether mock mode is enabled for this run
+pub fn is_enabled() -> bool {
+    MockConfig::from_env().is_some()
+}
+
+/// Fixture-backed request handler
+pub struct MockProvider {
+    config: MockConfig,
+    requests: AtomicU64,
+}
+
+impl MockProvider {
+    pub fn new(config: MockConfig) -> Self {
+        Self {
+            config,
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Answer a request, applying the configured latency and failures
+    pub async fn respond(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<Value>,
+    ) -> Result<Value, GoogleApiError> {
+        if !self.config.latency.is_zero() {
+            tokio::time::sleep(self.config.latency).await;
+        }
+
+        let count = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        if self
+            .config
+            .fail_every
+            .is_some_and(|n| count.is_multiple_of(n))
+        {
+            let status = StatusCode::from_u16(self.config.fail_status)
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(GoogleApiError::from_response(
+                status,
+                "injected mock failure".to_string(),
+            ));
+        }
+
+        let path = url.split('?').next().unwrap_or(url);
+        route(method, path, body).ok_or_else(|| {
+            GoogleApiError::from_response(
+                StatusCode::NOT_FOUND,
+                format!("no mock fixture for {} {}", method, path),
+            )
+        })
+    }
+}
+
+fn route(method: &str, path: &str, body: Option<Value>) -> Option<Value> {
+    if let Some(rest) = path.strip_prefix(GMAIL_API_BASE) {
+        return gmail(method, rest);
+    }
+    if let Some(rest) = path.strip_prefix(CALENDAR_API_BASE) {
+        return calendar(method, rest, body);
+    }
+    if let Some(rest) = path.strip_prefix(TASKS_API_BASE) {
+        return tasks(method, rest, body);
+    }
+    None
+}
+
+// ============================================================================
+// Fixtures
+// ============================================================================
+
+const THREADS: &[(&str, &str, &str, &str)] = &[
+    (
+        "mock-thread-1",
+        "Quarterly review",
+        "Ana Ruiz <ana@example.com>",
+        "Can we move the review to Thursday afternoon?",
+    ),
+    (
+        "mock-thread-2",
+        "Invoice #4821",
+        "Billing <billing@example.com>",
+        "Your invoice for January is ready.",
+    ),
+    (
+        "mock-thread-3",
+        "Lunch?",
+        "Sam Lee <sam@example.com>",
+        "Free for lunch tomorrow around 1pm?",
+    ),
+];
+
+const TASKS: &[(&str, &str, &str)] = &[
+    ("mock-task-1", "Prepare review slides", "needsAction"),
+    ("mock-task-2", "Pay invoice #4821", "needsAction"),
+    ("mock-task-3", "Book flights", "completed"),
+];
+
+fn gmail(method: &str, rest: &str) -> Option<Value> {
+    let segments: Vec<&str> = rest.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["users", _, "threads"]) => Some(json!({
+            "threads": THREADS
+                .iter()
+                .map(|(id, _, _, snippet)| {
+                    json!({ "id": id, "snippet": snippet, "historyId": "1" })
+                })
+                .collect::<Vec<_>>(),
+            "resultSizeEstimate": THREADS.len(),
+        })),
+        ("GET", ["users", user, "profile"]) => {
+            let email = match *user {
+                "me" => MOCK_ACCOUNT_EMAIL.to_string(),
+                user => urlencoding::decode(user).ok()?.into_owned(),
+            };
+            Some(json!({
+                "emailAddress": email,
+                "messagesTotal": THREADS.len(),
+                "threadsTotal": THREADS.len(),
+            }))
+        }
+        ("GET", ["users", _, "threads", id]) => {
+            let (id, subject, from, snippet) = THREADS.iter().find(|t| t.0 == *id)?;
+            Some(json!({
+                "id": id,
+                "messages": [{
+                    "id": format!("{}-msg-1", id),
+                    "threadId": id,
+                    "labelIds": ["INBOX", "UNREAD"],
+                    "snippet": snippet,
+                    "internalDate": today_at(8, 15),
+                    "payload": {
+                        "mimeType": "text/plain",
+                        "headers": [
+                            { "name": "Subject", "value": subject },
+                            { "name": "From", "value": from },
+                        ],
+                    },
+                }],
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn calendar(method: &str, rest: &str, body: Option<Value>) -> Option<Value> {
+    let segments: Vec<&str> = rest.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["calendars", "primary", "events"]) => {
+            let mut review = mock_event("mock-event-2", "Quarterly review", (14, 0), (15, 0), None);
+            review["colorId"] = json!("11");
+            Some(json!({
+                "items": [
+                    mock_event(
+                        "mock-event-1",
+                        "Daily standup",
+                        (9, 30),
+                        (10, 0),
+                        Some("https://meet.google.com/mock-standup"),
+                    ),
+                    review,
+                ],
+                "timeZone": "UTC",
+            }))
+        }
+        ("GET", ["colors"]) => Some(json!({
+            "calendar": { "14": { "background": "#9fe1e7", "foreground": "#1d1d1d" } },
+            "event": { "11": { "background": "#dc2127", "foreground": "#1d1d1d" } },
+        })),
+        ("GET", ["users", "me", "calendarList", "primary"]) => Some(json!({
+            "id": MOCK_ACCOUNT_EMAIL,
+            "colorId": "14",
+        })),
+        // Holiday and other secondary calendars are empty
+        ("GET", ["calendars", _, "events"]) => Some(json!({ "items": [] })),
+        ("POST", ["freeBusy"]) => Some(json!({
+            "calendars": {
+                "primary": {
+                    "busy": [
+                        { "start": rfc3339_today(9, 30), "end": rfc3339_today(10, 0) },
+                        { "start": rfc3339_today(14, 0), "end": rfc3339_today(15, 0) },
+                    ],
+                },
+            },
+        })),
+        ("POST", ["calendars", _, "events"]) => Some(with_id(body?, "mock-event")),
+        _ => None,
+    }
+}
+
+fn tasks(method: &str, rest: &str, body: Option<Value>) -> Option<Value> {
+    let segments: Vec<&str> = rest.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["users", "@me", "lists"]) => Some(json!({
+            "items": [{ "id": "mock-list", "title": "My Tasks", "updated": rfc3339_today(8, 0) }],
+        })),
+        ("GET", ["lists", _, "tasks"]) => Some(json!({
+            "items": TASKS
+                .iter()
+                .map(|(id, title, status)| json!({
+                    "id": id,
+                    "title": title,
+                    "status": status,
+                    "due": rfc3339_today(0, 0),
+                }))
+                .collect::<Vec<_>>(),
+        })),
+        ("POST", ["lists", _, "tasks"]) => Some(with_id(body?, "mock-task")),
+        ("PATCH", ["lists", _, "tasks", id]) => {
+            let (_, title, status) = TASKS.iter().find(|t| t.0 == *id)?;
+            let mut task = json!({ "id": id, "title": title, "status": status });
+            if let (Some(task), Some(Value::Object(update))) = (task.as_object_mut(), body) {
+                task.extend(update.into_iter().filter(|(_, v)| !v.is_null()));
+            }
+            Some(task)
+        }
+        ("DELETE", ["lists", _, "tasks", id]) => {
+            TASKS.iter().any(|t| t.0 == *id).then(|| json!({}))
+        }
+        _ => None,
+    }
+}
+
+fn mock_event(
+    id: &str,
+    summary: &str,
+    start: (u32, u32),
+    end: (u32, u32),
+    meeting_link: Option<&str>,
+) -> Value {
+    json!({
+        "id": id,
+        "summary": summary,
+        "start": { "dateTime": rfc3339_today(start.0, start.1) },
+        "end": { "dateTime": rfc3339_today(end.0, end.1) },
+        "attendees": [{ "email": MOCK_ACCOUNT_EMAIL }, { "email": "ana@example.com" }],
+        "hangoutLink": meeting_link,
+    })
+}
+
+/// Echo a created resource back with a deterministic ID
+fn with_id(mut body: Value, prefix: &str) -> Value {
+    static CREATED: AtomicU64 = AtomicU64::new(0);
+    if let Some(object) = body.as_object_mut() {
+        let n = CREATED.fetch_add(1, Ordering::Relaxed) + 1;
+        object.insert("id".to_string(), json!(format!("{}-new-{}", prefix, n)));
+    }
+    body
+}
+
+fn today_at_local(hour: u32, minute: u32) -> Option<chrono::DateTime<Local>> {
+    let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+    Local
+        .from_local_datetime(&Local::now().date_naive().and_time(time))
+        .earliest()
+}
+
+fn rfc3339_today(hour: u32, minute: u32) -> String {
+    today_at_local(hour, minute)
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn today_at(hour: u32, minute: u32) -> String {
+    today_at_local(hour, minute)
+        .map(|d| d.timestamp_millis().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{CalendarEventsResponse, GmailThreadsPage, Task, TasksResponse};
+
+    fn provider(config: MockConfig) -> MockProvider {
+        MockProvider::new(config)
+    }
+
+    #[tokio::test]
+    async fn test_fixtures_match_api_types() {
+        let mock = provider(MockConfig::default());
+
+        let threads = mock
+            .respond(
+                "GET",
+                &format!("{}/users/me/threads?q=x", GMAIL_API_BASE),
+                None,
+            )
+            .await
+            .unwrap();
+        let body = serde_json::to_vec(&threads).unwrap();
+        let threads: GmailThreadsPage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(threads.threads.len(), THREADS.len());
+
+        let events = mock
+            .respond(
+                "GET",
+                &format!("{}/calendars/primary/events", CALENDAR_API_BASE),
+                None,
+            )
+            .await
+            .unwrap();
+        let events: CalendarEventsResponse = serde_json::from_value(events).unwrap();
+        assert_eq!(events.items.unwrap().len(), 2);
+
+        let tasks = mock
+            .respond(
+                "GET",
+                &format!("{}/lists/mock-list/tasks", TASKS_API_BASE),
+                None,
+            )
+            .await
+            .unwrap();
+        let tasks: TasksResponse = serde_json::from_value(tasks).unwrap();
+        assert_eq!(tasks.items.unwrap().len(), TASKS.len());
+
+        let patched = mock
+            .respond(
+                "PATCH",
+                &format!("{}/lists/mock-list/tasks/mock-task-1", TASKS_API_BASE),
+                Some(json!({ "status": "completed", "title": null })),
+            )
+            .await
+            .unwrap();
+        let patched: Task = serde_json::from_value(patched).unwrap();
+        assert_eq!(patched.status.as_deref(), Some("completed"));
+        assert_eq!(patched.title, "Prepare review slides");
+    }
+
+    #[tokio::test]
+    async fn test_error_injection_is_deterministic() {
+        let mock = provider(MockConfig {
+            fail_every: Some(2),
+            fail_status: 429,
+            ..MockConfig::default()
+        });
+        let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+
+        assert!(mock.respond("GET", &url, None).await.is_ok());
+        let err = mock.respond("GET", &url, None).await.unwrap_err();
+        assert_eq!(err.status(), Some(429));
+        assert!(err.retryable());
+        assert!(mock.respond("GET", &url, None).await.is_ok());
+
+        let missing = provider(MockConfig::default())
+            .respond("GET", "https://example.com/nope", None)
+            .await;
+        assert_eq!(missing.unwrap_err().status(), Some(404));
+    }
+}
@@ -1,134 +1,740 @@
 //! Google API client modules
 //!
 //! Provides typed clients for:
-//! - Gmail API (threads, messages)
+//! - Gmail API (threads, messages), including delegated mailboxes
 //! - Calendar API (events)
 //! - Tasks API (task lists, tasks)
+//!
+//! Requests fail with a structured `GoogleApiError` (status, Google's error
+//! reason, whether a retry may help). Bursts are smoothed per API by
+//! `rate_limit` before they reach Google, and identical GETs in flight share
+//! one request (`coalesce`). Reads that only need part of a resource ask for
+//! just that part with a typed `fields` selector (`get_fields`). Many calls
+//! to one API can go out as multipart batches (`batch`), and independent calls
+//! run side by side through `fan_out`, which bounds how many are in flight.
+//! A host that keeps failing is paused by its `circuit` breaker, so callers
+//! fail fast instead of waiting on an outage. Connections and reads are
+//! bounded by configurable `timeouts`, and a command can cap the total time
+//! of its requests with a `deadline`.
+//!
+//! Mutations report what they changed through `invalidation::mutated`, which
+//! clears the stale cache entries before emitting the data-change event.
 
+pub mod batch;
 pub mod calendar;
+pub mod circuit;
+pub mod coalesce;
+pub mod deadline;
+pub mod endpoints;
+pub mod error;
+pub mod fields;
 pub mod gmail;
+pub mod invalidation;
+pub mod mailbox;
+pub mod mock;
+pub mod quota;
+pub mod rate_limit;
+pub mod recording;
 pub mod tasks;
+pub mod timeouts;
 pub mod types;
 
-use reqwest::Client;
+use crate::auth::TokenStore;
+use crate::health::{ApiKind, HealthMonitor};
+use batch::{BatchCall, BatchOutcome, MAX_BATCH_SIZE};
+use circuit::CircuitBreaker;
+use coalesce::Coalescer;
+use endpoints::ApiEndpoints;
+pub use error::GoogleApiError;
+use fields::Fields;
+use futures::future::join_all;
+use mock::{MockConfig, MockProvider};
+use quota::QuotaTracker;
+use rate_limit::RateLimiter;
+use recording::RequestRecorder;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use timeouts::ApiTimeouts;
+use tokio::sync::Semaphore;
 
-/// Base URL for Google APIs
+/// Base URL for Google APIs (overridable at runtime, see `endpoints`)
 pub const GMAIL_API_BASE: &str = "https://gmail.googleapis.com/gmail/v1";
 pub const CALENDAR_API_BASE: &str = "https://www.googleapis.com/calendar/v3";
 pub const TASKS_API_BASE: &str = "https://tasks.googleapis.com/tasks/v1";
 
+/// Retries after a 429 before giving up
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// First backoff delay when the response has no Retry-After header
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for a single Retry-After wait
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+/// Largest response body accepted by default (16 MiB)
+pub const MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+/// Upper bound on calls `fan_out` keeps in flight
+pub const MAX_FAN_OUT_LIMIT: usize = 10;
+
+/// GET bodies being fetched, keyed by (user, URL, size limit, deadline): a
+/// caller never waits past its own deadline, nor gets a timeout meant for a
+/// tighter one
+type InFlightGets =
+    Coalescer<(String, String, usize, Option<Instant>), Result<Arc<Vec<u8>>, GoogleApiError>>;
+
 /// Shared HTTP client for all Google API requests
 pub struct GoogleClient {
-    http: Client,
+    /// Rebuilt when the timeouts change
+    http: RwLock<Client>,
+    timeouts: RwLock<ApiTimeouts>,
+    health: Arc<HealthMonitor>,
+    quota: Arc<QuotaTracker>,
+    /// Pauses requests to hosts that keep failing
+    circuit: Arc<CircuitBreaker>,
+    /// Smooths bursts per API before they reach Google
+    limiter: RateLimiter,
+    /// Identical GETs in flight
+    in_flight: InFlightGets,
+    /// Fixture provider used instead of the network in mock mode
+    mock: Option<MockProvider>,
+    /// Origin replacing Google's API hosts (fake-server test harness)
+    base_override: Option<String>,
+    /// Per-API base URL overrides from settings/env
+    endpoints: RwLock<ApiEndpoints>,
+    /// Set while record mode is on
+    recorder: RwLock<Option<Arc<RequestRecorder>>>,
 }
 
 impl GoogleClient {
     pub fn new() -> Self {
         Self {
-            http: Client::new(),
+            http: RwLock::new(timeouts::http_client(&ApiTimeouts::default()).unwrap_or_default()),
+            timeouts: RwLock::new(ApiTimeouts::default()),
+            health: Arc::new(HealthMonitor::new()),
+            quota: Arc::new(QuotaTracker::new()),
+            circuit: Arc::new(CircuitBreaker::new()),
+            limiter: RateLimiter::new(),
+            in_flight: Coalescer::new(),
+            mock: MockConfig::from_env().map(MockProvider::new),
+            base_override: None,
+            endpoints: RwLock::new(ApiEndpoints::default()),
+            recorder: RwLock::new(RequestRecorder::from_env().map(Arc::new)),
         }
     }
 
-    /// Make an authenticated GET request
-    pub async fn get<T: serde::de::DeserializeOwned>(
+    /// Client sending every request to `origin` instead of Google
+    ///
+    /// API paths are kept, so `GMAIL_API_BASE` requests go to
+    /// `<origin>/gmail/v1/...`, Calendar to `<origin>/calendar/v3/...` and
+    /// Tasks to `<origin>/tasks/v1/...`.
+    #[cfg(test)]
+    pub fn with_base_override(origin: &str) -> Self {
+        Self {
+            mock: None,
+            base_override: Some(origin.trim_end_matches('/').to_string()),
+            ..Self::new()
+        }
+    }
+
+    /// Health monitor fed by every request made through this client
+    pub fn health(&self) -> Arc<HealthMonitor> {
+        self.health.clone()
+    }
+
+    /// Per-user request counter fed by every request made through this client
+    pub fn quota(&self) -> Arc<QuotaTracker> {
+        self.quota.clone()
+    }
+
+    /// Circuit breaker shared by every request made through this client
+    pub fn circuit(&self) -> Arc<CircuitBreaker> {
+        self.circuit.clone()
+    }
+
+    /// Base URL overrides currently applied
+    pub fn endpoints(&self) -> ApiEndpoints {
+        self.endpoints.read().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    pub fn set_endpoints(&self, endpoints: ApiEndpoints) {
+        if let Ok(mut current) = self.endpoints.write() {
+            *current = endpoints;
+        }
+    }
+
+    /// Connect and read timeouts currently applied
+    pub fn timeouts(&self) -> ApiTimeouts {
+        self.timeouts.read().map(|t| *t).unwrap_or_default()
+    }
+
+    /// Apply new timeouts to the requests sent from now on
+    pub fn set_timeouts(&self, timeouts: ApiTimeouts) -> Result<(), String> {
+        let http = timeouts::http_client(&timeouts)?;
+        if let Ok(mut current) = self.http.write() {
+            *current = http;
+        }
+        if let Ok(mut current) = self.timeouts.write() {
+            *current = timeouts;
+        }
+        Ok(())
+    }
+
+    fn http(&self) -> Client {
+        self.http.read().map(|h| h.clone()).unwrap_or_default()
+    }
+
+    /// Active request recorder, if record mode is on
+    pub fn recorder(&self) -> Option<Arc<RequestRecorder>> {
+        self.recorder.read().ok().and_then(|r| r.clone())
+    }
+
+    pub fn set_recorder(&self, recorder: Option<RequestRecorder>) {
+        if let Ok(mut current) = self.recorder.write() {
+            *current = recorder.map(Arc::new);
+        }
+    }
+
+    fn resolve(&self, url: &str) -> String {
+        let Some(origin) = &self.base_override else {
+            return self
+                .endpoints
+                .read()
+                .ok()
+                .and_then(|e| e.rewrite(url))
+                .unwrap_or_else(|| url.to_string());
+        };
+
+        for base in [GMAIL_API_BASE, CALENDAR_API_BASE, TASKS_API_BASE] {
+            if let Some(rest) = url.strip_prefix(base) {
+                let path = base.splitn(4, '/').nth(3).unwrap_or_default();
+                return format!("{}/{}{}", origin, path, rest);
+            }
+        }
+        url.to_string()
+    }
+
+    /// Send an authenticated request, check the status and record the outcome
+    ///
+    /// Fails right away while the host's circuit is open. Every attempt
+    /// first waits for a slot from the API's rate limiter. A 401 triggers one
+    /// token refresh and retry. A 429 is retried up to
+    /// `MAX_RATE_LIMIT_RETRIES` times, honoring Retry-After when present and
+    /// backing off exponentially otherwise. In record mode the final exchange
+    /// is written to disk.
+    ///
+    /// Under a deadline (see `deadline`) the whole exchange, waits included,
+    /// must finish in the time left, and each request is bounded by it.
+    async fn send(
         &self,
         url: &str,
-        token: &str,
-    ) -> Result<T, String> {
-        let response = self
-            .http
-            .get(url)
-            .bearer_auth(token)
-            .send()
+        token_store: &TokenStore,
+        build: impl Fn(&Client, &str, &str) -> RequestBuilder,
+    ) -> Result<Response, GoogleApiError> {
+        match deadline::remaining() {
+            Some(left) => tokio::time::timeout(left, self.send_attempts(url, token_store, build))
+                .await
+                .unwrap_or_else(|_| Err(deadline::exceeded())),
+            None => self.send_attempts(url, token_store, build).await,
+        }
+    }
+
+    async fn send_attempts(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+        build: impl Fn(&Client, &str, &str) -> RequestBuilder,
+    ) -> Result<Response, GoogleApiError> {
+        let http = self.http();
+        let target = self.resolve(url);
+        let host = circuit::host_of(&target);
+        self.circuit.check(&host)?;
+        let mut token = token_store
+            .get_access_token()
+            .await
+            .map_err(GoogleApiError::Auth)?;
+        let user = token_store
+            .account_context()
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .map(|a| a.email().to_string())
+            .unwrap_or_default();
+        let mut refreshed = false;
+        let mut rate_limit_retries = 0;
+
+        loop {
+            self.limiter.acquire(url).await;
+            self.quota.record(&user, url);
+            let mut builder = build(&http, &target, &token);
+            if let Some(left) = deadline::remaining() {
+                if left.is_zero() {
+                    return Err(deadline::exceeded());
+                }
+                builder = builder.timeout(left);
+            }
+            let request = builder
+                .build()
+                .map_err(|e| GoogleApiError::Other(format!("Failed to build request: {}", e)))?;
+            let recording = self
+                .recorder()
+                .map(|recorder| (recorder, RequestRecorder::capture(&request)));
+            let started = Instant::now();
+            let response = http.execute(request).await.map_err(|e| {
+                let message = format!("Request failed: {}", e);
+                self.health.record_failure(url, &message, false);
+                if !e.is_timeout() {
+                    self.circuit.record_failure(&host);
+                    return GoogleApiError::Network(message);
+                }
+                // Running out of the command's own deadline says nothing
+                // about the host
+                if deadline::remaining().is_some_and(|left| left.is_zero()) {
+                    return deadline::exceeded();
+                }
+                self.circuit.record_failure(&host);
+                GoogleApiError::Timeout(message)
+            })?;
 
-        if !response.status().is_success() {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("API error {}: {}", status, body));
+            if status == StatusCode::UNAUTHORIZED && !refreshed {
+                refreshed = true;
+                token = token_store
+                    .refresh_access_token()
+                    .await
+                    .map_err(GoogleApiError::Auth)?;
+                continue;
+            }
+            if status == StatusCode::TOO_MANY_REQUESTS
+                && rate_limit_retries < MAX_RATE_LIMIT_RETRIES
+            {
+                let delay = retry_after(&response)
+                    .unwrap_or(RATE_LIMIT_BACKOFF * 2u32.pow(rate_limit_retries));
+                rate_limit_retries += 1;
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                if let Some((recorder, pending)) = recording {
+                    recorder.record(pending, status, body.as_bytes(), started.elapsed());
+                }
+                let error = GoogleApiError::from_response(status, body);
+                self.health.record_failure(url, &error.to_string(), true);
+                if status.is_server_error() {
+                    self.circuit.record_failure(&host);
+                } else {
+                    self.circuit.record_success(&host);
+                }
+                return Err(error);
+            }
+
+            self.health.record_success(url);
+            self.circuit.record_success(&host);
+            return match recording {
+                Some((recorder, pending)) => {
+                    let headers = response.headers().clone();
+                    let body = read_body(response, MAX_RESPONSE_BYTES).await?;
+                    recorder.record(pending, status, &body, started.elapsed());
+                    Ok(rebuild_response(status, headers, body))
+                }
+                None => Ok(response),
+            };
         }
+    }
 
-        response
-            .json()
+    /// Serve a request from fixtures and record the outcome
+    async fn mock_json<T: serde::de::DeserializeOwned>(
+        &self,
+        mock: &MockProvider,
+        method: &str,
+        url: &str,
+        body: Option<Value>,
+    ) -> Result<T, GoogleApiError> {
+        match mock.respond(method, url, body).await {
+            Ok(value) => {
+                self.health.record_success(url);
+                serde_json::from_value(value).map_err(|e| {
+                    GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e))
+                })
+            }
+            Err(error) => {
+                self.health.record_failure(url, &error.to_string(), true);
+                Err(error)
+            }
+        }
+    }
+
+    /// GET a size-limited body, sharing it with identical GETs in flight
+    async fn get_body(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+        max_bytes: usize,
+    ) -> Result<Arc<Vec<u8>>, GoogleApiError> {
+        let user = token_store
+            .account_context()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+            .map(|a| a.email().to_string())
+            .unwrap_or_default();
+        let key = (user, url.to_string(), max_bytes, deadline::current());
+
+        self.in_flight
+            .run(key, || async {
+                let response = self
+                    .send(url, token_store, |http, url, token| {
+                        http.get(url).bearer_auth(token)
+                    })
+                    .await?;
+                read_body(response, max_bytes).await.map(Arc::new)
+            })
+            .await
+    }
+
+    /// Make an authenticated GET request
+    ///
+    /// Concurrent calls for the same URL (under the same deadline) share one
+    /// HTTP request.
+    pub async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+    ) -> Result<T, GoogleApiError> {
+        if let Some(mock) = &self.mock {
+            return self.mock_json(mock, "GET", url, None).await;
+        }
+
+        let body = self.get_body(url, token_store, MAX_RESPONSE_BYTES).await?;
+        serde_json::from_slice(&body).map_err(|e| {
+            GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })
+    }
+
+    /// Make an authenticated GET request for the parts of the response `fields` selects
+    pub async fn get_fields<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+        fields: Fields<T>,
+    ) -> Result<T, GoogleApiError> {
+        self.get(&fields.apply(url), token_store).await
+    }
+
+    /// Make a non-urgent GET request (background syncs, prefetching)
+    ///
+    /// Waits first if the user is close to the API's quota, leaving the
+    /// remaining budget to interactive requests.
+    pub async fn get_background<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+    ) -> Result<T, GoogleApiError> {
+        if let (Some(api), Ok(account)) =
+            (ApiKind::from_url(url), token_store.account_context().await)
+        {
+            if let Some(delay) = self.quota.background_delay(account.email(), api) {
+                println!(
+                    "Near {:?} quota, delaying background request by {:?}",
+                    api, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        self.get(url, token_store).await
+    }
+
+    /// Make an authenticated GET request and parse the raw body with `parse`
+    ///
+    /// The body is read chunk by chunk into a single buffer and rejected as
+    /// soon as it exceeds `max_bytes`. `parse` can deserialize into types
+    /// borrowing from that buffer (e.g. `GmailThreadsPage`), so large listings
+    /// don't allocate an owned copy of every field.
+    pub async fn get_with<R>(
+        &self,
+        url: &str,
+        token_store: &TokenStore,
+        max_bytes: usize,
+        parse: impl FnOnce(&[u8]) -> Result<R, serde_json::Error>,
+    ) -> Result<R, GoogleApiError> {
+        let body = if let Some(mock) = &self.mock {
+            let value: Value = self.mock_json(mock, "GET", url, None).await?;
+            Arc::new(serde_json::to_vec(&value).map_err(|e| {
+                GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e))
+            })?)
+        } else {
+            self.get_body(url, token_store, max_bytes).await?
+        };
+
+        parse(&body).map_err(|e| {
+            GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })
     }
 
     /// Make an authenticated POST request with JSON body
     pub async fn post<T: serde::de::DeserializeOwned, B: serde::Serialize>(
         &self,
         url: &str,
-        token: &str,
+        token_store: &TokenStore,
         body: &B,
-    ) -> Result<T, String> {
-        let response = self
-            .http
-            .post(url)
-            .bearer_auth(token)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("API error {}: {}", status, body));
+    ) -> Result<T, GoogleApiError> {
+        if let Some(mock) = &self.mock {
+            let body = serde_json::to_value(body).ok();
+            return self.mock_json(mock, "POST", url, body).await;
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+        let response = self
+            .send(url, token_store, |http, url, token| {
+                http.post(url).bearer_auth(token).json(body)
+            })
+            .await?;
+
+        parse_json(response).await
     }
 
     /// Make an authenticated PATCH request with JSON body
     pub async fn patch<T: serde::de::DeserializeOwned, B: serde::Serialize>(
         &self,
         url: &str,
-        token: &str,
+        token_store: &TokenStore,
         body: &B,
-    ) -> Result<T, String> {
+    ) -> Result<T, GoogleApiError> {
+        if let Some(mock) = &self.mock {
+            let body = serde_json::to_value(body).ok();
+            return self.mock_json(mock, "PATCH", url, body).await;
+        }
+
         let response = self
-            .http
-            .patch(url)
-            .bearer_auth(token)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .send(url, token_store, |http, url, token| {
+                http.patch(url).bearer_auth(token).json(body)
+            })
+            .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("API error {}: {}", status, body));
+        parse_json(response).await
+    }
+
+    /// Make an authenticated DELETE request
+    pub async fn delete(&self, url: &str, token_store: &TokenStore) -> Result<(), GoogleApiError> {
+        if let Some(mock) = &self.mock {
+            return self
+                .mock_json::<Value>(mock, "DELETE", url, None)
+                .await
+                .map(|_| ());
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+        self.send(url, token_store, |http, url, token| {
+            http.delete(url).bearer_auth(token)
+        })
+        .await?;
+
+        Ok(())
     }
 
-    /// Make an authenticated DELETE request
-    pub async fn delete(&self, url: &str, token: &str) -> Result<(), String> {
+    /// Send `calls` as Google batch requests, `MAX_BATCH_SIZE` calls per request
+    ///
+    /// Every call must go to the same API. Outcomes come back in call order; a
+    /// failed call doesn't fail the others, only a failed batch request does.
+    pub async fn batch(
+        &self,
+        calls: &[BatchCall],
+        token_store: &TokenStore,
+    ) -> Result<Vec<BatchOutcome>, GoogleApiError> {
+        let Some(first) = calls.first() else {
+            return Ok(Vec::new());
+        };
+        let api = ApiKind::from_url(&first.url)
+            .ok_or_else(|| GoogleApiError::Other(format!("Not a Google API URL: {}", first.url)))?;
+        if let Some(other) = calls
+            .iter()
+            .find(|call| ApiKind::from_url(&call.url) != Some(api))
+        {
+            return Err(GoogleApiError::Other(format!(
+                "Batched calls must all go to {:?}: {}",
+                api, other.url
+            )));
+        }
+
+        let mut outcomes = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(MAX_BATCH_SIZE) {
+            if let Some(mock) = &self.mock {
+                for call in chunk {
+                    let method = call.method.to_uppercase();
+                    let result = self
+                        .mock_json(mock, &method, &call.url, call.body.clone())
+                        .await;
+                    outcomes.push(BatchOutcome {
+                        id: call.id.clone(),
+                        result,
+                    });
+                }
+                continue;
+            }
+            outcomes.extend(self.send_batch(api, chunk, token_store).await?);
+        }
+        Ok(outcomes)
+    }
+
+    /// Send one batch request of at most `MAX_BATCH_SIZE` calls
+    async fn send_batch(
+        &self,
+        api: ApiKind,
+        calls: &[BatchCall],
+        token_store: &TokenStore,
+    ) -> Result<Vec<BatchOutcome>, GoogleApiError> {
+        // `send` takes one rate limiter slot, but Google counts every call
+        for call in calls.iter().skip(1) {
+            self.limiter.acquire(&call.url).await;
+        }
+        let resolved: Vec<BatchCall> = calls
+            .iter()
+            .map(|call| BatchCall {
+                url: self.resolve(&call.url),
+                ..call.clone()
+            })
+            .collect();
+        let boundary = batch::new_boundary();
+        let body = batch::encode(&boundary, &resolved)?;
+
         let response = self
-            .http
-            .delete(url)
-            .bearer_auth(token)
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .send(api.base_url(), token_store, |http, base, token| {
+                http.post(batch::batch_url(base))
+                    .bearer_auth(token)
+                    .header(
+                        reqwest::header::CONTENT_TYPE,
+                        format!("multipart/mixed; boundary={}", boundary),
+                    )
+                    .body(body.clone())
+            })
+            .await?;
+        let boundary = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(batch::response_boundary)
+            .ok_or_else(|| {
+                GoogleApiError::InvalidResponse("Batch response is not multipart".to_string())
+            })?;
+        let body = read_body(response, MAX_RESPONSE_BYTES).await?;
+        Ok(batch::decode(
+            &boundary,
+            &String::from_utf8_lossy(&body),
+            calls,
+        ))
+    }
+}
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("API error {}: {}", status, body));
+/// Outcome of `fan_out`: each item with its result, in input order
+#[derive(Debug)]
+pub struct FanOut<'a, I, T, E> {
+    pub succeeded: Vec<(&'a I, T)>,
+    pub failed: Vec<(&'a I, E)>,
+}
+
+impl<I, T, E> FanOut<'_, I, T, E> {
+    /// All results, or the first error
+    pub fn into_result(self) -> Result<Vec<T>, E> {
+        match self.failed.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(self.succeeded.into_iter().map(|(_, value)| value).collect()),
         }
+    }
+}
 
-        Ok(())
+/// Run `call` for every item with at most `limit` calls in flight
+///
+/// `limit` is clamped to 1..=`MAX_FAN_OUT_LIMIT`. Never fails as a whole:
+/// every item ends up in `succeeded` or `failed`.
+pub async fn fan_out<'a, I, T, E, F, Fut>(
+    items: &'a [I],
+    limit: usize,
+    call: F,
+) -> FanOut<'a, I, T, E>
+where
+    F: Fn(&'a I) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let semaphore = Semaphore::new(limit.clamp(1, MAX_FAN_OUT_LIMIT));
+    let results = join_all(items.iter().map(|item| async {
+        // The semaphore is never closed, so a permit always comes
+        let _permit = semaphore.acquire().await.ok();
+        call(item).await
+    }))
+    .await;
+
+    let mut outcome = FanOut {
+        succeeded: Vec::with_capacity(items.len()),
+        failed: Vec::new(),
+    };
+    for (item, result) in items.iter().zip(results) {
+        match result {
+            Ok(value) => outcome.succeeded.push((item, value)),
+            Err(error) => outcome.failed.push((item, error)),
+        }
     }
+    outcome
+}
+
+/// Read a response body, failing once it exceeds `max_bytes`
+async fn read_body(mut response: Response, max_bytes: usize) -> Result<Vec<u8>, GoogleApiError> {
+    let too_large = |size: usize| {
+        GoogleApiError::InvalidResponse(format!(
+            "Response too large: {} bytes exceeds the {} byte limit",
+            size, max_bytes
+        ))
+    };
+
+    let expected = response.content_length().unwrap_or(0) as usize;
+    if expected > max_bytes {
+        return Err(too_large(expected));
+    }
+
+    let mut body = Vec::with_capacity(expected);
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        let message = format!("Failed to read response: {}", e);
+        if e.is_timeout() {
+            GoogleApiError::Timeout(message)
+        } else {
+            GoogleApiError::Network(message)
+        }
+    })? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large(body.len() + chunk.len()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Response equivalent to one whose body was already read for recording
+fn rebuild_response(
+    status: StatusCode,
+    headers: reqwest::header::HeaderMap,
+    body: Vec<u8>,
+) -> Response {
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
+
+/// Read a size-limited response body and deserialize it
+async fn parse_json<T: serde::de::DeserializeOwned>(
+    response: Response,
+) -> Result<T, GoogleApiError> {
+    let body = read_body(response, MAX_RESPONSE_BYTES).await?;
+    serde_json::from_slice(&body)
+        .map_err(|e| GoogleApiError::InvalidResponse(format!("Failed to parse response: {}", e)))
+}
+
+/// Delay requested by a Retry-After header (in seconds), capped
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
 }
 
 impl Default for GoogleClient {
@@ -136,3 +742,38 @@ impl Default for GoogleClient {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_fan_out_bounds_concurrency() {
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<u32> = (0..12).collect();
+
+        let outcome = fan_out(&items, 3, |n| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if n % 5 == 4 {
+                    Err(format!("item {} failed", n))
+                } else {
+                    Ok(n * 10)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        let failed: Vec<u32> = outcome.failed.iter().map(|(n, _)| **n).collect();
+        assert_eq!(failed, [4, 9]);
+        assert_eq!(outcome.succeeded[..2], [(&0, 0), (&1, 10)]);
+        assert_eq!(outcome.into_result().unwrap_err(), "item 4 failed");
+    }
+}
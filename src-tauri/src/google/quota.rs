@@ -0,0 +1,217 @@
+//! Per-user API quota tracking
+//!
+//! Google enforces per-user rate limits over a rolling 100-second window.
+//! `QuotaTracker` counts every request `GoogleClient` sends in that window, per
+//! account and API, against conservative request budgets derived from the
+//! published limits:
+//! - Gmail: 250 quota units/user/second, ~10 units per threads call
+//! - Calendar: 500 requests/user/100 seconds
+//! - Tasks: 500 requests/user/100 seconds
+//!
+//! Interactive requests are never delayed. Background work goes through
+//! `GoogleClient::get_background`, which waits once usage passes
+//! `NEAR_LIMIT_RATIO` of the budget so it can't starve the UI of quota.
+
+use super::GoogleClient;
+use crate::auth::TokenStore;
+use crate::health::ApiKind;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+/// Rolling window Google's per-user limits apply to
+pub const QUOTA_WINDOW: Duration = Duration::from_secs(100);
+/// Share of the budget after which background requests are delayed
+pub const NEAR_LIMIT_RATIO: f64 = 0.8;
+
+/// Request budget per user per `QUOTA_WINDOW`
+pub fn request_limit(api: ApiKind) -> u32 {
+    match api {
+        ApiKind::Gmail => 2_500,
+        ApiKind::Calendar => 500,
+        ApiKind::Tasks => 500,
+    }
+}
+
+fn near_limit_threshold(api: ApiKind) -> usize {
+    (request_limit(api) as f64 * NEAR_LIMIT_RATIO) as usize
+}
+
+/// Quota usage of one API for the signed-in user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub api: ApiKind,
+    /// Requests sent in the current window
+    pub used: u32,
+    pub limit: u32,
+    pub window_secs: u64,
+    /// Whether background requests are currently being delayed
+    pub near_limit: bool,
+    /// Time until the oldest counted request leaves the window
+    pub resets_in_ms: Option<u64>,
+}
+
+/// Rolling request counter per (account, API)
+#[derive(Default)]
+pub struct QuotaTracker {
+    requests: Mutex<HashMap<(String, ApiKind), VecDeque<Instant>>>,
+}
+
+impl QuotaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request to `url` made for `user`
+    pub fn record(&self, user: &str, url: &str) {
+        if let Some(api) = ApiKind::from_url(url) {
+            self.record_at(user, api, Instant::now());
+        }
+    }
+
+    fn record_at(&self, user: &str, api: ApiKind, at: Instant) {
+        if let Ok(mut requests) = self.requests.lock() {
+            let window = requests.entry((user.to_string(), api)).or_default();
+            prune(window, at);
+            window.push_back(at);
+        }
+    }
+
+    /// Current usage of every API for `user`
+    pub fn usage(&self, user: &str) -> Vec<QuotaUsage> {
+        self.usage_at(user, Instant::now())
+    }
+
+    fn usage_at(&self, user: &str, now: Instant) -> Vec<QuotaUsage> {
+        let Ok(mut requests) = self.requests.lock() else {
+            return Vec::new();
+        };
+
+        ApiKind::ALL
+            .into_iter()
+            .map(|api| {
+                let window = requests.entry((user.to_string(), api)).or_default();
+                prune(window, now);
+                QuotaUsage {
+                    api,
+                    used: window.len() as u32,
+                    limit: request_limit(api),
+                    window_secs: QUOTA_WINDOW.as_secs(),
+                    near_limit: window.len() >= near_limit_threshold(api),
+                    resets_in_ms: window.front().map(|t| {
+                        (*t + QUOTA_WINDOW)
+                            .saturating_duration_since(now)
+                            .as_millis() as u64
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// How long a background request to `api` should wait to stay under the threshold
+    pub fn background_delay(&self, user: &str, api: ApiKind) -> Option<Duration> {
+        self.background_delay_at(user, api, Instant::now())
+    }
+
+    fn background_delay_at(&self, user: &str, api: ApiKind, now: Instant) -> Option<Duration> {
+        let mut requests = self.requests.lock().ok()?;
+        let window = requests.get_mut(&(user.to_string(), api))?;
+        prune(window, now);
+
+        // Wait until enough of the oldest requests expire to drop below the threshold
+        let threshold = near_limit_threshold(api);
+        if window.len() < threshold {
+            return None;
+        }
+        let expiring = window.get(window.len() - threshold)?;
+        Some((*expiring + QUOTA_WINDOW).saturating_duration_since(now))
+    }
+
+    /// Forget all usage of `user` (e.g. on account purge)
+    pub fn clear_user(&self, user: &str) {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.retain(|(u, _), _| u != user);
+        }
+    }
+}
+
+/// Get the signed-in user's request usage per API in the current window
+#[tauri::command]
+pub async fn get_quota_usage(
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+) -> Result<Vec<QuotaUsage>, String> {
+    crate::perf::trace_command!();
+    let account = token_store.account_context().await?;
+    Ok(client.quota().usage(account.email()))
+}
+
+fn prune(window: &mut VecDeque<Instant>, now: Instant) {
+    while window
+        .front()
+        .is_some_and(|t| now.saturating_duration_since(*t) >= QUOTA_WINDOW)
+    {
+        window.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_counts_rolling_window() {
+        let tracker = QuotaTracker::new();
+        let start = Instant::now();
+
+        tracker.record_at("a@example.com", ApiKind::Tasks, start);
+        tracker.record_at(
+            "a@example.com",
+            ApiKind::Tasks,
+            start + Duration::from_secs(50),
+        );
+        tracker.record_at("b@example.com", ApiKind::Tasks, start);
+
+        let usage = tracker.usage_at("a@example.com", start + Duration::from_secs(60));
+        let tasks = usage.iter().find(|u| u.api == ApiKind::Tasks).unwrap();
+        assert_eq!(tasks.used, 2);
+        assert_eq!(tasks.resets_in_ms, Some(40_000));
+
+        // The first request has left the window
+        let usage = tracker.usage_at("a@example.com", start + Duration::from_secs(101));
+        let tasks = usage.iter().find(|u| u.api == ApiKind::Tasks).unwrap();
+        assert_eq!(tasks.used, 1);
+        assert!(!tasks.near_limit);
+    }
+
+    #[test]
+    fn test_background_delay_near_limit() {
+        let tracker = QuotaTracker::new();
+        let start = Instant::now();
+        let threshold = near_limit_threshold(ApiKind::Calendar);
+
+        for i in 0..threshold {
+            tracker.record_at(
+                "a@example.com",
+                ApiKind::Calendar,
+                start + Duration::from_millis(i as u64),
+            );
+        }
+
+        let now = start + Duration::from_secs(10);
+        assert_eq!(
+            tracker.background_delay_at("a@example.com", ApiKind::Calendar, now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            tracker.background_delay_at("a@example.com", ApiKind::Tasks, now),
+            None
+        );
+        assert_eq!(
+            tracker.background_delay_at("b@example.com", ApiKind::Calendar, now),
+            None
+        );
+    }
+}
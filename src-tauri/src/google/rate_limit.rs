@@ -0,0 +1,108 @@
+//! Client-side request rate limiting
+//!
+//! A dashboard refresh fires dozens of requests at once, enough to trip
+//! Google's per-second limits. `RateLimiter` keeps a token bucket per API:
+//! requests within the burst go out immediately, the rest are spaced out at
+//! the bucket's refill rate instead of failing with a 429.
+//!
+//! Waiting requests reserve their slot (the bucket goes negative), so they
+//! leave in the order they arrived.
+
+use crate::health::ApiKind;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Requests sent without waiting, and the sustained rate, per API
+fn bucket_limits(api: ApiKind) -> (f64, f64) {
+    match api {
+        // 250 quota units/user/second at ~10 units per call
+        ApiKind::Gmail => (20.0, 20.0),
+        // 500 requests/user/100 seconds
+        ApiKind::Calendar => (10.0, 5.0),
+        ApiKind::Tasks => (10.0, 5.0),
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets per API family
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<ApiKind, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a token for `api`; returns how long to wait before sending
+    fn reserve_at(&self, api: ApiKind, now: Instant) -> Duration {
+        let (burst, per_sec) = bucket_limits(api);
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Duration::ZERO;
+        };
+        let bucket = buckets.entry(api).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(burst);
+        bucket.updated = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / per_sec)
+        }
+    }
+
+    /// Wait for a slot to send a request to `url`
+    pub async fn acquire(&self, url: &str) {
+        let Some(api) = ApiKind::from_url(url) else {
+            return;
+        };
+        let wait = self.reserve_at(api, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_spaces_out_bursts() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+
+        for _ in 0..10 {
+            assert_eq!(limiter.reserve_at(ApiKind::Tasks, start), Duration::ZERO);
+        }
+        // Past the burst, each request waits one more refill interval
+        assert_eq!(
+            limiter.reserve_at(ApiKind::Tasks, start),
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            limiter.reserve_at(ApiKind::Tasks, start),
+            Duration::from_millis(400)
+        );
+        // Other APIs have their own bucket
+        assert_eq!(limiter.reserve_at(ApiKind::Gmail, start), Duration::ZERO);
+
+        // After a quiet period the bucket is full again, but no fuller
+        let later = start + Duration::from_secs(60);
+        for _ in 0..10 {
+            assert_eq!(limiter.reserve_at(ApiKind::Tasks, later), Duration::ZERO);
+        }
+        assert!(limiter.reserve_at(ApiKind::Tasks, later) > Duration::ZERO);
+    }
+}
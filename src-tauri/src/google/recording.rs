@@ -0,0 +1,280 @@
+//! Request recording ("record mode")
+//!
+//! While enabled, every Google API exchange made through `GoogleClient` is
+//! written to `<dir>/<seq>-<method>.json` with the method, URL, request body,
+//! status, response body and duration. Recordings are sanitized before they
+//! touch disk: credentials in query strings and credential JSON fields
+//! (access/refresh/ID tokens, API keys, secrets, passwords) are replaced with
+//! `REDACTED`, and request headers
+//! (including `Authorization`) are never written. Message content is kept so
+//! bugs can be reproduced from the recording.
+//!
+//! Enable with `set_request_recording` (writes under the app data dir) or by
+//! setting `RAINY_DAY_RECORD_DIR`.
+
+use super::GoogleClient;
+use reqwest::{Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const RECORD_DIR_ENV: &str = "RAINY_DAY_RECORD_DIR";
+/// Subdirectory of the app data dir holding recording sessions
+const RECORDINGS_DIR: &str = "recordings";
+const REDACTED: &str = "REDACTED";
+
+/// Query parameters carrying credentials
+const SECRET_PARAMS: &[&str] = &["access_token", "key", "token", "client_secret"];
+/// JSON fields carrying credentials (compared lowercased, without underscores)
+const SECRET_FIELDS: &[&str] = &[
+    "accesstoken",
+    "refreshtoken",
+    "idtoken",
+    "authorization",
+    "apikey",
+];
+/// JSON field name fragments carrying credentials
+const SECRET_FRAGMENTS: &[&str] = &["secret", "password"];
+
+/// Writes sanitized request/response pairs to a directory
+pub struct RequestRecorder {
+    dir: PathBuf,
+    seq: AtomicU64,
+}
+
+/// Request captured before sending
+pub struct PendingRecord {
+    method: String,
+    url: String,
+    body: Option<Value>,
+}
+
+/// One recorded exchange as written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: String,
+    pub url: String,
+    pub request_body: Option<Value>,
+    pub status: u16,
+    pub response_body: Value,
+    pub duration_ms: u64,
+    pub recorded_at: String,
+}
+
+/// Whether recording is on, and where to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub enabled: bool,
+    pub dir: Option<String>,
+}
+
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_lowercase().replace('_', "");
+    SECRET_FIELDS.contains(&name.as_str()) || SECRET_FRAGMENTS.iter().any(|f| name.contains(f))
+}
+
+/// Redact credential query parameters
+pub fn sanitize_url(url: &str) -> String {
+    let Some((path, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let params: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if SECRET_PARAMS.contains(&name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", path, params.join("&"))
+}
+
+/// Redact string values of credential-like fields, recursively
+pub fn sanitize_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                if is_secret_field(name) && field.is_string() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    sanitize_json(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize_json),
+        _ => {}
+    }
+}
+
+/// Parse a body as JSON, falling back to a string
+fn body_value(body: &[u8]) -> Value {
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+impl RequestRecorder {
+    pub fn new(dir: PathBuf) -> Result<Self, String> {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create recording directory: {}", e))?;
+        Ok(Self {
+            dir,
+            seq: AtomicU64::new(0),
+        })
+    }
+
+    /// Recorder from `RAINY_DAY_RECORD_DIR`, if set
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var(RECORD_DIR_ENV).ok()?;
+        Self::new(PathBuf::from(dir))
+            .map_err(|e| eprintln!("Request recording disabled: {}", e))
+            .ok()
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Capture a request before it is sent
+    pub fn capture(request: &Request) -> PendingRecord {
+        PendingRecord {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            body: request.body().and_then(|b| b.as_bytes()).map(body_value),
+        }
+    }
+
+    /// Sanitize and write one exchange (failures are only logged)
+    pub fn record(
+        &self,
+        pending: PendingRecord,
+        status: StatusCode,
+        response_body: &[u8],
+        duration: Duration,
+    ) {
+        let mut exchange = RecordedExchange {
+            url: sanitize_url(&pending.url),
+            request_body: pending.body,
+            status: status.as_u16(),
+            response_body: body_value(response_body),
+            duration_ms: duration.as_millis() as u64,
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            method: pending.method,
+        };
+        if let Some(body) = exchange.request_body.as_mut() {
+            sanitize_json(body);
+        }
+        sanitize_json(&mut exchange.response_body);
+
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!(
+            "{:06}-{}.json",
+            seq,
+            exchange.method.to_lowercase()
+        ));
+        let result = serde_json::to_vec_pretty(&exchange)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to record request to {}: {}", path.display(), e);
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+fn status(client: &GoogleClient) -> RecordingStatus {
+    let dir = client
+        .recorder()
+        .map(|r| r.dir().to_string_lossy().into_owned());
+    RecordingStatus {
+        enabled: dir.is_some(),
+        dir,
+    }
+}
+
+/// Get whether API requests are being recorded
+#[tauri::command]
+pub fn get_request_recording(client: State<'_, GoogleClient>) -> RecordingStatus {
+    crate::perf::trace_command!();
+    status(&client)
+}
+
+/// Start recording API requests to a new session directory, or stop
+#[tauri::command]
+pub fn set_request_recording(
+    app: AppHandle,
+    client: State<'_, GoogleClient>,
+    enabled: bool,
+) -> Result<RecordingStatus, String> {
+    crate::perf::trace_command!();
+    if !enabled {
+        client.set_recorder(None);
+        return Ok(status(&client));
+    }
+
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?
+        .join(RECORDINGS_DIR)
+        .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+    client.set_recorder(Some(RequestRecorder::new(dir)?));
+    Ok(status(&client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(
+            sanitize_url("https://example.com/v1/x?key=abc&q=is%3Aunread&access_token=t"),
+            "https://example.com/v1/x?key=REDACTED&q=is%3Aunread&access_token=REDACTED"
+        );
+
+        let mut body = serde_json::json!({
+            "access_token": "ya29.secret",
+            "items": [{ "clientSecret": "s", "summary": "Standup" }],
+            "nextPageToken": "page-2",
+            "error": { "code": 401 },
+        });
+        sanitize_json(&mut body);
+        assert_eq!(body["access_token"], REDACTED);
+        assert_eq!(body["items"][0]["clientSecret"], REDACTED);
+        assert_eq!(body["nextPageToken"], "page-2");
+        assert_eq!(body["items"][0]["summary"], "Standup");
+        assert_eq!(body["error"]["code"], 401);
+    }
+
+    #[test]
+    fn test_record_writes_sanitized_file() {
+        let dir = std::env::temp_dir().join(format!("rainyday-recording-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let recorder = RequestRecorder::new(dir.clone()).unwrap();
+
+        recorder.record(
+            PendingRecord {
+                method: "GET".to_string(),
+                url: "https://example.com/x?key=abc".to_string(),
+                body: None,
+            },
+            StatusCode::OK,
+            br#"{"refresh_token":"r","id":"1"}"#,
+            Duration::from_millis(12),
+        );
+
+        let written = std::fs::read_to_string(dir.join("000000-get.json")).unwrap();
+        let exchange: RecordedExchange = serde_json::from_str(&written).unwrap();
+        assert_eq!(exchange.url, "https://example.com/x?key=REDACTED");
+        assert_eq!(exchange.response_body["refresh_token"], REDACTED);
+        assert_eq!(exchange.response_body["id"], "1");
+        assert_eq!(exchange.status, 200);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -6,130 +6,807 @@
 //! - tasks.insert: Create a new task
 //! - tasks.patch: Update a task
 //! - tasks.delete: Delete a task
+//!
+//! Smart lists ("Today", "Overdue", "Waiting on others", "High priority") are
+//! computed here from the synced tasks plus local sidecar metadata (priority,
+//! who a task waits on) and per-list defaults, so the sidebar gets counts and
+//! contents from a single `get_smart_list` call.
+//!
+//! Tasks created from an email keep a `TaskRef` to their source thread.
+//! Completing such a task archives the thread, marks it read, or offers to
+//! archive it, following the link's policy or else its list's default.
 
-use super::types::{NewTask, Task, TaskList, TaskListsResponse, TaskUpdate, TasksResponse};
-use super::{GoogleClient, TASKS_API_BASE};
+use super::fields::Fields;
+use super::types::{
+    NewTask, Task, TaskList, TaskListsResponse, TaskRef, TaskUpdate, TasksResponse, ThreadFollowUp,
+};
+use super::{deadline, invalidation, GoogleApiError, GoogleClient, GMAIL_API_BASE, TASKS_API_BASE};
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::capabilities::{self, Feature};
 use crate::auth::TokenStore;
-use tauri::State;
+use crate::cache::{CacheState, API_RESPONSE_TTL_SECS, TASKS_KEY_PREFIX};
+use crate::events::{self, DataEvent};
+use crate::policy;
+use crate::settings::{self, SettingsSection};
+use crate::storage::{LocalStorage, TASK_LIST_DEFAULTS, TASK_METADATA, TASK_REFS};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
 
 /// Get all task lists for the user
 #[tauri::command]
 pub async fn get_task_lists(
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
-) -> Result<Vec<TaskList>, String> {
-    let token = token_store.get_access_token().await?;
-
+    deadline_ms: Option<u64>,
+) -> Result<Vec<TaskList>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     let url = format!("{}/users/@me/lists", TASKS_API_BASE);
 
-    let response: TaskListsResponse = client.get(&url, &token).await?;
+    let response: TaskListsResponse =
+        deadline::within(deadline_ms, client.get(&url, &token_store)).await?;
 
     Ok(response.items.unwrap_or_default())
 }
 
+/// Upper bound on pages fetched for one list
+const MAX_TASK_PAGES: usize = 20;
+/// Parts of tasks.list responses `TasksResponse` reads
+pub const TASK_LIST_FIELDS: Fields<TasksResponse> =
+    Fields::new("items(id,title,notes,status,due,completed,updated,parent,position),nextPageToken");
+
+/// Fetch all tasks of a list, following `nextPageToken`
+pub async fn fetch_tasks(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    list_id: &str,
+    show_completed: bool,
+) -> Result<Vec<Task>, GoogleApiError> {
+    let base_url = format!(
+        "{}/lists/{}/tasks?showCompleted={}&showHidden=false&maxResults=100",
+        TASKS_API_BASE, list_id, show_completed
+    );
+
+    let mut tasks = Vec::new();
+    let mut page_token: Option<String> = None;
+    for _ in 0..MAX_TASK_PAGES {
+        let url = match &page_token {
+            Some(page) => format!("{}&pageToken={}", base_url, urlencoding::encode(page)),
+            None => base_url.clone(),
+        };
+
+        let response = client
+            .get_fields(&url, token_store, TASK_LIST_FIELDS)
+            .await?;
+        tasks.extend(response.items.unwrap_or_default());
+
+        page_token = response.next_page_token;
+        if page_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(tasks)
+}
+
 /// Get all tasks from a specific list
 #[tauri::command]
 pub async fn get_tasks(
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
     list_id: String,
     show_completed: Option<bool>,
-) -> Result<Vec<Task>, String> {
-    let token = token_store.get_access_token().await?;
+    deadline_ms: Option<u64>,
+) -> Result<Vec<Task>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let tasks = deadline::within(
+        deadline_ms,
+        fetch_tasks(
+            &token_store,
+            &client,
+            &list_id,
+            show_completed.unwrap_or(false),
+        ),
+    )
+    .await?;
 
-    let show = if show_completed.unwrap_or(false) {
-        "true"
-    } else {
-        "false"
-    };
-    let url = format!(
-        "{}/lists/{}/tasks?showCompleted={}&showHidden=false",
-        TASKS_API_BASE, list_id, show
+    let account = token_store.account_context().await?;
+    cache.0.set_json(
+        &account.cache_key(&format!("{}{}", TASKS_KEY_PREFIX, list_id)),
+        &tasks,
+        API_RESPONSE_TTL_SECS,
     );
+    crate::perf::mark_plan_part(crate::perf::PlanPart::Tasks);
 
-    let response: TasksResponse = client.get(&url, &token).await?;
-
-    Ok(response.items.unwrap_or_default())
+    Ok(tasks)
 }
 
-/// Create a new task in a list
+/// Create a new task in a list, optionally linked to the email thread it came from
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_task(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
     list_id: String,
     task: NewTask,
-) -> Result<Task, String> {
-    let token = token_store.get_access_token().await?;
-
+    source_thread_id: Option<String>,
+) -> Result<Task, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     let url = format!("{}/lists/{}/tasks", TASKS_API_BASE, list_id);
 
-    client.post(&url, &token, &task).await
+    let created: Task = client.post(&url, &token_store, &task).await?;
+    if let Some(task_id) = created.id.clone() {
+        if let Some(thread_id) = source_thread_id {
+            let account = token_store.account_context().await?;
+            let link = task_ref(&list_id, &created, thread_id, None);
+            put_task_ref(&storage, &account, &link)?;
+        }
+        invalidation::mutated(&app, DataEvent::TaskCreated { list_id, task_id }).await;
+    }
+    Ok(created)
+}
+
+pub(crate) async fn patch_task(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    list_id: &str,
+    task_id: &str,
+    update: &TaskUpdate,
+) -> Result<Task, GoogleApiError> {
+    let url = format!("{}/lists/{}/tasks/{}", TASKS_API_BASE, list_id, task_id);
+
+    client.patch(&url, token_store, update).await
+}
+
+fn status_update(status: &str) -> TaskUpdate {
+    TaskUpdate {
+        title: None,
+        notes: None,
+        status: Some(status.to_string()),
+        due: None,
+    }
 }
 
 /// Update an existing task
 #[tauri::command]
 pub async fn update_task(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
     list_id: String,
     task_id: String,
     update: TaskUpdate,
-) -> Result<Task, String> {
-    let token = token_store.get_access_token().await?;
-
-    let url = format!("{}/lists/{}/tasks/{}", TASKS_API_BASE, list_id, task_id);
-
-    client.patch(&url, &token, &update).await
+) -> Result<Task, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let task = patch_task(&token_store, &client, &list_id, &task_id, &update).await?;
+    invalidation::mutated(&app, DataEvent::TaskUpdated { list_id, task_id }).await;
+    Ok(task)
 }
 
 /// Complete a task
+///
+/// A linked source thread then gets its follow-up; failing that is logged and
+/// never fails the completion.
 #[tauri::command]
 pub async fn complete_task(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
     list_id: String,
     task_id: String,
-) -> Result<Task, String> {
-    let update = TaskUpdate {
-        title: None,
-        notes: None,
-        status: Some("completed".to_string()),
-        due: None,
-    };
-
-    update_task(token_store, client, list_id, task_id, update).await
+) -> Result<Task, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let update = status_update("completed");
+    let task = patch_task(&token_store, &client, &list_id, &task_id, &update).await?;
+    if let Err(e) =
+        follow_up_thread(&app, &token_store, &client, &storage, &list_id, &task_id).await
+    {
+        eprintln!("Failed to follow up the thread of task {}: {}", task_id, e);
+    }
+    invalidation::mutated(&app, DataEvent::TaskCompleted { list_id, task_id }).await;
+    Ok(task)
 }
 
 /// Reopen a completed task
 #[tauri::command]
 pub async fn reopen_task(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
     list_id: String,
     task_id: String,
-) -> Result<Task, String> {
-    let update = TaskUpdate {
-        title: None,
-        notes: None,
-        status: Some("needsAction".to_string()),
-        due: None,
-    };
-
-    update_task(token_store, client, list_id, task_id, update).await
+) -> Result<Task, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let update = status_update("needsAction");
+    let task = patch_task(&token_store, &client, &list_id, &task_id, &update).await?;
+    invalidation::mutated(&app, DataEvent::TaskReopened { list_id, task_id }).await;
+    Ok(task)
 }
 
 /// Delete a task
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn delete_task(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
     token_store: State<'_, TokenStore>,
     client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
     list_id: String,
     task_id: String,
+    confirmation: Option<String>,
+) -> Result<(), GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let target = metadata_id(&list_id, &task_id);
+    policy::authorize(&app, "delete_task", &target, confirmation.as_deref()).await?;
+    let url = format!("{}/lists/{}/tasks/{}", TASKS_API_BASE, list_id, task_id);
+
+    client.delete(&url, &token_store).await?;
+    let account = token_store.account_context().await?;
+    storage.remove(&account, TASK_REFS, &metadata_id(&list_id, &task_id))?;
+    invalidation::mutated(&app, DataEvent::TaskDeleted { list_id, task_id }).await;
+    Ok(())
+}
+
+// ============================================================================
+// Source Threads
+// ============================================================================
+
+fn task_ref(
+    list_id: &str,
+    task: &Task,
+    thread_id: String,
+    on_complete: Option<ThreadFollowUp>,
+) -> TaskRef {
+    TaskRef {
+        provider: "google".to_string(),
+        external_id: task.id.clone().unwrap_or_default(),
+        list_id: list_id.to_string(),
+        source_thread_id: Some(thread_id),
+        last_sync_at: chrono::Utc::now().timestamp_millis(),
+        title: task.title.clone(),
+        status: task.status.clone().unwrap_or_default(),
+        due: task.due.clone(),
+        on_complete,
+    }
+}
+
+fn put_task_ref(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    link: &TaskRef,
 ) -> Result<(), String> {
-    let token = token_store.get_access_token().await?;
+    let value =
+        serde_json::to_value(link).map_err(|e| format!("Failed to serialize task link: {}", e))?;
+    storage.put(
+        account,
+        TASK_REFS,
+        &metadata_id(&link.list_id, &link.external_id),
+        value,
+    )
+}
+
+fn get_task_ref(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    list_id: &str,
+    task_id: &str,
+) -> Result<Option<TaskRef>, String> {
+    Ok(storage
+        .get(account, TASK_REFS, &metadata_id(list_id, task_id))?
+        .and_then(|record| serde_json::from_value(record.value).ok()))
+}
+
+/// The follow-up for a completed task's thread: the link's own, else the list's
+fn resolve_follow_up(link: &TaskRef, defaults: &ListDefaults) -> ThreadFollowUp {
+    link.on_complete.unwrap_or(defaults.on_complete_thread)
+}
+
+/// Remove labels from a thread of the signed-in mailbox
+async fn remove_thread_labels(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    thread_id: &str,
+    labels: &[&str],
+) -> Result<(), GoogleApiError> {
+    let url = format!("{}/users/me/threads/{}/modify", GMAIL_API_BASE, thread_id);
+    let body = serde_json::json!({ "removeLabelIds": labels });
+    let _: serde::de::IgnoredAny = client.post(&url, token_store, &body).await?;
+    Ok(())
+}
+
+/// Apply the follow-up of a just-completed task's source thread, if linked
+async fn follow_up_thread(
+    app: &AppHandle,
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    storage: &LocalStorage,
+    list_id: &str,
+    task_id: &str,
+) -> Result<(), GoogleApiError> {
+    let account = token_store.account_context().await?;
+    let Some(mut link) = get_task_ref(storage, &account, list_id, task_id)? else {
+        return Ok(());
+    };
+    link.status = "completed".to_string();
+    link.last_sync_at = chrono::Utc::now().timestamp_millis();
+    put_task_ref(storage, &account, &link)?;
+    let Some(thread_id) = link.source_thread_id.clone() else {
+        return Ok(());
+    };
 
+    let defaults: ListDefaults = storage
+        .get(&account, TASK_LIST_DEFAULTS, list_id)?
+        .and_then(|record| serde_json::from_value(record.value).ok())
+        .unwrap_or_default();
+    let labels: &[&str] = match resolve_follow_up(&link, &defaults) {
+        ThreadFollowUp::Keep => return Ok(()),
+        ThreadFollowUp::Offer => {
+            events::emit(
+                app,
+                DataEvent::ThreadFollowUpOffered {
+                    list_id: list_id.to_string(),
+                    task_id: task_id.to_string(),
+                    thread_id,
+                },
+            );
+            return Ok(());
+        }
+        ThreadFollowUp::Archive => &["INBOX"],
+        ThreadFollowUp::MarkRead => &["UNREAD"],
+    };
+    remove_thread_labels(token_store, client, &thread_id, labels).await?;
+    invalidation::mutated(
+        app,
+        DataEvent::ThreadsModified {
+            thread_ids: vec![thread_id],
+        },
+    )
+    .await;
+    Ok(())
+}
+
+/// Link a task to the email thread it came from
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn link_task_thread(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    list_id: String,
+    task_id: String,
+    thread_id: String,
+    on_complete: Option<ThreadFollowUp>,
+) -> Result<TaskRef, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
     let url = format!("{}/lists/{}/tasks/{}", TASKS_API_BASE, list_id, task_id);
+    let task: Task = client.get(&url, &token_store).await?;
+
+    let account = token_store.account_context().await?;
+    let link = task_ref(&list_id, &task, thread_id, on_complete);
+    put_task_ref(&storage, &account, &link)?;
+    Ok(link)
+}
+
+/// Get a task's link to its source thread
+#[tauri::command]
+pub async fn get_task_thread(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    list_id: String,
+    task_id: String,
+) -> Result<Option<TaskRef>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    Ok(get_task_ref(&storage, &account, &list_id, &task_id)?)
+}
+
+/// Archive a task's source thread (accepting a `task:thread_follow_up` offer)
+#[tauri::command]
+pub async fn archive_linked_thread(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    list_id: String,
+    task_id: String,
+) -> Result<(), GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    capabilities::require(&token_store, Feature::Modify).await?;
+    let account = token_store.account_context().await?;
+    let thread_id = get_task_ref(&storage, &account, &list_id, &task_id)?
+        .and_then(|link| link.source_thread_id)
+        .ok_or_else(|| "Task has no source thread".to_string())?;
+
+    remove_thread_labels(&token_store, &client, &thread_id, &["INBOX"]).await?;
+    invalidation::mutated(
+        &app,
+        DataEvent::ThreadsModified {
+            thread_ids: vec![thread_id],
+        },
+    )
+    .await;
+    Ok(())
+}
+
+// ============================================================================
+// Smart Lists
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Local data kept next to a Google task (Google Tasks has no such fields)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskMetadata {
+    /// None uses the list's default priority
+    #[serde(default)]
+    pub priority: Option<TaskPriority>,
+    /// Who the task is blocked on (a name or email)
+    #[serde(default)]
+    pub waiting_on: Option<String>,
+}
+
+/// Behaviour applied to every task of a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListDefaults {
+    /// Priority of tasks that don't set their own
+    #[serde(default)]
+    pub priority: TaskPriority,
+    /// Lists like "Someday" can stay out of smart lists
+    #[serde(default = "default_true")]
+    pub include_in_smart_lists: bool,
+    /// Follow-up of a completed task's source thread
+    #[serde(default)]
+    pub on_complete_thread: ThreadFollowUp,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ListDefaults {
+    fn default() -> Self {
+        Self {
+            priority: TaskPriority::Normal,
+            include_in_smart_lists: true,
+            on_complete_thread: ThreadFollowUp::Offer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartListName {
+    Today,
+    Overdue,
+    WaitingOnOthers,
+    HighPriority,
+}
+
+const SMART_LISTS: [SmartListName; 4] = [
+    SmartListName::Today,
+    SmartListName::Overdue,
+    SmartListName::WaitingOnOthers,
+    SmartListName::HighPriority,
+];
+
+/// An open task with its effective local metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartTask {
+    pub list_id: String,
+    pub task: Task,
+    pub priority: TaskPriority,
+    pub waiting_on: Option<String>,
+}
+
+/// Contents of one smart list plus the counts of all of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartListView {
+    pub name: SmartListName,
+    pub tasks: Vec<SmartTask>,
+    pub counts: HashMap<SmartListName, usize>,
+}
+
+fn metadata_id(list_id: &str, task_id: &str) -> String {
+    format!("{}:{}", list_id, task_id)
+}
+
+fn due_date(task: &Task) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(task.due.as_deref()?.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn in_smart_list(name: SmartListName, task: &SmartTask, today: NaiveDate) -> bool {
+    match name {
+        SmartListName::Today => due_date(&task.task) == Some(today),
+        SmartListName::Overdue => due_date(&task.task).is_some_and(|due| due < today),
+        SmartListName::WaitingOnOthers => task.waiting_on.is_some(),
+        SmartListName::HighPriority => task.priority == TaskPriority::High,
+    }
+}
+
+/// Members of `name`, highest priority then earliest due first, and all counts
+fn build_smart_list(name: SmartListName, tasks: Vec<SmartTask>, today: NaiveDate) -> SmartListView {
+    let counts = SMART_LISTS
+        .iter()
+        .map(|list| {
+            let count = tasks
+                .iter()
+                .filter(|t| in_smart_list(*list, t, today))
+                .count();
+            (*list, count)
+        })
+        .collect();
+
+    let mut members: Vec<SmartTask> = tasks
+        .into_iter()
+        .filter(|t| in_smart_list(name, t, today))
+        .collect();
+    members.sort_by_key(|t| {
+        (
+            std::cmp::Reverse(t.priority),
+            due_date(&t.task).unwrap_or(NaiveDate::MAX),
+        )
+    });
+
+    SmartListView {
+        name,
+        tasks: members,
+        counts,
+    }
+}
+
+fn stored<T: serde::de::DeserializeOwned>(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    collection: &str,
+) -> Result<HashMap<String, T>, String> {
+    Ok(storage
+        .list(account, collection)?
+        .into_iter()
+        .filter_map(|(id, record)| Some((id, serde_json::from_value(record.value).ok()?)))
+        .collect())
+}
+
+/// Open tasks of a list, from the cache `get_tasks` fills when warm
+async fn synced_tasks(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    cache: &CacheState,
+    account: &AccountContext,
+    list_id: &str,
+) -> Result<Vec<Task>, GoogleApiError> {
+    let key = account.cache_key(&format!("{}{}", TASKS_KEY_PREFIX, list_id));
+    let tasks = match cache.0.get_json::<Vec<Task>>(&key) {
+        Some(tasks) => tasks,
+        None => {
+            let tasks = fetch_tasks(token_store, client, list_id, false).await?;
+            cache.0.set_json(&key, &tasks, API_RESPONSE_TTL_SECS);
+            tasks
+        }
+    };
+    Ok(tasks
+        .into_iter()
+        .filter(|t| t.status.as_deref() != Some("completed"))
+        .collect())
+}
+
+/// Get a smart list's tasks along with the counts of every smart list
+#[tauri::command]
+pub async fn get_smart_list(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
+    storage: State<'_, LocalStorage>,
+    name: SmartListName,
+    deadline_ms: Option<u64>,
+) -> Result<SmartListView, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let metadata: HashMap<String, TaskMetadata> = stored(&storage, &account, TASK_METADATA)?;
+    let defaults: HashMap<String, ListDefaults> = stored(&storage, &account, TASK_LIST_DEFAULTS)?;
+
+    let tasks = deadline::within(deadline_ms, async {
+        let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+        let lists: Vec<TaskList> = client
+            .get::<TaskListsResponse>(&url, &token_store)
+            .await?
+            .items
+            .unwrap_or_default();
+
+        let mut tasks = Vec::new();
+        for list in lists {
+            let list_defaults = defaults.get(&list.id).cloned().unwrap_or_default();
+            if !list_defaults.include_in_smart_lists {
+                continue;
+            }
+            for task in synced_tasks(&token_store, &client, &cache, &account, &list.id).await? {
+                let meta = task
+                    .id
+                    .as_deref()
+                    .and_then(|id| metadata.get(&metadata_id(&list.id, id)))
+                    .cloned()
+                    .unwrap_or_default();
+                tasks.push(SmartTask {
+                    list_id: list.id.clone(),
+                    priority: meta.priority.unwrap_or(list_defaults.priority),
+                    waiting_on: meta.waiting_on.filter(|w| !w.trim().is_empty()),
+                    task,
+                });
+            }
+        }
+        Ok::<_, GoogleApiError>(tasks)
+    })
+    .await?;
+
+    Ok(build_smart_list(name, tasks, Local::now().date_naive()))
+}
+
+/// Set a task's local priority and who it waits on
+#[tauri::command]
+pub async fn set_task_metadata(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    list_id: String,
+    task_id: String,
+    metadata: TaskMetadata,
+) -> Result<(), GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let id = metadata_id(&list_id, &task_id);
+    if metadata == TaskMetadata::default() {
+        storage.remove(&account, TASK_METADATA, &id)?;
+        return Ok(());
+    }
+    let value = serde_json::to_value(&metadata)
+        .map_err(|e| format!("Failed to serialize task metadata: {}", e))?;
+    Ok(storage.put(&account, TASK_METADATA, &id, value)?)
+}
+
+/// Get the default behaviour of every configured list (others use the defaults)
+#[tauri::command]
+pub async fn get_list_defaults(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<HashMap<String, ListDefaults>, GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    Ok(stored(&storage, &account, TASK_LIST_DEFAULTS)?)
+}
+
+/// Set the default behaviour of a list
+#[tauri::command]
+pub async fn set_list_defaults(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    list_id: String,
+    defaults: ListDefaults,
+) -> Result<(), GoogleApiError> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let value = serde_json::to_value(&defaults)
+        .map_err(|e| format!("Failed to serialize list defaults: {}", e))?;
+    storage.put(&account, TASK_LIST_DEFAULTS, &list_id, value)?;
+    settings::touch(&app, SettingsSection::SmartLists);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smart_task(id: &str, due: Option<&str>, priority: TaskPriority) -> SmartTask {
+        SmartTask {
+            list_id: "l1".to_string(),
+            task: Task {
+                id: Some(id.to_string()),
+                title: id.to_string(),
+                notes: None,
+                status: Some("needsAction".to_string()),
+                due: due.map(|d| format!("{}T00:00:00.000Z", d)),
+                completed: None,
+                updated: None,
+                parent: None,
+                position: None,
+            },
+            priority,
+            waiting_on: None,
+        }
+    }
+
+    #[test]
+    fn test_build_smart_list() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let mut waiting = smart_task("waiting", None, TaskPriority::Normal);
+        waiting.waiting_on = Some("jane@example.com".to_string());
+        let tasks = vec![
+            smart_task("today-low", Some("2026-03-10"), TaskPriority::Low),
+            smart_task("today-high", Some("2026-03-10"), TaskPriority::High),
+            smart_task("late", Some("2026-03-01"), TaskPriority::Normal),
+            smart_task("later", Some("2026-04-01"), TaskPriority::High),
+            waiting,
+        ];
+
+        let view = build_smart_list(SmartListName::Today, tasks.clone(), today);
+        let ids: Vec<_> = view.tasks.iter().map(|t| t.task.title.as_str()).collect();
+        assert_eq!(ids, ["today-high", "today-low"]);
+        assert_eq!(view.counts[&SmartListName::Overdue], 1);
+        assert_eq!(view.counts[&SmartListName::WaitingOnOthers], 1);
+        assert_eq!(view.counts[&SmartListName::HighPriority], 2);
+
+        let view = build_smart_list(SmartListName::HighPriority, tasks, today);
+        let ids: Vec<_> = view.tasks.iter().map(|t| t.task.title.as_str()).collect();
+        assert_eq!(ids, ["today-high", "later"]);
+    }
+
+    #[test]
+    fn test_resolve_follow_up() {
+        // Links stored before the policy existed follow the list default
+        let link: TaskRef = serde_json::from_value(serde_json::json!({
+            "provider": "google",
+            "external_id": "t1",
+            "source_thread_id": "thread-1",
+            "last_sync_at": 0,
+            "title": "Reply to Jane",
+            "status": "needsAction",
+            "due": null,
+        }))
+        .unwrap();
+        let defaults = ListDefaults::default();
+        assert_eq!(resolve_follow_up(&link, &defaults), ThreadFollowUp::Offer);
+
+        let archive = ListDefaults {
+            on_complete_thread: ThreadFollowUp::Archive,
+            ..ListDefaults::default()
+        };
+        assert_eq!(resolve_follow_up(&link, &archive), ThreadFollowUp::Archive);
 
-    client.delete(&url, &token).await
+        let keep = TaskRef {
+            on_complete: Some(ThreadFollowUp::Keep),
+            ..link
+        };
+        assert_eq!(resolve_follow_up(&keep, &archive), ThreadFollowUp::Keep);
+    }
 }
@@ -0,0 +1,106 @@
+//! Connect and read timeouts of Google requests
+//!
+//! Without them a hung connection stalls a command indefinitely. The values
+//! come from the `api.json` settings store and apply to every request made
+//! through `GoogleClient`; a command can set a tighter overall limit with a
+//! deadline (see `deadline`).
+
+use super::GoogleClient;
+use crate::storage;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const API_STORE_FILE: &str = "api.json";
+const TIMEOUTS_KEY: &str = "timeouts";
+
+/// Longest timeout accepted, in seconds
+const MAX_TIMEOUT_SECS: u64 = 300;
+
+/// Timeouts of every Google request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiTimeouts {
+    /// Time to establish the connection
+    pub connect_secs: u64,
+    /// Longest wait for the next bytes of a response
+    pub read_secs: u64,
+}
+
+impl Default for ApiTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_secs: 10,
+            read_secs: 30,
+        }
+    }
+}
+
+impl ApiTimeouts {
+    fn validate(self) -> Result<Self, String> {
+        for (name, secs) in [("Connect", self.connect_secs), ("Read", self.read_secs)] {
+            if !(1..=MAX_TIMEOUT_SECS).contains(&secs) {
+                return Err(format!(
+                    "{} timeout must be between 1 and {} seconds",
+                    name, MAX_TIMEOUT_SECS
+                ));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// HTTP client applying `timeouts`
+pub fn http_client(timeouts: &ApiTimeouts) -> Result<Client, String> {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(timeouts.connect_secs))
+        .read_timeout(Duration::from_secs(timeouts.read_secs))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn load_stored(app: &AppHandle) -> Result<ApiTimeouts, String> {
+    let store = storage::fs::settings_store(app, API_STORE_FILE)
+        .map_err(|e| format!("Failed to access API settings store: {}", e))?;
+
+    Ok(store
+        .get(TIMEOUTS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Apply the stored timeouts to the shared client
+pub fn initialize(app: &AppHandle) -> Result<(), String> {
+    let timeouts = load_stored(app)?.validate()?;
+    app.state::<GoogleClient>().set_timeouts(timeouts)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the request timeouts in effect
+#[tauri::command]
+pub fn get_api_timeouts(client: State<'_, GoogleClient>) -> ApiTimeouts {
+    crate::perf::trace_command!();
+    client.timeouts()
+}
+
+/// Save the request timeouts and apply them to new requests
+#[tauri::command]
+pub async fn set_api_timeouts(
+    app: AppHandle,
+    client: State<'_, GoogleClient>,
+    timeouts: ApiTimeouts,
+) -> Result<ApiTimeouts, String> {
+    crate::perf::trace_command!();
+    let timeouts = timeouts.validate()?;
+    client.set_timeouts(timeouts)?;
+
+    let store = storage::fs::settings_store(&app, API_STORE_FILE)
+        .map_err(|e| format!("Failed to access API settings store: {}", e))?;
+    store.set(TIMEOUTS_KEY, serde_json::json!(timeouts));
+    storage::fs::save_store(&app, API_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save API timeouts: {}", e))?;
+    Ok(timeouts)
+}
@@ -1,27 +1,33 @@
 //! Shared types for Google API responses
 
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 // ================================
 // Gmail Types
 // ================================
 
-/// Gmail thread summary (from threads.list)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct GmailThread {
-    pub id: String,
-    pub snippet: String,
-    pub history_id: Option<String>,
+/// Gmail thread borrowing from the response buffer (from threads.list)
+///
+/// Strings without JSON escapes are borrowed instead of copied.
+#[derive(Debug, Deserialize)]
+pub struct GmailThreadRef<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub snippet: Cow<'a, str>,
 }
 
-/// Gmail threads list response
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct GmailThreadsResponse {
-    pub threads: Option<Vec<GmailThread>>,
+/// Gmail threads list response, parsed with `GoogleClient::get_with`
+#[derive(Debug, Deserialize)]
+pub struct GmailThreadsPage<'a> {
+    #[serde(borrow, default)]
+    pub threads: Vec<GmailThreadRef<'a>>,
+    #[serde(rename = "nextPageToken", default)]
     pub next_page_token: Option<String>,
-    pub result_size_estimate: Option<u32>,
+    /// Gmail's rough count of all matching threads
+    #[serde(rename = "resultSizeEstimate", default)]
+    pub result_size_estimate: u32,
 }
 
 /// Gmail message header
@@ -31,12 +37,31 @@ pub struct GmailHeader {
     pub value: String,
 }
 
-/// Gmail message payload
+/// Gmail message payload (or one of its MIME parts)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GmailPayload {
     pub headers: Option<Vec<GmailHeader>>,
     #[serde(rename = "mimeType")]
     pub mime_type: Option<String>,
+    /// Attachment file name (empty for body parts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<GmailPartBody>,
+    /// Nested MIME parts (only returned with `format=full`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parts: Option<Vec<GmailPayload>>,
+}
+
+/// Body of a MIME part; attachments carry an ID instead of inline data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GmailPartBody {
+    pub attachment_id: Option<String>,
+    pub size: Option<u64>,
+    /// Inline base64url content of body parts (only returned with `format=full`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
 }
 
 /// Gmail message (from threads.get)
@@ -49,6 +74,9 @@ pub struct GmailMessage {
     pub snippet: String,
     pub payload: Option<GmailPayload>,
     pub internal_date: Option<String>,
+    /// Estimated size in bytes, attachments included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_estimate: Option<u64>,
 }
 
 /// Gmail thread detail (from threads.get)
@@ -58,6 +86,23 @@ pub struct GmailThreadDetail {
     pub messages: Option<Vec<GmailMessage>>,
 }
 
+/// A thread that could not be hydrated
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadFetchError {
+    pub thread_id: String,
+    pub error: super::GoogleApiError,
+}
+
+/// Result of hydrating a batch of threads
+///
+/// Threads that failed are reported in `errors` instead of failing the batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadHydration {
+    /// Hydrated threads, in request order
+    pub threads: Vec<GmailThreadDetail>,
+    pub errors: Vec<ThreadFetchError>,
+}
+
 // ================================
 // Calendar Types
 // ================================
@@ -80,6 +125,18 @@ pub struct EventAttendee {
     pub response_status: Option<String>,
     #[serde(rename = "self")]
     pub is_self: Option<bool>,
+    /// Meeting rooms and other resources
+    pub resource: Option<bool>,
+}
+
+/// Calendar event organizer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventOrganizer {
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    #[serde(rename = "self")]
+    pub is_self: Option<bool>,
 }
 
 /// Calendar event
@@ -87,15 +144,62 @@ pub struct EventAttendee {
 #[serde(rename_all = "camelCase")]
 pub struct CalendarEvent {
     pub id: String,
+    /// Shared by copies of the same meeting on different calendars
+    #[serde(rename = "iCalUID")]
+    pub ical_uid: Option<String>,
     pub summary: Option<String>,
     pub description: Option<String>,
     pub location: Option<String>,
     pub start: Option<EventDateTime>,
     pub end: Option<EventDateTime>,
     pub attendees: Option<Vec<EventAttendee>>,
+    pub organizer: Option<EventOrganizer>,
     pub hangout_link: Option<String>,
     pub html_link: Option<String>,
     pub status: Option<String>,
+    /// Key into the `event` palette of colors.get (None uses the calendar color)
+    pub color_id: Option<String>,
+    /// "opaque" (busy, the default) or "transparent" (free)
+    pub transparency: Option<String>,
+    /// "default", "public", "private" or "confidential"
+    pub visibility: Option<String>,
+    /// "default", "workingLocation", "outOfOffice", "focusTime", ...
+    pub event_type: Option<String>,
+    pub working_location_properties: Option<WorkingLocationProperties>,
+    /// Conference attached to the event (Meet, or add-ons like Zoom/Teams)
+    pub conference_data: Option<ConferenceData>,
+}
+
+/// Conference details of an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConferenceData {
+    pub entry_points: Option<Vec<ConferenceEntryPoint>>,
+}
+
+/// A way to join a conference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConferenceEntryPoint {
+    /// "video", "phone", "sip" or "more"
+    pub entry_point_type: Option<String>,
+    pub uri: Option<String>,
+}
+
+/// Where the user works during a working-location event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingLocationProperties {
+    /// "homeOffice", "officeLocation" or "customLocation"
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub office_location: Option<LabeledLocation>,
+    pub custom_location: Option<LabeledLocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledLocation {
+    pub label: Option<String>,
 }
 
 /// Calendar events list response
@@ -107,6 +211,81 @@ pub struct CalendarEventsResponse {
     pub time_zone: Option<String>,
 }
 
+/// A background/foreground color pair
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorDefinition {
+    pub background: String,
+    pub foreground: String,
+}
+
+/// Color palettes (from colors.get)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalendarColors {
+    #[serde(default)]
+    pub calendar: std::collections::HashMap<String, ColorDefinition>,
+    #[serde(default)]
+    pub event: std::collections::HashMap<String, ColorDefinition>,
+}
+
+/// Calendar list entry (from calendarList.get)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarListEntry {
+    pub id: String,
+    pub color_id: Option<String>,
+    pub background_color: Option<String>,
+    pub foreground_color: Option<String>,
+}
+
+/// FreeBusy query request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyRequest {
+    pub time_min: String,
+    pub time_max: String,
+    pub items: Vec<FreeBusyRequestItem>,
+}
+
+/// Calendar to include in a FreeBusy query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreeBusyRequestItem {
+    pub id: String,
+}
+
+/// Busy interval returned by FreeBusy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimePeriod {
+    pub start: String,
+    pub end: String,
+}
+
+/// Busy intervals for a single calendar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreeBusyCalendar {
+    pub busy: Option<Vec<TimePeriod>>,
+}
+
+/// FreeBusy query response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreeBusyResponse {
+    pub calendars: Option<std::collections::HashMap<String, FreeBusyCalendar>>,
+}
+
+/// New calendar event creation request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCalendarEvent {
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub start: EventDateTime,
+    pub end: EventDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attendees: Option<Vec<EventAttendee>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_properties: Option<serde_json::Value>,
+}
+
 // ================================
 // Tasks Types
 // ================================
@@ -180,6 +359,9 @@ pub struct ThreadSummary {
     pub is_unread: bool,
     pub message_count: u32,
     pub priority_score: f32,
+    /// Messages not yet seen in the app (see `gmail::ReadPosition`)
+    #[serde(default)]
+    pub unseen_message_count: u32,
 }
 
 /// Processed calendar event for UI
@@ -192,6 +374,76 @@ pub struct ProcessedEvent {
     pub location: Option<String>,
     pub meeting_link: Option<String>,
     pub attendees_count: u32,
+    /// "google" or "ical:<subscription name>"
+    #[serde(default)]
+    pub source: String,
+    /// iCalendar UID, shared by copies of the meeting on other calendars
+    #[serde(default)]
+    pub ical_uid: Option<String>,
+    /// Sources of duplicates merged into this event
+    #[serde(default)]
+    pub also_on: Vec<String>,
+    #[serde(default)]
+    pub color_id: Option<String>,
+    /// Hex colors resolved from the event or calendar color
+    #[serde(default)]
+    pub background_color: Option<String>,
+    #[serde(default)]
+    pub foreground_color: Option<String>,
+    /// "opaque" (busy) or "transparent" (free)
+    #[serde(default)]
+    pub transparency: Option<String>,
+    #[serde(default)]
+    pub visibility: Option<String>,
+    /// Links found in the description (docs, alternate meeting rooms, ...)
+    #[serde(default)]
+    pub links: Vec<EventLink>,
+    /// Bullet or numbered agenda items from the description
+    #[serde(default)]
+    pub agenda: Vec<String>,
+    #[serde(default)]
+    pub dial_ins: Vec<DialIn>,
+}
+
+/// What a link in an event description points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventLinkKind {
+    /// Docs, Sheets, Drive, Notion, ...
+    Document,
+    /// Zoom, Meet, Teams, Webex, ...
+    Meeting,
+    Other,
+}
+
+/// A link extracted from an event description
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLink {
+    pub url: String,
+    pub kind: EventLinkKind,
+}
+
+/// A phone dial-in extracted from an event description
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DialIn {
+    pub number: String,
+    /// PIN, passcode or meeting ID entered after dialing
+    pub pin: Option<String>,
+}
+
+/// What to do with a task's source thread once the task is completed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadFollowUp {
+    /// Ask the user whether to archive the thread
+    #[default]
+    Offer,
+    /// Archive the thread right away
+    Archive,
+    /// Only mark the thread read
+    MarkRead,
+    /// Leave the thread alone
+    Keep,
 }
 
 /// Task reference for tracking external tasks
@@ -199,9 +451,14 @@ pub struct ProcessedEvent {
 pub struct TaskRef {
     pub provider: String,
     pub external_id: String,
+    #[serde(default)]
+    pub list_id: String,
     pub source_thread_id: Option<String>,
     pub last_sync_at: i64,
     pub title: String,
     pub status: String,
     pub due: Option<String>,
+    /// None follows the list's default
+    #[serde(default)]
+    pub on_complete: Option<ThreadFollowUp>,
 }
@@ -0,0 +1,318 @@
+//! Connectivity and service health monitor
+//!
+//! Tracks per-API health (Gmail, Calendar, Tasks) from two sources:
+//! - Every request made through `GoogleClient`
+//! - A background connectivity probe that runs on a fixed interval
+//!
+//! Status changes are pushed to the frontend as `health:changed` events so the
+//! UI can show an accurate offline/degraded banner.
+
+use crate::google::{GoogleClient, CALENDAR_API_BASE, GMAIL_API_BASE, TASKS_API_BASE};
+use crate::sync::SyncScheduler;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::broadcast;
+
+/// Event name emitted when the health snapshot changes
+pub const HEALTH_CHANGED_EVENT: &str = "health:changed";
+
+/// Interval between background connectivity probes
+const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+/// Timeout for a single probe request
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Google API tracked by the monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKind {
+    Gmail,
+    Calendar,
+    Tasks,
+}
+
+impl ApiKind {
+    pub const ALL: [ApiKind; 3] = [ApiKind::Gmail, ApiKind::Calendar, ApiKind::Tasks];
+
+    pub(crate) fn base_url(self) -> &'static str {
+        match self {
+            ApiKind::Gmail => GMAIL_API_BASE,
+            ApiKind::Calendar => CALENDAR_API_BASE,
+            ApiKind::Tasks => TASKS_API_BASE,
+        }
+    }
+
+    /// Resolve the API a request URL belongs to
+    pub fn from_url(url: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|k| url.starts_with(k.base_url()))
+    }
+}
+
+/// Overall connectivity state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverallStatus {
+    Online,
+    Degraded,
+    Offline,
+}
+
+/// Health of a single API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiHealth {
+    pub reachable: bool,
+    pub last_success_ms: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_error_ms: Option<i64>,
+}
+
+/// Snapshot returned to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub status: OverallStatus,
+    pub apis: HashMap<ApiKind, ApiHealth>,
+    pub last_probe_ms: Option<i64>,
+}
+
+/// Thread-safe health tracker shared with `GoogleClient`
+pub struct HealthMonitor {
+    apis: RwLock<HashMap<ApiKind, ApiHealth>>,
+    last_probe_ms: RwLock<Option<i64>>,
+    changed: broadcast::Sender<HealthStatus>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        let apis = ApiKind::ALL
+            .into_iter()
+            .map(|k| {
+                (
+                    k,
+                    ApiHealth {
+                        reachable: true,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            apis: RwLock::new(apis),
+            last_probe_ms: RwLock::new(None),
+            changed: broadcast::channel(16).0,
+        }
+    }
+
+    /// Record a successful response for the API owning `url`
+    pub fn record_success(&self, url: &str) {
+        if let Some(kind) = ApiKind::from_url(url) {
+            self.update(kind, |entry| {
+                entry.reachable = true;
+                entry.last_success_ms = Some(chrono::Utc::now().timestamp_millis());
+            });
+        }
+    }
+
+    /// Record a failure for the API owning `url`
+    ///
+    /// `reachable` is false only for transport errors; HTTP error statuses
+    /// still prove the service is reachable.
+    pub fn record_failure(&self, url: &str, error: &str, reachable: bool) {
+        if let Some(kind) = ApiKind::from_url(url) {
+            self.update(kind, |entry| {
+                entry.reachable = reachable;
+                entry.last_error = Some(error.to_string());
+                entry.last_error_ms = Some(chrono::Utc::now().timestamp_millis());
+            });
+        }
+    }
+
+    fn set_reachable(&self, kind: ApiKind, reachable: bool, error: Option<String>) {
+        self.update(kind, |entry| {
+            entry.reachable = reachable;
+            if let Some(error) = error {
+                entry.last_error = Some(error);
+                entry.last_error_ms = Some(chrono::Utc::now().timestamp_millis());
+            }
+        });
+    }
+
+    /// Apply `f` to the API's entry, broadcasting a snapshot when its
+    /// reachability (and so possibly the overall status) changed
+    fn update(&self, kind: ApiKind, f: impl FnOnce(&mut ApiHealth)) {
+        let changed = match self.apis.write() {
+            Ok(mut apis) => {
+                let entry = apis.entry(kind).or_default();
+                let was_reachable = entry.reachable;
+                f(entry);
+                entry.reachable != was_reachable
+            }
+            Err(_) => false,
+        };
+
+        if changed {
+            // No receivers just means nothing is listening yet
+            let _ = self.changed.send(self.snapshot());
+        }
+    }
+
+    /// Receive a snapshot whenever an API's reachability changes
+    pub fn subscribe(&self) -> broadcast::Receiver<HealthStatus> {
+        self.changed.subscribe()
+    }
+
+    /// Current health snapshot
+    pub fn snapshot(&self) -> HealthStatus {
+        let apis = self.apis.read().map(|a| a.clone()).unwrap_or_default();
+        let last_probe_ms = self.last_probe_ms.read().ok().and_then(|p| *p);
+
+        HealthStatus {
+            status: overall_status(&apis),
+            apis,
+            last_probe_ms,
+        }
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn overall_status(apis: &HashMap<ApiKind, ApiHealth>) -> OverallStatus {
+    let reachable = apis.values().filter(|a| a.reachable).count();
+    if reachable == apis.len() {
+        OverallStatus::Online
+    } else if reachable == 0 {
+        OverallStatus::Offline
+    } else {
+        OverallStatus::Degraded
+    }
+}
+
+// ============================================================================
+// Background Probe
+// ============================================================================
+
+/// Probe each API host once and update the monitor
+async fn probe_once(http: &reqwest::Client, monitor: &HealthMonitor) {
+    for kind in ApiKind::ALL {
+        // Any HTTP response (even 401/404) means the host is reachable
+        match http.get(kind.base_url()).send().await {
+            Ok(_) => monitor.set_reachable(kind, true, None),
+            Err(e) => monitor.set_reachable(kind, false, Some(format!("Probe failed: {}", e))),
+        }
+    }
+
+    if let Ok(mut last) = monitor.last_probe_ms.write() {
+        *last = Some(chrono::Utc::now().timestamp_millis());
+    }
+}
+
+/// Start the background connectivity probe
+pub fn spawn_probe(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let http = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+            Ok(http) => http,
+            Err(e) => {
+                eprintln!("Failed to create health probe client: {}", e);
+                return;
+            }
+        };
+
+        let monitor = app.state::<GoogleClient>().health();
+        loop {
+            probe_once(&http, &monitor).await;
+            app.state::<SyncScheduler>().wait(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+/// Forward reachability changes to the frontend as `health:changed`
+///
+/// Changes come from both the probe and requests made through `GoogleClient`.
+pub fn spawn_events(app: AppHandle) {
+    let mut rx = app.state::<GoogleClient>().health().subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(snapshot) => {
+                    let _ = app.emit(HEALTH_CHANGED_EVENT, &snapshot);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the current connectivity and per-API health status
+#[tauri::command]
+pub fn get_health_status(client: State<'_, GoogleClient>) -> HealthStatus {
+    crate::perf::trace_command!();
+    client.health().snapshot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_kind_from_url() {
+        assert_eq!(
+            ApiKind::from_url(&format!("{}/users/me/threads", GMAIL_API_BASE)),
+            Some(ApiKind::Gmail)
+        );
+        assert_eq!(
+            ApiKind::from_url(&format!("{}/lists/abc/tasks", TASKS_API_BASE)),
+            Some(ApiKind::Tasks)
+        );
+        assert_eq!(ApiKind::from_url("https://example.com"), None);
+    }
+
+    #[test]
+    fn test_overall_status() {
+        let monitor = HealthMonitor::new();
+        assert_eq!(monitor.snapshot().status, OverallStatus::Online);
+
+        let gmail_url = format!("{}/users/me/threads", GMAIL_API_BASE);
+        monitor.record_failure(&gmail_url, "timeout", false);
+        assert_eq!(monitor.snapshot().status, OverallStatus::Degraded);
+
+        monitor.record_success(&gmail_url);
+        let snapshot = monitor.snapshot();
+        assert_eq!(snapshot.status, OverallStatus::Online);
+        assert_eq!(
+            snapshot.apis[&ApiKind::Gmail].last_error.as_deref(),
+            Some("timeout")
+        );
+    }
+
+    #[test]
+    fn test_reachability_changes_are_broadcast() {
+        let monitor = HealthMonitor::new();
+        let mut rx = monitor.subscribe();
+        let gmail_url = format!("{}/users/me/threads", GMAIL_API_BASE);
+
+        // An HTTP error keeps the API reachable: nothing to report
+        monitor.record_failure(&gmail_url, "HTTP 500", true);
+        assert!(rx.try_recv().is_err());
+
+        monitor.record_failure(&gmail_url, "timeout", false);
+        assert_eq!(rx.try_recv().unwrap().status, OverallStatus::Degraded);
+        monitor.record_failure(&gmail_url, "timeout", false);
+        assert!(rx.try_recv().is_err());
+
+        monitor.record_success(&gmail_url);
+        assert_eq!(rx.try_recv().unwrap().status, OverallStatus::Online);
+    }
+}
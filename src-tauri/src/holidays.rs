@@ -0,0 +1,282 @@
+//! Public holidays and PTO awareness
+//!
+//! Public holidays come from Google's regional holiday calendars
+//! (`<lang>.<region>#holiday@group.v.calendar.google.com`) and are cached per
+//! region and year. PTO days are declared by the user and stored locally.
+//!
+//! Used by the planner (no focus blocks on holidays) and by the daily note
+//! context ("tomorrow is a public holiday").
+
+use crate::auth::TokenStore;
+use crate::cache::CacheState;
+use crate::google::types::CalendarEventsResponse;
+use crate::google::{calendar, GoogleClient, CALENDAR_API_BASE};
+use crate::storage;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+const HOLIDAYS_STORE_FILE: &str = "holidays.json";
+const SETTINGS_KEY: &str = "settings";
+
+/// Cached holidays rarely change; refresh once a day
+const HOLIDAYS_CACHE_TTL_SECS: u64 = 86_400;
+
+/// Holiday settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HolidaySettings {
+    /// Google holiday calendar region (e.g. "usa", "spain", "mexican"); None disables
+    pub region: Option<String>,
+    /// Language of holiday names (e.g. "en", "es")
+    pub language: String,
+    /// User-declared days off (YYYY-MM-DD)
+    pub pto_dates: Vec<String>,
+}
+
+impl Default for HolidaySettings {
+    fn default() -> Self {
+        Self {
+            region: None,
+            language: "en".to_string(),
+            pto_dates: Vec::new(),
+        }
+    }
+}
+
+/// Kind of day off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HolidayKind {
+    Public,
+    Pto,
+}
+
+/// A day off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holiday {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub name: String,
+    pub kind: HolidayKind,
+}
+
+fn holiday_calendar_id(settings: &HolidaySettings, region: &str) -> String {
+    format!(
+        "{}.{}#holiday@group.v.calendar.google.com",
+        settings.language, region
+    )
+}
+
+pub fn load_settings(app: &AppHandle) -> Result<HolidaySettings, String> {
+    let store = storage::fs::settings_store(app, HOLIDAYS_STORE_FILE)
+        .map_err(|e| format!("Failed to access holidays store: {}", e))?;
+
+    Ok(store
+        .get(SETTINGS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Fetch (or read from cache) the public holidays of one year
+async fn public_holidays_for_year(
+    client: &GoogleClient,
+    cache: &CacheState,
+    token_store: &TokenStore,
+    settings: &HolidaySettings,
+    region: &str,
+    year: i32,
+) -> Result<Vec<Holiday>, String> {
+    let cache_key = format!("holidays:{}:{}:{}", settings.language, region, year);
+    if let Some(cached) = cache.0.get_json::<Vec<Holiday>>(&cache_key) {
+        return Ok(cached);
+    }
+
+    let url = format!(
+        "{}/calendars/{}/events?timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime",
+        CALENDAR_API_BASE,
+        urlencoding::encode(&holiday_calendar_id(settings, region)),
+        urlencoding::encode(&format!("{}-01-01T00:00:00Z", year)),
+        urlencoding::encode(&format!("{}-01-01T00:00:00Z", year + 1)),
+    );
+
+    let response: CalendarEventsResponse = client
+        .get_background(&calendar::EVENT_LIST_FIELDS.apply(&url), token_store)
+        .await?;
+    let holidays: Vec<Holiday> = response
+        .items
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|e| {
+            Some(Holiday {
+                date: e.start?.date?,
+                name: e.summary.unwrap_or_else(|| "Holiday".to_string()),
+                kind: HolidayKind::Public,
+            })
+        })
+        .collect();
+
+    cache
+        .0
+        .set_json(&cache_key, &holidays, HOLIDAYS_CACHE_TTL_SECS);
+    Ok(holidays)
+}
+
+/// All days off (public holidays and PTO) between two dates, inclusive
+pub async fn holidays_between(
+    settings: &HolidaySettings,
+    client: &GoogleClient,
+    cache: &CacheState,
+    token_store: &TokenStore,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<Holiday>, String> {
+    let in_range = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|d| d >= start && d <= end)
+            .unwrap_or(false)
+    };
+
+    let mut holidays: Vec<Holiday> = settings
+        .pto_dates
+        .iter()
+        .filter(|d| in_range(d))
+        .map(|d| Holiday {
+            date: d.clone(),
+            name: "Time off".to_string(),
+            kind: HolidayKind::Pto,
+        })
+        .collect();
+
+    if let Some(region) = &settings.region {
+        for year in start.year()..=end.year() {
+            let yearly =
+                public_holidays_for_year(client, cache, token_store, settings, region, year)
+                    .await?;
+            holidays.extend(yearly.into_iter().filter(|h| in_range(&h.date)));
+        }
+    }
+
+    holidays.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(holidays)
+}
+
+/// The day off falling on `date`, if any
+pub async fn holiday_on(
+    app: &AppHandle,
+    client: &GoogleClient,
+    cache: &CacheState,
+    token_store: &TokenStore,
+    date: NaiveDate,
+) -> Result<Option<Holiday>, String> {
+    let settings = load_settings(app)?;
+    let holidays = holidays_between(&settings, client, cache, token_store, date, date).await?;
+    Ok(holidays.into_iter().next())
+}
+
+/// Human-readable notice for a holiday tomorrow
+pub fn tomorrow_notice(holiday: &Holiday) -> String {
+    match holiday.kind {
+        HolidayKind::Public => format!("Tomorrow is a public holiday: {}", holiday.name),
+        HolidayKind::Pto => "Tomorrow you're off (PTO)".to_string(),
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get holiday settings
+#[tauri::command]
+pub async fn get_holiday_settings(app: AppHandle) -> Result<HolidaySettings, String> {
+    crate::perf::trace_command!();
+    load_settings(&app)
+}
+
+/// Save holiday settings
+#[tauri::command]
+pub async fn set_holiday_settings(app: AppHandle, settings: HolidaySettings) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if let Some(bad) = settings
+        .pto_dates
+        .iter()
+        .find(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_err())
+    {
+        return Err(format!("Invalid PTO date: {}. Expected YYYY-MM-DD", bad));
+    }
+
+    let store = storage::fs::settings_store(&app, HOLIDAYS_STORE_FILE)
+        .map_err(|e| format!("Failed to access holidays store: {}", e))?;
+
+    store.set(SETTINGS_KEY, serde_json::json!(settings));
+    storage::fs::save_store(&app, HOLIDAYS_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save holiday settings: {}", e))
+}
+
+/// Get days off between two dates (YYYY-MM-DD, inclusive)
+#[tauri::command]
+pub async fn get_holidays(
+    app: AppHandle,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<Holiday>, String> {
+    crate::perf::trace_command!();
+    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .map_err(|_| "Invalid start date, expected YYYY-MM-DD".to_string())?;
+    let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+        .map_err(|_| "Invalid end date, expected YYYY-MM-DD".to_string())?;
+
+    let settings = load_settings(&app)?;
+    holidays_between(&settings, &client, &cache, &token_store, start, end).await
+}
+
+/// Get a notice if tomorrow is a holiday or PTO day
+#[tauri::command]
+pub async fn get_holiday_notice(
+    app: AppHandle,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    cache: State<'_, CacheState>,
+) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    let tomorrow = Local::now()
+        .date_naive()
+        .succ_opt()
+        .ok_or("Failed to create date")?;
+
+    let holiday = holiday_on(&app, &client, &cache, &token_store, tomorrow).await?;
+    Ok(holiday.as_ref().map(tomorrow_notice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_holiday_calendar_id() {
+        let settings = HolidaySettings {
+            region: Some("spain".to_string()),
+            language: "es".to_string(),
+            pto_dates: vec![],
+        };
+        assert_eq!(
+            holiday_calendar_id(&settings, "spain"),
+            "es.spain#holiday@group.v.calendar.google.com"
+        );
+    }
+
+    #[test]
+    fn test_tomorrow_notice() {
+        let holiday = Holiday {
+            date: "2026-12-25".to_string(),
+            name: "Christmas Day".to_string(),
+            kind: HolidayKind::Public,
+        };
+        assert_eq!(
+            tomorrow_notice(&holiday),
+            "Tomorrow is a public holiday: Christmas Day"
+        );
+    }
+}
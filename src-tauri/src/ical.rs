@@ -0,0 +1,967 @@
+//! External iCal subscription feeds
+//!
+//! Read-only ICS subscriptions (team calendars, public holidays, ...) that are
+//! fetched periodically, parsed, expanded (RRULE) and merged into
+//! `get_today_events` with a source tag.
+//!
+//! Times with an IANA `TZID` (e.g. `Europe/Berlin`) are converted to local
+//! time; other zone names and floating times are taken as local. Recurrences
+//! are expanded in local time, and only the common RRULE parts (FREQ,
+//! INTERVAL, COUNT, UNTIL, BYDAY for weekly rules) are supported.
+//!
+//! Feeds are untrusted input: bodies are capped at `MAX_FEED_BYTES`, and
+//! durations and recurrence steps that don't fit are dropped instead of
+//! overflowing.
+
+use crate::app_lock::AppLockState;
+use crate::events::DataEvent;
+use crate::google::invalidation;
+use crate::google::types::ProcessedEvent;
+use crate::processing;
+use crate::storage;
+use crate::sync::SyncScheduler;
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, Months, NaiveDate, NaiveDateTime,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const ICAL_STORE_FILE: &str = "ical.json";
+const SUBSCRIPTIONS_KEY: &str = "subscriptions";
+
+/// Interval between background feed refreshes
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Upper bound on RRULE iterations per event
+const MAX_RECURRENCE_ITERATIONS: usize = 10_000;
+/// Largest feed body accepted (10 MiB)
+const MAX_FEED_BYTES: usize = 10 * 1024 * 1024;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A subscribed ICS feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcalSubscription {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+}
+
+/// Fetch status of a feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedStatus {
+    pub id: String,
+    pub name: String,
+    pub event_count: usize,
+    pub fetched_at_ms: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+/// Recurrence frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Parsed RRULE
+#[derive(Debug, Clone)]
+struct RecurrenceRule {
+    freq: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<NaiveDateTime>,
+    by_day: Vec<Weekday>,
+}
+
+/// Parsed VEVENT (times are local)
+#[derive(Debug, Clone)]
+struct IcalEvent {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: NaiveDateTime,
+    duration: ChronoDuration,
+    all_day: bool,
+    rrule: Option<RecurrenceRule>,
+    exdates: Vec<NaiveDateTime>,
+}
+
+/// A concrete occurrence of an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcalOccurrence {
+    pub uid: String,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+    pub source: String,
+}
+
+impl IcalOccurrence {
+    fn to_processed_event(&self) -> ProcessedEvent {
+        let format_time = |t: &NaiveDateTime| {
+            if self.all_day {
+                t.format("%Y-%m-%d").to_string()
+            } else {
+                local_from_naive(t)
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_else(|| t.format("%Y-%m-%dT%H:%M:%S").to_string())
+            }
+        };
+
+        ProcessedEvent {
+            id: format!("{}:{}", self.uid, self.start.format("%Y%m%dT%H%M%S")),
+            title: self.summary.clone(),
+            start_time: format_time(&self.start),
+            end_time: format_time(&self.end),
+            location: self.location.clone(),
+            meeting_link: self
+                .location
+                .as_deref()
+                .and_then(processing::find_meeting_link),
+            attendees_count: 0,
+            source: self.source.clone(),
+            ical_uid: Some(self.uid.clone()),
+            also_on: Vec::new(),
+            color_id: None,
+            background_color: None,
+            foreground_color: None,
+            transparency: None,
+            visibility: None,
+            links: Vec::new(),
+            agenda: Vec::new(),
+            dial_ins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct FeedData {
+    /// Shared so readers can expand them without holding the lock
+    events: Arc<Vec<IcalEvent>>,
+    fetched_at_ms: Option<i64>,
+    last_error: Option<String>,
+}
+
+/// Parsed feeds, keyed by subscription ID
+pub struct IcalState {
+    feeds: RwLock<HashMap<String, FeedData>>,
+}
+
+impl IcalState {
+    pub fn new() -> Self {
+        Self {
+            feeds: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// All occurrences overlapping `[start, end)` across subscriptions
+    pub fn occurrences_between(
+        &self,
+        subscriptions: &[IcalSubscription],
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Vec<IcalOccurrence> {
+        let feeds: Vec<(String, Arc<Vec<IcalEvent>>)> = match self.feeds.read() {
+            Ok(feeds) => subscriptions
+                .iter()
+                .filter_map(|sub| {
+                    let feed = feeds.get(&sub.id)?;
+                    Some((format!("ical:{}", sub.name), feed.events.clone()))
+                })
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+
+        feeds
+            .iter()
+            .flat_map(|(source, events)| {
+                events
+                    .iter()
+                    .flat_map(move |e| expand_event(e, start, end, source))
+            })
+            .collect()
+    }
+
+    /// Today's occurrences as processed events
+    pub fn today_events(&self, subscriptions: &[IcalSubscription]) -> Vec<ProcessedEvent> {
+        let today = Local::now().date_naive();
+        let (Some(start), Some(end)) = (
+            today.and_hms_opt(0, 0, 0),
+            today.succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0)),
+        ) else {
+            return Vec::new();
+        };
+
+        self.occurrences_between(subscriptions, start, end)
+            .iter()
+            .map(IcalOccurrence::to_processed_event)
+            .collect()
+    }
+}
+
+impl Default for IcalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// Join folded lines (continuations start with a space or tab)
+fn unfold_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.lines() {
+        let line = raw.trim_end_matches('\r');
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+/// Content line split into (name, params, value)
+type Property = (String, HashMap<String, String>, String);
+
+/// Split a content line into (name, params, value)
+fn split_property(line: &str) -> Option<Property> {
+    // The value starts at the first ':' outside of quoted parameter values
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+
+    Some((name, params, value.to_string()))
+}
+
+fn unescape_text(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// Parse a DATE or DATE-TIME value into local time; returns (time, is_date)
+///
+/// `tzid` is the value's `TZID` parameter; unknown zones are taken as local.
+fn parse_ical_time(value: &str, tzid: Option<&str>) -> Option<(NaiveDateTime, bool)> {
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms_opt(0, 0, 0)?, true));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        let local = Utc.from_utc_datetime(&naive).with_timezone(&Local);
+        return Some((local.naive_local(), false));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = tzid.and_then(|id| id.trim_start_matches('/').parse::<Tz>().ok());
+    let Some(zone) = zone else {
+        // Floating time or a zone we don't know
+        return Some((naive, false));
+    };
+    let zoned = zone.from_local_datetime(&naive).earliest()?;
+    Some((zoned.with_timezone(&Local).naive_local(), false))
+}
+
+/// Parse an ISO 8601 duration such as `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<ChronoDuration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim_start_matches('+')),
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut total = ChronoDuration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            'T' => {}
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                let part = match unit {
+                    'W' => ChronoDuration::try_weeks(n),
+                    'D' => ChronoDuration::try_days(n),
+                    'H' => ChronoDuration::try_hours(n),
+                    'M' => ChronoDuration::try_minutes(n),
+                    'S' => ChronoDuration::try_seconds(n),
+                    _ => return None,
+                }?;
+                total = total.checked_add(&part)?;
+            }
+        }
+    }
+
+    Some(if negative { -total } else { total })
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    // Ordinal prefixes (e.g. "1MO", "-1FR") are ignored
+    let code = value.trim_start_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit());
+    match code {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_rrule(value: &str) -> Option<RecurrenceRule> {
+    let parts: HashMap<String, String> = value
+        .split(';')
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.to_string()))
+        .collect();
+
+    let freq = match parts.get("FREQ")?.as_str() {
+        "DAILY" => Frequency::Daily,
+        "WEEKLY" => Frequency::Weekly,
+        "MONTHLY" => Frequency::Monthly,
+        "YEARLY" => Frequency::Yearly,
+        _ => return None,
+    };
+
+    Some(RecurrenceRule {
+        freq,
+        interval: parts
+            .get("INTERVAL")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1)
+            .max(1),
+        count: parts.get("COUNT").and_then(|v| v.parse().ok()),
+        until: parts
+            .get("UNTIL")
+            .and_then(|v| parse_ical_time(v, None))
+            .map(|(t, _)| t),
+        by_day: parts
+            .get("BYDAY")
+            .map(|v| v.split(',').filter_map(parse_weekday).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Parse all VEVENTs from an ICS document, skipping cancelled events
+fn parse_ics(content: &str) -> Vec<IcalEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+
+    for line in unfold_lines(content) {
+        match line.as_str() {
+            "BEGIN:VEVENT" => current = Some(Vec::new()),
+            "END:VEVENT" => {
+                if let Some(props) = current.take() {
+                    if let Some(event) = build_event(props) {
+                        events.push(event);
+                    }
+                }
+            }
+            _ => {
+                if let (Some(props), Some(prop)) = (current.as_mut(), split_property(&line)) {
+                    props.push(prop);
+                }
+            }
+        }
+    }
+
+    events
+}
+
+fn build_event(props: Vec<Property>) -> Option<IcalEvent> {
+    let mut uid = None;
+    let mut summary = None;
+    let mut location = None;
+    let mut start = None;
+    let mut end = None;
+    let mut duration = None;
+    let mut rrule = None;
+    let mut exdates = Vec::new();
+
+    for (name, params, value) in props {
+        let tzid = params.get("TZID").map(String::as_str);
+        match name.as_str() {
+            "UID" => uid = Some(value),
+            "SUMMARY" => summary = Some(unescape_text(&value)),
+            "LOCATION" => location = Some(unescape_text(&value)).filter(|l| !l.is_empty()),
+            "DTSTART" => start = parse_ical_time(&value, tzid),
+            "DTEND" => end = parse_ical_time(&value, tzid),
+            "DURATION" => duration = parse_duration(&value),
+            "RRULE" => rrule = parse_rrule(&value),
+            "EXDATE" => exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|v| parse_ical_time(v, tzid))
+                    .map(|(t, _)| t),
+            ),
+            "STATUS" if value.eq_ignore_ascii_case("CANCELLED") => return None,
+            _ => {}
+        }
+    }
+
+    let (start, all_day) = start?;
+    let duration = match (end, duration) {
+        (Some((end, _)), _) => end - start,
+        (None, Some(duration)) => duration,
+        (None, None) if all_day => ChronoDuration::days(1),
+        (None, None) => ChronoDuration::zero(),
+    };
+
+    Some(IcalEvent {
+        uid: uid.unwrap_or_else(|| format!("{}", start.format("%Y%m%dT%H%M%S"))),
+        summary: summary.unwrap_or_else(|| "(No title)".to_string()),
+        location,
+        start,
+        duration,
+        all_day,
+        rrule,
+        exdates,
+    })
+}
+
+// ============================================================================
+// Recurrence Expansion
+// ============================================================================
+
+fn local_from_naive(t: &NaiveDateTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(t).earliest()
+}
+
+/// Add `months` to `t`, returning None when the day does not exist (e.g. Feb 30)
+fn add_months_exact(t: NaiveDateTime, months: u32) -> Option<NaiveDateTime> {
+    let shifted = t.checked_add_months(Months::new(months))?;
+    (shifted.day() == t.day()).then_some(shifted)
+}
+
+/// Start times of all occurrences (in order), stopping once `window_end` is passed
+fn occurrence_starts(event: &IcalEvent, window_end: NaiveDateTime) -> Vec<NaiveDateTime> {
+    let rule = match &event.rrule {
+        Some(rule) => rule,
+        None => return vec![event.start],
+    };
+
+    let mut starts = Vec::new();
+    let in_range = |t: &NaiveDateTime| t < &window_end && rule.until.is_none_or(|u| *t <= u);
+    let count_reached = |n: usize| rule.count.is_some_and(|c| n >= c);
+
+    if rule.freq == Frequency::Weekly && !rule.by_day.is_empty() {
+        let Some(week_start) = event.start.date().checked_sub_signed(ChronoDuration::days(
+            event.start.weekday().num_days_from_monday() as i64,
+        )) else {
+            return starts;
+        };
+        let mut days: Vec<Weekday> = rule.by_day.clone();
+        days.sort_by_key(|d| d.num_days_from_monday());
+
+        for week in 0..MAX_RECURRENCE_ITERATIONS {
+            // Past the last representable date the series just ends
+            let Some(monday) = ChronoDuration::try_weeks(week as i64 * rule.interval as i64)
+                .and_then(|offset| week_start.checked_add_signed(offset))
+            else {
+                return starts;
+            };
+            for day in &days {
+                let Some(date) = monday
+                    .checked_add_signed(ChronoDuration::days(day.num_days_from_monday() as i64))
+                else {
+                    return starts;
+                };
+                let candidate = date.and_time(event.start.time());
+                if candidate < event.start {
+                    continue;
+                }
+                if !in_range(&candidate) || count_reached(starts.len()) {
+                    return starts;
+                }
+                starts.push(candidate);
+            }
+        }
+        return starts;
+    }
+
+    for n in 0..MAX_RECURRENCE_ITERATIONS {
+        let Some(step) = (n as u32).checked_mul(rule.interval) else {
+            break;
+        };
+        let offset = |delta: Option<ChronoDuration>| {
+            delta.and_then(|delta| event.start.checked_add_signed(delta))
+        };
+        let candidate = match rule.freq {
+            Frequency::Daily => offset(ChronoDuration::try_days(step as i64)),
+            Frequency::Weekly => offset(ChronoDuration::try_weeks(step as i64)),
+            Frequency::Monthly => add_months_exact(event.start, step),
+            Frequency::Yearly => step
+                .checked_mul(12)
+                .and_then(|months| add_months_exact(event.start, months)),
+        };
+
+        let Some(candidate) = candidate else {
+            // A missing day of the month is skipped, anything else is out of range
+            if matches!(rule.freq, Frequency::Daily | Frequency::Weekly) {
+                break;
+            }
+            continue;
+        };
+        if !in_range(&candidate) || count_reached(starts.len()) {
+            break;
+        }
+        starts.push(candidate);
+    }
+
+    starts
+}
+
+/// Occurrences of `event` overlapping `[start, end)`
+fn expand_event(
+    event: &IcalEvent,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    source: &str,
+) -> Vec<IcalOccurrence> {
+    occurrence_starts(event, end)
+        .into_iter()
+        .filter(|s| !event.exdates.contains(s))
+        .filter_map(|s| Some((s, s.checked_add_signed(event.duration)?)))
+        .filter(|(s, e)| *s < end && (*e > start || (*e == *s && *s >= start)))
+        .map(|(s, e)| IcalOccurrence {
+            uid: event.uid.clone(),
+            summary: event.summary.clone(),
+            location: event.location.clone(),
+            start: s,
+            end: e,
+            all_day: event.all_day,
+            source: source.to_string(),
+        })
+        .collect()
+}
+
+// ============================================================================
+// Fetching
+// ============================================================================
+
+async fn fetch_feed(http: &reqwest::Client, url: &str) -> Result<Vec<IcalEvent>, String> {
+    // webcal:// is a conventional alias for https://
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+
+    let response = http
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch feed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Feed error {}", response.status()));
+    }
+
+    let body = read_feed_body(response).await?;
+
+    if !body.contains("BEGIN:VCALENDAR") {
+        return Err("Response is not an iCalendar feed".to_string());
+    }
+
+    Ok(parse_ics(&body))
+}
+
+/// Read a feed body, refusing anything over `MAX_FEED_BYTES`
+async fn read_feed_body(mut response: reqwest::Response) -> Result<String, String> {
+    let too_large = || format!("Feed is larger than {} MB", MAX_FEED_BYTES / (1024 * 1024));
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_FEED_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read feed: {}", e))?
+    {
+        if body.len() + chunk.len() > MAX_FEED_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+async fn refresh_subscription(
+    http: &reqwest::Client,
+    state: &IcalState,
+    subscription: &IcalSubscription,
+) -> Result<usize, String> {
+    let result = fetch_feed(http, &subscription.url).await;
+
+    // The feed is replaced wholesale, so a poisoned lock holds nothing stale
+    let mut feeds = state
+        .feeds
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let feed = feeds.entry(subscription.id.clone()).or_default();
+
+    match result {
+        Ok(events) => {
+            let count = events.len();
+            feed.events = Arc::new(events);
+            feed.fetched_at_ms = Some(Utc::now().timestamp_millis());
+            feed.last_error = None;
+            Ok(count)
+        }
+        Err(e) => {
+            // Keep the previously cached events on failure
+            feed.last_error = Some(e.clone());
+            Err(e)
+        }
+    }
+}
+
+pub fn load_subscriptions(app: &AppHandle) -> Result<Vec<IcalSubscription>, String> {
+    let store = storage::fs::settings_store(app, ICAL_STORE_FILE)
+        .map_err(|e| format!("Failed to access iCal store: {}", e))?;
+
+    Ok(store
+        .get(SUBSCRIPTIONS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_subscriptions(app: &AppHandle, subscriptions: &[IcalSubscription]) -> Result<(), String> {
+    let store = storage::fs::settings_store(app, ICAL_STORE_FILE)
+        .map_err(|e| format!("Failed to access iCal store: {}", e))?;
+
+    store.set(SUBSCRIPTIONS_KEY, serde_json::json!(subscriptions));
+    storage::fs::save_store(app, ICAL_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save iCal subscriptions: {}", e))
+}
+
+fn feed_statuses(state: &IcalState, subscriptions: &[IcalSubscription]) -> Vec<FeedStatus> {
+    let feeds = state.feeds.read().map(|f| f.clone()).unwrap_or_default();
+    subscriptions
+        .iter()
+        .map(|sub| {
+            let feed = feeds.get(&sub.id).cloned().unwrap_or_default();
+            FeedStatus {
+                id: sub.id.clone(),
+                name: sub.name.clone(),
+                event_count: feed.events.len(),
+                fetched_at_ms: feed.fetched_at_ms,
+                last_error: feed.last_error,
+            }
+        })
+        .collect()
+}
+
+async fn emit_refreshed(app: &AppHandle, subscription: &IcalSubscription, event_count: usize) {
+    invalidation::mutated(
+        app,
+        DataEvent::FeedRefreshed {
+            subscription_id: subscription.id.clone(),
+            event_count,
+        },
+    )
+    .await;
+}
+
+/// Start the periodic feed refresh task
+pub fn spawn_refresh(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let http = match http_client() {
+            Ok(http) => http,
+            Err(e) => {
+                eprintln!("iCal refresh disabled: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            if let Ok(subscriptions) = load_subscriptions(&app) {
+                let state = app.state::<IcalState>();
+                for sub in &subscriptions {
+                    match refresh_subscription(&http, &state, sub).await {
+                        Ok(count) => emit_refreshed(&app, sub, count).await,
+                        Err(e) => eprintln!("Failed to refresh iCal feed {}: {}", sub.name, e),
+                    }
+                }
+            }
+
+            app.state::<SyncScheduler>().wait(REFRESH_INTERVAL).await;
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// List iCal subscriptions with their fetch status
+#[tauri::command]
+pub async fn list_ical_subscriptions(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    state: State<'_, IcalState>,
+) -> Result<Vec<FeedStatus>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let subscriptions = load_subscriptions(&app)?;
+    Ok(feed_statuses(&state, &subscriptions))
+}
+
+/// Subscribe to a read-only ICS feed (validated by fetching it once)
+#[tauri::command]
+pub async fn add_ical_subscription(
+    app: AppHandle,
+    state: State<'_, IcalState>,
+    name: String,
+    url: String,
+) -> Result<IcalSubscription, String> {
+    crate::perf::trace_command!();
+    if !(url.starts_with("https://") || url.starts_with("http://") || url.starts_with("webcal://"))
+    {
+        return Err("Feed URL must start with https://, http:// or webcal://".to_string());
+    }
+
+    let subscription = IcalSubscription {
+        id: format!("ical-{}", Utc::now().timestamp_millis()),
+        name,
+        url,
+    };
+
+    let count = refresh_subscription(&http_client()?, &state, &subscription).await?;
+
+    let mut subscriptions = load_subscriptions(&app)?;
+    subscriptions.push(subscription.clone());
+    save_subscriptions(&app, &subscriptions)?;
+    emit_refreshed(&app, &subscription, count).await;
+
+    Ok(subscription)
+}
+
+/// Remove an iCal subscription and its cached events
+#[tauri::command]
+pub async fn remove_ical_subscription(
+    app: AppHandle,
+    state: State<'_, IcalState>,
+    id: String,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let mut subscriptions = load_subscriptions(&app)?;
+    subscriptions.retain(|s| s.id != id);
+    save_subscriptions(&app, &subscriptions)?;
+
+    if let Ok(mut feeds) = state.feeds.write() {
+        feeds.remove(&id);
+    }
+    Ok(())
+}
+
+/// Refetch all iCal subscriptions now
+#[tauri::command]
+pub async fn refresh_ical_feeds(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    state: State<'_, IcalState>,
+) -> Result<Vec<FeedStatus>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let subscriptions = load_subscriptions(&app)?;
+    let http = http_client()?;
+
+    for sub in &subscriptions {
+        // Failures are reported per feed in the returned status
+        if let Ok(count) = refresh_subscription(&http, &state, sub).await {
+            emit_refreshed(&app, sub, count).await;
+        }
+    }
+
+    Ok(feed_statuses(&state, &subscriptions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    const SAMPLE: &str = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup\r\n\
+SUMMARY:Team standup\r\n\
+DTSTART:20260105T093000\r\n\
+DTEND:20260105T094500\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR;COUNT=5\r\n\
+EXDATE:20260109T093000\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:holiday\r\n\
+SUMMARY:Public\r\n \\, holiday\r\n\
+DTSTART;VALUE=DATE:20260106\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:gone\r\n\
+SUMMARY:Cancelled\r\n\
+DTSTART:20260106T100000\r\n\
+STATUS:CANCELLED\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics() {
+        let events = parse_ics(SAMPLE);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].duration, ChronoDuration::minutes(15));
+        assert_eq!(events[1].summary, "Public, holiday");
+        assert!(events[1].all_day);
+        assert_eq!(events[1].duration, ChronoDuration::days(1));
+    }
+
+    #[test]
+    fn test_weekly_byday_expansion() {
+        let events = parse_ics(SAMPLE);
+        let occurrences = expand_event(
+            &events[0],
+            dt("2026-01-01 00:00"),
+            dt("2026-02-01 00:00"),
+            "ical:test",
+        );
+        let starts: Vec<NaiveDateTime> = occurrences.iter().map(|o| o.start).collect();
+
+        // Mon 5, Wed 7, (Fri 9 excluded), Mon 12, Wed 14 -> COUNT=5 includes the EXDATE
+        assert_eq!(
+            starts,
+            vec![
+                dt("2026-01-05 09:30"),
+                dt("2026-01-07 09:30"),
+                dt("2026-01-12 09:30"),
+                dt("2026-01-14 09:30"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_monthly_skips_missing_days() {
+        let event = IcalEvent {
+            uid: "rent".to_string(),
+            summary: "Rent".to_string(),
+            location: None,
+            start: dt("2026-01-31 10:00"),
+            duration: ChronoDuration::hours(1),
+            all_day: false,
+            rrule: parse_rrule("FREQ=MONTHLY;COUNT=3"),
+            exdates: vec![],
+        };
+        let starts = occurrence_starts(&event, dt("2027-01-01 00:00"));
+        assert_eq!(
+            starts,
+            vec![
+                dt("2026-01-31 10:00"),
+                dt("2026-03-31 10:00"),
+                dt("2026-05-31 10:00")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M"), Some(ChronoDuration::minutes(90)));
+        assert_eq!(parse_duration("P1D"), Some(ChronoDuration::days(1)));
+        assert_eq!(parse_duration("-PT15M"), Some(ChronoDuration::minutes(-15)));
+        assert_eq!(parse_duration("1H"), None);
+        assert_eq!(parse_duration("P9223372036854775807W"), None);
+        assert_eq!(parse_duration("P100000000000DT1000000000000H"), None);
+    }
+
+    #[test]
+    fn test_hostile_recurrence_does_not_panic() {
+        let mut event = IcalEvent {
+            uid: "huge".to_string(),
+            summary: "Huge".to_string(),
+            location: None,
+            start: dt("2026-01-05 10:00"),
+            duration: ChronoDuration::MAX,
+            all_day: false,
+            rrule: parse_rrule("FREQ=DAILY;INTERVAL=4294967295;COUNT=100"),
+            exdates: vec![],
+        };
+        let starts = occurrence_starts(&event, NaiveDateTime::MAX);
+        assert_eq!(starts, vec![dt("2026-01-05 10:00")]);
+        // The end overflows, so the occurrence is dropped
+        assert!(expand_event(&event, dt("2026-01-01 00:00"), NaiveDateTime::MAX, "x").is_empty());
+
+        for rule in [
+            "FREQ=WEEKLY;INTERVAL=4294967295",
+            "FREQ=WEEKLY;BYDAY=MO;INTERVAL=4294967295",
+            "FREQ=YEARLY;INTERVAL=4294967295",
+        ] {
+            event.rrule = parse_rrule(rule);
+            assert_eq!(occurrence_starts(&event, NaiveDateTime::MAX).len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_tzid_is_converted_to_local() {
+        let expected = Utc
+            .with_ymd_and_hms(2026, 1, 5, 8, 30, 0)
+            .unwrap()
+            .with_timezone(&Local)
+            .naive_local();
+        assert_eq!(
+            parse_ical_time("20260105T093000", Some("Europe/Berlin")),
+            Some((expected, false))
+        );
+        // Unknown zones are taken as local
+        assert_eq!(
+            parse_ical_time("20260105T093000", Some("Custom Zone")),
+            Some((dt("2026-01-05 09:30"), false))
+        );
+    }
+}
@@ -3,19 +3,65 @@
 //! A Tauri v2 application that integrates with Gmail, Calendar, and Google Tasks
 //! to help you focus on what matters most.
 
+mod account;
+mod ai;
+mod analytics;
+mod app_lock;
+mod attachments;
 mod auth;
+mod automation;
 mod cache;
+mod commands;
+mod daily_note;
 mod data_pipeline;
+mod diagnostics;
+mod digest;
+mod events;
+mod export;
+mod glance;
 mod google;
+mod health;
+mod holidays;
+mod ical;
+mod natural_date;
 mod notifications;
+mod outbox;
+mod perf;
+mod planner;
+mod policy;
+mod privacy;
 mod processing;
+mod push;
+mod routines;
+mod rules;
 mod search;
+mod settings;
+mod setup;
+mod storage;
+mod sync;
+#[cfg(test)]
+mod test_harness;
 mod theme;
+mod travel;
+mod triage;
+mod updates;
+mod windows;
 
+use ai::NoteStreams;
+use app_lock::AppLockState;
 use auth::{AuthState, TokenStore};
 use cache::CacheState;
 use google::GoogleClient;
+use ical::IcalState;
+use outbox::OutboxState;
+use policy::PolicyState;
+use push::PushState;
+use storage::LocalStorage;
+use sync::{BackfillState, SyncScheduler};
 use tauri::Manager;
+use triage::TriageState;
+use updates::UpdateState;
+use windows::WindowRegistry;
 
 /// Environment variable for Google Client ID
 const GOOGLE_CLIENT_ID_ENV: &str = "GOOGLE_CLIENT_ID";
@@ -23,6 +69,8 @@ const GOOGLE_CLIENT_SECRET_ENV: &str = "GOOGLE_CLIENT_SECRET";
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    perf::startup_began();
+
     // Load .env file from the project root (parent of src-tauri)
     let env_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -45,24 +93,27 @@ pub fn run() {
         String::new()
     });
 
-    let client_secret = std::env::var(GOOGLE_CLIENT_SECRET_ENV).unwrap_or_else(|_| {
-        eprintln!("ERROR: {} not set!", GOOGLE_CLIENT_SECRET_ENV);
-        String::new()
-    });
+    // Optional: without it, sign-in runs as a public PKCE-only client
+    let client_secret = std::env::var(GOOGLE_CLIENT_SECRET_ENV).unwrap_or_default();
 
-    if client_id.is_empty() || client_secret.is_empty() {
+    if client_id.is_empty() {
         eprintln!("ERROR: Missing Google OAuth credentials in .env file.");
-        eprintln!(
-            "Required: {} and {}",
-            GOOGLE_CLIENT_ID_ENV, GOOGLE_CLIENT_SECRET_ENV
-        );
+        eprintln!("Required: {}", GOOGLE_CLIENT_ID_ENV);
     } else {
+        if client_secret.is_empty() {
+            println!(
+                "{} not set: signing in as a public client (PKCE only)",
+                GOOGLE_CLIENT_SECRET_ENV
+            );
+        }
         println!(
             "Starting Rainy Day with client ID: {}...",
             &client_id[..20.min(client_id.len())]
         );
     }
 
+    perf::mark_startup(perf::StartupStage::EnvLoaded);
+
     // Clone credentials for setup hook
     let client_id_for_setup = client_id.clone();
     let client_secret_for_setup = client_secret.clone();
@@ -79,6 +130,27 @@ pub fn run() {
         .manage(TokenStore::new())
         .manage(GoogleClient::new())
         .manage(CacheState::default())
+        .manage(UpdateState::default())
+        .manage(LocalStorage::default())
+        .manage(IcalState::default())
+        .manage(AppLockState::default())
+        .manage(SyncScheduler::default())
+        .manage(BackfillState::default())
+        .manage(TriageState::default())
+        .manage(OutboxState::default())
+        .manage(WindowRegistry::default())
+        .manage(NoteStreams::default())
+        .manage(PushState::default())
+        .manage(PolicyState::default())
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Focused(focused) => {
+                sync::on_window_focus(window.app_handle(), window.label(), *focused);
+            }
+            tauri::WindowEvent::Destroyed => {
+                windows::on_window_destroyed(window.app_handle(), window.label());
+            }
+            _ => {}
+        })
         .setup(move |app| {
             // Initialize TokenStore with app data directory
             let token_store = app.state::<TokenStore>();
@@ -87,37 +159,171 @@ pub fn run() {
                 .app_data_dir()
                 .expect("Failed to get app data directory");
 
+            // Recover from writes interrupted by a crash before any settings
+            // store or session file is read
+            match storage::fs::recover_dir(&app_data_dir) {
+                Ok(report) if !report.is_empty() => println!("Recovered app data: {:?}", report),
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to check app data: {}", e),
+            }
+
+            // Keep this profile's secrets apart from other installs'
+            auth::use_keychain_profile(&app_data_dir);
+
+            // Initialize local storage and start the retention vacuum
+            if let Err(e) = app.state::<LocalStorage>().initialize(app_data_dir.clone()) {
+                eprintln!("Failed to initialize local storage: {}", e);
+            }
+            match app.state::<LocalStorage>().recover() {
+                Ok(report) if !report.is_empty() => {
+                    println!("Recovered local storage: {:?}", report)
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to check local storage: {}", e),
+            }
+            storage::spawn_vacuum(app.handle().clone());
+            perf::mark_startup(perf::StartupStage::StorageReady);
+
+            // Load app lock settings (an enabled lock starts locked)
+            if let Err(e) = app.state::<AppLockState>().initialize(app.handle()) {
+                eprintln!("Failed to load app lock settings: {}", e);
+            }
+            app_lock::spawn_idle_watch(app.handle().clone());
+
+            // Apply API base URL overrides from settings/env
+            if let Err(e) = google::endpoints::initialize(app.handle()) {
+                eprintln!("Failed to load API endpoints: {}", e);
+            }
+
+            // Apply the stored Google request timeouts
+            if let Err(e) = google::timeouts::initialize(app.handle()) {
+                eprintln!("Failed to load API timeouts: {}", e);
+            }
+
+            // Start periodic iCal subscription refresh
+            ical::spawn_refresh(app.handle().clone());
+
+            // Mobile sign-in completes through a deep-link redirect
+            #[cfg(mobile)]
+            auth::mobile::register_redirect_handler(app.handle());
+
             // Use tokio runtime to run async initialization
             let client_id = client_id_for_setup.clone();
             let client_secret = client_secret_for_setup.clone();
 
-            tauri::async_runtime::block_on(async {
-                if let Err(e) = token_store
-                    .initialize(app_data_dir, client_id, client_secret)
-                    .await
-                {
-                    eprintln!("Failed to initialize token store: {}", e);
-                }
-            });
+            // Mock mode signs in a fake account instead of the stored Google
+            // session; the background tasks below run against it as usual
+            if google::mock::is_enabled() {
+                println!("Mock provider mode enabled: serving fixture data");
+                tauri::async_runtime::block_on(
+                    token_store.use_mock_session(google::mock::MOCK_ACCOUNT_EMAIL),
+                );
+            } else {
+                tauri::async_runtime::block_on(async {
+                    if let Err(e) = token_store
+                        .initialize(app_data_dir, client_id, client_secret)
+                        .await
+                    {
+                        eprintln!("Failed to initialize token store: {}", e);
+                    }
+                });
+
+                // Finish a sign-in interrupted by the last exit
+                auth::resume_pending_auth(app.handle().clone());
+            }
+            perf::mark_startup(perf::StartupStage::TokensReady);
+
+            // Refresh access tokens ahead of expiry
+            auth::spawn_token_refresh(app.handle().clone());
+
+            // Start background connectivity probe
+            health::spawn_probe(app.handle().clone());
+            health::spawn_events(app.handle().clone());
+
+            // Tell the frontend when a failing Google host recovers
+            google::circuit::spawn_events(app.handle().clone());
+
+            // Start background plan sync and change notifications
+            planner::spawn_plan_watch(app.handle().clone());
+
+            // Roll over unfinished tasks at the end of the day
+            planner::spawn_rollover(app.handle().clone());
+
+            // Start background retry of unsent messages
+            outbox::spawn_flush(app.handle().clone());
+
+            // Generate the daily note at the scheduled morning time
+            daily_note::spawn_note_schedule(app.handle().clone());
+
+            // Run the morning and evening routines at their set times
+            routines::spawn_routines(app.handle().clone());
+
+            // Resume an interrupted mailbox import
+            sync::spawn_backfill(app.handle().clone());
+
+            // Receive plan updates and AI results pushed by the backend
+            push::spawn_push_stream(app.handle().clone());
 
+            perf::mark_startup(perf::StartupStage::SetupDone);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             auth::start_google_auth,
             auth::wait_for_oauth_callback,
+            auth::device::start_device_auth,
+            auth::device::poll_device_auth,
+            auth::ports::get_callback_ports,
+            auth::ports::set_callback_ports,
+            auth::capabilities::get_missing_scopes,
+            auth::capabilities::get_feature_access,
+            auth::capabilities::request_feature_access,
+            auth::os_gate::get_os_gate_settings,
+            auth::os_gate::set_os_gate_settings,
             auth::is_authenticated,
             auth::logout,
             // Backend token commands
             auth::store_backend_tokens,
             auth::get_backend_access_token,
             auth::get_backend_refresh_token,
+            auth::store_backend_push_secret,
             auth::clear_backend_tokens,
+            push::get_push_status,
             // Google API commands
             google::gmail::get_inbox_summary,
+            google::gmail::get_inbox_sample,
             google::gmail::get_thread_detail,
+            google::gmail::get_thread_details,
             google::gmail::open_thread_in_gmail,
+            google::gmail::report_read_position,
+            google::gmail::get_read_position,
+            google::gmail::get_thread_participants,
+            google::gmail::get_thread_timeline,
+            google::gmail::list_recent_attachments,
+            google::gmail::get_storage_insights,
+            google::gmail::get_thread_content,
+            attachments::download_attachment,
+            attachments::extract_attachment_text,
+            attachments::get_download_policy,
+            attachments::set_download_policy,
+            attachments::set_attachment_scanner,
+            export::export_thread,
+            google::mailbox::list_delegated_mailboxes,
+            google::mailbox::add_delegated_mailbox,
+            google::mailbox::remove_delegated_mailbox,
+            // API endpoint and recording commands
+            google::endpoints::get_api_endpoints,
+            google::endpoints::set_api_endpoints,
+            google::timeouts::get_api_timeouts,
+            google::timeouts::set_api_timeouts,
+            google::recording::get_request_recording,
+            google::recording::set_request_recording,
             google::calendar::get_today_events,
             google::calendar::get_events_range,
+            google::calendar::get_week_events,
+            google::calendar::get_working_location,
+            google::calendar::create_event_from_thread,
+            google::calendar::get_rsvp_rollup,
+            google::calendar::respond_to_event,
             google::tasks::get_task_lists,
             google::tasks::get_tasks,
             google::tasks::create_task,
@@ -125,11 +331,21 @@ pub fn run() {
             google::tasks::complete_task,
             google::tasks::reopen_task,
             google::tasks::delete_task,
+            google::tasks::link_task_thread,
+            google::tasks::get_task_thread,
+            google::tasks::archive_linked_thread,
+            google::tasks::get_smart_list,
+            google::tasks::set_task_metadata,
+            google::tasks::get_list_defaults,
+            google::tasks::set_list_defaults,
+            google::quota::get_quota_usage,
             // Theme commands
             theme::get_theme,
             theme::set_theme,
             theme::get_system_theme,
             theme::reset_theme,
+            settings::sync,
+            settings::get_settings_sync_status,
             // Notification commands
             notifications::check_notification_permission,
             notifications::request_notification_permission,
@@ -153,16 +369,171 @@ pub fn run() {
             processing::calculate_priority_score,
             processing::clean_snippet,
             processing::has_urgent_keywords,
+            processing::extract_links,
+            processing::sanitize_html,
+            privacy::get_image_allowlist,
+            privacy::allow_images_from,
+            privacy::block_images_from,
+            processing::suggest_quick_replies,
+            processing::get_week_settings,
+            processing::set_week_settings,
+            processing::get_week_info,
             processing::batch_process_tasks,
             processing::batch_process_emails,
             // Search commands (v0.5.13 performance layer)
             search::search_tasks,
             search::search_emails,
+            search::search_mailbox,
+            search::verify_index,
+            search::rebuild_index,
             // Data Pipeline commands (v0.5.20 - Note AI)
             data_pipeline::prepare_note_context,
             data_pipeline::validate_note_schema,
             data_pipeline::normalize_response,
             data_pipeline::prepare_batch_requests,
+            data_pipeline::execute_batch_requests,
+            // Daily note commands
+            daily_note::get_note_schedule,
+            daily_note::set_note_schedule,
+            daily_note::get_daily_note,
+            daily_note::save_daily_note,
+            daily_note::get_note_encryption,
+            daily_note::set_note_encryption,
+            routines::get_routine_settings,
+            routines::set_routine_settings,
+            routines::get_routine_runs,
+            routines::run_routine_now,
+            routines::get_tomorrow_preview,
+            // Planner commands
+            planner::auto_schedule_tasks,
+            planner::get_task_event_links,
+            planner::get_upcoming_items,
+            planner::get_week_items,
+            planner::get_plan_window_settings,
+            planner::set_plan_window_settings,
+            planner::get_plan_window,
+            planner::get_invitation_section,
+            planner::get_rollover_settings,
+            planner::set_rollover_settings,
+            planner::get_rollover_candidates,
+            planner::roll_over_tasks,
+            // AI provider commands
+            ai::get_ai_settings,
+            ai::set_ai_settings,
+            ai::set_ai_api_key,
+            ai::has_ai_api_key,
+            ai::generate_ai_note,
+            ai::generate_note_streamed,
+            ai::cancel_note_generation,
+            ai::usage::get_ai_usage,
+            ai::usage::get_ai_budget,
+            ai::usage::set_ai_budget,
+            planner::pin_item,
+            planner::unpin_item,
+            planner::get_plan_pins,
+            planner::apply_plan_pins,
+            planner::save_plan,
+            planner::get_plan_history,
+            planner::get_week_plan_reviews,
+            planner::get_inbox_sections,
+            planner::set_inbox_sections,
+            planner::section_threads,
+            digest::get_reading_digest,
+            digest::archive_digest,
+            // Diagnostics commands
+            diagnostics::run_checks,
+            diagnostics::auth_diagnostics,
+            // Health commands
+            health::get_health_status,
+            // Glance commands (menubar/widget mini-view)
+            glance::get_glance_data,
+            // Update commands
+            updates::get_update_channel,
+            updates::set_update_channel,
+            updates::check_for_updates,
+            updates::get_pending_release_notes,
+            updates::install_pending_update,
+            // Window commands
+            windows::open_window,
+            windows::close_window,
+            windows::register_window,
+            windows::list_windows,
+            windows::send_to_window,
+            windows::get_shared_state,
+            windows::set_shared_state,
+            // Storage commands
+            storage::storage_put,
+            storage::storage_get,
+            storage::storage_list,
+            storage::storage_remove,
+            storage::get_retention_policies,
+            storage::set_retention_policy,
+            storage::purge_now,
+            storage::export_all_data,
+            // iCal subscription commands
+            ical::list_ical_subscriptions,
+            ical::add_ical_subscription,
+            ical::remove_ical_subscription,
+            ical::refresh_ical_feeds,
+            // Account commands
+            account::purge_account_data,
+            policy::get_command_risk,
+            policy::request_confirmation,
+            setup::get_setup_state,
+            setup::advance_setup,
+            // Analytics commands
+            analytics::get_inbox_noise_report,
+            analytics::get_contact_timeline,
+            analytics::get_meeting_load_heatmap,
+            // Holiday commands
+            holidays::get_holiday_settings,
+            holidays::set_holiday_settings,
+            holidays::get_holidays,
+            holidays::get_holiday_notice,
+            // Travel commands
+            travel::get_travel_settings,
+            travel::set_travel_settings,
+            travel::set_routing_api_key,
+            travel::has_routing_api_key,
+            travel::get_leave_by_entries,
+            // App lock commands
+            app_lock::get_app_lock_status,
+            app_lock::set_app_lock_settings,
+            app_lock::set_app_lock_passcode,
+            app_lock::lock_app,
+            app_lock::record_app_activity,
+            app_lock::unlock_with_passcode,
+            app_lock::unlock_with_biometric,
+            // Sync scheduling commands
+            sync::get_sync_status,
+            sync::request_sync,
+            sync::start_backfill,
+            sync::pause_backfill,
+            sync::get_backfill_status,
+            // Triage session commands
+            triage::start_session,
+            triage::next_item,
+            triage::act,
+            triage::end_session,
+            // Rule commands
+            rules::suggest_rules,
+            rules::adopt_rule,
+            rules::list_rules,
+            rules::delete_rule,
+            // Outbox commands
+            outbox::send_message,
+            outbox::list_outbox,
+            outbox::retry_outbox_item,
+            outbox::discard_outbox_item,
+            // Performance commands
+            perf::get_performance_report,
+            perf::reset_performance_stats,
+            perf::get_startup_profile,
+            // Automation commands
+            automation::list_scripts,
+            automation::set_script_enabled,
+            // Command palette commands
+            commands::list_available_actions,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
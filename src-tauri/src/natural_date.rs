@@ -0,0 +1,450 @@
+//! Natural-language date/time extraction
+//!
+//! Finds proposed dates and times in free text, relative to a reference time:
+//! - Relative days: "today", "tonight", "tomorrow", "day after tomorrow"
+//! - Weekdays: "friday", "next tuesday", "this thu"
+//! - Calendar dates: "march 5", "5th of march", "mar 5 2027", "2026-03-05"
+//! - Times: "3pm", "3:30 pm", "15:00", "noon", "at 4"
+//!
+//! A time is attached to the nearest date a few words before or after it
+//! ("tomorrow at 3pm", "3pm tomorrow"). English only.
+//!
+//! `due_date` finds deadlines: mentions right after "due", "by", "before", ...
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// Words between a date and the time it belongs to
+const PAIRING_DISTANCE: usize = 3;
+/// Time assumed for "tonight" without an explicit time
+const TONIGHT_HOUR: u32 = 20;
+/// Words introducing a deadline ("due friday", "pay by march 5")
+const DUE_WORDS: &[&str] = &["due", "by", "before", "until", "deadline", "payable"];
+/// Words between a deadline word and its date
+const DUE_DISTANCE: usize = 2;
+
+/// A date (and optionally time) mentioned in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateMention {
+    pub start: NaiveDateTime,
+    /// False when only a day was mentioned (start is midnight)
+    pub has_time: bool,
+    /// The words the mention was parsed from
+    pub text: String,
+}
+
+/// A parsed token span
+struct Span<T> {
+    value: T,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == ':' || c == '-'))
+        .map(|t| t.trim_matches(|c| c == ':' || c == '-').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn weekday(token: &str) -> Option<Weekday> {
+    let day = match token {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+fn month(token: &str) -> Option<u32> {
+    let month = match token {
+        "jan" | "january" => 1,
+        "feb" | "february" => 2,
+        "mar" | "march" => 3,
+        "apr" | "april" => 4,
+        // "may" is also a verb; only accepted next to a day number
+        "may" => 5,
+        "jun" | "june" => 6,
+        "jul" | "july" => 7,
+        "aug" | "august" => 8,
+        "sep" | "sept" | "september" => 9,
+        "oct" | "october" => 10,
+        "nov" | "november" => 11,
+        "dec" | "december" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+/// Day of month, with optional ordinal suffix ("5", "5th", "21st")
+fn day_of_month(token: &str) -> Option<u32> {
+    let digits = token
+        .strip_suffix("st")
+        .or_else(|| token.strip_suffix("nd"))
+        .or_else(|| token.strip_suffix("rd"))
+        .or_else(|| token.strip_suffix("th"))
+        .unwrap_or(token);
+    let day: u32 = digits.parse().ok()?;
+    (1..=31).contains(&day).then_some(day)
+}
+
+fn year(token: Option<&String>) -> Option<i32> {
+    let year: i32 = token?.parse().ok()?;
+    (2000..=2100).contains(&year).then_some(year)
+}
+
+/// Next occurrence of `target` after `today` (a week ahead if it is today)
+fn next_weekday(today: NaiveDate, target: Weekday) -> NaiveDate {
+    let ahead = (target.num_days_from_monday() as i64
+        - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7);
+    today + Duration::days(if ahead == 0 { 7 } else { ahead })
+}
+
+/// Month/day without a year: this year, or next year if already past
+fn upcoming_date(today: NaiveDate, month: u32, day: u32, year: Option<i32>) -> Option<NaiveDate> {
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year, month, day);
+    }
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date < today {
+        NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+    } else {
+        Some(date)
+    }
+}
+
+/// Parse a date starting at token `i`; returns the date, tokens consumed and
+/// whether it means "tonight"
+fn parse_date(tokens: &[String], i: usize, today: NaiveDate) -> Option<(NaiveDate, usize, bool)> {
+    let token = tokens[i].as_str();
+    let next = tokens.get(i + 1).map(String::as_str);
+
+    match token {
+        "today" => return Some((today, 1, false)),
+        "tonight" => return Some((today, 1, true)),
+        "tomorrow" | "tmrw" | "tmr" => return Some((today + Duration::days(1), 1, false)),
+        "day"
+            if next == Some("after")
+                && tokens.get(i + 2).map(String::as_str) == Some("tomorrow") =>
+        {
+            return Some((today + Duration::days(2), 3, false));
+        }
+        "next" | "this" | "on" => {
+            if let Some(day) = next.and_then(weekday) {
+                return Some((next_weekday(today, day), 2, false));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(day) = weekday(token) {
+        return Some((next_weekday(today, day), 1, false));
+    }
+
+    // ISO date
+    if token.len() == 10 && token.as_bytes()[4] == b'-' {
+        if let Ok(date) = NaiveDate::parse_from_str(token, "%Y-%m-%d") {
+            return Some((date, 1, false));
+        }
+    }
+
+    // "march 5", "march 5th 2027"
+    if let (Some(m), Some(d)) = (month(token), next.and_then(day_of_month)) {
+        let y = year(tokens.get(i + 2));
+        let date = upcoming_date(today, m, d, y)?;
+        return Some((date, if y.is_some() { 3 } else { 2 }, false));
+    }
+
+    // "5 march", "5th of march"
+    if let Some(d) = day_of_month(token) {
+        let (month_at, consumed) = if next == Some("of") {
+            (i + 2, 3)
+        } else {
+            (i + 1, 2)
+        };
+        if let Some(m) = tokens.get(month_at).and_then(|t| month(t)) {
+            let y = year(tokens.get(month_at + 1));
+            let date = upcoming_date(today, m, d, y)?;
+            return Some((date, consumed + y.is_some() as usize, false));
+        }
+    }
+
+    None
+}
+
+/// Hour and minute from "3", "3:30" or "15:00"
+fn clock(token: &str) -> Option<(u32, u32)> {
+    let (hour, minute) = match token.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse().ok()?, m.parse().ok()?),
+        Some(_) => return None,
+        None => (token.parse().ok()?, 0),
+    };
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+fn with_meridiem(hour: u32, minute: u32, meridiem: &str) -> Option<NaiveTime> {
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    let hour = match meridiem {
+        "am" => hour % 12,
+        "pm" => hour % 12 + 12,
+        _ => return None,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Parse a time starting at token `i`; returns the time and tokens consumed
+fn parse_time(tokens: &[String], i: usize) -> Option<(NaiveTime, usize)> {
+    let token = tokens[i].as_str();
+    let next = tokens.get(i + 1).map(String::as_str);
+
+    match token {
+        "noon" | "midday" => return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1)),
+        "midnight" => return Some((NaiveTime::from_hms_opt(0, 0, 0)?, 1)),
+        _ => {}
+    }
+
+    // "3pm", "3:30am"
+    for meridiem in ["am", "pm"] {
+        if let Some(digits) = token.strip_suffix(meridiem) {
+            let (hour, minute) = clock(digits)?;
+            return Some((with_meridiem(hour, minute, meridiem)?, 1));
+        }
+    }
+
+    let (hour, minute) = clock(token)?;
+
+    // "3 pm"
+    if let Some(meridiem) = next.filter(|n| *n == "am" || *n == "pm") {
+        return Some((with_meridiem(hour, minute, meridiem)?, 2));
+    }
+
+    // "15:00", "9:30"
+    if token.contains(':') {
+        return Some((NaiveTime::from_hms_opt(hour, minute, 0)?, 1));
+    }
+
+    // "at 4": assume business hours (1-7 is afternoon)
+    if i > 0 && tokens[i - 1] == "at" && (1..=12).contains(&hour) {
+        let hour = if hour <= 7 { hour + 12 } else { hour };
+        return Some((NaiveTime::from_hms_opt(hour, 0, 0)?, 1));
+    }
+
+    None
+}
+
+/// Extract every date/time mentioned in `text`, in order of appearance
+pub fn extract(text: &str, now: NaiveDateTime) -> Vec<DateMention> {
+    let (_, mentions) = extract_positions(text, now);
+    mentions.into_iter().map(|(_, mention)| mention).collect()
+}
+
+/// Tokens of `text` and its mentions with the token index they start at
+fn extract_positions(text: &str, now: NaiveDateTime) -> (Vec<String>, Vec<(usize, DateMention)>) {
+    let tokens = tokenize(text);
+    let today = now.date();
+
+    let mut dates: Vec<Span<(NaiveDate, bool)>> = Vec::new();
+    let mut times: Vec<Span<NaiveTime>> = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some((date, consumed, tonight)) = parse_date(&tokens, i, today) {
+            dates.push(Span {
+                value: (date, tonight),
+                start: i,
+                end: i + consumed,
+            });
+            i += consumed;
+        } else if let Some((time, consumed)) = parse_time(&tokens, i) {
+            times.push(Span {
+                value: time,
+                start: i,
+                end: i + consumed,
+            });
+            i += consumed;
+        } else {
+            i += 1;
+        }
+    }
+
+    let words = |start: usize, end: usize| tokens[start..end].join(" ");
+    let mut used_times = vec![false; times.len()];
+    let mut mentions: Vec<(usize, DateMention)> = Vec::new();
+
+    for date in &dates {
+        // Closest unused time within reach, preferring one after the date on ties
+        let paired = times
+            .iter()
+            .enumerate()
+            .filter(|(t, _)| !used_times[*t])
+            .filter_map(|(t, time)| {
+                let distance = if time.start >= date.end {
+                    time.start - date.end
+                } else if time.end <= date.start {
+                    date.start - time.end + 1
+                } else {
+                    return None;
+                };
+                (distance <= PAIRING_DISTANCE).then_some((distance, t))
+            })
+            .min()
+            .map(|(_, t)| t);
+
+        let (day, tonight) = date.value;
+        let mention = match paired {
+            Some(t) => {
+                used_times[t] = true;
+                let time = &times[t];
+                DateMention {
+                    start: day.and_time(time.value),
+                    has_time: true,
+                    text: words(date.start.min(time.start), date.end.max(time.end)),
+                }
+            }
+            None if tonight => DateMention {
+                start: day.and_hms_opt(TONIGHT_HOUR, 0, 0).unwrap_or_default(),
+                has_time: true,
+                text: words(date.start, date.end),
+            },
+            None => DateMention {
+                start: day.and_hms_opt(0, 0, 0).unwrap_or_default(),
+                has_time: false,
+                text: words(date.start, date.end),
+            },
+        };
+        mentions.push((date.start, mention));
+    }
+
+    // Times without a date: the next occurrence of that time
+    for (t, time) in times.iter().enumerate() {
+        if used_times[t] {
+            continue;
+        }
+        let day = if time.value > now.time() {
+            today
+        } else {
+            today + Duration::days(1)
+        };
+        mentions.push((
+            time.start,
+            DateMention {
+                start: day.and_time(time.value),
+                has_time: true,
+                text: words(time.start, time.end),
+            },
+        ));
+    }
+
+    mentions.sort_by_key(|(position, _)| *position);
+    (tokens, mentions)
+}
+
+/// The first proposed date/time after `now`, preferring mentions with a time
+pub fn first_proposal(text: &str, now: NaiveDateTime) -> Option<DateMention> {
+    let upcoming: Vec<DateMention> = extract(text, now)
+        .into_iter()
+        .filter(|m| m.start >= now || (!m.has_time && m.start.date() >= now.date()))
+        .collect();
+
+    upcoming
+        .iter()
+        .find(|m| m.has_time)
+        .or(upcoming.first())
+        .cloned()
+}
+
+/// The first deadline in `text`: a date right after a word like "due" or "by"
+pub fn due_date(text: &str, now: NaiveDateTime) -> Option<DateMention> {
+    let (tokens, mentions) = extract_positions(text, now);
+    mentions
+        .into_iter()
+        .find(|(position, _)| {
+            tokens[position.saturating_sub(DUE_DISTANCE + 1)..*position]
+                .iter()
+                .any(|t| DUE_WORDS.contains(&t.as_str()))
+        })
+        .map(|(_, mention)| mention)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday 2026-01-14 09:00
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_relative_and_weekday_dates() {
+        let mentions = extract("Can we meet tomorrow at 3pm or next Monday 10:30?", now());
+        assert_eq!(mentions.len(), 2);
+        assert_eq!(mentions[0].start, at(2026, 1, 15, 15, 0));
+        assert_eq!(mentions[0].text, "tomorrow at 3pm");
+        assert_eq!(mentions[1].start, at(2026, 1, 19, 10, 30));
+
+        // Same weekday means next week
+        let mentions = extract("wednesday works", now());
+        assert_eq!(mentions[0].start, at(2026, 1, 21, 0, 0));
+        assert!(!mentions[0].has_time);
+    }
+
+    #[test]
+    fn test_calendar_dates_and_times() {
+        let mentions = extract("How about 3 pm on March 5th? Or 2026-02-02 at noon", now());
+        assert_eq!(mentions[0].start, at(2026, 3, 5, 15, 0));
+        assert_eq!(mentions[1].start, at(2026, 2, 2, 12, 0));
+
+        // Past month/day rolls over to next year
+        let mentions = extract("the 5th of january at 4", now());
+        assert_eq!(mentions[0].start, at(2027, 1, 5, 16, 0));
+    }
+
+    #[test]
+    fn test_first_proposal() {
+        let proposal = first_proposal(
+            "Sent yesterday. Free Friday? Say 8:00 or after lunch",
+            now(),
+        );
+        assert_eq!(proposal.unwrap().start, at(2026, 1, 16, 8, 0));
+
+        // A bare time that already passed today means tomorrow
+        let proposal = first_proposal("call at 8am", now()).unwrap();
+        assert_eq!(proposal.start, at(2026, 1, 15, 8, 0));
+
+        assert!(first_proposal("no dates here, may be later", now()).is_none());
+    }
+
+    #[test]
+    fn test_due_date() {
+        let due = due_date("Invoice #1042. Payment due Friday, thanks!", now()).unwrap();
+        assert_eq!(
+            due.start.date(),
+            NaiveDate::from_ymd_opt(2026, 1, 16).unwrap()
+        );
+        let due = due_date("Sent on monday, please pay by the 20th of january", now());
+        assert_eq!(
+            due.map(|m| m.start.date()),
+            NaiveDate::from_ymd_opt(2026, 1, 20)
+        );
+        assert_eq!(due_date("Lunch on friday?", now()), None);
+    }
+}
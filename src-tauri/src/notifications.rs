@@ -3,11 +3,54 @@
 //! Provides Tauri commands for sending native OS notifications.
 //! Uses tauri-plugin-notification for cross-platform support.
 
+use crate::auth::TokenStore;
+use crate::storage::{LocalStorage, NOTIFICATION_HISTORY};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::State;
 use tauri_plugin_notification::NotificationExt;
 
+/// Tells apart history entries recorded in the same millisecond
+static HISTORY_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// History entry ID: the time it was shown, then a sequence number
+fn history_id(now_ms: i64) -> String {
+    format!(
+        "{}-{:06}",
+        now_ms,
+        HISTORY_SEQ.fetch_add(1, Ordering::Relaxed) % 1_000_000
+    )
+}
+
+/// Record a shown notification in the signed-in account's history collection
+pub(crate) async fn record_history(
+    token_store: &TokenStore,
+    storage: &LocalStorage,
+    notification_type: Option<&str>,
+    title: &str,
+    body: Option<&str>,
+) {
+    // History is per account; nothing to record while signed out
+    let Ok(account) = token_store.account_context().await else {
+        return;
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let entry = serde_json::json!({
+        "type": notification_type,
+        "title": title,
+        "body": body,
+        "shown_at_ms": now,
+    });
+
+    if let Err(e) = storage.put(&account, NOTIFICATION_HISTORY, &history_id(now), entry) {
+        eprintln!("Failed to record notification history: {}", e);
+    }
+}
+
 /// Check if notification permission is granted
 #[tauri::command]
 pub async fn check_notification_permission(app: tauri::AppHandle) -> Result<bool, String> {
+    crate::perf::trace_command!();
     app.notification()
         .permission_state()
         .map(|state| state == tauri_plugin_notification::PermissionState::Granted)
@@ -17,6 +60,7 @@ pub async fn check_notification_permission(app: tauri::AppHandle) -> Result<bool
 /// Request notification permission from user
 #[tauri::command]
 pub async fn request_notification_permission(app: tauri::AppHandle) -> Result<String, String> {
+    crate::perf::trace_command!();
     app.notification()
         .request_permission()
         .map(|state| match state {
@@ -39,10 +83,13 @@ pub async fn request_notification_permission(app: tauri::AppHandle) -> Result<St
 #[tauri::command]
 pub async fn send_native_notification(
     app: tauri::AppHandle,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
     title: String,
     body: Option<String>,
     sound: Option<String>,
 ) -> Result<(), String> {
+    crate::perf::trace_command!();
     let mut builder = app.notification().builder().title(&title);
 
     if let Some(body_text) = &body {
@@ -53,7 +100,9 @@ pub async fn send_native_notification(
         builder = builder.sound(sound_name);
     }
 
-    builder.show().map_err(|e| e.to_string())
+    builder.show().map_err(|e| e.to_string())?;
+    record_history(&token_store, &storage, None, &title, body.as_deref()).await;
+    Ok(())
 }
 
 /// Send a notification with specific type styling
@@ -62,10 +111,13 @@ pub async fn send_native_notification(
 #[tauri::command]
 pub async fn send_typed_notification(
     app: tauri::AppHandle,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
     notification_type: String,
     title: String,
     body: Option<String>,
 ) -> Result<(), String> {
+    crate::perf::trace_command!();
     // Map notification types to macOS system sounds
     let sound = match notification_type.as_str() {
         "task_due" => Some("Hero"),
@@ -86,5 +138,26 @@ pub async fn send_typed_notification(
         builder = builder.sound(sound_name);
     }
 
-    builder.show().map_err(|e| e.to_string())
+    builder.show().map_err(|e| e.to_string())?;
+    record_history(
+        &token_store,
+        &storage,
+        Some(&notification_type),
+        &title,
+        body.as_deref(),
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_ids_are_unique() {
+        let ids: std::collections::HashSet<String> = (0..100).map(|_| history_id(1_000)).collect();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.iter().all(|id| id.starts_with("1000-")));
+    }
 }
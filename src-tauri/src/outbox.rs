@@ -0,0 +1,435 @@
+//! Outbox for outgoing mail
+//!
+//! `send_message` persists a composed message in local storage before
+//! handing it to Gmail (`messages.send`), so a send that fails (flaky Wi-Fi,
+//! expired token, Google outage) never loses the text. Failed items stay in
+//! the outbox and are retried in the background with exponential backoff;
+//! after `MAX_ATTEMPTS` they are marked failed and wait for
+//! `retry_outbox_item` or `discard_outbox_item`. Sent items leave the outbox.
+
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::capabilities::{self, Feature};
+use crate::auth::TokenStore;
+use crate::events::DataEvent;
+use crate::google::{invalidation, GoogleClient, GMAIL_API_BASE};
+use crate::policy;
+use crate::storage::{LocalStorage, OUTBOX};
+use crate::sync::SyncScheduler;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Interval of the background outbox flush
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+/// Delay before the first retry; doubles with every failed attempt
+const RETRY_BASE_MS: i64 = 30_000;
+const MAX_RETRY_DELAY_MS: i64 = 30 * 60_000;
+/// Automatic attempts before an item is marked failed
+const MAX_ATTEMPTS: u32 = 8;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A plain-text message written in the app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposedMessage {
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    /// Thread the message replies to
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    /// `Message-ID` header of the message being answered
+    #[serde(default)]
+    pub in_reply_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Waiting for its next attempt
+    Queued,
+    /// Out of automatic attempts
+    Failed,
+    /// Accepted by Gmail (no longer stored)
+    Sent,
+}
+
+/// A message in the outbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub id: String,
+    pub message: ComposedMessage,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at_ms: i64,
+    pub last_error: Option<String>,
+    pub created_at_ms: i64,
+    /// Gmail message ID once sent
+    pub sent_message_id: Option<String>,
+}
+
+/// Serializes send attempts so an item is never sent twice
+#[derive(Default)]
+pub struct OutboxState(tokio::sync::Mutex<()>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SentMessage {
+    id: String,
+    thread_id: Option<String>,
+}
+
+// ============================================================================
+// Message Encoding
+// ============================================================================
+
+const BASE64_STANDARD: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL_SAFE: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            out.push_str(&"=".repeat(3 - chunk.len()));
+        }
+    }
+    out
+}
+
+/// RFC 2047 encoded-word for non-ASCII header values
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!(
+            "=?UTF-8?B?{}?=",
+            base64(value.as_bytes(), BASE64_STANDARD, true)
+        )
+    }
+}
+
+/// Strip line breaks so a header value can't inject headers
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// RFC 2822 message as Gmail's `raw` field expects it (base64url)
+fn raw_message(message: &ComposedMessage) -> String {
+    let mut headers = vec![format!("To: {}", header_value(&message.to.join(", ")))];
+    if !message.cc.is_empty() {
+        headers.push(format!("Cc: {}", header_value(&message.cc.join(", "))));
+    }
+    headers.push(format!(
+        "Subject: {}",
+        encode_header(&header_value(&message.subject))
+    ));
+    if let Some(reply_to) = &message.in_reply_to {
+        headers.push(format!("In-Reply-To: {}", header_value(reply_to)));
+        headers.push(format!("References: {}", header_value(reply_to)));
+    }
+    headers.push("MIME-Version: 1.0".to_string());
+    headers.push("Content-Type: text/plain; charset=\"UTF-8\"".to_string());
+    headers.push("Content-Transfer-Encoding: 8bit".to_string());
+
+    let body = message.body.replace("\r\n", "\n").replace('\n', "\r\n");
+    let raw = format!("{}\r\n\r\n{}", headers.join("\r\n"), body);
+    base64(raw.as_bytes(), BASE64_URL_SAFE, false)
+}
+
+// ============================================================================
+// Sending
+// ============================================================================
+
+/// Delay before retry number `attempts` (1-based)
+fn retry_delay_ms(attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE_MS << exponent).min(MAX_RETRY_DELAY_MS)
+}
+
+fn load_item(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    id: &str,
+) -> Result<Option<OutboxItem>, String> {
+    storage
+        .get(account, OUTBOX, id)?
+        .map(|record| {
+            serde_json::from_value(record.value)
+                .map_err(|e| format!("Invalid outbox item {}: {}", id, e))
+        })
+        .transpose()
+}
+
+fn save_item(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    item: &OutboxItem,
+) -> Result<(), String> {
+    let value = serde_json::to_value(item)
+        .map_err(|e| format!("Failed to serialize outbox item: {}", e))?;
+    storage.put(account, OUTBOX, &item.id, value)
+}
+
+/// Try to send one item, updating or removing its stored record
+///
+/// Callers must hold the `OutboxState` lock.
+async fn attempt(
+    app: &AppHandle,
+    account: &AccountContext,
+    mut item: OutboxItem,
+) -> Result<OutboxItem, String> {
+    let token_store = app.state::<TokenStore>();
+    let client = app.state::<GoogleClient>();
+    let storage = app.state::<LocalStorage>();
+
+    let mut body = serde_json::json!({ "raw": raw_message(&item.message) });
+    if let Some(thread_id) = &item.message.thread_id {
+        body["threadId"] = serde_json::json!(thread_id);
+    }
+    let url = format!("{}/users/me/messages/send", GMAIL_API_BASE);
+    let now = chrono::Utc::now().timestamp_millis();
+    item.attempts += 1;
+
+    match client
+        .post::<SentMessage, _>(&url, &token_store, &body)
+        .await
+    {
+        Ok(sent) => {
+            storage.remove(account, OUTBOX, &item.id)?;
+            item.status = OutboxStatus::Sent;
+            item.last_error = None;
+            if let Some(thread_id) = sent.thread_id.or(item.message.thread_id.clone()) {
+                invalidation::mutated(
+                    app,
+                    DataEvent::ThreadsModified {
+                        thread_ids: vec![thread_id],
+                    },
+                )
+                .await;
+            }
+            item.sent_message_id = Some(sent.id);
+        }
+        Err(e) => {
+            item.last_error = Some(e.to_string());
+            if item.attempts >= MAX_ATTEMPTS {
+                item.status = OutboxStatus::Failed;
+            } else {
+                item.status = OutboxStatus::Queued;
+                item.next_attempt_at_ms = now + retry_delay_ms(item.attempts);
+            }
+            save_item(&storage, account, &item)?;
+        }
+    }
+    Ok(item)
+}
+
+/// Send every queued item whose retry time has come
+async fn flush(app: &AppHandle) -> Result<(), String> {
+    let Ok(account) = app.state::<TokenStore>().account_context().await else {
+        return Ok(());
+    };
+    let outbox = app.state::<OutboxState>();
+    let _sending = outbox.0.lock().await;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut due: Vec<OutboxItem> = app
+        .state::<LocalStorage>()
+        .list(&account, OUTBOX)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value::<OutboxItem>(record.value).ok())
+        .filter(|item| item.status == OutboxStatus::Queued && item.next_attempt_at_ms <= now)
+        .collect();
+    due.sort_by_key(|item| item.created_at_ms);
+
+    for item in due {
+        let id = item.id.clone();
+        if let Err(e) = attempt(app, &account, item).await {
+            eprintln!("Failed to update outbox item {}: {}", id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Start the background retry of queued messages
+pub fn spawn_flush(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            app.state::<SyncScheduler>().wait(FLUSH_INTERVAL).await;
+            if let Err(e) = flush(&app).await {
+                eprintln!("Outbox flush failed: {}", e);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Store a message in the outbox and try to send it right away
+///
+/// A failed send is not an error: the returned item is queued for retry.
+#[tauri::command]
+pub async fn send_message(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    outbox: State<'_, OutboxState>,
+    message: ComposedMessage,
+) -> Result<OutboxItem, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if message.to.iter().all(|to| to.trim().is_empty()) {
+        return Err("A message needs at least one recipient".to_string());
+    }
+    capabilities::require(&token_store, Feature::Send).await?;
+    let account = token_store.account_context().await?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let item = OutboxItem {
+        id: format!("out-{}", now),
+        message,
+        status: OutboxStatus::Queued,
+        attempts: 0,
+        next_attempt_at_ms: now,
+        last_error: None,
+        created_at_ms: now,
+        sent_message_id: None,
+    };
+    // Persisted before the first attempt, so nothing is lost if it fails
+    save_item(&storage, &account, &item)?;
+
+    let _sending = outbox.0.lock().await;
+    attempt(&app, &account, item).await
+}
+
+/// List messages waiting in the outbox, oldest first
+#[tauri::command]
+pub async fn list_outbox(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<Vec<OutboxItem>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+
+    let mut items: Vec<OutboxItem> = storage
+        .list(&account, OUTBOX)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok())
+        .collect();
+    items.sort_by_key(|item| item.created_at_ms);
+    Ok(items)
+}
+
+/// Send an outbox item now, also when it was marked failed
+#[tauri::command]
+pub async fn retry_outbox_item(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    outbox: State<'_, OutboxState>,
+    id: String,
+) -> Result<OutboxItem, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+
+    let _sending = outbox.0.lock().await;
+    let mut item = load_item(&storage, &account, &id)?
+        .ok_or_else(|| format!("Outbox item not found: {}", id))?;
+    // A manual retry gets a fresh set of automatic attempts
+    if item.status == OutboxStatus::Failed {
+        item.attempts = 0;
+    }
+    attempt(&app, &account, item).await
+}
+
+/// Drop a message from the outbox without sending it
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn discard_outbox_item(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    outbox: State<'_, OutboxState>,
+    id: String,
+    confirmation: Option<String>,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    policy::authorize(&app, "discard_outbox_item", &id, confirmation.as_deref()).await?;
+    let account = token_store.account_context().await?;
+
+    let _sending = outbox.0.lock().await;
+    storage.remove(&account, OUTBOX, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_message_and_backoff() {
+        assert_eq!(base64(b"Ma", BASE64_STANDARD, true), "TWE=");
+        assert_eq!(base64(b"\xfb\xff", BASE64_URL_SAFE, false), "-_8");
+
+        let message = ComposedMessage {
+            to: vec!["jane@example.com".to_string()],
+            cc: Vec::new(),
+            subject: "Re: Año nuevo\r\nBcc: evil@example.com".to_string(),
+            body: "Hi Jane,\nsee you".to_string(),
+            thread_id: Some("t1".to_string()),
+            in_reply_to: Some("<m1@mail.example.com>".to_string()),
+        };
+        let raw = raw_message(&message);
+        assert!(!raw.contains(['+', '/', '=']));
+
+        // Decode with the standard alphabet to check the headers
+        let decoded: Vec<u8> = {
+            let values: Vec<u32> = raw
+                .bytes()
+                .map(|c| BASE64_URL_SAFE.iter().position(|a| *a == c).unwrap() as u32)
+                .collect();
+            values
+                .chunks(4)
+                .flat_map(|chunk| {
+                    let n = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |n, (i, v)| n | v << (18 - 6 * i));
+                    (0..chunk.len() - 1).map(move |i| (n >> (16 - 8 * i)) as u8)
+                })
+                .collect()
+        };
+        let text = String::from_utf8(decoded).unwrap();
+        assert!(text.starts_with("To: jane@example.com\r\nSubject: =?UTF-8?B?"));
+        assert!(!text.contains("\r\nBcc:"));
+        assert!(text.contains("In-Reply-To: <m1@mail.example.com>\r\n"));
+        assert!(text.ends_with("\r\n\r\nHi Jane,\r\nsee you"));
+
+        assert_eq!(retry_delay_ms(1), 30_000);
+        assert_eq!(retry_delay_ms(3), 120_000);
+        assert_eq!(retry_delay_ms(MAX_ATTEMPTS), MAX_RETRY_DELAY_MS);
+    }
+}
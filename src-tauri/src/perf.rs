@@ -0,0 +1,546 @@
+//! Per-command performance tracing
+//!
+//! Every `#[tauri::command]` starts with `perf::trace_command!()`, which holds
+//! a `CommandTimer` until the command returns (for async commands, until the
+//! future completes). Durations feed a per-command histogram and a ring of
+//! recent calls, reported by `get_performance_report` to find the backend
+//! calls that make the UI feel sluggish.
+//!
+//! The pure `processing` helpers that batch commands also call per item
+//! (priority scoring, snippet cleaning, relative times, urgency checks) are
+//! left untraced so batch calls aren't counted once per email.
+//!
+//! Cold start is profiled separately: `mark_startup` records when each
+//! `StartupStage` is first reached, measured from the start of `run`, and
+//! `get_startup_profile` compares the stages against their budgets and
+//! against the profiles of previous launches (kept with the app version, so
+//! regressions show up release to release).
+
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// Histogram bucket upper bounds in milliseconds (the last bucket is unbounded)
+const BUCKET_BOUNDS_MS: [u64; 11] = [1, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000];
+/// Calls kept for the "slowest recent" list
+const RECENT_CALLS: usize = 500;
+/// Slowest recent calls included in a report
+const SLOWEST_LIMIT: usize = 20;
+
+static REGISTRY: LazyLock<PerfRegistry> = LazyLock::new(PerfRegistry::default);
+
+/// Time the enclosing command until it returns
+///
+/// The command name is taken from the enclosing function.
+macro_rules! trace_command {
+    () => {
+        let _perf_timer = {
+            fn f() {}
+            $crate::perf::CommandTimer::start($crate::perf::command_name(
+                std::any::type_name_of_val(&f),
+            ))
+        };
+    };
+}
+pub(crate) use trace_command;
+
+/// Command name from the type name of a fn item declared in its body
+/// (`crate::module::command::{{closure}}::f`)
+pub fn command_name(type_name: &'static str) -> &'static str {
+    type_name
+        .trim_end_matches("::f")
+        .rsplit("::")
+        .find(|segment| !segment.starts_with('{'))
+        .unwrap_or(type_name)
+}
+
+/// Records the elapsed time of a command when dropped
+pub struct CommandTimer {
+    command: &'static str,
+    started: Instant,
+}
+
+impl CommandTimer {
+    pub fn start(command: &'static str) -> Self {
+        Self {
+            command,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for CommandTimer {
+    fn drop(&mut self) {
+        REGISTRY.record(self.command, self.started.elapsed());
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct CommandStats {
+    calls: u64,
+    total: Duration,
+    max: Duration,
+    /// One count per `BUCKET_BOUNDS_MS` entry plus the overflow bucket
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+#[derive(Debug, Clone)]
+struct CallSample {
+    command: &'static str,
+    duration: Duration,
+    at_ms: i64,
+}
+
+#[derive(Default)]
+struct PerfRegistry {
+    stats: Mutex<HashMap<&'static str, CommandStats>>,
+    recent: Mutex<VecDeque<CallSample>>,
+}
+
+impl PerfRegistry {
+    fn record(&self, command: &'static str, duration: Duration) {
+        let millis = duration.as_millis() as u64;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| millis <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+
+        if let Ok(mut stats) = self.stats.lock() {
+            let entry = stats.entry(command).or_default();
+            entry.calls += 1;
+            entry.total += duration;
+            entry.max = entry.max.max(duration);
+            entry.buckets[bucket] += 1;
+        }
+
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_CALLS {
+                recent.pop_front();
+            }
+            recent.push_back(CallSample {
+                command,
+                duration,
+                at_ms: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+    }
+
+    fn report(&self) -> PerformanceReport {
+        let mut commands: Vec<CommandPerf> = self
+            .stats
+            .lock()
+            .map(|stats| {
+                stats
+                    .iter()
+                    .map(|(command, s)| command_perf(command, s))
+                    .collect()
+            })
+            .unwrap_or_default();
+        commands.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+        let mut slowest_recent: Vec<SlowCall> = self
+            .recent
+            .lock()
+            .map(|recent| {
+                recent
+                    .iter()
+                    .map(|c| SlowCall {
+                        command: c.command.to_string(),
+                        duration_ms: as_ms(c.duration),
+                        at_ms: c.at_ms,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        slowest_recent.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+        slowest_recent.truncate(SLOWEST_LIMIT);
+
+        PerformanceReport {
+            commands,
+            slowest_recent,
+        }
+    }
+
+    fn reset(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.clear();
+        }
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.clear();
+        }
+    }
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Upper bound of the bucket holding the `quantile` call (None if unbounded)
+fn bucket_quantile(stats: &CommandStats, quantile: f64) -> Option<u64> {
+    let target = (stats.calls as f64 * quantile).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (i, count) in stats.buckets.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return BUCKET_BOUNDS_MS.get(i).copied();
+        }
+    }
+    None
+}
+
+fn command_perf(command: &str, stats: &CommandStats) -> CommandPerf {
+    CommandPerf {
+        command: command.to_string(),
+        calls: stats.calls,
+        total_ms: as_ms(stats.total),
+        mean_ms: as_ms(stats.total) / stats.calls.max(1) as f64,
+        max_ms: as_ms(stats.max),
+        p50_le_ms: bucket_quantile(stats, 0.5),
+        p95_le_ms: bucket_quantile(stats, 0.95),
+        histogram: stats
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| HistogramBucket {
+                le_ms: BUCKET_BOUNDS_MS.get(i).copied(),
+                count: *count,
+            })
+            .collect(),
+    }
+}
+
+// ============================================================================
+// Report Types
+// ============================================================================
+
+/// Calls in a duration bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Upper bound in milliseconds (None for the overflow bucket)
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+/// Timing summary of one command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPerf {
+    pub command: String,
+    pub calls: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Bucket bound containing the median (None if above the largest bound)
+    pub p50_le_ms: Option<u64>,
+    pub p95_le_ms: Option<u64>,
+    pub histogram: Vec<HistogramBucket>,
+}
+
+/// A recent slow call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowCall {
+    pub command: String,
+    pub duration_ms: f64,
+    pub at_ms: i64,
+}
+
+/// Command timings since startup (or the last reset)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    /// Sorted by total time spent, highest first
+    pub commands: Vec<CommandPerf>,
+    pub slowest_recent: Vec<SlowCall>,
+}
+
+// ============================================================================
+// Startup Profile
+// ============================================================================
+
+const PERF_STORE_FILE: &str = "perf.json";
+const STARTUP_HISTORY_KEY: &str = "startup_history";
+/// Launches kept for comparison
+const STARTUP_HISTORY_LEN: usize = 10;
+
+static STARTUP: LazyLock<StartupRecorder> = LazyLock::new(StartupRecorder::default);
+
+/// Milestones of a cold start, in the order they are expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupStage {
+    /// `.env` read and OAuth credentials resolved
+    EnvLoaded,
+    /// Local storage initialized and recovered
+    StorageReady,
+    /// Session restored from the keychain (or mock mode signed in)
+    TokensReady,
+    /// Tauri setup finished and background loops spawned
+    SetupDone,
+    /// First inbox fetch from Gmail
+    FirstSync,
+    /// Inbox, today's events and tasks all fetched for the plan
+    PlanDataReady,
+}
+
+const STARTUP_STAGES: [StartupStage; 6] = [
+    StartupStage::EnvLoaded,
+    StartupStage::StorageReady,
+    StartupStage::TokensReady,
+    StartupStage::SetupDone,
+    StartupStage::FirstSync,
+    StartupStage::PlanDataReady,
+];
+
+impl StartupStage {
+    /// Time from the start of `run` by which the stage should be reached
+    fn budget_ms(self) -> u64 {
+        match self {
+            StartupStage::EnvLoaded => 50,
+            StartupStage::StorageReady => 300,
+            StartupStage::TokensReady => 800,
+            StartupStage::SetupDone => 1_000,
+            StartupStage::FirstSync => 2_500,
+            StartupStage::PlanDataReady => 3_000,
+        }
+    }
+}
+
+/// Data the plan needs before it can render
+#[derive(Debug, Clone, Copy)]
+pub enum PlanPart {
+    Inbox,
+    Events,
+    Tasks,
+}
+
+struct StartupRecorder {
+    started: Instant,
+    stages: Mutex<Vec<(StartupStage, Duration)>>,
+    /// Bit per `PlanPart` fetched so far
+    plan_parts: Mutex<u8>,
+    /// Whether this launch's profile was added to the history
+    saved: AtomicBool,
+}
+
+impl Default for StartupRecorder {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            stages: Mutex::new(Vec::new()),
+            plan_parts: Mutex::new(0),
+            saved: AtomicBool::new(false),
+        }
+    }
+}
+
+impl StartupRecorder {
+    fn mark(&self, stage: StartupStage) {
+        if let Ok(mut stages) = self.stages.lock() {
+            if !stages.iter().any(|(s, _)| *s == stage) {
+                stages.push((stage, self.started.elapsed()));
+            }
+        }
+    }
+
+    fn stages(&self) -> Vec<(StartupStage, Duration)> {
+        self.stages.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+}
+
+/// Start the startup clock (call first thing in `run`)
+pub fn startup_began() {
+    LazyLock::force(&STARTUP);
+}
+
+/// Record that `stage` was reached (only the first time counts)
+pub fn mark_startup(stage: StartupStage) {
+    STARTUP.mark(stage);
+}
+
+/// Record that part of the plan's data was fetched
+pub fn mark_plan_part(part: PlanPart) {
+    if matches!(part, PlanPart::Inbox) {
+        mark_startup(StartupStage::FirstSync);
+    }
+    let ready = STARTUP.plan_parts.lock().is_ok_and(|mut parts| {
+        *parts |= 1 << part as u8;
+        *parts == 0b111
+    });
+    if ready {
+        mark_startup(StartupStage::PlanDataReady);
+    }
+}
+
+/// When a stage was reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: StartupStage,
+    /// Since the start of `run`
+    pub at_ms: f64,
+    /// Since the previous recorded stage
+    pub since_previous_ms: f64,
+    pub budget_ms: u64,
+    pub over_budget: bool,
+}
+
+/// Stage timings of one launch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupProfile {
+    pub version: String,
+    pub recorded_at_ms: i64,
+    /// Stages reached so far, in startup order
+    pub stages: Vec<StageTiming>,
+    /// Time until the plan's data was ready (None while still starting)
+    pub total_ms: Option<f64>,
+}
+
+/// This launch's profile and earlier launches' for comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupReport {
+    pub current: StartupProfile,
+    /// Earlier complete launches, newest first
+    pub history: Vec<StartupProfile>,
+}
+
+fn startup_profile(version: &str, reached: &[(StartupStage, Duration)]) -> StartupProfile {
+    let mut previous = Duration::ZERO;
+    let mut stages = Vec::new();
+    for stage in STARTUP_STAGES {
+        let Some((_, at)) = reached.iter().find(|(s, _)| *s == stage) else {
+            continue;
+        };
+        stages.push(StageTiming {
+            stage,
+            at_ms: as_ms(*at),
+            since_previous_ms: as_ms(at.saturating_sub(previous)),
+            budget_ms: stage.budget_ms(),
+            over_budget: at.as_millis() > stage.budget_ms() as u128,
+        });
+        previous = previous.max(*at);
+    }
+
+    StartupProfile {
+        version: version.to_string(),
+        recorded_at_ms: chrono::Utc::now().timestamp_millis(),
+        total_ms: stages
+            .iter()
+            .find(|t| t.stage == StartupStage::PlanDataReady)
+            .map(|t| t.at_ms),
+        stages,
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get per-command timing histograms and the slowest recent calls
+#[tauri::command]
+pub fn get_performance_report() -> PerformanceReport {
+    REGISTRY.report()
+}
+
+/// Clear collected timings
+#[tauri::command]
+pub fn reset_performance_stats() {
+    REGISTRY.reset();
+}
+
+/// Get this launch's startup stage timings against their budgets, with the
+/// profiles of earlier launches
+///
+/// A complete profile is added to the history the first time it is read.
+#[tauri::command]
+pub async fn get_startup_profile(app: AppHandle) -> Result<StartupReport, String> {
+    let version = app.package_info().version.to_string();
+    let current = startup_profile(&version, &STARTUP.stages());
+
+    let store = storage::fs::settings_store(&app, PERF_STORE_FILE)
+        .map_err(|e| format!("Failed to access perf store: {}", e))?;
+    let history: Vec<StartupProfile> = store
+        .get(STARTUP_HISTORY_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    if current.total_ms.is_some() && !STARTUP.saved.swap(true, Ordering::SeqCst) {
+        let mut updated = vec![current.clone()];
+        updated.extend(history.iter().take(STARTUP_HISTORY_LEN - 1).cloned());
+        store.set(STARTUP_HISTORY_KEY, serde_json::json!(updated));
+        storage::fs::save_store(&app, PERF_STORE_FILE, &store)
+            .map_err(|e| format!("Failed to save perf store: {}", e))?;
+    }
+
+    Ok(StartupReport { current, history })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_name_from_type_name() {
+        assert_eq!(
+            command_name("rainy_day_lib::google::gmail::get_inbox_summary::{{closure}}::f"),
+            "get_inbox_summary"
+        );
+        assert_eq!(
+            command_name("rainy_day_lib::theme::get_theme::f"),
+            "get_theme"
+        );
+
+        fn probe_command() {
+            trace_command!();
+        }
+        probe_command();
+        assert!(REGISTRY
+            .report()
+            .commands
+            .iter()
+            .any(|c| c.command == "probe_command"));
+    }
+
+    #[test]
+    fn test_histogram_and_quantiles() {
+        let registry = PerfRegistry::default();
+        for ms in [2, 3, 4, 40, 700] {
+            registry.record("slow_command", Duration::from_millis(ms));
+        }
+        registry.record("fast_command", Duration::from_micros(300));
+
+        let report = registry.report();
+        let slow = &report.commands[0];
+        assert_eq!(slow.command, "slow_command");
+        assert_eq!(slow.calls, 5);
+        assert_eq!(slow.p50_le_ms, Some(5));
+        assert_eq!(slow.p95_le_ms, Some(1_000));
+        assert_eq!(slow.histogram[1].count, 3);
+
+        assert_eq!(report.slowest_recent[0].command, "slow_command");
+        assert_eq!(report.slowest_recent.len(), 6);
+    }
+
+    #[test]
+    fn test_startup_profile() {
+        let reached = [
+            (StartupStage::TokensReady, Duration::from_millis(900)),
+            (StartupStage::EnvLoaded, Duration::from_millis(10)),
+            (StartupStage::StorageReady, Duration::from_millis(200)),
+        ];
+        let profile = startup_profile("1.0.0", &reached);
+        let stages: Vec<StartupStage> = profile.stages.iter().map(|t| t.stage).collect();
+        assert_eq!(
+            stages,
+            [
+                StartupStage::EnvLoaded,
+                StartupStage::StorageReady,
+                StartupStage::TokensReady
+            ]
+        );
+        assert_eq!(profile.stages[1].since_previous_ms, 190.0);
+        assert!(!profile.stages[1].over_budget);
+        assert!(profile.stages[2].over_budget);
+        assert_eq!(profile.total_ms, None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Command risk policy
+//!
+//! Commands are tagged by risk (`risk_of`). Destructive ones (deleting a task,
+//! purging stored data) don't run on a plain invoke: the frontend first asks
+//! `request_confirmation` for a one-time token tied to the command and its
+//! target, shows its confirmation dialog, and passes the token along with the
+//! command. `authorize` consumes the token, so a stray or replayed call from
+//! another code path fails with `CONFIRMATION_REQUIRED_ERROR`.
+//!
+//! Sensitive commands confirm the user with the OS prompt when the gate is on
+//! (`auth::os_gate::confirm`).
+//!
+//! Every destructive invocation, allowed or refused, is written to the
+//! notification history with the `audit` type.
+
+use crate::auth::TokenStore;
+use crate::notifications;
+use crate::storage::LocalStorage;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+/// Prefix of the error returned when a destructive command lacks a valid token
+pub const CONFIRMATION_REQUIRED_ERROR: &str = "Confirmation required";
+/// How long a confirmation token stays valid
+const CONFIRMATION_TTL_MS: i64 = 2 * 60 * 1000;
+/// Notification history type of audit entries
+const AUDIT_TYPE: &str = "audit";
+
+/// How much damage a command can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    /// Reads or reversible changes
+    Normal,
+    /// Hands out secrets or copies of data; confirmed with the OS prompt when
+    /// the gate is on (see `auth::os_gate`)
+    Sensitive,
+    /// Deletes data for good; needs a confirmation token
+    Destructive,
+}
+
+/// Commands above `Risk::Normal`
+const COMMAND_RISKS: &[(&str, Risk)] = &[
+    ("delete_task", Risk::Destructive),
+    ("delete_rule", Risk::Destructive),
+    ("discard_outbox_item", Risk::Destructive),
+    ("purge_account_data", Risk::Destructive),
+    ("purge_now", Risk::Destructive),
+    ("storage_remove", Risk::Destructive),
+    ("get_backend_refresh_token", Risk::Sensitive),
+    ("export_all_data", Risk::Sensitive),
+    ("export_thread", Risk::Sensitive),
+];
+
+/// Risk of a command by name
+pub fn risk_of(command: &str) -> Risk {
+    COMMAND_RISKS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map_or(Risk::Normal, |(_, risk)| *risk)
+}
+
+/// A token handed out by `request_confirmation`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Confirmation {
+    pub token: String,
+    pub command: String,
+    pub target: String,
+    pub expires_at_ms: i64,
+}
+
+/// Outstanding confirmation tokens
+#[derive(Default)]
+pub struct PolicyState(Mutex<HashMap<String, Confirmation>>);
+
+impl PolicyState {
+    fn issue(&self, command: &str, target: &str, now_ms: i64) -> Result<Confirmation, String> {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let confirmation = Confirmation {
+            token: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            command: command.to_string(),
+            target: target.to_string(),
+            expires_at_ms: now_ms + CONFIRMATION_TTL_MS,
+        };
+        let mut pending = self
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to lock confirmations: {}", e))?;
+        pending.retain(|_, c| c.expires_at_ms > now_ms);
+        pending.insert(confirmation.token.clone(), confirmation.clone());
+        Ok(confirmation)
+    }
+
+    /// Consume `token` if it confirms `command` on `target`
+    fn redeem(&self, command: &str, target: &str, token: &str, now_ms: i64) -> Result<(), String> {
+        let mut pending = self
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to lock confirmations: {}", e))?;
+        let refused = |reason: &str| format!("{}: {}", CONFIRMATION_REQUIRED_ERROR, reason);
+        let confirmation = pending
+            .remove(token)
+            .ok_or_else(|| refused("unknown or used token"))?;
+        if confirmation.expires_at_ms <= now_ms {
+            return Err(refused("token expired"));
+        }
+        if confirmation.command != command || confirmation.target != target {
+            return Err(refused("token was issued for another action"));
+        }
+        Ok(())
+    }
+}
+
+/// Check the confirmation of a destructive command and record it in the audit log
+///
+/// `target` identifies what the command acts on, as passed to
+/// `request_confirmation`.
+pub async fn authorize(
+    app: &AppHandle,
+    command: &str,
+    target: &str,
+    confirmation: Option<&str>,
+) -> Result<(), String> {
+    if risk_of(command) != Risk::Destructive {
+        return Ok(());
+    }
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let result = match confirmation {
+        Some(token) => app
+            .state::<PolicyState>()
+            .redeem(command, target, token, now_ms),
+        None => Err(format!("{}: no token", CONFIRMATION_REQUIRED_ERROR)),
+    };
+
+    let body = match &result {
+        Ok(()) => format!("{} on {}", command, target),
+        Err(e) => format!("{} on {} refused ({})", command, target, e),
+    };
+    notifications::record_history(
+        &app.state::<TokenStore>(),
+        &app.state::<LocalStorage>(),
+        Some(AUDIT_TYPE),
+        "Destructive action",
+        Some(&body),
+    )
+    .await;
+    result
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the risk tag of a command
+#[tauri::command]
+pub fn get_command_risk(command: String) -> Risk {
+    crate::perf::trace_command!();
+    risk_of(&command)
+}
+
+/// Issue a one-time token confirming `command` on `target`
+///
+/// Call after the user confirmed in the UI; pass the token as the command's
+/// `confirmation` argument.
+#[tauri::command]
+pub fn request_confirmation(
+    policy: State<'_, PolicyState>,
+    command: String,
+    target: String,
+) -> Result<Confirmation, String> {
+    crate::perf::trace_command!();
+    if risk_of(&command) != Risk::Destructive {
+        return Err(format!("{} doesn't need a confirmation", command));
+    }
+    policy.issue(&command, &target, chrono::Utc::now().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_tokens() {
+        assert_eq!(risk_of("delete_task"), Risk::Destructive);
+        assert_eq!(risk_of("discard_outbox_item"), Risk::Destructive);
+        assert_eq!(risk_of("get_tasks"), Risk::Normal);
+
+        let policy = PolicyState::default();
+        let token = policy.issue("delete_task", "list:task", 0).unwrap().token;
+        assert!(policy
+            .redeem("delete_task", "list:other", &token, 1)
+            .is_err());
+        // A refused token is spent too
+        assert!(policy
+            .redeem("delete_task", "list:task", &token, 1)
+            .is_err());
+
+        let token = policy.issue("delete_task", "list:task", 0).unwrap().token;
+        assert!(policy
+            .redeem("delete_task", "list:task", &token, CONFIRMATION_TTL_MS)
+            .is_err());
+
+        let token = policy.issue("delete_task", "list:task", 0).unwrap().token;
+        assert!(policy.redeem("delete_task", "list:task", &token, 1).is_ok());
+        assert!(policy
+            .redeem("delete_task", "list:task", &token, 2)
+            .is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Tracking protection
+//!
+//! Message HTML is sanitized before rendering (`processing::sanitize_html`)
+//! and remote images are stripped by default, since loading them tells the
+//! sender when and where a message was opened. Senders the user trusts go on
+//! an allowlist (`allow_images_from`), either as an address or as a whole
+//! `@domain`. Open-tracking pixels are removed even for allowed senders.
+
+use crate::app_lock::AppLockState;
+use crate::storage;
+use tauri::{AppHandle, State};
+
+const PRIVACY_STORE_FILE: &str = "privacy.json";
+const IMAGE_SENDERS_KEY: &str = "image_senders";
+
+/// Normalize an allowlist entry: a lowercase address or `@domain`
+fn normalize_sender(sender: &str) -> Option<String> {
+    let sender = sender.trim().to_lowercase();
+    let (local, domain) = sender.rsplit_once('@')?;
+    if domain.is_empty() || !domain.contains('.') || local.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(sender)
+}
+
+/// Whether `from_email` matches an allowlist entry
+fn sender_allowed(allowlist: &[String], from_email: &str) -> bool {
+    let from_email = from_email.trim().to_lowercase();
+    let domain = from_email
+        .rsplit_once('@')
+        .map(|(_, domain)| format!("@{}", domain));
+    allowlist
+        .iter()
+        .any(|entry| *entry == from_email || Some(entry) == domain.as_ref())
+}
+
+fn load_allowlist(app: &AppHandle) -> Result<Vec<String>, String> {
+    let store = storage::fs::settings_store(app, PRIVACY_STORE_FILE)
+        .map_err(|e| format!("Failed to access privacy store: {}", e))?;
+
+    Ok(store
+        .get(IMAGE_SENDERS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_allowlist(app: &AppHandle, allowlist: &[String]) -> Result<(), String> {
+    let store = storage::fs::settings_store(app, PRIVACY_STORE_FILE)
+        .map_err(|e| format!("Failed to access privacy store: {}", e))?;
+
+    store.set(IMAGE_SENDERS_KEY, serde_json::json!(allowlist));
+    storage::fs::save_store(app, PRIVACY_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save privacy store: {}", e))
+}
+
+/// Whether remote images from `from_email` may be loaded
+///
+/// A store that can't be read blocks images.
+pub fn images_allowed(app: &AppHandle, from_email: &str) -> bool {
+    load_allowlist(app)
+        .map(|allowlist| sender_allowed(&allowlist, from_email))
+        .unwrap_or(false)
+}
+
+/// Get the senders whose remote images are loaded
+#[tauri::command]
+pub async fn get_image_allowlist(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+) -> Result<Vec<String>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    load_allowlist(&app)
+}
+
+/// Always load remote images from a sender (`jane@example.com`) or a domain
+/// (`@example.com`)
+#[tauri::command]
+pub async fn allow_images_from(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    sender: String,
+) -> Result<Vec<String>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let entry = normalize_sender(&sender)
+        .ok_or_else(|| format!("Invalid sender address or domain: {}", sender))?;
+
+    let mut allowlist = load_allowlist(&app)?;
+    if !allowlist.contains(&entry) {
+        allowlist.push(entry);
+        allowlist.sort();
+        save_allowlist(&app, &allowlist)?;
+    }
+    Ok(allowlist)
+}
+
+/// Block remote images from a sender or domain again
+#[tauri::command]
+pub async fn block_images_from(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    sender: String,
+) -> Result<Vec<String>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let entry = sender.trim().to_lowercase();
+
+    let mut allowlist = load_allowlist(&app)?;
+    let before = allowlist.len();
+    allowlist.retain(|e| *e != entry);
+    if allowlist.len() != before {
+        save_allowlist(&app, &allowlist)?;
+    }
+    Ok(allowlist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_allowlist() {
+        assert_eq!(
+            normalize_sender(" Jane@Example.com "),
+            Some("jane@example.com".to_string())
+        );
+        assert_eq!(
+            normalize_sender("@Example.com"),
+            Some("@example.com".to_string())
+        );
+        assert_eq!(normalize_sender("example.com"), None);
+        assert_eq!(normalize_sender("jane@localhost"), None);
+
+        let allowlist = vec!["@shop.com".to_string(), "jane@example.com".to_string()];
+        assert!(sender_allowed(&allowlist, "Jane@example.com"));
+        assert!(sender_allowed(&allowlist, "deals@shop.com"));
+        assert!(!sender_allowed(&allowlist, "joe@example.com"));
+        assert!(!sender_allowed(&allowlist, "deals@shop.com.evil.io"));
+    }
+}
@@ -3,9 +3,18 @@
 //! Provides fast client-side data processing for improved UI responsiveness.
 //! These are performance optimizations - the cloud backend remains the source of truth.
 
-use chrono::{DateTime, Local, TimeZone, Utc};
+use crate::google::types::{DialIn, EventLink, EventLinkKind};
+use crate::natural_date;
+use crate::privacy;
+use crate::storage;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc, Weekday};
 use rayon::prelude::*;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use tauri::AppHandle;
 
 // ============================================================================
 // Date/Time Formatting
@@ -60,6 +69,7 @@ pub fn format_relative_time(timestamp_ms: i64) -> String {
 /// Format a timestamp for display in the UI (local time)
 #[tauri::command]
 pub fn format_time(timestamp_ms: i64, format: Option<String>) -> String {
+    crate::perf::trace_command!();
     let format_str = format.as_deref().unwrap_or("%I:%M %p");
     DateTime::from_timestamp_millis(timestamp_ms)
         .map(|d| d.with_timezone(&Local).format(format_str).to_string())
@@ -69,6 +79,7 @@ pub fn format_time(timestamp_ms: i64, format: Option<String>) -> String {
 /// Format a date for display (local time)
 #[tauri::command]
 pub fn format_date(timestamp_ms: i64, format: Option<String>) -> String {
+    crate::perf::trace_command!();
     let format_str = format.as_deref().unwrap_or("%B %d, %Y");
     DateTime::from_timestamp_millis(timestamp_ms)
         .map(|d| d.with_timezone(&Local).format(format_str).to_string())
@@ -78,6 +89,7 @@ pub fn format_date(timestamp_ms: i64, format: Option<String>) -> String {
 /// Get a greeting based on current time of day
 #[tauri::command]
 pub fn get_time_greeting() -> String {
+    crate::perf::trace_command!();
     let hour = Local::now().hour();
     match hour {
         5..=11 => "Good morning",
@@ -93,6 +105,7 @@ use chrono::Timelike;
 /// Check if a date is today (in local timezone)
 #[tauri::command]
 pub fn is_today(timestamp_ms: i64) -> bool {
+    crate::perf::trace_command!();
     DateTime::from_timestamp_millis(timestamp_ms)
         .map(|d| d.with_timezone(&Local).date_naive() == Local::now().date_naive())
         .unwrap_or(false)
@@ -101,9 +114,185 @@ pub fn is_today(timestamp_ms: i64) -> bool {
 /// Get today's date as YYYY-MM-DD string (local timezone)
 #[tauri::command]
 pub fn get_today_date_string() -> String {
+    crate::perf::trace_command!();
     Local::now().format("%Y-%m-%d").to_string()
 }
 
+// ============================================================================
+// Weeks
+// ============================================================================
+
+const WEEK_STORE_FILE: &str = "week.json";
+const WEEK_SETTINGS_KEY: &str = "settings";
+
+/// Regions whose weeks start on Sunday (CLDR `firstDay`)
+const SUNDAY_REGIONS: &[&str] = &[
+    "AG", "BR", "BS", "BZ", "CA", "CO", "DO", "GT", "HK", "HN", "IL", "IN", "JM", "JP", "KE", "KR",
+    "MX", "NI", "PA", "PE", "PH", "PK", "PR", "PY", "SA", "SV", "TH", "TW", "US", "VE", "ZA",
+];
+/// Regions whose weeks start on Saturday
+const SATURDAY_REGIONS: &[&str] = &[
+    "AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY",
+];
+
+/// First day of the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirstDayOfWeek {
+    Monday,
+    Sunday,
+    Saturday,
+}
+
+impl FirstDayOfWeek {
+    pub(crate) fn weekday(self) -> Weekday {
+        match self {
+            FirstDayOfWeek::Monday => Weekday::Mon,
+            FirstDayOfWeek::Sunday => Weekday::Sun,
+            FirstDayOfWeek::Saturday => Weekday::Sat,
+        }
+    }
+
+    /// First day used in a POSIX locale's region (`en_US.UTF-8`, `ar-EG`)
+    pub fn from_locale(locale: &str) -> Self {
+        let region = locale
+            .split(['.', '@'])
+            .next()
+            .and_then(|tag| tag.split(['_', '-']).nth(1))
+            .unwrap_or_default()
+            .to_uppercase();
+        if SUNDAY_REGIONS.contains(&region.as_str()) {
+            FirstDayOfWeek::Sunday
+        } else if SATURDAY_REGIONS.contains(&region.as_str()) {
+            FirstDayOfWeek::Saturday
+        } else {
+            FirstDayOfWeek::Monday
+        }
+    }
+
+    /// First day of the system locale (Monday when unknown)
+    fn system() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+            .map_or(FirstDayOfWeek::Monday, |locale| Self::from_locale(&locale))
+    }
+}
+
+/// Week preferences
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeekSettings {
+    /// None follows the system locale
+    pub first_day: Option<FirstDayOfWeek>,
+}
+
+/// A week as shown in the app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeekInfo {
+    /// YYYY-MM-DD of the first and last day
+    pub start_date: String,
+    pub end_date: String,
+    /// Local midnight of the first day and of the day after the last
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// ISO 8601 week holding most of the week's days
+    pub iso_year: i32,
+    pub iso_week: u32,
+    pub first_day: FirstDayOfWeek,
+}
+
+pub fn load_week_settings(app: &AppHandle) -> Result<WeekSettings, String> {
+    let store = storage::fs::settings_store(app, WEEK_STORE_FILE)
+        .map_err(|e| format!("Failed to access week store: {}", e))?;
+
+    Ok(store
+        .get(WEEK_SETTINGS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// The configured first day of the week, falling back to the system locale
+pub fn first_day_of_week(app: &AppHandle) -> FirstDayOfWeek {
+    load_week_settings(app)
+        .ok()
+        .and_then(|settings| settings.first_day)
+        .unwrap_or_else(FirstDayOfWeek::system)
+}
+
+/// First day of the week containing `date`
+pub fn week_start(date: NaiveDate, first_day: FirstDayOfWeek) -> NaiveDate {
+    let offset = date.weekday().days_since(first_day.weekday());
+    date - Duration::days(offset as i64)
+}
+
+/// ISO 8601 (year, week) of the week starting on `start`
+///
+/// ISO weeks belong to the year of their Thursday, so a week starting on any
+/// day is numbered after the ISO week holding its 4th day, i.e. most of it.
+pub fn iso_week_number(start: NaiveDate) -> (i32, u32) {
+    let iso = (start + Duration::days(3)).iso_week();
+    (iso.year(), iso.week())
+}
+
+/// Local midnight of `date` as epoch milliseconds
+pub fn local_midnight_ms(date: NaiveDate) -> Result<i64, String> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|d| Local.from_local_datetime(&d).earliest())
+        .map(|d| d.timestamp_millis())
+        .ok_or_else(|| format!("Failed to create local midnight of {}", date))
+}
+
+/// The week containing `date`
+pub fn week_info(date: NaiveDate, first_day: FirstDayOfWeek) -> Result<WeekInfo, String> {
+    let start = week_start(date, first_day);
+    let end = start + Duration::days(6);
+    let (iso_year, iso_week) = iso_week_number(start);
+    Ok(WeekInfo {
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        start_ms: local_midnight_ms(start)?,
+        end_ms: local_midnight_ms(end + Duration::days(1))?,
+        iso_year,
+        iso_week,
+        first_day,
+    })
+}
+
+/// Parse an optional YYYY-MM-DD date, defaulting to today
+pub fn date_or_today(date: Option<&str>) -> Result<NaiveDate, String> {
+    match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| "Invalid date, expected YYYY-MM-DD".to_string()),
+        None => Ok(Local::now().date_naive()),
+    }
+}
+
+/// Get the week preferences
+#[tauri::command]
+pub async fn get_week_settings(app: AppHandle) -> Result<WeekSettings, String> {
+    crate::perf::trace_command!();
+    load_week_settings(&app)
+}
+
+/// Save the week preferences
+#[tauri::command]
+pub async fn set_week_settings(app: AppHandle, settings: WeekSettings) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let store = storage::fs::settings_store(&app, WEEK_STORE_FILE)
+        .map_err(|e| format!("Failed to access week store: {}", e))?;
+    store.set(WEEK_SETTINGS_KEY, serde_json::json!(settings));
+    storage::fs::save_store(&app, WEEK_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save week store: {}", e))
+}
+
+/// Get the week containing `date` (YYYY-MM-DD, default today)
+#[tauri::command]
+pub async fn get_week_info(app: AppHandle, date: Option<String>) -> Result<WeekInfo, String> {
+    crate::perf::trace_command!();
+    week_info(date_or_today(date.as_deref())?, first_day_of_week(&app))
+}
+
 // ============================================================================
 // Priority Scoring
 // ============================================================================
@@ -241,6 +430,689 @@ pub fn has_urgent_keywords(text: String) -> bool {
         .any(|keyword| lowercase.contains(keyword))
 }
 
+// ============================================================================
+// Event Descriptions
+// ============================================================================
+
+/// Structured content of a calendar event description
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventDescription {
+    pub links: Vec<EventLink>,
+    pub agenda: Vec<String>,
+    pub dial_ins: Vec<DialIn>,
+}
+
+/// Hosts of video meeting services
+const MEETING_HOSTS: &[&str] = &[
+    "zoom.us",
+    "meet.google.com",
+    "teams.microsoft.com",
+    "teams.live.com",
+    "webex.com",
+    "meet.jit.si",
+    "whereby.com",
+    "gotomeeting.com",
+    "gotomeet.me",
+    "chime.aws",
+    "bluejeans.com",
+];
+
+/// Hosts of shared documents
+const DOCUMENT_HOSTS: &[&str] = &[
+    "docs.google.com",
+    "drive.google.com",
+    "notion.so",
+    "notion.site",
+    "dropbox.com",
+    "sharepoint.com",
+    "onedrive.live.com",
+    "figma.com",
+    "atlassian.net",
+    "quip.com",
+    "coda.io",
+];
+
+static HREF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).unwrap());
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s<>"']+"#).unwrap());
+static LINE_BREAK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>|</p>|</div>|</li>|</h\d>").unwrap());
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<li[^>]*>").unwrap());
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+static BULLET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:[-*•·▪◦]|\d{1,2}[.)])\s+(.+)$").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\+\d[\d ().-]{6,}\d").unwrap());
+static PIN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:(?:pin|passcode|meeting id|access code|conference id)\s*[:#]?\s*|,,)(\d[\d ]*\d)",
+    )
+    .unwrap()
+});
+
+/// Drop sentence punctuation picked up after a URL in running text
+fn trim_url(url: &str) -> &str {
+    url.trim_end_matches(['.', ',', ';', ':', ')', '!', '?', ']'])
+}
+
+/// Lowercased host of a URL
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Classify a URL by its host
+pub fn link_kind(url: &str) -> EventLinkKind {
+    let host = url_host(url);
+    let matches = |hosts: &[&str]| {
+        hosts
+            .iter()
+            .any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+    };
+    if matches(MEETING_HOSTS) {
+        EventLinkKind::Meeting
+    } else if matches(DOCUMENT_HOSTS) {
+        EventLinkKind::Document
+    } else {
+        EventLinkKind::Other
+    }
+}
+
+/// First video meeting URL (Zoom, Teams, Webex, Jitsi, ...) in free text
+pub fn find_meeting_link(text: &str) -> Option<String> {
+    URL.find_iter(text)
+        .map(|m| trim_url(m.as_str()))
+        .find(|url| link_kind(url) == EventLinkKind::Meeting)
+        .map(str::to_string)
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Plain text of a description, keeping line breaks and list items
+fn description_text(html: &str) -> String {
+    if !html.contains('<') {
+        return decode_entities(html);
+    }
+    let text = LINE_BREAK.replace_all(html, "\n");
+    let text = LIST_ITEM.replace_all(&text, "\n- ");
+    decode_entities(&TAG.replace_all(&text, ""))
+}
+
+/// Extract links, agenda items and dial-in numbers from an event description
+///
+/// Calendar descriptions are HTML when written in Google Calendar and plain
+/// text when synced from other clients; both are handled.
+pub fn parse_event_description(html: &str) -> EventDescription {
+    let text = description_text(html);
+
+    let mut seen = HashSet::new();
+    let links = HREF
+        .captures_iter(html)
+        .map(|c| decode_entities(&c[1]))
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .chain(
+            URL.find_iter(&text)
+                .map(|m| trim_url(m.as_str()).to_string()),
+        )
+        .filter(|url| seen.insert(url.clone()))
+        .map(|url| EventLink {
+            kind: link_kind(&url),
+            url,
+        })
+        .collect();
+
+    let agenda = text
+        .lines()
+        .filter_map(|line| BULLET.captures(line))
+        .map(|c| c[1].split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|item| !item.is_empty())
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut dial_ins = Vec::new();
+    for line in text.lines() {
+        let pin = PIN.captures(line).map(|c| c[1].replace(' ', ""));
+        for number in PHONE.find_iter(line) {
+            let digits: String = number
+                .as_str()
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect();
+            if seen.insert(digits) {
+                dial_ins.push(DialIn {
+                    number: number.as_str().trim().to_string(),
+                    pin: pin.clone(),
+                });
+            }
+        }
+    }
+
+    EventDescription {
+        links,
+        agenda,
+        dial_ins,
+    }
+}
+
+// ============================================================================
+// Link Safety
+// ============================================================================
+
+/// A link found in an email body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractedLink {
+    pub url: String,
+    /// Anchor text (None for bare URLs in the text)
+    pub text: Option<String>,
+    pub host: String,
+    /// The anchor text shows a different site than the link opens
+    pub mismatched: bool,
+    /// The host is a URL shortener hiding the real destination
+    pub shortened: bool,
+}
+
+/// Well-known URL shortener domains
+const SHORTENER_HOSTS: &[&str] = &[
+    "bit.ly",
+    "tinyurl.com",
+    "t.co",
+    "goo.gl",
+    "ow.ly",
+    "is.gd",
+    "buff.ly",
+    "rebrand.ly",
+    "cutt.ly",
+    "shorturl.at",
+    "tiny.cc",
+    "lnkd.in",
+    "rb.gy",
+    "t.ly",
+    "s.id",
+];
+
+static ANCHOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<a\b[^>]*?href\s*=\s*["']([^"']+)["'][^>]*>(.*?)</a\s*>"#).unwrap()
+});
+/// Anchor text that reads like an address (`paypal.com/login`, `https://...`)
+static URL_LIKE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:https?://)?(?:[a-z0-9-]+\.)+[a-z]{2,}(?::\d+)?(?:[/?#]\S*)?$").unwrap()
+});
+
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Whether two hosts belong to the same site (one is a subdomain of the other)
+fn same_site(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim_start_matches("www."), b.trim_start_matches("www."));
+    host_matches(a, b) || host_matches(b, a)
+}
+
+fn extracted_link(url: String, text: Option<String>) -> ExtractedLink {
+    let host = url_host(&url);
+    let mismatched = text
+        .as_deref()
+        .filter(|text| URL_LIKE.is_match(text))
+        .is_some_and(|text| !same_site(&url_host(text), &host));
+    let shortened = SHORTENER_HOSTS.iter().any(|s| host_matches(&host, s));
+    ExtractedLink {
+        url,
+        text,
+        host,
+        mismatched,
+        shortened,
+    }
+}
+
+/// Extract every link of an email body with its display text and safety flags
+///
+/// Anchors whose text shows another site than their href, and links through
+/// URL shorteners, are flagged so the UI can warn before opening them.
+#[tauri::command]
+pub fn extract_links(body: String) -> Vec<ExtractedLink> {
+    crate::perf::trace_command!();
+    let mut links: Vec<ExtractedLink> = ANCHOR
+        .captures_iter(&body)
+        .map(|c| {
+            let url = decode_entities(c[1].trim());
+            let text = description_text(&c[2])
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            (url, (!text.is_empty()).then_some(text))
+        })
+        .filter(|(url, _)| url.starts_with("http://") || url.starts_with("https://"))
+        .map(|(url, text)| extracted_link(url, text))
+        .collect();
+
+    // Bare URLs outside anchors (anchor texts are links already covered)
+    let text = description_text(&ANCHOR.replace_all(&body, " "));
+    links.extend(
+        URL.find_iter(&text)
+            .map(|m| extracted_link(trim_url(m.as_str()).to_string(), None)),
+    );
+
+    let mut seen = HashSet::new();
+    links.retain(|link| seen.insert((link.url.clone(), link.text.clone())));
+    links
+}
+
+// ============================================================================
+// HTML Sanitization
+// ============================================================================
+
+/// Elements kept (with allowed attributes only); others are unwrapped
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "center",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "font",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "small",
+    "span",
+    "strike",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+/// Elements removed together with their content
+const DROPPED_TAGS: &[&str] = &[
+    "applet", "audio", "base", "button", "canvas", "embed", "form", "frame", "frameset", "iframe",
+    "input", "link", "math", "meta", "noscript", "object", "script", "select", "style", "svg",
+    "template", "textarea", "title", "video",
+];
+/// Attributes kept on every allowed element
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "align",
+    "alt",
+    "bgcolor",
+    "border",
+    "cellpadding",
+    "cellspacing",
+    "color",
+    "colspan",
+    "dir",
+    "face",
+    "height",
+    "lang",
+    "rowspan",
+    "size",
+    "start",
+    "title",
+    "type",
+    "valign",
+    "width",
+];
+/// Attributes always removed, seen by the filter only to count remote loads.
+/// Styles can reach the network in too many ways (`url()`, `image-set()`,
+/// CSS escapes, ...) to be filtered, so they are dropped whole.
+const STRIPPED_ATTRIBUTES: &[&str] = &["style", "background"];
+/// Schemes ammonia lets through; `sanitize_html_with` narrows them per
+/// element (links: web/mail/phone, images: `cid:`, `data:image/`, web)
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "cid", "data"];
+const LINK_SCHEMES: &[&str] = &["http:", "https:", "mailto:", "tel:"];
+
+/// Open-tracking beacons by URL (`/track/open`, `/wf/open`, mail tracker hosts)
+static TRACKER_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)/(?:track(?:ing)?|open|pixel|beacon|wf/open)(?:[/._?]|$)|mailtrack\.io|sidekickopen|yesware\.com")
+        .unwrap()
+});
+
+/// Style URLs pointing at another host (only counted; styles are dropped)
+static REMOTE_STYLE_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)url\s*\(\s*['"]?\s*(?:https?:)?//"#).unwrap());
+
+/// Message HTML that is safe to render
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SanitizedHtml {
+    pub html: String,
+    /// Remote images, backgrounds and style URLs that were removed
+    pub blocked_remote: usize,
+    /// Of those, open-tracking pixels (removed even when remote images are
+    /// allowed)
+    pub tracking_pixels: usize,
+    /// Content IDs of inline images; their `img` carries `data-cid` instead of
+    /// `src` so the UI can point it at the attachment
+    pub inline_images: Vec<String>,
+}
+
+/// Whether a remote image looks like an open-tracking pixel
+fn is_tracking_pixel(src: &str, attributes: &kuchikiki::Attributes) -> bool {
+    let tiny = |name: &str| {
+        attributes
+            .get(name)
+            .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok())
+            .is_some_and(|v| v <= 1)
+    };
+    tiny("width") || tiny("height") || TRACKER_URL.is_match(src)
+}
+
+fn is_remote(url: &str) -> bool {
+    let url = url.trim().to_lowercase();
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
+
+fn is_allowed_link(href: &str) -> bool {
+    let lower = href.trim().to_lowercase();
+    LINK_SCHEMES.iter().any(|s| lower.starts_with(s)) || lower.starts_with('#')
+}
+
+fn is_allowed_image(src: &str) -> bool {
+    let lower = src.trim().to_lowercase();
+    lower.starts_with("cid:") || lower.starts_with("data:image/") || is_remote(&lower)
+}
+
+/// Keep only allowlisted markup: no scripts, handlers, styles, forms or
+/// frames, links restricted to web/mail/phone, remote resources removed
+/// unless `allow_remote` (then only images, tracking pixels never), `cid:`
+/// images marked for the UI
+///
+/// ammonia does the cleaning; the image policy then runs over its output.
+pub fn sanitize_html_with(html: &str, allow_remote: bool) -> SanitizedHtml {
+    use kuchikiki::traits::TendrilSink;
+
+    let stripped_remote = Arc::new(AtomicUsize::new(0));
+    let counter = stripped_remote.clone();
+    let clean = ammonia::Builder::empty()
+        .tags(ALLOWED_TAGS.iter().copied().collect())
+        .clean_content_tags(DROPPED_TAGS.iter().copied().collect())
+        .generic_attributes(
+            ALLOWED_ATTRIBUTES
+                .iter()
+                .chain(STRIPPED_ATTRIBUTES)
+                .copied()
+                .collect(),
+        )
+        .tag_attributes(HashMap::from([
+            ("a", HashSet::from(["href"])),
+            ("img", HashSet::from(["src"])),
+        ]))
+        .url_schemes(URL_SCHEMES.iter().copied().collect())
+        .link_rel(None)
+        .strip_comments(true)
+        .attribute_filter(move |element, attribute, value| {
+            let keep = match (element, attribute) {
+                (_, "style") => {
+                    if REMOTE_STYLE_URL.is_match(value) {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                    false
+                }
+                (_, "background") => {
+                    if is_remote(value) {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                    false
+                }
+                ("a", "href") => is_allowed_link(value),
+                ("img", "src") => is_allowed_image(value),
+                _ => true,
+            };
+            keep.then(|| value.into())
+        })
+        .clean(html)
+        .to_string();
+
+    let document = kuchikiki::parse_html().one(clean).document_node;
+    let mut result = SanitizedHtml {
+        blocked_remote: stripped_remote.load(Ordering::Relaxed),
+        ..SanitizedHtml::default()
+    };
+
+    for node in document.descendants() {
+        let Some(element) = node.as_element() else {
+            continue;
+        };
+        let mut attributes = element.attributes.borrow_mut();
+        match element.name.local.as_ref() {
+            "a" if attributes.contains("href") => {
+                attributes.insert("target", "_blank".to_string());
+                attributes.insert("rel", "noopener noreferrer".to_string());
+            }
+            "img" => {
+                let Some(src) = attributes.remove("src").map(|a| a.value) else {
+                    continue;
+                };
+                let src = src.trim();
+                if src.to_lowercase().starts_with("cid:") {
+                    let cid = src[4..].trim_matches(['<', '>']).to_string();
+                    attributes.insert("data-cid", cid.clone());
+                    result.inline_images.push(cid);
+                } else if src.to_lowercase().starts_with("data:image/") {
+                    attributes.insert("src", src.to_string());
+                } else {
+                    let pixel = is_tracking_pixel(src, &attributes);
+                    if allow_remote && !pixel {
+                        attributes.insert("src", src.to_string());
+                    } else {
+                        result.blocked_remote += 1;
+                        result.tracking_pixels += pixel as usize;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut output = Vec::new();
+    if let Ok(body) = document.select_first("body") {
+        for child in body.as_node().children() {
+            if let Err(e) = child.serialize(&mut output) {
+                eprintln!("Failed to serialize sanitized HTML: {}", e);
+            }
+        }
+    }
+    result.html = String::from_utf8_lossy(&output).into_owned();
+    result
+}
+
+/// Sanitize message HTML before rendering it
+///
+/// Remote images are blocked unless `sender` is on the image allowlist.
+#[tauri::command]
+pub fn sanitize_html(app: AppHandle, html: String, sender: Option<String>) -> SanitizedHtml {
+    crate::perf::trace_command!();
+    let allow_remote = sender.is_some_and(|sender| privacy::images_allowed(&app, &sender));
+    sanitize_html_with(&html, allow_remote)
+}
+
+// ============================================================================
+// Quick Replies
+// ============================================================================
+
+/// What the latest message asks of the reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyIntent {
+    /// Proposes a meeting or a time
+    Scheduling,
+    /// Asks the reader to do something
+    Request,
+    /// Asks a question
+    Question,
+    /// Sends a file or document
+    Delivery,
+    /// Thanks the reader
+    Gratitude,
+    /// Shares information
+    Update,
+}
+
+/// A one-tap reply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickReply {
+    pub text: String,
+    pub intent: ReplyIntent,
+}
+
+/// Replies offered at most
+const MAX_QUICK_REPLIES: usize = 3;
+
+static SCHEDULING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(meet(ing)?|call|catch up|sync|available|availability|works for you|does \w+ work|schedule|reschedule|(mon|tues|wednes|thurs|fri|satur|sun)day|tomorrow|next week|\d{1,2}(:\d{2})?\s?(am|pm))\b").unwrap()
+});
+static REQUEST: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(could you|can you|would you|please|kindly|let me know|need you to|send (me|over)|review|approve|sign)\b").unwrap()
+});
+static DELIVERY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(attached|attachment|please find|here is|here's|enclosed|sharing|shared (a|the)|i've sent)\b").unwrap()
+});
+static GRATITUDE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(thank you|thanks|thx|appreciate|grateful)\b").unwrap());
+/// Start of a quoted earlier message (`On Mon, Jan 5, Jane wrote:`)
+static QUOTE_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^\s*on .{0,200}wrote:\s*$").unwrap());
+
+/// The newest message of a thread: text before the first quote
+pub(crate) fn latest_message(thread_text: &str) -> &str {
+    let end = QUOTE_HEADER
+        .find(thread_text)
+        .map_or(thread_text.len(), |m| m.start());
+    let text = &thread_text[..end];
+    text.find("\n>").map_or(text, |i| &text[..i])
+}
+
+fn intent_replies(intent: ReplyIntent, urgent: bool) -> &'static [&'static str] {
+    match (intent, urgent) {
+        (ReplyIntent::Scheduling, _) => &[
+            "Works for me — see you then.",
+            "Could we find another time?",
+            "Let me check my calendar and get back to you.",
+        ],
+        (ReplyIntent::Request, true) => &[
+            "On it now.",
+            "I'll take care of it right away.",
+            "Sorry, I can't get to this today.",
+        ],
+        (ReplyIntent::Request, false) => &[
+            "Sure, I'll take care of it.",
+            "On it — I'll get back to you soon.",
+            "Sorry, I can't this time.",
+        ],
+        (ReplyIntent::Question, _) => &[
+            "Yes, that works.",
+            "Let me check and get back to you.",
+            "No, unfortunately not.",
+        ],
+        (ReplyIntent::Delivery, _) => &["Thanks, received!", "Thanks, I'll take a look."],
+        (ReplyIntent::Gratitude, _) => &["You're welcome!", "Happy to help."],
+        (ReplyIntent::Update, _) => &["Thanks for the update.", "Got it, thanks!"],
+    }
+}
+
+/// Intents of a message, strongest first
+fn detect_intents(message: &str) -> Vec<ReplyIntent> {
+    let mut intents = Vec::new();
+    if SCHEDULING.is_match(message) && (message.contains('?') || REQUEST.is_match(message)) {
+        intents.push(ReplyIntent::Scheduling);
+    }
+    if DELIVERY.is_match(message) {
+        intents.push(ReplyIntent::Delivery);
+    }
+    if REQUEST.is_match(message) {
+        intents.push(ReplyIntent::Request);
+    }
+    if message.contains('?') {
+        intents.push(ReplyIntent::Question);
+    }
+    if GRATITUDE.is_match(message) {
+        intents.push(ReplyIntent::Gratitude);
+    }
+    if intents.is_empty() {
+        intents.push(ReplyIntent::Update);
+    }
+    intents
+}
+
+/// Suggest 2-3 short replies to the latest message of a thread
+///
+/// Rule-based: intents are detected from the newest message (quoted history
+/// is ignored) and each contributes its templates, strongest intent first.
+#[tauri::command]
+pub fn suggest_quick_replies(thread_text: String) -> Vec<QuickReply> {
+    crate::perf::trace_command!();
+    let message = latest_message(&thread_text);
+    let urgent = has_urgent_keywords(message.to_string());
+    let intents = detect_intents(message);
+
+    // The strongest intent leads with two replies; others add their first
+    let mut replies: Vec<QuickReply> = Vec::new();
+    for (rank, intent) in intents.iter().enumerate() {
+        let take = if rank == 0 { 2 } else { 1 };
+        for text in intent_replies(*intent, urgent).iter().take(take) {
+            if !replies.iter().any(|r| r.text == *text) {
+                replies.push(QuickReply {
+                    text: text.to_string(),
+                    intent: *intent,
+                });
+            }
+        }
+    }
+    // A single intent offers its whole set
+    if let [intent] = intents[..] {
+        replies.extend(
+            intent_replies(intent, urgent)
+                .iter()
+                .skip(2)
+                .map(|text| QuickReply {
+                    text: text.to_string(),
+                    intent,
+                }),
+        );
+    }
+    replies.truncate(MAX_QUICK_REPLIES);
+    replies
+}
+
 // ============================================================================
 // Batch Processing
 // ============================================================================
@@ -270,6 +1142,7 @@ pub struct ProcessedTask {
 /// Batch process tasks for display (Parallelized with Rayon)
 #[tauri::command]
 pub fn batch_process_tasks(tasks: Vec<TaskInput>) -> Vec<ProcessedTask> {
+    crate::perf::trace_command!();
     let now = Utc::now().timestamp_millis();
     let today_start = Local::now()
         .date_naive()
@@ -331,6 +1204,9 @@ pub struct EmailInput {
     pub is_unread: bool,
     pub thread_size: usize,
     pub is_direct: bool,
+    /// Text extracted from the thread's attachments (see `attachments::extract_attachment_text`)
+    #[serde(default)]
+    pub attachment_text: Option<String>,
 }
 
 /// Processed email output
@@ -348,13 +1224,31 @@ pub struct ProcessedEmail {
     pub has_urgent_keywords: bool,
 }
 
+/// Days ahead within which an attachment's deadline makes its thread urgent
+pub const ATTACHMENT_DUE_SOON_DAYS: i64 = 3;
+
+/// Whether attachment text asks for action soon (urgent wording, or a
+/// deadline like "due Friday" within `ATTACHMENT_DUE_SOON_DAYS`)
+pub fn attachment_is_urgent(text: &str) -> bool {
+    let now = Local::now().naive_local();
+    has_urgent_keywords(text.to_string())
+        || natural_date::due_date(text, now).is_some_and(|due| {
+            due.start.date() - now.date() <= Duration::days(ATTACHMENT_DUE_SOON_DAYS)
+        })
+}
+
 /// Batch process emails for display (Parallelized with Rayon)
 #[tauri::command]
 pub fn batch_process_emails(emails: Vec<EmailInput>) -> Vec<ProcessedEmail> {
+    crate::perf::trace_command!();
     emails
         .into_par_iter() // Parallel iterator
         .map(|email| {
-            let urgent = has_urgent_keywords(format!("{} {}", email.subject, email.snippet));
+            let urgent = has_urgent_keywords(format!("{} {}", email.subject, email.snippet))
+                || email
+                    .attachment_text
+                    .as_deref()
+                    .is_some_and(attachment_is_urgent);
             let score = calculate_priority_score(PriorityInput {
                 is_unread: email.is_unread,
                 age_hours: (Utc::now().timestamp_millis() - email.timestamp_ms) as f64
@@ -416,4 +1310,220 @@ mod tests {
         assert!(has_urgent_keywords("Action Required: Review".to_string()));
         assert!(!has_urgent_keywords("Hello, how are you?".to_string()));
     }
+
+    #[test]
+    fn test_parse_event_description() {
+        let html = "Agenda:<ul><li>Q3 roadmap</li><li>Hiring &amp; budget</li></ul>\
+            Notes: <a href=\"https://docs.google.com/document/d/abc/edit?a=1&amp;b=2\">doc</a><br>\
+            Join Zoom: https://acme.zoom.us/j/123456789.<br>\
+            Join by phone<br>(US) +1 413-555-0123 PIN: 123 456#<br>\
+            More info at https://example.com/faq";
+        let parsed = parse_event_description(html);
+
+        assert_eq!(parsed.agenda, vec!["Q3 roadmap", "Hiring & budget"]);
+        let links: Vec<_> = parsed
+            .links
+            .iter()
+            .map(|l| (l.url.as_str(), l.kind))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "https://docs.google.com/document/d/abc/edit?a=1&b=2",
+                    EventLinkKind::Document
+                ),
+                ("https://acme.zoom.us/j/123456789", EventLinkKind::Meeting),
+                ("https://example.com/faq", EventLinkKind::Other),
+            ]
+        );
+        assert_eq!(
+            parsed.dial_ins,
+            vec![DialIn {
+                number: "+1 413-555-0123".to_string(),
+                pin: Some("123456".to_string()),
+            }]
+        );
+
+        let plain = "1. Intro\n2) Demo\n\nDial: +16699006833,,987654321# US";
+        let parsed = parse_event_description(plain);
+        assert_eq!(parsed.agenda, vec!["Intro", "Demo"]);
+        assert_eq!(parsed.dial_ins[0].number, "+16699006833");
+        assert_eq!(parsed.dial_ins[0].pin.as_deref(), Some("987654321"));
+        assert!(parsed.links.is_empty());
+    }
+
+    #[test]
+    fn test_find_meeting_link() {
+        assert_eq!(
+            find_meeting_link("Room 4 / https://teams.microsoft.com/l/meetup-join/19%3a).")
+                .as_deref(),
+            Some("https://teams.microsoft.com/l/meetup-join/19%3a")
+        );
+        assert_eq!(
+            find_meeting_link("https://example.com then https://acme.webex.com/meet/jane")
+                .as_deref(),
+            Some("https://acme.webex.com/meet/jane")
+        );
+        assert_eq!(find_meeting_link("https://zoom.example.com/x"), None);
+        assert_eq!(find_meeting_link("Conference room B"), None);
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let body = r#"<p>Hi! Please <a href="https://evil.example.net/login">https://www.paypal.com</a>
+            or <a class="btn" href='https://accounts.paypal.com/reset'>paypal.com</a>,
+            <a href="https://bit.ly/3xYz">Read more</a> and
+            <a href="mailto:me@example.com">mail me</a>.</p>
+            Docs: https://docs.example.com/a?b=1&amp;c=2."#;
+        let links = extract_links(body.to_string());
+
+        let summary: Vec<_> = links
+            .iter()
+            .map(|l| {
+                (
+                    l.host.as_str(),
+                    l.text.as_deref(),
+                    l.mismatched,
+                    l.shortened,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "evil.example.net",
+                    Some("https://www.paypal.com"),
+                    true,
+                    false
+                ),
+                ("accounts.paypal.com", Some("paypal.com"), false, false),
+                ("bit.ly", Some("Read more"), false, true),
+                ("docs.example.com", None, false, false),
+            ]
+        );
+        assert_eq!(links[3].url, "https://docs.example.com/a?b=1&c=2");
+    }
+
+    #[test]
+    fn test_week_start_and_iso_week() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        // Wednesday 2026-01-07
+        let wednesday = date("2026-01-07");
+        assert_eq!(
+            week_start(wednesday, FirstDayOfWeek::Monday),
+            date("2026-01-05")
+        );
+        assert_eq!(
+            week_start(wednesday, FirstDayOfWeek::Sunday),
+            date("2026-01-04")
+        );
+        assert_eq!(
+            week_start(wednesday, FirstDayOfWeek::Saturday),
+            date("2026-01-03")
+        );
+        // A week start maps to itself
+        assert_eq!(
+            week_start(date("2026-01-04"), FirstDayOfWeek::Sunday),
+            date("2026-01-04")
+        );
+
+        // ISO week 1 of 2026 starts Monday 2025-12-29
+        let week = week_info(date("2025-12-31"), FirstDayOfWeek::Monday).unwrap();
+        assert_eq!((week.iso_year, week.iso_week), (2026, 1));
+        assert_eq!(week.start_date, "2025-12-29");
+        assert_eq!(week.end_date, "2026-01-04");
+        // Sunday-first week of 2026-01-04..10 is mostly ISO week 2
+        let week = week_info(date("2026-01-04"), FirstDayOfWeek::Sunday).unwrap();
+        assert_eq!((week.iso_year, week.iso_week), (2026, 2));
+        assert!(week.end_ms > week.start_ms);
+
+        assert_eq!(
+            FirstDayOfWeek::from_locale("en_US.UTF-8"),
+            FirstDayOfWeek::Sunday
+        );
+        assert_eq!(FirstDayOfWeek::from_locale("es-ES"), FirstDayOfWeek::Monday);
+        assert_eq!(
+            FirstDayOfWeek::from_locale("ar_EG"),
+            FirstDayOfWeek::Saturday
+        );
+        assert_eq!(FirstDayOfWeek::from_locale("de"), FirstDayOfWeek::Monday);
+    }
+
+    #[test]
+    fn test_suggest_quick_replies() {
+        let texts = |text: &str| -> Vec<String> {
+            suggest_quick_replies(text.to_string())
+                .into_iter()
+                .map(|r| r.text)
+                .collect()
+        };
+
+        let replies = suggest_quick_replies("Does Thursday at 3pm work for a quick call?".into());
+        assert_eq!(replies[0].intent, ReplyIntent::Scheduling);
+        assert_eq!(replies[0].text, "Works for me — see you then.");
+        assert!(replies.len() >= 2 && replies.len() <= 3);
+
+        assert_eq!(
+            texts("Please find the signed contract attached."),
+            [
+                "Thanks, received!",
+                "Thanks, I'll take a look.",
+                "Sure, I'll take care of it."
+            ]
+        );
+        assert_eq!(
+            texts("Thanks so much for your help yesterday!"),
+            ["You're welcome!", "Happy to help."]
+        );
+        assert_eq!(texts("Can you review this ASAP?")[0], "On it now.");
+
+        // Quoted history doesn't count
+        let thread = "Sounds good, the deploy went fine.\n\nOn Mon, Jan 5, 2026 at 9:00 AM Jane wrote:\n> Can we meet tomorrow?";
+        assert_eq!(texts(thread), ["Thanks for the update.", "Got it, thanks!"]);
+    }
+
+    #[test]
+    fn test_sanitize_html() {
+        let html = r#"<html><head><style>body{}</style></head><body>
+            <p onclick="steal()" style="color:red">Hi <b>there</b><script>alert(1)</script></p>
+            <a href="javascript:alert(1)">bad</a><a href="https://example.com">good</a>
+            <img src="https://tracker.example.com/p.gif" width="1" height="1">
+            <img src="cid:logo@mail"><img src="https://cdn.example.com/hero.png" width="600">
+            <table><tr><td background="http://example.com/bg.png"
+                style="background:url(http://x.io/a.png)">x</td></tr></table>
+            <form action="/x"><input name="q"></form><custom-tag>kept text</custom-tag>
+            <!-- comment --></body></html>"#;
+
+        let sanitized = sanitize_html_with(html, false);
+        let out = &sanitized.html;
+        assert!(out.contains("<p>Hi <b>there</b></p>"));
+        assert!(!out.contains("script") && !out.contains("onclick") && !out.contains("style>"));
+        assert!(out.contains("<a>bad</a>"));
+        assert!(out.contains(
+            r#"<a href="https://example.com" target="_blank" rel="noopener noreferrer">good</a>"#
+        ));
+        assert!(!out.contains("tracker.example.com"));
+        assert!(out.contains(r#"data-cid="logo@mail""#));
+        assert!(!out.contains("form") && !out.contains("input") && !out.contains("comment"));
+        assert!(out.contains("kept text") && !out.contains("custom-tag"));
+        assert_eq!(sanitized.blocked_remote, 4);
+        assert_eq!(sanitized.tracking_pixels, 1);
+        assert_eq!(sanitized.inline_images, vec!["logo@mail".to_string()]);
+
+        // Allowed remote images still never include tracking pixels
+        let allowed = sanitize_html_with(html, true);
+        assert!(allowed.html.contains("cdn.example.com/hero.png"));
+        assert!(!allowed.html.contains("tracker.example.com"));
+        assert_eq!(allowed.blocked_remote, 3);
+        assert_eq!(allowed.tracking_pixels, 1);
+
+        // Styles go whole, whatever way they would reach the network
+        let styled = sanitize_html_with(
+            r#"<div style="background:image-set('https://x.io/a.png' 1x)">a</div><div style="background:u\72l(https://x.io/b.png)">b</div>"#,
+            true,
+        );
+        assert_eq!(styled.html, "<div>a</div><div>b</div>");
+    }
 }
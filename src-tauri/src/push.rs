@@ -0,0 +1,426 @@
+//! Pushes from the Rainy Day cloud backend
+//!
+//! While signed in to the backend, `spawn_push_stream` keeps a server-sent
+//! events stream open on `/push/stream`, so plan updates and AI results show
+//! up as soon as the backend has them instead of on the next poll.
+//!
+//! Every event is an envelope `{timestamp, body, signature}`: `signature` is
+//! the hex HMAC-SHA256 of `"{timestamp}.{body}"` keyed with the push secret
+//! from the keychain (`store_backend_push_secret`). Unsigned, badly signed,
+//! stale or replayed envelopes are dropped; `body` is then read as a
+//! `PushEvent` and applied like a local change, clearing stale cache entries,
+//! saving to storage and emitting the matching `DataEvent`.
+//!
+//! A dropped or silent stream reconnects with exponential backoff, resuming
+//! after the last event seen (`Last-Event-ID`). A rejected access token is
+//! refreshed first.
+
+use crate::auth::{self, TokenStore};
+use crate::events::{self, DataEvent};
+use crate::google::invalidation;
+use crate::{ai, daily_note};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Path of the event stream on the backend
+const STREAM_PATH: &str = "/push/stream";
+/// Largest clock difference accepted on a signed envelope
+const MAX_SKEW_SECS: u64 = 300;
+/// Longest silence (events or keep-alives) before the stream is reopened
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Error of a stream the backend refused the access token for
+const STREAM_UNAUTHORIZED: &str = "Push stream unauthorized";
+/// Delay before the first reconnect
+const RECONNECT_BASE: Duration = Duration::from_secs(2);
+/// Longest delay between reconnects
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+/// How often to check again while signed out of the backend
+const SIGNED_OUT_INTERVAL: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ============================================================================
+// Events
+// ============================================================================
+
+/// A change pushed by the backend
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PushEvent {
+    /// The backend regenerated a day's plan
+    PlanUpdated { date: String },
+    /// An AI-written daily note, in the `save_daily_note` schema
+    NoteGenerated { note: serde_json::Value },
+    /// Inbox threads changed on Gmail
+    InboxChanged { thread_ids: Vec<String> },
+    /// A task changed on Google Tasks
+    TaskChanged { list_id: String, task_id: String },
+    /// A type this version doesn't know; ignored
+    #[serde(other)]
+    Unknown,
+}
+
+/// A signed event as sent on the stream
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    /// Unix seconds
+    timestamp: i64,
+    body: String,
+    signature: String,
+}
+
+fn signature(secret: &str, timestamp: i64, body: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Signatures of the envelopes accepted within the skew window
+#[derive(Default)]
+struct SeenEnvelopes(HashMap<String, i64>);
+
+impl SeenEnvelopes {
+    /// Record an envelope; false if it was already seen
+    fn insert(&mut self, signature: &str, timestamp: i64, now_secs: i64) -> bool {
+        // Older envelopes fail the age check anyway
+        self.0
+            .retain(|_, seen_at| now_secs.abs_diff(*seen_at) <= MAX_SKEW_SECS);
+        self.0.insert(signature.to_string(), timestamp).is_none()
+    }
+}
+
+/// Check an envelope's signature, age and novelty, then read its event
+fn open_envelope(
+    secret: &str,
+    data: &str,
+    now_secs: i64,
+    seen: &mut SeenEnvelopes,
+) -> Result<PushEvent, String> {
+    let envelope: Envelope =
+        serde_json::from_str(data).map_err(|e| format!("Invalid push envelope: {}", e))?;
+    let expected = decode_hex(&envelope.signature).ok_or("Invalid push signature")?;
+    signature(secret, envelope.timestamp, &envelope.body)
+        .verify_slice(&expected)
+        .map_err(|_| "Push signature mismatch".to_string())?;
+    if now_secs.abs_diff(envelope.timestamp) > MAX_SKEW_SECS {
+        return Err("Push envelope is too old".to_string());
+    }
+    if !seen.insert(
+        &envelope.signature.to_lowercase(),
+        envelope.timestamp,
+        now_secs,
+    ) {
+        return Err("Push envelope was replayed".to_string());
+    }
+    serde_json::from_str(&envelope.body).map_err(|e| format!("Invalid push event: {}", e))
+}
+
+/// Apply a pushed change to the cache and storage, then notify the windows
+async fn apply(app: &AppHandle, event: PushEvent) -> Result<(), String> {
+    match event {
+        PushEvent::PlanUpdated { date } => {
+            invalidation::mutated(app, DataEvent::PlanRegenerated { date }).await;
+        }
+        PushEvent::NoteGenerated { note } => {
+            let account = app.state::<TokenStore>().account_context().await?;
+            let note = daily_note::save_ai_note(app, &account, note)?;
+            events::emit(app, DataEvent::NoteReady { date: note.date });
+        }
+        PushEvent::InboxChanged { thread_ids } => {
+            invalidation::mutated(app, DataEvent::ThreadsModified { thread_ids }).await;
+        }
+        PushEvent::TaskChanged { list_id, task_id } => {
+            invalidation::mutated(app, DataEvent::TaskUpdated { list_id, task_id }).await;
+        }
+        PushEvent::Unknown => {}
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Stream
+// ============================================================================
+
+/// One server-sent event
+#[derive(Debug, Default, PartialEq)]
+struct SseEvent {
+    id: Option<String>,
+    data: String,
+}
+
+/// Incremental `text/event-stream` parser
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    id: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed a chunk; returns the events it completed
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        id: self.id.clone(),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                continue;
+            }
+            // Lines starting with ':' are keep-alive comments
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "data" => self.data.push(value.to_string()),
+                "id" => self.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Connection state of the push stream
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PushStatus {
+    pub connected: bool,
+    pub received: u64,
+    /// Envelopes dropped for a bad signature or format
+    pub rejected: u64,
+    pub last_event_at_ms: Option<i64>,
+    pub last_event_id: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct PushState(Mutex<PushStatus>);
+
+impl PushState {
+    fn update(&self, f: impl FnOnce(&mut PushStatus)) {
+        if let Ok(mut status) = self.0.lock() {
+            f(&mut status);
+        }
+    }
+
+    fn last_event_id(&self) -> Option<String> {
+        self.0.lock().ok()?.last_event_id.clone()
+    }
+}
+
+/// Delay before reconnect number `attempts` (1-based)
+fn reconnect_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (RECONNECT_BASE * 2u32.pow(exponent)).min(MAX_RECONNECT_DELAY)
+}
+
+/// Read the stream until it ends; `Ok` when it was opened
+async fn run_stream(
+    app: &AppHandle,
+    http: &reqwest::Client,
+    token: &str,
+    secret: &str,
+    seen: &mut SeenEnvelopes,
+) -> Result<(), String> {
+    let state = app.state::<PushState>();
+    let url = format!("{}{}", ai::backend_url(app)?, STREAM_PATH);
+    let mut request = http
+        .get(url)
+        .bearer_auth(token)
+        .header("Accept", "text/event-stream");
+    if let Some(id) = state.last_event_id() {
+        request = request.header("Last-Event-ID", id);
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| format!("Push stream request failed: {}", e))?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(STREAM_UNAUTHORIZED.to_string());
+    }
+    if !response.status().is_success() {
+        return Err(format!("Push stream error {}", response.status()));
+    }
+    state.update(|s| {
+        s.connected = true;
+        s.last_error = None;
+    });
+
+    let mut parser = SseParser::default();
+    while let Some(chunk) = tokio::time::timeout(IDLE_TIMEOUT, response.chunk())
+        .await
+        .map_err(|_| "Push stream went silent".to_string())?
+        .map_err(|e| format!("Push stream broke: {}", e))?
+    {
+        for event in parser.feed(&chunk) {
+            let now = chrono::Utc::now();
+            match open_envelope(secret, &event.data, now.timestamp(), seen) {
+                Ok(push) => {
+                    if let Err(e) = apply(app, push).await {
+                        eprintln!("Failed to apply push: {}", e);
+                    }
+                    state.update(|s| {
+                        s.received += 1;
+                        s.last_event_at_ms = Some(now.timestamp_millis());
+                        s.last_event_id = event.id.clone().or(s.last_event_id.take());
+                    });
+                }
+                Err(e) => {
+                    eprintln!("Rejected push: {}", e);
+                    state.update(|s| s.rejected += 1);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keep the push stream open while signed in to the backend
+pub fn spawn_push_stream(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let http = match reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+        {
+            Ok(http) => http,
+            Err(e) => {
+                eprintln!("Push stream disabled: {}", e);
+                return;
+            }
+        };
+        let mut attempts = 0;
+        let mut seen = SeenEnvelopes::default();
+        let mut just_refreshed = false;
+        loop {
+            let credentials = auth::backend_access_token()
+                .and_then(|token| Ok(token.zip(auth::backend_push_secret()?)));
+            let Ok(Some((token, secret))) = credentials else {
+                tokio::time::sleep(SIGNED_OUT_INTERVAL).await;
+                continue;
+            };
+
+            let result = match run_stream(&app, &http, &token, &secret, &mut seen).await {
+                // Refresh an expired access token and reconnect right away,
+                // once; a token refused again waits like any failure
+                Err(e) if e == STREAM_UNAUTHORIZED && !just_refreshed => {
+                    let refreshed = match ai::backend_url(&app) {
+                        Ok(url) => auth::refresh_backend_tokens(&http, &url).await,
+                        Err(e) => Err(e),
+                    };
+                    match refreshed {
+                        Ok(()) => {
+                            just_refreshed = true;
+                            continue;
+                        }
+                        Err(refresh_error) => Err(format!("{}: {}", e, refresh_error)),
+                    }
+                }
+                other => other,
+            };
+            just_refreshed = false;
+            let state = app.state::<PushState>();
+            state.update(|s| {
+                s.connected = false;
+                s.last_error = result.as_ref().err().cloned();
+            });
+            attempts = match result {
+                Ok(()) => 1,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    attempts + 1
+                }
+            };
+            tokio::time::sleep(reconnect_delay(attempts)).await;
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the push stream's connection state
+#[tauri::command]
+pub fn get_push_status(push: State<'_, PushState>) -> Result<PushStatus, String> {
+    crate::perf::trace_command!();
+    push.0
+        .lock()
+        .map(|status| status.clone())
+        .map_err(|e| format!("Failed to read push status: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+        signature(secret, timestamp, body)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_open_envelope() {
+        let body = r#"{"type":"plan_updated","date":"2026-10-16"}"#;
+        let envelope = serde_json::json!({
+            "timestamp": 1_000,
+            "body": body,
+            "signature": sign("secret", 1_000, body),
+        })
+        .to_string();
+
+        let mut parser = SseParser::default();
+        let events = parser.feed(format!(": ping\nid: 7\ndata: {}\n\n", envelope).as_bytes());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+
+        let mut seen = SeenEnvelopes::default();
+        assert!(open_envelope("other", &envelope, 1_060, &mut seen).is_err());
+        assert!(open_envelope("secret", &envelope, 2_000, &mut seen).is_err());
+        assert_eq!(
+            open_envelope("secret", &events[0].data, 1_060, &mut seen),
+            Ok(PushEvent::PlanUpdated {
+                date: "2026-10-16".to_string()
+            })
+        );
+        assert_eq!(
+            open_envelope("secret", &envelope, 1_070, &mut seen),
+            Err("Push envelope was replayed".to_string())
+        );
+
+        // A timestamp far from now can't overflow the age check
+        let body = "{}";
+        let envelope = serde_json::json!({
+            "timestamp": i64::MIN,
+            "body": body,
+            "signature": sign("secret", i64::MIN, body),
+        })
+        .to_string();
+        assert!(open_envelope("secret", &envelope, i64::MAX, &mut seen).is_err());
+    }
+}
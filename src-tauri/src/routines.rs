@@ -0,0 +1,623 @@
+//! Morning and evening routines
+//!
+//! A routine runs a fixed sequence of steps once a day at its configured time:
+//! - morning: request a sync, generate the day's plan (the daily note) and
+//!   notify about the reading digest of yesterday's bulk mail
+//! - evening: roll over the day's open tasks and prepare tomorrow's preview
+//!
+//! Routines are off by default. `spawn_routines` checks every minute like the
+//! daily note scheduler; a routine whose time has passed runs right away.
+//! Every step runs even when an earlier one failed, and each reports its own
+//! result: the run is emitted on `routine:finished` and kept as the routine's
+//! last run (`get_routine_runs`). Failed steps are retried after
+//! `RETRY_AFTER` until they succeed or the day ends; the steps that already
+//! succeeded don't run again.
+//!
+//! The steps reuse the regular jobs, which guard against running twice: the
+//! plan step skips a day that already has a note, and the rollover step a day
+//! that the rollover job already handled.
+
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::cache::CacheState;
+use crate::data_pipeline::{EventSummary, TaskSummary};
+use crate::google::{calendar, GoogleClient};
+use crate::storage::{self, LocalStorage};
+use crate::sync::{SyncScheduler, SYNC_RESUME_EVENT};
+use crate::{daily_note, digest, holidays, notifications, planner, processing};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+/// Emitted with a `RoutineRun` after every run
+pub const ROUTINE_FINISHED_EVENT: &str = "routine:finished";
+
+pub(crate) const ROUTINES_STORE_FILE: &str = "routines.json";
+const SETTINGS_KEY: &str = "settings";
+const LAST_RUNS_KEY: &str = "last_runs";
+const TOMORROW_PREVIEW_KEY: &str = "tomorrow_preview";
+
+/// How often the scheduler checks whether a routine is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Wait before retrying the failed steps of a run
+const RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Routine {
+    Morning,
+    Evening,
+}
+
+impl Routine {
+    fn steps(self) -> &'static [RoutineStep] {
+        match self {
+            Routine::Morning => &[
+                RoutineStep::Sync,
+                RoutineStep::GeneratePlan,
+                RoutineStep::DigestNotification,
+            ],
+            Routine::Evening => &[RoutineStep::Rollover, RoutineStep::TomorrowPreview],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutineStep {
+    Sync,
+    GeneratePlan,
+    DigestNotification,
+    Rollover,
+    TomorrowPreview,
+}
+
+/// When a routine runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutineHook {
+    pub enabled: bool,
+    /// Local time, HH:MM
+    pub time: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutineSettings {
+    pub morning: RoutineHook,
+    pub evening: RoutineHook,
+}
+
+impl Default for RoutineSettings {
+    fn default() -> Self {
+        Self {
+            morning: RoutineHook {
+                enabled: false,
+                time: "07:30".to_string(),
+            },
+            evening: RoutineHook {
+                enabled: false,
+                time: "18:00".to_string(),
+            },
+        }
+    }
+}
+
+impl RoutineSettings {
+    fn hook(&self, routine: Routine) -> &RoutineHook {
+        match routine {
+            Routine::Morning => &self.morning,
+            Routine::Evening => &self.evening,
+        }
+    }
+}
+
+/// Outcome of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepResult {
+    pub step: RoutineStep,
+    pub ok: bool,
+    /// What the step did, or why it failed
+    pub detail: String,
+    pub finished_at_ms: i64,
+}
+
+/// A routine's run for one day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutineRun {
+    pub routine: Routine,
+    /// YYYY-MM-DD
+    pub date: String,
+    pub started_at_ms: i64,
+    pub finished_at_ms: i64,
+    pub steps: Vec<StepResult>,
+}
+
+impl RoutineRun {
+    pub fn succeeded(&self) -> bool {
+        self.steps.iter().all(|s| s.ok)
+    }
+}
+
+/// The last run of each routine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutineRuns {
+    pub morning: Option<RoutineRun>,
+    pub evening: Option<RoutineRun>,
+}
+
+impl RoutineRuns {
+    fn get(&self, routine: Routine) -> Option<&RoutineRun> {
+        match routine {
+            Routine::Morning => self.morning.as_ref(),
+            Routine::Evening => self.evening.as_ref(),
+        }
+    }
+
+    fn set(&mut self, run: RoutineRun) {
+        match run.routine {
+            Routine::Morning => self.morning = Some(run),
+            Routine::Evening => self.evening = Some(run),
+        }
+    }
+}
+
+/// Tomorrow's meetings and due tasks, prepared by the evening routine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TomorrowPreview {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub meetings: Vec<EventSummary>,
+    pub tasks: Vec<TaskSummary>,
+    pub holiday_notice: Option<String>,
+    pub generated_at_ms: i64,
+}
+
+impl TomorrowPreview {
+    /// Notification text
+    pub fn summary(&self) -> String {
+        let count = |n: usize, what: &str| match n {
+            0 => format!("No {}s", what),
+            1 => format!("1 {}", what),
+            n => format!("{} {}s", n, what),
+        };
+        let mut summary = count(self.meetings.len(), "meeting");
+        let first = self
+            .meetings
+            .iter()
+            .filter(|m| !m.is_all_day)
+            .map(|m| m.start_ms)
+            .min()
+            .and_then(chrono::DateTime::from_timestamp_millis);
+        if let Some(first) = first {
+            summary.push_str(&format!(
+                " (first at {})",
+                first.with_timezone(&Local).format("%H:%M")
+            ));
+        }
+        summary.push_str(&format!(", {} due", count(self.tasks.len(), "task")));
+        if let Some(notice) = &self.holiday_notice {
+            summary.push_str(&format!(". {}", notice));
+        }
+        summary
+    }
+}
+
+// ============================================================================
+// Schedule
+// ============================================================================
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| format!("Invalid time: {} (expected HH:MM)", time))
+}
+
+/// The day `routine` should have run for by `now`, if its time has passed
+fn due_date(settings: &RoutineSettings, routine: Routine, now: NaiveDateTime) -> Option<NaiveDate> {
+    let hook = settings.hook(routine);
+    let time = parse_time(&hook.time).ok()?;
+    (hook.enabled && now.time() >= time).then(|| now.date())
+}
+
+/// Steps of `routine` still to run on `date`: all of them on a new day, the
+/// failed ones when `last` already ran that day
+fn pending_steps(routine: Routine, last: Option<&RoutineRun>, date: &str) -> Vec<RoutineStep> {
+    match last.filter(|run| run.date == date) {
+        Some(run) => run.steps.iter().filter(|s| !s.ok).map(|s| s.step).collect(),
+        None => routine.steps().to_vec(),
+    }
+}
+
+fn load_settings(app: &AppHandle) -> Result<RoutineSettings, String> {
+    let store = storage::fs::settings_store(app, ROUTINES_STORE_FILE)
+        .map_err(|e| format!("Failed to access routines store: {}", e))?;
+
+    Ok(store
+        .get(SETTINGS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn load_runs(app: &AppHandle) -> Result<RoutineRuns, String> {
+    let store = storage::fs::settings_store(app, ROUTINES_STORE_FILE)
+        .map_err(|e| format!("Failed to access routines store: {}", e))?;
+
+    Ok(store
+        .get(LAST_RUNS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_value(app: &AppHandle, key: &str, value: serde_json::Value) -> Result<(), String> {
+    let store = storage::fs::settings_store(app, ROUTINES_STORE_FILE)
+        .map_err(|e| format!("Failed to access routines store: {}", e))?;
+    store.set(key, value);
+    storage::fs::save_store(app, ROUTINES_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save routines store: {}", e))
+}
+
+// ============================================================================
+// Steps
+// ============================================================================
+
+async fn notify(app: &AppHandle, notification_type: &str, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("Failed to show {} notification: {}", notification_type, e);
+        return;
+    }
+    notifications::record_history(
+        &app.state::<TokenStore>(),
+        &app.state::<LocalStorage>(),
+        Some(notification_type),
+        title,
+        Some(body),
+    )
+    .await;
+}
+
+/// Wake the background sync loops and have the windows refetch
+fn request_sync(app: &AppHandle) -> Result<String, String> {
+    app.state::<SyncScheduler>().resync_now();
+    app.emit(SYNC_RESUME_EVENT, ())
+        .map_err(|e| format!("Failed to emit {}: {}", SYNC_RESUME_EVENT, e))?;
+    Ok("Sync requested".to_string())
+}
+
+async fn generate_plan(app: &AppHandle, today: NaiveDate) -> Result<String, String> {
+    if daily_note::run_pipeline(app, today).await? {
+        Ok("Generated today's plan".to_string())
+    } else {
+        Ok("Today's plan was already generated".to_string())
+    }
+}
+
+/// Notify about the reading digest of the day before `today`
+async fn digest_notification(app: &AppHandle, today: NaiveDate) -> Result<String, String> {
+    let yesterday = today.pred_opt().ok_or("Invalid routine date")?;
+    let digest = digest::reading_digest(
+        app,
+        &app.state::<TokenStore>(),
+        &app.state::<GoogleClient>(),
+        yesterday,
+        false,
+    )
+    .await?;
+    if digest.thread_ids.is_empty() {
+        return Ok("No bulk mail to digest".to_string());
+    }
+
+    let body = format!(
+        "{} emails from {} senders since yesterday",
+        digest.thread_ids.len(),
+        digest.senders.len()
+    );
+    notify(app, "reading_digest", "Reading digest", &body).await;
+    Ok(body)
+}
+
+async fn rollover(app: &AppHandle, today: NaiveDate) -> Result<String, String> {
+    Ok(match planner::roll_over_day(app, today).await? {
+        Some(summary) => summary
+            .message()
+            .unwrap_or_else(|| "No open tasks left today".to_string()),
+        None => "Today's tasks were already rolled over".to_string(),
+    })
+}
+
+/// Collect tomorrow's meetings, due tasks and holiday
+async fn tomorrow_preview(app: &AppHandle, tomorrow: NaiveDate) -> Result<TomorrowPreview, String> {
+    let token_store = app.state::<TokenStore>();
+    let client = app.state::<GoogleClient>();
+    let start_ms = processing::local_midnight_ms(tomorrow)?;
+    let end_ms = processing::local_midnight_ms(tomorrow + chrono::Duration::days(1))?;
+
+    let mut meetings: Vec<EventSummary> =
+        calendar::events_between(&token_store, &client, start_ms, end_ms, None)
+            .await?
+            .into_iter()
+            .filter(|e| e.status.as_deref() != Some("cancelled"))
+            .filter(|e| calendar::working_location_of(e).is_none())
+            .filter_map(daily_note::event_summary)
+            .collect();
+    meetings.sort_by_key(|m| m.start_ms);
+
+    let tasks = planner::due_task_items(&token_store, &client)
+        .await?
+        .into_iter()
+        .filter(|item| (start_ms..end_ms).contains(&item.at_ms))
+        .map(|item| TaskSummary {
+            id: item.id,
+            title: item.title,
+            due_ms: Some(item.at_ms),
+            completed: false,
+            list_name: None,
+        })
+        .collect();
+
+    // A missing holiday notice never blocks the preview
+    let holiday_notice = holidays::holiday_on(
+        app,
+        &client,
+        &app.state::<CacheState>(),
+        &token_store,
+        tomorrow,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to check tomorrow's holidays: {}", e);
+        None
+    })
+    .as_ref()
+    .map(holidays::tomorrow_notice);
+
+    Ok(TomorrowPreview {
+        date: tomorrow.format("%Y-%m-%d").to_string(),
+        meetings,
+        tasks,
+        holiday_notice,
+        generated_at_ms: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+async fn prepare_tomorrow(app: &AppHandle, today: NaiveDate) -> Result<String, String> {
+    let tomorrow = today.succ_opt().ok_or("Invalid routine date")?;
+    let account = app.state::<TokenStore>().account_context().await?;
+    let preview = tomorrow_preview(app, tomorrow).await?;
+    let value = serde_json::to_value(&preview)
+        .map_err(|e| format!("Failed to serialize tomorrow preview: {}", e))?;
+    save_value(app, &account.store_key(TOMORROW_PREVIEW_KEY), value)?;
+
+    let body = preview.summary();
+    notify(app, "tomorrow_preview", "Tomorrow", &body).await;
+    Ok(body)
+}
+
+async fn run_step(app: &AppHandle, step: RoutineStep, today: NaiveDate) -> StepResult {
+    let result = match step {
+        RoutineStep::Sync => request_sync(app),
+        RoutineStep::GeneratePlan => generate_plan(app, today).await,
+        RoutineStep::DigestNotification => digest_notification(app, today).await,
+        RoutineStep::Rollover => rollover(app, today).await,
+        RoutineStep::TomorrowPreview => prepare_tomorrow(app, today).await,
+    };
+    let (ok, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, e),
+    };
+    StepResult {
+        step,
+        ok,
+        detail,
+        finished_at_ms: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+/// Run `steps` of `routine` for `today`, merging the results into `previous`
+/// when it is a run of the same day
+async fn run_routine(
+    app: &AppHandle,
+    routine: Routine,
+    today: NaiveDate,
+    steps: &[RoutineStep],
+    previous: Option<RoutineRun>,
+) -> RoutineRun {
+    let date = today.format("%Y-%m-%d").to_string();
+    let mut run = previous
+        .filter(|run| run.date == date)
+        .unwrap_or_else(|| RoutineRun {
+            routine,
+            date,
+            started_at_ms: chrono::Utc::now().timestamp_millis(),
+            finished_at_ms: 0,
+            steps: Vec::new(),
+        });
+
+    for &step in steps {
+        let result = run_step(app, step, today).await;
+        if !result.ok {
+            eprintln!("Routine step {:?} failed: {}", step, result.detail);
+        }
+        match run.steps.iter_mut().find(|s| s.step == step) {
+            Some(slot) => *slot = result,
+            None => run.steps.push(result),
+        }
+    }
+    run.finished_at_ms = chrono::Utc::now().timestamp_millis();
+
+    let mut runs = load_runs(app).unwrap_or_default();
+    runs.set(run.clone());
+    match serde_json::to_value(&runs) {
+        Ok(value) => {
+            if let Err(e) = save_value(app, LAST_RUNS_KEY, value) {
+                eprintln!("{}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to serialize routine runs: {}", e),
+    }
+    if app
+        .try_state::<AppLockState>()
+        .is_some_and(|lock| lock.is_locked())
+    {
+        return run;
+    }
+    if let Err(e) = app.emit(ROUTINE_FINISHED_EVENT, &run) {
+        eprintln!("Failed to emit {}: {}", ROUTINE_FINISHED_EVENT, e);
+    }
+    run
+}
+
+/// Start the routine scheduler
+pub fn spawn_routines(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut retry_at: [Option<Instant>; 2] = [None, None];
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+
+            let settings = load_settings(&app).unwrap_or_else(|e| {
+                eprintln!("Failed to load routine settings: {}", e);
+                RoutineSettings::default()
+            });
+            let now = Local::now().naive_local();
+            for (i, routine) in [Routine::Morning, Routine::Evening].into_iter().enumerate() {
+                let Some(today) = due_date(&settings, routine, now) else {
+                    continue;
+                };
+                if retry_at[i].is_some_and(|at| at.elapsed() < RETRY_AFTER) {
+                    continue;
+                }
+                // Signed out: run once someone signs in
+                if app.state::<TokenStore>().account_context().await.is_err() {
+                    continue;
+                }
+
+                let previous = load_runs(&app).unwrap_or_default().get(routine).cloned();
+                let date = today.format("%Y-%m-%d").to_string();
+                let steps = pending_steps(routine, previous.as_ref(), &date);
+                if steps.is_empty() {
+                    continue;
+                }
+                let run = run_routine(&app, routine, today, &steps, previous).await;
+                retry_at[i] = (!run.succeeded()).then(Instant::now);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the routine settings
+#[tauri::command]
+pub async fn get_routine_settings(app: AppHandle) -> Result<RoutineSettings, String> {
+    crate::perf::trace_command!();
+    load_settings(&app)
+}
+
+/// Save the routine settings
+#[tauri::command]
+pub async fn set_routine_settings(app: AppHandle, settings: RoutineSettings) -> Result<(), String> {
+    crate::perf::trace_command!();
+    parse_time(&settings.morning.time)?;
+    parse_time(&settings.evening.time)?;
+    save_value(&app, SETTINGS_KEY, serde_json::json!(settings))
+}
+
+/// Get the last run of each routine
+#[tauri::command]
+pub async fn get_routine_runs(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+) -> Result<RoutineRuns, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    load_runs(&app)
+}
+
+/// Run every step of `routine` now, whatever its schedule
+#[tauri::command]
+pub async fn run_routine_now(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    routine: Routine,
+) -> Result<RoutineRun, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    token_store.account_context().await?;
+    let today = Local::now().date_naive();
+    Ok(run_routine(&app, routine, today, routine.steps(), None).await)
+}
+
+/// Get the preview of tomorrow the last evening routine prepared for the
+/// signed-in account
+#[tauri::command]
+pub async fn get_tomorrow_preview(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+) -> Result<Option<TomorrowPreview>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let store = storage::fs::settings_store(&app, ROUTINES_STORE_FILE)
+        .map_err(|e| format!("Failed to access routines store: {}", e))?;
+    Ok(store
+        .get(account.store_key(TOMORROW_PREVIEW_KEY))
+        .and_then(|v| serde_json::from_value(v).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(step: RoutineStep, ok: bool) -> StepResult {
+        StepResult {
+            step,
+            ok,
+            detail: String::new(),
+            finished_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_due_date_and_pending_steps() {
+        let mut settings = RoutineSettings::default();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let at = |h, m| day.and_hms_opt(h, m, 0).unwrap();
+        assert_eq!(due_date(&settings, Routine::Morning, at(9, 0)), None);
+        settings.morning.enabled = true;
+        assert_eq!(due_date(&settings, Routine::Morning, at(7, 29)), None);
+        assert_eq!(due_date(&settings, Routine::Morning, at(7, 30)), Some(day));
+        assert_eq!(due_date(&settings, Routine::Evening, at(19, 0)), None);
+
+        assert_eq!(
+            pending_steps(Routine::Evening, None, "2024-03-04"),
+            vec![RoutineStep::Rollover, RoutineStep::TomorrowPreview]
+        );
+        let run = RoutineRun {
+            routine: Routine::Morning,
+            date: "2024-03-04".to_string(),
+            started_at_ms: 0,
+            finished_at_ms: 0,
+            steps: vec![
+                result(RoutineStep::Sync, true),
+                result(RoutineStep::GeneratePlan, false),
+                result(RoutineStep::DigestNotification, true),
+            ],
+        };
+        assert!(!run.succeeded());
+        assert_eq!(
+            pending_steps(Routine::Morning, Some(&run), "2024-03-04"),
+            vec![RoutineStep::GeneratePlan]
+        );
+        assert_eq!(
+            pending_steps(Routine::Morning, Some(&run), "2024-03-05").len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Inbox rules learned from behaviour
+//!
+//! `suggest_rules` mines two local histories for senders the user handles the
+//! same way every time:
+//! - triage decisions (see `triage`): threads archived or trashed while still
+//!   unread
+//! - analytics metadata (see `analytics`): senders whose mail is never opened
+//!
+//! Each candidate carries a confidence score and the evidence behind it, and
+//! `adopt_rule` saves it in one click. Adopted rules pre-fill the suggested
+//! action of matching threads in triage sessions.
+
+use crate::account::AccountContext;
+use crate::analytics::MessageMetadata;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::policy;
+use crate::settings::{self, SettingsSection};
+use crate::storage::{LocalStorage, EMAIL_METADATA, MAIL_RULES, TRIAGE_HISTORY};
+use crate::triage::{TriageAction, TriageRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+/// Triage decisions on a sender before a rule can be suggested
+const MIN_TRIAGE_DECISIONS: u32 = 3;
+/// Messages from a sender before never opening them counts as a pattern
+const MIN_IGNORED_MESSAGES: u32 = 5;
+/// Share of consistent decisions (or unopened messages) required
+const MIN_CONSISTENCY: f64 = 0.8;
+/// Never opening mail is weaker evidence than explicitly archiving it
+const IGNORED_WEIGHT: f64 = 0.7;
+/// Only recent metadata reflects current habits
+const HISTORY_WINDOW_MS: i64 = 90 * 86_400_000;
+/// Suggestions returned at most
+const SUGGESTION_LIMIT: usize = 20;
+
+/// What a rule does to matching threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Archive,
+    MarkRead,
+    Trash,
+}
+
+impl RuleAction {
+    fn from_triage(action: TriageAction) -> Option<Self> {
+        match action {
+            TriageAction::Archive => Some(RuleAction::Archive),
+            TriageAction::MarkRead => Some(RuleAction::MarkRead),
+            TriageAction::Trash => Some(RuleAction::Trash),
+            TriageAction::Star | TriageAction::Skip => None,
+        }
+    }
+
+    pub fn as_triage(self) -> TriageAction {
+        match self {
+            RuleAction::Archive => TriageAction::Archive,
+            RuleAction::MarkRead => TriageAction::MarkRead,
+            RuleAction::Trash => TriageAction::Trash,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RuleAction::Archive => "archive",
+            RuleAction::MarkRead => "mark_read",
+            RuleAction::Trash => "trash",
+        }
+    }
+}
+
+/// A rule applying an action to every thread from one sender
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailRule {
+    pub id: String,
+    pub from_email: String,
+    pub action: RuleAction,
+    pub created_at_ms: i64,
+}
+
+/// What a suggestion is based on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleEvidence {
+    /// Triage decisions on the sender
+    pub triage_decisions: u32,
+    /// Of those, decisions taking the suggested action on an unread thread
+    pub matching_decisions: u32,
+    /// Recorded messages from the sender
+    pub messages: u32,
+    /// Of those, messages never opened
+    pub unopened: u32,
+}
+
+/// A candidate rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSuggestion {
+    pub rule: MailRule,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    pub evidence: RuleEvidence,
+}
+
+fn rule_id(from_email: &str, action: RuleAction) -> String {
+    format!("{}:{}", from_email, action.as_str())
+}
+
+/// Shrinks a consistency ratio toward zero while evidence is thin
+fn confidence(consistent: u32, total: u32) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let ratio = consistent as f64 / total as f64;
+    ratio * total as f64 / (total as f64 + 2.0)
+}
+
+/// Candidate rules from triage decisions and message metadata
+fn mine_suggestions(
+    history: &[TriageRecord],
+    metadata: &[MessageMetadata],
+    now_ms: i64,
+) -> Vec<RuleSuggestion> {
+    let mut evidence: HashMap<String, RuleEvidence> = HashMap::new();
+    let mut actions: HashMap<(String, RuleAction), u32> = HashMap::new();
+
+    for record in history {
+        let entry = evidence.entry(record.from_email.clone()).or_default();
+        entry.triage_decisions += 1;
+        if let Some(action) = RuleAction::from_triage(record.action) {
+            if record.was_unread {
+                *actions
+                    .entry((record.from_email.clone(), action))
+                    .or_default() += 1;
+            }
+        }
+    }
+    for m in metadata
+        .iter()
+        .filter(|m| m.date_ms >= now_ms - HISTORY_WINDOW_MS)
+    {
+        let entry = evidence.entry(m.from_email.clone()).or_default();
+        entry.messages += 1;
+        entry.unopened += !m.opened as u32;
+    }
+
+    let mut suggestions = Vec::new();
+    for (from_email, mut sender) in evidence {
+        // The action most often taken on the sender's unread threads
+        let best = actions
+            .iter()
+            .filter(|((email, _), _)| *email == from_email)
+            .max_by_key(|((_, action), count)| (**count, action.as_str()))
+            .map(|((_, action), count)| (*action, *count));
+
+        let from_triage = best.filter(|(_, count)| {
+            sender.triage_decisions >= MIN_TRIAGE_DECISIONS
+                && *count as f64 / sender.triage_decisions as f64 >= MIN_CONSISTENCY
+        });
+        let from_metadata = sender.messages >= MIN_IGNORED_MESSAGES
+            && sender.unopened as f64 / sender.messages as f64 >= MIN_CONSISTENCY;
+
+        let (action, score) = match (from_triage, from_metadata) {
+            (Some((action, count)), _) => {
+                sender.matching_decisions = count;
+                let mut score = confidence(count, sender.triage_decisions);
+                if from_metadata {
+                    score =
+                        score.max(IGNORED_WEIGHT * confidence(sender.unopened, sender.messages));
+                }
+                (action, score)
+            }
+            (None, true) => (
+                RuleAction::Archive,
+                IGNORED_WEIGHT * confidence(sender.unopened, sender.messages),
+            ),
+            (None, false) => continue,
+        };
+
+        suggestions.push(RuleSuggestion {
+            rule: MailRule {
+                id: rule_id(&from_email, action),
+                from_email,
+                action,
+                created_at_ms: now_ms,
+            },
+            confidence: score,
+            evidence: sender,
+        });
+    }
+
+    suggestions.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.rule.from_email.cmp(&b.rule.from_email))
+    });
+    suggestions.truncate(SUGGESTION_LIMIT);
+    suggestions
+}
+
+fn load_records<T: serde::de::DeserializeOwned>(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    collection: &str,
+) -> Result<Vec<T>, String> {
+    Ok(storage
+        .list(account, collection)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok())
+        .collect())
+}
+
+/// Adopted rules of the signed-in account, keyed by sender
+pub fn load_rules(
+    storage: &LocalStorage,
+    account: &AccountContext,
+) -> Result<HashMap<String, MailRule>, String> {
+    Ok(load_records::<MailRule>(storage, account, MAIL_RULES)?
+        .into_iter()
+        .map(|rule| (rule.from_email.clone(), rule))
+        .collect())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Suggest rules for senders the user consistently archives or ignores
+///
+/// Senders that already have a rule are left out.
+#[tauri::command]
+pub async fn suggest_rules(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<Vec<RuleSuggestion>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let history: Vec<TriageRecord> = load_records(&storage, &account, TRIAGE_HISTORY)?;
+    let metadata: Vec<MessageMetadata> = load_records(&storage, &account, EMAIL_METADATA)?;
+    let rules = load_rules(&storage, &account)?;
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    Ok(mine_suggestions(&history, &metadata, now_ms)
+        .into_iter()
+        .filter(|s| !rules.contains_key(&s.rule.from_email))
+        .collect())
+}
+
+/// Save a rule (usually a suggestion's); replaces any rule for the same sender
+#[tauri::command]
+pub async fn adopt_rule(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    rule: MailRule,
+) -> Result<MailRule, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let from_email = rule.from_email.trim().to_lowercase();
+    if !from_email.contains('@') {
+        return Err(format!("Invalid sender address: {}", rule.from_email));
+    }
+
+    if let Some(existing) = load_rules(&storage, &account)?.get(&from_email) {
+        storage.remove(&account, MAIL_RULES, &existing.id)?;
+    }
+    let rule = MailRule {
+        id: rule_id(&from_email, rule.action),
+        from_email,
+        action: rule.action,
+        created_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    let value =
+        serde_json::to_value(&rule).map_err(|e| format!("Failed to serialize rule: {}", e))?;
+    storage.put(&account, MAIL_RULES, &rule.id, value)?;
+    settings::touch(&app, SettingsSection::Rules);
+    Ok(rule)
+}
+
+/// List adopted rules
+#[tauri::command]
+pub async fn list_rules(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<Vec<MailRule>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let mut rules: Vec<MailRule> = load_rules(&storage, &account)?.into_values().collect();
+    rules.sort_by(|a, b| a.from_email.cmp(&b.from_email));
+    Ok(rules)
+}
+
+/// Delete an adopted rule; returns false if it didn't exist
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
+#[tauri::command]
+pub async fn delete_rule(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    rule_id: String,
+    confirmation: Option<String>,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    policy::authorize(&app, "delete_rule", &rule_id, confirmation.as_deref()).await?;
+    let account = token_store.account_context().await?;
+    let removed = storage.remove(&account, MAIL_RULES, &rule_id)?;
+    if removed {
+        settings::touch(&app, SettingsSection::Rules);
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(from: &str, action: TriageAction, was_unread: bool) -> TriageRecord {
+        TriageRecord {
+            thread_id: "t".to_string(),
+            from_email: from.to_string(),
+            action,
+            was_unread,
+            decided_at_ms: 0,
+        }
+    }
+
+    fn metadata(from: &str, opened: bool, date_ms: i64) -> MessageMetadata {
+        MessageMetadata {
+            thread_id: "t".to_string(),
+            from_name: String::new(),
+            from_email: from.to_string(),
+            domain: "example.com".to_string(),
+            date_ms,
+            opened,
+            has_list_unsubscribe: false,
+        }
+    }
+
+    #[test]
+    fn test_mine_suggestions() {
+        let now_ms = 1_768_000_000_000;
+        let mut history = vec![record("promo@example.com", TriageAction::Archive, true); 4];
+        history.push(record("promo@example.com", TriageAction::Skip, true));
+        // Read before archiving: not a pattern of ignoring
+        history.extend(vec![
+            record("boss@example.com", TriageAction::Archive, false);
+            5
+        ]);
+
+        let mut messages = vec![metadata("digest@example.com", false, now_ms); 6];
+        messages.push(metadata("digest@example.com", true, now_ms));
+        // Outside the history window
+        messages.extend(vec![metadata("old@example.com", false, 0); 10]);
+
+        let suggestions = mine_suggestions(&history, &messages, now_ms);
+        let senders: Vec<&str> = suggestions
+            .iter()
+            .map(|s| s.rule.from_email.as_str())
+            .collect();
+        assert_eq!(senders, ["promo@example.com", "digest@example.com"]);
+
+        let promo = &suggestions[0];
+        assert_eq!(promo.rule.action, RuleAction::Archive);
+        assert_eq!(promo.rule.id, "promo@example.com:archive");
+        assert_eq!(promo.evidence.matching_decisions, 4);
+        // 4 of 5 consistent, shrunk for the small sample
+        assert!((promo.confidence - 0.8 * 5.0 / 7.0).abs() < 1e-9);
+        assert!(suggestions[1].confidence < promo.confidence);
+    }
+}
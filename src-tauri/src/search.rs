@@ -2,10 +2,33 @@
 //!
 //! Provides regex-based search for emails and tasks.
 //! Uses Rust for speed and safety.
+//!
+//! Fetched and backfilled threads are also kept in the `SEARCH_INDEX`
+//! storage collection, so `search_mailbox` finds mail that was never loaded
+//! into the UI.
+//!
+//! The index can drift from the analytics history (`EMAIL_METADATA`), which
+//! is recorded alongside it: retention purges the two collections separately
+//! and a crash can leave an unreadable entry. `verify_index` reports corrupt,
+//! orphaned and missing entries and can repair them; `rebuild_index` re-fetches
+//! every known thread. Both report progress with `INDEX_PROGRESS_EVENT`.
 
+use crate::account::AccountContext;
+use crate::analytics::{self, MessageMetadata};
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::google::mailbox::Mailbox;
+use crate::google::types::GmailThreadDetail;
+use crate::google::{gmail, GoogleClient};
 use crate::processing::{EmailInput, TaskInput};
-use regex::RegexBuilder;
+use crate::storage::{LocalStorage, StoredRecord, EMAIL_METADATA, SEARCH_INDEX};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Results returned by `search_mailbox` when no limit is given
+const DEFAULT_MAILBOX_RESULTS: usize = 50;
 
 /// Search results
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +40,7 @@ pub struct SearchResult {
 /// Search for tasks using regex OR simple string matching
 #[tauri::command]
 pub fn search_tasks(query: &str, tasks: Vec<TaskInput>) -> SearchResult {
+    crate::perf::trace_command!();
     let regex = RegexBuilder::new(query).case_insensitive(true).build();
 
     let matches: Vec<String> = match regex {
@@ -45,6 +69,7 @@ pub fn search_tasks(query: &str, tasks: Vec<TaskInput>) -> SearchResult {
 /// Search for emails using regex OR simple string matching
 #[tauri::command]
 pub fn search_emails(query: &str, emails: Vec<EmailInput>) -> SearchResult {
+    crate::perf::trace_command!();
     let regex = RegexBuilder::new(query).case_insensitive(true).build();
 
     let matches: Vec<String> = match regex {
@@ -76,6 +101,324 @@ pub fn search_emails(query: &str, emails: Vec<EmailInput>) -> SearchResult {
     }
 }
 
+// ============================================================================
+// Mailbox Index
+// ============================================================================
+
+/// An indexed thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedThread {
+    pub thread_id: String,
+    pub subject: String,
+    /// From headers of the thread's messages, oldest first, deduplicated
+    pub senders: Vec<String>,
+    /// Snippet of the latest message
+    pub snippet: String,
+    pub last_date_ms: i64,
+    pub message_count: usize,
+}
+
+fn indexed_thread(thread: &GmailThreadDetail) -> Option<IndexedThread> {
+    let messages = thread.messages.as_deref()?;
+    let last = messages.last()?;
+    let mut senders: Vec<String> = Vec::new();
+    for from in messages.iter().filter_map(|m| analytics::header(m, "From")) {
+        if !senders.iter().any(|s| s == from) {
+            senders.push(from.to_string());
+        }
+    }
+
+    Some(IndexedThread {
+        thread_id: thread.id.clone(),
+        subject: messages
+            .first()
+            .and_then(|m| analytics::header(m, "Subject"))
+            .unwrap_or_default()
+            .to_string(),
+        senders,
+        snippet: last.snippet.clone(),
+        last_date_ms: messages
+            .iter()
+            .filter_map(|m| m.internal_date.as_deref()?.parse().ok())
+            .max()
+            .unwrap_or_default(),
+        message_count: messages.len(),
+    })
+}
+
+/// Add (or refresh) threads in the mailbox index
+pub fn index_threads(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    threads: &[GmailThreadDetail],
+) -> Result<usize, String> {
+    let mut records = Vec::new();
+    for thread in threads.iter().filter_map(indexed_thread) {
+        let value = serde_json::to_value(&thread)
+            .map_err(|e| format!("Failed to serialize index entry: {}", e))?;
+        records.push((thread.thread_id, value));
+    }
+
+    let indexed = records.len();
+    storage.put_many(account, SEARCH_INDEX, records)?;
+    Ok(indexed)
+}
+
+/// Regex match, or a plain case-insensitive match for invalid patterns
+enum Matcher {
+    Regex(Regex),
+    Contains(String),
+}
+
+impl Matcher {
+    fn new(query: &str) -> Self {
+        match RegexBuilder::new(query).case_insensitive(true).build() {
+            Ok(re) => Matcher::Regex(re),
+            Err(_) => Matcher::Contains(query.to_lowercase()),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Regex(re) => re.is_match(text),
+            Matcher::Contains(query) => text.to_lowercase().contains(query),
+        }
+    }
+
+    fn matches_thread(&self, thread: &IndexedThread) -> bool {
+        self.is_match(&thread.subject)
+            || self.is_match(&thread.snippet)
+            || thread.senders.iter().any(|s| self.is_match(s))
+    }
+}
+
+/// Search the local mailbox index, newest threads first
+#[tauri::command]
+pub async fn search_mailbox(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<IndexedThread>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let matcher = Matcher::new(&query);
+
+    let mut threads: Vec<IndexedThread> = storage
+        .list(&account, SEARCH_INDEX)?
+        .into_values()
+        .filter_map(|record| serde_json::from_value(record.value).ok())
+        .filter(|thread| matcher.matches_thread(thread))
+        .collect();
+    threads.sort_by_key(|t| std::cmp::Reverse(t.last_date_ms));
+    threads.truncate(limit.unwrap_or(DEFAULT_MAILBOX_RESULTS));
+    Ok(threads)
+}
+
+// ============================================================================
+// Index Integrity
+// ============================================================================
+
+/// Emitted with an `IndexProgress` after every re-indexed batch
+pub const INDEX_PROGRESS_EVENT: &str = "search:index";
+
+/// Threads re-fetched per batch
+const REINDEX_BATCH: usize = 50;
+const REINDEX_PARALLELISM: usize = 4;
+
+/// Which index operation is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexOperation {
+    Verify,
+    Rebuild,
+}
+
+/// Progress of a running index operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexProgress {
+    pub operation: IndexOperation,
+    /// Threads re-fetched so far (including failures)
+    pub done: usize,
+    pub total: usize,
+    pub failed: usize,
+}
+
+/// State of the index against the analytics history
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexReport {
+    /// Readable index entries
+    pub indexed: usize,
+    /// Entries that don't parse or are stored under another thread's ID
+    pub corrupt: Vec<String>,
+    /// Entries of threads missing from the analytics history
+    pub orphaned: Vec<String>,
+    /// Known threads without a readable entry
+    pub missing: Vec<String>,
+    /// Threads re-indexed by the repair
+    pub repaired: usize,
+    /// Threads the repair couldn't fetch
+    pub failed: usize,
+}
+
+impl IndexReport {
+    pub fn is_consistent(&self) -> bool {
+        self.corrupt.is_empty() && self.orphaned.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Thread IDs of the analytics history
+fn known_threads(metadata: HashMap<String, StoredRecord>) -> BTreeSet<String> {
+    metadata
+        .into_values()
+        .filter_map(|record| serde_json::from_value::<MessageMetadata>(record.value).ok())
+        .map(|m| m.thread_id)
+        .collect()
+}
+
+/// Compare the index with the threads it should hold
+fn check_index(index: &HashMap<String, StoredRecord>, known: &BTreeSet<String>) -> IndexReport {
+    let mut report = IndexReport::default();
+    let mut readable = BTreeSet::new();
+    for (id, record) in index {
+        match serde_json::from_value::<IndexedThread>(record.value.clone()) {
+            Ok(thread) if thread.thread_id == *id => {
+                readable.insert(id.clone());
+                if !known.contains(id) {
+                    report.orphaned.push(id.clone());
+                }
+            }
+            _ => report.corrupt.push(id.clone()),
+        }
+    }
+    report.indexed = readable.len();
+    report.missing = known.difference(&readable).cloned().collect();
+    report.corrupt.sort();
+    report.orphaned.sort();
+    report
+}
+
+fn emit_index_progress(app: &AppHandle, progress: &IndexProgress) {
+    if let Err(e) = app.emit(INDEX_PROGRESS_EVENT, progress) {
+        eprintln!("Failed to emit {}: {}", INDEX_PROGRESS_EVENT, e);
+    }
+}
+
+/// Re-fetch and index threads batch by batch, returning (indexed, failed)
+async fn reindex_threads(
+    app: &AppHandle,
+    account: &AccountContext,
+    operation: IndexOperation,
+    thread_ids: &[String],
+) -> Result<(usize, usize), String> {
+    let token_store = app.state::<TokenStore>();
+    let client = app.state::<GoogleClient>();
+    let storage = app.state::<LocalStorage>();
+    let mut progress = IndexProgress {
+        operation,
+        done: 0,
+        total: thread_ids.len(),
+        failed: 0,
+    };
+    emit_index_progress(app, &progress);
+
+    let mut indexed = 0;
+    for batch in thread_ids.chunks(REINDEX_BATCH) {
+        let hydration = gmail::hydrate_threads(
+            &token_store,
+            &client,
+            &Mailbox::Own,
+            batch,
+            REINDEX_PARALLELISM,
+        )
+        .await;
+        indexed += index_threads(&storage, account, &hydration.threads)?;
+        progress.done += batch.len();
+        progress.failed += hydration.errors.len();
+        emit_index_progress(app, &progress);
+    }
+    Ok((indexed, progress.failed))
+}
+
+/// Check the mailbox index against the analytics history
+///
+/// With `repair`, corrupt and orphaned entries are dropped and missing
+/// threads are re-fetched from Gmail.
+#[tauri::command]
+pub async fn verify_index(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    repair: Option<bool>,
+) -> Result<IndexReport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let known = known_threads(storage.list(&account, EMAIL_METADATA)?);
+    let mut report = check_index(&storage.list(&account, SEARCH_INDEX)?, &known);
+    if !repair.unwrap_or(false) || report.is_consistent() {
+        return Ok(report);
+    }
+
+    let dropped: Vec<String> = report
+        .corrupt
+        .iter()
+        .chain(&report.orphaned)
+        .cloned()
+        .collect();
+    storage.remove_many(&account, SEARCH_INDEX, &dropped)?;
+
+    // Corrupt entries of known threads are re-fetched too
+    let mut refetch = report.missing.clone();
+    refetch.extend(
+        report
+            .corrupt
+            .iter()
+            .filter(|id| known.contains(*id))
+            .cloned(),
+    );
+    let (repaired, failed) =
+        reindex_threads(&app, &account, IndexOperation::Verify, &refetch).await?;
+    report.repaired = repaired;
+    report.failed = failed;
+    Ok(report)
+}
+
+/// Re-fetch every thread of the analytics history into the index
+///
+/// Entries are replaced in place, so search keeps working meanwhile; entries
+/// of unknown threads are dropped at the end.
+#[tauri::command]
+pub async fn rebuild_index(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+) -> Result<IndexReport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let known = known_threads(storage.list(&account, EMAIL_METADATA)?);
+    let thread_ids: Vec<String> = known.iter().cloned().collect();
+    let (repaired, failed) =
+        reindex_threads(&app, &account, IndexOperation::Rebuild, &thread_ids).await?;
+
+    let stale: Vec<String> = storage
+        .list(&account, SEARCH_INDEX)?
+        .into_keys()
+        .filter(|id| !known.contains(id))
+        .collect();
+    storage.remove_many(&account, SEARCH_INDEX, &stale)?;
+
+    let mut report = check_index(&storage.list(&account, SEARCH_INDEX)?, &known);
+    report.repaired = repaired;
+    report.failed = failed;
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,4 +447,55 @@ mod tests {
         let result = search_tasks("^Buy.*", tasks);
         assert_eq!(result.matches.len(), 1);
     }
+
+    #[test]
+    fn test_mailbox_matcher() {
+        let thread = IndexedThread {
+            thread_id: "t1".to_string(),
+            subject: "Q3 budget review".to_string(),
+            senders: vec!["Jane Doe <jane@example.com>".to_string()],
+            snippet: "Numbers attached".to_string(),
+            last_date_ms: 0,
+            message_count: 1,
+        };
+        assert!(Matcher::new("BUDGET").matches_thread(&thread));
+        assert!(Matcher::new("jane@").matches_thread(&thread));
+        assert!(Matcher::new("^Q[0-9] ").matches_thread(&thread));
+        // Invalid regex falls back to a plain match
+        assert!(!Matcher::new("review (").matches_thread(&thread));
+        assert!(Matcher::new("numbers att").matches_thread(&thread));
+    }
+
+    #[test]
+    fn test_check_index() {
+        let record = |value: serde_json::Value| StoredRecord {
+            value,
+            created_at_ms: 0,
+            updated_at_ms: 0,
+        };
+        let entry = |id: &str| {
+            record(serde_json::json!({
+                "thread_id": id,
+                "subject": "Hi",
+                "senders": [],
+                "snippet": "",
+                "last_date_ms": 0,
+                "message_count": 1,
+            }))
+        };
+        let index = HashMap::from([
+            ("t1".to_string(), entry("t1")),
+            ("t2".to_string(), entry("t2")),
+            ("t3".to_string(), record(serde_json::json!({"subject": 1}))),
+            ("t4".to_string(), entry("t9")),
+        ]);
+        let known = BTreeSet::from(["t1".to_string(), "t3".to_string(), "t5".to_string()]);
+
+        let report = check_index(&index, &known);
+        assert_eq!(report.indexed, 2);
+        assert_eq!(report.corrupt, ["t3", "t4"]);
+        assert_eq!(report.orphaned, ["t2"]);
+        assert_eq!(report.missing, ["t3", "t5"]);
+        assert!(!report.is_consistent());
+    }
 }
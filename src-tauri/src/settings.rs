@@ -0,0 +1,451 @@
+//! Cross-device sync of non-sensitive settings
+//!
+//! `sync` keeps a few settings identical across the user's devices through
+//! the Rainy Day backend, signed in with the existing backend tokens:
+//! - the theme
+//! - the planner's day boundaries (plan window and rollover hour)
+//! - the adopted inbox rules
+//! - the per-list task defaults behind smart lists
+//!
+//! Nothing from the keychain, the app lock or the AI provider setup is ever
+//! sent.
+//!
+//! Each section is synced as a whole. The commands changing one call `touch`,
+//! which dates the change; a sync then compares that date with the backend's
+//! copy and the newer one wins (last write wins). A section never changed on
+//! this device takes the backend's copy, and a section the backend doesn't
+//! have yet is uploaded. Every sync registers this device, so the backend
+//! returns the list of devices sharing the settings.
+//!
+//! Dates come from each device's clock, so devices with skewed clocks may
+//! resolve near-simultaneous changes the wrong way.
+
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::{self, TokenStore};
+use crate::google::tasks::ListDefaults;
+use crate::planner::{self, PlanWindowSettings, RolloverSettings};
+use crate::rules::MailRule;
+use crate::storage::{self, LocalStorage, MAIL_RULES, TASK_LIST_DEFAULTS};
+use crate::{ai, theme};
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+const SETTINGS_SYNC_STORE_FILE: &str = "settings_sync.json";
+const DEVICE_KEY: &str = "device";
+const MODIFIED_KEY: &str = "modified";
+const STATUS_KEY: &str = "status";
+
+/// Path of the settings on the backend
+const SYNC_PATH: &str = "/settings/sync";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A group of settings synced as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsSection {
+    Theme,
+    /// Plan window and rollover hour
+    Planner,
+    Rules,
+    /// Per-list task defaults (`tasks::ListDefaults`)
+    SmartLists,
+}
+
+impl SettingsSection {
+    const ALL: [SettingsSection; 4] = [
+        SettingsSection::Theme,
+        SettingsSection::Planner,
+        SettingsSection::Rules,
+        SettingsSection::SmartLists,
+    ];
+}
+
+/// A device sharing the settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncDevice {
+    pub id: String,
+    pub name: String,
+    pub platform: String,
+    #[serde(default)]
+    pub last_sync_ms: Option<i64>,
+}
+
+/// A section as stored on the backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncedSection {
+    pub section: SettingsSection,
+    pub value: Value,
+    pub updated_at_ms: i64,
+    /// Device that wrote it
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+/// The backend's copy of the settings
+#[derive(Debug, Default, Deserialize)]
+struct RemoteSettings {
+    #[serde(default)]
+    sections: Vec<SyncedSection>,
+    #[serde(default)]
+    devices: Vec<SyncDevice>,
+}
+
+/// Upload of the sections this device wins
+#[derive(Debug, Serialize)]
+struct SyncUpload<'a> {
+    device: &'a SyncDevice,
+    sections: Vec<SyncedSection>,
+}
+
+/// Planner settings synced as the `planner` section
+#[derive(Debug, Serialize, Deserialize)]
+struct PlannerSettings {
+    plan_window: PlanWindowSettings,
+    rollover: RolloverSettings,
+}
+
+/// What a sync did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettingsSyncResult {
+    /// Sections uploaded from this device
+    pub pushed: Vec<SettingsSection>,
+    /// Sections replaced by the backend's copy
+    pub pulled: Vec<SettingsSection>,
+    pub devices: Vec<SyncDevice>,
+    pub synced_at_ms: i64,
+}
+
+/// This device and the outcome of the last sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsSyncStatus {
+    pub device: SyncDevice,
+    pub last_sync: Option<SettingsSyncResult>,
+}
+
+/// Which copy of a section wins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    Push,
+    Pull,
+    UpToDate,
+}
+
+/// Last write wins; `local_ms` is None for a section never changed here
+fn resolve(local_ms: Option<i64>, remote_ms: Option<i64>) -> Resolution {
+    match (local_ms, remote_ms) {
+        (_, None) => Resolution::Push,
+        (None, Some(_)) => Resolution::Pull,
+        (Some(local), Some(remote)) if remote > local => Resolution::Pull,
+        (Some(local), Some(remote)) if local > remote => Resolution::Push,
+        _ => Resolution::UpToDate,
+    }
+}
+
+// ============================================================================
+// Local State
+// ============================================================================
+
+fn load_key<T: DeserializeOwned + Default>(app: &AppHandle, key: &str) -> Result<T, String> {
+    let store = storage::fs::settings_store(app, SETTINGS_SYNC_STORE_FILE)
+        .map_err(|e| format!("Failed to access settings sync store: {}", e))?;
+
+    Ok(store
+        .get(key)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_key(app: &AppHandle, key: &str, value: Value) -> Result<(), String> {
+    let store = storage::fs::settings_store(app, SETTINGS_SYNC_STORE_FILE)
+        .map_err(|e| format!("Failed to access settings sync store: {}", e))?;
+    store.set(key, value);
+    storage::fs::save_store(app, SETTINGS_SYNC_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save settings sync store: {}", e))
+}
+
+/// When each section last changed on this device
+fn load_modified(app: &AppHandle) -> Result<HashMap<SettingsSection, i64>, String> {
+    load_key(app, MODIFIED_KEY)
+}
+
+fn set_modified(app: &AppHandle, section: SettingsSection, at_ms: i64) -> Result<(), String> {
+    let mut modified = load_modified(app)?;
+    modified.insert(section, at_ms);
+    save_key(app, MODIFIED_KEY, serde_json::json!(modified))
+}
+
+/// Date a change of `section` made on this device, for the next sync
+pub(crate) fn touch(app: &AppHandle, section: SettingsSection) {
+    if let Err(e) = set_modified(app, section, chrono::Utc::now().timestamp_millis()) {
+        eprintln!("Failed to record settings change: {}", e);
+    }
+}
+
+/// This device, registered on first use
+fn load_device(app: &AppHandle) -> Result<SyncDevice, String> {
+    if let Some(device) = load_key::<Option<SyncDevice>>(app, DEVICE_KEY)? {
+        return Ok(device);
+    }
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let device = SyncDevice {
+        id: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        name: std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_else(|_| std::env::consts::OS.to_string()),
+        platform: std::env::consts::OS.to_string(),
+        last_sync_ms: None,
+    };
+    save_key(app, DEVICE_KEY, serde_json::json!(device))?;
+    Ok(device)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+/// Records of a collection as `{id: value}`
+fn collection_value(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    collection: &str,
+) -> Result<Value, String> {
+    let records: BTreeMap<String, Value> = storage
+        .list(account, collection)?
+        .into_iter()
+        .map(|(id, record)| (id, record.value))
+        .collect();
+    to_value(&records)
+}
+
+/// Replace a collection with the `{id: value}` records of `value`, each
+/// checked to parse as `T`
+fn replace_collection<T: DeserializeOwned>(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    collection: &str,
+    value: Value,
+) -> Result<(), String> {
+    let records: BTreeMap<String, Value> = serde_json::from_value(value)
+        .map_err(|e| format!("Invalid synced {}: {}", collection, e))?;
+    for (id, record) in &records {
+        serde_json::from_value::<T>(record.clone())
+            .map_err(|e| format!("Invalid synced {} record {}: {}", collection, id, e))?;
+    }
+
+    let stale: Vec<String> = storage
+        .list(account, collection)?
+        .into_keys()
+        .filter(|id| !records.contains_key(id))
+        .collect();
+    storage.remove_many(account, collection, &stale)?;
+    storage.put_many(account, collection, records.into_iter().collect())
+}
+
+/// Current value of a section on this device
+fn local_value(
+    app: &AppHandle,
+    storage: &LocalStorage,
+    account: &AccountContext,
+    section: SettingsSection,
+) -> Result<Value, String> {
+    match section {
+        SettingsSection::Theme => to_value(&theme::load_preference(app)?),
+        SettingsSection::Planner => to_value(&PlannerSettings {
+            plan_window: planner::load_plan_window_settings(app)?,
+            rollover: planner::load_rollover_settings(app)?,
+        }),
+        SettingsSection::Rules => collection_value(storage, account, MAIL_RULES),
+        SettingsSection::SmartLists => collection_value(storage, account, TASK_LIST_DEFAULTS),
+    }
+}
+
+/// Replace a section on this device with the backend's copy
+fn apply_value(
+    app: &AppHandle,
+    storage: &LocalStorage,
+    account: &AccountContext,
+    section: SettingsSection,
+    value: Value,
+) -> Result<(), String> {
+    let invalid = |e: serde_json::Error| format!("Invalid synced {:?} settings: {}", section, e);
+    match section {
+        SettingsSection::Theme => {
+            theme::save_preference(app, &serde_json::from_value(value).map_err(invalid)?)
+        }
+        SettingsSection::Planner => {
+            let settings: PlannerSettings = serde_json::from_value(value).map_err(invalid)?;
+            planner::save_plan_window_settings(app, &settings.plan_window)?;
+            planner::save_rollover_settings(app, &settings.rollover)
+        }
+        SettingsSection::Rules => {
+            replace_collection::<MailRule>(storage, account, MAIL_RULES, value)
+        }
+        SettingsSection::SmartLists => {
+            replace_collection::<ListDefaults>(storage, account, TASK_LIST_DEFAULTS, value)
+        }
+    }
+}
+
+// ============================================================================
+// Backend
+// ============================================================================
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+async fn read_remote(response: reqwest::Response) -> Result<RemoteSettings, String> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Settings sync failed ({}): {}", status, body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid settings sync response: {}", e))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Sync the non-sensitive settings with the other devices through the backend
+///
+/// `device_name` renames this device in the device list.
+#[tauri::command]
+pub async fn sync(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    device_name: Option<String>,
+) -> Result<SettingsSyncResult, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let token = auth::backend_access_token()?.ok_or("Not signed in to the backend")?;
+    let mut device = load_device(&app)?;
+    if let Some(name) = device_name.map(|n| n.trim().to_string()) {
+        if name.is_empty() {
+            return Err("Device name can't be empty".to_string());
+        }
+        device.name = name;
+    }
+
+    let http = http_client()?;
+    let url = format!("{}{}", ai::backend_url(&app)?, SYNC_PATH);
+    let response = http
+        .get(&url)
+        .bearer_auth(&token)
+        .send()
+        .await
+        .map_err(|e| format!("Settings sync request failed: {}", e))?;
+    let remote = read_remote(response).await?;
+
+    let modified = load_modified(&app)?;
+    let mut result = SettingsSyncResult::default();
+    let mut upload = Vec::new();
+    for section in SettingsSection::ALL {
+        let theirs = remote.sections.iter().find(|s| s.section == section);
+        let local_ms = modified.get(&section).copied();
+        match resolve(local_ms, theirs.map(|s| s.updated_at_ms)) {
+            Resolution::Push => {
+                upload.push(SyncedSection {
+                    section,
+                    value: local_value(&app, &storage, &account, section)?,
+                    updated_at_ms: local_ms.unwrap_or_default(),
+                    device_id: Some(device.id.clone()),
+                });
+                result.pushed.push(section);
+            }
+            Resolution::Pull => {
+                let Some(theirs) = theirs else { continue };
+                if local_value(&app, &storage, &account, section)? != theirs.value {
+                    apply_value(&app, &storage, &account, section, theirs.value.clone())?;
+                    result.pulled.push(section);
+                }
+                set_modified(&app, section, theirs.updated_at_ms)?;
+            }
+            Resolution::UpToDate => {}
+        }
+    }
+
+    // The upload also registers the device, so it goes out even when empty
+    let response = http
+        .put(&url)
+        .bearer_auth(&token)
+        .json(&SyncUpload {
+            device: &device,
+            sections: upload,
+        })
+        .send()
+        .await
+        .map_err(|e| format!("Settings sync request failed: {}", e))?;
+    let remote = read_remote(response).await?;
+
+    result.synced_at_ms = chrono::Utc::now().timestamp_millis();
+    result.devices = remote.devices;
+    device.last_sync_ms = Some(result.synced_at_ms);
+    save_key(&app, DEVICE_KEY, to_value(&device)?)?;
+    save_key(&app, STATUS_KEY, to_value(&result)?)?;
+    Ok(result)
+}
+
+/// Get this device and the outcome of the last settings sync
+#[tauri::command]
+pub async fn get_settings_sync_status(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+) -> Result<SettingsSyncStatus, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    Ok(SettingsSyncStatus {
+        device: load_device(&app)?,
+        last_sync: load_key(&app, STATUS_KEY)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_last_write_wins() {
+        // Unknown to the backend: upload, even if never changed here
+        assert_eq!(resolve(None, None), Resolution::Push);
+        assert_eq!(resolve(Some(5), None), Resolution::Push);
+        // Never changed here: take the backend's copy
+        assert_eq!(resolve(None, Some(5)), Resolution::Pull);
+        assert_eq!(resolve(Some(5), Some(9)), Resolution::Pull);
+        assert_eq!(resolve(Some(9), Some(5)), Resolution::Push);
+        assert_eq!(resolve(Some(5), Some(5)), Resolution::UpToDate);
+
+        let remote: RemoteSettings = serde_json::from_value(serde_json::json!({
+            "sections": [{
+                "section": "smart_lists",
+                "value": {"list1": {"include_in_smart_lists": false}},
+                "updated_at_ms": 7
+            }],
+            "devices": [{"id": "d1", "name": "Laptop", "platform": "macos"}]
+        }))
+        .unwrap();
+        assert_eq!(remote.sections[0].section, SettingsSection::SmartLists);
+        assert_eq!(remote.devices[0].last_sync_ms, None);
+    }
+}
@@ -0,0 +1,309 @@
+//! First-run guided setup
+//!
+//! Onboarding is a fixed sequence of steps: check the OAuth credentials,
+//! sign in, verify the granted scopes, pick the default task list, ask for
+//! notification permission and run the initial sync. The frontend shows the
+//! current step (`get_setup_state`) and calls `advance_setup` with that step's
+//! input; the step is checked (or performed) in Rust and, once satisfied,
+//! setup moves to the next one.
+//!
+//! Progress is saved after every change, so a crash or restart mid-onboarding
+//! resumes at the step it stopped on. A step that isn't satisfied yet (no
+//! sign-in, sync still running) stays current and reports why in `blocker`.
+
+use crate::auth::{self, AuthState, TokenStore};
+use crate::google::types::TaskListsResponse;
+use crate::google::{GoogleClient, TASKS_API_BASE};
+use crate::storage;
+use crate::sync::{self, BackfillPhase};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+const SETUP_STORE_FILE: &str = "setup.json";
+const SETUP_KEY: &str = "state";
+
+/// How far back the initial sync imports mail
+const INITIAL_SYNC_DAYS: i64 = 30;
+/// Task list used until one is picked
+const DEFAULT_TASK_LIST: &str = "@default";
+
+/// A step of the onboarding flow, in order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStep {
+    #[default]
+    Credentials,
+    SignIn,
+    Scopes,
+    TaskList,
+    Notifications,
+    InitialSync,
+    Done,
+}
+
+impl SetupStep {
+    fn next(self) -> Self {
+        match self {
+            SetupStep::Credentials => SetupStep::SignIn,
+            SetupStep::SignIn => SetupStep::Scopes,
+            SetupStep::Scopes => SetupStep::TaskList,
+            SetupStep::TaskList => SetupStep::Notifications,
+            SetupStep::Notifications => SetupStep::InitialSync,
+            SetupStep::InitialSync | SetupStep::Done => SetupStep::Done,
+        }
+    }
+}
+
+/// What the user gives to finish a step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum SetupInput {
+    Credentials,
+    /// After the OAuth flow (`start_oauth` / `wait_for_oauth_callback`)
+    SignIn,
+    Scopes,
+    TaskList {
+        list_id: String,
+    },
+    /// `request: false` skips the OS prompt
+    Notifications {
+        request: bool,
+    },
+    /// Starts the sync, then finishes once it's done
+    InitialSync,
+}
+
+impl SetupInput {
+    fn step(&self) -> SetupStep {
+        match self {
+            SetupInput::Credentials => SetupStep::Credentials,
+            SetupInput::SignIn => SetupStep::SignIn,
+            SetupInput::Scopes => SetupStep::Scopes,
+            SetupInput::TaskList { .. } => SetupStep::TaskList,
+            SetupInput::Notifications { .. } => SetupStep::Notifications,
+            SetupInput::InitialSync => SetupStep::InitialSync,
+        }
+    }
+}
+
+/// Persisted onboarding progress
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SetupState {
+    pub step: SetupStep,
+    pub completed: Vec<SetupStep>,
+    pub default_task_list: Option<String>,
+    /// None until the notifications step ran
+    pub notifications_granted: Option<bool>,
+    /// Why the current step isn't done yet, from the last `advance_setup`
+    pub blocker: Option<String>,
+    pub updated_at_ms: i64,
+}
+
+impl SetupState {
+    /// Finish the current step and move on
+    fn complete(&mut self) {
+        if self.step == SetupStep::Done {
+            return;
+        }
+        if !self.completed.contains(&self.step) {
+            self.completed.push(self.step);
+        }
+        self.step = self.step.next();
+        self.blocker = None;
+    }
+}
+
+fn load_state(app: &AppHandle) -> Result<SetupState, String> {
+    let store = storage::fs::settings_store(app, SETUP_STORE_FILE)
+        .map_err(|e| format!("Failed to access setup store: {}", e))?;
+
+    Ok(store
+        .get(SETUP_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+fn save_state(app: &AppHandle, state: &mut SetupState) -> Result<(), String> {
+    state.updated_at_ms = chrono::Utc::now().timestamp_millis();
+    let store = storage::fs::settings_store(app, SETUP_STORE_FILE)
+        .map_err(|e| format!("Failed to access setup store: {}", e))?;
+    store.set(SETUP_KEY, serde_json::json!(state));
+    storage::fs::save_store(app, SETUP_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save setup store: {}", e))
+}
+
+/// Task list new tasks go to when none is given
+pub fn default_task_list(app: &AppHandle) -> String {
+    load_state(app)
+        .ok()
+        .and_then(|state| state.default_task_list)
+        .unwrap_or_else(|| DEFAULT_TASK_LIST.to_string())
+}
+
+/// Sign-in API scopes missing from `granted` (empty means a session from
+/// before scopes were recorded, which had them all)
+fn missing_sign_in_scopes(granted: &[String]) -> Vec<&'static str> {
+    if granted.is_empty() {
+        return Vec::new();
+    }
+    // Google reports openid/email/profile under other names; only API scopes matter
+    auth::SCOPES
+        .iter()
+        .filter(|scope| scope.starts_with("https://"))
+        .filter(|scope| !granted.iter().any(|g| g == *scope))
+        .copied()
+        .collect()
+}
+
+/// Check or perform a step; `Ok(Some(reason))` when it isn't done yet
+async fn run_step(
+    app: &AppHandle,
+    auth_state: &AuthState,
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    state: &mut SetupState,
+    input: SetupInput,
+) -> Result<Option<String>, String> {
+    match input {
+        SetupInput::Credentials => {
+            if auth_state.client_id.is_empty() {
+                return Ok(Some("Missing: GOOGLE_CLIENT_ID".to_string()));
+            }
+        }
+        SetupInput::SignIn => {
+            if token_store.account_context().await.is_err() {
+                return Ok(Some("Not signed in yet".to_string()));
+            }
+        }
+        SetupInput::Scopes => {
+            let granted = token_store.granted_scopes().await?;
+            let missing = missing_sign_in_scopes(&granted);
+            if !missing.is_empty() {
+                return Ok(Some(format!(
+                    "Sign in again and allow: {}",
+                    missing.join(", ")
+                )));
+            }
+        }
+        SetupInput::TaskList { list_id } => {
+            let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+            let lists = client
+                .get::<TaskListsResponse>(&url, token_store)
+                .await?
+                .items
+                .unwrap_or_default();
+            if list_id != DEFAULT_TASK_LIST && !lists.iter().any(|l| l.id == list_id) {
+                return Err(format!("Unknown task list: {}", list_id));
+            }
+            state.default_task_list = Some(list_id);
+        }
+        SetupInput::Notifications { request } => {
+            let notifications = app.notification();
+            let permission = if request {
+                notifications.request_permission()
+            } else {
+                notifications.permission_state()
+            };
+            // Declining is a valid answer; it doesn't block setup
+            state.notifications_granted =
+                Some(permission.is_ok_and(|p| p == PermissionState::Granted));
+        }
+        SetupInput::InitialSync => {
+            let account = token_store.account_context().await?;
+            let mut status = sync::account_backfill_status(app, &account)?;
+            let done = status
+                .checkpoint
+                .as_ref()
+                .is_some_and(|c| c.phase == BackfillPhase::Done);
+            // Not started yet, or interrupted by the crash being recovered from
+            if !done && !status.active {
+                let floor_ms =
+                    chrono::Utc::now().timestamp_millis() - INITIAL_SYNC_DAYS * 24 * 60 * 60 * 1000;
+                status = sync::begin_backfill(app, &account, floor_ms)?;
+            }
+            if let Some(checkpoint) = &status.checkpoint {
+                if checkpoint.phase != BackfillPhase::Done {
+                    return Ok(Some(format!(
+                        "Syncing ({}%)",
+                        (status.progress * 100.0).round()
+                    )));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the onboarding progress
+#[tauri::command]
+pub async fn get_setup_state(app: AppHandle) -> Result<SetupState, String> {
+    crate::perf::trace_command!();
+    load_state(&app)
+}
+
+/// Finish the current onboarding step with `input`
+///
+/// Returns the new state; when the step isn't satisfied yet it stays current
+/// with the reason in `blocker`.
+#[tauri::command]
+pub async fn advance_setup(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    input: SetupInput,
+) -> Result<SetupState, String> {
+    crate::perf::trace_command!();
+    let mut state = load_state(&app)?;
+    if input.step() != state.step {
+        return Err(format!(
+            "Setup is at step {:?}, not {:?}",
+            state.step,
+            input.step()
+        ));
+    }
+
+    match run_step(&app, &auth_state, &token_store, &client, &mut state, input).await? {
+        Some(blocker) => state.blocker = Some(blocker),
+        None => state.complete(),
+    }
+    save_state(&app, &mut state)?;
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_progress() {
+        let mut state = SetupState::default();
+        assert_eq!(state.step, SetupStep::Credentials);
+        while state.step != SetupStep::Done {
+            state.complete();
+        }
+        assert_eq!(state.completed.len(), 6);
+        state.complete();
+        assert_eq!(state.completed.len(), 6);
+
+        let input: SetupInput =
+            serde_json::from_str(r#"{"step":"task_list","list_id":"abc"}"#).unwrap();
+        assert_eq!(input.step(), SetupStep::TaskList);
+
+        assert!(missing_sign_in_scopes(&[]).is_empty());
+        let granted = vec![
+            "openid".to_string(),
+            "https://www.googleapis.com/auth/gmail.readonly".to_string(),
+            "https://www.googleapis.com/auth/tasks".to_string(),
+        ];
+        assert_eq!(
+            missing_sign_in_scopes(&granted),
+            ["https://www.googleapis.com/auth/calendar.readonly"]
+        );
+    }
+}
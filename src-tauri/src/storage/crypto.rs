@@ -0,0 +1,119 @@
+//! Encryption of stored records
+//!
+//! Sensitive collections can store their values sealed with AES-256-GCM. A
+//! sealed value replaces the record's JSON with an envelope holding the nonce
+//! and ciphertext, so the collection file keeps its shape and plaintext and
+//! sealed records can live side by side while a collection is migrated.
+//!
+//! The key comes from a random secret the caller keeps in the OS keychain
+//! (see `derive_key`); this module never touches the keychain itself.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Envelope format version
+const ENVELOPE_VERSION: u32 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Sealed form of a record value
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    /// Envelope format version; its presence marks a sealed value
+    encrypted: u32,
+    nonce: String,
+    data: String,
+}
+
+/// Key sealing the records of one collection
+pub struct RecordKey(Key<Aes256Gcm>);
+
+/// Create a random secret to keep in the keychain
+pub fn new_secret() -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// Derive the key of `context` (e.g. a collection name) from a keychain secret
+pub fn derive_key(secret: &[u8], context: &str) -> RecordKey {
+    let mut hasher = Sha256::new();
+    hasher.update(b"rainyday-record-key:");
+    hasher.update(context.as_bytes());
+    hasher.update(b":");
+    hasher.update(secret);
+    RecordKey(Key::<Aes256Gcm>::from(<[u8; 32]>::from(hasher.finalize())))
+}
+
+/// Whether a stored value is sealed
+pub fn is_encrypted(value: &Value) -> bool {
+    value.get("encrypted").is_some_and(Value::is_u64)
+}
+
+/// Seal a record value
+pub fn encrypt(key: &RecordKey, value: &Value) -> Result<Value, String> {
+    let plaintext =
+        serde_json::to_vec(value).map_err(|e| format!("Failed to serialize record: {}", e))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = Aes256Gcm::new(&key.0)
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt record".to_string())?;
+
+    serde_json::to_value(Envelope {
+        encrypted: ENVELOPE_VERSION,
+        nonce: STANDARD.encode(nonce),
+        data: STANDARD.encode(ciphertext),
+    })
+    .map_err(|e| format!("Failed to serialize record: {}", e))
+}
+
+/// Open a sealed record value
+pub fn decrypt(key: &RecordKey, value: Value) -> Result<Value, String> {
+    let envelope: Envelope =
+        serde_json::from_value(value).map_err(|e| format!("Invalid encrypted record: {}", e))?;
+    if envelope.encrypted != ENVELOPE_VERSION {
+        return Err(format!(
+            "Unsupported encrypted record version {}",
+            envelope.encrypted
+        ));
+    }
+    let nonce = STANDARD
+        .decode(&envelope.nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == NONCE_LEN)
+        .ok_or("Invalid encrypted record nonce")?;
+    let ciphertext = STANDARD
+        .decode(&envelope.data)
+        .map_err(|e| format!("Invalid encrypted record data: {}", e))?;
+    let plaintext = Aes256Gcm::new(&key.0)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Failed to decrypt record: wrong key or damaged data".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid decrypted record: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let key = derive_key(&new_secret(), "daily_notes");
+        let value = serde_json::json!({"date": "2026-01-15", "summary": "Board deal"});
+        assert!(!is_encrypted(&value));
+
+        let sealed = encrypt(&key, &value).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.to_string().contains("Board deal"));
+        assert_eq!(decrypt(&key, sealed.clone()).unwrap(), value);
+
+        let other = derive_key(&new_secret(), "daily_notes");
+        assert!(decrypt(&other, sealed).is_err());
+    }
+}
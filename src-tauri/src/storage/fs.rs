@@ -0,0 +1,285 @@
+//! Crash-safe file writes and corrupted-file recovery
+//!
+//! `write_atomic` writes to a `<file>.tmp` sibling, flushes it and renames it
+//! over the target, so a crash mid-write leaves either the old or the new
+//! content, never a truncated file. The replaced version is kept as
+//! `<file>.bak`.
+//!
+//! A file that still fails to parse (damaged on disk, or written before this
+//! module existed) is moved aside as `<file>.corrupt-<ms>` and replaced by its
+//! backup when that one parses. `recover_dir` runs this check over a
+//! directory at startup, before any settings store loads its file.
+//!
+//! Settings stores (`tauri_plugin_store`) write with a plain `fs::write`, so
+//! the app opens them through `settings_store` (auto-save off) and persists
+//! them with `save_store`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::{resolve_store_path, Store, StoreBuilder};
+
+const TMP_SUFFIX: &str = "tmp";
+const BACKUP_SUFFIX: &str = "bak";
+const CORRUPT_SUFFIX: &str = "corrupt";
+
+/// What `recover_dir` found
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RecoveryReport {
+    /// Leftovers of interrupted writes (their targets are intact)
+    pub temp_files_removed: usize,
+    /// Corrupted files moved aside
+    pub quarantined: Vec<String>,
+    /// Quarantined files replaced by their backup
+    pub restored: Vec<String>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.temp_files_removed == 0 && self.quarantined.is_empty()
+    }
+}
+
+/// `<file>.<suffix>` next to `path`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace `path` with `contents` atomically, keeping the old file as backup
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let tmp = sibling(path, TMP_SUFFIX);
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("Failed to write {}: {}", tmp.display(), e));
+    }
+
+    if path.exists() {
+        // A hard link keeps the target in place the whole time; not every
+        // filesystem supports one, so fall back to a copy
+        let backup = sibling(path, BACKUP_SUFFIX);
+        let _ = fs::remove_file(&backup);
+        if fs::hard_link(path, &backup).is_err() {
+            let _ = fs::copy(path, &backup);
+        }
+    }
+
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// Serialize `value` as pretty JSON and write it atomically
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    write_atomic(path, &json)
+}
+
+/// Delete a file together with its backup
+///
+/// Returns false if the file didn't exist.
+pub fn remove(path: &Path) -> Result<bool, String> {
+    let _ = fs::remove_file(sibling(path, BACKUP_SUFFIX));
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete {}: {}", path.display(), e)),
+    }
+}
+
+/// Move a corrupted file aside, returning its new path
+pub fn quarantine(path: &Path) -> Result<PathBuf, String> {
+    let target = sibling(
+        path,
+        &format!(
+            "{}-{}",
+            CORRUPT_SUFFIX,
+            chrono::Utc::now().timestamp_millis()
+        ),
+    );
+    fs::rename(path, &target)
+        .map_err(|e| format!("Failed to quarantine {}: {}", path.display(), e))?;
+    eprintln!(
+        "Quarantined corrupted file {} as {}",
+        path.display(),
+        target.display()
+    );
+    Ok(target)
+}
+
+/// Put the backup of a quarantined file back in place if it parses
+fn restore_backup<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let backup = sibling(path, BACKUP_SUFFIX);
+    let value = serde_json::from_slice(&fs::read(&backup).ok()?).ok()?;
+    match fs::copy(&backup, path) {
+        Ok(_) => {
+            eprintln!("Restored {} from its backup", path.display());
+            Some(value)
+        }
+        Err(e) => {
+            eprintln!("Failed to restore {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// How a JSON file was loaded
+enum Loaded<T> {
+    Missing,
+    Intact(T),
+    Restored(T),
+    /// Corrupted and no usable backup
+    Lost,
+}
+
+fn load<T: DeserializeOwned>(path: &Path) -> Result<Loaded<T>, String> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Loaded::Missing),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    match serde_json::from_slice(&content) {
+        Ok(value) => Ok(Loaded::Intact(value)),
+        Err(e) => {
+            eprintln!("{} is corrupted: {}", path.display(), e);
+            quarantine(path)?;
+            Ok(restore_backup(path).map_or(Loaded::Lost, Loaded::Restored))
+        }
+    }
+}
+
+/// Read a JSON file, recovering from corruption
+///
+/// A missing file, or a corrupted one without a usable backup, reads as None.
+/// A deleted file is never brought back from its backup.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    Ok(match load(path)? {
+        Loaded::Intact(value) | Loaded::Restored(value) => Some(value),
+        Loaded::Missing | Loaded::Lost => None,
+    })
+}
+
+/// Clean up interrupted writes and quarantine corrupted `*.json` files
+/// directly inside `dir`
+pub fn recover_dir(dir: &Path) -> Result<RecoveryReport, String> {
+    let mut report = RecoveryReport::default();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        if name.ends_with(&format!(".json.{}", TMP_SUFFIX)) {
+            if fs::remove_file(&path).is_ok() {
+                report.temp_files_removed += 1;
+            }
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        match load::<serde_json::Value>(&path)? {
+            Loaded::Missing | Loaded::Intact(_) => {}
+            Loaded::Restored(_) => {
+                report.quarantined.push(name.clone());
+                report.restored.push(name);
+            }
+            Loaded::Lost => report.quarantined.push(name),
+        }
+    }
+    Ok(report)
+}
+
+/// Open a settings store with auto-save off; persist it with `save_store`
+pub fn settings_store<R: Runtime>(
+    app: &AppHandle<R>,
+    file: &str,
+) -> tauri_plugin_store::Result<Arc<Store<R>>> {
+    StoreBuilder::new(app, file).disable_auto_save().build()
+}
+
+/// Write a settings store to disk atomically
+///
+/// The file format matches the store plugin's own (a pretty JSON object).
+pub fn save_store<R: Runtime>(
+    app: &AppHandle<R>,
+    file: &str,
+    store: &Store<R>,
+) -> Result<(), String> {
+    let path =
+        resolve_store_path(app, file).map_err(|e| format!("Failed to resolve {}: {}", file, e))?;
+    let entries: serde_json::Map<String, serde_json::Value> = store.entries().into_iter().collect();
+    write_json(&path, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rainyday-fs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_atomic_and_recover() {
+        let dir = temp_dir("recover");
+        let path = dir.join("settings.json");
+
+        write_json(&path, &serde_json::json!({ "v": 1 })).unwrap();
+        write_json(&path, &serde_json::json!({ "v": 2 })).unwrap();
+        assert!(!sibling(&path, TMP_SUFFIX).exists());
+        let value: Option<serde_json::Value> = read_json(&path).unwrap();
+        assert_eq!(value, Some(serde_json::json!({ "v": 2 })));
+
+        // Killed mid-write: truncated target and a leftover temp file
+        fs::write(&path, "{\"v\": 3").unwrap();
+        fs::write(sibling(&path, TMP_SUFFIX), "{\"v\"").unwrap();
+        fs::write(dir.join("other.json"), "not json").unwrap();
+
+        let report = recover_dir(&dir).unwrap();
+        assert_eq!(report.temp_files_removed, 1);
+        assert_eq!(report.quarantined, ["other.json", "settings.json"]);
+        assert_eq!(report.restored, ["settings.json"]);
+
+        // Restored from the backup of the last good write
+        let value: Option<serde_json::Value> = read_json(&path).unwrap();
+        assert_eq!(value, Some(serde_json::json!({ "v": 1 })));
+        assert!(!dir.join("other.json").exists());
+        assert!(recover_dir(&dir).unwrap().is_empty());
+
+        // Deleted files stay deleted
+        assert!(remove(&path).unwrap());
+        assert_eq!(read_json::<serde_json::Value>(&path).unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1021 @@
+//! Local persistent storage
+//!
+//! Simple collection-based JSON storage in the app data directory. Data is
+//! isolated per account: each account has its own directory (see
+//! `AccountContext::storage_id`) in which every collection is a single
+//! `<name>.json` file holding records keyed by ID with creation/update
+//! timestamps, which lets retention policies purge old data.
+//!
+//! Secrets never go here - they live in the OS keychain (see `auth::keychain`).
+//! Sensitive collections can seal their records with a key derived from a
+//! keychain secret (see `crypto`).
+//!
+//! Collection files are written atomically and recovered when corrupted (see
+//! `fs`); `recover` checks every account's files at startup.
+
+pub mod crypto;
+pub mod fs;
+
+use crate::account::AccountContext;
+use crate::app_lock::AppLockState;
+use crate::auth::os_gate::{self, SensitiveAction};
+use crate::auth::TokenStore;
+use crate::cache::CacheState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Subdirectory of the app data dir holding collection files
+const STORAGE_DIR: &str = "storage";
+
+/// Collection of cached email bodies
+pub const EMAIL_BODIES: &str = "email_bodies";
+/// Collection of per-message email metadata (see `analytics::MessageMetadata`)
+pub const EMAIL_METADATA: &str = "email_metadata";
+/// Collection of sent notifications
+pub const NOTIFICATION_HISTORY: &str = "notification_history";
+/// Collection of snoozed email threads (see `planner::SnoozedEmail`)
+pub const SNOOZED_EMAILS: &str = "snoozed_emails";
+/// Collection of emails queued for later sending (see `planner::ScheduledSend`)
+pub const SCHEDULED_SENDS: &str = "scheduled_sends";
+/// Collection of notifications to fire later (see `planner::ScheduledNotification`)
+pub const SCHEDULED_NOTIFICATIONS: &str = "scheduled_notifications";
+/// Collection of events created from email threads (see `calendar::ThreadEventLink`)
+pub const THREAD_EVENT_LINKS: &str = "thread_event_links";
+/// Collection of threads and tasks pinned to a day's plan (see `planner::PlanPin`)
+pub const PLAN_PINS: &str = "plan_pins";
+/// Collection of generated plans keyed by date (see `planner::DailyPlan`)
+pub const PLAN_HISTORY: &str = "plan_history";
+/// Collection of mailboxes delegated to the account (see `mailbox::DelegatedMailbox`)
+pub const DELEGATED_MAILBOXES: &str = "delegated_mailboxes";
+/// Collection of past triage decisions (see `triage::TriageRecord`)
+pub const TRIAGE_HISTORY: &str = "triage_history";
+/// Collection of adopted inbox rules (see `rules::MailRule`)
+pub const MAIL_RULES: &str = "mail_rules";
+/// Collection of messages waiting to be sent (see `outbox::OutboxItem`)
+pub const OUTBOX: &str = "outbox";
+/// Collection of local task data keyed by `<list_id>:<task_id>` (see `tasks::TaskMetadata`)
+pub const TASK_METADATA: &str = "task_metadata";
+/// Collection of per-list task behaviour keyed by list ID (see `tasks::ListDefaults`)
+pub const TASK_LIST_DEFAULTS: &str = "task_list_defaults";
+/// Collection of tasks linked to their source threads keyed by `<list_id>:<task_id>` (see `types::TaskRef`)
+pub const TASK_REFS: &str = "task_refs";
+/// Collection of locally searchable threads keyed by thread ID (see `search::IndexedThread`)
+pub const SEARCH_INDEX: &str = "search_index";
+/// Mailbox import checkpoint (see `sync::BackfillCheckpoint`)
+pub const SYNC_BACKFILL: &str = "sync_backfill";
+/// Collection of in-app reading state keyed by thread ID (see `gmail::ReadPosition`)
+pub const READ_POSITIONS: &str = "read_positions";
+/// Generated daily notes, keyed by date
+pub const DAILY_NOTES: &str = "daily_notes";
+/// Collection of tasks left undone at the end of their day keyed by `<list_id>:<task_id>` (see `planner::TaskSlip`)
+pub const TASK_SLIPS: &str = "task_slips";
+/// Monthly AI token and cost totals keyed by `YYYY-MM` (see `ai::usage::MonthlyUsage`)
+pub const AI_USAGE: &str = "ai_usage";
+
+const RETENTION_STORE_FILE: &str = "retention.json";
+const RETENTION_POLICIES_KEY: &str = "policies";
+
+/// Interval between automatic storage vacuums
+const VACUUM_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// ============================================================================
+// Records
+// ============================================================================
+
+/// A stored record with timestamps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredRecord {
+    pub value: Value,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+}
+
+type Collection = HashMap<String, StoredRecord>;
+
+/// Collection-based JSON storage
+pub struct LocalStorage {
+    root: RwLock<Option<PathBuf>>,
+    /// Serializes read-modify-write cycles on collection files
+    write_lock: Mutex<()>,
+}
+
+impl LocalStorage {
+    pub fn new() -> Self {
+        Self {
+            root: RwLock::new(None),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Initialize the storage root inside the app data directory
+    pub fn initialize(&self, app_data_dir: PathBuf) -> Result<(), String> {
+        let root = app_data_dir.join(STORAGE_DIR);
+        std::fs::create_dir_all(&root)
+            .map_err(|e| format!("Failed to create storage dir: {}", e))?;
+
+        let mut guard = self
+            .root
+            .write()
+            .map_err(|_| "Storage lock poisoned".to_string())?;
+        *guard = Some(root);
+        Ok(())
+    }
+
+    fn root(&self) -> Result<PathBuf, String> {
+        let guard = self
+            .root
+            .read()
+            .map_err(|_| "Storage lock poisoned".to_string())?;
+        guard
+            .clone()
+            .ok_or_else(|| "Storage not initialized".to_string())
+    }
+
+    fn account_dir(&self, account: &AccountContext) -> Result<PathBuf, String> {
+        Ok(self.root()?.join(account.storage_id()))
+    }
+
+    fn collection_path(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+    ) -> Result<PathBuf, String> {
+        if collection.is_empty()
+            || !collection
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!("Invalid collection name: {}", collection));
+        }
+
+        Ok(self
+            .account_dir(account)?
+            .join(format!("{}.json", collection)))
+    }
+
+    fn read_collection(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+    ) -> Result<Collection, String> {
+        let path = self.collection_path(account, collection)?;
+        Ok(fs::read_json(&path)?.unwrap_or_default())
+    }
+
+    fn write_collection(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        records: &Collection,
+    ) -> Result<(), String> {
+        let path = self.collection_path(account, collection)?;
+        let json = serde_json::to_string(records)
+            .map_err(|e| format!("Failed to serialize {}: {}", collection, e))?;
+        fs::write_atomic(&path, json.as_bytes())
+    }
+
+    fn file_size(&self, account: &AccountContext, collection: &str) -> u64 {
+        self.collection_path(account, collection)
+            .ok()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Insert or replace a record
+    pub fn put(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        id: &str,
+        value: Value,
+    ) -> Result<(), String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+        let mut records = self.read_collection(account, collection)?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let created_at_ms = records.get(id).map(|r| r.created_at_ms).unwrap_or(now);
+        records.insert(
+            id.to_string(),
+            StoredRecord {
+                value,
+                created_at_ms,
+                updated_at_ms: now,
+            },
+        );
+
+        self.write_collection(account, collection, &records)
+    }
+
+    /// Insert or replace many records with a single write
+    pub fn put_many(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        values: Vec<(String, Value)>,
+    ) -> Result<(), String> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+        let mut records = self.read_collection(account, collection)?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        for (id, value) in values {
+            let created_at_ms = records.get(&id).map(|r| r.created_at_ms).unwrap_or(now);
+            records.insert(
+                id,
+                StoredRecord {
+                    value,
+                    created_at_ms,
+                    updated_at_ms: now,
+                },
+            );
+        }
+
+        self.write_collection(account, collection, &records)
+    }
+
+    /// Get a single record
+    pub fn get(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        id: &str,
+    ) -> Result<Option<StoredRecord>, String> {
+        Ok(self.read_collection(account, collection)?.remove(id))
+    }
+
+    /// Get all records in a collection
+    pub fn list(&self, account: &AccountContext, collection: &str) -> Result<Collection, String> {
+        self.read_collection(account, collection)
+    }
+
+    /// Remove a single record
+    pub fn remove(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        id: &str,
+    ) -> Result<bool, String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+        let mut records = self.read_collection(account, collection)?;
+        let removed = records.remove(id).is_some();
+        if removed {
+            self.write_collection(account, collection, &records)?;
+        }
+        Ok(removed)
+    }
+
+    /// Remove many records with a single write, returning how many existed
+    pub fn remove_many(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        ids: &[String],
+    ) -> Result<usize, String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+        let mut records = self.read_collection(account, collection)?;
+        let removed = ids
+            .iter()
+            .filter(|id| records.remove(*id).is_some())
+            .count();
+        if removed > 0 {
+            self.write_collection(account, collection, &records)?;
+        }
+        Ok(removed)
+    }
+
+    /// Remove records last updated before `cutoff_ms`
+    pub fn purge_older_than(
+        &self,
+        account: &AccountContext,
+        collection: &str,
+        cutoff_ms: i64,
+    ) -> Result<PurgeResult, String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+        let size_before = self.file_size(account, collection);
+        let mut records = self.read_collection(account, collection)?;
+
+        let before = records.len();
+        records.retain(|_, r| r.updated_at_ms >= cutoff_ms);
+        let removed = before - records.len();
+
+        if removed > 0 {
+            self.write_collection(account, collection, &records)?;
+        }
+
+        Ok(PurgeResult {
+            account: account.email().to_string(),
+            collection: collection.to_string(),
+            removed,
+            bytes_reclaimed: size_before.saturating_sub(self.file_size(account, collection)),
+        })
+    }
+
+    /// Names of an account's collections on disk
+    pub fn collections(&self, account: &AccountContext) -> Result<Vec<String>, String> {
+        let dir = self.account_dir(account)?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = json_file_stems(&dir)?;
+        names.sort();
+        Ok(names)
+    }
+
+    /// Accounts that have data on disk
+    pub fn accounts(&self) -> Result<Vec<AccountContext>, String> {
+        let root = self.root()?;
+        let entries = std::fs::read_dir(&root)
+            .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+
+        Ok(entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| AccountContext::from_storage_id(&e.file_name().to_string_lossy()))
+            .collect())
+    }
+
+    /// Clean up interrupted writes and corrupted collection files of every account
+    pub fn recover(&self) -> Result<fs::RecoveryReport, String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+
+        let mut report = fs::RecoveryReport::default();
+        for account in self.accounts()? {
+            let found = fs::recover_dir(&self.account_dir(&account)?)?;
+            report.temp_files_removed += found.temp_files_removed;
+            report.quarantined.extend(found.quarantined);
+            report.restored.extend(found.restored);
+        }
+        Ok(report)
+    }
+
+    /// Delete all of an account's collections, returning the number of records removed
+    pub fn remove_account(&self, account: &AccountContext) -> Result<usize, String> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock poisoned")?;
+
+        let mut removed = 0;
+        for name in self.collections(account)? {
+            removed += self.read_collection(account, &name).map_or(0, |r| r.len());
+        }
+
+        let dir = self.account_dir(account)?;
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to remove account storage: {}", e))?;
+        }
+        Ok(removed)
+    }
+}
+
+/// File stems of all `*.json` files directly inside `dir`
+fn json_file_stems(dir: &std::path::Path) -> Result<Vec<String>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+    Ok(entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            if path.is_file() && path.extension()? == "json" {
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Retention Policies
+// ============================================================================
+
+/// Maximum age for records in a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub collection: String,
+    pub max_age_days: u32,
+}
+
+/// Outcome of purging one collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeResult {
+    pub account: String,
+    pub collection: String,
+    pub removed: usize,
+    pub bytes_reclaimed: u64,
+}
+
+/// Outcome of a purge run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub results: Vec<PurgeResult>,
+    pub total_removed: usize,
+    pub total_bytes_reclaimed: u64,
+    /// Expired entries dropped from the in-memory cache
+    pub cache_entries_removed: usize,
+    pub ran_at_ms: i64,
+}
+
+fn default_policies() -> Vec<RetentionPolicy> {
+    vec![
+        RetentionPolicy {
+            collection: EMAIL_BODIES.to_string(),
+            max_age_days: 30,
+        },
+        RetentionPolicy {
+            collection: NOTIFICATION_HISTORY.to_string(),
+            max_age_days: 90,
+        },
+    ]
+}
+
+fn load_policies(app: &AppHandle) -> Result<Vec<RetentionPolicy>, String> {
+    let store = fs::settings_store(app, RETENTION_STORE_FILE)
+        .map_err(|e| format!("Failed to access retention store: {}", e))?;
+
+    Ok(store
+        .get(RETENTION_POLICIES_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_else(default_policies))
+}
+
+fn apply_policies(
+    storage: &LocalStorage,
+    cache: &CacheState,
+    policies: &[RetentionPolicy],
+) -> PurgeReport {
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let accounts = storage.accounts().unwrap_or_else(|e| {
+        eprintln!("Failed to list storage accounts: {}", e);
+        Vec::new()
+    });
+
+    let results: Vec<PurgeResult> = accounts
+        .iter()
+        .flat_map(|account| policies.iter().map(move |p| (account, p)))
+        .filter_map(|(account, p)| {
+            let cutoff = now - p.max_age_days as i64 * 86_400_000;
+            match storage.purge_older_than(account, &p.collection, cutoff) {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    eprintln!("Failed to purge {}: {}", p.collection, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    PurgeReport {
+        total_removed: results.iter().map(|r| r.removed).sum(),
+        total_bytes_reclaimed: results.iter().map(|r| r.bytes_reclaimed).sum(),
+        results,
+        cache_entries_removed: cache.0.cleanup_expired(),
+        ran_at_ms: now,
+    }
+}
+
+/// Start the periodic storage vacuum task
+pub fn spawn_vacuum(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(VACUUM_INTERVAL).await;
+
+            match load_policies(&app) {
+                Ok(policies) => {
+                    let report = apply_policies(
+                        &app.state::<LocalStorage>(),
+                        &app.state::<CacheState>(),
+                        &policies,
+                    );
+                    if report.total_removed > 0 {
+                        println!(
+                            "Storage vacuum removed {} records ({} bytes)",
+                            report.total_removed, report.total_bytes_reclaimed
+                        );
+                    }
+                }
+                Err(e) => eprintln!("Storage vacuum skipped: {}", e),
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Data Export
+// ============================================================================
+
+const EXPORT_README: &str = r#"# Rainy Day data export
+
+This bundle contains everything Rainy Day stores locally on this device.
+Secrets (OAuth and backend tokens) live in the OS keychain and are never
+exported.
+
+## Layout
+
+- `manifest.json` - export time, app version and the list of files
+- `collections/<account>/<name>.json` - local collections of each account
+  (notes, rules, analytics, notification history, cached email bodies, ...).
+  Each file is an object keyed by record ID:
+  `{ "value": ..., "created_at_ms": ..., "updated_at_ms": ... }`
+- `collections/<account>/<name>.csv` - the same records as CSV with the columns
+  `id,created_at_ms,updated_at_ms,value` (value is JSON-encoded)
+- `settings/<name>.json` - settings and session metadata (theme, planner
+  links, update channel, retention policies, signed-in account info). The
+  app lock passcode, sign-in state and calendar feed URLs are left out.
+
+Timestamps are Unix epoch milliseconds (UTC).
+"#;
+
+/// Settings stores copied into an export. Anything else in the app data
+/// directory stays out: the app lock passcode hash, sign-in state (`auth`),
+/// the attachment scanner and calendar feed URLs, which can embed private
+/// tokens.
+const EXPORTED_SETTINGS: &[&str] = &[
+    "ai",
+    "api",
+    "automation",
+    "daily_note",
+    "holidays",
+    "perf",
+    "planner",
+    "privacy",
+    "retention",
+    "routines",
+    "session_metadata",
+    "settings_sync",
+    "setup",
+    "theme",
+    "travel",
+    "updates",
+    "week",
+];
+
+/// Fields removed from exported settings stores, by store. Files saved before
+/// a secret moved to the keychain can still hold it.
+const STRIPPED_SETTING_FIELDS: &[(&str, &str)] = &[("travel", "routing_api_key")];
+
+/// Remove `STRIPPED_SETTING_FIELDS` from a settings store's JSON; `None` when
+/// it holds such fields but can't be parsed, so it is left out
+fn strip_setting_secrets(name: &str, content: Vec<u8>) -> Option<Vec<u8>> {
+    let fields: Vec<&str> = STRIPPED_SETTING_FIELDS
+        .iter()
+        .filter(|(store, _)| *store == name)
+        .map(|(_, field)| *field)
+        .collect();
+    if fields.is_empty() {
+        return Some(content);
+    }
+
+    let mut store: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(&content).ok()?;
+    for value in store.values_mut() {
+        if let Some(entry) = value.as_object_mut() {
+            for field in &fields {
+                entry.remove(*field);
+            }
+        }
+    }
+    serde_json::to_vec_pretty(&store).ok()
+}
+
+/// Outcome of a data export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub files: Vec<String>,
+    pub bytes_written: u64,
+    pub exported_at_ms: i64,
+}
+
+/// Quote a CSV field when it contains separators, quotes or newlines
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn collection_to_csv(records: &Collection) -> String {
+    let mut ids: Vec<&String> = records.keys().collect();
+    ids.sort();
+
+    let mut csv = String::from("id,created_at_ms,updated_at_ms,value\n");
+    for id in ids {
+        let record = &records[id];
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(id),
+            record.created_at_ms,
+            record.updated_at_ms,
+            csv_field(&record.value.to_string())
+        ));
+    }
+    csv
+}
+
+/// Write the export bundle into a new directory under `dest`
+fn write_export(
+    storage: &LocalStorage,
+    settings_dir: &std::path::Path,
+    dest: &std::path::Path,
+    app_version: &str,
+) -> Result<ExportReport, String> {
+    let now = chrono::Utc::now();
+    let bundle = dest.join(format!("rainy-day-export-{}", now.format("%Y%m%d-%H%M%S")));
+    let settings_out = bundle.join("settings");
+    std::fs::create_dir_all(&settings_out)
+        .map_err(|e| format!("Failed to create {}: {}", settings_out.display(), e))?;
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+
+    for account in storage.accounts()? {
+        let account_dir = bundle.join("collections").join(account.email());
+        std::fs::create_dir_all(&account_dir)
+            .map_err(|e| format!("Failed to create {}: {}", account_dir.display(), e))?;
+
+        for name in storage.collections(&account)? {
+            let records = storage.list(&account, &name)?;
+            let json = serde_json::to_string_pretty(&records)
+                .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+            let base = format!("collections/{}/{}", account.email(), name);
+            files.push((format!("{}.json", base), json.into_bytes()));
+            files.push((
+                format!("{}.csv", base),
+                collection_to_csv(&records).into_bytes(),
+            ));
+        }
+    }
+
+    // Settings stores and session metadata are plain JSON files in the app
+    // data directory; only the allowlisted ones are exported
+    for name in EXPORTED_SETTINGS {
+        let source = settings_dir.join(format!("{}.json", name));
+        if !source.is_file() {
+            continue;
+        }
+        let content =
+            std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        match strip_setting_secrets(name, content) {
+            Some(content) => files.push((format!("settings/{}.json", name), content)),
+            None => eprintln!("Leaving unreadable {} settings out of the export", name),
+        }
+    }
+
+    let file_names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+    let manifest = serde_json::json!({
+        "format_version": 1,
+        "app_version": app_version,
+        "exported_at_ms": now.timestamp_millis(),
+        "files": file_names,
+    });
+    files.push((
+        "manifest.json".to_string(),
+        serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?,
+    ));
+    files.push(("README.md".to_string(), EXPORT_README.as_bytes().to_vec()));
+
+    let mut bytes_written = 0u64;
+    for (name, content) in &files {
+        std::fs::write(bundle.join(name), content)
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        bytes_written += content.len() as u64;
+    }
+
+    Ok(ExportReport {
+        path: bundle.to_string_lossy().into_owned(),
+        files: files.into_iter().map(|(name, _)| name).collect(),
+        bytes_written,
+        exported_at_ms: now.timestamp_millis(),
+    })
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the configured retention policies
+#[tauri::command]
+pub async fn get_retention_policies(app: AppHandle) -> Result<Vec<RetentionPolicy>, String> {
+    crate::perf::trace_command!();
+    load_policies(&app)
+}
+
+/// Create or replace the retention policy for a collection
+#[tauri::command]
+pub async fn set_retention_policy(app: AppHandle, policy: RetentionPolicy) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if policy.max_age_days == 0 {
+        return Err("max_age_days must be at least 1".to_string());
+    }
+
+    let mut policies = load_policies(&app)?;
+    policies.retain(|p| p.collection != policy.collection);
+    policies.push(policy);
+
+    let store = fs::settings_store(&app, RETENTION_STORE_FILE)
+        .map_err(|e| format!("Failed to access retention store: {}", e))?;
+    store.set(RETENTION_POLICIES_KEY, serde_json::json!(policies));
+    fs::save_store(&app, RETENTION_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save retention policies: {}", e))
+}
+
+/// Purge now, using `policy` if given or all configured policies otherwise
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
+#[tauri::command]
+pub async fn purge_now(
+    app: AppHandle,
+    storage: State<'_, LocalStorage>,
+    cache: State<'_, CacheState>,
+    policy: Option<RetentionPolicy>,
+    confirmation: Option<String>,
+) -> Result<PurgeReport, String> {
+    crate::perf::trace_command!();
+    let target = policy.as_ref().map_or("all", |p| p.collection.as_str());
+    crate::policy::authorize(&app, "purge_now", target, confirmation.as_deref()).await?;
+    let policies = match policy {
+        Some(policy) => vec![policy],
+        None => load_policies(&app)?,
+    };
+
+    Ok(apply_policies(&storage, &cache, &policies))
+}
+
+/// Export all locally stored data (secrets excluded) into a bundle under `dest`
+///
+/// Needs an OS confirmation when the gate is on (see `auth::os_gate`).
+#[tauri::command]
+pub async fn export_all_data(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    storage: State<'_, LocalStorage>,
+    dest: String,
+) -> Result<ExportReport, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    os_gate::confirm(&app, SensitiveAction::ExportData).await?;
+    let settings_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    write_export(
+        &storage,
+        &settings_dir,
+        std::path::Path::new(&dest),
+        &app.package_info().version.to_string(),
+    )
+}
+
+/// Store a record in a local collection of the signed-in account
+#[tauri::command]
+pub async fn storage_put(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    collection: String,
+    id: String,
+    value: Value,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    storage.put(&account, &collection, &id, value)
+}
+
+/// Get a record from a local collection of the signed-in account
+#[tauri::command]
+pub async fn storage_get(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    collection: String,
+    id: String,
+) -> Result<Option<Value>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    Ok(storage.get(&account, &collection, &id)?.map(|r| r.value))
+}
+
+/// List all records in a local collection of the signed-in account (keyed by ID)
+#[tauri::command]
+pub async fn storage_list(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    collection: String,
+) -> Result<HashMap<String, Value>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    Ok(storage
+        .list(&account, &collection)?
+        .into_iter()
+        .map(|(id, r)| (id, r.value))
+        .collect())
+}
+
+/// Remove a record from a local collection of the signed-in account
+///
+/// Destructive: needs a `confirmation` token (see `policy`).
+#[tauri::command]
+pub async fn storage_remove(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    storage: State<'_, LocalStorage>,
+    collection: String,
+    id: String,
+    confirmation: Option<String>,
+) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let target = format!("{}:{}", collection, id);
+    crate::policy::authorize(&app, "storage_remove", &target, confirmation.as_deref()).await?;
+    let account = token_store.account_context().await?;
+    storage.remove(&account, &collection, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_storage(name: &str) -> LocalStorage {
+        let dir =
+            std::env::temp_dir().join(format!("rainyday-storage-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = LocalStorage::new();
+        storage.initialize(dir).expect("Failed to init storage");
+        storage
+    }
+
+    fn account() -> AccountContext {
+        AccountContext::new("user@example.com")
+    }
+
+    #[test]
+    fn test_put_get_remove() {
+        let storage = temp_storage("crud");
+        let account = account();
+        storage
+            .put(
+                &account,
+                "notes",
+                "n1",
+                serde_json::json!({"text": "hello"}),
+            )
+            .unwrap();
+
+        let record = storage.get(&account, "notes", "n1").unwrap().unwrap();
+        assert_eq!(record.value["text"], "hello");
+        assert_eq!(
+            storage.collections(&account).unwrap(),
+            vec!["notes".to_string()]
+        );
+
+        assert!(storage.remove(&account, "notes", "n1").unwrap());
+        assert!(storage.get(&account, "notes", "n1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_accounts_are_isolated() {
+        let storage = temp_storage("accounts");
+        let a = AccountContext::new("a@example.com");
+        let b = AccountContext::new("b@example.com");
+        storage
+            .put(&a, "notes", "n1", serde_json::json!(1))
+            .unwrap();
+        storage
+            .put(&a, "notes", "n2", serde_json::json!(2))
+            .unwrap();
+        storage
+            .put(&b, "notes", "n1", serde_json::json!(3))
+            .unwrap();
+
+        assert_eq!(storage.list(&a, "notes").unwrap().len(), 2);
+        assert_eq!(storage.accounts().unwrap().len(), 2);
+
+        assert_eq!(storage.remove_account(&a).unwrap(), 2);
+        assert!(storage.collections(&a).unwrap().is_empty());
+        assert_eq!(storage.accounts().unwrap(), vec![b.clone()]);
+        assert!(storage.get(&b, "notes", "n1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_purge_older_than() {
+        let storage = temp_storage("purge");
+        let account = account();
+        storage
+            .put(
+                &account,
+                EMAIL_BODIES,
+                "old",
+                serde_json::json!("a".repeat(100)),
+            )
+            .unwrap();
+        storage
+            .put(&account, EMAIL_BODIES, "new", serde_json::json!("b"))
+            .unwrap();
+
+        // Age the first record by rewriting its timestamp
+        let mut records = storage.list(&account, EMAIL_BODIES).unwrap();
+        records.get_mut("old").unwrap().updated_at_ms = 0;
+        storage
+            .write_collection(&account, EMAIL_BODIES, &records)
+            .unwrap();
+
+        let result = storage.purge_older_than(&account, EMAIL_BODIES, 1).unwrap();
+        assert_eq!(result.removed, 1);
+        assert!(result.bytes_reclaimed >= 100);
+        assert!(storage
+            .get(&account, EMAIL_BODIES, "new")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_export_copies_allowlisted_settings_only() {
+        let storage = temp_storage("export");
+        let dir = std::env::temp_dir().join(format!("rainyday-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["theme", "app_lock", "auth", "attachments", "ical"] {
+            std::fs::write(dir.join(format!("{}.json", name)), "{}").unwrap();
+        }
+        // Saved before the Maps API key moved to the keychain
+        std::fs::write(
+            dir.join("travel.json"),
+            r#"{"settings":{"enabled":true,"routing_api_key":"AIza-secret"}}"#,
+        )
+        .unwrap();
+
+        let report = write_export(&storage, &dir, &dir, "1.0.0").unwrap();
+        assert!(report.files.contains(&"settings/theme.json".to_string()));
+        let travel = std::fs::read_to_string(
+            std::path::Path::new(&report.path).join("settings/travel.json"),
+        )
+        .unwrap();
+        assert!(travel.contains("\"enabled\""));
+        assert!(!travel.contains("AIza-secret"));
+        assert!(!report
+            .files
+            .iter()
+            .any(|f| ["app_lock", "auth", "attachments", "ical"]
+                .iter()
+                .any(|name| f == &format!("settings/{}.json", name))));
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_rejects_invalid_collection_names() {
+        let storage = temp_storage("names");
+        assert!(storage
+            .put(&account(), "../escape", "x", serde_json::json!(1))
+            .is_err());
+    }
+}
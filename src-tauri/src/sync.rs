@@ -0,0 +1,742 @@
+//! Power- and activity-aware sync scheduling
+//!
+//! Background loops wait through `SyncScheduler::wait` instead of sleeping a
+//! fixed interval. The wait is stretched while the machine is on battery, in
+//! battery saver, idle, or while the main window has been in the background for
+//! a long time, and polling pauses entirely after long idle/background periods.
+//! Refocusing the main window wakes every waiting loop and emits `sync:resume`
+//! so the frontend refetches immediately.
+//!
+//! System idle time is read from the OS on macOS and Windows; on Linux only
+//! battery state is detected and the window background time stands in for
+//! idleness.
+//!
+//! The first import of a mailbox runs as a backfill job: it walks back in time
+//! one date window at a time, page by page, feeding each page of threads into
+//! the local analytics history and search index. A checkpoint is saved after
+//! every page, so a paused, failed or killed import resumes where it stopped.
+
+use crate::account::AccountContext;
+use crate::analytics;
+use crate::app_lock::AppLockState;
+use crate::auth::TokenStore;
+use crate::google::gmail;
+use crate::google::mailbox::Mailbox;
+use crate::google::types::GmailThreadsPage;
+use crate::google::{GoogleClient, GMAIL_API_BASE, MAX_RESPONSE_BYTES};
+use crate::processing;
+use crate::search;
+use crate::storage::{LocalStorage, SYNC_BACKFILL};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+/// Emitted when the main window regains focus after being in the background
+pub const SYNC_RESUME_EVENT: &str = "sync:resume";
+/// Label of the window whose focus drives the background timer
+const MAIN_WINDOW_LABEL: &str = "main";
+
+/// Background time before refocusing triggers a resync
+const RESYNC_ON_FOCUS_AFTER: Duration = Duration::from_secs(60);
+/// How often a paused scheduler rechecks conditions
+const PAUSED_RECHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+const IDLE_SLOW_AFTER_SECS: u64 = 10 * 60;
+const IDLE_PAUSE_AFTER_SECS: u64 = 60 * 60;
+const BACKGROUND_SLOW_AFTER_SECS: u64 = 30 * 60;
+const BACKGROUND_PAUSE_AFTER_SECS: u64 = 4 * 60 * 60;
+
+const BATTERY_MULTIPLIER: u32 = 2;
+const SLOW_MULTIPLIER: u32 = 4;
+
+/// System and window state affecting sync frequency
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncConditions {
+    /// Seconds since the last keyboard/mouse input (None if unknown)
+    pub system_idle_secs: Option<u64>,
+    pub on_battery: bool,
+    /// Battery saver / low power mode is on
+    pub power_saver: bool,
+    /// Seconds the main window has been unfocused (None while focused)
+    pub background_secs: Option<u64>,
+}
+
+/// How background sync is currently throttled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    Normal,
+    Slow,
+    Paused,
+}
+
+/// Current scheduling decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub mode: SyncMode,
+    /// Factor applied to every polling interval (1 when normal)
+    pub interval_multiplier: u32,
+    pub conditions: SyncConditions,
+}
+
+/// Decide how much to throttle sync for the given conditions
+pub fn throttle(conditions: &SyncConditions) -> (SyncMode, u32) {
+    let idle = conditions.system_idle_secs.unwrap_or(0);
+    let background = conditions.background_secs.unwrap_or(0);
+
+    if idle >= IDLE_PAUSE_AFTER_SECS || background >= BACKGROUND_PAUSE_AFTER_SECS {
+        return (SyncMode::Paused, 0);
+    }
+
+    if conditions.power_saver
+        || idle >= IDLE_SLOW_AFTER_SECS
+        || background >= BACKGROUND_SLOW_AFTER_SECS
+    {
+        return (SyncMode::Slow, SLOW_MULTIPLIER);
+    }
+
+    if conditions.on_battery {
+        return (SyncMode::Slow, BATTERY_MULTIPLIER);
+    }
+
+    (SyncMode::Normal, 1)
+}
+
+/// Shared sync scheduler
+pub struct SyncScheduler {
+    background_since: Mutex<Option<Instant>>,
+    resync: Notify,
+}
+
+impl Default for SyncScheduler {
+    fn default() -> Self {
+        Self {
+            background_since: Mutex::new(None),
+            resync: Notify::new(),
+        }
+    }
+}
+
+impl SyncScheduler {
+    /// Track main window focus; returns true if focus returned after a long background period
+    pub fn set_focused(&self, focused: bool) -> bool {
+        let Ok(mut since) = self.background_since.lock() else {
+            return false;
+        };
+
+        if focused {
+            let was_away = since.is_some_and(|s| s.elapsed() >= RESYNC_ON_FOCUS_AFTER);
+            *since = None;
+            was_away
+        } else {
+            since.get_or_insert_with(Instant::now);
+            false
+        }
+    }
+
+    /// Wake every loop waiting in `wait`
+    pub fn resync_now(&self) {
+        self.resync.notify_waiters();
+    }
+
+    /// Read the current system and window conditions
+    pub async fn status(&self) -> SyncStatus {
+        let background_secs = self
+            .background_since
+            .lock()
+            .ok()
+            .and_then(|s| s.map(|s| s.elapsed().as_secs()));
+
+        let mut conditions = tokio::task::spawn_blocking(power::probe)
+            .await
+            .unwrap_or_default();
+        conditions.background_secs = background_secs;
+
+        let (mode, interval_multiplier) = throttle(&conditions);
+        SyncStatus {
+            mode,
+            interval_multiplier,
+            conditions,
+        }
+    }
+
+    /// Wait before the next sync of a loop polling every `base`
+    ///
+    /// Returns early when a resync is requested.
+    pub async fn wait(&self, base: Duration) {
+        loop {
+            let status = self.status().await;
+            let delay = match status.mode {
+                SyncMode::Paused => PAUSED_RECHECK_INTERVAL,
+                _ => base * status.interval_multiplier,
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.resync.notified() => return,
+            }
+
+            if status.mode != SyncMode::Paused {
+                return;
+            }
+        }
+    }
+}
+
+/// Handle main window focus changes
+pub fn on_window_focus(app: &AppHandle, label: &str, focused: bool) {
+    if label != MAIN_WINDOW_LABEL {
+        return;
+    }
+
+    let scheduler = app.state::<SyncScheduler>();
+    if scheduler.set_focused(focused) {
+        scheduler.resync_now();
+        if let Err(e) = app.emit(SYNC_RESUME_EVENT, ()) {
+            eprintln!("Failed to emit {}: {}", SYNC_RESUME_EVENT, e);
+        }
+    }
+}
+
+// ============================================================================
+// Mailbox Backfill
+// ============================================================================
+
+/// Emitted with a `BackfillStatus` after every imported page
+pub const BACKFILL_PROGRESS_EVENT: &str = "sync:backfill";
+const BACKFILL_CHECKPOINT_ID: &str = "checkpoint";
+
+/// Date range listed per window
+const BACKFILL_WINDOW_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+const BACKFILL_PAGE_SIZE: u32 = 100;
+const BACKFILL_PARALLELISM: usize = 4;
+/// Pause between pages (stretched by the scheduler like any polling loop)
+const BACKFILL_PAGE_INTERVAL: Duration = Duration::from_secs(2);
+/// How often an interrupted import is retried
+const BACKFILL_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Gmail opened on 2004-04-01, so no mailbox holds older mail
+const GMAIL_LAUNCH_MS: i64 = 1_080_777_600_000;
+
+/// Where an import stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackfillPhase {
+    Running,
+    Paused,
+    Done,
+}
+
+/// Resumable position of a mailbox import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillCheckpoint {
+    pub phase: BackfillPhase,
+    /// End (exclusive, epoch ms) of the window being imported; windows move back in time
+    pub window_end_ms: i64,
+    /// Next page of the current window
+    pub page_token: Option<String>,
+    /// Oldest date imported
+    pub floor_ms: i64,
+    pub started_at_ms: i64,
+    pub updated_at_ms: i64,
+    pub threads_imported: u64,
+    pub threads_failed: u64,
+    pub last_error: Option<String>,
+}
+
+impl BackfillCheckpoint {
+    fn new(now_ms: i64, floor_ms: i64) -> Self {
+        Self {
+            phase: BackfillPhase::Running,
+            window_end_ms: now_ms,
+            page_token: None,
+            floor_ms,
+            started_at_ms: now_ms,
+            updated_at_ms: now_ms,
+            threads_imported: 0,
+            threads_failed: 0,
+            last_error: None,
+        }
+    }
+
+    fn window_start_ms(&self) -> i64 {
+        (self.window_end_ms - BACKFILL_WINDOW_MS).max(self.floor_ms)
+    }
+
+    /// Gmail query of the current window (`after`/`before` take epoch seconds)
+    fn query(&self) -> String {
+        format!(
+            "after:{} before:{}",
+            self.window_start_ms() / 1000,
+            self.window_end_ms / 1000
+        )
+    }
+
+    /// Move past the page just imported
+    fn advance(&mut self, next_page_token: Option<String>) {
+        if next_page_token.is_some() {
+            self.page_token = next_page_token;
+            return;
+        }
+        self.page_token = None;
+        self.window_end_ms = self.window_start_ms();
+        if self.window_end_ms <= self.floor_ms {
+            self.phase = BackfillPhase::Done;
+        }
+    }
+
+    /// Share of the date range already covered (0.0 - 1.0)
+    fn progress(&self) -> f64 {
+        let total = (self.started_at_ms - self.floor_ms).max(1) as f64;
+        ((self.started_at_ms - self.window_end_ms) as f64 / total).clamp(0.0, 1.0)
+    }
+}
+
+/// Import state reported to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillStatus {
+    /// None before the first import
+    pub checkpoint: Option<BackfillCheckpoint>,
+    /// A job is currently importing
+    pub active: bool,
+    pub progress: f64,
+}
+
+/// Guards against concurrent imports and carries pause requests
+#[derive(Default)]
+pub struct BackfillState {
+    active: AtomicBool,
+    pause_requested: AtomicBool,
+}
+
+fn load_checkpoint(
+    app: &AppHandle,
+    account: &AccountContext,
+) -> Result<Option<BackfillCheckpoint>, String> {
+    Ok(app
+        .state::<LocalStorage>()
+        .get(account, SYNC_BACKFILL, BACKFILL_CHECKPOINT_ID)?
+        .and_then(|record| serde_json::from_value(record.value).ok()))
+}
+
+fn save_checkpoint(
+    app: &AppHandle,
+    account: &AccountContext,
+    checkpoint: &mut BackfillCheckpoint,
+) -> Result<(), String> {
+    checkpoint.updated_at_ms = chrono::Utc::now().timestamp_millis();
+    let value = serde_json::to_value(&*checkpoint)
+        .map_err(|e| format!("Failed to serialize backfill checkpoint: {}", e))?;
+    app.state::<LocalStorage>()
+        .put(account, SYNC_BACKFILL, BACKFILL_CHECKPOINT_ID, value)
+}
+
+fn backfill_status(app: &AppHandle, checkpoint: Option<BackfillCheckpoint>) -> BackfillStatus {
+    BackfillStatus {
+        progress: checkpoint
+            .as_ref()
+            .map_or(0.0, BackfillCheckpoint::progress),
+        active: app.state::<BackfillState>().active.load(Ordering::SeqCst),
+        checkpoint,
+    }
+}
+
+fn emit_progress(app: &AppHandle, checkpoint: &BackfillCheckpoint) {
+    let status = backfill_status(app, Some(checkpoint.clone()));
+    if let Err(e) = app.emit(BACKFILL_PROGRESS_EVENT, &status) {
+        eprintln!("Failed to emit {}: {}", BACKFILL_PROGRESS_EVENT, e);
+    }
+}
+
+/// Import one page of the checkpoint's window
+async fn import_page(app: &AppHandle, checkpoint: &mut BackfillCheckpoint) -> Result<(), String> {
+    let token_store = app.state::<TokenStore>();
+    let client = app.state::<GoogleClient>();
+    let storage = app.state::<LocalStorage>();
+
+    let mut url = format!(
+        "{}/users/me/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        BACKFILL_PAGE_SIZE,
+        urlencoding::encode(&checkpoint.query())
+    );
+    if let Some(page) = &checkpoint.page_token {
+        url.push_str(&format!("&pageToken={}", urlencoding::encode(page)));
+    }
+    let (thread_ids, next_page_token) = client
+        .get_with(&url, &token_store, MAX_RESPONSE_BYTES, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            let ids: Vec<String> = page
+                .threads
+                .into_iter()
+                .map(|t| t.id.into_owned())
+                .collect();
+            Ok((ids, page.next_page_token))
+        })
+        .await?;
+
+    let hydration = gmail::hydrate_threads(
+        &token_store,
+        &client,
+        &Mailbox::Own,
+        &thread_ids,
+        BACKFILL_PARALLELISM,
+    )
+    .await;
+    let account = token_store.account_context().await?;
+    analytics::record_threads(&storage, &account, &hydration.threads)?;
+    search::index_threads(&storage, &account, &hydration.threads)?;
+
+    checkpoint.threads_imported += hydration.threads.len() as u64;
+    checkpoint.threads_failed += hydration.errors.len() as u64;
+    checkpoint.last_error = None;
+    checkpoint.advance(next_page_token);
+    Ok(())
+}
+
+/// Run the import until it is done, paused or fails
+async fn run_backfill(app: &AppHandle) -> Result<(), String> {
+    let account = app.state::<TokenStore>().account_context().await?;
+    let Some(mut checkpoint) = load_checkpoint(app, &account)? else {
+        return Ok(());
+    };
+    let state = app.state::<BackfillState>();
+
+    while checkpoint.phase == BackfillPhase::Running {
+        if state.pause_requested.swap(false, Ordering::SeqCst) {
+            checkpoint.phase = BackfillPhase::Paused;
+        } else if let Err(e) = import_page(app, &mut checkpoint).await {
+            checkpoint.last_error = Some(e.clone());
+            save_checkpoint(app, &account, &mut checkpoint)?;
+            emit_progress(app, &checkpoint);
+            return Err(e);
+        }
+        save_checkpoint(app, &account, &mut checkpoint)?;
+        emit_progress(app, &checkpoint);
+
+        if checkpoint.phase == BackfillPhase::Running {
+            app.state::<SyncScheduler>()
+                .wait(BACKFILL_PAGE_INTERVAL)
+                .await;
+        }
+    }
+    Ok(())
+}
+
+/// Start a backfill job unless one is already importing
+fn launch_backfill(app: &AppHandle) {
+    let state = app.state::<BackfillState>();
+    if state.active.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run_backfill(&app).await {
+            eprintln!("Mailbox backfill interrupted: {}", e);
+        }
+        app.state::<BackfillState>()
+            .active
+            .store(false, Ordering::SeqCst);
+    });
+}
+
+/// Resume an unfinished import at startup and after failures
+pub fn spawn_backfill(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let running = match app.state::<TokenStore>().account_context().await {
+                Ok(account) => load_checkpoint(&app, &account)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|c| c.phase == BackfillPhase::Running),
+                Err(_) => false,
+            };
+            if running {
+                launch_backfill(&app);
+            }
+            app.state::<SyncScheduler>()
+                .wait(BACKFILL_RETRY_INTERVAL)
+                .await;
+        }
+    });
+}
+
+// ============================================================================
+// Platform Probes
+// ============================================================================
+
+#[cfg(target_os = "macos")]
+mod power {
+    use super::SyncConditions;
+    use std::process::Command;
+
+    fn run(cmd: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(cmd).args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// `HIDIdleTime` is reported in nanoseconds
+    fn idle_secs() -> Option<u64> {
+        let out = run("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+        let line = out.lines().find(|l| l.contains("\"HIDIdleTime\""))?;
+        let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+        Some(nanos / 1_000_000_000)
+    }
+
+    pub fn probe() -> SyncConditions {
+        SyncConditions {
+            system_idle_secs: idle_secs(),
+            on_battery: run("pmset", &["-g", "batt"])
+                .is_some_and(|out| out.contains("'Battery Power'")),
+            power_saver: run("pmset", &["-g"]).is_some_and(|out| {
+                out.lines().any(|l| {
+                    let mut parts = l.split_whitespace();
+                    parts.next() == Some("lowpowermode") && parts.next() == Some("1")
+                })
+            }),
+            background_secs: None,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod power {
+    use super::SyncConditions;
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    fn idle_secs() -> Option<u64> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a valid, correctly sized LASTINPUTINFO
+        if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+            return None;
+        }
+        // SAFETY: no preconditions
+        let now = unsafe { GetTickCount() };
+        Some(now.wrapping_sub(info.dwTime) as u64 / 1000)
+    }
+
+    pub fn probe() -> SyncConditions {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        // SAFETY: `status` is a valid SYSTEM_POWER_STATUS to fill
+        let power = unsafe { GetSystemPowerStatus(&mut status) }
+            .ok()
+            .map(|_| status);
+
+        SyncConditions {
+            system_idle_secs: idle_secs(),
+            // ACLineStatus: 0 = offline, 1 = online, 255 = unknown
+            on_battery: power.is_some_and(|s| s.ACLineStatus == 0),
+            // SystemStatusFlag: 1 = battery saver on
+            power_saver: power.is_some_and(|s| s.SystemStatusFlag == 1),
+            background_secs: None,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod power {
+    use super::SyncConditions;
+    use std::fs;
+
+    fn read(path: &std::path::Path) -> Option<String> {
+        fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+    }
+
+    fn on_battery() -> bool {
+        let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+            return false;
+        };
+        entries.flatten().any(|entry| {
+            let dir = entry.path();
+            read(&dir.join("type")).as_deref() == Some("Battery")
+                && read(&dir.join("status")).as_deref() == Some("Discharging")
+        })
+    }
+
+    pub fn probe() -> SyncConditions {
+        SyncConditions {
+            system_idle_secs: None,
+            on_battery: on_battery(),
+            power_saver: read("/sys/firmware/acpi/platform_profile".as_ref()).as_deref()
+                == Some("low-power"),
+            background_secs: None,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+mod power {
+    use super::SyncConditions;
+
+    pub fn probe() -> SyncConditions {
+        SyncConditions::default()
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the current sync throttling decision and the conditions behind it
+#[tauri::command]
+pub async fn get_sync_status(scheduler: State<'_, SyncScheduler>) -> Result<SyncStatus, String> {
+    crate::perf::trace_command!();
+    Ok(scheduler.status().await)
+}
+
+/// Wake all background sync loops now
+#[tauri::command]
+pub fn request_sync(scheduler: State<'_, SyncScheduler>) {
+    crate::perf::trace_command!();
+    scheduler.resync_now();
+}
+
+/// Start (or resume) importing the mailbox back to `since` (YYYY-MM-DD,
+/// default everything)
+///
+/// A finished import is started over; a paused or interrupted one continues
+/// from its checkpoint.
+#[tauri::command]
+pub async fn start_backfill(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    since: Option<String>,
+) -> Result<BackfillStatus, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let account = token_store.account_context().await?;
+    let floor_ms = match since.as_deref() {
+        Some(date) => processing::local_midnight_ms(processing::date_or_today(Some(date))?)?,
+        None => GMAIL_LAUNCH_MS,
+    };
+    begin_backfill(&app, &account, floor_ms)
+}
+
+/// Start (or resume) the import back to `floor_ms`
+pub(crate) fn begin_backfill(
+    app: &AppHandle,
+    account: &AccountContext,
+    floor_ms: i64,
+) -> Result<BackfillStatus, String> {
+    let mut checkpoint = match load_checkpoint(app, account)? {
+        Some(checkpoint) if checkpoint.phase != BackfillPhase::Done => checkpoint,
+        _ => BackfillCheckpoint::new(chrono::Utc::now().timestamp_millis(), floor_ms),
+    };
+    checkpoint.phase = BackfillPhase::Running;
+    save_checkpoint(app, account, &mut checkpoint)?;
+
+    app.state::<BackfillState>()
+        .pause_requested
+        .store(false, Ordering::SeqCst);
+    launch_backfill(app);
+    Ok(backfill_status(app, Some(checkpoint)))
+}
+
+/// Import progress of `account`
+pub(crate) fn account_backfill_status(
+    app: &AppHandle,
+    account: &AccountContext,
+) -> Result<BackfillStatus, String> {
+    Ok(backfill_status(app, load_checkpoint(app, account)?))
+}
+
+/// Pause the import after the page in flight
+#[tauri::command]
+pub fn pause_backfill(state: State<'_, BackfillState>) {
+    crate::perf::trace_command!();
+    if state.active.load(Ordering::SeqCst) {
+        state.pause_requested.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Get the mailbox import progress
+#[tauri::command]
+pub async fn get_backfill_status(
+    app: AppHandle,
+    token_store: State<'_, TokenStore>,
+) -> Result<BackfillStatus, String> {
+    crate::perf::trace_command!();
+    let account = token_store.account_context().await?;
+    account_backfill_status(&app, &account)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_modes() {
+        let mut conditions = SyncConditions::default();
+        assert_eq!(throttle(&conditions), (SyncMode::Normal, 1));
+
+        conditions.on_battery = true;
+        assert_eq!(throttle(&conditions), (SyncMode::Slow, BATTERY_MULTIPLIER));
+
+        conditions.power_saver = true;
+        assert_eq!(throttle(&conditions), (SyncMode::Slow, SLOW_MULTIPLIER));
+
+        conditions.system_idle_secs = Some(IDLE_PAUSE_AFTER_SECS);
+        assert_eq!(throttle(&conditions).0, SyncMode::Paused);
+
+        let hidden = SyncConditions {
+            background_secs: Some(BACKGROUND_SLOW_AFTER_SECS),
+            ..Default::default()
+        };
+        assert_eq!(throttle(&hidden), (SyncMode::Slow, SLOW_MULTIPLIER));
+    }
+
+    #[test]
+    fn test_focus_tracking() {
+        let scheduler = SyncScheduler::default();
+        assert!(!scheduler.set_focused(true));
+
+        // A quick alt-tab doesn't trigger a resync
+        assert!(!scheduler.set_focused(false));
+        assert!(!scheduler.set_focused(true));
+
+        scheduler.set_focused(false);
+        *scheduler.background_since.lock().unwrap() = Some(Instant::now() - RESYNC_ON_FOCUS_AFTER);
+        assert!(scheduler.set_focused(true));
+    }
+
+    #[test]
+    fn test_backfill_checkpoint_windows() {
+        let day = 24 * 60 * 60 * 1000;
+        let now = GMAIL_LAUNCH_MS + 45 * day;
+        let mut checkpoint = BackfillCheckpoint::new(now, GMAIL_LAUNCH_MS);
+        assert_eq!(
+            checkpoint.query(),
+            format!("after:{} before:{}", (now - 30 * day) / 1000, now / 1000)
+        );
+
+        // Pages stay in the window
+        checkpoint.advance(Some("page-2".to_string()));
+        assert_eq!(checkpoint.window_end_ms, now);
+        assert_eq!(checkpoint.page_token.as_deref(), Some("page-2"));
+
+        // The last window is cut at the floor
+        checkpoint.advance(None);
+        assert_eq!(checkpoint.page_token, None);
+        assert_eq!(checkpoint.window_start_ms(), GMAIL_LAUNCH_MS);
+        assert!((checkpoint.progress() - 30.0 / 45.0).abs() < 1e-9);
+
+        checkpoint.advance(None);
+        assert_eq!(checkpoint.phase, BackfillPhase::Done);
+        assert_eq!(checkpoint.progress(), 1.0);
+    }
+}
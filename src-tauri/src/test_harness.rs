@@ -0,0 +1,444 @@
+//! End-to-end tests against a fake Google server
+//!
+//! Stands up a wiremock server playing Gmail, Calendar, Tasks and the OAuth
+//! token endpoint, then drives the real `TokenStore` refresh flow and the
+//! `google` fetchers through a `GoogleClient` pointed at it.
+
+use crate::auth::TokenStore;
+use crate::google::batch::BatchCall;
+use crate::google::deadline;
+use crate::google::endpoints::ApiEndpoints;
+use crate::google::error::GoogleErrorKind;
+use crate::google::mailbox::Mailbox;
+use crate::google::recording::RequestRecorder;
+use crate::google::types::{CalendarEventsResponse, GmailThreadsPage, TaskListsResponse};
+use crate::google::{
+    gmail, tasks, GoogleApiError, GoogleClient, CALENDAR_API_BASE, GMAIL_API_BASE, TASKS_API_BASE,
+};
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::Duration;
+use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Fake Google: one server for every API plus the token endpoint
+struct FakeGoogle {
+    server: MockServer,
+    metadata_path: PathBuf,
+}
+
+impl FakeGoogle {
+    async fn start(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "rainyday-fake-google-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Failed to create temp dir");
+
+        Self {
+            server: MockServer::start().await,
+            metadata_path: dir.join("session_metadata.json"),
+        }
+    }
+
+    fn client(&self) -> GoogleClient {
+        GoogleClient::with_base_override(&self.server.uri())
+    }
+
+    /// Token store holding `access_token`, expiring `expires_in` seconds from now
+    async fn token_store(&self, access_token: &str, expires_in: i64) -> TokenStore {
+        TokenStore::for_fake_server(
+            &format!("{}/token", self.server.uri()),
+            self.metadata_path.clone(),
+            "user@example.com",
+            access_token,
+            chrono::Utc::now().timestamp() + expires_in,
+        )
+        .await
+    }
+
+    /// Token endpoint issuing `access_token` for the test refresh token
+    async fn mount_token_endpoint(&self, access_token: &str) {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=test-refresh-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": access_token,
+                "expires_in": 3599,
+                "token_type": "Bearer",
+            })))
+            .expect(1)
+            .mount(&self.server)
+            .await;
+    }
+}
+
+fn task_json(id: &str) -> serde_json::Value {
+    json!({ "id": id, "title": format!("Task {}", id), "status": "needsAction" })
+}
+
+#[tokio::test]
+async fn test_expired_session_refreshes_before_request() {
+    let fake = FakeGoogle::start("refresh").await;
+    fake.mount_token_endpoint("fresh-token").await;
+
+    Mock::given(method("GET"))
+        .and(path("/gmail/v1/users/me/threads/t1"))
+        .and(header("authorization", "Bearer fresh-token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "id": "t1", "messages": [] })),
+        )
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("expired-token", -60).await;
+    let detail = gmail::fetch_thread_detail(&token_store, &fake.client(), &Mailbox::Own, "t1")
+        .await
+        .unwrap();
+
+    assert_eq!(detail.id, "t1");
+    // The refreshed expiry is persisted in the session metadata
+    let metadata = std::fs::read_to_string(&fake.metadata_path).unwrap();
+    assert!(metadata.contains("user@example.com"));
+}
+
+#[tokio::test]
+async fn test_auth_status_refreshes_expiring_session() {
+    let fake = FakeGoogle::start("auth-status").await;
+    fake.mount_token_endpoint("fresh-token").await;
+
+    let token_store = fake.token_store("expiring-token", 60).await;
+    assert!(
+        !token_store
+            .get_auth_status()
+            .await
+            .unwrap()
+            .is_authenticated
+    );
+
+    let status = token_store.refreshed_auth_status().await.unwrap();
+    assert!(status.is_authenticated);
+    assert_eq!(token_store.get_access_token().await.unwrap(), "fresh-token");
+}
+
+#[tokio::test]
+async fn test_concurrent_refreshes_share_one_request() {
+    let fake = FakeGoogle::start("single-flight").await;
+    // expect(1): a second refresh would present a rotated-away token
+    fake.mount_token_endpoint("fresh-token").await;
+
+    let token_store = fake.token_store("expired-token", -60).await;
+    let (first, second, third) = tokio::join!(
+        token_store.get_access_token(),
+        token_store.refresh_access_token(),
+        token_store.get_access_token(),
+    );
+
+    for token in [first, second, third] {
+        assert_eq!(token.unwrap(), "fresh-token");
+    }
+}
+
+#[tokio::test]
+async fn test_thread_hydration_reports_partial_failures() {
+    let fake = FakeGoogle::start("hydrate").await;
+
+    for id in ["t1", "t3"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/gmail/v1/users/me/threads/{}", id)))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "id": id, "messages": [] })),
+            )
+            .mount(&fake.server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/gmail/v1/users/me/threads/t2"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let ids: Vec<String> = ["t1", "t2", "t3"].iter().map(|s| s.to_string()).collect();
+    let hydration =
+        gmail::hydrate_threads(&token_store, &fake.client(), &Mailbox::Own, &ids, 2).await;
+
+    let hydrated: Vec<_> = hydration.threads.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(hydrated, vec!["t1", "t3"]);
+    assert_eq!(hydration.errors.len(), 1);
+    assert_eq!(hydration.errors[0].thread_id, "t2");
+    assert_eq!(hydration.errors[0].error.status(), Some(404));
+}
+
+#[tokio::test]
+async fn test_unauthorized_refreshes_and_retries_once() {
+    let fake = FakeGoogle::start("401").await;
+    fake.mount_token_endpoint("fresh-token").await;
+
+    let events_path = "/calendar/v3/calendars/primary/events";
+    Mock::given(method("GET"))
+        .and(path(events_path))
+        .and(header("authorization", "Bearer revoked-token"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(events_path))
+        .and(header("authorization", "Bearer fresh-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [{
+                "id": "e1",
+                "summary": "Standup",
+                "start": { "dateTime": "2026-01-15T09:30:00Z" },
+                "end": { "dateTime": "2026-01-15T10:00:00Z" },
+            }],
+        })))
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    // Not expired locally, but the server has revoked it
+    let token_store = fake.token_store("revoked-token", 3600).await;
+    let url = format!("{}/calendars/primary/events", CALENDAR_API_BASE);
+    let response: CalendarEventsResponse = fake.client().get(&url, &token_store).await.unwrap();
+
+    assert_eq!(response.items.unwrap()[0].id, "e1");
+    assert_eq!(token_store.get_access_token().await.unwrap(), "fresh-token");
+}
+
+#[tokio::test]
+async fn test_tasks_pagination_follows_next_page_token() {
+    let fake = FakeGoogle::start("pages").await;
+    let tasks_path = "/tasks/v1/lists/list-1/tasks";
+
+    Mock::given(method("GET"))
+        .and(path(tasks_path))
+        .and(query_param("pageToken", "page-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [task_json("3")],
+        })))
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+    // Lower priority so the pageToken mock wins for the second page
+    Mock::given(method("GET"))
+        .and(path(tasks_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [task_json("1"), task_json("2")],
+            "nextPageToken": "page-2",
+        })))
+        .with_priority(10)
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let tasks = tasks::fetch_tasks(&token_store, &fake.client(), "list-1", false)
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = tasks.iter().filter_map(|t| t.id.clone()).collect();
+    assert_eq!(ids, vec!["1", "2", "3"]);
+}
+
+#[tokio::test]
+async fn test_rate_limited_request_is_retried() {
+    let fake = FakeGoogle::start("429").await;
+    let lists_path = "/tasks/v1/users/@me/lists";
+
+    Mock::given(method("GET"))
+        .and(path(lists_path))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&fake.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(lists_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [{ "id": "list-1", "title": "My Tasks" }],
+        })))
+        .with_priority(10)
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let client = fake.client();
+    let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+    let response: TaskListsResponse = client.get(&url, &token_store).await.unwrap();
+
+    assert_eq!(response.items.unwrap()[0].id, "list-1");
+}
+
+#[tokio::test]
+async fn test_persistent_rate_limit_gives_up() {
+    let fake = FakeGoogle::start("429-forever").await;
+
+    Mock::given(method("GET"))
+        .and(path("/tasks/v1/users/@me/lists"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let client = fake.client();
+    let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+    let result: Result<TaskListsResponse, GoogleApiError> = client.get(&url, &token_store).await;
+
+    let error = result.unwrap_err();
+    assert!(error.to_string().starts_with("API error 429"));
+    assert_eq!(error.kind(), GoogleErrorKind::RateLimited);
+}
+
+#[tokio::test]
+async fn test_deadline_cuts_slow_request() {
+    let fake = FakeGoogle::start("deadline").await;
+
+    Mock::given(method("GET"))
+        .and(path("/tasks/v1/users/@me/lists"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "items": [] }))
+                .set_delay(Duration::from_secs(5)),
+        )
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let client = fake.client();
+    let url = format!("{}/users/@me/lists", TASKS_API_BASE);
+    let started = std::time::Instant::now();
+    let result: Result<TaskListsResponse, GoogleApiError> =
+        deadline::within(Some(100), client.get(&url, &token_store)).await;
+
+    let error = result.unwrap_err();
+    assert_eq!(error.kind(), GoogleErrorKind::Timeout);
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_borrowed_parse_and_size_limit() {
+    let fake = FakeGoogle::start("size-limit").await;
+    let threads: Vec<_> = (0..200)
+        .map(|i| json!({ "id": format!("t{}", i), "snippet": "Quarterly \"numbers\" attached" }))
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path("/gmail/v1/users/me/threads"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "threads": threads })))
+        .mount(&fake.server)
+        .await;
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let client = fake.client();
+    let url = format!("{}/users/me/threads", GMAIL_API_BASE);
+
+    let ids = client
+        .get_with(&url, &token_store, 1024 * 1024, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            assert_eq!(page.threads[0].snippet, "Quarterly \"numbers\" attached");
+            Ok(page.threads.len())
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids, 200);
+
+    let result = client
+        .get_with(&url, &token_store, 1024, |body| {
+            serde_json::from_slice::<GmailThreadsPage>(body).map(|p| p.threads.len())
+        })
+        .await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("Response too large"));
+}
+
+#[tokio::test]
+async fn test_configured_endpoint_and_recording() {
+    let fake = FakeGoogle::start("recording").await;
+    Mock::given(method("GET"))
+        .and(path("/proxy/gmail/users/me/threads/t1"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "id": "t1", "messages": [] })),
+        )
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    let client = GoogleClient::new();
+    client.set_endpoints(ApiEndpoints {
+        gmail: Some(format!("{}/proxy/gmail", fake.server.uri())),
+        ..Default::default()
+    });
+    let dir = fake.metadata_path.with_file_name("recordings");
+    client.set_recorder(Some(RequestRecorder::new(dir.clone()).unwrap()));
+
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let detail = gmail::fetch_thread_detail(&token_store, &client, &Mailbox::Own, "t1")
+        .await
+        .unwrap();
+    assert_eq!(detail.id, "t1");
+
+    let recorded = std::fs::read_to_string(dir.join("000000-get.json")).unwrap();
+    assert!(recorded.contains("/proxy/gmail/users/me/threads/t1"));
+    assert!(!recorded.contains("valid-token"));
+}
+
+#[tokio::test]
+async fn test_batch_maps_parts_to_calls() {
+    let fake = FakeGoogle::start("batch").await;
+    let body = "--batch_fake\r\n\
+Content-Type: application/http\r\n\
+Content-ID: <response-item1>\r\n\r\n\
+HTTP/1.1 404 Not Found\r\n\
+Content-Type: application/json\r\n\r\n\
+{\"error\": {\"code\": 404, \"message\": \"Requested entity was not found.\"}}\r\n\
+--batch_fake\r\n\
+Content-Type: application/http\r\n\
+Content-ID: <response-item0>\r\n\r\n\
+HTTP/1.1 200 OK\r\n\
+Content-Type: application/json\r\n\r\n\
+{\"id\": \"t1\", \"messages\": []}\r\n\
+--batch_fake--\r\n";
+    Mock::given(method("POST"))
+        .and(path("/batch/gmail/v1"))
+        .and(body_string_contains(
+            "GET /gmail/v1/users/me/threads/t1 HTTP/1.1",
+        ))
+        .and(body_string_contains(
+            "GET /gmail/v1/users/me/threads/gone HTTP/1.1",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(body, "multipart/mixed; boundary=batch_fake"),
+        )
+        .expect(1)
+        .mount(&fake.server)
+        .await;
+
+    let calls: Vec<BatchCall> = ["t1", "gone"]
+        .iter()
+        .map(|id| BatchCall {
+            id: id.to_string(),
+            method: "GET".to_string(),
+            url: format!("{}/users/me/threads/{}", GMAIL_API_BASE, id),
+            body: None,
+        })
+        .collect();
+    let token_store = fake.token_store("valid-token", 3600).await;
+    let outcomes = fake.client().batch(&calls, &token_store).await.unwrap();
+
+    assert_eq!(outcomes[0].id, "t1");
+    assert_eq!(outcomes[0].result.as_ref().unwrap()["id"], "t1");
+    assert_eq!(outcomes[1].id, "gone");
+    assert_eq!(
+        outcomes[1].result.as_ref().unwrap_err().kind(),
+        GoogleErrorKind::NotFound
+    );
+}
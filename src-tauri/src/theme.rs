@@ -2,24 +2,23 @@
 //!
 //! Handles theme persistence and system theme detection
 
+use crate::settings::{self, SettingsSection};
+use crate::storage;
 use tauri::AppHandle;
-use tauri_plugin_store::StoreExt;
 
 const THEME_STORE_FILE: &str = "theme.json";
 const THEME_MODE_KEY: &str = "mode";
 const THEME_NAME_KEY: &str = "name";
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ThemePreference {
     pub mode: String,
     pub name: String,
 }
 
-/// Get the saved theme preference
-#[tauri::command]
-pub async fn get_theme(app: AppHandle) -> Result<ThemePreference, String> {
-    let store = app
-        .store(THEME_STORE_FILE)
+/// The saved theme preference
+pub(crate) fn load_preference(app: &AppHandle) -> Result<ThemePreference, String> {
+    let store = storage::fs::settings_store(app, THEME_STORE_FILE)
         .map_err(|e| format!("Failed to access theme store: {}", e))?;
 
     let mode = match store.get(THEME_MODE_KEY) {
@@ -35,9 +34,9 @@ pub async fn get_theme(app: AppHandle) -> Result<ThemePreference, String> {
     Ok(ThemePreference { mode, name })
 }
 
-/// Save the theme preference
-#[tauri::command]
-pub async fn set_theme(app: AppHandle, mode: String, name: String) -> Result<(), String> {
+/// Validate and save a theme preference
+pub(crate) fn save_preference(app: &AppHandle, preference: &ThemePreference) -> Result<(), String> {
+    let ThemePreference { mode, name } = preference;
     // Validate theme mode
     let valid_modes = ["day", "night", "automatic"];
     if !valid_modes.contains(&mode.as_str()) {
@@ -65,17 +64,29 @@ pub async fn set_theme(app: AppHandle, mode: String, name: String) -> Result<(),
         ));
     }
 
-    let store = app
-        .store(THEME_STORE_FILE)
+    let store = storage::fs::settings_store(app, THEME_STORE_FILE)
         .map_err(|e| format!("Failed to access theme store: {}", e))?;
 
     store.set(THEME_MODE_KEY, serde_json::json!(mode));
     store.set(THEME_NAME_KEY, serde_json::json!(name));
 
-    store
-        .save()
-        .map_err(|e| format!("Failed to save theme: {}", e))?;
+    storage::fs::save_store(app, THEME_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save theme: {}", e))
+}
 
+/// Get the saved theme preference
+#[tauri::command]
+pub async fn get_theme(app: AppHandle) -> Result<ThemePreference, String> {
+    crate::perf::trace_command!();
+    load_preference(&app)
+}
+
+/// Save the theme preference
+#[tauri::command]
+pub async fn set_theme(app: AppHandle, mode: String, name: String) -> Result<(), String> {
+    crate::perf::trace_command!();
+    save_preference(&app, &ThemePreference { mode, name })?;
+    settings::touch(&app, SettingsSection::Theme);
     Ok(())
 }
 
@@ -83,6 +94,7 @@ pub async fn set_theme(app: AppHandle, mode: String, name: String) -> Result<(),
 /// Returns "day" or "night" based on system appearance
 #[tauri::command]
 pub fn get_system_theme() -> String {
+    crate::perf::trace_command!();
     // On macOS, we can use dark-light crate, but for simplicity
     // we'll return "night" as default and let the frontend handle it
     // via CSS media query prefers-color-scheme
@@ -95,5 +107,6 @@ pub fn get_system_theme() -> String {
 /// Reset theme to default (automatic)
 #[tauri::command]
 pub async fn reset_theme(app: AppHandle) -> Result<(), String> {
+    crate::perf::trace_command!();
     set_theme(app, "automatic".to_string(), "default".to_string()).await
 }
@@ -0,0 +1,495 @@
+//! Travel time and "leave by" reminders
+//!
+//! Events with a physical location get a travel estimate: either the
+//! configured default, or (when an origin and a Google Maps API key are set)
+//! the Distance Matrix driving time in current traffic. The key lives in the
+//! keychain (`set_routing_api_key`), not in the settings file. The estimate plus a
+//! buffer gives a "leave by" time, which is returned for the day's plan and
+//! written to the scheduled notifications so the reminder fires at departure
+//! rather than at the meeting start.
+//!
+//! The day's working location (see `calendar::day_working_location`) picks the
+//! routing origin: `office_origin` on office days, `origin` otherwise.
+
+use crate::app_lock::AppLockState;
+use crate::auth::{self, TokenStore};
+use crate::google::calendar::{self, WorkingLocation, WorkingLocationKind};
+use crate::google::types::CalendarEvent;
+use crate::google::GoogleClient;
+use crate::planner::ScheduledNotification;
+use crate::storage::{self, LocalStorage, SCHEDULED_NOTIFICATIONS};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+const TRAVEL_STORE_FILE: &str = "travel.json";
+const SETTINGS_KEY: &str = "settings";
+/// Settings field that held the Maps API key before it moved to the keychain
+const LEGACY_ROUTING_KEY_FIELD: &str = "routing_api_key";
+
+const DISTANCE_MATRIX_URL: &str = "https://maps.googleapis.com/maps/api/distancematrix/json";
+const ROUTING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Prefix of scheduled notification IDs created for departures
+const LEAVE_BY_ID_PREFIX: &str = "leave_by:";
+
+/// Location words that mean the meeting is not somewhere you travel to
+const VIRTUAL_LOCATION_HINTS: &[&str] = &[
+    "http://",
+    "https://",
+    "meet.google.com",
+    "zoom",
+    "teams",
+    "webex",
+    "skype",
+    "online",
+    "virtual",
+    "remote",
+    "phone",
+    "tbd",
+];
+
+/// Travel settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TravelSettings {
+    pub enabled: bool,
+    /// Travel time assumed when no routing estimate is available
+    pub default_minutes: u32,
+    /// Extra margin before the travel time
+    pub buffer_minutes: u32,
+    /// Starting address for routing estimates
+    pub origin: Option<String>,
+    /// Starting address on office days (from the calendar's working location)
+    #[serde(default)]
+    pub office_origin: Option<String>,
+}
+
+impl Default for TravelSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_minutes: 20,
+            buffer_minutes: 5,
+            origin: None,
+            office_origin: None,
+        }
+    }
+}
+
+/// Where a travel estimate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TravelEstimateSource {
+    Default,
+    Routing,
+}
+
+/// Departure entry for an event with a physical location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaveBy {
+    pub event_id: String,
+    pub title: String,
+    pub location: String,
+    pub event_start_ms: i64,
+    pub leave_by_ms: i64,
+    pub travel_minutes: u32,
+    pub source: TravelEstimateSource,
+    /// Working location the trip starts from, if the day has one
+    pub working_location: Option<WorkingLocationKind>,
+}
+
+pub fn load_settings(app: &AppHandle) -> Result<TravelSettings, String> {
+    let store = storage::fs::settings_store(app, TRAVEL_STORE_FILE)
+        .map_err(|e| format!("Failed to access travel store: {}", e))?;
+
+    let mut value = store.get(SETTINGS_KEY);
+    if let Some(value) = value.as_mut() {
+        migrate_routing_api_key(app, value);
+    }
+
+    Ok(value
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Move a Maps API key saved in the settings file into the keychain
+fn migrate_routing_api_key(app: &AppHandle, value: &mut serde_json::Value) {
+    let Some(key) = take_legacy_routing_key(value) else {
+        return;
+    };
+    if !key.is_empty() {
+        if let Err(e) = auth::store_routing_api_key(&key) {
+            eprintln!("Failed to move routing API key to the keychain: {}", e);
+            return;
+        }
+    }
+
+    let result = storage::fs::settings_store(app, TRAVEL_STORE_FILE)
+        .map_err(|e| e.to_string())
+        .and_then(|store| {
+            store.set(SETTINGS_KEY, value.clone());
+            storage::fs::save_store(app, TRAVEL_STORE_FILE, &store).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("Failed to save travel settings: {}", e);
+    }
+}
+
+/// Remove the legacy key field from saved settings, returning its value
+fn take_legacy_routing_key(value: &mut serde_json::Value) -> Option<String> {
+    let key = value.as_object_mut()?.remove(LEGACY_ROUTING_KEY_FIELD)?;
+    Some(key.as_str().unwrap_or_default().trim().to_string())
+}
+
+/// Whether a location is a place to travel to (not a link or dial-in)
+pub fn is_physical_location(location: &str) -> bool {
+    let location = location.trim().to_lowercase();
+    !location.is_empty()
+        && !VIRTUAL_LOCATION_HINTS
+            .iter()
+            .any(|hint| location.contains(hint))
+}
+
+/// Departure time for an event starting at `event_start_ms`
+fn leave_by_ms(event_start_ms: i64, travel_minutes: u32, buffer_minutes: u32) -> i64 {
+    event_start_ms - (travel_minutes as i64 + buffer_minutes as i64) * 60_000
+}
+
+// ============================================================================
+// Routing
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+struct DistanceMatrixResponse {
+    status: String,
+    #[serde(default)]
+    rows: Vec<DistanceMatrixRow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DistanceMatrixRow {
+    #[serde(default)]
+    elements: Vec<DistanceMatrixElement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DistanceMatrixElement {
+    status: String,
+    duration: Option<DistanceMatrixValue>,
+    duration_in_traffic: Option<DistanceMatrixValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DistanceMatrixValue {
+    /// Seconds
+    value: u64,
+}
+
+/// Travel minutes from a Distance Matrix response (traffic-aware when available)
+fn parse_distance_matrix(response: &DistanceMatrixResponse) -> Result<u32, String> {
+    if response.status != "OK" {
+        return Err(format!("Routing request failed: {}", response.status));
+    }
+    let element = response
+        .rows
+        .first()
+        .and_then(|row| row.elements.first())
+        .ok_or("Routing response has no route")?;
+    if element.status != "OK" {
+        return Err(format!("No route found: {}", element.status));
+    }
+    let seconds = element
+        .duration_in_traffic
+        .as_ref()
+        .or(element.duration.as_ref())
+        .ok_or("Routing response has no duration")?
+        .value;
+    Ok(seconds.div_ceil(60) as u32)
+}
+
+async fn routing_estimate(
+    http: &reqwest::Client,
+    origin: &str,
+    api_key: &str,
+    destination: &str,
+) -> Result<u32, String> {
+    let url = format!(
+        "{}?origins={}&destinations={}&mode=driving&departure_time=now&key={}",
+        DISTANCE_MATRIX_URL,
+        urlencoding::encode(origin),
+        urlencoding::encode(destination),
+        urlencoding::encode(api_key)
+    );
+    let response: DistanceMatrixResponse = http
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Routing request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse routing response: {}", e))?;
+    parse_distance_matrix(&response)
+}
+
+/// Routing origin for a day: the office address on office days, else the default
+fn day_origin(settings: &TravelSettings, location: Option<&WorkingLocation>) -> Option<String> {
+    match location.map(|l| l.kind) {
+        Some(WorkingLocationKind::Office) => settings
+            .office_origin
+            .clone()
+            .or_else(|| settings.origin.clone()),
+        _ => settings.origin.clone(),
+    }
+}
+
+/// Travel time from `origin` to `destination`, falling back to the default estimate
+async fn estimate_travel(
+    http: &reqwest::Client,
+    settings: &TravelSettings,
+    routing_key: Option<&str>,
+    origin: Option<&str>,
+    destination: &str,
+) -> (u32, TravelEstimateSource) {
+    if let (Some(origin), Some(key)) = (origin, routing_key) {
+        match routing_estimate(http, origin, key, destination).await {
+            Ok(minutes) => return (minutes, TravelEstimateSource::Routing),
+            Err(e) => eprintln!("Travel estimate for '{}' failed: {}", destination, e),
+        }
+    }
+    (settings.default_minutes, TravelEstimateSource::Default)
+}
+
+// ============================================================================
+// Leave-by Entries
+// ============================================================================
+
+fn event_start_ms(event: &CalendarEvent) -> Option<i64> {
+    // All-day events have no start time to travel to
+    let start = event.start.as_ref()?.date_time.as_deref()?;
+    DateTime::parse_from_rfc3339(start)
+        .ok()
+        .map(|d| d.timestamp_millis())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get travel settings
+#[tauri::command]
+pub async fn get_travel_settings(app: AppHandle) -> Result<TravelSettings, String> {
+    crate::perf::trace_command!();
+    load_settings(&app)
+}
+
+/// Save travel settings
+#[tauri::command]
+pub async fn set_travel_settings(app: AppHandle, settings: TravelSettings) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let store = storage::fs::settings_store(&app, TRAVEL_STORE_FILE)
+        .map_err(|e| format!("Failed to access travel store: {}", e))?;
+
+    store.set(SETTINGS_KEY, serde_json::json!(settings));
+    storage::fs::save_store(&app, TRAVEL_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save travel settings: {}", e))
+}
+
+/// Save the Google Maps API key in the keychain; an empty key removes it
+#[tauri::command]
+pub async fn set_routing_api_key(
+    app_lock: State<'_, AppLockState>,
+    api_key: String,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        auth::delete_routing_api_key()
+    } else {
+        auth::store_routing_api_key(api_key)
+    }
+}
+
+/// Whether a Google Maps API key is saved
+#[tauri::command]
+pub async fn has_routing_api_key(app_lock: State<'_, AppLockState>) -> Result<bool, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    Ok(auth::get_routing_api_key()?.is_some())
+}
+
+/// Get "leave by" entries for events with physical locations on `date` (YYYY-MM-DD)
+///
+/// Also schedules a departure reminder for each upcoming entry.
+#[tauri::command]
+pub async fn get_leave_by_entries(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    date: String,
+) -> Result<Vec<LeaveBy>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let settings = load_settings(&app)?;
+    if !settings.enabled {
+        return Ok(Vec::new());
+    }
+    let day = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|_| "Invalid date, expected YYYY-MM-DD".to_string())?;
+
+    let http = reqwest::Client::builder()
+        .timeout(ROUTING_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let events = calendar::events_on(&token_store, &client, day, None).await?;
+    let working_location = calendar::day_working_location(&events);
+    let origin = day_origin(&settings, working_location.as_ref());
+    let routing_key = auth::get_routing_api_key().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        None
+    });
+
+    let mut entries = Vec::new();
+    for event in events {
+        if event.status.as_deref() == Some("cancelled")
+            || calendar::working_location_of(&event).is_some()
+        {
+            continue;
+        }
+        let (Some(location), Some(start_ms)) = (event.location.clone(), event_start_ms(&event))
+        else {
+            continue;
+        };
+        if !is_physical_location(&location) {
+            continue;
+        }
+
+        let (travel_minutes, source) = estimate_travel(
+            &http,
+            &settings,
+            routing_key.as_deref(),
+            origin.as_deref(),
+            &location,
+        )
+        .await;
+        entries.push(LeaveBy {
+            event_id: event.id,
+            title: event.summary.unwrap_or_else(|| "(No title)".to_string()),
+            location,
+            event_start_ms: start_ms,
+            leave_by_ms: leave_by_ms(start_ms, travel_minutes, settings.buffer_minutes),
+            travel_minutes,
+            source,
+            working_location: working_location.as_ref().map(|l| l.kind),
+        });
+    }
+
+    let account = token_store.account_context().await?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let reminders = entries
+        .iter()
+        .filter(|e| e.leave_by_ms > now)
+        .map(|e| {
+            let reminder = ScheduledNotification {
+                title: format!("Leave now for {}", e.title),
+                body: Some(format!(
+                    "{} min to {}",
+                    e.travel_minutes + settings.buffer_minutes,
+                    e.location
+                )),
+                fire_at_ms: e.leave_by_ms,
+            };
+            serde_json::to_value(&reminder)
+                .map(|value| (format!("{}{}", LEAVE_BY_ID_PREFIX, e.event_id), value))
+                .map_err(|e| format!("Failed to serialize reminder: {}", e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    storage.put_many(&account, SCHEDULED_NOTIFICATIONS, reminders)?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_physical_location() {
+        assert!(is_physical_location(
+            "1600 Amphitheatre Pkwy, Mountain View"
+        ));
+        assert!(is_physical_location("Room 4B"));
+        assert!(!is_physical_location("https://zoom.us/j/123"));
+        assert!(!is_physical_location("Microsoft Teams Meeting"));
+        assert!(!is_physical_location("  "));
+    }
+
+    #[test]
+    fn test_leave_by_from_routing_estimate() {
+        let response: DistanceMatrixResponse = serde_json::from_str(
+            r#"{"status":"OK","rows":[{"elements":[{"status":"OK",
+                "duration":{"value":1200},"duration_in_traffic":{"value":1530}}]}]}"#,
+        )
+        .unwrap();
+        let minutes = parse_distance_matrix(&response).unwrap();
+        assert_eq!(minutes, 26);
+        assert_eq!(
+            leave_by_ms(10_000_000, minutes, 5),
+            10_000_000 - 31 * 60_000
+        );
+
+        let no_route: DistanceMatrixResponse = serde_json::from_str(
+            r#"{"status":"OK","rows":[{"elements":[{"status":"ZERO_RESULTS"}]}]}"#,
+        )
+        .unwrap();
+        assert!(parse_distance_matrix(&no_route).is_err());
+    }
+
+    #[test]
+    fn test_take_legacy_routing_key() {
+        let mut value = serde_json::json!({
+            "enabled": true,
+            "default_minutes": 20,
+            "buffer_minutes": 5,
+            "origin": null,
+            "routing_api_key": " AIza-secret ",
+        });
+        assert_eq!(
+            take_legacy_routing_key(&mut value).as_deref(),
+            Some("AIza-secret")
+        );
+        assert!(!value.to_string().contains("AIza-secret"));
+        assert!(serde_json::from_value::<TravelSettings>(value.clone()).is_ok());
+        assert_eq!(take_legacy_routing_key(&mut value), None);
+    }
+
+    #[test]
+    fn test_day_origin_follows_working_location() {
+        let settings = TravelSettings {
+            origin: Some("Home St 1".to_string()),
+            office_origin: Some("Office Ave 9".to_string()),
+            ..Default::default()
+        };
+        let office = WorkingLocation {
+            kind: WorkingLocationKind::Office,
+            label: None,
+        };
+        let home = WorkingLocation {
+            kind: WorkingLocationKind::Home,
+            label: None,
+        };
+        assert_eq!(
+            day_origin(&settings, Some(&office)).as_deref(),
+            Some("Office Ave 9")
+        );
+        assert_eq!(
+            day_origin(&settings, Some(&home)).as_deref(),
+            Some("Home St 1")
+        );
+        assert_eq!(day_origin(&settings, None).as_deref(), Some("Home St 1"));
+    }
+}
@@ -0,0 +1,658 @@
+//! Inbox zero triage sessions
+//!
+//! `start_session` loads the inbox (or a Gmail query) once, scores every
+//! thread with `processing::calculate_priority_score` and queues them highest
+//! first. `next_item` serves the thread at the head of the queue and `act`
+//! records a decision for it. Nothing is changed in Gmail until
+//! `end_session`, which applies every decision at once (one request per
+//! thread, with bounded concurrency) and returns the session stats.
+//!
+//! Decisions are kept in the `TRIAGE_HISTORY` collection, which `rules` mines
+//! for rule suggestions; adopted rules pre-fill `suggested_action`.
+//!
+//! Only one session runs at a time; it lives in memory, so decisions are lost
+//! if the app quits before the session ends.
+
+use crate::account::AccountContext;
+use crate::analytics::{self, MessageMetadata};
+use crate::app_lock::AppLockState;
+use crate::auth::capabilities::{self, Feature};
+use crate::auth::TokenStore;
+use crate::events::DataEvent;
+use crate::google::calendar::split_addresses;
+use crate::google::gmail::{self, DEFAULT_HYDRATION_PARALLELISM};
+use crate::google::mailbox::{self, Mailbox};
+use crate::google::types::{GmailThreadDetail, GmailThreadsPage};
+use crate::google::{fan_out, invalidation, GoogleClient, GMAIL_API_BASE, MAX_RESPONSE_BYTES};
+use crate::processing::{calculate_priority_score, has_urgent_keywords, PriorityInput};
+use crate::rules;
+use crate::storage::{LocalStorage, EMAIL_METADATA, TRIAGE_HISTORY};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+const DEFAULT_QUERY: &str = "in:inbox";
+const DEFAULT_MAX_ITEMS: u32 = 50;
+/// Upper bound on threads loaded into one session
+const MAX_ITEMS: u32 = 200;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Decision taken on a thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageAction {
+    /// Remove from the inbox
+    Archive,
+    MarkRead,
+    Star,
+    Trash,
+    /// Leave the thread as it is
+    Skip,
+}
+
+impl TriageAction {
+    /// Labels to add and remove (None for actions that aren't label changes)
+    fn label_changes(self) -> Option<(&'static [&'static str], &'static [&'static str])> {
+        match self {
+            TriageAction::Archive => Some((&[], &["INBOX"])),
+            TriageAction::MarkRead => Some((&[], &["UNREAD"])),
+            TriageAction::Star => Some((&["STARRED"], &[])),
+            TriageAction::Trash | TriageAction::Skip => None,
+        }
+    }
+}
+
+/// A thread in the triage queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageItem {
+    pub thread_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub snippet: String,
+    pub message_count: usize,
+    pub is_unread: bool,
+    pub priority_score: f64,
+    /// Zero-based position in the queue
+    pub position: usize,
+    /// Action of an adopted rule matching the sender (see `rules`)
+    pub suggested_action: Option<TriageAction>,
+}
+
+/// A decision kept in the triage history, for rule suggestions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageRecord {
+    pub thread_id: String,
+    pub from_email: String,
+    pub action: TriageAction,
+    pub was_unread: bool,
+    pub decided_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageDecision {
+    pub thread_id: String,
+    pub action: TriageAction,
+    pub decided_at_ms: i64,
+}
+
+/// Progress and pace of a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageStats {
+    pub session_id: String,
+    pub total: usize,
+    pub processed: usize,
+    pub remaining: usize,
+    /// Decisions per action (snake_case action name)
+    pub actions: HashMap<String, usize>,
+    pub elapsed_ms: i64,
+    pub items_per_minute: f64,
+}
+
+/// A thread whose decision could not be applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageFailure {
+    pub thread_id: String,
+    pub action: TriageAction,
+    pub error: String,
+}
+
+/// Result of ending a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageSummary {
+    pub stats: TriageStats,
+    /// Threads changed in Gmail
+    pub applied: usize,
+    pub failures: Vec<TriageFailure>,
+}
+
+struct TriageSession {
+    id: String,
+    account_email: String,
+    mailbox: Mailbox,
+    queue: Vec<TriageItem>,
+    decisions: Vec<TriageDecision>,
+    started_at_ms: i64,
+}
+
+impl TriageSession {
+    fn current(&self) -> Option<&TriageItem> {
+        self.queue.get(self.decisions.len())
+    }
+
+    fn stats(&self, now_ms: i64) -> TriageStats {
+        let mut actions: HashMap<String, usize> = HashMap::new();
+        for decision in &self.decisions {
+            *actions.entry(action_name(decision.action)).or_default() += 1;
+        }
+        let elapsed_ms = (now_ms - self.started_at_ms).max(0);
+        let minutes = elapsed_ms as f64 / 60_000.0;
+
+        TriageStats {
+            session_id: self.id.clone(),
+            total: self.queue.len(),
+            processed: self.decisions.len(),
+            remaining: self.queue.len() - self.decisions.len(),
+            actions,
+            elapsed_ms,
+            items_per_minute: if minutes > 0.0 {
+                self.decisions.len() as f64 / minutes
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+fn action_name(action: TriageAction) -> String {
+    serde_json::to_value(action)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Managed triage state (at most one session)
+#[derive(Default)]
+pub struct TriageState(Mutex<Option<TriageSession>>);
+
+impl TriageState {
+    fn guard(&self) -> Result<std::sync::MutexGuard<'_, Option<TriageSession>>, String> {
+        self.0
+            .lock()
+            .map_err(|_| "Triage state poisoned".to_string())
+    }
+}
+
+// ============================================================================
+// Queue
+// ============================================================================
+
+/// Score a hydrated thread from its newest message
+///
+/// `own_email` is the mailbox owner (for the direct-recipient check) and
+/// `known_senders` the senders whose mail was opened before.
+fn triage_item(
+    thread: &GmailThreadDetail,
+    own_email: &str,
+    known_senders: &HashSet<String>,
+    now_ms: i64,
+) -> TriageItem {
+    let messages = thread.messages.as_deref().unwrap_or_default();
+    let newest = messages.last();
+    let header = |name: &str| {
+        newest
+            .and_then(|m| analytics::header(m, name))
+            .unwrap_or_default()
+    };
+
+    let subject = header("Subject").to_string();
+    let (from_name, from_email) = analytics::parse_from_header(header("From"));
+    let snippet = newest.map(|m| m.snippet.clone()).unwrap_or_default();
+    let is_unread = messages.iter().any(|m| {
+        m.label_ids
+            .as_ref()
+            .is_some_and(|labels| labels.iter().any(|l| l == "UNREAD"))
+    });
+    let sent_ms: i64 = newest
+        .and_then(|m| m.internal_date.as_deref())
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(now_ms);
+
+    let to = split_addresses(header("To"));
+    let cc = split_addresses(header("Cc"));
+    let is_direct = to
+        .iter()
+        .any(|a| analytics::parse_from_header(a).1 == own_email);
+
+    let priority_score = calculate_priority_score(PriorityInput {
+        is_unread,
+        age_hours: (now_ms - sent_ms).max(0) as f64 / 3_600_000.0,
+        from_known_contact: known_senders.contains(&from_email),
+        has_urgent_keywords: has_urgent_keywords(format!("{} {}", subject, snippet)),
+        recipient_count: to.len() + cc.len(),
+        is_direct,
+        thread_size: messages.len(),
+    });
+
+    TriageItem {
+        thread_id: thread.id.clone(),
+        subject,
+        from_name,
+        from_email,
+        snippet,
+        message_count: messages.len(),
+        is_unread,
+        priority_score,
+        position: 0,
+        suggested_action: None,
+    }
+}
+
+/// Highest priority first; ties keep the inbox order
+fn build_queue(mut items: Vec<TriageItem>) -> Vec<TriageItem> {
+    items.sort_by(|a, b| b.priority_score.total_cmp(&a.priority_score));
+    for (position, item) in items.iter_mut().enumerate() {
+        item.position = position;
+    }
+    items
+}
+
+/// Senders whose mail the user has opened before (from the analytics history)
+fn known_senders(records: impl Iterator<Item = serde_json::Value>) -> HashSet<String> {
+    records
+        .filter_map(|value| serde_json::from_value::<MessageMetadata>(value).ok())
+        .filter(|m| m.opened)
+        .map(|m| m.from_email)
+        .collect()
+}
+
+// ============================================================================
+// Applying Decisions
+// ============================================================================
+
+/// Keep the session's decisions (except failed ones) for rule suggestions
+fn record_history(
+    storage: &LocalStorage,
+    account: &AccountContext,
+    session: &TriageSession,
+    failures: &[TriageFailure],
+) -> Result<(), String> {
+    let records = session
+        .decisions
+        .iter()
+        .filter(|d| !failures.iter().any(|f| f.thread_id == d.thread_id))
+        .filter_map(|decision| {
+            let item = session
+                .queue
+                .iter()
+                .find(|item| item.thread_id == decision.thread_id)?;
+            let record = TriageRecord {
+                thread_id: decision.thread_id.clone(),
+                from_email: item.from_email.clone(),
+                action: decision.action,
+                was_unread: item.is_unread,
+                decided_at_ms: decision.decided_at_ms,
+            };
+            Some(
+                serde_json::to_value(&record)
+                    .map(|value| (format!("{}:{}", session.id, decision.thread_id), value))
+                    .map_err(|e| format!("Failed to serialize triage record: {}", e)),
+            )
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    storage.put_many(account, TRIAGE_HISTORY, records)
+}
+
+async fn apply_decision(
+    token_store: &TokenStore,
+    client: &GoogleClient,
+    mailbox: &Mailbox,
+    decision: &TriageDecision,
+) -> Result<(), String> {
+    let thread_url = format!(
+        "{}/{}/threads/{}",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        decision.thread_id
+    );
+
+    if decision.action == TriageAction::Trash {
+        let _: serde::de::IgnoredAny = client
+            .post(
+                &format!("{}/trash", thread_url),
+                token_store,
+                &serde_json::json!({}),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let Some((add, remove)) = decision.action.label_changes() else {
+        return Ok(());
+    };
+    let body = serde_json::json!({
+        "addLabelIds": add,
+        "removeLabelIds": remove,
+    });
+    let _: serde::de::IgnoredAny = client
+        .post(&format!("{}/modify", thread_url), token_store, &body)
+        .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Start a triage session over the inbox (or `query`), replacing any open one
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_session(
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    triage: State<'_, TriageState>,
+    query: Option<String>,
+    max_items: Option<u32>,
+    mailbox: Option<String>,
+) -> Result<TriageStats, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mailbox = mailbox::resolve(&token_store, &storage, mailbox).await?;
+    let account = token_store.account_context().await?;
+    let max = max_items.unwrap_or(DEFAULT_MAX_ITEMS).clamp(1, MAX_ITEMS);
+    let q = query.unwrap_or_else(|| DEFAULT_QUERY.to_string());
+
+    let url = format!(
+        "{}/{}/threads?maxResults={}&q={}",
+        GMAIL_API_BASE,
+        mailbox.user_path(),
+        max,
+        urlencoding::encode(&q)
+    );
+    let thread_ids: Vec<String> = client
+        .get_with(&url, &token_store, MAX_RESPONSE_BYTES, |body| {
+            let page: GmailThreadsPage = serde_json::from_slice(body)?;
+            Ok(page
+                .threads
+                .into_iter()
+                .map(|t| t.id.into_owned())
+                .collect())
+        })
+        .await?;
+
+    let hydration = gmail::hydrate_threads(
+        &token_store,
+        &client,
+        &mailbox,
+        &thread_ids,
+        DEFAULT_HYDRATION_PARALLELISM,
+    )
+    .await;
+    for error in &hydration.errors {
+        eprintln!(
+            "Skipping thread {} in triage: {}",
+            error.thread_id, error.error
+        );
+    }
+
+    let own_email = match &mailbox {
+        Mailbox::Own => account.email().to_string(),
+        Mailbox::Delegated(email) => email.clone(),
+    };
+    let known = if mailbox.is_own() {
+        known_senders(
+            storage
+                .list(&account, EMAIL_METADATA)?
+                .into_values()
+                .map(|record| record.value),
+        )
+    } else {
+        HashSet::new()
+    };
+    let rules = if mailbox.is_own() {
+        rules::load_rules(&storage, &account)?
+    } else {
+        HashMap::new()
+    };
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let queue = build_queue(
+        hydration
+            .threads
+            .iter()
+            .map(|thread| {
+                let mut item = triage_item(thread, &own_email, &known, now_ms);
+                item.suggested_action = rules.get(&item.from_email).map(|r| r.action.as_triage());
+                item
+            })
+            .collect(),
+    );
+
+    let session = TriageSession {
+        id: format!("triage-{}", now_ms),
+        account_email: account.email().to_string(),
+        mailbox,
+        queue,
+        decisions: Vec::new(),
+        started_at_ms: now_ms,
+    };
+    let stats = session.stats(now_ms);
+    *triage.guard()? = Some(session);
+    Ok(stats)
+}
+
+/// Get the thread to decide on next (None once the queue is done)
+#[tauri::command]
+pub fn next_item(
+    app_lock: State<'_, AppLockState>,
+    triage: State<'_, TriageState>,
+) -> Result<Option<TriageItem>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let guard = triage.guard()?;
+    let session = guard.as_ref().ok_or("No triage session in progress")?;
+    Ok(session.current().cloned())
+}
+
+/// Record a decision for the current thread and move to the next one
+#[tauri::command]
+pub fn act(
+    app_lock: State<'_, AppLockState>,
+    triage: State<'_, TriageState>,
+    action: TriageAction,
+) -> Result<TriageStats, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    let mut guard = triage.guard()?;
+    let session = guard.as_mut().ok_or("No triage session in progress")?;
+    let thread_id = session
+        .current()
+        .map(|item| item.thread_id.clone())
+        .ok_or("Every thread in this session has been triaged")?;
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    session.decisions.push(TriageDecision {
+        thread_id,
+        action,
+        decided_at_ms: now_ms,
+    });
+    Ok(session.stats(now_ms))
+}
+
+/// End the session and apply its decisions to Gmail
+///
+/// Threads that couldn't be changed are reported in `failures`; the session
+/// is closed either way.
+#[tauri::command]
+pub async fn end_session(
+    app: AppHandle,
+    app_lock: State<'_, AppLockState>,
+    token_store: State<'_, TokenStore>,
+    client: State<'_, GoogleClient>,
+    storage: State<'_, LocalStorage>,
+    triage: State<'_, TriageState>,
+) -> Result<TriageSummary, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    // Checked before taking the session, so a denied one can be ended later
+    capabilities::require(&token_store, Feature::Modify).await?;
+    let session = triage
+        .guard()?
+        .take()
+        .ok_or("No triage session in progress")?;
+    let stats = session.stats(chrono::Utc::now().timestamp_millis());
+
+    let account = token_store.account_context().await?;
+    if account.email() != session.account_email {
+        return Err(format!(
+            "Triage session belongs to {}; its decisions were discarded",
+            session.account_email
+        ));
+    }
+
+    let pending: Vec<&TriageDecision> = session
+        .decisions
+        .iter()
+        .filter(|d| d.action != TriageAction::Skip)
+        .collect();
+    let outcome = fan_out(&pending, DEFAULT_HYDRATION_PARALLELISM, |decision| {
+        apply_decision(&token_store, &client, &session.mailbox, decision)
+    })
+    .await;
+
+    let applied: Vec<String> = outcome
+        .succeeded
+        .into_iter()
+        .map(|(decision, ())| decision.thread_id.clone())
+        .collect();
+    let failures: Vec<TriageFailure> = outcome
+        .failed
+        .into_iter()
+        .map(|(decision, error)| TriageFailure {
+            thread_id: decision.thread_id.clone(),
+            action: decision.action,
+            error,
+        })
+        .collect();
+
+    if session.mailbox.is_own() {
+        if let Err(e) = record_history(&storage, &account, &session, &failures) {
+            eprintln!("Failed to record triage history: {}", e);
+        }
+    }
+
+    if !applied.is_empty() {
+        invalidation::mutated(
+            &app,
+            DataEvent::ThreadsModified {
+                thread_ids: applied.clone(),
+            },
+        )
+        .await;
+    }
+
+    Ok(TriageSummary {
+        stats,
+        applied: applied.len(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::types::{GmailHeader, GmailMessage, GmailPayload};
+
+    fn message(headers: &[(&str, &str)], labels: &[&str], date_ms: i64) -> GmailMessage {
+        GmailMessage {
+            id: "m1".to_string(),
+            thread_id: "t1".to_string(),
+            label_ids: Some(labels.iter().map(|l| l.to_string()).collect()),
+            snippet: "Can you review this today?".to_string(),
+            payload: Some(GmailPayload {
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(name, value)| GmailHeader {
+                            name: name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                ),
+                mime_type: None,
+                filename: None,
+                body: None,
+                parts: None,
+            }),
+            internal_date: Some(date_ms.to_string()),
+            size_estimate: None,
+        }
+    }
+
+    #[test]
+    fn test_queue_scoring_and_stats() {
+        let now_ms = 1_768_000_000_000;
+        let direct = GmailThreadDetail {
+            id: "direct".to_string(),
+            messages: Some(vec![message(
+                &[
+                    ("Subject", "Urgent: contract"),
+                    ("From", "Ana <ana@example.com>"),
+                    ("To", "\"Me, Myself\" <me@example.com>"),
+                ],
+                &["INBOX", "UNREAD"],
+                now_ms - 600_000,
+            )]),
+        };
+        let newsletter = GmailThreadDetail {
+            id: "newsletter".to_string(),
+            messages: Some(vec![message(
+                &[
+                    ("Subject", "Weekly digest"),
+                    ("From", "news@example.org"),
+                    ("To", "a@x.com, b@x.com, c@x.com, d@x.com"),
+                ],
+                &["INBOX"],
+                now_ms - 5 * 86_400_000,
+            )]),
+        };
+
+        let known = HashSet::from(["ana@example.com".to_string()]);
+        let queue = build_queue(
+            [&newsletter, &direct]
+                .iter()
+                .map(|t| triage_item(t, "me@example.com", &known, now_ms))
+                .collect(),
+        );
+        assert_eq!(queue[0].thread_id, "direct");
+        assert_eq!(queue[0].position, 0);
+        assert!(queue[0].is_unread);
+        assert_eq!(queue[0].from_name, "Ana");
+        assert!(queue[0].priority_score > queue[1].priority_score);
+
+        let mut session = TriageSession {
+            id: "s".to_string(),
+            account_email: "me@example.com".to_string(),
+            mailbox: Mailbox::Own,
+            queue,
+            decisions: Vec::new(),
+            started_at_ms: now_ms,
+        };
+        session.decisions.push(TriageDecision {
+            thread_id: "direct".to_string(),
+            action: TriageAction::MarkRead,
+            decided_at_ms: now_ms,
+        });
+        assert_eq!(session.current().unwrap().thread_id, "newsletter");
+
+        let stats = session.stats(now_ms + 30_000);
+        assert_eq!(stats.remaining, 1);
+        assert_eq!(stats.actions["mark_read"], 1);
+        assert_eq!(stats.items_per_minute, 2.0);
+    }
+}
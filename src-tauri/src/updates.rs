@@ -0,0 +1,179 @@
+//! Update channel management
+//!
+//! Lets the user choose between the stable and beta release channels, check
+//! for updates on demand, and read the pending update's release notes before
+//! installing ("What's new").
+
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tokio::sync::Mutex;
+
+const UPDATES_STORE_FILE: &str = "updates.json";
+const CHANNEL_KEY: &str = "channel";
+
+/// Update manifest for the stable channel (latest non-prerelease)
+const STABLE_ENDPOINT: &str =
+    "https://github.com/ferxalbs/rainy-day/releases/latest/download/latest.json";
+/// Update manifest for the beta channel (rolling `beta` release)
+const BETA_ENDPOINT: &str =
+    "https://github.com/ferxalbs/rainy-day/releases/download/beta/latest.json";
+
+/// Release channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => STABLE_ENDPOINT,
+            UpdateChannel::Beta => BETA_ENDPOINT,
+        }
+    }
+}
+
+/// Information about an available update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    pub available: bool,
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    pub version: Option<String>,
+    pub release_notes: Option<String>,
+    pub date: Option<String>,
+}
+
+/// Update found by the last check, kept for install
+pub struct UpdateState {
+    pending: Mutex<Option<Update>>,
+}
+
+impl Default for UpdateState {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::new(None),
+        }
+    }
+}
+
+fn load_channel(app: &AppHandle) -> Result<UpdateChannel, String> {
+    let store = storage::fs::settings_store(app, UPDATES_STORE_FILE)
+        .map_err(|e| format!("Failed to access updates store: {}", e))?;
+
+    Ok(store
+        .get(CHANNEL_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or(UpdateChannel::Stable))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get the selected release channel
+#[tauri::command]
+pub async fn get_update_channel(app: AppHandle) -> Result<UpdateChannel, String> {
+    crate::perf::trace_command!();
+    load_channel(&app)
+}
+
+/// Select the release channel used for update checks
+#[tauri::command]
+pub async fn set_update_channel(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+    channel: UpdateChannel,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let store = storage::fs::settings_store(&app, UPDATES_STORE_FILE)
+        .map_err(|e| format!("Failed to access updates store: {}", e))?;
+
+    store.set(CHANNEL_KEY, serde_json::json!(channel));
+    storage::fs::save_store(&app, UPDATES_STORE_FILE, &store)
+        .map_err(|e| format!("Failed to save update channel: {}", e))?;
+
+    // A pending update from the other channel is no longer relevant
+    *state.pending.lock().await = None;
+
+    Ok(())
+}
+
+/// Check the selected channel for an update
+#[tauri::command]
+pub async fn check_for_updates(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+) -> Result<UpdateInfo, String> {
+    crate::perf::trace_command!();
+    let channel = load_channel(&app)?;
+    let endpoint = Url::parse(channel.endpoint()).map_err(|e| e.to_string())?;
+
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .map_err(|e| format!("Invalid update endpoint: {}", e))?
+        .build()
+        .map_err(|e| format!("Failed to build updater: {}", e))?;
+
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))?;
+
+    let info = match &update {
+        Some(update) => UpdateInfo {
+            available: true,
+            channel,
+            current_version: update.current_version.clone(),
+            version: Some(update.version.clone()),
+            release_notes: update.body.clone(),
+            date: update.date.map(|d| d.to_string()),
+        },
+        None => UpdateInfo {
+            available: false,
+            channel,
+            current_version: app.package_info().version.to_string(),
+            version: None,
+            release_notes: None,
+            date: None,
+        },
+    };
+
+    *state.pending.lock().await = update;
+
+    Ok(info)
+}
+
+/// Get the release notes of the update found by the last check
+#[tauri::command]
+pub async fn get_pending_release_notes(
+    state: State<'_, UpdateState>,
+) -> Result<Option<String>, String> {
+    crate::perf::trace_command!();
+    let pending = state.pending.lock().await;
+    Ok(pending.as_ref().and_then(|u| u.body.clone()))
+}
+
+/// Download and install the update found by the last check
+///
+/// The app must be restarted afterwards (see `process:allow-restart`).
+#[tauri::command]
+pub async fn install_pending_update(state: State<'_, UpdateState>) -> Result<(), String> {
+    crate::perf::trace_command!();
+    let update = state
+        .pending
+        .lock()
+        .await
+        .take()
+        .ok_or("No pending update. Call check_for_updates first.")?;
+
+    update
+        .download_and_install(|_, _| {}, || {})
+        .await
+        .map_err(|e| format!("Failed to install update: {}", e))
+}
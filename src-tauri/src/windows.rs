@@ -0,0 +1,408 @@
+//! Multi-window support
+//!
+//! Next to the main plan the app can open a detached daily note and a quick
+//! capture window (`open_window`). Every window registers itself on mount
+//! (`register_window`) with the data-change topics it cares about, e.g.
+//! `task:*`. `events::emit` delivers changes to window-scoped listeners only
+//! when the window subscribed to them; windows without topics, and app-wide
+//! listeners, still get everything.
+//!
+//! Windows message each other with `send_to_window`, and small UI state
+//! (selected date, focused task, draft text) lives in the registry so every
+//! window reads the same value: `set_shared_state` bumps its version and
+//! broadcasts `state:changed`, so a change made in one window shows up in the
+//! others right away.
+
+use crate::app_lock::AppLockState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+/// Emitted with a `SharedValue` whenever shared state changes
+pub const STATE_CHANGED_EVENT: &str = "state:changed";
+/// Emitted with the registered windows whenever one opens or closes
+pub const WINDOWS_CHANGED_EVENT: &str = "windows:changed";
+/// Channel of `send_to_window` messages, delivered to the target window only
+pub const WINDOW_MESSAGE_EVENT: &str = "window:message";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// The app's windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowKind {
+    Main,
+    Note,
+    QuickCapture,
+}
+
+impl WindowKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            WindowKind::Main => "main",
+            WindowKind::Note => "note",
+            WindowKind::QuickCapture => "quick-capture",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [WindowKind::Main, WindowKind::Note, WindowKind::QuickCapture]
+            .into_iter()
+            .find(|kind| kind.label() == label)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            WindowKind::Main => "Rainy Day",
+            WindowKind::Note => "Daily Note",
+            WindowKind::QuickCapture => "Quick Capture",
+        }
+    }
+
+    /// Initial (width, height)
+    fn size(self) -> (f64, f64) {
+        match self {
+            WindowKind::Main => (1200.0, 800.0),
+            WindowKind::Note => (440.0, 680.0),
+            WindowKind::QuickCapture => (520.0, 180.0),
+        }
+    }
+}
+
+/// A registered window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub label: String,
+    pub kind: Option<WindowKind>,
+    /// Data-change channels the window listens to (`task:completed`,
+    /// `task:*`); empty means all of them
+    pub topics: Vec<String>,
+    pub registered_at: i64,
+}
+
+/// A shared state entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedValue {
+    pub key: String,
+    pub value: Value,
+    /// Increases with every change of the key
+    pub version: u64,
+    /// Label of the window that made the change
+    pub updated_by: String,
+    pub updated_at: i64,
+}
+
+/// A message from one window to another
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowMessage {
+    pub from: String,
+    pub event: String,
+    pub payload: Value,
+}
+
+/// Open windows and the state they share
+#[derive(Default)]
+pub struct WindowRegistry {
+    windows: Mutex<HashMap<String, WindowInfo>>,
+    state: Mutex<HashMap<String, SharedValue>>,
+}
+
+/// Whether `topic` covers `channel` (`task:*` covers `task:completed`)
+fn topic_matches(topic: &str, channel: &str) -> bool {
+    match topic.strip_suffix('*') {
+        Some(prefix) => channel.starts_with(prefix),
+        None => topic == channel,
+    }
+}
+
+impl WindowRegistry {
+    pub fn register(&self, label: &str, topics: Vec<String>) -> WindowInfo {
+        let info = WindowInfo {
+            label: label.to_string(),
+            kind: WindowKind::from_label(label),
+            topics,
+            registered_at: chrono::Utc::now().timestamp_millis(),
+        };
+        if let Ok(mut windows) = self.windows.lock() {
+            windows.insert(label.to_string(), info.clone());
+        }
+        info
+    }
+
+    /// Forget a window; returns whether it was registered
+    pub fn unregister(&self, label: &str) -> bool {
+        self.windows
+            .lock()
+            .map(|mut windows| windows.remove(label).is_some())
+            .unwrap_or(false)
+    }
+
+    pub fn list(&self) -> Vec<WindowInfo> {
+        let mut windows: Vec<WindowInfo> = self
+            .windows
+            .lock()
+            .map(|windows| windows.values().cloned().collect())
+            .unwrap_or_default();
+        windows.sort_by_key(|w| w.registered_at);
+        windows
+    }
+
+    /// Whether the window `label` should get changes on `channel`
+    ///
+    /// Unregistered windows get everything.
+    pub fn wants(&self, label: &str, channel: &str) -> bool {
+        let Ok(windows) = self.windows.lock() else {
+            return true;
+        };
+        windows.get(label).is_none_or(|w| {
+            w.topics.is_empty() || w.topics.iter().any(|t| topic_matches(t, channel))
+        })
+    }
+
+    /// Whether a listener registered on `target` should get `channel`
+    pub fn delivers(&self, target: &EventTarget, channel: &str) -> bool {
+        match target {
+            EventTarget::Window { label }
+            | EventTarget::Webview { label }
+            | EventTarget::WebviewWindow { label }
+            | EventTarget::AnyLabel { label } => self.wants(label, channel),
+            _ => true,
+        }
+    }
+
+    pub fn get_state(&self, key: &str) -> Option<SharedValue> {
+        self.state.lock().ok()?.get(key).cloned()
+    }
+
+    pub fn all_state(&self) -> Vec<SharedValue> {
+        let mut state: Vec<SharedValue> = self
+            .state
+            .lock()
+            .map(|state| state.values().cloned().collect())
+            .unwrap_or_default();
+        state.sort_by(|a, b| a.key.cmp(&b.key));
+        state
+    }
+
+    /// Set a key and return the new entry
+    ///
+    /// `null` clears the value; the entry stays so its version keeps rising.
+    pub fn set_state(
+        &self,
+        key: &str,
+        value: Value,
+        updated_by: &str,
+    ) -> Result<SharedValue, String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| format!("Failed to lock shared state: {}", e))?;
+        let version = state.get(key).map(|v| v.version).unwrap_or(0) + 1;
+        let entry = SharedValue {
+            key: key.to_string(),
+            value,
+            version,
+            updated_by: updated_by.to_string(),
+            updated_at: chrono::Utc::now().timestamp_millis(),
+        };
+        state.insert(key.to_string(), entry.clone());
+        Ok(entry)
+    }
+}
+
+fn emit_windows_changed(app: &AppHandle) {
+    let windows = app.state::<WindowRegistry>().list();
+    if let Err(e) = app.emit(WINDOWS_CHANGED_EVENT, windows) {
+        eprintln!("Failed to emit {}: {}", WINDOWS_CHANGED_EVENT, e);
+    }
+}
+
+/// Forget a destroyed window
+pub fn on_window_destroyed(app: &AppHandle, label: &str) {
+    if app.state::<WindowRegistry>().unregister(label) {
+        emit_windows_changed(app);
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Open a window, or focus it when it's already open
+#[tauri::command]
+pub async fn open_window(app: AppHandle, kind: WindowKind) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if let Some(window) = app.get_webview_window(kind.label()) {
+        window
+            .show()
+            .and_then(|_| window.unminimize())
+            .and_then(|_| window.set_focus())
+            .map_err(|e| format!("Failed to focus window: {}", e))?;
+        return Ok(());
+    }
+
+    let url = format!("index.html?window={}", kind.label());
+    let builder =
+        tauri::WebviewWindowBuilder::new(&app, kind.label(), tauri::WebviewUrl::App(url.into()));
+    #[cfg(desktop)]
+    let builder = {
+        let (width, height) = kind.size();
+        builder
+            .title(kind.title())
+            .inner_size(width, height)
+            .always_on_top(kind == WindowKind::QuickCapture)
+    };
+    builder
+        .build()
+        .map_err(|e| format!("Failed to open {} window: {}", kind.label(), e))?;
+    Ok(())
+}
+
+/// Close a window
+#[tauri::command]
+pub async fn close_window(app: AppHandle, kind: WindowKind) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if kind == WindowKind::Main {
+        return Err("The main window can't be closed".to_string());
+    }
+    if let Some(window) = app.get_webview_window(kind.label()) {
+        window
+            .close()
+            .map_err(|e| format!("Failed to close window: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Register the calling window and the data-change topics it listens to
+#[tauri::command]
+pub async fn register_window(
+    app: AppHandle,
+    window: WebviewWindow,
+    registry: State<'_, WindowRegistry>,
+    topics: Option<Vec<String>>,
+) -> Result<WindowInfo, String> {
+    crate::perf::trace_command!();
+    let info = registry.register(window.label(), topics.unwrap_or_default());
+    emit_windows_changed(&app);
+    Ok(info)
+}
+
+/// List the registered windows
+#[tauri::command]
+pub async fn list_windows(registry: State<'_, WindowRegistry>) -> Result<Vec<WindowInfo>, String> {
+    crate::perf::trace_command!();
+    Ok(registry.list())
+}
+
+/// Send a message to another window
+#[tauri::command]
+pub async fn send_to_window(
+    app: AppHandle,
+    window: WebviewWindow,
+    target: WindowKind,
+    event: String,
+    payload: Option<Value>,
+) -> Result<(), String> {
+    crate::perf::trace_command!();
+    if app.get_webview_window(target.label()).is_none() {
+        return Err(format!("Window not open: {}", target.label()));
+    }
+    let message = WindowMessage {
+        from: window.label().to_string(),
+        event,
+        payload: payload.unwrap_or(Value::Null),
+    };
+    app.emit_to(
+        EventTarget::webview_window(target.label()),
+        WINDOW_MESSAGE_EVENT,
+        message,
+    )
+    .map_err(|e| format!("Failed to send message: {}", e))
+}
+
+/// Get one shared state entry, or all of them without a key
+#[tauri::command]
+pub async fn get_shared_state(
+    app_lock: State<'_, AppLockState>,
+    registry: State<'_, WindowRegistry>,
+    key: Option<String>,
+) -> Result<Vec<SharedValue>, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    Ok(match key {
+        Some(key) => registry.get_state(&key).into_iter().collect(),
+        None => registry.all_state(),
+    })
+}
+
+/// Set a shared state entry and tell every window about it
+#[tauri::command]
+pub async fn set_shared_state(
+    app: AppHandle,
+    window: WebviewWindow,
+    app_lock: State<'_, AppLockState>,
+    registry: State<'_, WindowRegistry>,
+    key: String,
+    value: Value,
+) -> Result<SharedValue, String> {
+    crate::perf::trace_command!();
+    app_lock.ensure_unlocked()?;
+    if key.trim().is_empty() {
+        return Err("Shared state key can't be empty".to_string());
+    }
+    let entry = registry.set_state(&key, value, window.label())?;
+    if let Err(e) = app.emit(STATE_CHANGED_EVENT, &entry) {
+        eprintln!("Failed to emit {}: {}", STATE_CHANGED_EVENT, e);
+    }
+    Ok(entry)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_topics() {
+        let registry = WindowRegistry::default();
+        let note = registry.register("note", vec!["task:*".to_string(), "note:ready".to_string()]);
+        assert_eq!(note.kind, Some(WindowKind::Note));
+        registry.register("main", Vec::new());
+
+        assert!(registry.wants("note", "task:completed"));
+        assert!(registry.wants("note", "note:ready"));
+        assert!(!registry.wants("note", "threads:modified"));
+        assert!(registry.wants("main", "threads:modified"));
+        assert!(registry.wants("unknown", "threads:modified"));
+        assert!(registry.delivers(&EventTarget::Any, "threads:modified"));
+        assert!(!registry.delivers(&EventTarget::webview_window("note"), "threads:modified"));
+
+        assert!(registry.unregister("note"));
+        assert!(!registry.unregister("note"));
+        assert!(registry.wants("note", "threads:modified"));
+    }
+
+    #[test]
+    fn test_shared_state_versions() {
+        let registry = WindowRegistry::default();
+        let first = registry
+            .set_state("selected_date", serde_json::json!("2026-01-15"), "main")
+            .unwrap();
+        let second = registry
+            .set_state("selected_date", serde_json::json!("2026-01-16"), "note")
+            .unwrap();
+        assert_eq!((first.version, second.version), (1, 2));
+        assert_eq!(
+            registry.get_state("selected_date").map(|v| v.updated_by),
+            Some("note".to_string())
+        );
+        assert_eq!(registry.all_state().len(), 1);
+    }
+}